@@ -0,0 +1,84 @@
+use crate::font::Font;
+use crate::itemize::Script;
+use crate::trace::ShapingTrace;
+use crate::unicode_tables::JOINING_RANGES;
+
+/// How a character connects to its neighbours (the Unicode joining type). A right joining
+/// character connects to the character before it (on its right) but not to the one after, the
+/// left joining type doesn't occur in the scripts handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum JoiningType {
+    Dual,
+    Right,
+    /// Connects on both sides without changing form itself, like tatweel and ZWJ
+    JoinCausing,
+    /// Skipped over when finding the neighbours, like the harakat
+    Transparent,
+    NonJoining,
+}
+
+pub(crate) fn joining_type(c: char) -> JoiningType {
+    let code = c as u32;
+    let at = JOINING_RANGES.partition_point(|&(_, last, _)| last < code);
+    match JOINING_RANGES.get(at) {
+        Some(&(first, _, joining)) if first <= code => joining,
+        _ if Script::of(c) == Script::Inherited => JoiningType::Transparent,
+        _ => JoiningType::NonJoining,
+    }
+}
+
+/// The GSUB feature giving each character of a run (in logical order) its contextual form:
+/// `medi` when it connects on both sides, `fina` only to the character before, `init` only to
+/// the one after, `isol` to neither. `None` for characters that don't take forms.
+fn joining_forms(chars: &[char]) -> Vec<Option<&'static [u8; 4]>> {
+    let types: Vec<JoiningType> = chars.iter().map(|c| joining_type(*c)).collect();
+    // the type of the nearest character after each one that isn't transparent
+    let mut next = vec![None; types.len()];
+    let mut following = None;
+    for (i, joining) in types.iter().enumerate().rev() {
+        next[i] = following;
+        if *joining != JoiningType::Transparent {
+            following = Some(*joining);
+        }
+    }
+    let mut previous = None;
+    let mut forms = Vec::with_capacity(types.len());
+    for (joining, next) in types.iter().zip(next) {
+        use JoiningType::*;
+        let joins_previous = matches!(joining, Dual | Right) && matches!(previous, Some(Dual | JoinCausing));
+        let joins_next = *joining == Dual && matches!(next, Some(Dual | Right | JoinCausing));
+        forms.push(match (joining, joins_previous, joins_next) {
+            (Transparent | JoinCausing | NonJoining, _, _) => None,
+            (_, true, true) => Some(b"medi"),
+            (_, true, false) => Some(b"fina"),
+            (_, false, true) => Some(b"init"),
+            (_, false, false) => Some(b"isol"),
+        });
+        if *joining != Transparent {
+            previous = Some(*joining);
+        }
+    }
+    forms
+}
+
+impl Font {
+    /// Shape a run of a joining script: give each letter its contextual form with the GSUB
+    /// `isol`, `fina`, `medi` and `init` features, then form the required ligatures (like
+    /// lam-alef) with `rlig`. `glyphs` are the (cluster, glyph index) of the run in logical
+    /// order, clusters being byte offsets in `text`. The substitutions go in `trace`.
+    pub(crate) fn shape_joining(&self, text: &str, glyphs: &mut Vec<(usize, u16)>, trace: &mut ShapingTrace) {
+        let Some(gsub) = self.gsub() else {
+            return;
+        };
+        let chars: Vec<char> = glyphs
+            .iter()
+            .map(|(cluster, _)| text[*cluster..].chars().next().unwrap_or(' '))
+            .collect();
+        for ((cluster, glyph), form) in glyphs.iter_mut().zip(joining_forms(&chars)) {
+            if let Some(feature) = form {
+                *glyph = gsub.substitute_traced(feature, (*cluster, *glyph), trace);
+            }
+        }
+        gsub.ligate_traced(b"rlig", glyphs, |glyph| self.is_mark(glyph), trace);
+    }
+}
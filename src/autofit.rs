@@ -0,0 +1,193 @@
+use crate::font::Font;
+use crate::hinting::interpolate_untouched;
+
+/// Points this close to a blue zone (as a fraction of the em) are considered to sit on it, which
+/// covers the overshoot of round glyphs.
+const ZONE_TOLERANCE_EM: f32 = 0.025;
+/// Horizontal edges further apart than this aren't paired up as the two sides of a stem.
+const MAX_STEM_EM: f32 = 0.2;
+/// A run between two points counts as a horizontal edge when it's at least this flat.
+const MAX_EDGE_SLOPE: f32 = 0.05;
+
+/// The vertical alignment zones autofit snaps to, in font units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlueZones {
+    pub baseline: f32,
+    pub x_height: f32,
+    pub cap_height: f32,
+}
+
+/// A horizontal run between two consecutive outline points.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    points: (usize, usize),
+    y: f32,
+    x_range: (f32, f32),
+    /// Which way the outline runs along the edge, the two sides of a stem run in opposite ways
+    direction: f32,
+}
+
+/// The outline of a glyph after autofitting: the same points as the glyph, in pixels (y up).
+#[derive(Debug, Clone)]
+pub struct AutofitGlyph {
+    pub points: Vec<(f32, f32)>,
+    pub on_curve: Vec<bool>,
+    pub contour_end_indices: Vec<u16>,
+}
+
+fn find_edges(points: &[(f32, f32)], contour_end_indices: &[u16], min_length: f32) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    let mut start = 0usize;
+    for end in contour_end_indices {
+        let end = *end as usize;
+        if end >= points.len() || end < start {
+            break;
+        }
+        for i in start..=end {
+            let j = if i == end { start } else { i + 1 };
+            let (p0, p1) = (points[i], points[j]);
+            let dx = p1.0 - p0.0;
+            if dx.abs() >= min_length && (p1.1 - p0.1).abs() <= dx.abs() * MAX_EDGE_SLOPE {
+                edges.push(Edge {
+                    points: (i, j),
+                    y: (p0.1 + p1.1) / 2.,
+                    x_range: (p0.0.min(p1.0), p0.0.max(p1.0)),
+                    direction: dx.signum(),
+                });
+            }
+        }
+        start = end + 1;
+    }
+    edges
+}
+
+/// Snap an outline in pixels to the pixel grid vertically: points on the blue zones move to the
+/// zone's rounded position (overshoots under half a pixel are flattened onto it), horizontal stems
+/// get their edges on pixel boundaries with a width of at least one pixel, and everything in
+/// between is interpolated. x coordinates are never changed.
+pub fn autofit_points(
+    points: &[(f32, f32)],
+    contour_end_indices: &[u16],
+    zones_px: &[f32],
+    px_per_em: f32,
+) -> Vec<(f32, f32)> {
+    let mut fitted = points.to_vec();
+    let mut touched = vec![false; points.len()];
+    let zone_tolerance = ZONE_TOLERANCE_EM * px_per_em;
+
+    for (i, (_, y)) in points.iter().enumerate() {
+        let Some(zone) = zones_px.iter().find(|zone| (y - **zone).abs() <= zone_tolerance) else {
+            continue;
+        };
+        let overshoot = if (y - zone).abs() < 0.5 { 0. } else { y - zone };
+        fitted[i].1 = zone.round() + overshoot;
+        touched[i] = true;
+    }
+
+    // stems: pair each horizontal edge with the closest overlapping edge running the other way
+    let edges = find_edges(points, contour_end_indices, 0.5);
+    let max_stem = MAX_STEM_EM * px_per_em;
+    let partner = |a: &Edge| {
+        edges
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| {
+                b.direction != a.direction
+                    && b.x_range.0 < a.x_range.1
+                    && a.x_range.0 < b.x_range.1
+                    && (b.y - a.y).abs() <= max_stem
+                    && (b.y - a.y).abs() > f32::EPSILON
+            })
+            .min_by(|(_, b1), (_, b2)| (b1.y - a.y).abs().total_cmp(&(b2.y - a.y).abs()))
+            .map(|(index, _)| index)
+    };
+    // edges with a point on a blue zone are already placed
+    let mut edge_y: Vec<Option<f32>> = edges
+        .iter()
+        .map(|edge| {
+            [edge.points.0, edge.points.1]
+                .into_iter()
+                .find(|point| touched[*point])
+                .map(|point| fitted[point].1 - (points[point].1 - edge.y))
+        })
+        .collect();
+    let stem_width = |a: &Edge, b: &Edge| (a.y - b.y).abs().round().max(1.) * (a.y - b.y).signum();
+
+    // edges whose partner is already on a zone keep the stem width from it, then free pairs are
+    // placed from their lower edge, then lone edges are simply rounded
+    for pass in 0..3 {
+        for i in 0..edges.len() {
+            if edge_y[i].is_some() {
+                continue;
+            }
+            let partner = partner(&edges[i]);
+            match (pass, partner) {
+                (0, Some(j)) => {
+                    if let Some(y) = edge_y[j] {
+                        edge_y[i] = Some(y + stem_width(&edges[i], &edges[j]));
+                    }
+                }
+                (1, Some(j)) if edge_y[j].is_none() => {
+                    let (lower, upper) = if edges[i].y < edges[j].y { (i, j) } else { (j, i) };
+                    let y = edges[lower].y.round();
+                    edge_y[lower] = Some(y);
+                    edge_y[upper] = Some(y + stem_width(&edges[upper], &edges[lower]));
+                }
+                (2, _) => edge_y[i] = Some(edges[i].y.round()),
+                _ => {}
+            }
+        }
+    }
+
+    for (edge, y) in edges.iter().zip(edge_y) {
+        let Some(y) = y else {
+            continue;
+        };
+        for point in [edge.points.0, edge.points.1] {
+            if !touched[point] {
+                fitted[point].1 = y + points[point].1 - edge.y;
+                touched[point] = true;
+            }
+        }
+    }
+
+    interpolate_untouched(points, &mut fitted, &touched, contour_end_indices, false);
+    fitted
+}
+
+impl Font {
+    /// Baseline, x-height and cap-height, from OS/2 when it has them, otherwise measured on the
+    /// outlines of 'x' and 'H'.
+    pub fn blue_zones(&self) -> BlueZones {
+        let measured = |c: char, fallback: f32| {
+            self.glyph_index_for_char(c)
+                .and_then(|index| self.glyph(index))
+                .filter(|glyph| glyph.num_points() > 0)
+                .map_or(fallback, |glyph| glyph.y_max as f32)
+        };
+        let os2 = self.os2();
+        let from_os2 = |value: Option<i16>| value.filter(|v| *v > 0).map(|v| v as f32);
+        let cap_height = from_os2(os2.and_then(|os2| os2.cap_height)).unwrap_or_else(|| measured('H', self.ascender() as f32));
+        let x_height = from_os2(os2.and_then(|os2| os2.x_height)).unwrap_or_else(|| measured('x', cap_height * 0.5));
+        BlueZones {
+            baseline: 0.,
+            x_height,
+            cap_height,
+        }
+    }
+
+    /// The glyph outline scaled to `px_size` with autofit vertical grid fitting applied, `None`
+    /// for composite glyphs.
+    pub fn autofit_glyph(&self, index: u16, px_size: f32) -> Option<AutofitGlyph> {
+        let glyph = self.glyph(index).filter(|glyph| glyph.is_simple)?;
+        let scale = self.scale_for_px(px_size);
+        let zones = self.blue_zones();
+        let zones_px = [zones.baseline * scale, zones.x_height * scale, zones.cap_height * scale];
+        let points: Vec<(f32, f32)> = glyph.points().into_iter().map(|(x, y)| (x * scale, y * scale)).collect();
+        Some(AutofitGlyph {
+            points: autofit_points(&points, &glyph.contour_end_indices, &zones_px, px_size),
+            on_curve: glyph.on_curve.clone(),
+            contour_end_indices: glyph.contour_end_indices.clone(),
+        })
+    }
+}
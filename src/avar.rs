@@ -0,0 +1,49 @@
+use anyhow::Result;
+
+use crate::reader::FontReader;
+
+/// The axis variations table: per axis piecewise linear maps applied to normalized coordinates,
+/// so that e.g. the midpoint of the weight slider can land somewhere other than halfway.
+#[derive(Debug, Clone, Default)]
+pub struct Avar {
+    /// (from, to) pairs per axis in fvar order, sorted by `from`
+    pub segment_maps: Vec<Vec<(f32, f32)>>,
+}
+
+impl Avar {
+    pub fn from_reader(reader: &mut FontReader, avar_offset: u32) -> Result<Avar> {
+        reader.seek(avar_offset as u64 + 6)?; // skip version and reserved
+        let axis_count = reader.read_u16()?;
+        let mut segment_maps = Vec::with_capacity(axis_count as usize);
+        for _ in 0..axis_count {
+            let position_map_count = reader.read_u16()?;
+            let mut map = Vec::with_capacity(position_map_count as usize);
+            for _ in 0..position_map_count {
+                map.push((reader.read_f2dot14()?.to_f32(), reader.read_f2dot14()?.to_f32()));
+            }
+            segment_maps.push(map);
+        }
+        Ok(Avar { segment_maps })
+    }
+
+    /// Remap a normalized coordinate of `axis`. Axes without a map (or with a map too short to
+    /// mean anything) keep the value, values past the first or last segment point use its mapping.
+    pub fn map(&self, axis: usize, value: f32) -> f32 {
+        let Some(map) = self.segment_maps.get(axis).filter(|map| map.len() >= 2) else {
+            return value;
+        };
+        let (first, last) = (map[0], map[map.len() - 1]);
+        if value <= first.0 {
+            return first.1;
+        }
+        if value >= last.0 {
+            return last.1;
+        }
+        let end = map.iter().position(|(from, _)| *from >= value).unwrap();
+        let ((from0, to0), (from1, to1)) = (map[end - 1], map[end]);
+        if from1 == from0 {
+            return to1;
+        }
+        to0 + (to1 - to0) * (value - from0) / (from1 - from0)
+    }
+}
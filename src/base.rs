@@ -0,0 +1,148 @@
+use anyhow::Result;
+
+use crate::font::Font;
+use crate::itemize::Script;
+use crate::otlayout::Tag;
+use crate::reader::FontReader;
+
+/// The alphabetic baseline Latin, Greek and Cyrillic sit on.
+pub const ROMAN_BASELINE: Tag = *b"romn";
+/// The bottom of the ideographic em-box, what CJK glyphs are aligned on.
+pub const IDEOGRAPHIC_BASELINE: Tag = *b"ideo";
+/// The hanging baseline of Devanagari, Bengali and Tibetan.
+pub const HANGING_BASELINE: Tag = *b"hang";
+
+/// The script tag BASE (like GSUB and GPOS) falls back to for scripts it doesn't list.
+const DEFAULT_SCRIPT: Tag = *b"DFLT";
+
+/// The baselines of one script: where, in font units above the glyph origin, each of the axis'
+/// baselines is for glyphs of that script.
+#[derive(Debug, Clone)]
+pub struct BaseScript {
+    pub tag: Tag,
+    /// The baseline the script's glyphs are aligned on
+    pub default_baseline: Option<Tag>,
+    /// (baseline, coordinate), for the baselines of the axis this script gives a position
+    pub coordinates: Vec<(Tag, i16)>,
+}
+
+/// The baseline table, for lining up glyphs of scripts with different baselines (or drawn from
+/// fonts with different metrics). Only the horizontal axis is read, and min/max extents and
+/// device tables are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Base {
+    pub scripts: Vec<BaseScript>,
+}
+
+impl Base {
+    pub fn from_reader(reader: &mut FontReader, base_offset: u32) -> Result<Base> {
+        let start = base_offset as u64;
+        reader.seek(start + 4)?; // skip version
+        let horizontal_axis = match reader.read_u16()? {
+            0 => return Ok(Base::default()),
+            offset => start + offset as u64,
+        };
+        reader.seek(horizontal_axis)?;
+        let tag_list_offset = reader.read_u16()? as u64;
+        let script_list = horizontal_axis + reader.read_u16()? as u64;
+
+        let mut baselines = Vec::new();
+        if tag_list_offset != 0 {
+            reader.seek(horizontal_axis + tag_list_offset)?;
+            let count = reader.read_u16()?;
+            for _ in 0..count {
+                let mut tag = [0u8; 4];
+                reader.read_exact(&mut tag)?;
+                baselines.push(tag);
+            }
+        }
+
+        reader.seek(script_list)?;
+        let script_count = reader.read_u16()?;
+        let mut records = Vec::with_capacity(script_count as usize);
+        for _ in 0..script_count {
+            let mut tag = [0u8; 4];
+            reader.read_exact(&mut tag)?;
+            records.push((tag, script_list + reader.read_u16()? as u64));
+        }
+        let mut scripts = Vec::with_capacity(records.len());
+        for (tag, script) in records {
+            reader.seek(script)?;
+            let values = match reader.read_u16()? {
+                0 => {
+                    scripts.push(BaseScript {
+                        tag,
+                        default_baseline: None,
+                        coordinates: Vec::new(),
+                    });
+                    continue;
+                }
+                offset => script + offset as u64,
+            };
+            reader.seek(values)?;
+            let default_index = reader.read_u16()?;
+            let coordinate_count = reader.read_u16()?;
+            let offsets: Vec<u16> = (0..coordinate_count).map(|_| reader.read_u16()).collect::<Result<_, _>>()?;
+            let mut coordinates = Vec::with_capacity(offsets.len());
+            // the coordinates are in the order of the axis' baseline tags
+            for (baseline, offset) in baselines.iter().zip(offsets) {
+                reader.seek(values + offset as u64 + 2)?; // skip the BaseCoord format, all start alike
+                coordinates.push((*baseline, reader.read_i16()?));
+            }
+            scripts.push(BaseScript {
+                tag,
+                default_baseline: baselines.get(default_index as usize).copied(),
+                coordinates,
+            });
+        }
+        Ok(Base { scripts })
+    }
+
+    /// The baselines of `script`, or of the default script when it isn't listed.
+    pub fn script(&self, script: Tag) -> Option<&BaseScript> {
+        let find = |tag: Tag| self.scripts.iter().find(|record| record.tag == tag);
+        find(script).or_else(|| find(DEFAULT_SCRIPT))
+    }
+}
+
+impl BaseScript {
+    pub fn coordinate(&self, baseline: Tag) -> Option<i16> {
+        self.coordinates.iter().find(|(tag, _)| *tag == baseline).map(|(_, coordinate)| *coordinate)
+    }
+}
+
+impl Font {
+    /// Where BASE puts `baseline` for glyphs of `script` (OpenType tags like `b"latn"` and
+    /// `b"ideo"`), in font units above the glyph origin. `None` without a BASE table or when it
+    /// doesn't give that baseline for the script (nor for its default script).
+    pub fn baseline_offset(&self, script: Tag, baseline: Tag) -> Option<i16> {
+        self.base()?.script(script)?.coordinate(baseline)
+    }
+
+    /// The baseline BASE aligns glyphs of `script` on, `None` without a BASE table saying.
+    pub fn default_baseline(&self, script: Tag) -> Option<Tag> {
+        self.base()?.script(script)?.default_baseline
+    }
+
+    /// How far (in pixels, down) to move the origin of glyphs of `script` drawn at `px_size` so
+    /// that their `baseline` lines up with the same baseline of `other`'s glyphs of
+    /// `other_script` at `other_px_size`, both fonts saying where through BASE. 0 when either
+    /// doesn't.
+    pub fn baseline_shift(
+        &self,
+        script: Script,
+        px_size: f32,
+        other: &Font,
+        other_script: Script,
+        other_px_size: f32,
+        baseline: Tag,
+    ) -> f32 {
+        let (Some(own), Some(theirs)) = (
+            self.baseline_offset(script.tag(), baseline),
+            other.baseline_offset(other_script.tag(), baseline),
+        ) else {
+            return 0.;
+        };
+        own as f32 * self.scale_for_px(px_size) - theirs as f32 * other.scale_for_px(other_px_size)
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use raqote::DrawTarget;
+
+use crate::raster::{GlyphBitmap, RenderMode};
+
+/// Pixel layouts a `Bitmap` can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BitmapFormat {
+    /// 8-bit alpha (coverage) only, what GPU glyph atlases want
+    A8,
+    /// Opaque 8-bit R, G, B
+    Rgb8,
+    /// 8-bit R, G, B, A with straight (not premultiplied) alpha, what PNG and image crates want
+    Rgba8,
+    /// Native endian u32 0xAARRGGBB with premultiplied alpha, the raqote DrawTarget layout
+    ArgbPremultiplied,
+    /// Native endian u32 0x00RRGGBB, the minifb window buffer layout
+    Xrgb,
+}
+
+impl BitmapFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            BitmapFormat::A8 => 1,
+            BitmapFormat::Rgb8 => 3,
+            BitmapFormat::Rgba8 | BitmapFormat::ArgbPremultiplied | BitmapFormat::Xrgb => 4,
+        }
+    }
+}
+
+/// An image in one of the `BitmapFormat` layouts, rows are `stride` bytes apart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+    pub format: BitmapFormat,
+    pub data: Vec<u8>,
+}
+
+pub(crate) fn premultiply(value: u8, alpha: u8) -> u8 {
+    ((value as u32 * alpha as u32 + 127) / 255) as u8
+}
+
+pub(crate) fn unpremultiply(value: u8, alpha: u8) -> u8 {
+    if alpha == 0 {
+        0
+    } else {
+        ((value as u32 * 255 + alpha as u32 / 2) / alpha as u32).min(255) as u8
+    }
+}
+
+impl Bitmap {
+    /// A transparent bitmap whose rows are padded to a multiple of `row_alignment` bytes.
+    pub fn new(width: usize, height: usize, format: BitmapFormat, row_alignment: usize) -> Bitmap {
+        let alignment = row_alignment.max(1);
+        let stride = (width * format.bytes_per_pixel()).div_ceil(alignment) * alignment;
+        Bitmap {
+            width,
+            height,
+            stride,
+            format,
+            data: vec![0; stride * height],
+        }
+    }
+
+    /// Wrap a raqote/minifb style buffer of premultiplied 0xAARRGGBB pixels.
+    pub fn from_argb_premultiplied(width: usize, height: usize, pixels: &[u32]) -> Bitmap {
+        let mut bitmap = Bitmap::new(width, height, BitmapFormat::ArgbPremultiplied, 4);
+        for (i, pixel) in pixels.iter().take(width * height).enumerate() {
+            let at = (i / width) * bitmap.stride + (i % width) * 4;
+            bitmap.data[at..at + 4].copy_from_slice(&pixel.to_ne_bytes());
+        }
+        bitmap
+    }
+
+    fn offset(&self, x: usize, y: usize) -> usize {
+        y * self.stride + x * self.format.bytes_per_pixel()
+    }
+
+    /// The pixel at (x, y) as straight alpha R, G, B, A. A8 pixels read as white with that alpha.
+    pub fn get_rgba(&self, x: usize, y: usize) -> [u8; 4] {
+        let at = self.offset(x, y);
+        let pixel = &self.data[at..at + self.format.bytes_per_pixel()];
+        match self.format {
+            BitmapFormat::A8 => [255, 255, 255, pixel[0]],
+            BitmapFormat::Rgb8 => [pixel[0], pixel[1], pixel[2], 255],
+            BitmapFormat::Rgba8 => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            BitmapFormat::ArgbPremultiplied | BitmapFormat::Xrgb => {
+                let value = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                let [a, r, g, b] = value.to_be_bytes();
+                if self.format == BitmapFormat::Xrgb {
+                    [r, g, b, 255]
+                } else {
+                    [unpremultiply(r, a), unpremultiply(g, a), unpremultiply(b, a), a]
+                }
+            }
+        }
+    }
+
+    /// Store a straight alpha pixel. Formats without alpha get it composited over black, A8 only
+    /// keeps the alpha.
+    pub fn set_rgba(&mut self, x: usize, y: usize, [r, g, b, a]: [u8; 4]) {
+        let at = self.offset(x, y);
+        let format = self.format;
+        let pixel = &mut self.data[at..at + format.bytes_per_pixel()];
+        match format {
+            BitmapFormat::A8 => pixel[0] = a,
+            BitmapFormat::Rgb8 => pixel.copy_from_slice(&[premultiply(r, a), premultiply(g, a), premultiply(b, a)]),
+            BitmapFormat::Rgba8 => pixel.copy_from_slice(&[r, g, b, a]),
+            BitmapFormat::ArgbPremultiplied | BitmapFormat::Xrgb => {
+                let alpha = if format == BitmapFormat::Xrgb { 0 } else { a };
+                let value = u32::from_be_bytes([alpha, premultiply(r, a), premultiply(g, a), premultiply(b, a)]);
+                pixel.copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+    }
+
+    pub fn convert(&self, format: BitmapFormat, row_alignment: usize) -> Bitmap {
+        let mut converted = Bitmap::new(self.width, self.height, format, row_alignment);
+        if self.format == BitmapFormat::ArgbPremultiplied && format == BitmapFormat::Xrgb {
+            // premultiplied color already is the color composited over black, only the alpha goes
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let (from, to) = (self.offset(x, y), converted.offset(x, y));
+                    let pixel = &self.data[from..from + 4];
+                    let value = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]) & 0x00ff_ffff;
+                    converted.data[to..to + 4].copy_from_slice(&value.to_ne_bytes());
+                }
+            }
+            return converted;
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                converted.set_rgba(x, y, self.get_rgba(x, y));
+            }
+        }
+        converted
+    }
+
+    /// The pixels as u32 values, for the 4 byte formats that minifb and raqote take directly.
+    pub fn to_u32_pixels(&self) -> Vec<u32> {
+        let source = match self.format {
+            BitmapFormat::ArgbPremultiplied | BitmapFormat::Xrgb if self.stride == self.width * 4 => self,
+            _ => &self.convert(BitmapFormat::ArgbPremultiplied, 4),
+        };
+        source
+            .data
+            .chunks_exact(4)
+            .map(|pixel| u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]))
+            .collect()
+    }
+
+    pub fn write_png(&self, path: &str) -> Result<()> {
+        let dt = DrawTarget::from_vec(self.width as i32, self.height as i32, self.to_u32_pixels());
+        dt.write_png(path).map_err(|err| anyhow!("Failed to write {path} : {err}"))
+    }
+}
+
+impl GlyphBitmap {
+    /// The coverage as a bitmap of `color` (unpremultiplied R, G, B). A8 keeps the raw coverage,
+    /// and Rgb8 keeps an LCD bitmap's per channel coverage as is.
+    pub fn to_bitmap(&self, format: BitmapFormat, color: (u8, u8, u8), row_alignment: usize) -> Bitmap {
+        let mut bitmap = Bitmap::new(self.width, self.height, format, row_alignment);
+        let lcd = matches!(self.mode, RenderMode::Lcd(_));
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let coverage = if lcd {
+                    let at = (y * self.width + x) * 3;
+                    let channels = &self.data[at..at + 3];
+                    if format == BitmapFormat::Rgb8 {
+                        let at = bitmap.offset(x, y);
+                        bitmap.data[at..at + 3].copy_from_slice(channels);
+                        continue;
+                    }
+                    ((channels[0] as u32 + channels[1] as u32 + channels[2] as u32) / 3) as u8
+                } else {
+                    self.data[y * self.width + x]
+                };
+                bitmap.set_rgba(x, y, [color.0, color.1, color.2, coverage]);
+            }
+        }
+        bitmap
+    }
+}
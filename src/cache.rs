@@ -0,0 +1,77 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+const SHARD_COUNT: usize = 16;
+
+/// A map shared between threads, split over several mutex protected shards so that threads
+/// working on different keys rarely wait on each other. Values are cloned out, so they're
+/// usually `Arc`s or small copies. `get_or_insert_with` counts its hits and misses.
+#[derive(Debug)]
+pub struct ShardedCache<K, V> {
+    shards: Vec<Mutex<HashMap<K, V>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Hash + Eq, V: Clone> Default for ShardedCache<K, V> {
+    fn default() -> Self {
+        ShardedCache::new()
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> ShardedCache<K, V> {
+    pub fn new() -> ShardedCache<K, V> {
+        ShardedCache {
+            shards: (0..SHARD_COUNT).map(|_| Mutex::new(HashMap::new())).collect(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn shard(&self, key: &K) -> &Mutex<HashMap<K, V>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARD_COUNT]
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.shard(key).lock().unwrap().get(key).cloned()
+    }
+
+    /// The cached value, or the one `create` makes. `create` runs without holding the lock, two
+    /// threads missing at the same time may both create a value, the first one stored wins.
+    pub fn get_or_insert_with(&self, key: K, create: impl FnOnce() -> V) -> V {
+        if let Some(value) = self.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let value = create();
+        self.shard(&key).lock().unwrap().entry(key).or_insert(value).clone()
+    }
+
+    /// How many `get_or_insert_with` calls found their value, since the cache was made.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// How many `get_or_insert_with` calls had to create their value.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.shards.iter().for_each(|shard| shard.lock().unwrap().clear());
+    }
+}
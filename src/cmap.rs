@@ -0,0 +1,529 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::ops::{Range, RangeInclusive};
+
+use crate::reader::FontReader;
+
+/// (platform, encoding) of Windows symbol subtables
+const SYMBOL: (u16, u16) = (3, 0);
+/// Characters a symbol font is typed with, mapped `SYMBOL_OFFSET` higher in its subtable
+const SYMBOL_REMAPPED: RangeInclusive<u32> = 0x20..=0xFF;
+const SYMBOL_OFFSET: u32 = 0xF000;
+/// (platform, encoding) of Macintosh Roman subtables
+const MAC_ROMAN: (u16, u16) = (1, 0);
+
+/// The characters of the MacRoman bytes 0x80 to 0xFF, the lower half is ASCII. 0xDB is the euro
+/// sign as it is since Mac OS 8.5, older fonts have the currency sign there.
+const MAC_ROMAN_HIGH: [char; 128] = [
+    '\u{00C4}', '\u{00C5}', '\u{00C7}', '\u{00C9}', '\u{00D1}', '\u{00D6}', '\u{00DC}', '\u{00E1}',
+    '\u{00E0}', '\u{00E2}', '\u{00E4}', '\u{00E3}', '\u{00E5}', '\u{00E7}', '\u{00E9}', '\u{00E8}',
+    '\u{00EA}', '\u{00EB}', '\u{00ED}', '\u{00EC}', '\u{00EE}', '\u{00EF}', '\u{00F1}', '\u{00F3}',
+    '\u{00F2}', '\u{00F4}', '\u{00F6}', '\u{00F5}', '\u{00FA}', '\u{00F9}', '\u{00FB}', '\u{00FC}',
+    '\u{2020}', '\u{00B0}', '\u{00A2}', '\u{00A3}', '\u{00A7}', '\u{2022}', '\u{00B6}', '\u{00DF}',
+    '\u{00AE}', '\u{00A9}', '\u{2122}', '\u{00B4}', '\u{00A8}', '\u{2260}', '\u{00C6}', '\u{00D8}',
+    '\u{221E}', '\u{00B1}', '\u{2264}', '\u{2265}', '\u{00A5}', '\u{00B5}', '\u{2202}', '\u{2211}',
+    '\u{220F}', '\u{03C0}', '\u{222B}', '\u{00AA}', '\u{00BA}', '\u{03A9}', '\u{00E6}', '\u{00F8}',
+    '\u{00BF}', '\u{00A1}', '\u{00AC}', '\u{221A}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00AB}',
+    '\u{00BB}', '\u{2026}', '\u{00A0}', '\u{00C0}', '\u{00C3}', '\u{00D5}', '\u{0152}', '\u{0153}',
+    '\u{2013}', '\u{2014}', '\u{201C}', '\u{201D}', '\u{2018}', '\u{2019}', '\u{00F7}', '\u{25CA}',
+    '\u{00FF}', '\u{0178}', '\u{2044}', '\u{20AC}', '\u{2039}', '\u{203A}', '\u{FB01}', '\u{FB02}',
+    '\u{2021}', '\u{00B7}', '\u{201A}', '\u{201E}', '\u{2030}', '\u{00C2}', '\u{00CA}', '\u{00C1}',
+    '\u{00CB}', '\u{00C8}', '\u{00CD}', '\u{00CE}', '\u{00CF}', '\u{00CC}', '\u{00D3}', '\u{00D4}',
+    '\u{F8FF}', '\u{00D2}', '\u{00DA}', '\u{00DB}', '\u{00D9}', '\u{0131}', '\u{02C6}', '\u{02DC}',
+    '\u{00AF}', '\u{02D8}', '\u{02D9}', '\u{02DA}', '\u{00B8}', '\u{02DD}', '\u{02DB}', '\u{02C7}',
+];
+
+#[derive(Debug, Clone)]
+struct Format4Segment {
+    start_code: u16,
+    end_code: u16,
+    id_delta: i16,
+    id_range_offset: u16,
+}
+
+#[derive(Debug, Clone)]
+struct Format12Group {
+    start_char_code: u32,
+    end_char_code: u32,
+    start_glyph_id: u32,
+}
+
+#[derive(Debug, Clone)]
+enum CmapSubtable {
+    Format4 {
+        segments: Vec<Format4Segment>,
+        glyph_id_array: Vec<u16>,
+    },
+    Format12 {
+        groups: Vec<Format12Group>,
+    },
+}
+
+/// The MacRoman byte of `c`, `None` for the characters MacRoman doesn't have.
+pub(crate) fn mac_roman_byte(c: char) -> Option<u8> {
+    match c as u32 {
+        code @ 0..=0x7f => Some(code as u8),
+        _ => MAC_ROMAN_HIGH.iter().position(|high| *high == c).map(|i| 0x80 + i as u8),
+    }
+}
+
+/// The format 14 records of one variation selector.
+#[derive(Debug, Clone)]
+struct VariationSelectorRecord {
+    selector: u32,
+    /// Ranges (first, last) of base characters whose variant is their default glyph
+    default_ranges: Vec<(u32, u32)>,
+    /// Base characters with a glyph of their own for this selector, sorted
+    non_default: Vec<(u32, u16)>,
+}
+
+/// Whether `c` is a variation selector: VS1-VS16, the ideographic VS17-VS256 and the Mongolian
+/// free variation selectors.
+pub fn is_variation_selector(c: char) -> bool {
+    matches!(c as u32, 0xFE00..=0xFE0F | 0xE0100..=0xE01EF | 0x180B..=0x180D | 0x180F)
+}
+
+/// A character of a string and the glyph it maps to, see `Font::map_str`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharMapping {
+    /// Which character of the string this is, counting every char before it
+    pub char_index: usize,
+    /// The bytes of the character and of the variation selectors after it
+    pub byte_range: Range<usize>,
+    /// `None` when the font has no glyph for it
+    pub glyph: Option<u16>,
+}
+
+/// Walk `text` once, mapping each character with the variation selector following it (if any)
+/// through `lookup`. Selectors join the mapping before them, those at the start are left out.
+pub fn map_str_with(text: &str, mut lookup: impl FnMut(char, Option<char>) -> Option<u16>) -> Vec<CharMapping> {
+    let mut mappings: Vec<CharMapping> = Vec::with_capacity(text.len());
+    let mut chars = text.char_indices().enumerate().peekable();
+    while let Some((char_index, (start, c))) = chars.next() {
+        if is_variation_selector(c) {
+            if let Some(previous) = mappings.last_mut() {
+                previous.byte_range.end = start + c.len_utf8();
+            }
+            continue;
+        }
+        let selector = chars.peek().map(|(_, (_, next))| *next).filter(|next| is_variation_selector(*next));
+        let mut end = start + c.len_utf8();
+        if let Some(selector) = selector {
+            chars.next();
+            end += selector.len_utf8();
+        }
+        mappings.push(CharMapping {
+            char_index,
+            byte_range: start..end,
+            glyph: lookup(c, selector),
+        });
+    }
+    mappings
+}
+
+/// The character to glyph mapping of a font, backed by the best unicode subtable we found.
+/// Lookups never return a glyph the font doesn't have, broken and badly subsetted fonts can map
+/// characters past the last glyph.
+#[derive(Debug, Clone)]
+pub struct Cmap {
+    subtable: CmapSubtable,
+    /// From the format 14 subtable, sorted by selector
+    variation_selectors: Vec<VariationSelectorRecord>,
+    num_glyphs: u16,
+    /// The subtable is a Windows symbol one (3, 0), see `glyph_index`
+    symbol: bool,
+}
+
+impl Cmap {
+    pub fn from_reader(reader: &mut FontReader, cmap_offset: u32, num_glyphs: u16) -> Result<Cmap> {
+        reader.seek(cmap_offset as u64 + 2)?; // skip version
+        let num_subtables = reader.read_u16()?;
+
+        // (platform, encoding, offset, format)
+        let mut candidates: Vec<(u16, u16, u32, u16)> = Vec::new();
+        for i in 0..num_subtables {
+            reader.seek(cmap_offset as u64 + 4 + i as u64 * 8)?;
+            let platform_id = reader.read_u16()?;
+            let encoding_id = reader.read_u16()?;
+            let offset = reader.read_u32()?;
+            reader.seek(cmap_offset as u64 + offset as u64)?;
+            let format = reader.read_u16()?;
+            candidates.push((platform_id, encoding_id, offset, format));
+        }
+
+        // Prefer full unicode (format 12) subtables, then the BMP-only format 4 ones, then a
+        // Windows symbol one and last the MacRoman byte table of old Mac fonts
+        let is_unicode = |platform_id: u16, encoding_id: u16| {
+            platform_id == 0 || (platform_id == 3 && (encoding_id == 1 || encoding_id == 10))
+        };
+        let chosen = candidates
+            .iter()
+            .find(|(p, e, _, format)| *format == 12 && is_unicode(*p, *e))
+            .or_else(|| candidates.iter().find(|(p, e, _, format)| *format == 4 && is_unicode(*p, *e)))
+            .or_else(|| candidates.iter().find(|(p, e, _, format)| *format == 4 && (*p, *e) == SYMBOL))
+            .or_else(|| candidates.iter().find(|(p, e, _, format)| *format == 0 && (*p, *e) == MAC_ROMAN))
+            .ok_or_else(|| anyhow!("No supported unicode, symbol or MacRoman cmap subtable found"))?;
+        let symbol = (chosen.0, chosen.1) == SYMBOL;
+
+        let subtable_offset = cmap_offset as u64 + chosen.2 as u64;
+        let subtable = match chosen.3 {
+            0 => reader.with_context("format 0 subtable", |reader| Self::read_format0(reader, subtable_offset))?,
+            4 => reader.with_context("format 4 subtable", |reader| Self::read_format4(reader, subtable_offset))?,
+            _ => reader.with_context("format 12 subtable", |reader| Self::read_format12(reader, subtable_offset))?,
+        };
+        // the variation sequences live in their own (Unicode, Variation Sequences) subtable
+        let format14 = candidates.iter().find(|(p, e, _, format)| *format == 14 && *p == 0 && *e == 5);
+        let variation_selectors = match format14 {
+            Some((_, _, offset, _)) => reader
+                .with_context("format 14 subtable", |reader| {
+                    Self::read_format14(reader, cmap_offset as u64 + *offset as u64)
+                })
+                .unwrap_or_else(|err| {
+                    println!("Error reading cmap : {err}");
+                    Vec::new()
+                }),
+            None => Vec::new(),
+        };
+        Ok(Cmap {
+            subtable,
+            variation_selectors,
+            num_glyphs,
+            symbol,
+        })
+    }
+
+    fn read_format14(reader: &mut FontReader, offset: u64) -> Result<Vec<VariationSelectorRecord>> {
+        reader.seek(offset + 6)?; // skip format and length
+        let num_records = reader.read_u32()?;
+        let mut headers = Vec::with_capacity(num_records as usize);
+        for _ in 0..num_records {
+            let selector = reader.read_u24()?;
+            let default_offset = reader.read_u32()?;
+            let non_default_offset = reader.read_u32()?;
+            headers.push((selector, default_offset, non_default_offset));
+        }
+
+        let mut records = Vec::with_capacity(headers.len());
+        for (selector, default_offset, non_default_offset) in headers {
+            let mut default_ranges = Vec::new();
+            if default_offset != 0 {
+                reader.seek(offset + default_offset as u64)?;
+                let num_ranges = reader.read_u32()?;
+                for _ in 0..num_ranges {
+                    let start = reader.read_u24()?;
+                    let additional_count = reader.read_u8()? as u32;
+                    default_ranges.push((start, start + additional_count));
+                }
+            }
+            let mut non_default = Vec::new();
+            if non_default_offset != 0 {
+                reader.seek(offset + non_default_offset as u64)?;
+                let num_mappings = reader.read_u32()?;
+                for _ in 0..num_mappings {
+                    non_default.push((reader.read_u24()?, reader.read_u16()?));
+                }
+            }
+            records.push(VariationSelectorRecord {
+                selector,
+                default_ranges,
+                non_default,
+            });
+        }
+        records.sort_by_key(|record| record.selector);
+        Ok(records)
+    }
+
+    fn read_format4(reader: &mut FontReader, offset: u64) -> Result<CmapSubtable> {
+        reader.seek(offset + 2)?; // skip format
+        let length = reader.read_u16()? as u64;
+        reader.skip(2)?; // skip language
+        let seg_count = (reader.read_u16()? / 2) as usize;
+        reader.skip(6)?; // skip searchRange, entrySelector, rangeShift
+
+        let mut end_codes = Vec::with_capacity(seg_count);
+        for _ in 0..seg_count {
+            end_codes.push(reader.read_u16()?);
+        }
+        reader.skip(2)?; // reservedPad
+        let mut start_codes = Vec::with_capacity(seg_count);
+        for _ in 0..seg_count {
+            start_codes.push(reader.read_u16()?);
+        }
+        let mut id_deltas = Vec::with_capacity(seg_count);
+        for _ in 0..seg_count {
+            id_deltas.push(reader.read_i16()?);
+        }
+        let mut id_range_offsets = Vec::with_capacity(seg_count);
+        for _ in 0..seg_count {
+            id_range_offsets.push(reader.read_u16()?);
+        }
+
+        // whatever is left of the subtable is the glyph id array
+        let glyph_array_start = reader.position();
+        let glyph_array_len = (offset + length).saturating_sub(glyph_array_start) / 2;
+        let mut glyph_id_array = Vec::with_capacity(glyph_array_len as usize);
+        for _ in 0..glyph_array_len {
+            glyph_id_array.push(reader.read_u16()?);
+        }
+
+        let segments = (0..seg_count)
+            .map(|i| Format4Segment {
+                start_code: start_codes[i],
+                end_code: end_codes[i],
+                id_delta: id_deltas[i],
+                id_range_offset: id_range_offsets[i],
+            })
+            .collect();
+        Ok(CmapSubtable::Format4 {
+            segments,
+            glyph_id_array,
+        })
+    }
+
+    /// A MacRoman format 0 subtable, kept as format 12 groups of the characters its bytes are.
+    fn read_format0(reader: &mut FontReader, offset: u64) -> Result<CmapSubtable> {
+        reader.seek(offset + 6)?; // skip format, length and language
+        let mut glyphs = [0u8; 256];
+        reader.read_exact(&mut glyphs)?;
+        let mut mapping: Vec<(u32, u32)> = glyphs
+            .iter()
+            .enumerate()
+            .filter(|(_, glyph)| **glyph != 0)
+            .map(|(byte, glyph)| match byte {
+                0..=0x7f => (byte as u32, *glyph as u32),
+                _ => (MAC_ROMAN_HIGH[byte - 0x80] as u32, *glyph as u32),
+            })
+            .collect();
+        mapping.sort();
+        let mut groups: Vec<Format12Group> = Vec::new();
+        for (code, glyph) in mapping {
+            match groups.last_mut() {
+                Some(group)
+                    if group.end_char_code + 1 == code
+                        && group.start_glyph_id + (code - group.start_char_code) == glyph =>
+                {
+                    group.end_char_code = code
+                }
+                _ => groups.push(Format12Group {
+                    start_char_code: code,
+                    end_char_code: code,
+                    start_glyph_id: glyph,
+                }),
+            }
+        }
+        Ok(CmapSubtable::Format12 { groups })
+    }
+
+    fn read_format12(reader: &mut FontReader, offset: u64) -> Result<CmapSubtable> {
+        reader.seek(offset + 12)?; // skip format, reserved, length and language
+        let num_groups = reader.read_u32()?;
+        let mut groups = Vec::with_capacity(num_groups as usize);
+        for _ in 0..num_groups {
+            groups.push(Format12Group {
+                start_char_code: reader.read_u32()?,
+                end_char_code: reader.read_u32()?,
+                start_glyph_id: reader.read_u32()?,
+            });
+        }
+        Ok(CmapSubtable::Format12 { groups })
+    }
+
+    /// Glyph lookup for a single segment of a format 4 subtable.
+    fn format4_glyph(segment_index: usize, segments: &[Format4Segment], glyph_id_array: &[u16], code: u16) -> u16 {
+        let segment = &segments[segment_index];
+        if segment.id_range_offset == 0 {
+            return (code as i32 + segment.id_delta as i32) as u16;
+        }
+        // idRangeOffset is relative to its own position in the idRangeOffset array, which
+        // sits just before the glyph id array
+        let index = (segment.id_range_offset as usize / 2 + (code - segment.start_code) as usize)
+            .checked_sub(segments.len() - segment_index);
+        match index.and_then(|index| glyph_id_array.get(index)) {
+            Some(0) | None => 0,
+            Some(glyph) => (*glyph as i32 + segment.id_delta as i32) as u16,
+        }
+    }
+
+    /// The glyph for `c`. Symbol subtables put their characters in the private use range
+    /// U+F020 to U+F0FF, so there U+0020 to U+00FF the font doesn't map itself are looked up
+    /// 0xF000 higher, like Windows does: typing "A" in Wingdings gives the glyph at U+F041.
+    pub fn glyph_index(&self, c: char) -> Option<u16> {
+        let mut no_hint = usize::MAX;
+        self.glyph_index_from(c, &mut no_hint)
+    }
+
+    /// `glyph_index` looking in the segment (or group) `hint` first, and pointing it at the one
+    /// `c` is in. Text mostly keeps to a few ranges, so the next character is often in the same
+    /// one and needs no search.
+    fn glyph_index_from(&self, c: char, hint: &mut usize) -> Option<u16> {
+        let glyph = self.subtable_glyph(c as u32, hint);
+        match glyph {
+            None if self.symbol && SYMBOL_REMAPPED.contains(&(c as u32)) => {
+                self.subtable_glyph(SYMBOL_OFFSET + c as u32, hint)
+            }
+            glyph => glyph,
+        }
+    }
+
+    /// What the subtable maps `code` to, see `glyph_index_from`.
+    fn subtable_glyph(&self, code: u32, hint: &mut usize) -> Option<u16> {
+        let glyph = match &self.subtable {
+            CmapSubtable::Format4 {
+                segments,
+                glyph_id_array,
+            } => {
+                if code > 0xFFFF {
+                    return None;
+                }
+                let code = code as u16;
+                let in_hint = segments
+                    .get(*hint)
+                    .is_some_and(|segment| (segment.start_code..=segment.end_code).contains(&code));
+                if !in_hint {
+                    *hint = segments.partition_point(|segment| segment.end_code < code);
+                }
+                let segment = segments.get(*hint)?;
+                if segment.start_code > code {
+                    return None;
+                }
+                Self::format4_glyph(*hint, segments, glyph_id_array, code) as u32
+            }
+            CmapSubtable::Format12 { groups } => {
+                let in_hint = groups
+                    .get(*hint)
+                    .is_some_and(|group| (group.start_char_code..=group.end_char_code).contains(&code));
+                if !in_hint {
+                    *hint = groups.partition_point(|group| group.end_char_code < code);
+                }
+                let group = groups.get(*hint)?;
+                if group.start_char_code > code {
+                    return None;
+                }
+                group.start_glyph_id.saturating_add(code - group.start_char_code)
+            }
+        };
+        self.existing_glyph(glyph)
+    }
+
+    /// `glyph` unless it's .notdef (no mapping) or past the last glyph.
+    fn existing_glyph(&self, glyph: u32) -> Option<u16> {
+        (glyph != 0 && glyph < self.num_glyphs as u32).then_some(glyph as u16)
+    }
+
+    /// `Font::map_str` with this cmap. Besides the search hint, the glyphs of the characters
+    /// seen so far are remembered in a small table indexed by the low bits of the code point,
+    /// text repeats the same few characters a lot.
+    pub fn map_str(&self, text: &str) -> Vec<CharMapping> {
+        const SEEN_SIZE: usize = 128;
+        let mut hint = usize::MAX;
+        let mut seen: [(char, Option<u16>); SEEN_SIZE] = [(char::MAX, None); SEEN_SIZE];
+        map_str_with(text, |c, selector| {
+            if let Some(glyph) = selector.and_then(|selector| self.glyph_index_with_variation(c, selector)) {
+                return Some(glyph);
+            }
+            let slot = &mut seen[c as usize % SEEN_SIZE];
+            if slot.0 != c {
+                *slot = (c, self.glyph_index_from(c, &mut hint));
+            }
+            slot.1
+        })
+    }
+
+    /// The glyph for the variation sequence `c` followed by `selector`. `None` when the font
+    /// doesn't list the sequence (or lists a glyph it doesn't have), callers then use the glyph
+    /// of `c` on its own.
+    pub fn glyph_index_with_variation(&self, c: char, selector: char) -> Option<u16> {
+        let (code, selector) = (c as u32, selector as u32);
+        let at = self.variation_selectors.binary_search_by_key(&selector, |record| record.selector).ok()?;
+        let record = &self.variation_selectors[at];
+        if let Ok(at) = record.non_default.binary_search_by_key(&code, |(base, _)| *base) {
+            return self.existing_glyph(record.non_default[at].1 as u32);
+        }
+        let range = record.default_ranges.partition_point(|(_, last)| *last < code);
+        match record.default_ranges.get(range) {
+            Some((first, _)) if *first <= code => self.glyph_index(c),
+            _ => None,
+        }
+    }
+
+    /// Build the glyph to characters map by walking the subtable's segments/groups. Glyphs past
+    /// the last one are left out, see `out_of_range_mappings`.
+    pub fn reverse_map(&self) -> HashMap<u16, Vec<char>> {
+        let mut reverse: HashMap<u16, Vec<char>> = HashMap::new();
+        self.for_each_mapping(|code, glyph| {
+            if let (Some(glyph), Some(c)) = (self.existing_glyph(glyph), char::from_u32(code)) {
+                reverse.entry(glyph).or_default().push(c);
+            }
+        });
+        // the characters of a symbol font's private use glyphs that are typed, see `glyph_index`
+        if self.symbol {
+            let mut hint = usize::MAX;
+            for code in SYMBOL_REMAPPED {
+                let typed = self.subtable_glyph(code, &mut hint);
+                if let (None, Some(glyph)) = (typed, self.subtable_glyph(SYMBOL_OFFSET + code, &mut hint)) {
+                    reverse.entry(glyph).or_default().push(char::from_u32(code).unwrap());
+                }
+            }
+        }
+        for chars in reverse.values_mut() {
+            chars.sort();
+        }
+        reverse
+    }
+
+    /// Every character mapped to a glyph the font has, in codepoint order.
+    pub fn chars(&self) -> Vec<char> {
+        let mut chars: Vec<char> = self.reverse_map().into_values().flatten().collect();
+        chars.sort();
+        chars
+    }
+
+    /// The glyph ids at or past the font's glyph count the subtable maps characters to, sorted,
+    /// each with its characters in codepoint order. Lookups treat those characters as unmapped.
+    pub fn out_of_range_mappings(&self) -> Vec<(u32, Vec<char>)> {
+        let mut out_of_range: HashMap<u32, Vec<char>> = HashMap::new();
+        self.for_each_mapping(|code, glyph| {
+            if glyph >= self.num_glyphs as u32 {
+                if let Some(c) = char::from_u32(code) {
+                    out_of_range.entry(glyph).or_default().push(c);
+                }
+            }
+        });
+        let mut out_of_range: Vec<(u32, Vec<char>)> = out_of_range.into_iter().collect();
+        out_of_range.sort();
+        out_of_range
+    }
+
+    /// Call `f` with every code point the subtable maps and its glyph, as the subtable has it.
+    fn for_each_mapping(&self, mut f: impl FnMut(u32, u32)) {
+        match &self.subtable {
+            CmapSubtable::Format4 {
+                segments,
+                glyph_id_array,
+            } => {
+                for (segment_index, segment) in segments.iter().enumerate() {
+                    // the final 0xFFFF segment only terminates the table
+                    if segment.start_code == 0xFFFF {
+                        continue;
+                    }
+                    for code in segment.start_code..=segment.end_code {
+                        let glyph = Self::format4_glyph(segment_index, segments, glyph_id_array, code);
+                        if glyph != 0 {
+                            f(code as u32, glyph as u32);
+                        }
+                    }
+                }
+            }
+            CmapSubtable::Format12 { groups } => {
+                for group in groups {
+                    for code in group.start_char_code..=group.end_char_code {
+                        let glyph = group.start_glyph_id.saturating_add(code - group.start_char_code);
+                        if glyph != 0 {
+                            f(code, glyph);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Result};
+
+use crate::fixed::F2Dot14;
+use crate::font::Font;
+use crate::glyph::{
+    ARGS_ARE_XY_VALUES, ARG_1_AND_2_ARE_WORDS, MORE_COMPONENTS, SCALED_COMPONENT_OFFSET, WE_HAVE_AN_X_AND_Y_SCALE,
+    WE_HAVE_A_SCALE, WE_HAVE_A_TWO_BY_TWO,
+};
+use crate::hinting::read_u16_at;
+use crate::outline::Outline;
+
+/// Nesting limit for composite glyphs, whatever maxp.maxComponentDepth claims.
+const MAX_COMPONENT_DEPTH: u16 = 16;
+/// Components a composite may expand to, counting those of nested composites every time they're
+/// used, so that a few glyphs referencing each other many times can't blow up exponentially.
+const MAX_COMPONENTS: usize = 4096;
+/// Points in all the simple glyphs a composite expands to.
+const MAX_COMPOSED_POINTS: usize = 1 << 16;
+
+/// One component of a composite glyph: another glyph placed with a transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Component {
+    pub glyph_index: u16,
+    /// [xx, yx, xy, yy, dx, dy], see `Outline::transform`. The offset is in font units, 0 when
+    /// the component is placed by `anchor_points` instead.
+    pub transform: [f32; 6],
+    /// The flags of the component record, `ROUND_XY_TO_GRID`, `USE_MY_METRICS` and the like
+    pub flags: u16,
+    /// The point of the glyph so far and the point of the component that land on each other,
+    /// when the arguments are point numbers rather than an offset
+    pub anchor_points: Option<(u16, u16)>,
+}
+
+/// What a glyf record holds: an outline, or components referencing other glyphs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlyphDescription {
+    Simple(Outline),
+    Composite(Vec<Component>),
+}
+
+/// The component records of a composite glyph, `None` when they run past the glyph data.
+pub fn parse_components(glyph: &[u8]) -> Option<Vec<Component>> {
+    let read_i16 = |at: usize| glyph.get(at..at + 2).map(|_| read_u16_at(glyph, at) as i16);
+    let read_f2dot14 = |at: usize| read_i16(at).map(|value| F2Dot14(value).to_f32());
+    let mut components = Vec::new();
+    let mut at = 10; // after the glyph header
+    loop {
+        let flags = read_i16(at)? as u16;
+        let glyph_index = read_i16(at + 2)? as u16;
+        at += 4;
+        let (arg1, arg2) = match flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            true => {
+                at += 4;
+                (read_i16(at - 4)? as i32, read_i16(at - 2)? as i32)
+            }
+            false => {
+                let args = glyph.get(at..at + 2)?;
+                at += 2;
+                match flags & ARGS_ARE_XY_VALUES != 0 {
+                    true => (args[0] as i8 as i32, args[1] as i8 as i32),
+                    false => (args[0] as i32, args[1] as i32),
+                }
+            }
+        };
+        let (xx, yx, xy, yy) = if flags & WE_HAVE_A_SCALE != 0 {
+            at += 2;
+            let scale = read_f2dot14(at - 2)?;
+            (scale, 0., 0., scale)
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            at += 4;
+            (read_f2dot14(at - 4)?, 0., 0., read_f2dot14(at - 2)?)
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            at += 8;
+            (read_f2dot14(at - 8)?, read_f2dot14(at - 6)?, read_f2dot14(at - 4)?, read_f2dot14(at - 2)?)
+        } else {
+            (1., 0., 0., 1.)
+        };
+        let (transform, anchor_points) = match flags & ARGS_ARE_XY_VALUES != 0 {
+            // offsets apply after the matrix unless the font asks for them to be scaled too
+            true if flags & SCALED_COMPONENT_OFFSET != 0 => {
+                let (dx, dy) = (arg1 as f32, arg2 as f32);
+                ([xx, yx, xy, yy, xx * dx + xy * dy, yx * dx + yy * dy], None)
+            }
+            true => ([xx, yx, xy, yy, arg1 as f32, arg2 as f32], None),
+            false => ([xx, yx, xy, yy, 0., 0.], Some((arg1 as u16, arg2 as u16))),
+        };
+        components.push(Component {
+            glyph_index,
+            transform,
+            flags,
+            anchor_points,
+        });
+        if flags & MORE_COMPONENTS == 0 {
+            return Some(components);
+        }
+    }
+}
+
+/// Glyph indices referenced by the components of a composite glyph, `None` when the component
+/// records run past the glyph data.
+pub(crate) fn component_indices(glyph: &[u8]) -> Option<Vec<u16>> {
+    Some(parse_components(glyph)?.iter().map(|component| component.glyph_index).collect())
+}
+
+impl Font {
+    /// The glyph as stored: its outline (at the current variation coordinates) or the
+    /// components of a composite (with their offsets as stored, gvar doesn't move them), which
+    /// `glyph_outline` puts together. `None` for glyphs past
+    /// the end of the font; composites that fail to load come back as empty outlines, see
+    /// `check_composite`.
+    pub fn glyph_description(&self, index: u16) -> Option<GlyphDescription> {
+        let glyph = self.glyph(index)?;
+        if glyph.is_simple {
+            return Some(GlyphDescription::Simple(glyph.outline()));
+        }
+        let components = self.raw_glyph(index).and_then(parse_components).unwrap_or_default();
+        Some(GlyphDescription::Composite(components))
+    }
+
+    /// The outline of a glyph with composites resolved: each component's outline transformed
+    /// and added in order. Components that don't exist are skipped, and an anchored component
+    /// whose points don't exist is placed without an offset.
+    pub fn glyph_outline(&self, index: u16) -> Option<Outline> {
+        let components = match self.glyph_description(index)? {
+            GlyphDescription::Simple(outline) => return Some(outline),
+            GlyphDescription::Composite(components) => components,
+        };
+        // loading the glyph checked the components for cycles and limits, so this terminates
+        let mut outline = Outline::default();
+        for component in components {
+            let Some(mut part) = self.glyph_outline(component.glyph_index) else {
+                continue;
+            };
+            let [xx, yx, xy, yy, mut dx, mut dy] = component.transform;
+            part.transform([xx, yx, xy, yy, 0., 0.]);
+            if let Some((parent, child)) = component.anchor_points {
+                let parent = outline.points().nth(parent as usize);
+                if let (Some(parent), Some(child)) = (parent, part.points().nth(child as usize)) {
+                    (dx, dy) = (parent.x - child.x, parent.y - child.y);
+                }
+            }
+            part.translate(dx, dy);
+            outline.append(part);
+        }
+        Some(outline)
+    }
+
+    /// How deep composites may nest: maxp.maxComponentDepth, capped at `MAX_COMPONENT_DEPTH`
+    /// (and that when maxp leaves it at 0).
+    fn max_component_depth(&self) -> u16 {
+        match self.table_data("maxp").filter(|maxp| maxp.len() >= 32).map(|maxp| read_u16_at(maxp, 30)) {
+            Some(depth) if depth > 0 => depth.min(MAX_COMPONENT_DEPTH),
+            _ => MAX_COMPONENT_DEPTH,
+        }
+    }
+
+    /// Walk composite glyph `index` down to its simple glyphs and fail when it's too complex to
+    /// load: nested too deep, reaching itself through its components, or expanding to too many
+    /// components or points. Components that don't exist are skipped.
+    pub(crate) fn check_composite(&self, index: u16) -> Result<()> {
+        let too_complex = |reason: String| anyhow!("glyph {index} is too complex: {reason}");
+        let components = |glyph: u16, raw: &[u8]| {
+            component_indices(raw).ok_or_else(|| anyhow!("component records of glyph {glyph} run past its data"))
+        };
+        let max_depth = self.max_component_depth() as usize;
+        let (mut total_components, mut total_points) = (0, 0);
+        // the glyphs being expanded, outermost first, and the components each has left
+        let mut path = vec![index];
+        let mut stack = vec![components(index, self.raw_glyph(index).unwrap_or_default())?.into_iter()];
+        while let Some(remaining) = stack.last_mut() {
+            let Some(component) = remaining.next() else {
+                stack.pop();
+                path.pop();
+                continue;
+            };
+            total_components += 1;
+            if total_components > MAX_COMPONENTS {
+                return Err(too_complex(format!("more than {MAX_COMPONENTS} components")));
+            }
+            if path.contains(&component) {
+                let cycle: Vec<String> = path.iter().chain([&component]).map(|glyph| glyph.to_string()).collect();
+                return Err(too_complex(format!("composite cycle {}", cycle.join(" -> "))));
+            }
+            let Some(raw) = self.raw_glyph(component).filter(|raw| raw.len() >= 10) else {
+                continue;
+            };
+            let num_contours = read_u16_at(raw, 0) as i16;
+            if num_contours < 0 {
+                if path.len() >= max_depth {
+                    return Err(too_complex(format!("components nest deeper than {max_depth}")));
+                }
+                stack.push(components(component, raw)?.into_iter());
+                path.push(component);
+            } else if num_contours > 0 {
+                // the last contour end is the index of the last point
+                total_points += read_u16_at(raw, 10 + 2 * (num_contours as usize - 1)) as usize + 1;
+                if total_points > MAX_COMPOSED_POINTS {
+                    return Err(too_complex(format!("more than {MAX_COMPOSED_POINTS} points")));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt;
+
+use crate::flatten::flatten_segments;
+use crate::glyph::GlyphData;
+use crate::number::fixed;
+use crate::outline::{Contour, Outline, OutlinePoint};
+
+/// Flattening tolerance for the direction of a contour, in font units.
+const DIRECTION_TOLERANCE_FONT_UNITS: f32 = 1.0;
+
+/// Which way a contour runs, y up. TrueType fills clockwise contours and cuts counter-clockwise
+/// ones out of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContourDirection {
+    Clockwise,
+    CounterClockwise,
+    /// Encloses no area, a line or a point
+    Degenerate,
+}
+
+/// What a contour of a glyph is made of, for debugging a parse, see `GlyphData::contour_stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContourStats {
+    /// Index of the contour's first point in the glyph
+    pub start_index: usize,
+    pub num_points: usize,
+    pub on_curve: usize,
+    pub off_curve: usize,
+    /// (x_min, y_min, x_max, y_max) of the points, off-curve ones included, all 0 without points
+    pub bbox: (f32, f32, f32, f32),
+    pub direction: ContourDirection,
+    pub starts_off_curve: bool,
+}
+
+impl ContourStats {
+    /// Conditions that usually come from a parser bug rather than from the font: contours too
+    /// short to enclose anything and contours without an on-curve point.
+    pub fn problems(&self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        if self.num_points < 3 {
+            problems.push("fewer than 3 points");
+        }
+        if self.num_points > 0 && self.on_curve == 0 {
+            problems.push("all points off-curve");
+        }
+        problems
+    }
+}
+
+impl fmt::Display for ContourStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            ContourDirection::Clockwise => "clockwise",
+            ContourDirection::CounterClockwise => "counter-clockwise",
+            ContourDirection::Degenerate => "degenerate",
+        };
+        let (x_min, y_min, x_max, y_max) = self.bbox;
+        let [x_min, y_min, x_max, y_max] = [x_min, y_min, x_max, y_max].map(|value| fixed(value, 3));
+        write!(
+            f,
+            "start {}, {} points ({} on, {} off), {direction}, bbox ({x_min}, {y_min})-({x_max}, {y_max})",
+            self.start_index, self.num_points, self.on_curve, self.off_curve
+        )?;
+        if self.starts_off_curve {
+            write!(f, ", starts off-curve")?;
+        }
+        for problem in self.problems() {
+            write!(f, ", {problem}!")?;
+        }
+        Ok(())
+    }
+}
+
+impl GlyphData {
+    /// Statistics for each contour of the glyph, in order. Unlike `outline` this keeps the
+    /// contours with no points or end indices past the points (as far as the points go), since
+    /// those are what a broken parse looks like. Composite glyphs have no contours here.
+    pub fn contour_stats(&self) -> Vec<ContourStats> {
+        let points = self.points();
+        let available = points.len().min(self.on_curve.len());
+        self.contour_ranges()
+            .map(|range| {
+                let start = range.start.min(available);
+                let range = start..range.end.clamp(start, available);
+                let contour = Contour {
+                    points: range
+                        .clone()
+                        .map(|i| OutlinePoint {
+                            x: points[i].0,
+                            y: points[i].1,
+                            on_curve: self.on_curve[i],
+                        })
+                        .collect(),
+                };
+                let on_curve = contour.points.iter().filter(|point| point.on_curve).count();
+                let area = flatten_segments(vec![contour.segments()], DIRECTION_TOLERANCE_FONT_UNITS)
+                    .first()
+                    .map_or(0., |polyline| polyline.signed_area());
+                let direction = match area {
+                    area if area < 0. => ContourDirection::Clockwise,
+                    area if area > 0. => ContourDirection::CounterClockwise,
+                    _ => ContourDirection::Degenerate,
+                };
+                let num_points = contour.points.len();
+                let starts_off_curve = contour.points.first().is_some_and(|point| !point.on_curve);
+                let mut outline = Outline {
+                    contours: vec![contour],
+                    bbox: (0., 0., 0., 0.),
+                };
+                outline.update_bbox();
+                ContourStats {
+                    start_index: range.start,
+                    num_points,
+                    on_curve,
+                    off_curve: num_points - on_curve,
+                    bbox: outline.bbox,
+                    direction,
+                    starts_off_curve,
+                }
+            })
+            .collect()
+    }
+}
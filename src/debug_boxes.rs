@@ -0,0 +1,102 @@
+//! The boxes layout places text in, stroked over it to see what layout did: the pixels each
+//! glyph's bitmap covers, each glyph's advance, each line and the whole text. They're drawn
+//! from the same `PositionedGlyph`s and `TextLine`s that drawing and hit testing go by.
+
+use raqote::{DrawOptions, DrawTarget, PathBuilder, SolidSource, Source, StrokeStyle};
+
+use crate::font::Font;
+use crate::layout::{LayoutOptions, TextLayout, TextLine};
+use crate::render::{layout_line_with, PositionedGlyph};
+
+/// The pixels of a glyph's bitmap, see `Font::raster_bounds`.
+pub const RASTER_BOUNDS_COLOR: SolidSource = SolidSource { r: 0xe0, g: 0x30, b: 0x30, a: 0xff };
+/// A glyph's advance, from the ascender to the descender.
+pub const ADVANCE_COLOR: SolidSource = SolidSource { r: 0x20, g: 0xa0, b: 0x40, a: 0xff };
+/// A line, from the start of the line to the pen after it.
+pub const LINE_COLOR: SolidSource = SolidSource { r: 0x30, g: 0x60, b: 0xe0, a: 0xff };
+/// All the lines of a `TextLayout`.
+pub const LAYOUT_COLOR: SolidSource = SolidSource { r: 0xc0, g: 0x30, b: 0xc0, a: 0xff };
+
+/// Stroke the rectangle (x, top, width, height) with a 1px line on the centers of the pixels
+/// just inside it, so that it's crisp and covers the pixels of its edges.
+fn stroke_box(dt: &mut DrawTarget, (x, top, width, height): (f32, f32, f32, f32), color: SolidSource) {
+    let (left, right) = (x.round() + 0.5, (x + width).round() - 0.5);
+    let (top, bottom) = (top.round() + 0.5, (top + height).round() - 0.5);
+    let mut pb = PathBuilder::new();
+    pb.rect(left, top, (right - left).max(0.), (bottom - top).max(0.));
+    let style = StrokeStyle {
+        width: 1.,
+        ..StrokeStyle::default()
+    };
+    dt.stroke(&pb.finish(), &Source::Solid(color), &style, &DrawOptions::new());
+}
+
+/// The raster bounds and advance boxes of laid out glyphs, marks and inline objects having no
+/// advance box.
+fn stroke_glyph_boxes(font: &Font, dt: &mut DrawTarget, glyphs: &[PositionedGlyph], px_size: f32) {
+    let scale = font.scale_for_px(px_size);
+    let (ascender, descender) = (font.ascender() as f32 * scale, font.descender() as f32 * scale);
+    for glyph in glyphs {
+        let (x, y) = glyph.position;
+        if glyph.object.is_none() && glyph.missing.is_none() {
+            let bounds = font.raster_bounds(glyph.index, px_size, x - x.floor());
+            if bounds.width > 0 && bounds.height > 0 {
+                let (left, top) = (x.floor() + bounds.left as f32, y.round() - bounds.top as f32);
+                stroke_box(dt, (left, top, bounds.width as f32, bounds.height as f32), RASTER_BOUNDS_COLOR);
+            }
+        }
+        if glyph.advance != 0. {
+            stroke_box(dt, (x, y - ascender, glyph.advance, ascender - descender), ADVANCE_COLOR);
+        }
+    }
+}
+
+impl Font {
+    /// Stroke the boxes of the line `draw_text_with_layout` draws with the same arguments: each
+    /// glyph's raster bounds and advance, and the line from `origin` to the pen after it, in
+    /// the colors of this module.
+    pub fn draw_debug_boxes(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        px_size: f32,
+        layout: &LayoutOptions,
+    ) {
+        let (glyphs, end_x) = layout_line_with(self, text, origin, px_size, None, layout);
+        stroke_line_boxes(self, dt, (&glyphs, end_x), origin, px_size);
+    }
+}
+
+/// `Font::draw_debug_boxes` of glyphs laid out already, the pen at `end_x` after them.
+pub(crate) fn stroke_line_boxes(
+    font: &Font,
+    dt: &mut DrawTarget,
+    (glyphs, end_x): (&[PositionedGlyph], f32),
+    origin: (f32, f32),
+    px_size: f32,
+) {
+    stroke_glyph_boxes(font, dt, glyphs, px_size);
+    let scale = font.scale_for_px(px_size);
+    let (ascender, descender) = (font.ascender() as f32 * scale, font.descender() as f32 * scale);
+    stroke_box(dt, (origin.0, origin.1 - ascender, end_x - origin.0, ascender - descender), LINE_COLOR);
+}
+
+/// The boxes of the glyphs of a line of a `TextLayout` and of the `TextLine`, as wide as the
+/// line and as tall as the layout made it, the text drawn from `origin` (its top left).
+pub(crate) fn stroke_text_line_boxes(
+    font: &Font,
+    dt: &mut DrawTarget,
+    glyphs: &[PositionedGlyph],
+    line: &TextLine,
+    origin: (f32, f32),
+    px_size: f32,
+) {
+    stroke_glyph_boxes(font, dt, glyphs, px_size);
+    stroke_box(dt, (origin.0, origin.1 + line.top, line.width, line.height), LINE_COLOR);
+}
+
+/// The rectangle all the lines of `layout` take, drawn from `origin`.
+pub(crate) fn stroke_layout_box(dt: &mut DrawTarget, layout: &TextLayout, origin: (f32, f32)) {
+    stroke_box(dt, (origin.0, origin.1, layout.width(), layout.height()), LAYOUT_COLOR);
+}
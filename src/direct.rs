@@ -0,0 +1,248 @@
+use anyhow::{anyhow, Result};
+use raqote::SolidSource;
+
+use crate::bitmap::{premultiply, unpremultiply, Bitmap, BitmapFormat};
+use crate::font::Font;
+use crate::raster::{FillRule, Rasterizer, FLATTEN_TOLERANCE_PX};
+use crate::render::{layout_line, PositionedGlyph};
+
+/// Lines a `RenderScratch` keeps laid out unless told otherwise.
+const DEFAULT_LINES: usize = 16;
+
+/// Pixels owned by the caller that `Font::render_text_into` draws into: `height` rows of `width`
+/// pixels in `format`, `stride` bytes apart, like a `Bitmap` without the allocation.
+#[derive(Debug)]
+pub struct PixelBuffer<'a> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    format: BitmapFormat,
+}
+
+impl<'a> PixelBuffer<'a> {
+    /// Fails when the rows don't fit their stride or `data` is too short for them.
+    pub fn new(
+        data: &'a mut [u8],
+        width: usize,
+        height: usize,
+        stride: usize,
+        format: BitmapFormat,
+    ) -> Result<PixelBuffer<'a>> {
+        let row_bytes = width * format.bytes_per_pixel();
+        if stride < row_bytes {
+            return Err(anyhow!("A stride of {stride} bytes can't hold rows of {width} {format:?} pixels"));
+        }
+        let needed = if height == 0 { 0 } else { stride * (height - 1) + row_bytes };
+        if data.len() < needed {
+            let available = data.len();
+            return Err(anyhow!("{width}x{height} {format:?} pixels need {needed} bytes, the buffer has {available}"));
+        }
+        Ok(PixelBuffer {
+            data,
+            width,
+            height,
+            stride,
+            format,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Blend `color` (premultiplied) covering the pixels `x..x + len` of row `y` by `coverage`
+    /// over what's there.
+    fn blend_run(&mut self, x: usize, y: usize, len: usize, color: SolidSource, coverage: u8) {
+        let [r, g, b, a] = [color.r, color.g, color.b, color.a].map(|channel| premultiply(channel, coverage));
+        let keep = 255 - a;
+        let over = |source: u8, destination: u8| source.saturating_add(premultiply(destination, keep));
+        let bytes = self.format.bytes_per_pixel();
+        let start = y * self.stride + x * bytes;
+        for pixel in self.data[start..start + len * bytes].chunks_exact_mut(bytes) {
+            match self.format {
+                BitmapFormat::A8 => pixel[0] = over(a, pixel[0]),
+                BitmapFormat::Rgb8 => {
+                    pixel.copy_from_slice(&[over(r, pixel[0]), over(g, pixel[1]), over(b, pixel[2])]);
+                }
+                BitmapFormat::Rgba8 => {
+                    let alpha = pixel[3];
+                    let [dr, dg, db] = [pixel[0], pixel[1], pixel[2]].map(|channel| premultiply(channel, alpha));
+                    let alpha = over(a, alpha);
+                    let [r, g, b] = [over(r, dr), over(g, dg), over(b, db)];
+                    pixel[..3].copy_from_slice(&[r, g, b].map(|channel| unpremultiply(channel, alpha)));
+                    pixel[3] = alpha;
+                }
+                BitmapFormat::ArgbPremultiplied | BitmapFormat::Xrgb => {
+                    let [da, dr, dg, db] = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]).to_be_bytes();
+                    let alpha = if self.format == BitmapFormat::Xrgb { 0 } else { over(a, da) };
+                    let value = u32::from_be_bytes([alpha, over(r, dr), over(g, dg), over(b, db)]);
+                    pixel.copy_from_slice(&value.to_ne_bytes());
+                }
+            }
+        }
+    }
+}
+
+impl Bitmap {
+    /// The bitmap's pixels for `Font::render_text_into` to draw into.
+    pub fn pixel_buffer(&mut self) -> PixelBuffer<'_> {
+        PixelBuffer {
+            data: &mut self.data,
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            format: self.format,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ScratchLine {
+    text: String,
+    px_size: u32, // the bits of the f32
+    glyphs: Vec<PositionedGlyph>,
+    end_x: f32,
+    last_used: u64,
+}
+
+/// What `Font::render_text_into_with` reuses between calls: the rasterizer's buffers and the
+/// last few lines laid out (at the origin, by text and pixel size). Once a line was drawn and
+/// its glyphs were flattened (see `Font::flattened_glyph`) drawing it again allocates nothing,
+/// as long as no glyph is larger than the buffers have grown to. Laying out a new line does.
+pub struct RenderScratch {
+    lines: Vec<ScratchLine>,
+    capacity: usize,
+    /// The variation coordinates the lines were laid out at
+    coords: Vec<f32>,
+    clock: u64,
+    rasterizer: Rasterizer,
+    sums: Vec<f32>,
+}
+
+impl Default for RenderScratch {
+    fn default() -> RenderScratch {
+        RenderScratch::new(DEFAULT_LINES)
+    }
+}
+
+impl RenderScratch {
+    /// A scratch keeping up to `lines` lines (at least one) laid out.
+    pub fn new(lines: usize) -> RenderScratch {
+        RenderScratch {
+            lines: Vec::new(),
+            capacity: lines.max(1),
+            coords: Vec::new(),
+            clock: 0,
+            rasterizer: Rasterizer::new(0, 0),
+            sums: Vec::new(),
+        }
+    }
+
+    /// The line `text` at `px_size`, laid out now unless it's one of the lines kept.
+    fn line(&mut self, font: &Font, text: &str, px_size: f32) -> usize {
+        if self.coords != font.normalized_coords {
+            self.lines.clear();
+            self.coords.clone_from(&font.normalized_coords);
+        }
+        self.clock += 1;
+        let px_bits = px_size.to_bits();
+        let at = match self.lines.iter().position(|line| line.px_size == px_bits && line.text == text) {
+            Some(at) => at,
+            None => {
+                let (glyphs, end_x) = layout_line(font, text, (0., 0.), px_size, None);
+                let line = ScratchLine {
+                    text: text.to_string(),
+                    px_size: px_bits,
+                    glyphs,
+                    end_x,
+                    last_used: 0,
+                };
+                if self.lines.len() < self.capacity {
+                    self.lines.push(line);
+                    self.lines.len() - 1
+                } else {
+                    let oldest = (0..self.lines.len()).min_by_key(|&i| self.lines[i].last_used).unwrap();
+                    self.lines[oldest] = line;
+                    oldest
+                }
+            }
+        };
+        self.lines[at].last_used = self.clock;
+        at
+    }
+}
+
+impl Font {
+    /// Draw a line of text like `draw_text`, its origin on the baseline at `origin`, straight
+    /// into `target` instead of a DrawTarget, blending `color` over the pixels. Glyphs are
+    /// drawn from their outlines (no hinting, color glyphs or embedded bitmaps, hex boxes are
+    /// .notdef) and clipped to the buffer. Returns the x of the pen after the line. This lays the
+    /// line out again on every call, `render_text_into_with` doesn't.
+    pub fn render_text_into(
+        &self,
+        target: &mut PixelBuffer,
+        text: &str,
+        px_size: f32,
+        origin: (f32, f32),
+        color: SolidSource,
+    ) -> f32 {
+        self.render_text_into_with(target, text, px_size, origin, color, &mut RenderScratch::new(1))
+    }
+
+    /// `render_text_into` reusing `scratch` between calls, see `RenderScratch`.
+    pub fn render_text_into_with(
+        &self,
+        target: &mut PixelBuffer,
+        text: &str,
+        px_size: f32,
+        origin: (f32, f32),
+        color: SolidSource,
+        scratch: &mut RenderScratch,
+    ) -> f32 {
+        let scale = self.scale_for_px(px_size);
+        let at = scratch.line(self, text, px_size);
+        let RenderScratch {
+            lines, rasterizer, sums, ..
+        } = scratch;
+        let line = &lines[at];
+        for glyph in &line.glyphs {
+            if glyph.object.is_some() {
+                continue;
+            }
+            let Some(contours) = self.flattened_glyph(glyph.index, FLATTEN_TOLERANCE_PX / scale) else {
+                continue;
+            };
+            let (x, y) = (origin.0 + glyph.position.0, origin.1 + glyph.position.1);
+            let to_pixels = |(px, py): (f32, f32)| (x + px * scale, y - py * scale);
+            let (mut min, mut max) = ((f32::INFINITY, f32::INFINITY), (f32::NEG_INFINITY, f32::NEG_INFINITY));
+            for point in contours.iter().flat_map(|contour| &contour.points) {
+                let (px, py) = to_pixels(*point);
+                min = (min.0.min(px), min.1.min(py));
+                max = (max.0.max(px), max.1.max(py));
+            }
+            // the glyph's pixels, clipped to the buffer
+            let left = min.0.floor().max(0.);
+            let top = min.1.floor().max(0.);
+            let right = max.0.ceil().min(target.width as f32);
+            let bottom = max.1.ceil().min(target.height as f32);
+            if !(left < right && top < bottom) {
+                continue;
+            }
+            let (left, top) = (left as usize, top as usize);
+            rasterizer.reset(right as usize - left, bottom as usize - top);
+            rasterizer.draw_contours(&contours, |point| {
+                let (px, py) = to_pixels(point);
+                (px - left as f32, py - top as f32)
+            });
+            rasterizer.spans_with(sums, FillRule::NonZero, |row, column, len, coverage| {
+                target.blend_run(left + column, top + row, len, color, coverage)
+            });
+        }
+        origin.0 + line.end_x
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::font::Font;
+
+impl Font {
+    /// Groups of glyphs with the same outline (up to a translation), each sorted by glyph index,
+    /// the groups by their first glyph. Empty glyphs aren't reported, see
+    /// `find_duplicate_glyphs_with`.
+    pub fn find_duplicate_glyphs(&self) -> Vec<Vec<u16>> {
+        self.find_duplicate_glyphs_with(false)
+    }
+
+    /// With `include_empty` the glyphs without an outline (space, the format characters...) make
+    /// up a group of their own. Composite glyphs are duplicates when their records are identical.
+    pub fn find_duplicate_glyphs_with(&self, include_empty: bool) -> Vec<Vec<u16>> {
+        let mut candidates: HashMap<(bool, u64), Vec<u16>> = HashMap::new();
+        for index in 0..self.num_glyphs() {
+            let Some(glyph) = self.glyph(index) else {
+                continue;
+            };
+            if glyph.is_simple && glyph.num_points() == 0 && !include_empty {
+                continue;
+            }
+            let hash = match glyph.is_simple {
+                true => glyph.outline_hash(),
+                false => {
+                    let mut hasher = DefaultHasher::new();
+                    self.raw_glyph(index).unwrap_or_default().hash(&mut hasher);
+                    hasher.finish()
+                }
+            };
+            candidates.entry((glyph.is_simple, hash)).or_default().push(index);
+        }
+
+        // a hash collision can put different outlines in a bucket, split it by exact comparison
+        let mut groups = Vec::new();
+        for (_, bucket) in candidates {
+            let mut remaining = bucket;
+            while let Some(first) = remaining.first().copied() {
+                let (same, different): (Vec<u16>, Vec<u16>) =
+                    remaining.iter().partition(|index| self.same_glyph(first, **index));
+                if same.len() > 1 {
+                    groups.push(same);
+                }
+                remaining = different;
+            }
+        }
+        groups.sort();
+        groups
+    }
+
+    fn same_glyph(&self, a: u16, b: u16) -> bool {
+        match (self.glyph(a), self.glyph(b)) {
+            (Some(first), Some(second)) if first.is_simple && second.is_simple => first.same_outline(second),
+            (Some(_), Some(_)) => self.raw_glyph(a) == self.raw_glyph(b),
+            _ => false,
+        }
+    }
+}
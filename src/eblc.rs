@@ -0,0 +1,293 @@
+use anyhow::{anyhow, Result};
+
+use crate::font::Font;
+use crate::raster::{GlyphBitmap, RenderMode};
+use crate::reader::FontReader;
+
+/// The horizontal part of EBDT small and big glyph metrics, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BitmapMetrics {
+    height: u8,
+    width: u8,
+    /// From the origin to the left edge of the bitmap
+    bearing_x: i8,
+    /// From the baseline up to the top edge of the bitmap
+    bearing_y: i8,
+    advance: u8,
+}
+
+impl BitmapMetrics {
+    fn from_reader(reader: &mut FontReader) -> Result<BitmapMetrics> {
+        Ok(BitmapMetrics {
+            height: reader.read_u8()?,
+            width: reader.read_u8()?,
+            bearing_x: reader.read_i8()?,
+            bearing_y: reader.read_i8()?,
+            advance: reader.read_u8()?,
+        })
+    }
+
+    /// Big glyph metrics: the small ones followed by the vertical bearings and advance, which
+    /// are skipped.
+    fn from_reader_big(reader: &mut FontReader) -> Result<BitmapMetrics> {
+        let metrics = BitmapMetrics::from_reader(reader)?;
+        reader.read_exact(&mut [0u8; 3])?;
+        Ok(metrics)
+    }
+}
+
+/// Where the images of an index subtable's glyphs are, by index format.
+#[derive(Debug, Clone)]
+enum ImageLocations {
+    /// Formats 1 and 3: an offset for each glyph of the range plus one for the end
+    Offsets(Vec<u32>),
+    /// Format 2: images of the same size and metrics one after the other
+    Fixed { image_size: u32, metrics: BitmapMetrics },
+    /// Format 4: the glyphs that have images (sorted) with their offsets, plus the end offset
+    Sparse { glyphs: Vec<u16>, offsets: Vec<u32> },
+    /// Format 5: like format 2 but only for the listed (sorted) glyphs
+    SparseFixed {
+        image_size: u32,
+        metrics: BitmapMetrics,
+        glyphs: Vec<u16>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct IndexSubtable {
+    first_glyph: u16,
+    last_glyph: u16,
+    image_format: u16,
+    /// Offset of the images from the start of EBDT
+    image_data_offset: u32,
+    locations: ImageLocations,
+}
+
+impl IndexSubtable {
+    /// The EBDT byte range of a glyph's image and the metrics index formats 2 and 5 keep for
+    /// all their glyphs.
+    fn image(&self, glyph: u16) -> Option<(u32, u32, Option<BitmapMetrics>)> {
+        if glyph < self.first_glyph || glyph > self.last_glyph {
+            return None;
+        }
+        let (start, end, metrics) = match &self.locations {
+            ImageLocations::Offsets(offsets) => {
+                let at = (glyph - self.first_glyph) as usize;
+                (*offsets.get(at)?, *offsets.get(at + 1)?, None)
+            }
+            ImageLocations::Fixed { image_size, metrics } => {
+                let start = (glyph - self.first_glyph) as u32 * image_size;
+                (start, start + image_size, Some(*metrics))
+            }
+            ImageLocations::Sparse { glyphs, offsets } => {
+                let at = glyphs.binary_search(&glyph).ok()?;
+                (*offsets.get(at)?, *offsets.get(at + 1)?, None)
+            }
+            ImageLocations::SparseFixed {
+                image_size,
+                metrics,
+                glyphs,
+            } => {
+                let start = glyphs.binary_search(&glyph).ok()? as u32 * image_size;
+                (start, start + image_size, Some(*metrics))
+            }
+        };
+        // glyphs without an image have an empty range
+        (end > start).then_some((self.image_data_offset + start, self.image_data_offset + end, metrics))
+    }
+}
+
+/// The embedded bitmaps for one pixel size.
+#[derive(Debug, Clone)]
+pub struct BitmapStrike {
+    pub ppem_x: u8,
+    pub ppem_y: u8,
+    /// Bits per pixel: 1 for monochrome, 2, 4 or 8 for gray levels
+    pub bit_depth: u8,
+    subtables: Vec<IndexSubtable>,
+}
+
+/// The embedded bitmap location table, EBLC. The images themselves are decoded from EBDT when
+/// asked for.
+#[derive(Debug, Clone, Default)]
+pub struct Eblc {
+    pub strikes: Vec<BitmapStrike>,
+}
+
+impl Eblc {
+    pub fn from_reader(reader: &mut FontReader, eblc_offset: u32) -> Result<Eblc> {
+        let table = eblc_offset as u64;
+        reader.seek(table + 4)?; // skip the version
+        let num_sizes = reader.read_u32()?;
+        let mut strikes = Vec::with_capacity(num_sizes.min(256) as usize);
+        for size in 0..num_sizes as u64 {
+            // BitmapSize records are 48 bytes, ppem and bit depth follow the two line metrics
+            reader.seek(table + 8 + 48 * size)?;
+            let list_offset = table + reader.read_u32()? as u64;
+            reader.skip(4)?; // indexSubtableListSize
+            let subtable_count = reader.read_u32()?;
+            reader.skip(4 + 12 + 12 + 4)?; // colorRef, hori, vert, glyph range
+            let ppem_x = reader.read_u8()?;
+            let ppem_y = reader.read_u8()?;
+            let bit_depth = reader.read_u8()?;
+
+            let mut subtables = Vec::with_capacity(subtable_count.min(1024) as usize);
+            for i in 0..subtable_count as u64 {
+                reader.seek(list_offset + 8 * i)?;
+                let first_glyph = reader.read_u16()?;
+                let last_glyph = reader.read_u16()?;
+                let offset = list_offset + reader.read_u32()? as u64;
+                if last_glyph < first_glyph {
+                    return Err(anyhow!("Index subtable of glyphs {first_glyph} to {last_glyph}"));
+                }
+                if let Some(subtable) = read_index_subtable(reader, offset, first_glyph, last_glyph)? {
+                    subtables.push(subtable);
+                }
+            }
+            strikes.push(BitmapStrike {
+                ppem_x,
+                ppem_y,
+                bit_depth,
+                subtables,
+            });
+        }
+        Ok(Eblc { strikes })
+    }
+}
+
+fn read_index_subtable(
+    reader: &mut FontReader,
+    offset: u64,
+    first_glyph: u16,
+    last_glyph: u16,
+) -> Result<Option<IndexSubtable>> {
+    reader.seek(offset)?;
+    let index_format = reader.read_u16()?;
+    let image_format = reader.read_u16()?;
+    let image_data_offset = reader.read_u32()?;
+    let glyph_count = (last_glyph - first_glyph) as u32 + 1;
+    let locations = match index_format {
+        1 => ImageLocations::Offsets(
+            (0..=glyph_count).map(|_| reader.read_u32()).collect::<Result<_, _>>()?,
+        ),
+        3 => ImageLocations::Offsets(
+            (0..=glyph_count)
+                .map(|_| reader.read_u16().map(u32::from))
+                .collect::<Result<_, _>>()?,
+        ),
+        2 => ImageLocations::Fixed {
+            image_size: reader.read_u32()?,
+            metrics: BitmapMetrics::from_reader_big(reader)?,
+        },
+        4 => {
+            let count = reader.read_u32()?.min(glyph_count);
+            let (mut glyphs, mut offsets) = (Vec::with_capacity(count as usize), Vec::with_capacity(count as usize + 1));
+            for _ in 0..=count {
+                glyphs.push(reader.read_u16()?);
+                offsets.push(reader.read_u16()? as u32);
+            }
+            // the last pair only carries the end offset
+            glyphs.pop();
+            ImageLocations::Sparse { glyphs, offsets }
+        }
+        5 => {
+            let image_size = reader.read_u32()?;
+            let metrics = BitmapMetrics::from_reader_big(reader)?;
+            let count = reader.read_u32()?.min(glyph_count);
+            ImageLocations::SparseFixed {
+                image_size,
+                metrics,
+                glyphs: (0..count).map(|_| reader.read_u16()).collect::<Result<_, _>>()?,
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(IndexSubtable {
+        first_glyph,
+        last_glyph,
+        image_format,
+        image_data_offset,
+        locations,
+    }))
+}
+
+/// Unpack `metrics.width * metrics.height` pixels of `bit_depth` bits, rows padded to whole
+/// bytes or not, into 8-bit coverage.
+fn unpack_pixels(data: &[u8], metrics: &BitmapMetrics, bit_depth: u8, byte_aligned: bool) -> Option<Vec<u8>> {
+    let (width, height, depth) = (metrics.width as usize, metrics.height as usize, bit_depth as usize);
+    let row_bits = if byte_aligned { (width * depth).div_ceil(8) * 8 } else { width * depth };
+    if data.len() * 8 < row_bits * height {
+        return None;
+    }
+    let max = (1u32 << depth) - 1;
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in 0..height {
+        for column in 0..width {
+            let bit = row * row_bits + column * depth;
+            // pixels are packed from the most significant bit and never straddle a byte
+            let value = (data[bit / 8] as u32 >> (8 - depth - bit % 8)) & max;
+            pixels.push((value * 255 / max) as u8);
+        }
+    }
+    Some(pixels)
+}
+
+impl Font {
+    /// The strike for exactly `ppem` pixels per em, if the font has one.
+    fn bitmap_strike(&self, ppem: u16) -> Option<&BitmapStrike> {
+        self.eblc()?.strikes.iter().find(|strike| strike.ppem_y as u16 == ppem)
+    }
+
+    /// Decode a glyph's embedded bitmap with its advance in pixels.
+    fn embedded_bitmap_with_advance(&self, index: u16, ppem: u16) -> Option<(GlyphBitmap, u8)> {
+        let strike = self.bitmap_strike(ppem)?;
+        let (subtable, (start, end, index_metrics)) = strike
+            .subtables
+            .iter()
+            .find_map(|subtable| Some((subtable, subtable.image(index)?)))?;
+        let ebdt = self.table_data("EBDT")?;
+        let image = ebdt.get(start as usize..end as usize)?;
+        let mut reader = FontReader::new(image);
+        let (metrics, byte_aligned) = match subtable.image_format {
+            1 => (BitmapMetrics::from_reader(&mut reader).ok()?, true),
+            2 => (BitmapMetrics::from_reader(&mut reader).ok()?, false),
+            5 => (index_metrics?, false),
+            6 => (BitmapMetrics::from_reader_big(&mut reader).ok()?, true),
+            7 => (BitmapMetrics::from_reader_big(&mut reader).ok()?, false),
+            // composite (8, 9) and compressed formats aren't supported
+            _ => return None,
+        };
+        if !matches!(strike.bit_depth, 1 | 2 | 4 | 8) {
+            return None;
+        }
+        let data = &image[reader.position() as usize..];
+        let bitmap = GlyphBitmap {
+            width: metrics.width as usize,
+            height: metrics.height as usize,
+            left: metrics.bearing_x as i32,
+            top: metrics.bearing_y as i32,
+            mode: RenderMode::Grayscale,
+            data: unpack_pixels(data, &metrics, strike.bit_depth, byte_aligned)?,
+        };
+        Some((bitmap, metrics.advance))
+    }
+
+    /// The bitmap embedded in EBLC/EBDT for a glyph at exactly `ppem` pixels per em, as 0/255
+    /// coverage for monochrome strikes. `None` without a strike of that size or an image for the
+    /// glyph in it.
+    pub fn embedded_bitmap(&self, index: u16, ppem: u16) -> Option<GlyphBitmap> {
+        self.embedded_bitmap_with_advance(index, ppem).map(|(bitmap, _)| bitmap)
+    }
+
+    /// The advance in pixels the embedded bitmap metrics give a glyph at `ppem`.
+    pub fn embedded_bitmap_advance(&self, index: u16, ppem: u16) -> Option<u8> {
+        self.embedded_bitmap_with_advance(index, ppem).map(|(_, advance)| advance)
+    }
+
+    /// Pixel sizes the font has embedded bitmaps for.
+    pub fn bitmap_strike_sizes(&self) -> Vec<u16> {
+        self.eblc()
+            .map(|eblc| eblc.strikes.iter().map(|strike| strike.ppem_y as u16).collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+use crate::font::Font;
+
+const RESTRICTED_LICENSE: u16 = 0x0002;
+const PREVIEW_AND_PRINT: u16 = 0x0004;
+const EDITABLE: u16 = 0x0008;
+const NO_SUBSETTING: u16 = 0x0100;
+const BITMAP_ONLY: u16 = 0x0200;
+
+/// What a document the font is embedded in may do with it, the usage bits of OS/2 fsType.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EmbeddingUsage {
+    /// No restrictions, the font can be installed on the system it's embedded for
+    #[default]
+    Installable,
+    /// Not to be embedded, changed or passed on in any form without the owner's permission
+    Restricted,
+    /// Embedded only to view and print documents, which stay read-only
+    PreviewAndPrint,
+    /// Embedded to view, print and edit documents, and only installed temporarily
+    Editable,
+}
+
+/// The font's licensing flags from OS/2 fsType, see `Font::embedding_permissions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EmbeddingPermissions {
+    pub usage: EmbeddingUsage,
+    /// The font has to be embedded whole
+    pub no_subsetting: bool,
+    /// Only the bitmaps of the font may be embedded, not its outlines
+    pub bitmap_only: bool,
+    /// The fsType value they were read from
+    pub fs_type: u16,
+}
+
+impl EmbeddingPermissions {
+    /// Read fsType. Fonts have to set at most one usage bit but some set several, the least
+    /// restrictive one applies then like OpenType 1.8 says.
+    pub fn from_fs_type(fs_type: u16) -> EmbeddingPermissions {
+        let usage = if fs_type & 0x000f == 0 {
+            EmbeddingUsage::Installable
+        } else if fs_type & EDITABLE != 0 {
+            EmbeddingUsage::Editable
+        } else if fs_type & PREVIEW_AND_PRINT != 0 {
+            EmbeddingUsage::PreviewAndPrint
+        } else if fs_type & RESTRICTED_LICENSE != 0 {
+            EmbeddingUsage::Restricted
+        } else {
+            // only the reserved bit 0: nothing is restricted that this knows of
+            EmbeddingUsage::Installable
+        };
+        EmbeddingPermissions {
+            usage,
+            no_subsetting: fs_type & NO_SUBSETTING != 0,
+            bitmap_only: fs_type & BITMAP_ONLY != 0,
+            fs_type,
+        }
+    }
+
+    /// Whether fonts made from this one's outlines, like instances and merges, may be written.
+    pub fn allows_editing(&self) -> bool {
+        matches!(self.usage, EmbeddingUsage::Installable | EmbeddingUsage::Editable) && !self.bitmap_only
+    }
+
+    /// Whether the font may be embedded with only some of its glyphs.
+    pub fn allows_subsetting(&self) -> bool {
+        self.usage != EmbeddingUsage::Restricted && !self.no_subsetting && !self.bitmap_only
+    }
+
+    /// Fail with what the permissions say unless they allow editing the font, `operation`
+    /// being what would edit it, e.g. "Instancing the font".
+    pub(crate) fn check_editing(&self, operation: &str) -> Result<()> {
+        match self.allows_editing() {
+            true => Ok(()),
+            false => Err(anyhow!(
+                "{operation} isn't allowed by its embedding permissions: {self}. Set \
+                 ignore_embedding_permissions to do it anyway"
+            )),
+        }
+    }
+}
+
+/// In plain language, e.g. "editable embedding, no subsetting (fsType 0x0108)".
+impl fmt::Display for EmbeddingPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let usage = match self.usage {
+            EmbeddingUsage::Installable => "installable embedding",
+            EmbeddingUsage::Restricted => "restricted license, no embedding",
+            EmbeddingUsage::PreviewAndPrint => "preview and print embedding only",
+            EmbeddingUsage::Editable => "editable embedding",
+        };
+        write!(f, "{usage}")?;
+        if self.no_subsetting {
+            write!(f, ", no subsetting")?;
+        }
+        if self.bitmap_only {
+            write!(f, ", bitmaps only")?;
+        }
+        write!(f, " (fsType 0x{:04x})", self.fs_type)
+    }
+}
+
+impl Font {
+    /// The licensing flags of OS/2 fsType, installable without restrictions for fonts without
+    /// an OS/2 table.
+    pub fn embedding_permissions(&self) -> EmbeddingPermissions {
+        EmbeddingPermissions::from_fs_type(self.os2().map_or(0, |os2| os2.fs_type))
+    }
+}
@@ -0,0 +1,65 @@
+use std::ops::Range;
+
+use crate::font::Font;
+use crate::grapheme::{grapheme_boundaries, is_extended_pictographic};
+use crate::source::GlyphSource;
+use crate::trace::ShapingTrace;
+
+const ZWJ: char = '\u{200D}';
+
+/// The Fitzpatrick skin tone modifiers.
+fn is_emoji_modifier(c: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+}
+
+/// The byte ranges of the emoji sequences of `text`: grapheme clusters of an emoji with skin tone
+/// modifiers or of emoji joined by ZWJs, which fonts can draw as one glyph.
+pub(crate) fn emoji_sequences(text: &str) -> Vec<Range<usize>> {
+    grapheme_boundaries(text)
+        .windows(2)
+        .map(|pair| pair[0]..pair[1])
+        .filter(|range| {
+            let cluster = &text[range.clone()];
+            cluster.chars().next().is_some_and(is_extended_pictographic)
+                && cluster.chars().skip(1).any(|c| c == ZWJ || is_emoji_modifier(c))
+        })
+        .collect()
+}
+
+/// Make each emoji sequence of a run one cluster: the glyphs (cluster, glyph index, in logical
+/// order, clusters being byte offsets in `text`) of a sequence go through the font's emoji
+/// ligatures, and whatever isn't ligated stays as the glyphs of the components, minus the ZWJs,
+/// all with the cluster of the sequence's first character.
+pub(crate) fn shape_emoji_sequences<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    run: Range<usize>,
+    glyphs: &mut Vec<(usize, u16)>,
+    trace: &mut ShapingTrace,
+) {
+    for sequence in emoji_sequences(&text[run.clone()]).into_iter().rev() {
+        let sequence = run.start + sequence.start..run.start + sequence.end;
+        let first = glyphs.partition_point(|(cluster, _)| *cluster < sequence.start);
+        let last = glyphs.partition_point(|(cluster, _)| *cluster < sequence.end);
+        let mut shaped = glyphs[first..last].to_vec();
+        source.shape_emoji(&mut shaped, trace);
+        shaped.retain(|(cluster, _)| !text[*cluster..].starts_with(ZWJ));
+        for (cluster, _) in &mut shaped {
+            *cluster = sequence.start;
+        }
+        glyphs.splice(first..last, shaped);
+    }
+}
+
+impl Font {
+    /// Form the glyph of an emoji sequence with the GSUB `ccmp` and `liga` ligatures, the way
+    /// color emoji fonts map ZWJ sequences and skin tones to their own glyphs.
+    pub(crate) fn shape_emoji(&self, glyphs: &mut Vec<(usize, u16)>, trace: &mut ShapingTrace) {
+        let Some(gsub) = self.gsub() else {
+            return;
+        };
+        for feature in [b"ccmp", b"liga"] {
+            gsub.ligate_traced(feature, glyphs, |glyph| self.is_mark(glyph), trace);
+        }
+    }
+}
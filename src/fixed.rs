@@ -0,0 +1,92 @@
+use std::ops::Mul;
+
+/// `value >> shift` rounded to the nearest integer, ties to even.
+fn round_shift(value: i64, shift: u32) -> i64 {
+    let half = 1i64 << (shift - 1);
+    let (quotient, remainder) = (value >> shift, value & ((1 << shift) - 1));
+    match remainder.cmp(&half) {
+        std::cmp::Ordering::Less => quotient,
+        std::cmp::Ordering::Greater => quotient + 1,
+        std::cmp::Ordering::Equal => quotient + (quotient & 1),
+    }
+}
+
+/// A 16.16 signed fixed point number (`Fixed` in the OpenType spec), used for version numbers,
+/// fvar axis values and head.fontRevision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed(pub i32);
+
+impl Fixed {
+    pub const ONE: Fixed = Fixed(1 << 16);
+
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / 65536.
+    }
+
+    /// The nearest representable value, ties to even, saturating outside of the range.
+    pub fn from_f32(value: f32) -> Fixed {
+        Fixed((value as f64 * 65536.).round_ties_even().clamp(i32::MIN as f64, i32::MAX as f64) as i32)
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    /// Rounded to the nearest 1/65536, ties to even, saturating.
+    fn mul(self, other: Fixed) -> Fixed {
+        let product = round_shift(self.0 as i64 * other.0 as i64, 16);
+        Fixed(product.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+}
+
+impl From<Fixed> for f32 {
+    fn from(value: Fixed) -> f32 {
+        value.to_f32()
+    }
+}
+
+impl From<F2Dot14> for Fixed {
+    fn from(value: F2Dot14) -> Fixed {
+        Fixed((value.0 as i32) << 2)
+    }
+}
+
+/// A 2.14 signed fixed point number, from -2 to just under 2: normalized variation coordinates,
+/// avar maps, region peaks and composite glyph scales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct F2Dot14(pub i16);
+
+impl F2Dot14 {
+    pub const ONE: F2Dot14 = F2Dot14(1 << 14);
+
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / 16384.
+    }
+
+    /// The nearest representable value, ties to even, saturating outside of -2..2.
+    pub fn from_f32(value: f32) -> F2Dot14 {
+        F2Dot14((value * 16384.).round_ties_even().clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+    }
+
+    /// Scale a value in font units (a composite component offset, say) by this factor, rounded
+    /// to the nearest unit, ties to even.
+    pub fn scale(self, value: i32) -> i32 {
+        round_shift(value as i64 * self.0 as i64, 14) as i32
+    }
+}
+
+impl Mul for F2Dot14 {
+    type Output = F2Dot14;
+
+    /// Rounded to the nearest 1/16384, ties to even, saturating.
+    fn mul(self, other: F2Dot14) -> F2Dot14 {
+        let product = round_shift(self.0 as i64 * other.0 as i64, 14);
+        F2Dot14(product.clamp(i16::MIN as i64, i16::MAX as i64) as i16)
+    }
+}
+
+impl From<F2Dot14> for f32 {
+    fn from(value: F2Dot14) -> f32 {
+        value.to_f32()
+    }
+}
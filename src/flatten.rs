@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use crate::font::Font;
+use crate::glyph::{GlyphData, Segment};
+
+/// Subdivision stops at this depth even if the tolerance isn't met, 2^16 pieces per curve is plenty.
+const MAX_SUBDIVISION_DEPTH: u32 = 16;
+
+/// A closed polyline approximating one glyph contour, in font units. The last point repeats the first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Contour {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Contour {
+    /// Iterate over the edges of the polyline as (start, end) pairs.
+    pub fn edges(&self) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + '_ {
+        self.points.windows(2).map(|pair| (pair[0], pair[1]))
+    }
+}
+
+/// Upper bound of the distance between a quadratic curve and its chord: |p0 - 2p1 + p2| / 4.
+fn quad_flatness(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32)) -> f32 {
+    let dx = p0.0 - 2. * p1.0 + p2.0;
+    let dy = p0.1 - 2. * p1.1 + p2.1;
+    (dx * dx + dy * dy).sqrt() / 4.
+}
+
+/// Append the curve's points (excluding its start) to `points`, splitting it in half until each
+/// piece is within `tolerance` of its chord.
+fn flatten_quad(points: &mut Vec<(f32, f32)>, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), tolerance: f32, depth: u32) {
+    if depth >= MAX_SUBDIVISION_DEPTH || quad_flatness(p0, p1, p2) <= tolerance {
+        points.push(p2);
+        return;
+    }
+    let mid = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) / 2., (a.1 + b.1) / 2.);
+    let left_control = mid(p0, p1);
+    let right_control = mid(p1, p2);
+    let split = mid(left_control, right_control);
+    flatten_quad(points, p0, left_control, split, tolerance, depth + 1);
+    flatten_quad(points, split, right_control, p2, tolerance, depth + 1);
+}
+
+impl GlyphData {
+    /// Approximate every contour with line segments that stay within `tolerance_font_units` of the
+    /// real outline. Curves are subdivided adaptively, so flat curves only produce a handful of points.
+    pub fn flatten(&self, tolerance_font_units: f32) -> Vec<Contour> {
+        self.outline().flatten(tolerance_font_units)
+    }
+}
+
+/// Flatten contours given as segments, `tolerance` is in the same units as the segment points.
+pub fn flatten_segments(contours: Vec<Vec<Segment>>, tolerance: f32) -> Vec<Contour> {
+    let tolerance = tolerance.max(f32::EPSILON);
+    contours
+        .into_iter()
+        .filter(|segments| !segments.is_empty())
+        .map(|segments| {
+            let mut points = vec![segments[0].start()];
+            for segment in segments {
+                match segment {
+                    Segment::Line(_, p1) => points.push(p1),
+                    Segment::Quad(p0, c, p1) => flatten_quad(&mut points, p0, c, p1, tolerance, 0),
+                }
+            }
+            // close explicitly, the segments end on the start point but rounding shouldn't leave a gap
+            let first = points[0];
+            *points.last_mut().unwrap() = first;
+            Contour { points }
+        })
+        .collect()
+}
+
+/// The power of two `tolerance` is rounded down to by `Font::flattened_glyph`.
+pub(crate) fn tolerance_bucket(tolerance: f32) -> i32 {
+    tolerance.max(f32::EPSILON).log2().floor() as i32
+}
+
+impl Font {
+    /// The glyph's outline as drawn (see `drawn_outline`) at the current variation coordinates
+    /// flattened within `tolerance_font_units`, cached per glyph and power of two of the
+    /// tolerance: it's rounded down to one, so drawing a glyph at nearby sizes (the same error
+    /// in pixels being slightly different numbers of font units) flattens it once. `None` past the last glyph.
+    pub fn flattened_glyph(&self, index: u16, tolerance_font_units: f32) -> Option<Arc<Vec<Contour>>> {
+        let bucket = tolerance_bucket(tolerance_font_units);
+        if let Some(contours) = self.flattened.get(&(index, bucket)) {
+            self.stats.flatten_cache(true);
+            return Some(contours);
+        }
+        let outline = self.drawn_outline(index)?;
+        self.stats.flatten_cache(false);
+        Some(self.flattened.get_or_insert_with((index, bucket), || Arc::new(outline.flatten(2f32.powi(bucket)))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance_to_edge(p: (f32, f32), (a, b): ((f32, f32), (f32, f32))) -> f32 {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length_squared = dx * dx + dy * dy;
+        let t = match length_squared {
+            0. => 0.,
+            _ => (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_squared).clamp(0., 1.),
+        };
+        let (x, y) = (a.0 + t * dx - p.0, a.1 + t * dy - p.1);
+        (x * x + y * y).sqrt()
+    }
+
+    /// The furthest the curve gets from the polyline, sampled.
+    fn deviation(contour: &Contour, segments: &[Segment]) -> f32 {
+        let mut worst = 0f32;
+        for segment in segments {
+            let Segment::Quad(p0, p1, p2) = *segment else {
+                continue;
+            };
+            for step in 0..=256 {
+                let t = step as f32 / 256.;
+                let u = 1. - t;
+                let point = (
+                    u * u * p0.0 + 2. * u * t * p1.0 + t * t * p2.0,
+                    u * u * p0.1 + 2. * u * t * p1.1 + t * t * p2.1,
+                );
+                let nearest = contour.edges().map(|edge| distance_to_edge(point, edge)).fold(f32::MAX, f32::min);
+                worst = worst.max(nearest);
+            }
+        }
+        worst
+    }
+
+    #[test]
+    fn flattened_curves_stay_within_the_tolerance() {
+        let curves = [
+            ((0., 0.), (50., 100.), (100., 0.)),
+            ((0., 0.), (1000., 0.), (0., 1.)),
+            ((-300., 20.), (0., -500.), (300., 20.)),
+            ((0., 0.), (0.1, 0.1), (0.2, 0.)),
+            ((10., 10.), (10., 10.), (60., 60.)),
+        ];
+        for (p0, p1, p2) in curves {
+            let segments = vec![Segment::Quad(p0, p1, p2), Segment::Line(p2, p0)];
+            for tolerance in [0.01, 0.25, 1., 4.] {
+                let contours = flatten_segments(vec![segments.clone()], tolerance);
+                let worst = deviation(&contours[0], &segments);
+                assert!(worst <= tolerance * 1.001, "{worst} off with a tolerance of {tolerance} for {p1:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn flat_curves_take_few_points() {
+        let segments = vec![Segment::Quad((0., 0.), (50., 0.5), (100., 0.)), Segment::Line((100., 0.), (0., 0.))];
+        assert_eq!(flatten_segments(vec![segments], 0.5)[0].points.len(), 3);
+    }
+
+    #[test]
+    fn glyph_outlines_stay_within_the_tolerance() {
+        let font = Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf")).unwrap();
+        for c in ['a', 'g', 'S', '@', '&'] {
+            let outline = font.glyph_outline(font.glyph_index_for_char(c).unwrap()).unwrap();
+            for segments in outline.segments() {
+                let contours = flatten_segments(vec![segments.clone()], 0.5);
+                let worst = deviation(&contours[0], &segments);
+                assert!(worst <= 0.5 * 1.001, "{c:?} is {worst} units off");
+            }
+        }
+    }
+}
@@ -0,0 +1,1297 @@
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex, Once, OnceLock};
+use std::ops::{ControlFlow, Range};
+use std::time::{Duration, Instant};
+use std::{collections::HashMap, fs::File, io::Read};
+
+use crate::avar::Avar;
+use crate::cache::ShardedCache;
+use crate::cmap::{map_str_with, CharMapping, Cmap};
+use crate::fvar::Fvar;
+use crate::eblc::Eblc;
+use crate::fixed::Fixed;
+use crate::flatten::Contour;
+use crate::base::Base;
+use crate::gdef::Gdef;
+use crate::glyph::GlyphData;
+use crate::gpos::Gpos;
+use crate::gsub::Gsub;
+use crate::gvar::Gvar;
+use crate::hinting::Hinter;
+use crate::hvar::Hvar;
+use crate::name::Name;
+use crate::os2::Os2;
+use crate::post::Post;
+use crate::reader::FontReader;
+use crate::stat::Stat;
+use crate::trak::Trak;
+use crate::stats::{Stats, StatsCollector};
+use crate::svg::Svg;
+
+/// How many glyphs `parse_all_glyphs_with` parses between progress reports.
+const PROGRESS_INTERVAL: u32 = 256;
+
+/// More tables than any real font has, a directory claiming more is corrupt.
+pub(crate) const MAX_TABLES: u16 = 4096;
+
+/// The units per em of a font without head, the most common value after 2048 and the one CFF
+/// fonts default to.
+const ASSUMED_UNITS_PER_EM: u16 = 1000;
+
+/// How a font is read, see `Font::from_bytes_with`. Made with `default` (glyphs as the font
+/// has them) and the `with_*` methods, fields can be added without breaking callers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// Re-orient the contours of each simple glyph as it's parsed (see `GlyphData::fix_winding`),
+    /// for fonts mixing directions that the nonzero fill leaves without holes. Instructions
+    /// address points by number, glyphs rewound this way are best drawn unhinted
+    pub normalize_winding: bool,
+    /// Print the table directory and the glyph count while parsing
+    pub verbose: bool,
+}
+
+impl ParseOptions {
+    pub fn with_normalize_winding(mut self, normalize_winding: bool) -> Self {
+        self.normalize_winding = normalize_winding;
+        self
+    }
+
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+}
+
+/*
+#[derive(Debug)]
+struct FontHeader {
+    version: u32,
+    font_revision: u32,
+
+}*/
+
+#[derive(Debug)]
+pub struct Font {
+    tables: HashMap<String, (u32, u32, u32)>, // tag :(checkSum, offset, length)
+    duplicate_tables: Vec<(String, (u32, u32, u32))>, // directory entries not used, their tag was already there
+    assumptions: Vec<(String, String)>, // (tag, what was assumed) for missing tables
+    long_loca: bool, // head.indexToLocFormat, or what loca looks like without head
+    /// Parsed on first use, see `glyph`
+    glyph_data: Vec<OnceLock<GlyphData>>,
+    glyph_locations: Vec<u64>, // numGlyphs + 1 offsets into the file, from loca
+    declared_num_glyphs: u16, // maxp.numGlyphs, more than num_glyphs when loca is too short
+    blank_notdef: bool, // glyph 0 has no contours, see `Font::drawn_outline`
+    units_per_em: u16,
+    font_revision: Fixed,
+    mac_style: u16,
+    ascender: i16,
+    descender: i16,
+    line_gap: i16,
+    caret_slope: (i16, i16), // hhea caretSlopeRise, caretSlopeRun
+    caret_offset: i16,
+    advance_widths: Vec<u16>, // hmtx advances, expanded to numGlyphs, see `expand_advances`
+    left_side_bearings: Vec<i16>,
+    vertical_advances: Vec<u16>, // vmtx, empty for fonts without vertical metrics
+    top_side_bearings: Vec<i16>,
+    cmap: Option<Cmap>,
+    post: Post,
+    os2: Option<Os2>,
+    name: Name,
+    fvar: Option<Fvar>,
+    stat: Option<Stat>,
+    trak: Option<Trak>,
+    avar: Option<Avar>,
+    hvar: Option<Hvar>,
+    gvar: Option<Gvar>,
+    svg: Option<Svg>,
+    base: Option<Base>,
+    gdef: Option<Gdef>,
+    gpos: Option<Gpos>,
+    gsub: Option<Gsub>,
+    eblc: Option<Eblc>,
+    /// Current position in the design space, one normalized (-1 to 1) value per fvar axis. Empty
+    /// means the default instance
+    pub(crate) normalized_coords: Vec<f32>,
+    reverse_cmap: OnceLock<HashMap<u16, Vec<char>>>,
+    /// Hinting state per ppem, `None` when the font programs failed to run at that size
+    pub(crate) hinters: ShardedCache<u16, Option<Arc<Hinter>>>,
+    /// Flattened outlines by glyph and power of two of the tolerance, see `Font::flattened_glyph`
+    pub(crate) flattened: ShardedCache<(u16, i32), Arc<Vec<Contour>>>,
+    /// See `Font::warn_uninstructed`
+    pub(crate) uninstructed_warning: Once,
+    pub(crate) stats: StatsCollector,
+    normalize_winding: bool, // see `ParseOptions`
+    rewound_glyphs: Mutex<BTreeSet<u16>>, // see `Font::rewound_glyphs`
+    data: Cow<'static, [u8]>, // the whole font file, borrowed for the builtin font
+}
+
+/// Where each table of a font file is, from its table directory.
+pub(crate) struct TableDirectory {
+    pub num_tables: u16,
+    /// (checkSum, offset, length) by tag
+    pub tables: HashMap<String, (u32, u32, u32)>,
+    /// Entries not used, their tag was already there
+    pub duplicates: Vec<(String, (u32, u32, u32))>,
+}
+
+/// Read the table directory at the start of a font file of `file_len` bytes. The first entry
+/// for a tag wins, unless it points past the end of the file. `verbose` prints every entry.
+pub(crate) fn read_table_directory(reader: &mut FontReader, file_len: usize, verbose: bool) -> Result<TableDirectory> {
+    reader.with_context("table directory", |reader| {
+        reader.skip(4)?; // Skip scaler type
+        let num_tables = reader.read_u16()?;
+        if verbose {
+            println!("Font file has {num_tables} tables");
+        }
+        if num_tables > MAX_TABLES {
+            return Err(anyhow!("Implausible number of tables: {num_tables}"));
+        }
+        reader.skip(2 + 2 + 2)?; // Skip some of the fields in the file header
+
+        let fits = |(_, offset, length): (u32, u32, u32)| offset as usize + length as usize <= file_len;
+        let mut tables: HashMap<String, (u32, u32, u32)> = HashMap::new();
+        let mut duplicates = Vec::new();
+        for _ in 0..num_tables {
+            // tag : 4 | checkSum : 4 | offset : 4 | length : 4
+            let tag: String = String::from_utf8(reader.read_bytes(4)?.to_vec())?;
+            let check_sum = reader.read_u32()?;
+            let offset = reader.read_u32()?;
+            let length = reader.read_u32()?;
+            if verbose {
+                println!("Table directory with tag {tag} --- offset = {offset} | length = {length}");
+            }
+            let entry = (check_sum, offset, length);
+            match tables.get(&tag).copied() {
+                None => {
+                    tables.insert(tag, entry);
+                }
+                Some(first) if !fits(first) && fits(entry) => {
+                    duplicates.push((tag.clone(), first));
+                    tables.insert(tag, entry);
+                }
+                Some(_) => duplicates.push((tag, entry)),
+            }
+        }
+        Ok(TableDirectory {
+            num_tables,
+            tables,
+            duplicates,
+        })
+    })
+}
+
+/// Run a table parser, keeping track of how long it took.
+fn timed<T>(times: &mut Vec<(String, Duration)>, tag: &str, parse: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let parsed = parse();
+    times.push((tag.to_string(), started.elapsed()));
+    parsed
+}
+
+/// hmtx only stores advances for the first numberOfHMetrics glyphs, the ones after (the tail of
+/// a monospaced font, usually) all have the last one.
+fn expand_advances(mut advances: Vec<u16>, num_glyphs: u16) -> Vec<u16> {
+    let last = advances.last().copied().unwrap_or(0);
+    advances.resize(num_glyphs as usize, last);
+    advances
+}
+
+/// Whether loca reads as long offsets, for a font without head to say: a whole number of them,
+/// one more than maxp's glyph count when maxp is there, never decreasing and within glyf.
+fn long_loca_fits(loca: &[u8], glyph_table_length: u32, maxp_num_glyphs: Option<u16>) -> bool {
+    let entries = loca.len() / 4;
+    if entries < 1 || !loca.len().is_multiple_of(4) || maxp_num_glyphs.is_some_and(|n| entries != n as usize + 1) {
+        return false;
+    }
+    let offsets: Vec<u32> = loca
+        .chunks_exact(4)
+        .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+    offsets.windows(2).all(|pair| pair[0] <= pair[1]) && offsets.last().is_some_and(|last| *last <= glyph_table_length)
+}
+
+impl Font {
+    pub fn read_truetype(filename: &str) -> Result<Font> {
+        if let Ok(mut font_file) = File::open(filename) {
+            let mut contents = Vec::<u8>::new();
+            font_file.read_to_end(&mut contents)?;
+            Font::from_bytes(contents)
+        } else {
+            println!("Failed to read file contents");
+            Err(anyhow!("Failed to read file contents"))
+        }
+    }
+
+    /// Parse a font from the contents of a TrueType file, owned or `'static` (then it's read in
+    /// place, not copied).
+    pub fn from_bytes(contents: impl Into<Cow<'static, [u8]>>) -> Result<Font> {
+        Font::from_bytes_with(contents, &ParseOptions::default())
+    }
+
+    /// `from_bytes` reading the font as `options` say.
+    pub fn from_bytes_with(contents: impl Into<Cow<'static, [u8]>>, options: &ParseOptions) -> Result<Font> {
+        let contents = contents.into();
+        let file_len: usize = contents.len();
+        let mut reader = FontReader::new(&contents);
+        let TableDirectory {
+            num_tables,
+            tables,
+            duplicates: duplicate_tables,
+        } = read_table_directory(&mut reader, file_len, options.verbose)?;
+        let mut table_parse_times = Vec::new();
+        // a reader bounded to the table, its offset and length
+        let table = |tag: &str| {
+            let (_, offset, length) = tables.get(tag)?;
+            Some((reader.table(tag, *offset, *length), *offset, *length))
+        };
+
+        // a table the font can't do without
+        let required = |tag: &str| table(tag).ok_or_else(|| anyhow!("The font has no {tag} table"));
+        // what was assumed in place of missing tables, by table
+        let mut assumptions: Vec<(String, String)> = Vec::new();
+
+        // get number of glyphs
+        let maxp_num_glyphs = match table("maxp") {
+            Some((mut maxp, maxp_table_offset, _)) => {
+                maxp.seek(maxp_table_offset as u64 + 4)?; // we skip 4 bytes here for the "version number"
+                Some(maxp.read_u16()?)
+            }
+            None => None,
+        };
+
+        let (mut loca, location_table_offset, location_table_length) = required("loca")?;
+
+        // working with the glyph table
+        let (_, glyph_table_offset, glyph_table_length) = required("glyf")?;
+        let glyph_table_end = (glyph_table_offset as u64 + glyph_table_length as u64).min(file_len as u64);
+
+        let (font_revision, units_per_em, mac_style, use_two_byte_entry) = match table("head") {
+            Some((mut head, head_table_offset, _)) => {
+                head.seek((head_table_offset + 4) as u64)?; // skip the version
+                let font_revision = head.read_fixed()?;
+                head.seek((head_table_offset + 18) as u64)?; // skip checksum adjustment, magic and flags
+                let units_per_em = head.read_u16()?;
+                head.seek((head_table_offset + 44) as u64)?; // skip the dates and the bounding box
+                let mac_style = head.read_u16()?;
+                head.seek((head_table_offset + 50) as u64)?; // skip lowestRecPPEM and fontDirectionHint
+                // check if we use two bye entries (indexToLocFormat)
+                (font_revision, units_per_em, mac_style, head.read_i16()? == 0)
+            }
+            None => {
+                let loca_data = contents.get(location_table_offset as usize..).unwrap_or_default();
+                let loca_data = &loca_data[..loca_data.len().min(location_table_length as usize)];
+                let long = long_loca_fits(loca_data, glyph_table_length, maxp_num_glyphs);
+                let format = if long { "long" } else { "short" };
+                assumptions.push((
+                    "head".to_string(),
+                    format!("{ASSUMED_UNITS_PER_EM} units per em and {format} loca offsets"),
+                ));
+                (Fixed::default(), ASSUMED_UNITS_PER_EM, 0, !long)
+            }
+        };
+
+        // loca should have numGlyphs + 1 entries, the last one marking the end of the last glyph.
+        // Broken fonts disagree with maxp, only the glyphs both tables cover are usable.
+        let entry_size = if use_two_byte_entry { 2 } else { 4 };
+        let loca_entries = (location_table_length as usize).min(file_len.saturating_sub(location_table_offset as usize))
+            / entry_size;
+        let num_glyphs = match maxp_num_glyphs {
+            Some(num_glyphs) => num_glyphs,
+            None => {
+                let num_glyphs = loca_entries.saturating_sub(1).min(u16::MAX as usize) as u16;
+                assumptions.push(("maxp".to_string(), format!("{num_glyphs} glyphs, one per loca entry but the last")));
+                num_glyphs
+            }
+        };
+        if options.verbose {
+            println!("Font contains {num_glyphs} glyphs");
+        }
+        let declared_num_glyphs = num_glyphs;
+        // a mismatch is left to `validate` to report
+        let num_glyphs = num_glyphs.min(loca_entries.saturating_sub(1).min(u16::MAX as usize) as u16);
+
+        let mut glyph_locations: Vec<u64> = vec![0u64; num_glyphs as usize + 1];
+        for i in 0..(num_glyphs as u64 + 1) {
+            loca.seek(location_table_offset as u64 + i * entry_size as u64)?;
+
+            let glyph_start_offset = if use_two_byte_entry {
+                loca.read_u16()? as u32 * 2u32
+            } else {
+                loca.read_u32()?
+            };
+
+            // an offset past the end of glyf would read whatever follows it
+            let glyph_offset = glyph_table_offset as u64 + glyph_start_offset as u64;
+            glyph_locations[i as usize] = glyph_offset.min(glyph_table_end);
+        }
+
+        // horizontal metrics
+        let (mut hhea, hhea_table_offset, _) = required("hhea")?;
+        hhea.seek((hhea_table_offset + 4) as u64)?; // skip version
+        let ascender = hhea.read_i16()?;
+        let descender = hhea.read_i16()?;
+        let line_gap = hhea.read_i16()?;
+        hhea.skip(8)?; // skip advanceWidthMax, the side bearing minimums and xMaxExtent
+        let caret_slope = (hhea.read_i16()?, hhea.read_i16()?);
+        let caret_offset = hhea.read_i16()?;
+        hhea.seek((hhea_table_offset + 34) as u64)?; // numberOfHMetrics is the last field
+        let num_h_metrics = hhea.read_u16()?;
+        let (mut hmtx, _, _) = required("hmtx")?;
+        let mut advance_widths: Vec<u16> = Vec::with_capacity(num_h_metrics as usize);
+        let mut left_side_bearings: Vec<i16> = Vec::with_capacity(num_glyphs as usize);
+        for _ in 0..num_h_metrics {
+            advance_widths.push(hmtx.read_u16()?);
+            left_side_bearings.push(hmtx.read_i16()?);
+        }
+        // glyphs past numberOfHMetrics only store their left side bearing
+        for _ in num_h_metrics..num_glyphs {
+            match hmtx.read_i16() {
+                Ok(lsb) => left_side_bearings.push(lsb),
+                Err(_) => break,
+            }
+        }
+        let advance_widths = expand_advances(advance_widths, num_glyphs);
+
+        // vertical metrics, laid out like the horizontal ones
+        let mut vertical_advances: Vec<u16> = Vec::new();
+        let mut top_side_bearings: Vec<i16> = Vec::new();
+        if let (Some((mut vhea, vhea_table_offset, _)), Some((mut vmtx, _, _))) = (table("vhea"), table("vmtx")) {
+            vhea.seek((vhea_table_offset + 34) as u64)?; // numOfLongVerMetrics
+            let num_v_metrics = vhea.read_u16()?;
+            for _ in 0..num_v_metrics {
+                vertical_advances.push(vmtx.read_u16()?);
+                top_side_bearings.push(vmtx.read_i16()?);
+            }
+            for _ in num_v_metrics..num_glyphs {
+                match vmtx.read_i16() {
+                    Ok(tsb) => top_side_bearings.push(tsb),
+                    Err(_) => break,
+                }
+            }
+        }
+
+        let cmap = timed(&mut table_parse_times, "cmap", || match table("cmap") {
+            Some((mut reader, offset, _)) => match Cmap::from_reader(&mut reader, offset, num_glyphs) {
+                Ok(cmap) => Some(cmap),
+                Err(err) => {
+                    println!("Error reading cmap : {err}");
+                    None
+                }
+            },
+            None => None,
+        });
+
+        let post = timed(&mut table_parse_times, "post", || match table("post") {
+            Some((mut reader, offset, length)) => {
+                Post::from_reader(&mut reader, offset, length, num_glyphs)
+                    .unwrap_or_default()
+            }
+            None => Post::default(),
+        });
+
+        let os2 = timed(&mut table_parse_times, "OS/2", || match table("OS/2") {
+            Some((mut reader, offset, _)) => Os2::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let name = timed(&mut table_parse_times, "name", || match table("name") {
+            Some((mut reader, offset, _)) => Name::from_reader(&mut reader, offset).unwrap_or_default(),
+            None => Name::default(),
+        });
+
+        let fvar = timed(&mut table_parse_times, "fvar", || match table("fvar") {
+            Some((mut reader, offset, _)) => Fvar::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let stat = timed(&mut table_parse_times, "STAT", || match table("STAT") {
+            Some((mut reader, offset, _)) => Stat::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let trak = timed(&mut table_parse_times, "trak", || match table("trak") {
+            Some((mut reader, offset, _)) => Trak::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let avar = timed(&mut table_parse_times, "avar", || match table("avar") {
+            Some((mut reader, offset, _)) => Avar::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let hvar = timed(&mut table_parse_times, "HVAR", || match table("HVAR") {
+            Some((mut reader, offset, _)) => match Hvar::from_reader(&mut reader, offset) {
+                Ok(hvar) => Some(hvar),
+                Err(err) => {
+                    println!("Error reading HVAR : {err}");
+                    None
+                }
+            },
+            None => None,
+        });
+
+        let gvar = timed(&mut table_parse_times, "gvar", || match table("gvar") {
+            Some((mut reader, offset, _)) => match Gvar::from_reader(&mut reader, offset) {
+                Ok(gvar) => Some(gvar),
+                Err(err) => {
+                    println!("Error reading gvar : {err}");
+                    None
+                }
+            },
+            None => None,
+        });
+
+        let svg = timed(&mut table_parse_times, "SVG ", || match table("SVG ") {
+            Some((mut reader, offset, _)) => Svg::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let base = timed(&mut table_parse_times, "BASE", || match table("BASE") {
+            Some((mut reader, offset, _)) => Base::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let gdef = timed(&mut table_parse_times, "GDEF", || match table("GDEF") {
+            Some((mut reader, offset, _)) => Gdef::from_reader(&mut reader, offset).ok(),
+            None => None,
+        });
+
+        let gpos = timed(&mut table_parse_times, "GPOS", || match table("GPOS") {
+            Some((mut reader, offset, _)) => match Gpos::from_reader(&mut reader, offset) {
+                Ok(gpos) => Some(gpos),
+                Err(err) => {
+                    println!("Error reading GPOS : {err}");
+                    None
+                }
+            },
+            None => None,
+        });
+
+        let gsub = timed(&mut table_parse_times, "GSUB", || match table("GSUB") {
+            Some((mut reader, offset, _)) => match Gsub::from_reader(&mut reader, offset) {
+                Ok(gsub) => Some(gsub),
+                Err(err) => {
+                    println!("Error reading GSUB : {err}");
+                    None
+                }
+            },
+            None => None,
+        });
+
+        let eblc = timed(&mut table_parse_times, "EBLC", || match table("EBLC") {
+            Some((mut reader, offset, _)) if tables.contains_key("EBDT") => {
+                match Eblc::from_reader(&mut reader, offset) {
+                    Ok(eblc) => Some(eblc),
+                    Err(err) => {
+                        println!("Error reading EBLC : {err}");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        });
+
+        table_parse_times.retain(|(tag, _)| tables.contains_key(tag));
+        if options.verbose {
+            println!("Number of tables : {num_tables}");
+        }
+        let mut font = Font {
+            tables,
+            duplicate_tables,
+            assumptions,
+            long_loca: !use_two_byte_entry,
+            glyph_data: (0..num_glyphs).map(|_| OnceLock::new()).collect(),
+            glyph_locations,
+            declared_num_glyphs,
+            blank_notdef: false,
+            units_per_em,
+            font_revision,
+            mac_style,
+            ascender,
+            descender,
+            line_gap,
+            caret_slope,
+            caret_offset,
+            advance_widths,
+            left_side_bearings,
+            vertical_advances,
+            top_side_bearings,
+            cmap,
+            post,
+            os2,
+            name,
+            fvar,
+            stat,
+            trak,
+            avar,
+            hvar,
+            gvar,
+            svg,
+            base,
+            gdef,
+            gpos,
+            gsub,
+            eblc,
+            normalized_coords: Vec::new(),
+            reverse_cmap: OnceLock::new(),
+            hinters: ShardedCache::new(),
+            flattened: ShardedCache::new(),
+            uninstructed_warning: Once::new(),
+            stats: StatsCollector::new(table_parse_times),
+            normalize_winding: options.normalize_winding,
+            rewound_glyphs: Mutex::new(BTreeSet::new()),
+            data: contents,
+        };
+        font.blank_notdef = font.glyph_outline(0).is_some_and(|outline| outline.contours.is_empty());
+        Ok(font)
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.glyph_data.len() as u16
+    }
+
+    /// Whether glyph 0, .notdef, has no contours, which would leave characters the font has no
+    /// glyph for invisible. It's drawn as a box instead, see `Font::drawn_outline`.
+    pub fn notdef_is_blank(&self) -> bool {
+        self.blank_notdef
+    }
+
+    /// The glyph count maxp gives, which differs from `num_glyphs` in broken fonts whose loca has
+    /// fewer entries: only the glyphs both tables cover are used.
+    pub fn declared_num_glyphs(&self) -> u16 {
+        self.declared_num_glyphs
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    /// head.fontRevision, the version the font's designer gave it (e.g. 1.5 for "Version 1.500").
+    pub fn font_revision(&self) -> Fixed {
+        self.font_revision
+    }
+
+    /// head.macStyle, bit 0 bold, 1 italic, 5 condensed and 6 extended. See `Font::style`.
+    pub fn mac_style(&self) -> u16 {
+        self.mac_style
+    }
+
+    /// Typographic ascender from hhea, in font units.
+    pub fn ascender(&self) -> i16 {
+        self.ascender
+    }
+
+    /// Typographic descender from hhea, in font units (usually negative).
+    pub fn descender(&self) -> i16 {
+        self.descender
+    }
+
+    pub fn line_gap(&self) -> i16 {
+        self.line_gap
+    }
+
+    /// hhea caretSlopeRise and caretSlopeRun: the caret goes run right for every rise up, (1, 0)
+    /// for upright fonts.
+    pub fn caret_slope(&self) -> (i16, i16) {
+        self.caret_slope
+    }
+
+    /// hhea caretOffset, how far right of the pen a slanted caret is drawn, in font units.
+    pub fn caret_offset(&self) -> i16 {
+        self.caret_offset
+    }
+
+    pub fn post(&self) -> &Post {
+        &self.post
+    }
+
+    pub(crate) fn cmap(&self) -> Option<&Cmap> {
+        self.cmap.as_ref()
+    }
+
+    pub fn os2(&self) -> Option<&Os2> {
+        self.os2.as_ref()
+    }
+
+    /// A string from the name table, see the constants in `name` for the common ids.
+    pub fn name(&self, name_id: u16) -> Option<&str> {
+        self.name.get(name_id)
+    }
+
+    pub fn fvar(&self) -> Option<&Fvar> {
+        self.fvar.as_ref()
+    }
+
+    pub fn stat(&self) -> Option<&Stat> {
+        self.stat.as_ref()
+    }
+
+    pub fn trak(&self) -> Option<&Trak> {
+        self.trak.as_ref()
+    }
+
+    pub fn base(&self) -> Option<&Base> {
+        self.base.as_ref()
+    }
+
+    pub(crate) fn gdef(&self) -> Option<&Gdef> {
+        self.gdef.as_ref()
+    }
+
+    pub(crate) fn gpos(&self) -> Option<&Gpos> {
+        self.gpos.as_ref()
+    }
+
+    pub(crate) fn gsub(&self) -> Option<&Gsub> {
+        self.gsub.as_ref()
+    }
+
+    pub(crate) fn eblc(&self) -> Option<&Eblc> {
+        self.eblc.as_ref()
+    }
+
+    pub(crate) fn gvar(&self) -> Option<&Gvar> {
+        self.gvar.as_ref()
+    }
+
+    pub(crate) fn name_table(&self) -> &Name {
+        &self.name
+    }
+
+    pub(crate) fn svg(&self) -> Option<&Svg> {
+        self.svg.as_ref()
+    }
+
+    /// The font compiled into the crate with the `embedded-font` feature (Inconsolata Regular,
+    /// under the SIL Open Font License), parsed the first time it's asked for.
+    #[cfg(feature = "embedded-font")]
+    pub fn builtin() -> &'static Font {
+        static BUILTIN: OnceLock<Font> = OnceLock::new();
+        static DATA: &[u8] = include_bytes!("../Inconsolata-Regular.ttf");
+        BUILTIN.get_or_init(|| Font::from_bytes(DATA).expect("the builtin font parses"))
+    }
+
+    /// The whole font file.
+    pub(crate) fn file_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn avar(&self) -> Option<&Avar> {
+        self.avar.as_ref()
+    }
+
+    /// Tags in the table directory, sorted.
+    pub fn table_tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self.tables.keys().map(|tag| tag.as_str()).collect();
+        tags.sort();
+        tags
+    }
+
+    /// What parsing assumed in place of the tables the font lacks: (tag, the assumption).
+    pub fn assumptions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.assumptions.iter().map(|(tag, assumption)| (tag.as_str(), assumption.as_str()))
+    }
+
+    /// Whether loca holds 4 byte offsets, as head says or, without head, as loca looks.
+    pub(crate) fn long_loca(&self) -> bool {
+        self.long_loca
+    }
+
+    /// Directory entries left out because an earlier one had the same tag: the tag, offset and
+    /// length of each. Only malformed fonts have any.
+    pub fn duplicate_tables(&self) -> impl Iterator<Item = (&str, u32, u32)> {
+        self.duplicate_tables.iter().map(|(tag, (_, offset, length))| (tag.as_str(), *offset, *length))
+    }
+
+    /// Tags in the table directory with the checksum recorded for each.
+    pub(crate) fn table_checksums(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.tables.iter().map(|(tag, (check_sum, _, _))| (tag.as_str(), *check_sum))
+    }
+
+    /// Raw bytes of a table from the table directory.
+    pub fn table_data(&self, tag: &str) -> Option<&[u8]> {
+        let (_, offset, length) = self.tables.get(tag)?;
+        self.data
+            .get(*offset as usize..(*offset as usize + *length as usize))
+    }
+
+    /// The glyf record of a glyph as stored in the file, empty for glyphs without an outline.
+    pub fn raw_glyph(&self, index: u16) -> Option<&[u8]> {
+        let start = *self.glyph_locations.get(index as usize)? as usize;
+        let end = *self.glyph_locations.get(index as usize + 1)? as usize;
+        if start >= end {
+            return Some(&[]);
+        }
+        self.data.get(start..end)
+    }
+
+    /// The glyph outline at the current variation coordinates, parsed from glyf (and moved by
+    /// gvar) the first time it's asked for. Glyphs that fail to parse come back empty, `None`
+    /// past the last glyph.
+    pub fn glyph(&self, index: u16) -> Option<&GlyphData> {
+        let glyph = self.glyph_data.get(index as usize)?;
+        if let Some(glyph) = glyph.get() {
+            self.stats.glyph_cache(true);
+            return Some(glyph);
+        }
+        Some(glyph.get_or_init(|| {
+            self.stats.glyph_cache(false);
+            let started = Instant::now();
+            let glyph = self.parse_glyph(index).unwrap_or_else(|err| {
+                println!("Error reading glyf : {err}");
+                self.empty_glyph(index)
+            });
+            self.stats.record_glyph(started.elapsed(), &glyph);
+            glyph
+        }))
+    }
+
+    /// Parse glyph `index` from glyf afresh, with its phantom points and variations applied,
+    /// without caching it. A composite that can't be expanded within the limits is an error.
+    fn parse_glyph(&self, index: u16) -> Result<GlyphData> {
+        let location = |index: usize| self.glyph_locations.get(index).copied();
+        let (Some(start), Some(end)) = (location(index as usize), location(index as usize + 1)) else {
+            return Err(anyhow!("glyph {index} is past the last glyph ({} glyphs)", self.num_glyphs()));
+        };
+        // glyphs without any data (e.g. space) have the same offset as the next one
+        let mut glyph = if start >= end {
+            GlyphData::empty()
+        } else {
+            let mut reader = FontReader::new(&self.data).table("glyf", start as u32, (end - start) as u32);
+            let glyph = reader.with_context(format!("glyph {index}"), GlyphData::from_reader)?;
+            if !glyph.is_simple {
+                self.check_composite(index)?;
+            }
+            glyph
+        };
+        self.finish_glyph(index, &mut glyph);
+        // after the variations, gvar moves the points in the order they're stored
+        if self.normalize_winding && glyph.fix_winding() {
+            self.rewound_glyphs.lock().unwrap().insert(index);
+        }
+        Ok(glyph)
+    }
+
+    /// The glyphs parsed so far whose contours `ParseOptions::normalize_winding` re-oriented,
+    /// glyphs are parsed as they're first used (see `parse_all_glyphs_with` to parse them all).
+    pub fn rewound_glyphs(&self) -> Vec<u16> {
+        self.rewound_glyphs.lock().unwrap().iter().copied().collect()
+    }
+
+    /// What a glyph that fails to parse becomes: empty, so the font keeps working.
+    fn empty_glyph(&self, index: u16) -> GlyphData {
+        let mut glyph = GlyphData::empty();
+        self.finish_glyph(index, &mut glyph);
+        glyph
+    }
+
+    fn finish_glyph(&self, index: u16, glyph: &mut GlyphData) {
+        let (advance, lsb) = (self.default_advance_width(index), self.default_left_side_bearing(index));
+        glyph.phantom = self.compute_phantom_points(index, glyph, advance as f32, lsb as f32);
+        self.apply_glyph_variations(index, glyph);
+    }
+
+    /// Every glyph parsed one at a time as the iterator is advanced, see `glyphs_in`.
+    pub fn glyphs(&self) -> impl Iterator<Item = (u16, Result<GlyphData>)> + '_ {
+        self.glyphs_in(0..self.num_glyphs())
+    }
+
+    /// The glyphs of `range` (clamped to the glyphs the font has) parsed one at a time as the
+    /// iterator is advanced, at the current variation coordinates. Nothing is cached: only the
+    /// glyph being parsed is held, however many glyphs the font has, and `glyph` parses (and
+    /// caches) them again.
+    pub fn glyphs_in(&self, range: Range<u16>) -> impl Iterator<Item = (u16, Result<GlyphData>)> + '_ {
+        let range = range.start.min(self.num_glyphs())..range.end.min(self.num_glyphs());
+        range.map(|index| (index, self.parse_glyph(index)))
+    }
+
+    /// pp1 sits left of the bounding box by the side bearing, pp2 one advance further. Vertically
+    /// the origin is the top side bearing above the box, fonts without vmtx use the ascender and
+    /// the descender (the same as FreeType).
+    fn compute_phantom_points(&self, index: u16, glyph: &GlyphData, advance: f32, lsb: f32) -> [(f32, f32); 4] {
+        let origin_x = glyph.x_min as f32 - lsb;
+        let (top, bottom) = match (self.advance_height(index), self.top_side_bearing(index)) {
+            (Some(advance_height), Some(tsb)) => {
+                let top = (glyph.y_max as i32 + tsb as i32) as f32;
+                (top, top - advance_height as f32)
+            }
+            _ => (self.ascender as f32, self.descender as f32),
+        };
+        [(origin_x, 0.), (origin_x + advance, 0.), (0., top), (0., bottom)]
+    }
+
+    /// The phantom points of a glyph at the current variation coordinates, in font units.
+    pub fn phantom_points(&self, index: u16) -> Option<[(f32, f32); 4]> {
+        let glyph = self.glyph(index)?;
+        if self.normalized_coords.is_empty() || self.hvar.is_none() {
+            return Some(glyph.phantom_points());
+        }
+        let (advance, lsb) = (self.advance_width(index), self.left_side_bearing(index));
+        Some(self.compute_phantom_points(index, glyph, advance as f32, lsb as f32))
+    }
+
+    /// Parse every glyph up front instead of on first use. `progress` is called with the number of
+    /// glyphs done and the total every `PROGRESS_INTERVAL` glyphs and once at the end, returning
+    /// `Break` stops early. Glyphs parsed so far stay cached and the rest are parsed lazily as usual.
+    pub fn parse_all_glyphs_with(&self, mut progress: impl FnMut(u32, u32) -> ControlFlow<()>) -> ControlFlow<()> {
+        let total = self.glyph_data.len() as u32;
+        for index in 0..total {
+            self.glyph(index as u16);
+            let done = index + 1;
+            if done % PROGRESS_INTERVAL == 0 && done != total {
+                progress(done, total)?;
+            }
+        }
+        progress(total, total)
+    }
+
+    /// Table parse times, glyph parse times and memory, and cache hit rates up to now.
+    pub fn stats(&self) -> Stats {
+        self.stats.snapshot()
+    }
+
+    pub(crate) fn default_advance_width(&self, index: u16) -> u16 {
+        self.advance_widths.get(index as usize).copied().unwrap_or(0)
+    }
+
+    /// The hmtx advance of every glyph in font units, indexed by glyph, at the default instance.
+    pub fn advances(&self) -> &[u16] {
+        &self.advance_widths
+    }
+
+    /// The advance of every glyph in pixels at `px_size`, at the current variation coordinates.
+    pub fn scaled_advances(&self, px_size: f32) -> Vec<f32> {
+        let scale = self.scale_for_px(px_size);
+        if self.normalized_coords.is_empty() {
+            return self.advance_widths.iter().map(|advance| *advance as f32 * scale).collect();
+        }
+        (0..self.num_glyphs()).map(|index| self.advance_width(index) as f32 * scale).collect()
+    }
+
+    /// Advance in pixels of the glyph `c` maps to (.notdef's for unmapped characters).
+    pub fn advance_for_char(&self, c: char, px_size: f32) -> f32 {
+        let index = self.glyph_index_for_char(c).unwrap_or(0);
+        self.advance_width(index) as f32 * self.scale_for_px(px_size)
+    }
+
+    pub(crate) fn default_left_side_bearing(&self, index: u16) -> i16 {
+        self.left_side_bearings.get(index as usize).copied().unwrap_or(0)
+    }
+
+    /// Vertical advance from vmtx, `None` for fonts without vertical metrics.
+    pub fn advance_height(&self, index: u16) -> Option<u16> {
+        self.vertical_advances.get(index as usize).or(self.vertical_advances.last()).copied()
+    }
+
+    pub fn top_side_bearing(&self, index: u16) -> Option<i16> {
+        self.top_side_bearings.get(index as usize).copied()
+    }
+
+    /// Advance width in font units; glyphs past numberOfHMetrics share the last advance. With
+    /// variation coordinates set the HVAR delta is added, fonts without HVAR take the distance
+    /// between the glyph's varied phantom points.
+    pub fn advance_width(&self, index: u16) -> u16 {
+        let advance = self.default_advance_width(index);
+        if self.normalized_coords.is_empty() {
+            return advance;
+        }
+        match (&self.hvar, &self.gvar) {
+            (Some(hvar), _) => {
+                let delta = hvar.advance_delta(index, &self.normalized_coords);
+                (advance as f32 + delta).round().max(0.) as u16
+            }
+            (None, Some(_)) => self.glyph(index).map_or(advance, |glyph| {
+                let [origin, advance, ..] = glyph.phantom;
+                (advance.0 - origin.0).round().max(0.) as u16
+            }),
+            (None, None) => advance,
+        }
+    }
+
+    /// Left side bearing in font units. With variation coordinates set it's adjusted by HVAR when
+    /// the font has side bearing deltas, or else measured from the varied glyph and phantom points.
+    pub fn left_side_bearing(&self, index: u16) -> i16 {
+        let lsb = self.default_left_side_bearing(index);
+        if self.normalized_coords.is_empty() {
+            return lsb;
+        }
+        if let Some(delta) = self.hvar.as_ref().and_then(|hvar| hvar.lsb_delta(index, &self.normalized_coords)) {
+            return (lsb as f32 + delta).round() as i16;
+        }
+        match (&self.gvar, self.glyph(index)) {
+            (Some(_), Some(glyph)) => (glyph.x_min as f32 - glyph.phantom[0].0).round() as i16,
+            _ => lsb,
+        }
+    }
+
+    /// Forget the parsed glyphs, their flattenings and hinting state, for when the variation
+    /// coordinates change.
+    pub(crate) fn clear_glyph_cache(&mut self) {
+        for glyph in &mut self.glyph_data {
+            glyph.take();
+        }
+        self.hinters.clear();
+        self.flattened.clear();
+    }
+
+    /// `None` for unmapped characters, and for those the cmap maps past the last glyph.
+    pub fn glyph_index_for_char(&self, c: char) -> Option<u16> {
+        self.cmap.as_ref().and_then(|cmap| cmap.glyph_index(c))
+    }
+
+    /// Map every character of `text` to its glyph in one pass. A variation selector picks the
+    /// variant glyph of the character before it and belongs to its mapping (the glyph of the
+    /// character alone when the font has no such sequence). Lookups start from the cmap range
+    /// the previous character was in, so runs of one script skip the search.
+    pub fn map_str(&self, text: &str) -> Vec<CharMapping> {
+        match &self.cmap {
+            Some(cmap) => cmap.map_str(text),
+            None => map_str_with(text, |_, _| None),
+        }
+    }
+
+    /// The glyph for `c` followed by a variation selector (VS16 for emoji presentation, VS15 for
+    /// text, the ideographic variants...), `None` when the font has no such variation sequence.
+    pub fn glyph_index_with_variation(&self, c: char, selector: char) -> Option<u16> {
+        self.cmap.as_ref()?.glyph_index_with_variation(c, selector)
+    }
+
+    pub fn glyph_name(&self, index: u16) -> Option<&str> {
+        self.post.glyph_name(index)
+    }
+
+    /// Every character the cmap maps to a glyph, in codepoint order.
+    pub fn covered_chars(&self) -> Vec<char> {
+        self.cmap.as_ref().map_or_else(Vec::new, Cmap::chars)
+    }
+
+    /// All the characters the cmap maps to this glyph, in codepoint order.
+    pub fn chars_for_glyph(&self, index: u16) -> Vec<char> {
+        let reverse_cmap = self.reverse_cmap.get_or_init(|| match &self.cmap {
+            Some(cmap) => cmap.reverse_map(),
+            None => HashMap::new(),
+        });
+        reverse_cmap.get(&index).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outline::{Contour, Outline, OutlinePoint};
+    use crate::test_fonts::{rectangle, TestFontBuilder};
+
+    fn load(builder: &TestFontBuilder) -> Font {
+        Font::from_bytes(builder.build().unwrap()).unwrap()
+    }
+
+    /// An open run of on-curve points, `step` units apart in x and y.
+    fn diagonal(points: usize, step: f32) -> Outline {
+        let points = (0..points).map(|i| OutlinePoint {
+            x: i as f32 * step,
+            y: i as f32 * step,
+            on_curve: true,
+        });
+        let contour = Contour { points: points.collect() };
+        let mut outline = Outline {
+            contours: vec![contour],
+            ..Outline::default()
+        };
+        outline.update_bbox();
+        outline
+    }
+
+    #[test]
+    fn repeated_flags_apply_to_the_points_after_them() {
+        // four on-curve points with positive one byte x deltas and the same y, as one flag
+        // repeated three times
+        let flag = 0x01 | 0x02 | 0x08 | 0x10 | 0x20;
+        let mut record = Vec::new();
+        for value in [1i16, 10, 0, 100, 0, 3, 0] {
+            record.extend_from_slice(&value.to_be_bytes());
+        }
+        record.extend_from_slice(&[flag, 3, 10, 20, 30, 40]);
+        let font = load(TestFontBuilder::new().add_raw_glyph('a', &record, 500));
+        let glyph = font.glyph(1).unwrap();
+        assert_eq!(glyph.x_coords, [10, 30, 60, 100]);
+        assert_eq!(glyph.y_coords, [0; 4]);
+        assert_eq!(glyph.on_curve, [true; 4]);
+        assert_eq!(glyph.contour_end_indices, [3]);
+    }
+
+    #[test]
+    fn repeat_counts_past_the_last_point_are_cut_off() {
+        let flag = 0x01 | 0x10 | 0x20;
+        let mut record = Vec::new();
+        for value in [1i16, 0, 0, 0, 0, 1, 0] {
+            record.extend_from_slice(&value.to_be_bytes());
+        }
+        record.extend_from_slice(&[flag | 0x08, 5]);
+        let font = load(TestFontBuilder::new().add_raw_glyph('a', &record, 500));
+        assert_eq!(font.glyph(1).unwrap().num_points(), 2);
+    }
+
+    #[test]
+    fn written_flag_runs_read_back() {
+        // the points after the first share their flags, which the writer repeats
+        let outline = diagonal(40, 10.);
+        let font = load(TestFontBuilder::new().add_glyph('a', &outline));
+        let record = font.raw_glyph(1).unwrap();
+        // the header, one byte deltas after the first point and far fewer than 40 flags
+        assert!(record.len() < 14 + 2 * 39 + 40, "flags weren't repeated: {} bytes", record.len());
+        let glyph = font.glyph(1).unwrap();
+        let expected: Vec<i16> = (0..40).map(|i| i * 10).collect();
+        assert_eq!(glyph.x_coords, expected);
+        assert_eq!(glyph.y_coords, expected);
+    }
+
+    #[test]
+    fn empty_glyphs_have_no_record_and_no_points() {
+        let mut builder = TestFontBuilder::new();
+        builder
+            .add_glyph(' ', &Outline::default())
+            .add_raw_glyph('\u{a0}', &[], 300)
+            .add_glyph('a', &rectangle(0., 0., 100., 200.));
+        let font = load(&builder);
+        for c in [' ', '\u{a0}'] {
+            let index = font.glyph_index_for_char(c).unwrap();
+            assert_eq!(font.raw_glyph(index), Some(&[][..]), "{c:?}");
+            let glyph = font.glyph(index).unwrap();
+            assert!(glyph.is_simple && glyph.num_points() == 0, "{c:?}");
+        }
+        assert_eq!(font.advance_width(builder.glyph_index(' ')), 500);
+        assert_eq!(font.advance_width(builder.glyph_index('\u{a0}')), 300);
+        assert!(font.glyph(font.num_glyphs()).is_none());
+    }
+
+    #[test]
+    fn composites_place_their_components() {
+        let mut builder = TestFontBuilder::new();
+        builder
+            .add_glyph('a', &rectangle(0., 0., 100., 200.))
+            .add_composite('b', &[('a', (0, 0)), ('a', (150, -50))]);
+        let font = load(&builder);
+        let index = builder.glyph_index('b');
+        assert!(!font.glyph(index).unwrap().is_simple);
+        let outline = font.glyph_outline(index).unwrap();
+        assert_eq!(outline.contours.len(), 2);
+        assert_eq!(outline.tight_bbox(), (0., -50., 250., 200.));
+        assert_eq!(font.advance_width(index), font.advance_width(builder.glyph_index('a')));
+    }
+
+    #[test]
+    fn composites_of_composites_resolve() {
+        let mut builder = TestFontBuilder::new();
+        builder
+            .add_composite('c', &[('b', (10, 0))])
+            .add_composite('b', &[('a', (0, 20))])
+            .add_glyph('a', &rectangle(0., 0., 100., 200.));
+        let font = load(&builder);
+        let outline = font.glyph_outline(builder.glyph_index('c')).unwrap();
+        assert_eq!(outline.tight_bbox(), (10., 20., 110., 220.));
+    }
+
+    #[test]
+    fn composites_containing_themselves_are_refused() {
+        let mut builder = TestFontBuilder::new();
+        builder.add_composite('a', &[('a', (0, 0))]);
+        assert!(builder.build().is_err());
+    }
+
+    /// A font of three glyphs of different sizes, without the tables `missing`.
+    fn font_without(missing: &[&str]) -> (Font, Font) {
+        let mut builder = TestFontBuilder::new();
+        builder
+            .add_glyph('a', &rectangle(0., 0., 100., 200.))
+            .add_glyph(' ', &Outline::default())
+            .add_glyph('b', &diagonal(30, 10.));
+        let complete = load(&builder);
+        let mut tables = builder.font_builder().unwrap();
+        for tag in missing {
+            tables.remove_table(tag);
+        }
+        (complete, Font::from_bytes(tables.build().unwrap()).unwrap())
+    }
+
+    fn assert_same_glyphs(complete: &Font, font: &Font) {
+        assert_eq!(font.num_glyphs(), complete.num_glyphs());
+        for index in 0..complete.num_glyphs() {
+            let (expected, glyph) = (complete.glyph(index).unwrap(), font.glyph(index).unwrap());
+            assert_eq!(glyph.x_coords, expected.x_coords, "glyph {index}");
+            assert_eq!(glyph.y_coords, expected.y_coords, "glyph {index}");
+        }
+    }
+
+    #[test]
+    fn fonts_without_head_assume_the_units_per_em() {
+        let (complete, font) = font_without(&["head"]);
+        assert_eq!(font.units_per_em(), ASSUMED_UNITS_PER_EM);
+        assert_eq!(font.long_loca(), complete.long_loca());
+        let assumptions: Vec<_> = font.assumptions().map(|(tag, _)| tag).collect();
+        assert_eq!(assumptions, ["head"]);
+        assert_same_glyphs(&complete, &font);
+    }
+
+    #[test]
+    fn fonts_without_maxp_count_the_glyphs_in_loca() {
+        let (complete, font) = font_without(&["maxp"]);
+        let assumptions: Vec<_> = font.assumptions().map(|(tag, _)| tag).collect();
+        assert_eq!(assumptions, ["maxp"]);
+        assert_eq!(font.declared_num_glyphs(), 4);
+        assert_same_glyphs(&complete, &font);
+    }
+
+    #[test]
+    fn fonts_without_head_and_maxp_still_load() {
+        let (complete, font) = font_without(&["head", "maxp"]);
+        let assumptions: Vec<_> = font.assumptions().map(|(tag, _)| tag).collect();
+        assert_eq!(assumptions, ["head", "maxp"]);
+        assert_same_glyphs(&complete, &font);
+        assert_eq!(font.advance_width(1), complete.advance_width(1));
+    }
+
+    #[test]
+    fn fonts_without_loca_are_refused() {
+        let mut tables = TestFontBuilder::new().font_builder().unwrap();
+        tables.remove_table("loca");
+        assert!(Font::from_bytes(tables.build().unwrap()).is_err());
+    }
+
+    /// Expected phantom points follow fontTools' `glyf.getPhantomPoints`: pp1 is xMin less the
+    /// left side bearing and pp2 an advance right of it. Without vmtx fontTools puts pp3 at
+    /// hhea's ascender and pp4 an em below it, where this crate (like FreeType) uses the
+    /// descender: the test fonts' ascender and descender are an em apart, so both agree.
+    fn phantom_font() -> (TestFontBuilder, Font) {
+        let mut builder = TestFontBuilder::new();
+        builder
+            .add_glyph_with_advance('a', &rectangle(120., -50., 480., 700.), Some(600))
+            .add_composite('b', &[('a', (30, 100))]);
+        let mut tables = builder.font_builder().unwrap();
+        // a left side bearing of 20 for "a", unlike its xMin of 120
+        tables.table_mut("hmtx").unwrap()[6..8].copy_from_slice(&20i16.to_be_bytes());
+        (builder, Font::from_bytes(tables.build().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn phantom_points_of_simple_glyphs() {
+        let (builder, font) = phantom_font();
+        let index = builder.glyph_index('a');
+        let expected = [(100., 0.), (700., 0.), (0., 800.), (0., -200.)];
+        assert_eq!(font.glyph(index).unwrap().phantom_points(), expected);
+        assert_eq!(font.phantom_points(index), Some(expected));
+    }
+
+    #[test]
+    fn phantom_points_of_composites_use_their_own_box() {
+        let (builder, font) = phantom_font();
+        // xMin 150 from the offset component, with the composite's own side bearing of 150
+        let index = builder.glyph_index('b');
+        assert_eq!(font.glyph(index).unwrap().x_min, 150);
+        assert_eq!(font.left_side_bearing(index), 150);
+        assert_eq!(font.phantom_points(index), Some([(0., 0.), (600., 0.), (0., 800.), (0., -200.)]));
+    }
+
+    #[test]
+    fn phantom_points_of_a_real_font() {
+        let font = Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf")).unwrap();
+        // "A" and the composite "Á", 500 units wide with side bearings equal to their xMin
+        for c in ['A', '\u{c1}'] {
+            let [pp1, pp2, ..] = font.phantom_points(font.glyph_index_for_char(c).unwrap()).unwrap();
+            assert_eq!((pp1, pp2), ((0., 0.), (500., 0.)), "{c:?}");
+        }
+    }
+
+    #[test]
+    fn phantom_points_of_empty_glyphs_start_at_the_origin() {
+        let (_, font) = phantom_font();
+        assert_eq!(font.phantom_points(0), Some([(0., 0.), (500., 0.), (0., 800.), (0., -200.)]));
+    }
+
+    #[test]
+    fn unicode_cmaps_map_the_bmp_and_beyond() {
+        let mut builder = TestFontBuilder::new();
+        for c in ['a', 'b', '\u{20ac}', '\u{1f600}'] {
+            builder.add_glyph(c, &rectangle(0., 0., 100., 100.));
+        }
+        let font = load(&builder);
+        for c in ['a', 'b', '\u{20ac}', '\u{1f600}'] {
+            assert_eq!(font.glyph_index_for_char(c), Some(builder.glyph_index(c)), "{c:?}");
+        }
+        assert_eq!(font.glyph_index_for_char('c'), None);
+        assert_eq!(font.covered_chars(), ['a', 'b', '\u{20ac}', '\u{1f600}']);
+    }
+
+    #[test]
+    fn symbol_cmaps_map_typed_characters_to_the_private_use_area() {
+        let mut builder = TestFontBuilder::new();
+        builder.symbol_encoding().add_glyph('\u{f041}', &rectangle(0., 0., 100., 100.));
+        let font = load(&builder);
+        assert_eq!(font.glyph_index_for_char('A'), Some(1));
+        assert_eq!(font.glyph_index_for_char('\u{f041}'), Some(1));
+        assert_eq!(font.glyph_index_for_char('B'), None);
+    }
+
+    #[test]
+    fn mac_roman_cmaps_map_through_the_mac_encoding() {
+        let mut builder = TestFontBuilder::new();
+        builder.mac_roman_encoding();
+        for c in ['a', '\u{e9}', '\u{2122}'] {
+            builder.add_glyph(c, &rectangle(0., 0., 100., 100.));
+        }
+        let font = load(&builder);
+        for c in ['a', '\u{e9}', '\u{2122}'] {
+            assert_eq!(font.glyph_index_for_char(c), Some(builder.glyph_index(c)), "{c:?}");
+        }
+    }
+
+    /// Points, on-curve flags, contour ends, instructions and the resolved outline.
+    type GlyphSummary = (Vec<i16>, Vec<i16>, Vec<bool>, Vec<u16>, Vec<u8>, Option<Outline>);
+
+    /// What `Font::glyph` and `Font::glyph_outline` give for a glyph, to compare between fonts.
+    fn glyph_summary(font: &Font, index: u16) -> GlyphSummary {
+        let glyph = font.glyph(index).unwrap();
+        let (x, y) = (glyph.x_coords.clone(), glyph.y_coords.clone());
+        let (on_curve, ends) = (glyph.on_curve.clone(), glyph.contour_end_indices.clone());
+        (x, y, on_curve, ends, glyph.instructions.clone(), font.glyph_outline(index))
+    }
+
+    #[test]
+    fn glyphs_parsed_from_eight_threads_match_a_single_thread() {
+        fn shareable<T: Send + Sync>() {}
+        shareable::<Font>();
+        const THREADS: usize = 8;
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/SourceCodePro-Regular.ttf");
+        let alone = Font::read_truetype(path).unwrap();
+        let expected: Vec<_> = (0..alone.num_glyphs()).map(|index| glyph_summary(&alone, index)).collect();
+
+        let font = Arc::new(Font::read_truetype(path).unwrap());
+        let start = std::sync::Barrier::new(THREADS);
+        let results: Vec<Vec<_>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..THREADS)
+                .map(|thread| {
+                    let (font, start) = (Arc::clone(&font), &start);
+                    scope.spawn(move || {
+                        start.wait();
+                        // each thread starts elsewhere, so that first parses race with cache hits
+                        let num_glyphs = font.num_glyphs() as usize;
+                        let mut summaries = vec![None; num_glyphs];
+                        for i in 0..num_glyphs {
+                            let index = (i + thread * num_glyphs / THREADS) % num_glyphs;
+                            summaries[index] = Some(glyph_summary(&font, index as u16));
+                        }
+                        summaries.into_iter().map(Option::unwrap).collect()
+                    })
+                })
+                .collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+        for (thread, summaries) in results.iter().enumerate() {
+            for (index, (summary, expected)) in summaries.iter().zip(&expected).enumerate() {
+                assert!(summary == expected, "thread {thread}, glyph {index}");
+            }
+        }
+    }
+}
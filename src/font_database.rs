@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::font::{read_table_directory, TableDirectory, MAX_TABLES};
+use crate::name::Name;
+use crate::os2::Os2;
+use crate::reader::FontReader;
+use crate::style::{family_name, match_rank, FontStyle};
+
+/// Directories nested deeper than this under a font directory aren't looked into.
+const MAX_DEPTH: usize = 8;
+
+/// The name and OS/2 tables are read up to this much, against files claiming huge tables.
+const MAX_TABLE_LENGTH: u32 = 1 << 20;
+
+/// A font file found by `FontDatabase::scan_system`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemFace {
+    pub path: PathBuf,
+    pub family: String,
+    pub style: FontStyle,
+}
+
+/// The TrueType fonts installed on the machine by family and style, from their name, OS/2 and
+/// head tables only: files are opened to find a face but not parsed as fonts until one is
+/// loaded. Fonts this crate can't draw (without glyf, and collections) aren't listed.
+#[derive(Debug, Clone, Default)]
+pub struct FontDatabase {
+    faces: Vec<SystemFace>,
+}
+
+/// Where the platform keeps its fonts, for the user and for everyone.
+fn system_font_directories() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let under_home = |path: &str| home.as_ref().map(|home| home.join(path));
+    if cfg!(target_os = "macos") {
+        [under_home("Library/Fonts")]
+            .into_iter()
+            .flatten()
+            .chain(["/Library/Fonts", "/System/Library/Fonts"].map(PathBuf::from))
+            .collect()
+    } else if cfg!(windows) {
+        let windows = std::env::var_os("WINDIR").map_or_else(|| PathBuf::from("C:\\Windows"), PathBuf::from);
+        let user = std::env::var_os("LOCALAPPDATA").map(|local| PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        user.into_iter().chain([windows.join("Fonts")]).collect()
+    } else {
+        [under_home(".fonts"), under_home(".local/share/fonts")]
+            .into_iter()
+            .flatten()
+            .chain(["/usr/local/share/fonts", "/usr/share/fonts"].map(PathBuf::from))
+            .collect()
+    }
+}
+
+/// Read `length` bytes at `offset` of the file.
+fn read_at(file: &mut File, offset: u32, length: u32) -> Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset as u64))?;
+    let mut data = vec![0; length as usize];
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// The family and style of a TrueType file from its table directory and the few tables that
+/// name it, `None` for files that aren't single TrueType fonts with outlines in glyf.
+fn read_face(path: &Path) -> Result<Option<SystemFace>> {
+    let mut file = File::open(path)?;
+    let header = read_at(&mut file, 0, 12)?;
+    let mut reader = FontReader::new(&header);
+    let scaler_type = reader.read_u32()?;
+    // 0x00010000 or 'true', OpenType CFF ('OTTO') and collections ('ttcf') can't be loaded
+    if scaler_type != 0x00010000 && scaler_type != u32::from_be_bytes(*b"true") {
+        return Ok(None);
+    }
+    let num_tables = reader.read_u16()?;
+    if num_tables > MAX_TABLES {
+        return Err(anyhow!("Implausible number of tables: {num_tables}"));
+    }
+    let directory = read_at(&mut file, 0, 12 + num_tables as u32 * 16)?;
+    let file_len = file.metadata()?.len() as usize;
+    let TableDirectory { tables, .. } = read_table_directory(&mut FontReader::new(&directory), file_len, false)?;
+    let entry = |tag: &str| tables.get(tag).map(|(_, offset, length)| (*offset, *length));
+    let (name, os2, head, glyf) = (entry("name"), entry("OS/2"), entry("head"), tables.contains_key("glyf"));
+    let Some((name_offset, name_length)) = name.filter(|_| glyf) else {
+        return Ok(None);
+    };
+    let data = read_at(&mut file, name_offset, name_length.min(MAX_TABLE_LENGTH))?;
+    let name = Name::from_reader(&mut FontReader::new(&data), 0)?;
+    let Some(family) = family_name(|name_id| name.get(name_id)) else {
+        return Ok(None);
+    };
+    let os2 = match os2 {
+        Some((offset, length)) => {
+            let data = read_at(&mut file, offset, length.min(MAX_TABLE_LENGTH))?;
+            Os2::from_reader(&mut FontReader::new(&data), 0).ok()
+        }
+        None => None,
+    };
+    // head.macStyle
+    let mac_style = match head {
+        Some((offset, _)) => FontReader::new(&read_at(&mut file, offset + 44, 2)?).read_u16()?,
+        None => 0,
+    };
+    Ok(Some(SystemFace {
+        path: path.to_path_buf(),
+        family: family.to_string(),
+        style: FontStyle::from_tables(os2.as_ref(), mac_style),
+    }))
+}
+
+impl FontDatabase {
+    /// The fonts of the platform's font directories (and their subdirectories): ~/.fonts,
+    /// ~/.local/share/fonts, /usr/local/share/fonts and /usr/share/fonts on Linux,
+    /// ~/Library/Fonts, /Library/Fonts and /System/Library/Fonts on macOS, the user's and
+    /// %WINDIR%\Fonts on Windows. Directories that don't exist and files that don't parse are
+    /// skipped.
+    pub fn scan_system() -> FontDatabase {
+        FontDatabase::scan_directories(&system_font_directories())
+    }
+
+    /// The fonts of `directories` and their subdirectories, each file listed once even when
+    /// links reach it twice.
+    pub fn scan_directories(directories: &[PathBuf]) -> FontDatabase {
+        let mut database = FontDatabase::default();
+        let mut seen = HashSet::new();
+        let mut pending: Vec<(PathBuf, usize)> = directories.iter().map(|directory| (directory.clone(), 0)).collect();
+        while let Some((directory, depth)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                let Ok(canonical) = path.canonicalize() else {
+                    continue;
+                };
+                if !seen.insert(canonical.clone()) {
+                    continue;
+                }
+                if canonical.is_dir() {
+                    if depth < MAX_DEPTH {
+                        pending.push((path, depth + 1));
+                    }
+                    continue;
+                }
+                let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+                if !["ttf", "otf"].iter().any(|known| extension.eq_ignore_ascii_case(known)) {
+                    continue;
+                }
+                if let Ok(Some(face)) = read_face(&path) {
+                    database.faces.push(face);
+                }
+            }
+        }
+        database.faces.sort_by(|a, b| a.path.cmp(&b.path));
+        database
+    }
+
+    pub fn faces(&self) -> &[SystemFace] {
+        &self.faces
+    }
+
+    /// The family names, each once, sorted.
+    pub fn families(&self) -> Vec<&str> {
+        let mut families: Vec<&str> = self.faces.iter().map(|face| face.family.as_str()).collect();
+        families.sort_unstable_by_key(|family| family.to_lowercase());
+        families.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        families
+    }
+
+    /// The file of the face of `family` (ignoring case) best matching `weight` and `italic`, the
+    /// way `FontCollectionIndex::find` picks one.
+    pub fn find_family(&self, family: &str, weight: u16, italic: bool) -> Option<PathBuf> {
+        self.faces
+            .iter()
+            .filter(|face| face.family.eq_ignore_ascii_case(family))
+            .min_by_key(|face| match_rank(&face.style, weight, italic))
+            .map(|face| face.path.clone())
+    }
+}
@@ -0,0 +1,153 @@
+//! Glyph metrics from FreeType next to ours, to catch systematic scaling or rounding errors.
+//! Coverage isn't compared, the two rasterizers antialias differently, but advances and the
+//! extents of the bitmaps should agree within a pixel. FreeType sizes its bitmaps from the
+//! control box, off-curve points included, where ours hug the flattened curve, so a glyph whose
+//! off-curve points stick out past the curve can be a pixel wider there.
+
+use anyhow::{anyhow, Result};
+use freetype_sys::*;
+use std::ptr;
+
+use crate::font::Font;
+use crate::raster::RasterOptions;
+
+/// The metrics of one glyph at one ppem, bitmap extents in whole pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GlyphMetrics {
+    /// Unhinted advance width in pixels
+    pub advance: f32,
+    pub width: usize,
+    pub height: usize,
+    pub left: i32,
+    pub top: i32,
+}
+
+/// A glyph's metrics from both engines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricsComparison {
+    pub index: u16,
+    pub ours: GlyphMetrics,
+    pub freetype: GlyphMetrics,
+}
+
+impl MetricsComparison {
+    /// The largest difference between the two, in pixels. Bearings only count when both
+    /// bitmaps have pixels, an empty bitmap has no meaningful position.
+    pub fn max_deviation(&self) -> f32 {
+        let (ours, freetype) = (&self.ours, &self.freetype);
+        let mut deviation = (ours.advance - freetype.advance).abs();
+        deviation = deviation.max(ours.width.abs_diff(freetype.width) as f32);
+        deviation = deviation.max(ours.height.abs_diff(freetype.height) as f32);
+        let empty = |metrics: &GlyphMetrics| metrics.width == 0 || metrics.height == 0;
+        if !empty(ours) && !empty(freetype) {
+            deviation = deviation.max(ours.left.abs_diff(freetype.left) as f32);
+            deviation = deviation.max(ours.top.abs_diff(freetype.top) as f32);
+        }
+        deviation
+    }
+}
+
+/// A FreeType library with one face loaded from memory, freed together.
+struct FreeTypeFace {
+    library: FT_Library,
+    face: FT_Face,
+}
+
+impl FreeTypeFace {
+    fn new(data: &[u8], ppem: u32) -> Result<FreeTypeFace> {
+        let mut library = ptr::null_mut();
+        // SAFETY: FT_Init_FreeType only writes the library handle
+        let error = unsafe { FT_Init_FreeType(&mut library) };
+        if error != 0 {
+            return Err(anyhow!("FreeType failed to initialize (error {error})"));
+        }
+        let mut face = FreeTypeFace {
+            library,
+            face: ptr::null_mut(),
+        };
+        // SAFETY: the library is live, and the data outlives the face, which
+        // `compare_metrics_with_freetype` drops before returning
+        let error = unsafe { FT_New_Memory_Face(library, data.as_ptr(), data.len() as FT_Long, 0, &mut face.face) };
+        if error != 0 {
+            return Err(anyhow!("FreeType failed to load the font (error {error})"));
+        }
+        // SAFETY: the face is live
+        let error = unsafe { FT_Set_Pixel_Sizes(face.face, 0, ppem) };
+        if error != 0 {
+            return Err(anyhow!("FreeType failed to set the size to {ppem} ppem (error {error})"));
+        }
+        Ok(face)
+    }
+
+    fn num_glyphs(&self) -> u16 {
+        // SAFETY: the face is live
+        unsafe { (*self.face).num_glyphs.clamp(0, u16::MAX as FT_Long) as u16 }
+    }
+
+    /// Render a glyph unhinted from its outline, like `RasterOptions::default()` does.
+    fn metrics(&self, index: u16) -> Option<GlyphMetrics> {
+        let flags = FT_LOAD_NO_HINTING | FT_LOAD_NO_BITMAP | FT_LOAD_RENDER;
+        // SAFETY: the face is live, the glyph slot it owns stays valid until the next load
+        unsafe {
+            if FT_Load_Glyph(self.face, index as FT_UInt, flags) != 0 {
+                return None;
+            }
+            let slot = &*(*self.face).glyph;
+            Some(GlyphMetrics {
+                advance: slot.linearHoriAdvance as f32 / 65536.,
+                width: slot.bitmap.width.max(0) as usize,
+                height: slot.bitmap.rows.max(0) as usize,
+                left: slot.bitmap_left,
+                top: slot.bitmap_top,
+            })
+        }
+    }
+}
+
+impl Drop for FreeTypeFace {
+    fn drop(&mut self) {
+        // SAFETY: both handles came from FreeType and are freed once, the face first
+        unsafe {
+            if !self.face.is_null() {
+                FT_Done_Face(self.face);
+            }
+            FT_Done_FreeType(self.library);
+        }
+    }
+}
+
+impl Font {
+    /// The unhinted metrics of this glyph at `ppem` as `rasterize_glyph` renders it, an empty
+    /// glyph being an empty bitmap at the origin.
+    pub fn glyph_metrics_at(&self, index: u16, ppem: u32) -> GlyphMetrics {
+        let px_size = ppem as f32;
+        let bitmap = self.rasterize_glyph_with(index, px_size, &RasterOptions::default()).unwrap_or_default();
+        GlyphMetrics {
+            advance: self.advance_width(index) as f32 * self.scale_for_px(px_size),
+            width: bitmap.width,
+            height: bitmap.height,
+            left: bitmap.left,
+            top: bitmap.top,
+        }
+    }
+
+    /// Load the font file in FreeType too and compare the metrics of every glyph at `ppem`.
+    /// Glyphs FreeType fails to load are left out. Variation coordinates aren't passed on, so
+    /// compare variable fonts at their default instance.
+    pub fn compare_metrics_with_freetype(&self, ppem: u32) -> Result<Vec<MetricsComparison>> {
+        let face = FreeTypeFace::new(self.file_data(), ppem)?;
+        if face.num_glyphs() != self.num_glyphs() {
+            println!("FreeType sees {} glyphs, we see {}", face.num_glyphs(), self.num_glyphs());
+        }
+        let comparisons = (0..self.num_glyphs().min(face.num_glyphs()))
+            .filter_map(|index| {
+                Some(MetricsComparison {
+                    index,
+                    ours: self.glyph_metrics_at(index, ppem),
+                    freetype: face.metrics(index)?,
+                })
+            })
+            .collect();
+        Ok(comparisons)
+    }
+}
@@ -0,0 +1,249 @@
+use anyhow::Result;
+
+use crate::fixed::{F2Dot14, Fixed};
+use crate::font::Font;
+use crate::name::SUBFAMILY_NAME;
+use crate::reader::FontReader;
+
+/// Axis flag: the axis is meant for programmatic use and shouldn't be shown in a UI.
+const HIDDEN_AXIS: u16 = 0x1;
+
+/// Normalized coordinates are stored as 2.14 fixed point in the font, rounding the same way
+/// keeps us at exactly the positions the font's data is defined at.
+fn round_f2dot14(value: f32) -> f32 {
+    F2Dot14::from_f32(value).to_f32()
+}
+
+#[derive(Debug, Clone)]
+pub struct FvarAxis {
+    pub tag: String,
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+    pub hidden: bool,
+    pub name_id: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct FvarInstance {
+    pub subfamily_name_id: u16,
+    pub postscript_name_id: Option<u16>,
+    pub coords: Vec<f32>,
+}
+
+/// The font variations table: the axes of a variable font and its named instances.
+#[derive(Debug, Clone, Default)]
+pub struct Fvar {
+    pub axes: Vec<FvarAxis>,
+    pub instances: Vec<FvarInstance>,
+}
+
+impl Fvar {
+    pub fn from_reader(reader: &mut FontReader, fvar_offset: u32) -> Result<Fvar> {
+        let start = fvar_offset as u64;
+        reader.seek(start + 4)?; // skip version
+        let axes_offset = reader.read_u16()? as u64;
+        reader.skip(2)?; // reserved
+        let axis_count = reader.read_u16()?;
+        let axis_size = reader.read_u16()? as u64;
+        let instance_count = reader.read_u16()?;
+        let instance_size = reader.read_u16()? as u64;
+
+        let mut axes = Vec::with_capacity(axis_count as usize);
+        for i in 0..axis_count as u64 {
+            reader.seek(start + axes_offset + i * axis_size)?;
+            let mut tag = [0u8; 4];
+            reader.read_exact(&mut tag)?;
+            let min = reader.read_fixed()?.to_f32();
+            let default = reader.read_fixed()?.to_f32();
+            let max = reader.read_fixed()?.to_f32();
+            let flags = reader.read_u16()?;
+            axes.push(FvarAxis {
+                tag: String::from_utf8_lossy(&tag).into_owned(),
+                min,
+                default,
+                max,
+                hidden: flags & HIDDEN_AXIS != 0,
+                name_id: reader.read_u16()?,
+            });
+        }
+
+        // instances follow the axes, the PostScript name id is only there when the record has room for it
+        let instances_offset = start + axes_offset + axis_count as u64 * axis_size;
+        let has_postscript_name = instance_size >= axis_count as u64 * 4 + 6;
+        let mut instances = Vec::with_capacity(instance_count as usize);
+        for i in 0..instance_count as u64 {
+            reader.seek(instances_offset + i * instance_size)?;
+            let subfamily_name_id = reader.read_u16()?;
+            reader.skip(2)?; // flags, reserved
+            let coords = (0..axis_count).map(|_| reader.read_fixed().map(Fixed::to_f32)).collect::<Result<Vec<f32>>>()?;
+            let postscript_name_id = if has_postscript_name { Some(reader.read_u16()?) } else { None };
+            instances.push(FvarInstance {
+                subfamily_name_id,
+                postscript_name_id,
+                coords,
+            });
+        }
+        Ok(Fvar { axes, instances })
+    }
+}
+
+/// A variation axis of a variable font, in user space coordinates (e.g. 100 to 900 for wght).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    pub tag: String,
+    pub name: String,
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+    pub hidden: bool,
+}
+
+/// A named instance ("Bold", "Light Condensed") with one coordinate per axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub postscript_name: Option<String>,
+    pub coords: Vec<f32>,
+}
+
+impl Font {
+    /// Move to a point in the design space given in user coordinates per axis tag, e.g.
+    /// `[("wght", 700.)]`. Values are clamped to the axis range, axes not listed go back to their
+    /// default and unknown tags are ignored. Coordinates are normalized to -1..1 around the
+    /// default, then remapped through avar. Glyphs are parsed again at the new coordinates.
+    pub fn set_variation(&mut self, settings: &[(&str, f32)]) {
+        let Some(fvar) = self.fvar() else {
+            return;
+        };
+        let coords: Vec<f32> = fvar
+            .axes
+            .iter()
+            .zip(self.user_coords(settings))
+            .enumerate()
+            .map(|(i, (axis, value))| {
+                let normalized = if value < axis.default && axis.default > axis.min {
+                    (value - axis.default) / (axis.default - axis.min)
+                } else if value > axis.default && axis.max > axis.default {
+                    (value - axis.default) / (axis.max - axis.default)
+                } else {
+                    0.
+                };
+                let normalized = round_f2dot14(normalized);
+                match self.avar() {
+                    Some(avar) => round_f2dot14(avar.map(i, normalized)),
+                    None => normalized,
+                }
+            })
+            .collect();
+        let coords = if coords.iter().all(|coord| *coord == 0.) { Vec::new() } else { coords };
+        if coords != self.normalized_coords {
+            self.normalized_coords = coords;
+            self.clear_glyph_cache();
+        }
+    }
+
+    /// The user space value of each fvar axis for `settings`, the way `set_variation` reads them.
+    pub(crate) fn user_coords(&self, settings: &[(&str, f32)]) -> Vec<f32> {
+        let Some(fvar) = self.fvar() else {
+            return Vec::new();
+        };
+        fvar.axes
+            .iter()
+            .map(|axis| {
+                settings
+                    .iter()
+                    .rev()
+                    .find(|(tag, _)| *tag == axis.tag)
+                    .map_or(axis.default, |(_, value)| value.clamp(axis.min, axis.max))
+            })
+            .collect()
+    }
+
+    /// The normalized coordinates set by `set_variation`, all zeros at the default instance.
+    pub fn normalized_coords(&self) -> Vec<f32> {
+        let axis_count = self.fvar().map_or(0, |fvar| fvar.axes.len());
+        let mut coords = self.normalized_coords.clone();
+        coords.resize(axis_count, 0.);
+        coords
+    }
+
+    pub fn is_variable(&self) -> bool {
+        self.fvar().is_some_and(|fvar| !fvar.axes.is_empty())
+    }
+
+    /// The variation axes from fvar, named through the name table, or else by the STAT design
+    /// axis with the same tag. Empty for fonts that aren't variable.
+    pub fn variation_axes(&self) -> Vec<Axis> {
+        let Some(fvar) = self.fvar() else {
+            return Vec::new();
+        };
+        fvar.axes
+            .iter()
+            .map(|axis| {
+                let stat_name = || {
+                    self.stat()?
+                        .design_axes
+                        .iter()
+                        .find(|design_axis| design_axis.tag == axis.tag)
+                        .and_then(|design_axis| self.name(design_axis.name_id))
+                };
+                Axis {
+                    tag: axis.tag.clone(),
+                    name: self.name(axis.name_id).or_else(stat_name).unwrap_or(&axis.tag).to_string(),
+                    min: axis.min,
+                    default: axis.default,
+                    max: axis.max,
+                    hidden: axis.hidden,
+                }
+            })
+            .collect()
+    }
+
+    /// The named instances from fvar. An instance whose name is missing from the name table gets
+    /// one composed from the STAT names of its axis values, in STAT axis order.
+    pub fn named_instances(&self) -> Vec<Instance> {
+        let Some(fvar) = self.fvar() else {
+            return Vec::new();
+        };
+        fvar.instances
+            .iter()
+            .map(|instance| {
+                let name = self
+                    .name(instance.subfamily_name_id)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| self.stat_style_name(&instance.coords));
+                Instance {
+                    name,
+                    postscript_name: instance
+                        .postscript_name_id
+                        .and_then(|name_id| self.name(name_id))
+                        .map(str::to_string),
+                    coords: instance.coords.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn stat_style_name(&self, coords: &[f32]) -> String {
+        let (Some(fvar), Some(stat)) = (self.fvar(), self.stat()) else {
+            return String::new();
+        };
+        let mut parts: Vec<(u16, &str)> = fvar
+            .axes
+            .iter()
+            .zip(coords)
+            .filter_map(|(axis, value)| {
+                let axis_value = stat.axis_value(&axis.tag, *value).filter(|axis_value| !axis_value.elidable)?;
+                let ordering = stat.design_axes[axis_value.axis_index as usize].ordering;
+                Some((ordering, self.name(axis_value.name_id)?))
+            })
+            .collect();
+        parts.sort_by_key(|(ordering, _)| *ordering);
+        if parts.is_empty() {
+            let fallback = stat.elided_fallback_name_id.unwrap_or(SUBFAMILY_NAME);
+            return self.name(fallback).unwrap_or("Regular").to_string();
+        }
+        parts.iter().map(|(_, name)| *name).collect::<Vec<_>>().join(" ")
+    }
+}
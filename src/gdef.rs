@@ -0,0 +1,72 @@
+use anyhow::Result;
+
+use crate::font::Font;
+use crate::otlayout::ClassDef;
+use crate::reader::FontReader;
+
+/// What GDEF says a glyph is, which decides how lookups treat it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlyphClass {
+    Base,
+    Ligature,
+    /// A combining mark, positioned on another glyph and skipped when kerning
+    Mark,
+    /// A part of a ligature
+    Component,
+}
+
+/// The glyph definition table: glyph classes and mark attachment classes.
+#[derive(Debug, Clone, Default)]
+pub struct Gdef {
+    glyph_classes: ClassDef,
+    mark_attach_classes: ClassDef,
+}
+
+impl Gdef {
+    pub fn from_reader(reader: &mut FontReader, gdef_offset: u32) -> Result<Gdef> {
+        let start = gdef_offset as u64;
+        reader.seek(start + 4)?; // skip version
+        let glyph_class_offset = reader.read_u16()? as u64;
+        reader.skip(4)?; // skip the attachment point and ligature caret lists
+        let mark_attach_offset = reader.read_u16()? as u64;
+
+        let mut read_class_def = |offset: u64| match offset {
+            0 => Ok(ClassDef::default()),
+            offset => ClassDef::from_reader(reader, start + offset),
+        };
+        Ok(Gdef {
+            glyph_classes: read_class_def(glyph_class_offset)?,
+            mark_attach_classes: read_class_def(mark_attach_offset)?,
+        })
+    }
+
+    pub fn glyph_class(&self, glyph: u16) -> Option<GlyphClass> {
+        match self.glyph_classes.class(glyph) {
+            1 => Some(GlyphClass::Base),
+            2 => Some(GlyphClass::Ligature),
+            3 => Some(GlyphClass::Mark),
+            4 => Some(GlyphClass::Component),
+            _ => None,
+        }
+    }
+
+    pub fn mark_attachment_class(&self, glyph: u16) -> u16 {
+        self.mark_attach_classes.class(glyph)
+    }
+}
+
+impl Font {
+    /// The GDEF class of a glyph, `None` for fonts without GDEF classes and unclassified glyphs.
+    pub fn glyph_class(&self, index: u16) -> Option<GlyphClass> {
+        self.gdef()?.glyph_class(index)
+    }
+
+    pub fn is_mark(&self, index: u16) -> bool {
+        self.glyph_class(index) == Some(GlyphClass::Mark)
+    }
+
+    /// The mark attachment class of a mark glyph from GDEF, 0 when it has none.
+    pub fn mark_attachment_class(&self, index: u16) -> u16 {
+        self.gdef().map_or(0, |gdef| gdef.mark_attachment_class(index))
+    }
+}
@@ -0,0 +1,42 @@
+use crate::glyph::Segment;
+
+/// (x min, y min, x max, y max)
+pub type Bounds = (f32, f32, f32, f32);
+
+/// The point of the quadratic curve `p0`, `c`, `p1` at `t`.
+pub fn quad_point(p0: (f32, f32), c: (f32, f32), p1: (f32, f32), t: f32) -> (f32, f32) {
+    let at = |a: f32, b: f32, c: f32| (1. - t) * (1. - t) * a + 2. * (1. - t) * t * b + t * t * c;
+    (at(p0.0, c.0, p1.0), at(p0.1, c.1, p1.1))
+}
+
+/// Where strictly between its ends a quadratic turns on one axis, given that axis' coordinates
+/// of the start, control and end: B'(t) = 0 at t = (a - b) / (a - 2b + c). `None` when it's
+/// monotonic there.
+pub fn quad_extremum(a: f32, b: f32, c: f32) -> Option<f32> {
+    let t = (a - b) / (a - 2. * b + c);
+    (t > 0. && t < 1.).then_some(t)
+}
+
+/// The bounds of both.
+pub fn union(a: Bounds, b: Bounds) -> Bounds {
+    (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))
+}
+
+/// The exact extent of a segment: its end points and, for a curve, the points where it turns
+/// on either axis, solved for rather than sampled.
+pub fn segment_bounds(segment: &Segment) -> Bounds {
+    let (p0, p1) = (segment.start(), segment.end());
+    let mut bounds = (p0.0.min(p1.0), p0.1.min(p1.1), p0.0.max(p1.0), p0.1.max(p1.1));
+    if let Segment::Quad(p0, c, p1) = *segment {
+        for t in [quad_extremum(p0.0, c.0, p1.0), quad_extremum(p0.1, c.1, p1.1)].into_iter().flatten() {
+            let (x, y) = quad_point(p0, c, p1, t);
+            bounds = union(bounds, (x, y, x, y));
+        }
+    }
+    bounds
+}
+
+/// The exact extent of contours given as segments, `None` without any segment.
+pub fn segments_bounds(contours: &[Vec<Segment>]) -> Option<Bounds> {
+    contours.iter().flatten().map(segment_bounds).reduce(union)
+}
@@ -0,0 +1,452 @@
+use anyhow::Result;
+
+use crate::geom;
+use crate::reader::FontReader;
+
+// composite glyph component flags
+pub(crate) const ARG_1_AND_2_ARE_WORDS: u16 = 1 << 0;
+pub(crate) const ARGS_ARE_XY_VALUES: u16 = 1 << 1;
+pub(crate) const WE_HAVE_A_SCALE: u16 = 1 << 3;
+pub(crate) const MORE_COMPONENTS: u16 = 1 << 5;
+pub(crate) const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 1 << 6;
+pub(crate) const WE_HAVE_A_TWO_BY_TWO: u16 = 1 << 7;
+pub(crate) const WE_HAVE_INSTRUCTIONS: u16 = 1 << 8;
+pub(crate) const SCALED_COMPONENT_OFFSET: u16 = 1 << 11;
+
+// simple glyph point flags
+const ON_CURVE_POINT: u8 = 1 << 0;
+const X_SHORT_VECTOR: u8 = 1 << 1;
+const Y_SHORT_VECTOR: u8 = 1 << 2;
+const REPEAT_FLAG: u8 = 1 << 3;
+const X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR: u8 = 1 << 4;
+const Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR: u8 = 1 << 5;
+
+pub(crate) fn bit_is_set(flag: u8, flag_bit_index: u8) -> bool {
+    // 00100000, 6 -> 00000001 & 00000001
+    ((flag >> flag_bit_index) & 1) == 1
+}
+
+fn get_coordinates(reader: &mut FontReader, flags: &[u8], is_x: bool) -> Result<Vec<i16>> {
+    let num_points = flags.len();
+    let mut coords: Vec<i16> = vec![0i16; num_points];
+
+    for i in 0..num_points {
+        coords[i] = if i == 0 { 0 } else { coords[i - 1] };
+        let flag: u8 = flags[i];
+        let (is_short, is_positive_short) = if is_x {
+            (bit_is_set(flag, 1), bit_is_set(flag, 4))
+        } else {
+            (bit_is_set(flag, 2), bit_is_set(flag, 5))
+        };
+
+        // coordinate offset is 1 byte
+        if is_short {
+            let offset: u8 = reader.read_u8()?;
+            let sign: i16 = if is_positive_short { 1 } else { -1 };
+            coords[i] = coords[i].wrapping_add(sign * (offset as i16));
+        } else if !is_positive_short {
+            // coordinate offset value is represented by 2 byes (signed), wrapping around like
+            // `GlyphData::to_bytes` writes the deltas between far apart coordinates
+            coords[i] = coords[i].wrapping_add(reader.read_i16()?);
+        }
+    }
+    Ok(coords)
+}
+
+/// Write a coordinate delta in as few bytes as it takes, nothing when it's 0 and one byte up to
+/// 255 either way, and return the flag bits saying so for its axis.
+fn encode_delta(delta: i16, short_vector: u8, same_or_positive: u8, out: &mut Vec<u8>) -> u8 {
+    match delta {
+        0 => same_or_positive,
+        1..=255 => {
+            out.push(delta as u8);
+            short_vector | same_or_positive
+        }
+        -255..=-1 => {
+            out.push(delta.unsigned_abs() as u8);
+            short_vector
+        }
+        _ => {
+            out.extend_from_slice(&delta.to_be_bytes());
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GlyphData {
+    pub x_coords: Vec<i16>,
+    pub y_coords: Vec<i16>,
+    pub on_curve: Vec<bool>,
+    pub contour_end_indices: Vec<u16>,
+    pub is_simple: bool,
+    /// Bounding box as declared in the glyph header, in font units
+    pub x_min: i16,
+    pub y_min: i16,
+    pub x_max: i16,
+    pub y_max: i16,
+    /// TrueType hinting bytecode for this glyph
+    pub instructions: Vec<u8>,
+    /// Set by the font when the glyph is loaded, see `phantom_points`
+    pub(crate) phantom: [(f32, f32); 4],
+}
+
+impl GlyphData {
+    /// A glyph without any outline (what `loca` gives us for spaces and the like).
+    pub fn empty() -> GlyphData {
+        GlyphData {
+            is_simple: true,
+            ..GlyphData::default()
+        }
+    }
+
+    pub fn from_reader(reader: &mut FontReader) -> Result<GlyphData> {
+        let mut contour_end_indices: Vec<u16> = Vec::new();
+
+        let num_contour_end_indices = reader.read_i16()?;
+        let x_min = reader.read_i16()?;
+        let y_min = reader.read_i16()?;
+        let x_max = reader.read_i16()?;
+        let y_max = reader.read_i16()?;
+        if num_contour_end_indices >= 0 {
+            reader.with_context("contour end indices", |reader| {
+                for _ in 0..num_contour_end_indices {
+                    contour_end_indices.push(reader.read_u16()?)
+                }
+                Ok(())
+            })?;
+
+            let num_points = match contour_end_indices.last() {
+                Some(last) => last + 1, // the last contour end index is the last point, points are indexed from 0
+                None => return Ok(GlyphData::empty()),
+            };
+
+            // instructions are kept around for the hinting interpreter (instruction : 1 byte)
+            let instructions = reader.with_context("instructions", |reader| {
+                let num_instructions = reader.read_u16()?;
+                Ok(reader.read_bytes(num_instructions as usize)?.to_vec())
+            })?;
+
+            // adding all of the flags
+            let mut flags: Vec<u8> = Vec::new();
+
+            reader.with_context("flags", |reader| {
+                let mut i = 0;
+                while i < num_points {
+                    let flag: u8 = reader.read_u8()?;
+                    flags.push(flag);
+
+                    // handle repeat
+                    if bit_is_set(flag, 3) {
+                        let num_repetitions: u8 = reader.read_u8()?;
+                        for _ in 0..num_repetitions {
+                            flags.push(flag);
+                        }
+                        i += num_repetitions as u16;
+                    }
+                    i += 1;
+                }
+                Ok(())
+            })?;
+            flags.truncate(num_points as usize);
+
+            let x_coords = reader.with_context("x coordinates", |reader| get_coordinates(reader, &flags, true))?;
+            let y_coords = reader.with_context("y coordinates", |reader| get_coordinates(reader, &flags, false))?;
+            let on_curve: Vec<bool> = flags.iter().map(|flag| bit_is_set(*flag, 0)).collect();
+
+            Ok(GlyphData {
+                x_coords,
+                y_coords,
+                on_curve,
+                contour_end_indices,
+                is_simple: true,
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                instructions,
+                phantom: [(0., 0.); 4],
+            })
+        } else {
+            Ok(GlyphData {
+                is_simple: false,
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                ..GlyphData::default()
+            })
+        }
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.x_coords.len()
+    }
+
+    /// Bytes of heap memory the glyph holds on to.
+    pub fn heap_size(&self) -> usize {
+        (self.x_coords.capacity() + self.y_coords.capacity() + self.contour_end_indices.capacity()) * 2
+            + self.on_curve.capacity()
+            + self.instructions.capacity()
+    }
+
+    /// (x_min, y_min, x_max, y_max) of the points, off-curve ones included like the glyf header.
+    pub fn bounds(&self) -> (i16, i16, i16, i16) {
+        let min_max = |coords: &[i16]| {
+            let min = coords.iter().copied().min().unwrap_or(0);
+            (min, coords.iter().copied().max().unwrap_or(0))
+        };
+        let ((x_min, x_max), (y_min, y_max)) = (min_max(&self.x_coords), min_max(&self.y_coords));
+        (x_min, y_min, x_max, y_max)
+    }
+
+    /// Encode a simple glyph as a glyf record, with its bounding box recomputed from the points.
+    /// Coordinates are written as the shortest deltas and runs of more than two points with the
+    /// same flags as one repeated flag, which is as small as the format gets.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let (x_min, y_min, x_max, y_max) = self.bounds();
+        let mut data = Vec::with_capacity(12 + self.instructions.len() + self.num_points() * 5);
+        for value in [self.contour_end_indices.len() as i16, x_min, y_min, x_max, y_max] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        for end in &self.contour_end_indices {
+            data.extend_from_slice(&end.to_be_bytes());
+        }
+        data.extend_from_slice(&(self.instructions.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.instructions);
+        let mut flags = Vec::with_capacity(self.num_points());
+        let (mut x_deltas, mut y_deltas) = (Vec::new(), Vec::new());
+        let mut previous = (0i16, 0i16);
+        for ((x, y), on_curve) in self.x_coords.iter().zip(&self.y_coords).zip(&self.on_curve) {
+            let mut flag = if *on_curve { ON_CURVE_POINT } else { 0 };
+            let (dx, dy) = (x.wrapping_sub(previous.0), y.wrapping_sub(previous.1));
+            flag |= encode_delta(dx, X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR, &mut x_deltas);
+            flag |= encode_delta(dy, Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR, &mut y_deltas);
+            flags.push(flag);
+            previous = (*x, *y);
+        }
+        let mut i = 0;
+        while i < flags.len() {
+            // a repeat count is one byte, for up to 256 points with the flag
+            let run = flags[i..].iter().take(256).take_while(|flag| **flag == flags[i]).count();
+            if run > 2 {
+                data.extend_from_slice(&[flags[i] | REPEAT_FLAG, (run - 1) as u8]);
+            } else {
+                data.extend_from_slice(&flags[i..i + run]);
+            }
+            i += run;
+        }
+        data.extend_from_slice(&x_deltas);
+        data.extend_from_slice(&y_deltas);
+        data
+    }
+
+    /// The four TrueType phantom points that follow the outline points, in font units for the
+    /// default instance: the horizontal origin and advance on the baseline, then the vertical
+    /// origin (top) and advance (bottom) on the y axis. `Font::phantom_points` applies variations.
+    pub fn phantom_points(&self) -> [(f32, f32); 4] {
+        self.phantom
+    }
+
+    /// A hash of the outline that two glyphs share when they have the same contours and the same
+    /// points up to a translation: points are taken relative to the first one. FNV-1a, so the
+    /// value is the same across runs and builds. Composite glyphs have no points here and all
+    /// hash alike.
+    pub fn outline_hash(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash = (hash ^ *byte as u64).wrapping_mul(FNV_PRIME);
+            }
+        };
+        write(&(self.contour_end_indices.len() as u32).to_le_bytes());
+        for end in &self.contour_end_indices {
+            write(&end.to_le_bytes());
+        }
+        let origin = (self.x_coords.first().copied().unwrap_or(0), self.y_coords.first().copied().unwrap_or(0));
+        for i in 0..self.num_points().min(self.on_curve.len()) {
+            write(&[self.on_curve[i] as u8]);
+            write(&self.x_coords[i].wrapping_sub(origin.0).to_le_bytes());
+            write(&self.y_coords[i].wrapping_sub(origin.1).to_le_bytes());
+        }
+        hash
+    }
+
+    /// Whether the two glyphs have the same outline up to a translation, what `outline_hash`
+    /// groups by.
+    pub fn same_outline(&self, other: &GlyphData) -> bool {
+        let relative = |glyph: &GlyphData| {
+            let origin = glyph.points().first().copied().unwrap_or_default();
+            glyph
+                .points()
+                .into_iter()
+                .map(move |(x, y)| (x - origin.0, y - origin.1))
+                .collect::<Vec<_>>()
+        };
+        self.contour_end_indices == other.contour_end_indices
+            && self.on_curve == other.on_curve
+            && relative(self) == relative(other)
+    }
+
+    pub fn num_contours(&self) -> usize {
+        self.contour_end_indices.len()
+    }
+
+    /// Iterate over the point index ranges of each contour.
+    pub fn contour_ranges(&self) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+        let mut start = 0usize;
+        self.contour_end_indices.iter().map(move |end| {
+            let range = start..(*end as usize + 1);
+            start = *end as usize + 1;
+            range
+        })
+    }
+
+    pub fn point(&self, i: usize) -> (f32, f32) {
+        (self.x_coords[i] as f32, self.y_coords[i] as f32)
+    }
+
+    pub fn points(&self) -> Vec<(f32, f32)> {
+        (0..self.num_points()).map(|i| self.point(i)).collect()
+    }
+
+    /// The line and quadratic segments of each contour in font units, with the implied on-curve
+    /// points between consecutive off-curve points filled in. Every contour ends where it started.
+    pub fn contour_segments(&self) -> Vec<Vec<Segment>> {
+        self.outline().segments()
+    }
+}
+
+/// A piece of a glyph contour, either a straight line or a quadratic bezier (start, control, end).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    Line((f32, f32), (f32, f32)),
+    Quad((f32, f32), (f32, f32), (f32, f32)),
+}
+
+impl Segment {
+    pub fn start(&self) -> (f32, f32) {
+        match self {
+            Segment::Line(p0, _) | Segment::Quad(p0, _, _) => *p0,
+        }
+    }
+
+    pub fn end(&self) -> (f32, f32) {
+        match self {
+            Segment::Line(_, p1) | Segment::Quad(_, _, p1) => *p1,
+        }
+    }
+
+    /// The exact extent (x min, y min, x max, y max) of the segment, see `geom::segment_bounds`.
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        geom::segment_bounds(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A simple glyph of one contour through `points`, all on the curve unless `off_curve`.
+    fn glyph(points: &[(i16, i16)], off_curve: &[usize]) -> GlyphData {
+        GlyphData {
+            x_coords: points.iter().map(|point| point.0).collect(),
+            y_coords: points.iter().map(|point| point.1).collect(),
+            on_curve: (0..points.len()).map(|i| !off_curve.contains(&i)).collect(),
+            contour_end_indices: vec![points.len() as u16 - 1],
+            ..GlyphData::empty()
+        }
+    }
+
+    fn read_back(record: &[u8]) -> GlyphData {
+        GlyphData::from_reader(&mut FontReader::new(record)).unwrap()
+    }
+
+    /// The flags of a record of one contour without instructions, as written.
+    fn written_flags(record: &[u8], x_and_y_bytes: usize) -> &[u8] {
+        &record[14..record.len() - x_and_y_bytes]
+    }
+
+    #[test]
+    fn deltas_take_the_fewest_bytes() {
+        let x = (X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR);
+        let cases: [(i16, &[u8], u8); 10] = [
+            (0, &[], x.1),
+            (1, &[1], x.0 | x.1),
+            (-1, &[1], x.0),
+            (255, &[255], x.0 | x.1),
+            (-255, &[255], x.0),
+            (256, &[1, 0], 0),
+            (-256, &[0xff, 0], 0),
+            (i16::MAX, &[0x7f, 0xff], 0),
+            (i16::MIN, &[0x80, 0], 0),
+            (-32767, &[0x80, 1], 0),
+        ];
+        for (delta, bytes, flag) in cases {
+            let mut out = Vec::new();
+            assert_eq!(encode_delta(delta, x.0, x.1, &mut out), flag, "{delta}");
+            assert_eq!(out, bytes, "{delta}");
+        }
+    }
+
+    #[test]
+    fn every_delta_reads_back() {
+        for delta in i16::MIN..=i16::MAX {
+            // the y delta the other way, and a second point for the delta from the first
+            let points = [(100, -100), (100i16.wrapping_add(delta), (-100i16).wrapping_sub(delta))];
+            let record = glyph(&points, &[]).to_bytes();
+            let read = read_back(&record);
+            assert_eq!(read.x_coords, [points[0].0, points[1].0], "delta {delta}");
+            assert_eq!(read.y_coords, [points[0].1, points[1].1], "delta {delta}");
+            let per_axis = match delta.unsigned_abs() {
+                0 => 0,
+                1..=255 => 1,
+                _ => 2,
+            };
+            assert_eq!(record.len(), 14 + 2 + 2 + 2 * per_axis, "delta {delta}");
+        }
+    }
+
+    #[test]
+    fn coordinates_further_apart_than_an_i16_wrap_around() {
+        let points = [(i16::MIN, i16::MAX), (i16::MAX, i16::MIN), (0, 0), (i16::MIN, i16::MIN)];
+        let read = read_back(&glyph(&points, &[1]).to_bytes());
+        assert_eq!(read.x_coords, points.map(|point| point.0));
+        assert_eq!(read.y_coords, points.map(|point| point.1));
+        assert_eq!(read.on_curve, [true, false, true, true]);
+        assert_eq!((read.x_min, read.y_min, read.x_max, read.y_max), (i16::MIN, i16::MIN, i16::MAX, i16::MAX));
+    }
+
+    #[test]
+    fn runs_of_more_than_two_flags_are_repeated() {
+        // points on the origin have the same flags, a repeat count byte covers up to 255 more
+        let cases = [(1, vec![0x31]), (2, vec![0x31, 0x31]), (3, vec![0x39, 2]), (256, vec![0x39, 255])];
+        let longer = [
+            (257, vec![0x39, 255, 0x31]),
+            (258, vec![0x39, 255, 0x31, 0x31]),
+            (259, vec![0x39, 255, 0x39, 2]),
+        ];
+        for (points, flags) in cases.into_iter().chain(longer) {
+            let record = glyph(&vec![(0, 0); points], &[]).to_bytes();
+            assert_eq!(written_flags(&record, 0), flags, "{points} points");
+            let read = read_back(&record);
+            assert_eq!((read.num_points(), read.x_coords.iter().all(|x| *x == 0)), (points, true));
+        }
+    }
+
+    #[test]
+    fn runs_of_one_flag_are_not_repeated() {
+        // every other point off the curve, so no two neighbours share their flags
+        let points: Vec<(i16, i16)> = (0..9).map(|i| (i * 10, i * 10)).collect();
+        let off_curve = [1, 3, 5, 7];
+        let record = glyph(&points, &off_curve).to_bytes();
+        let flags = written_flags(&record, 2 * 8);
+        assert_eq!(flags.len(), 9);
+        assert!(flags.iter().all(|flag| flag & REPEAT_FLAG == 0));
+        let read = read_back(&record);
+        assert_eq!(read.x_coords, points.iter().map(|point| point.0).collect::<Vec<_>>());
+        assert_eq!(read.on_curve, (0..9).map(|i| i % 2 == 0).collect::<Vec<_>>());
+    }
+}
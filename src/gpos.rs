@@ -0,0 +1,276 @@
+use anyhow::Result;
+
+use crate::font::Font;
+use crate::otlayout::{read_feature_lookups, ClassDef, Coverage};
+use crate::reader::FontReader;
+
+const PAIR_ADJUSTMENT: u16 = 2;
+const MARK_TO_BASE: u16 = 4;
+const EXTENSION: u16 = 9;
+
+/// ValueRecord field bits, in the order the fields are stored.
+const X_ADVANCE: u16 = 0x0004;
+
+/// Read a ValueRecord and keep its XAdvance, the only field horizontal kerning needs.
+fn read_x_advance(reader: &mut FontReader, value_format: u16) -> Result<i16> {
+    let mut x_advance = 0;
+    for bit in 0..8 {
+        let flag = 1 << bit;
+        if value_format & flag == 0 {
+            continue;
+        }
+        let value = reader.read_i16()?;
+        if flag == X_ADVANCE {
+            x_advance = value;
+        }
+    }
+    Ok(x_advance)
+}
+
+#[derive(Debug, Clone)]
+enum PairSubtable {
+    /// Format 1: the second glyphs and adjustments for each covered first glyph
+    Glyphs {
+        coverage: Coverage,
+        pair_sets: Vec<Vec<(u16, i16)>>,
+    },
+    /// Format 2: adjustments between classes of first and second glyphs
+    Classes {
+        coverage: Coverage,
+        first_classes: ClassDef,
+        second_classes: ClassDef,
+        second_class_count: u16,
+        x_advances: Vec<i16>,
+    },
+}
+
+impl PairSubtable {
+    fn from_reader(reader: &mut FontReader, offset: u64) -> Result<Option<PairSubtable>> {
+        reader.seek(offset)?;
+        let format = reader.read_u16()?;
+        let coverage_offset = offset + reader.read_u16()? as u64;
+        let value_format1 = reader.read_u16()?;
+        let value_format2 = reader.read_u16()?;
+        let value2_size = value_format2.count_ones() as u64 * 2;
+        let subtable = match format {
+            1 => {
+                let pair_set_count = reader.read_u16()?;
+                let pair_set_offsets = (0..pair_set_count)
+                    .map(|_| reader.read_u16())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut pair_sets = Vec::with_capacity(pair_set_offsets.len());
+                for pair_set_offset in pair_set_offsets {
+                    reader.seek(offset + pair_set_offset as u64)?;
+                    let count = reader.read_u16()?;
+                    let mut pairs = Vec::with_capacity(count as usize);
+                    for _ in 0..count {
+                        let second = reader.read_u16()?;
+                        pairs.push((second, read_x_advance(reader, value_format1)?));
+                        reader.skip(value2_size)?;
+                    }
+                    pair_sets.push(pairs);
+                }
+                PairSubtable::Glyphs {
+                    coverage: Coverage::from_reader(reader, coverage_offset)?,
+                    pair_sets,
+                }
+            }
+            2 => {
+                let first_classes_offset = offset + reader.read_u16()? as u64;
+                let second_classes_offset = offset + reader.read_u16()? as u64;
+                let first_class_count = reader.read_u16()?;
+                let second_class_count = reader.read_u16()?;
+                let mut x_advances = Vec::with_capacity(first_class_count as usize * second_class_count as usize);
+                for _ in 0..first_class_count as u32 * second_class_count as u32 {
+                    x_advances.push(read_x_advance(reader, value_format1)?);
+                    reader.skip(value2_size)?;
+                }
+                PairSubtable::Classes {
+                    coverage: Coverage::from_reader(reader, coverage_offset)?,
+                    first_classes: ClassDef::from_reader(reader, first_classes_offset)?,
+                    second_classes: ClassDef::from_reader(reader, second_classes_offset)?,
+                    second_class_count,
+                    x_advances,
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(subtable))
+    }
+
+    /// The adjustment for the pair, `None` when the subtable doesn't apply to it and the next
+    /// subtable of the lookup should be tried.
+    fn x_advance(&self, first: u16, second: u16) -> Option<i16> {
+        match self {
+            PairSubtable::Glyphs { coverage, pair_sets } => {
+                let pairs = pair_sets.get(coverage.index(first)? as usize)?;
+                let at = pairs.binary_search_by_key(&second, |(glyph, _)| *glyph).ok()?;
+                Some(pairs[at].1)
+            }
+            PairSubtable::Classes {
+                coverage,
+                first_classes,
+                second_classes,
+                second_class_count,
+                x_advances,
+            } => {
+                coverage.index(first)?;
+                let at = first_classes.class(first) as usize * *second_class_count as usize
+                    + second_classes.class(second) as usize;
+                Some(x_advances.get(at).copied().unwrap_or(0))
+            }
+        }
+    }
+}
+
+/// Read an anchor's coordinates. Formats 2 (contour point) and 3 (device tables) add data after
+/// them that we don't use.
+fn read_anchor(reader: &mut FontReader, offset: u64) -> Result<(i16, i16)> {
+    reader.seek(offset + 2)?; // skip format
+    Ok((reader.read_i16()?, reader.read_i16()?))
+}
+
+/// Format 1 mark-to-base attachment: each mark has a class and an anchor, each base an anchor per
+/// mark class.
+#[derive(Debug, Clone)]
+struct MarkBaseSubtable {
+    mark_coverage: Coverage,
+    base_coverage: Coverage,
+    /// (class, anchor) per covered mark
+    marks: Vec<(u16, (i16, i16))>,
+    /// One anchor per mark class for each covered base, `None` for null anchors
+    bases: Vec<Vec<Option<(i16, i16)>>>,
+}
+
+impl MarkBaseSubtable {
+    fn from_reader(reader: &mut FontReader, offset: u64) -> Result<Option<MarkBaseSubtable>> {
+        reader.seek(offset)?;
+        if reader.read_u16()? != 1 {
+            return Ok(None);
+        }
+        let mark_coverage_offset = offset + reader.read_u16()? as u64;
+        let base_coverage_offset = offset + reader.read_u16()? as u64;
+        let mark_class_count = reader.read_u16()?;
+        let mark_array = offset + reader.read_u16()? as u64;
+        let base_array = offset + reader.read_u16()? as u64;
+
+        reader.seek(mark_array)?;
+        let mark_count = reader.read_u16()?;
+        let mut mark_records = Vec::with_capacity(mark_count as usize);
+        for _ in 0..mark_count {
+            mark_records.push((reader.read_u16()?, reader.read_u16()?));
+        }
+        let mut marks = Vec::with_capacity(mark_records.len());
+        for (class, anchor_offset) in mark_records {
+            marks.push((class, read_anchor(reader, mark_array + anchor_offset as u64)?));
+        }
+
+        reader.seek(base_array)?;
+        let base_count = reader.read_u16()?;
+        let base_records = (0..base_count as u32 * mark_class_count as u32)
+            .map(|_| reader.read_u16())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut bases = Vec::with_capacity(base_count as usize);
+        for anchor_offsets in base_records.chunks(mark_class_count.max(1) as usize) {
+            let mut anchors = Vec::with_capacity(anchor_offsets.len());
+            for anchor_offset in anchor_offsets {
+                anchors.push(match anchor_offset {
+                    0 => None,
+                    offset => Some(read_anchor(reader, base_array + *offset as u64)?),
+                });
+            }
+            bases.push(anchors);
+        }
+
+        Ok(Some(MarkBaseSubtable {
+            mark_coverage: Coverage::from_reader(reader, mark_coverage_offset)?,
+            base_coverage: Coverage::from_reader(reader, base_coverage_offset)?,
+            marks,
+            bases,
+        }))
+    }
+
+    /// The base anchor and the mark anchor, when the subtable covers both glyphs.
+    fn anchors(&self, base: u16, mark: u16) -> Option<((i16, i16), (i16, i16))> {
+        let (class, mark_anchor) = *self.marks.get(self.mark_coverage.index(mark)? as usize)?;
+        let base_anchors = self.bases.get(self.base_coverage.index(base)? as usize)?;
+        let base_anchor = (*base_anchors.get(class as usize)?)?;
+        Some((base_anchor, mark_anchor))
+    }
+}
+
+/// The glyph positioning table, so far the pair adjustments of the `kern` feature and the
+/// mark-to-base attachments of the `mark` feature.
+#[derive(Debug, Clone, Default)]
+pub struct Gpos {
+    /// Pair adjustment lookups of the kern feature, each a list of subtables
+    kerning: Vec<Vec<PairSubtable>>,
+    /// Mark-to-base lookups of the mark feature
+    mark_to_base: Vec<Vec<MarkBaseSubtable>>,
+}
+
+impl Gpos {
+    pub fn from_reader(reader: &mut FontReader, gpos_offset: u32) -> Result<Gpos> {
+        let (kern_lookups, lookups) = read_feature_lookups(reader, gpos_offset as u64, b"kern", EXTENSION)?;
+        let mut kerning = Vec::new();
+        for index in kern_lookups {
+            let Some(lookup) = lookups.get(index as usize).filter(|lookup| lookup.lookup_type == PAIR_ADJUSTMENT) else {
+                continue;
+            };
+            let mut subtables = Vec::new();
+            for offset in &lookup.subtables {
+                if let Some(subtable) = PairSubtable::from_reader(reader, *offset)? {
+                    subtables.push(subtable);
+                }
+            }
+            kerning.push(subtables);
+        }
+
+        let (mark_lookups, lookups) = read_feature_lookups(reader, gpos_offset as u64, b"mark", EXTENSION)?;
+        let mut mark_to_base = Vec::new();
+        for index in mark_lookups {
+            let Some(lookup) = lookups.get(index as usize).filter(|lookup| lookup.lookup_type == MARK_TO_BASE) else {
+                continue;
+            };
+            let mut subtables = Vec::new();
+            for offset in &lookup.subtables {
+                if let Some(subtable) = MarkBaseSubtable::from_reader(reader, *offset)? {
+                    subtables.push(subtable);
+                }
+            }
+            mark_to_base.push(subtables);
+        }
+        Ok(Gpos { kerning, mark_to_base })
+    }
+
+    /// Where the origin of `mark` goes relative to the origin of `base`, in font units, from the
+    /// first mark-to-base lookup that attaches them.
+    pub fn mark_offset(&self, base: u16, mark: u16) -> Option<(i16, i16)> {
+        self.mark_to_base
+            .iter()
+            .flatten()
+            .find_map(|subtable| subtable.anchors(base, mark))
+            .map(|(base_anchor, mark_anchor)| (base_anchor.0 - mark_anchor.0, base_anchor.1 - mark_anchor.1))
+    }
+
+    /// The horizontal adjustment between two glyphs in font units, summed over the kern lookups.
+    pub fn kerning(&self, first: u16, second: u16) -> i16 {
+        self.kerning
+            .iter()
+            .filter_map(|subtables| subtables.iter().find_map(|subtable| subtable.x_advance(first, second)))
+            .fold(0i16, |total, adjustment| total.saturating_add(adjustment))
+    }
+}
+
+impl Font {
+    /// GPOS kerning between two glyphs in font units (usually negative), 0 without GPOS.
+    pub fn kerning(&self, first: u16, second: u16) -> i16 {
+        self.gpos().map_or(0, |gpos| gpos.kerning(first, second))
+    }
+
+    /// The GPOS mark-to-base offset from the origin of `base` to the origin of the combining
+    /// `mark` drawn on it, in font units (y up). `None` when the font doesn't attach them.
+    pub fn mark_offset(&self, base: u16, mark: u16) -> Option<(i16, i16)> {
+        self.gpos()?.mark_offset(base, mark)
+    }
+}
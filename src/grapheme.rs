@@ -0,0 +1,105 @@
+use crate::unicode_tables::GRAPHEME_BREAK_RANGES;
+
+/// The grapheme cluster break class of a character (UAX #29), with Extended_Pictographic as a
+/// class of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GraphemeBreak {
+    Other,
+    Cr,
+    Lf,
+    Control,
+    /// Combining marks, variation selectors, emoji modifiers
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    /// Hangul leading consonant, vowel and trailing consonant jamo
+    L,
+    V,
+    T,
+    /// Hangul syllables without and with a trailing consonant
+    Lv,
+    Lvt,
+    ExtendedPictographic,
+}
+
+pub(crate) fn grapheme_break(c: char) -> GraphemeBreak {
+    match c {
+        '\r' => GraphemeBreak::Cr,
+        '\n' => GraphemeBreak::Lf,
+        '\u{200D}' => GraphemeBreak::Zwj,
+        '\u{1F1E6}'..='\u{1F1FF}' => GraphemeBreak::RegionalIndicator,
+        '\u{AC00}'..='\u{D7A3}' if (c as u32 - 0xAC00).is_multiple_of(28) => GraphemeBreak::Lv,
+        '\u{AC00}'..='\u{D7A3}' => GraphemeBreak::Lvt,
+        _ => {
+            let code = c as u32;
+            let at = GRAPHEME_BREAK_RANGES.partition_point(|&(_, last, _)| last < code);
+            match GRAPHEME_BREAK_RANGES.get(at) {
+                Some(&(first, _, class)) if first <= code => class,
+                _ => GraphemeBreak::Other,
+            }
+        }
+    }
+}
+
+pub(crate) fn is_extended_pictographic(c: char) -> bool {
+    grapheme_break(c) == GraphemeBreak::ExtendedPictographic
+}
+
+/// The byte offsets of the extended grapheme cluster boundaries of `text` (UAX #29), from 0 to
+/// `text.len()`: the places a caret can go and the units editing removes. Keeps a base with its
+/// combining marks, a flag's two regional indicators and emoji ZWJ sequences together.
+pub fn grapheme_boundaries(text: &str) -> Vec<usize> {
+    use GraphemeBreak::*;
+    let mut boundaries = vec![0];
+    let mut previous: Option<GraphemeBreak> = None;
+    // regional indicators in a row just before the character
+    let mut regional_indicators = 0u32;
+    // the characters since the last boundary are an emoji followed by Extend characters
+    let mut in_emoji = false;
+    // ... and then a ZWJ, which joins with a following emoji
+    let mut emoji_zwj = false;
+    for (i, c) in text.char_indices() {
+        let class = grapheme_break(c);
+        if let Some(previous) = previous {
+            let joins = match (previous, class) {
+                (Cr, Lf) => true,
+                (Cr | Lf | Control, _) | (_, Cr | Lf | Control) => false,
+                (L, L | V | Lv | Lvt) | (Lv | V, V | T) | (Lvt | T, T) => true,
+                (_, Extend | Zwj | SpacingMark) | (Prepend, _) => true,
+                (Zwj, ExtendedPictographic) => emoji_zwj,
+                (RegionalIndicator, RegionalIndicator) => !regional_indicators.is_multiple_of(2),
+                _ => false,
+            };
+            if !joins {
+                boundaries.push(i);
+            }
+        }
+        emoji_zwj = class == Zwj && in_emoji;
+        in_emoji = class == ExtendedPictographic || (class == Extend && in_emoji);
+        regional_indicators = if class == RegionalIndicator { regional_indicators + 1 } else { 0 };
+        previous = Some(class);
+    }
+    if !text.is_empty() {
+        boundaries.push(text.len());
+    }
+    boundaries
+}
+
+/// The grapheme boundary after byte `offset`, `text.len()` at the end.
+pub fn next_grapheme_boundary(text: &str, offset: usize) -> usize {
+    grapheme_boundaries(text)
+        .into_iter()
+        .find(|boundary| *boundary > offset)
+        .unwrap_or(text.len())
+}
+
+/// The grapheme boundary before byte `offset`, 0 at the start.
+pub fn previous_grapheme_boundary(text: &str, offset: usize) -> usize {
+    grapheme_boundaries(text)
+        .into_iter()
+        .rev()
+        .find(|boundary| *boundary < offset)
+        .unwrap_or(0)
+}
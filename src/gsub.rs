@@ -0,0 +1,277 @@
+use anyhow::Result;
+
+use crate::otlayout::{read_feature_lookups, Coverage};
+use crate::reader::FontReader;
+use crate::trace::{ShapingStep, ShapingTrace};
+
+const SINGLE: u16 = 1;
+const LIGATURE: u16 = 4;
+const EXTENSION: u16 = 7;
+
+/// Lookup flag: skip mark glyphs when matching
+const IGNORE_MARKS: u16 = 0x0008;
+
+/// The features read from GSUB: the Arabic joining forms, the required ligatures, the
+/// composition and standard ligatures emoji sequences are formed with and the vertical forms.
+const FEATURES: [&[u8; 4]; 9] = [
+    b"isol", b"fina", b"medi", b"init", b"rlig", b"ccmp", b"liga", b"vert", b"vrt2",
+];
+
+#[derive(Debug, Clone)]
+enum SingleSubtable {
+    /// Format 1: the same delta added to every covered glyph
+    Delta { coverage: Coverage, delta: i16 },
+    /// Format 2: a substitute for each covered glyph
+    Glyphs { coverage: Coverage, substitutes: Vec<u16> },
+}
+
+impl SingleSubtable {
+    fn from_reader(reader: &mut FontReader, offset: u64) -> Result<Option<SingleSubtable>> {
+        reader.seek(offset)?;
+        let format = reader.read_u16()?;
+        let coverage_offset = offset + reader.read_u16()? as u64;
+        let subtable = match format {
+            1 => {
+                let delta = reader.read_i16()?;
+                SingleSubtable::Delta {
+                    coverage: Coverage::from_reader(reader, coverage_offset)?,
+                    delta,
+                }
+            }
+            2 => {
+                let count = reader.read_u16()?;
+                let substitutes = (0..count).map(|_| reader.read_u16()).collect::<Result<_, _>>()?;
+                SingleSubtable::Glyphs {
+                    coverage: Coverage::from_reader(reader, coverage_offset)?,
+                    substitutes,
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(subtable))
+    }
+
+    fn substitute(&self, glyph: u16) -> Option<u16> {
+        match self {
+            SingleSubtable::Delta { coverage, delta } => {
+                coverage.index(glyph)?;
+                Some(glyph.wrapping_add(*delta as u16))
+            }
+            SingleSubtable::Glyphs { coverage, substitutes } => substitutes.get(coverage.index(glyph)? as usize).copied(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Ligature {
+    /// The components after the first (covered) one
+    components: Vec<u16>,
+    glyph: u16,
+}
+
+/// Format 1 ligature substitution: the ligatures starting with each covered glyph, the longest
+/// ones usually first.
+#[derive(Debug, Clone)]
+struct LigatureSubtable {
+    coverage: Coverage,
+    ligature_sets: Vec<Vec<Ligature>>,
+}
+
+impl LigatureSubtable {
+    fn from_reader(reader: &mut FontReader, offset: u64) -> Result<Option<LigatureSubtable>> {
+        reader.seek(offset)?;
+        if reader.read_u16()? != 1 {
+            return Ok(None);
+        }
+        let coverage_offset = offset + reader.read_u16()? as u64;
+        let set_count = reader.read_u16()?;
+        let set_offsets = (0..set_count).map(|_| reader.read_u16()).collect::<Result<Vec<_>, _>>()?;
+        let mut ligature_sets = Vec::with_capacity(set_offsets.len());
+        for set_offset in set_offsets {
+            let set = offset + set_offset as u64;
+            reader.seek(set)?;
+            let count = reader.read_u16()?;
+            let ligature_offsets = (0..count).map(|_| reader.read_u16()).collect::<Result<Vec<_>, _>>()?;
+            let mut ligatures = Vec::with_capacity(ligature_offsets.len());
+            for ligature_offset in ligature_offsets {
+                reader.seek(set + ligature_offset as u64)?;
+                let glyph = reader.read_u16()?;
+                let component_count = reader.read_u16()?;
+                let components = (1..component_count)
+                    .map(|_| reader.read_u16())
+                    .collect::<Result<_, _>>()?;
+                ligatures.push(Ligature { components, glyph });
+            }
+            ligature_sets.push(ligatures);
+        }
+        Ok(Some(LigatureSubtable {
+            coverage: Coverage::from_reader(reader, coverage_offset)?,
+            ligature_sets,
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Lookup {
+    Single(Vec<SingleSubtable>),
+    Ligature {
+        ignore_marks: bool,
+        subtables: Vec<LigatureSubtable>,
+    },
+}
+
+/// A lookup with its index in the lookup list.
+type IndexedLookup = (u16, Lookup);
+
+/// The glyph substitution table, so far the single and ligature substitutions of the features
+/// Arabic joining, emoji sequences and vertical text use.
+#[derive(Debug, Clone, Default)]
+pub struct Gsub {
+    /// The lookups of each feature, in lookup list order
+    features: Vec<([u8; 4], Vec<IndexedLookup>)>,
+}
+
+impl Gsub {
+    pub fn from_reader(reader: &mut FontReader, gsub_offset: u32) -> Result<Gsub> {
+        let mut features = Vec::new();
+        for tag in FEATURES {
+            let (feature_lookups, lookups) = read_feature_lookups(reader, gsub_offset as u64, tag, EXTENSION)?;
+            let mut feature = Vec::new();
+            for index in feature_lookups {
+                let Some(header) = lookups.get(index as usize) else {
+                    continue;
+                };
+                let lookup = match header.lookup_type {
+                    SINGLE => {
+                        let mut subtables = Vec::new();
+                        for offset in &header.subtables {
+                            subtables.extend(SingleSubtable::from_reader(reader, *offset)?);
+                        }
+                        Lookup::Single(subtables)
+                    }
+                    LIGATURE => {
+                        let mut subtables = Vec::new();
+                        for offset in &header.subtables {
+                            subtables.extend(LigatureSubtable::from_reader(reader, *offset)?);
+                        }
+                        Lookup::Ligature {
+                            ignore_marks: header.flags & IGNORE_MARKS != 0,
+                            subtables,
+                        }
+                    }
+                    _ => continue,
+                };
+                feature.push((index, lookup));
+            }
+            features.push((*tag, feature));
+        }
+        Ok(Gsub { features })
+    }
+
+    fn lookups(&self, feature: &[u8; 4]) -> &[IndexedLookup] {
+        self.features
+            .iter()
+            .find(|(tag, _)| tag == feature)
+            .map_or(&[], |(_, lookups)| lookups.as_slice())
+    }
+
+    /// Whether the font has lookups (that are read) for `feature`.
+    pub fn has_feature(&self, feature: &[u8; 4]) -> bool {
+        !self.lookups(feature).is_empty()
+    }
+
+    /// The glyph after the single substitutions of `feature`, `glyph` itself when none apply.
+    pub fn substitute(&self, feature: &[u8; 4], glyph: u16) -> u16 {
+        self.substitute_traced(feature, (0, glyph), &mut ShapingTrace::disabled())
+    }
+
+    /// `substitute` for the glyph (cluster, glyph index), recording the lookups that apply.
+    pub(crate) fn substitute_traced(
+        &self,
+        feature: &[u8; 4],
+        (cluster, glyph): (usize, u16),
+        trace: &mut ShapingTrace,
+    ) -> u16 {
+        self.lookups(feature).iter().fold(glyph, |glyph, (index, lookup)| match lookup {
+            Lookup::Single(subtables) => match subtables.iter().find_map(|subtable| subtable.substitute(glyph)) {
+                Some(substitute) => {
+                    trace.record(|| ShapingStep::Substituted {
+                        feature: *feature,
+                        lookup: *index,
+                        cluster,
+                        before: vec![glyph],
+                        after: substitute,
+                    });
+                    substitute
+                }
+                None => glyph,
+            },
+            Lookup::Ligature { .. } => glyph,
+        })
+    }
+
+    /// Replace sequences of `glyphs` (cluster, glyph index) by the ligatures of `feature`. A
+    /// ligature keeps the cluster of its first component, marks skipped over while matching stay
+    /// after it.
+    pub fn ligate(&self, feature: &[u8; 4], glyphs: &mut Vec<(usize, u16)>, is_mark: impl Fn(u16) -> bool) {
+        self.ligate_traced(feature, glyphs, is_mark, &mut ShapingTrace::disabled())
+    }
+
+    /// `ligate`, recording the ligatures formed.
+    pub(crate) fn ligate_traced(
+        &self,
+        feature: &[u8; 4],
+        glyphs: &mut Vec<(usize, u16)>,
+        is_mark: impl Fn(u16) -> bool,
+        trace: &mut ShapingTrace,
+    ) {
+        for (index, lookup) in self.lookups(feature) {
+            let Lookup::Ligature { ignore_marks, subtables } = lookup else {
+                continue;
+            };
+            let skip = |glyph: u16| *ignore_marks && is_mark(glyph);
+            let mut i = 0;
+            while i < glyphs.len() {
+                let first = glyphs[i].1;
+                let ligatures = subtables.iter().find_map(|subtable| {
+                    let set = subtable.coverage.index(first)?;
+                    subtable.ligature_sets.get(set as usize)
+                });
+                if skip(first) || ligatures.is_none() {
+                    i += 1;
+                    continue;
+                }
+                // the first ligature whose components follow, with where they are
+                let matched = ligatures.into_iter().flatten().find_map(|ligature| {
+                    let mut at = i;
+                    let mut positions = Vec::with_capacity(ligature.components.len());
+                    for component in &ligature.components {
+                        at += 1;
+                        while at < glyphs.len() && skip(glyphs[at].1) {
+                            at += 1;
+                        }
+                        if glyphs.get(at)?.1 != *component {
+                            return None;
+                        }
+                        positions.push(at);
+                    }
+                    Some((ligature.glyph, positions))
+                });
+                if let Some((glyph, positions)) = matched {
+                    trace.record(|| ShapingStep::Substituted {
+                        feature: *feature,
+                        lookup: *index,
+                        cluster: glyphs[i].0,
+                        before: [first].into_iter().chain(positions.iter().map(|at| glyphs[*at].1)).collect(),
+                        after: glyph,
+                    });
+                    glyphs[i].1 = glyph;
+                    for at in positions.into_iter().rev() {
+                        glyphs.remove(at);
+                    }
+                }
+                i += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use anyhow::Result;
+use std::ops::Range;
+
+use crate::font::Font;
+use crate::glyph::GlyphData;
+use crate::item_variation::RegionAxis;
+use crate::reader::FontReader;
+
+/// Header flag: the glyph variation data offsets are 32 bit instead of 16 bit ones halved
+const LONG_OFFSETS: u16 = 0x1;
+
+/// tupleVariationCount flag: the serialized data starts with point numbers shared by the tuples
+const SHARED_POINT_NUMBERS: u16 = 0x8000;
+const TUPLE_COUNT_MASK: u16 = 0x0FFF;
+
+/// tupleIndex flags, the low bits index the shared tuples when there's no embedded peak
+const EMBEDDED_PEAK_TUPLE: u16 = 0x8000;
+const INTERMEDIATE_REGION: u16 = 0x4000;
+const PRIVATE_POINT_NUMBERS: u16 = 0x2000;
+const TUPLE_INDEX_MASK: u16 = 0x0FFF;
+
+const POINTS_ARE_WORDS: u8 = 0x80;
+const POINT_RUN_COUNT_MASK: u8 = 0x7F;
+const DELTAS_ARE_ZERO: u8 = 0x80;
+const DELTAS_ARE_WORDS: u8 = 0x40;
+const DELTA_RUN_COUNT_MASK: u8 = 0x3F;
+
+/// The deltas of a glyph for one region of the design space.
+#[derive(Debug, Clone)]
+struct TupleVariation {
+    region: Vec<RegionAxis>,
+    /// The points the deltas are for, `None` for every point (phantom points included)
+    points: Option<Vec<u16>>,
+    deltas: Vec<(i16, i16)>,
+}
+
+/// The glyph variations table: how the points of each glyph move across the design space.
+#[derive(Debug, Clone, Default)]
+pub struct Gvar {
+    glyphs: Vec<Vec<TupleVariation>>,
+}
+
+/// Packed point numbers, `None` when they stand for all the points of the glyph.
+fn read_points(reader: &mut FontReader) -> Result<Option<Vec<u16>>> {
+    let first = reader.read_u8()?;
+    let count = if first & POINTS_ARE_WORDS != 0 {
+        ((first & POINT_RUN_COUNT_MASK) as usize) << 8 | reader.read_u8()? as usize
+    } else {
+        first as usize
+    };
+    if count == 0 {
+        return Ok(None);
+    }
+    // points are stored as differences from the previous one
+    let mut points = Vec::with_capacity(count);
+    let mut point = 0u16;
+    while points.len() < count {
+        let control = reader.read_u8()?;
+        for _ in 0..(control & POINT_RUN_COUNT_MASK) as usize + 1 {
+            let step = if control & POINTS_ARE_WORDS != 0 {
+                reader.read_u16()?
+            } else {
+                reader.read_u8()? as u16
+            };
+            point = point.wrapping_add(step);
+            points.push(point);
+        }
+    }
+    points.truncate(count);
+    Ok(Some(points))
+}
+
+/// Packed deltas up to `end`: the x deltas and then the y deltas of a tuple.
+fn read_deltas(reader: &mut FontReader, end: u64) -> Result<Vec<i16>> {
+    let mut deltas = Vec::new();
+    while reader.position() < end {
+        let control = reader.read_u8()?;
+        let run = (control & DELTA_RUN_COUNT_MASK) as usize + 1;
+        for _ in 0..run {
+            deltas.push(if control & DELTAS_ARE_ZERO != 0 {
+                0
+            } else if control & DELTAS_ARE_WORDS != 0 {
+                reader.read_i16()?
+            } else {
+                reader.read_i8()? as i16
+            });
+        }
+    }
+    Ok(deltas)
+}
+
+fn read_tuple(reader: &mut FontReader, axis_count: u16) -> Result<Vec<f32>> {
+    (0..axis_count).map(|_| Ok(reader.read_f2dot14()?.to_f32())).collect()
+}
+
+impl Gvar {
+    pub fn from_reader(reader: &mut FontReader, gvar_offset: u32) -> Result<Gvar> {
+        let start = gvar_offset as u64;
+        reader.seek(start + 4)?; // skip version
+        let axis_count = reader.read_u16()?;
+        let shared_tuple_count = reader.read_u16()?;
+        let shared_tuples_offset = start + reader.read_u32()? as u64;
+        let glyph_count = reader.read_u16()?;
+        let flags = reader.read_u16()?;
+        let data_offset = start + reader.read_u32()? as u64;
+        let offsets = (0..=glyph_count)
+            .map(|_| match flags & LONG_OFFSETS {
+                0 => reader.read_u16().map(|offset| offset as u64 * 2),
+                _ => reader.read_u32().map(|offset| offset as u64),
+            })
+            .collect::<Result<Vec<u64>, _>>()?;
+
+        reader.seek(shared_tuples_offset)?;
+        let shared_tuples = (0..shared_tuple_count)
+            .map(|_| read_tuple(reader, axis_count))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut glyphs = Vec::with_capacity(glyph_count as usize);
+        for (glyph, range) in offsets.windows(2).enumerate() {
+            glyphs.push(match range[1] > range[0] {
+                true => reader.with_context(format!("variations of glyph {glyph}"), |reader| {
+                    Gvar::read_glyph(reader, data_offset + range[0], axis_count, &shared_tuples)
+                })?,
+                false => Vec::new(),
+            });
+        }
+        Ok(Gvar { glyphs })
+    }
+
+    fn read_glyph(
+        reader: &mut FontReader,
+        offset: u64,
+        axis_count: u16,
+        shared_tuples: &[Vec<f32>],
+    ) -> Result<Vec<TupleVariation>> {
+        reader.seek(offset)?;
+        let tuple_count = reader.read_u16()?;
+        let mut data = offset + reader.read_u16()? as u64;
+
+        // (data size, peak, intermediate start and end, private points) of each tuple
+        let mut headers = Vec::with_capacity((tuple_count & TUPLE_COUNT_MASK) as usize);
+        for _ in 0..tuple_count & TUPLE_COUNT_MASK {
+            let size = reader.read_u16()? as u64;
+            let index = reader.read_u16()?;
+            let peak = match index & EMBEDDED_PEAK_TUPLE {
+                0 => shared_tuples.get((index & TUPLE_INDEX_MASK) as usize).cloned(),
+                _ => Some(read_tuple(reader, axis_count)?),
+            };
+            let intermediate = match index & INTERMEDIATE_REGION {
+                0 => None,
+                _ => Some((read_tuple(reader, axis_count)?, read_tuple(reader, axis_count)?)),
+            };
+            headers.push((size, peak, intermediate, index & PRIVATE_POINT_NUMBERS != 0));
+        }
+
+        reader.seek(data)?;
+        let shared_points = match tuple_count & SHARED_POINT_NUMBERS {
+            0 => None,
+            _ => read_points(reader)?,
+        };
+        data = reader.position();
+
+        let mut tuples = Vec::with_capacity(headers.len());
+        for (size, peak, intermediate, private_points) in headers {
+            let end = data + size;
+            reader.seek(data)?;
+            data = end;
+            let Some(peak) = peak else {
+                continue;
+            };
+            let points = if private_points { read_points(reader)? } else { shared_points.clone() };
+            let deltas = read_deltas(reader, end)?;
+            let (x_deltas, y_deltas) = deltas.split_at(deltas.len() / 2);
+            let region = peak
+                .iter()
+                .enumerate()
+                .map(|(axis, peak)| match &intermediate {
+                    Some((start, end)) => RegionAxis {
+                        start: start[axis],
+                        peak: *peak,
+                        end: end[axis],
+                    },
+                    None => RegionAxis {
+                        start: peak.min(0.),
+                        peak: *peak,
+                        end: peak.max(0.),
+                    },
+                })
+                .collect();
+            tuples.push(TupleVariation {
+                region,
+                points,
+                deltas: x_deltas.iter().copied().zip(y_deltas.iter().copied()).collect(),
+            });
+        }
+        Ok(tuples)
+    }
+
+    /// The offset of every point of a simple glyph at `coords` (normalized, one per axis), in
+    /// font units. `points` are the outline points followed by the four phantom points. Points a
+    /// tuple has no delta for are interpolated from their neighbours on the contour, phantom
+    /// points stay put. `None` when the glyph doesn't vary.
+    pub fn deltas(
+        &self,
+        glyph: u16,
+        coords: &[f32],
+        points: &[(f32, f32)],
+        contour_end_indices: &[u16],
+    ) -> Option<Vec<(f32, f32)>> {
+        let tuples = self.glyphs.get(glyph as usize).filter(|tuples| !tuples.is_empty())?;
+        let mut total = vec![(0f32, 0f32); points.len()];
+        for tuple in tuples {
+            let scalar: f32 = tuple
+                .region
+                .iter()
+                .enumerate()
+                .map(|(axis, region)| region.scalar(coords.get(axis).copied().unwrap_or(0.)))
+                .product();
+            if scalar == 0. {
+                continue;
+            }
+            let deltas: Vec<Option<(f32, f32)>> = match &tuple.points {
+                None => tuple.deltas.iter().map(|(x, y)| Some((*x as f32, *y as f32))).collect(),
+                Some(numbers) => {
+                    let mut deltas = vec![None; points.len()];
+                    for (number, (x, y)) in numbers.iter().zip(&tuple.deltas) {
+                        if let Some(delta) = deltas.get_mut(*number as usize) {
+                            *delta = Some((*x as f32, *y as f32));
+                        }
+                    }
+                    let mut start = 0;
+                    for end in contour_end_indices {
+                        let contour = start..(*end as usize + 1).min(points.len());
+                        start = contour.end;
+                        interpolate_untouched(points, &mut deltas, contour);
+                    }
+                    deltas
+                }
+            };
+            for (total, delta) in total.iter_mut().zip(deltas) {
+                if let Some((x, y)) = delta {
+                    total.0 += x * scalar;
+                    total.1 += y * scalar;
+                }
+            }
+        }
+        Some(total)
+    }
+}
+
+/// Infer the deltas of the points of a contour that have none from the nearest points before
+/// and after them that do (the IUP step), when only some of them have one.
+fn interpolate_untouched(points: &[(f32, f32)], deltas: &mut [Option<(f32, f32)>], contour: Range<usize>) {
+    let touched: Vec<usize> = contour.clone().filter(|i| deltas[*i].is_some()).collect();
+    if touched.is_empty() || touched.len() == contour.len() {
+        return;
+    }
+    for (k, &before) in touched.iter().enumerate() {
+        let after = touched[(k + 1) % touched.len()];
+        let (delta_before, delta_after) = (deltas[before].unwrap_or_default(), deltas[after].unwrap_or_default());
+        // the untouched points between the two, wrapping around the end of the contour
+        let mut i = before;
+        loop {
+            i = if i + 1 == contour.end { contour.start } else { i + 1 };
+            if i == after {
+                break;
+            }
+            deltas[i] = Some((
+                interpolate(points[before].0, points[after].0, delta_before.0, delta_after.0, points[i].0),
+                interpolate(points[before].1, points[after].1, delta_before.1, delta_after.1, points[i].1),
+            ));
+        }
+    }
+}
+
+/// The delta along one axis for a point at `at`, between reference points at `a` and `b`:
+/// interpolated between them, the nearest one's delta outside.
+fn interpolate(a: f32, b: f32, delta_a: f32, delta_b: f32, at: f32) -> f32 {
+    if a == b {
+        return if delta_a == delta_b { delta_a } else { 0. };
+    }
+    let ((low, delta_low), (high, delta_high)) = match a < b {
+        true => ((a, delta_a), (b, delta_b)),
+        false => ((b, delta_b), (a, delta_a)),
+    };
+    if at <= low {
+        delta_low
+    } else if at >= high {
+        delta_high
+    } else {
+        delta_low + (at - low) / (high - low) * (delta_high - delta_low)
+    }
+}
+
+impl Font {
+    /// Move a simple glyph loaded for the default instance to the current variation coordinates:
+    /// its points rounded to whole units, its bounding box and its phantom points. Composite
+    /// glyphs are left as they are.
+    pub(crate) fn apply_glyph_variations(&self, index: u16, glyph: &mut GlyphData) {
+        let Some(gvar) = self.gvar() else {
+            return;
+        };
+        if self.normalized_coords.is_empty() || !glyph.is_simple {
+            return;
+        }
+        let mut points = glyph.points();
+        points.extend(glyph.phantom);
+        let Some(deltas) = gvar.deltas(index, &self.normalized_coords, &points, &glyph.contour_end_indices) else {
+            return;
+        };
+        let num_points = glyph.num_points();
+        for (i, ((x, y), (dx, dy))) in points.iter().zip(&deltas).take(num_points).enumerate() {
+            glyph.x_coords[i] = (x + dx).round() as i16;
+            glyph.y_coords[i] = (y + dy).round() as i16;
+        }
+        for (phantom, (dx, dy)) in glyph.phantom.iter_mut().zip(&deltas[num_points..]) {
+            phantom.0 += dx;
+            phantom.1 += dy;
+        }
+        if num_points > 0 {
+            (glyph.x_min, glyph.y_min, glyph.x_max, glyph.y_max) = glyph.bounds();
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::font::Font;
+use crate::glyph::Segment;
+use crate::outline::Outline;
+use crate::source::GlyphSource;
+use crate::transform::FontUnitsToPixels;
+
+// hex box geometry, in ems
+const SIDE_BEARING: f32 = 0.05;
+const STROKE: f32 = 0.05;
+const PADDING: f32 = 0.07;
+const DIGIT_WIDTH: f32 = 0.2;
+const DIGIT_HEIGHT: f32 = 0.26;
+const ROW_GAP: f32 = 0.06;
+// box drawn for a blank .notdef, in ems
+const NOTDEF_WIDTH: f32 = 0.6;
+const NOTDEF_STROKE: f32 = 0.06;
+
+/// 3x5 pixel patterns of the hex digits for fonts without digit glyphs, a bit per pixel row by
+/// row from the top, the high bit of each row on the left.
+const DIGIT_PATTERNS: [[u8; 5]; 16] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b011, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b010, 0b101, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b011, 0b100, 0b100, 0b100, 0b011],
+    [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b110, 0b100, 0b111],
+    [0b111, 0b100, 0b110, 0b100, 0b100],
+];
+
+/// Hex digits of the code point, 4 for the BMP and 6 above it, in two rows.
+fn hex_digits(c: char) -> Vec<u32> {
+    let count = if (c as u32) > 0xFFFF { 6 } else { 4 };
+    (0..count).rev().map(|i| (c as u32 >> (4 * i)) & 0xF).collect()
+}
+
+/// The advance of the hex box drawn for `c`, in font units.
+pub fn hex_box_advance(units_per_em: u16, c: char) -> f32 {
+    let columns = hex_digits(c).len() as f32 / 2.;
+    (2. * (SIDE_BEARING + STROKE + PADDING) + columns * DIGIT_WIDTH) * units_per_em as f32
+}
+
+/// An axis aligned rectangle from (x0, y0) to (x1, y1), clockwise (y up) unless `reversed`.
+fn rectangle(x0: f32, y0: f32, x1: f32, y1: f32, reversed: bool) -> Vec<Segment> {
+    let mut corners = [(x0, y0), (x0, y1), (x1, y1), (x1, y0)];
+    if reversed {
+        corners.reverse();
+    }
+    (0..4).map(|i| Segment::Line(corners[i], corners[(i + 1) % 4])).collect()
+}
+
+/// The outline of the box standing in for a character the font has no glyph for: a hollow box
+/// sized to the em with the code point in hex inside, in font units with the origin on the
+/// baseline. The digits are the font's own glyphs scaled down, or built-in 3x5 pixel patterns
+/// when it has no glyph for one of them.
+pub fn hex_box_outline<S: GlyphSource + ?Sized>(source: &S, c: char) -> Vec<Vec<Segment>> {
+    let em = source.units_per_em() as f32;
+    let digits = hex_digits(c);
+    let columns = digits.len() / 2;
+    let (left, right) = (SIDE_BEARING * em, hex_box_advance(source.units_per_em(), c) - SIDE_BEARING * em);
+    let top = (2. * (STROKE + PADDING + DIGIT_HEIGHT) + ROW_GAP) * em;
+    let inset = STROKE * em;
+    let mut contours = vec![
+        rectangle(left, 0., right, top, false),
+        rectangle(left + inset, inset, right - inset, top - inset, true),
+    ];
+
+    let glyph_digits: Option<Vec<(u16, Vec<Vec<Segment>>)>> = digits
+        .iter()
+        .map(|digit| {
+            let index = source.glyph_index(char::from_digit(*digit, 16)?.to_ascii_uppercase())?;
+            Some((index, source.outline(index)?))
+        })
+        .collect();
+    // the tallest digit fills the cell height, they all share its scale
+    let digit_height = glyph_digits.as_ref().and_then(|digits| {
+        let height = digits
+            .iter()
+            .flat_map(|(_, outline)| outline.iter().flatten())
+            .flat_map(|segment| [segment.start().1, segment.end().1])
+            .fold(0f32, f32::max);
+        (height > 0.).then_some(height)
+    });
+    let (cell_width, cell_height) = (DIGIT_WIDTH * em, DIGIT_HEIGHT * em);
+    for (i, digit) in digits.iter().enumerate() {
+        let (row, column) = (i / columns, i % columns);
+        let x = left + (STROKE + PADDING) * em + column as f32 * cell_width;
+        let y = top - (STROKE + PADDING) * em - (row + 1) as f32 * cell_height - row as f32 * ROW_GAP * em;
+        match (&glyph_digits, digit_height) {
+            (Some(glyphs), Some(height)) => {
+                let (index, outline) = &glyphs[i];
+                let scale = cell_height / height;
+                // centered in the cell
+                let dx = x + (cell_width - source.advance(*index) as f32 * scale) / 2.;
+                let place = FontUnitsToPixels {
+                    scale,
+                    flip_y: false,
+                    origin: (dx, y),
+                };
+                contours.extend(place.apply_segments(outline));
+            }
+            _ => {
+                let pixel = cell_height / 5.;
+                let dx = x + (cell_width - 3. * pixel) / 2.;
+                for (pattern_row, bits) in DIGIT_PATTERNS[*digit as usize].iter().enumerate() {
+                    for pattern_column in (0..3).filter(|column| bits & (0b100 >> column) != 0) {
+                        let x0 = dx + pattern_column as f32 * pixel;
+                        let y1 = y + cell_height - pattern_row as f32 * pixel;
+                        contours.push(rectangle(x0, y1 - pixel, x0 + pixel, y1, false));
+                    }
+                }
+            }
+        }
+    }
+    contours
+}
+
+/// The hollow box glyph 0 is drawn as when the font's own is blank, in font units: 0.6 em wide
+/// and from the descender up to the ascender, with strokes of 0.06 em, centered in `advance`
+/// (or starting at the origin when that's narrower).
+pub fn notdef_box_outline(units_per_em: u16, ascender: i16, descender: i16, advance: u16) -> Outline {
+    let em = units_per_em as f32;
+    let (width, stroke) = (NOTDEF_WIDTH * em, NOTDEF_STROKE * em);
+    let x0 = ((advance as f32 - width) / 2.).max(0.);
+    let (x1, y0, y1) = (x0 + width, descender as f32, ascender as f32);
+    // clockwise outside, counter-clockwise inside
+    let points = [
+        (x0, y0),
+        (x0, y1),
+        (x1, y1),
+        (x1, y0),
+        (x0 + stroke, y0 + stroke),
+        (x1 - stroke, y0 + stroke),
+        (x1 - stroke, y1 - stroke),
+        (x0 + stroke, y1 - stroke),
+    ];
+    Outline::from_points(&points, &[true; 8], &[3, 7])
+}
+
+impl Font {
+    /// The outline glyph `index` is drawn with: `glyph_outline`, but a blank glyph 0 (see
+    /// `notdef_is_blank`) is the box of `notdef_box_outline`, so that the characters it stands
+    /// for don't vanish. Rasterizing, caching and the draw functions all go by this.
+    pub fn drawn_outline(&self, index: u16) -> Option<Outline> {
+        if index == 0 && self.notdef_is_blank() {
+            let advance = self.advance_width(0);
+            return Some(notdef_box_outline(self.units_per_em(), self.ascender(), self.descender(), advance));
+        }
+        self.glyph_outline(index)
+    }
+}
@@ -0,0 +1,1353 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::fixed::F2Dot14;
+use crate::font::Font;
+use crate::reader::FontReader;
+
+/// Every program run (fpgm, prep or a glyph program) may execute at most this many instructions,
+/// so that looping or recursive programs can't hang the renderer.
+const INSTRUCTION_BUDGET: usize = 1_000_000;
+const MAX_CALL_DEPTH: usize = 64;
+/// Fallback for fonts with a bogus maxStackElements.
+const MIN_STACK_SIZE: usize = 256;
+/// Value GETINFO reports as the interpreter version (the same as FreeType's classic interpreter).
+const INTERPRETER_VERSION: i32 = 35;
+
+const TWILIGHT: usize = 0;
+const GLYPH: usize = 1;
+
+/// Everything is 26.6 fixed point in the interpreter, we keep the coordinates as f32 in those
+/// units so that the rounding arithmetic stays exact for the integer values programs push.
+fn floor_64(v: f32) -> f32 {
+    (v / 64.).floor() * 64.
+}
+
+fn ceil_64(v: f32) -> f32 {
+    (v / 64.).ceil() * 64.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RoundState {
+    Grid,
+    HalfGrid,
+    DoubleGrid,
+    DownToGrid,
+    UpToGrid,
+    Off,
+    Super { period: f32, phase: f32, threshold: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GraphicsState {
+    projection: (f32, f32),
+    dual_projection: (f32, f32),
+    freedom: (f32, f32),
+    rp: [usize; 3],
+    zp: [usize; 3],
+    loop_count: i32,
+    min_distance: f32,
+    round_state: RoundState,
+    cvt_cut_in: f32,
+    single_width_cut_in: f32,
+    single_width_value: f32,
+    delta_base: i32,
+    delta_shift: i32,
+    auto_flip: bool,
+    instruct_control: i32,
+}
+
+impl Default for GraphicsState {
+    fn default() -> GraphicsState {
+        GraphicsState {
+            projection: (1., 0.),
+            dual_projection: (1., 0.),
+            freedom: (1., 0.),
+            rp: [0; 3],
+            zp: [GLYPH; 3],
+            loop_count: 1,
+            min_distance: 64.,
+            round_state: RoundState::Grid,
+            cvt_cut_in: 68., // 17/16 of a pixel
+            single_width_cut_in: 0.,
+            single_width_value: 0.,
+            delta_base: 9,
+            delta_shift: 3,
+            auto_flip: true,
+            instruct_control: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Zone {
+    original: Vec<(f32, f32)>,
+    current: Vec<(f32, f32)>,
+    touched_x: Vec<bool>,
+    touched_y: Vec<bool>,
+    on_curve: Vec<bool>,
+    contour_end_indices: Vec<u16>,
+}
+
+impl Zone {
+    fn with_points(points: Vec<(f32, f32)>) -> Zone {
+        let len = points.len();
+        Zone {
+            original: points.clone(),
+            current: points,
+            touched_x: vec![false; len],
+            touched_y: vec![false; len],
+            on_curve: vec![true; len],
+            contour_end_indices: Vec::new(),
+        }
+    }
+}
+
+/// A glyph outline after running its hinting instructions.
+#[derive(Debug, Clone)]
+pub struct HintedGlyph {
+    /// Grid fitted points in pixels, y up, relative to the glyph origin
+    pub points: Vec<(f32, f32)>,
+    pub on_curve: Vec<bool>,
+    pub contour_end_indices: Vec<u16>,
+    /// Advance width in pixels, measured between the hinted phantom points
+    pub advance: f32,
+    /// Distance in pixels from the hinted origin (pp1) to the leftmost point
+    pub left_side_bearing: f32,
+}
+
+/// The font wide hinting state for one pixel size: functions from fpgm, the CVT and storage
+/// after running prep, and the graphics state prep leaves behind for the glyph programs.
+#[derive(Debug)]
+pub struct Hinter {
+    ppem: u16,
+    scale: f32, // 26.6 units per font unit
+    functions: HashMap<i32, Arc<[u8]>>,
+    cvt: Vec<f32>,
+    storage: Vec<i32>,
+    twilight: Zone,
+    graphics_state: GraphicsState,
+    max_stack: usize,
+}
+
+struct Interpreter<'a> {
+    functions: &'a mut HashMap<i32, Arc<[u8]>>,
+    cvt: &'a mut Vec<f32>,
+    storage: &'a mut Vec<i32>,
+    zones: [Zone; 2],
+    gs: GraphicsState,
+    stack: Vec<i32>,
+    max_stack: usize,
+    ppem: u16,
+    scale: f32,
+    budget: usize,
+    depth: usize,
+    in_prep: bool,
+}
+
+/// Length in bytes of the instruction at `ip`, including inline push data.
+fn instruction_length(code: &[u8], ip: usize) -> usize {
+    match code[ip] {
+        0x40 => 2 + code.get(ip + 1).copied().unwrap_or(0) as usize,
+        0x41 => 2 + 2 * code.get(ip + 1).copied().unwrap_or(0) as usize,
+        op @ 0xB0..=0xB7 => 1 + (op - 0xB0 + 1) as usize,
+        op @ 0xB8..=0xBF => 1 + 2 * (op - 0xB8 + 1) as usize,
+        _ => 1,
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    fn pop(&mut self) -> Result<i32> {
+        self.stack.pop().ok_or_else(|| anyhow!("stack underflow"))
+    }
+
+    fn push(&mut self, value: i32) -> Result<()> {
+        if self.stack.len() >= self.max_stack {
+            return Err(anyhow!("stack overflow"));
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop_f(&mut self) -> Result<f32> {
+        Ok(self.pop()? as f32)
+    }
+
+    fn zone_index(&self, zp: usize) -> usize {
+        self.gs.zp[zp]
+    }
+
+    fn check_point(&self, zone: usize, point: usize) -> Result<()> {
+        if point < self.zones[zone].current.len() {
+            Ok(())
+        } else {
+            Err(anyhow!("point {point} out of range in zone {zone}"))
+        }
+    }
+
+    fn pop_point(&mut self, zp: usize) -> Result<usize> {
+        let point = self.pop()? as usize;
+        self.check_point(self.zone_index(zp), point)?;
+        Ok(point)
+    }
+
+    fn project(&self, v: (f32, f32)) -> f32 {
+        v.0 * self.gs.projection.0 + v.1 * self.gs.projection.1
+    }
+
+    fn dual_project(&self, v: (f32, f32)) -> f32 {
+        v.0 * self.gs.dual_projection.0 + v.1 * self.gs.dual_projection.1
+    }
+
+    fn current(&self, zone: usize, point: usize) -> (f32, f32) {
+        self.zones[zone].current[point]
+    }
+
+    fn original(&self, zone: usize, point: usize) -> (f32, f32) {
+        self.zones[zone].original[point]
+    }
+
+    fn round(&self, d: f32) -> f32 {
+        let with_sign = |magnitude: f32| if d >= 0. { magnitude.max(0.) } else { -magnitude.max(0.) };
+        match self.gs.round_state {
+            RoundState::Grid => with_sign(floor_64(d.abs() + 32.)),
+            RoundState::HalfGrid => with_sign(floor_64(d.abs()) + 32.),
+            RoundState::DoubleGrid => with_sign(((d.abs() + 16.) / 32.).floor() * 32.),
+            RoundState::DownToGrid => with_sign(floor_64(d.abs())),
+            RoundState::UpToGrid => with_sign(ceil_64(d.abs())),
+            RoundState::Off => d,
+            RoundState::Super { period, phase, threshold } => {
+                let v = ((d.abs() - phase + threshold) / period).floor() * period + phase;
+                if v < 0. {
+                    with_sign(phase)
+                } else {
+                    with_sign(v)
+                }
+            }
+        }
+    }
+
+    /// Move a point so that its projection changes by `distance`, along the freedom vector.
+    fn move_point(&mut self, zone: usize, point: usize, distance: f32) {
+        let (fx, fy) = self.gs.freedom;
+        let mut dot = fx * self.gs.projection.0 + fy * self.gs.projection.1;
+        if dot.abs() < 1. / 16384. {
+            dot = 1.;
+        }
+        let z = &mut self.zones[zone];
+        z.current[point].0 += fx * distance / dot;
+        z.current[point].1 += fy * distance / dot;
+        if fx != 0. {
+            z.touched_x[point] = true;
+        }
+        if fy != 0. {
+            z.touched_y[point] = true;
+        }
+    }
+
+    /// Shift a point by an already computed displacement without measuring anything.
+    fn shift_point(&mut self, zone: usize, point: usize, dx: f32, dy: f32) {
+        let z = &mut self.zones[zone];
+        z.current[point].0 += dx;
+        z.current[point].1 += dy;
+        if self.gs.freedom.0 != 0. {
+            z.touched_x[point] = true;
+        }
+        if self.gs.freedom.1 != 0. {
+            z.touched_y[point] = true;
+        }
+    }
+
+    fn displacement(&self, distance: f32) -> (f32, f32) {
+        let (fx, fy) = self.gs.freedom;
+        let mut dot = fx * self.gs.projection.0 + fy * self.gs.projection.1;
+        if dot.abs() < 1. / 16384. {
+            dot = 1.;
+        }
+        (fx * distance / dot, fy * distance / dot)
+    }
+
+    fn cvt_value(&self, index: i32) -> Result<f32> {
+        self.cvt
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| anyhow!("cvt index {index} out of range"))
+    }
+
+    fn write_cvt(&mut self, index: i32, value: f32) -> Result<()> {
+        let slot = self
+            .cvt
+            .get_mut(index as usize)
+            .ok_or_else(|| anyhow!("cvt index {index} out of range"))?;
+        *slot = value;
+        Ok(())
+    }
+
+    fn vector_from_line(&self, p1: (f32, f32), p2: (f32, f32), perpendicular: bool) -> (f32, f32) {
+        let (mut dx, mut dy) = (p2.0 - p1.0, p2.1 - p1.1);
+        if perpendicular {
+            (dx, dy) = (-dy, dx);
+        }
+        let len = dx.hypot(dy);
+        if len == 0. {
+            (1., 0.)
+        } else {
+            (dx / len, dy / len)
+        }
+    }
+
+    /// Position just past the matching ELSE (when `stop_at_else`) or EIF.
+    fn skip_conditional(code: &[u8], mut ip: usize, stop_at_else: bool) -> Result<usize> {
+        let mut nesting = 0;
+        while ip < code.len() {
+            match code[ip] {
+                0x58 => nesting += 1,
+                0x1B if nesting == 0 && stop_at_else => return Ok(ip + 1),
+                0x59 if nesting == 0 => return Ok(ip + 1),
+                0x59 => nesting -= 1,
+                _ => {}
+            }
+            ip += instruction_length(code, ip);
+        }
+        Err(anyhow!("unterminated IF"))
+    }
+
+    fn delta_point(&mut self, range_offset: i32) -> Result<()> {
+        let count = self.pop()?;
+        for _ in 0..count {
+            let point = self.pop()? as usize;
+            let arg = self.pop()?;
+            let ppem = self.gs.delta_base.saturating_add(range_offset + ((arg >> 4) & 0xF));
+            if ppem != self.ppem as i32 {
+                continue;
+            }
+            let zone = self.zone_index(0);
+            self.check_point(zone, point)?;
+            let mut step = (arg & 0xF) - 8;
+            if step >= 0 {
+                step += 1;
+            }
+            let distance = step as f32 * 64. / (1 << self.gs.delta_shift) as f32;
+            self.move_point(zone, point, distance);
+        }
+        Ok(())
+    }
+
+    fn delta_cvt(&mut self, range_offset: i32) -> Result<()> {
+        let count = self.pop()?;
+        for _ in 0..count {
+            let index = self.pop()?;
+            let arg = self.pop()?;
+            let ppem = self.gs.delta_base.saturating_add(range_offset + ((arg >> 4) & 0xF));
+            if ppem != self.ppem as i32 {
+                continue;
+            }
+            let mut step = (arg & 0xF) - 8;
+            if step >= 0 {
+                step += 1;
+            }
+            let value = self.cvt_value(index)? + step as f32 * 64. / (1 << self.gs.delta_shift) as f32;
+            self.write_cvt(index, value)?;
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, code: &[u8]) -> Result<()> {
+        let mut ip = 0usize;
+        while ip < code.len() {
+            if self.budget == 0 {
+                return Err(anyhow!("instruction budget exhausted"));
+            }
+            self.budget -= 1;
+            let op = code[ip];
+            let mut next = ip + instruction_length(code, ip);
+            if next > code.len() {
+                return Err(anyhow!("truncated push at {ip}"));
+            }
+
+            match op {
+                // vector setup
+                0x00..=0x05 => {
+                    let v = if op & 1 == 1 { (1., 0.) } else { (0., 1.) };
+                    match op >> 1 {
+                        0 => {
+                            self.gs.projection = v;
+                            self.gs.dual_projection = v;
+                            self.gs.freedom = v;
+                        }
+                        1 => {
+                            self.gs.projection = v;
+                            self.gs.dual_projection = v;
+                        }
+                        _ => self.gs.freedom = v,
+                    }
+                }
+                0x06..=0x09 => {
+                    let p2 = self.pop_point(1)?;
+                    let p1 = self.pop_point(2)?;
+                    let (z1, z2) = (self.zone_index(2), self.zone_index(1));
+                    let v = self.vector_from_line(self.current(z1, p1), self.current(z2, p2), op & 1 == 1);
+                    if op <= 0x07 {
+                        self.gs.projection = v;
+                        self.gs.dual_projection = v;
+                    } else {
+                        self.gs.freedom = v;
+                    }
+                }
+                0x0A | 0x0B => {
+                    let y = self.pop_f()? / 16384.;
+                    let x = self.pop_f()? / 16384.;
+                    let len = x.hypot(y);
+                    let v = if len == 0. { (1., 0.) } else { (x / len, y / len) };
+                    if op == 0x0A {
+                        self.gs.projection = v;
+                        self.gs.dual_projection = v;
+                    } else {
+                        self.gs.freedom = v;
+                    }
+                }
+                0x0C | 0x0D => {
+                    let v = if op == 0x0C { self.gs.projection } else { self.gs.freedom };
+                    self.push(F2Dot14::from_f32(v.0).0 as i32)?;
+                    self.push(F2Dot14::from_f32(v.1).0 as i32)?;
+                }
+                0x0E => self.gs.freedom = self.gs.projection,
+                0x0F => {
+                    // ISECT
+                    let b1 = self.pop_point(0)?;
+                    let b0 = self.pop_point(0)?;
+                    let a1 = self.pop_point(1)?;
+                    let a0 = self.pop_point(1)?;
+                    let point = self.pop_point(2)?;
+                    let (za, zb, zp) = (self.zone_index(1), self.zone_index(0), self.zone_index(2));
+                    let (pa0, pa1) = (self.current(za, a0), self.current(za, a1));
+                    let (pb0, pb1) = (self.current(zb, b0), self.current(zb, b1));
+                    let (ra, rb) = ((pa1.0 - pa0.0, pa1.1 - pa0.1), (pb1.0 - pb0.0, pb1.1 - pb0.1));
+                    let denominator = ra.0 * rb.1 - ra.1 * rb.0;
+                    let result = if denominator.abs() < 1e-6 {
+                        // parallel lines: use the middle of the four points
+                        ((pa0.0 + pa1.0 + pb0.0 + pb1.0) / 4., (pa0.1 + pa1.1 + pb0.1 + pb1.1) / 4.)
+                    } else {
+                        let t = ((pb0.0 - pa0.0) * rb.1 - (pb0.1 - pa0.1) * rb.0) / denominator;
+                        (pa0.0 + t * ra.0, pa0.1 + t * ra.1)
+                    };
+                    let z = &mut self.zones[zp];
+                    z.current[point] = result;
+                    z.touched_x[point] = true;
+                    z.touched_y[point] = true;
+                }
+                // graphics state
+                0x10..=0x12 => self.gs.rp[(op - 0x10) as usize] = self.pop()? as usize,
+                0x13..=0x16 => {
+                    let zone = self.pop()?;
+                    if !(0..=1).contains(&zone) {
+                        return Err(anyhow!("invalid zone {zone}"));
+                    }
+                    match op {
+                        0x13 => self.gs.zp[0] = zone as usize,
+                        0x14 => self.gs.zp[1] = zone as usize,
+                        0x15 => self.gs.zp[2] = zone as usize,
+                        _ => self.gs.zp = [zone as usize; 3],
+                    }
+                }
+                0x17 => self.gs.loop_count = self.pop()?,
+                0x18 => self.gs.round_state = RoundState::Grid,
+                0x19 => self.gs.round_state = RoundState::HalfGrid,
+                0x1A => self.gs.min_distance = self.pop_f()?,
+                0x1B => next = Self::skip_conditional(code, next, false)?, // ELSE while executing the IF branch
+                0x1C => {
+                    let offset = self.pop()?;
+                    next = (ip as i64 + offset as i64).try_into().map_err(|_| anyhow!("jump out of range"))?;
+                }
+                0x1D => self.gs.cvt_cut_in = self.pop_f()?,
+                0x1E => self.gs.single_width_cut_in = self.pop_f()?,
+                0x1F => self.gs.single_width_value = self.pop_f()? * self.scale, // in font units
+                // stack
+                0x20 => {
+                    let v = *self.stack.last().ok_or_else(|| anyhow!("stack underflow"))?;
+                    self.push(v)?;
+                }
+                0x21 => {
+                    self.pop()?;
+                }
+                0x22 => self.stack.clear(),
+                0x23 => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(b)?;
+                    self.push(a)?;
+                }
+                0x24 => self.push(self.stack.len() as i32)?,
+                0x25 | 0x26 => {
+                    let k = self.pop()? as usize;
+                    if k == 0 || k > self.stack.len() {
+                        return Err(anyhow!("stack index {k} out of range"));
+                    }
+                    let index = self.stack.len() - k;
+                    if op == 0x25 {
+                        self.push(self.stack[index])?;
+                    } else {
+                        let v = self.stack.remove(index);
+                        self.push(v)?;
+                    }
+                }
+                0x27 => {
+                    let p2 = self.pop_point(0)?;
+                    let p1 = self.pop_point(1)?;
+                    let (z1, z2) = (self.zone_index(1), self.zone_index(0));
+                    let c1 = self.current(z1, p1);
+                    let c2 = self.current(z2, p2);
+                    let d = self.project((c2.0 - c1.0, c2.1 - c1.1)) / 2.;
+                    self.move_point(z1, p1, d);
+                    self.move_point(z2, p2, -d);
+                }
+                0x29 => {
+                    let point = self.pop_point(0)?;
+                    let zone = self.zone_index(0);
+                    if self.gs.freedom.0 != 0. {
+                        self.zones[zone].touched_x[point] = false;
+                    }
+                    if self.gs.freedom.1 != 0. {
+                        self.zones[zone].touched_y[point] = false;
+                    }
+                }
+                // functions
+                0x2A | 0x2B => {
+                    let function = self.pop()?;
+                    let count = if op == 0x2A { self.pop()? } else { 1 };
+                    let body = self
+                        .functions
+                        .get(&function)
+                        .cloned()
+                        .ok_or_else(|| anyhow!("call to undefined function {function}"))?;
+                    if self.depth >= MAX_CALL_DEPTH {
+                        return Err(anyhow!("call depth exceeded"));
+                    }
+                    self.depth += 1;
+                    for _ in 0..count {
+                        // each call is charged, or a loop over an empty function would be free
+                        if self.budget == 0 {
+                            return Err(anyhow!("instruction budget exhausted"));
+                        }
+                        self.budget -= 1;
+                        self.run(&body)?;
+                    }
+                    self.depth -= 1;
+                }
+                0x2C => {
+                    let function = self.pop()?;
+                    let mut end = next;
+                    while end < code.len() && code[end] != 0x2D {
+                        end += instruction_length(code, end);
+                    }
+                    if end >= code.len() {
+                        return Err(anyhow!("unterminated FDEF"));
+                    }
+                    self.functions.insert(function, Arc::from(&code[next..end]));
+                    next = end + 1;
+                }
+                0x2D => return Ok(()), // ENDF returns from the running function
+                // point moving
+                0x2E | 0x2F => {
+                    let point = self.pop_point(0)?;
+                    let zone = self.zone_index(0);
+                    if op == 0x2F {
+                        let d = self.project(self.current(zone, point));
+                        self.move_point(zone, point, self.round(d) - d);
+                    } else {
+                        self.move_point(zone, point, 0.);
+                    }
+                    self.gs.rp[0] = point;
+                    self.gs.rp[1] = point;
+                }
+                0x30 | 0x31 => {
+                    if self.zone_index(2) == GLYPH {
+                        let zone = &mut self.zones[GLYPH];
+                        let x_axis = op == 0x31;
+                        let touched = if x_axis { &zone.touched_x } else { &zone.touched_y };
+                        interpolate_untouched(&zone.original, &mut zone.current, touched, &zone.contour_end_indices, x_axis);
+                    }
+                }
+                0x32..=0x37 => {
+                    // SHP, SHC, SHZ use rp2 in zp1 or rp1 in zp0
+                    let (rp, ref_zone) = if op & 1 == 0 {
+                        (self.gs.rp[2], self.zone_index(1))
+                    } else {
+                        (self.gs.rp[1], self.zone_index(0))
+                    };
+                    self.check_point(ref_zone, rp)?;
+                    let (c, o) = (self.current(ref_zone, rp), self.original(ref_zone, rp));
+                    let d = self.project((c.0 - o.0, c.1 - o.1));
+                    let (dx, dy) = self.displacement(d);
+                    match op {
+                        0x32 | 0x33 => {
+                            let zone = self.zone_index(2);
+                            for _ in 0..self.gs.loop_count.max(1) {
+                                let point = self.pop_point(2)?;
+                                self.shift_point(zone, point, dx, dy);
+                            }
+                            self.gs.loop_count = 1;
+                        }
+                        0x34 | 0x35 => {
+                            let contour = self.pop()? as usize;
+                            let zone = self.zone_index(2);
+                            let ends = &self.zones[zone].contour_end_indices;
+                            let end = *ends.get(contour).ok_or_else(|| anyhow!("contour {contour} out of range"))? as usize;
+                            let start = if contour == 0 { 0 } else { ends[contour - 1] as usize + 1 };
+                            for point in start..=end.min(self.zones[zone].current.len().saturating_sub(1)) {
+                                if !(zone == ref_zone && point == rp) {
+                                    self.shift_point(zone, point, dx, dy);
+                                }
+                            }
+                        }
+                        _ => {
+                            let zone = self.pop()? as usize;
+                            if zone > 1 {
+                                return Err(anyhow!("invalid zone {zone}"));
+                            }
+                            for point in 0..self.zones[zone].current.len() {
+                                if !(zone == ref_zone && point == rp) {
+                                    let z = &mut self.zones[zone];
+                                    z.current[point].0 += dx;
+                                    z.current[point].1 += dy;
+                                }
+                            }
+                        }
+                    }
+                }
+                0x38 => {
+                    let d = self.pop_f()?;
+                    let zone = self.zone_index(2);
+                    let (dx, dy) = (self.gs.freedom.0 * d, self.gs.freedom.1 * d);
+                    for _ in 0..self.gs.loop_count.max(1) {
+                        let point = self.pop_point(2)?;
+                        self.shift_point(zone, point, dx, dy);
+                    }
+                    self.gs.loop_count = 1;
+                }
+                0x39 => {
+                    let (z1, z2, zone) = (self.zone_index(0), self.zone_index(1), self.zone_index(2));
+                    let (rp1, rp2) = (self.gs.rp[1], self.gs.rp[2]);
+                    self.check_point(z1, rp1)?;
+                    self.check_point(z2, rp2)?;
+                    let (o1, o2) = (self.original(z1, rp1), self.original(z2, rp2));
+                    let (c1, c2) = (self.current(z1, rp1), self.current(z2, rp2));
+                    let original_range = self.dual_project((o2.0 - o1.0, o2.1 - o1.1));
+                    let current_range = self.project((c2.0 - c1.0, c2.1 - c1.1));
+                    for _ in 0..self.gs.loop_count.max(1) {
+                        let point = self.pop_point(2)?;
+                        let o = self.original(zone, point);
+                        let c = self.current(zone, point);
+                        let original_distance = self.dual_project((o.0 - o1.0, o.1 - o1.1));
+                        let new_distance = if original_range != 0. {
+                            original_distance * current_range / original_range
+                        } else {
+                            original_distance
+                        };
+                        let current_distance = self.project((c.0 - c1.0, c.1 - c1.1));
+                        self.move_point(zone, point, new_distance - current_distance);
+                    }
+                    self.gs.loop_count = 1;
+                }
+                0x3A | 0x3B => {
+                    let d = self.pop_f()?;
+                    let point = self.pop_point(1)?;
+                    let (zone, ref_zone, rp0) = (self.zone_index(1), self.zone_index(0), self.gs.rp[0]);
+                    self.check_point(ref_zone, rp0)?;
+                    if zone == TWILIGHT {
+                        let o = self.original(ref_zone, rp0);
+                        self.zones[zone].original[point] = o;
+                        self.zones[zone].current[point] = o;
+                    }
+                    let (c, r) = (self.current(zone, point), self.current(ref_zone, rp0));
+                    let current_distance = self.project((c.0 - r.0, c.1 - r.1));
+                    self.move_point(zone, point, d - current_distance);
+                    self.gs.rp[1] = rp0;
+                    self.gs.rp[2] = point;
+                    if op == 0x3B {
+                        self.gs.rp[0] = point;
+                    }
+                }
+                0x3C => {
+                    let (zone, ref_zone, rp0) = (self.zone_index(1), self.zone_index(0), self.gs.rp[0]);
+                    self.check_point(ref_zone, rp0)?;
+                    for _ in 0..self.gs.loop_count.max(1) {
+                        let point = self.pop_point(1)?;
+                        let (c, r) = (self.current(zone, point), self.current(ref_zone, rp0));
+                        let d = self.project((c.0 - r.0, c.1 - r.1));
+                        self.move_point(zone, point, -d);
+                    }
+                    self.gs.loop_count = 1;
+                }
+                0x3D => self.gs.round_state = RoundState::DoubleGrid,
+                0x3E | 0x3F => {
+                    let cvt_index = self.pop()?;
+                    let point = self.pop_point(0)?;
+                    let zone = self.zone_index(0);
+                    let mut distance = self.cvt_value(cvt_index)?;
+                    if zone == TWILIGHT {
+                        let v = (distance * self.gs.freedom.0, distance * self.gs.freedom.1);
+                        self.zones[zone].original[point] = v;
+                        self.zones[zone].current[point] = v;
+                    }
+                    let current_distance = self.project(self.current(zone, point));
+                    if op == 0x3F {
+                        if (distance - current_distance).abs() > self.gs.cvt_cut_in {
+                            distance = current_distance;
+                        }
+                        distance = self.round(distance);
+                    }
+                    self.move_point(zone, point, distance - current_distance);
+                    self.gs.rp[0] = point;
+                    self.gs.rp[1] = point;
+                }
+                // pushes
+                0x40 => {
+                    for i in 0..code[ip + 1] as usize {
+                        self.push(code[ip + 2 + i] as i32)?;
+                    }
+                }
+                0x41 => {
+                    for i in 0..code[ip + 1] as usize {
+                        let at = ip + 2 + 2 * i;
+                        self.push(i16::from_be_bytes([code[at], code[at + 1]]) as i32)?;
+                    }
+                }
+                0xB0..=0xB7 => {
+                    for i in 0..(op - 0xB0 + 1) as usize {
+                        self.push(code[ip + 1 + i] as i32)?;
+                    }
+                }
+                0xB8..=0xBF => {
+                    for i in 0..(op - 0xB8 + 1) as usize {
+                        let at = ip + 1 + 2 * i;
+                        self.push(i16::from_be_bytes([code[at], code[at + 1]]) as i32)?;
+                    }
+                }
+                // storage and cvt
+                0x42 => {
+                    let value = self.pop()?;
+                    let location = self.pop()? as usize;
+                    *self
+                        .storage
+                        .get_mut(location)
+                        .ok_or_else(|| anyhow!("storage index {location} out of range"))? = value;
+                }
+                0x43 => {
+                    let location = self.pop()? as usize;
+                    let value = *self
+                        .storage
+                        .get(location)
+                        .ok_or_else(|| anyhow!("storage index {location} out of range"))?;
+                    self.push(value)?;
+                }
+                0x44 => {
+                    let value = self.pop_f()?;
+                    let index = self.pop()?;
+                    self.write_cvt(index, value)?;
+                }
+                0x70 => {
+                    let value = self.pop_f()? * self.scale;
+                    let index = self.pop()?;
+                    self.write_cvt(index, value)?;
+                }
+                0x45 => {
+                    let index = self.pop()?;
+                    let value = self.cvt_value(index)?;
+                    self.push(value.round() as i32)?;
+                }
+                0x46 | 0x47 => {
+                    let point = self.pop_point(2)?;
+                    let zone = self.zone_index(2);
+                    let value = if op == 0x46 {
+                        self.project(self.current(zone, point))
+                    } else {
+                        self.dual_project(self.original(zone, point))
+                    };
+                    self.push(value.round() as i32)?;
+                }
+                0x48 => {
+                    let k = self.pop_f()?;
+                    let point = self.pop_point(2)?;
+                    let zone = self.zone_index(2);
+                    let d = self.project(self.current(zone, point));
+                    self.move_point(zone, point, k - d);
+                    if zone == TWILIGHT {
+                        self.zones[zone].original[point] = self.zones[zone].current[point];
+                    }
+                }
+                0x49 | 0x4A => {
+                    let k = self.pop_point(1)?;
+                    let l = self.pop_point(0)?;
+                    let (zl, zk) = (self.zone_index(0), self.zone_index(1));
+                    let d = if op == 0x49 {
+                        let (a, b) = (self.current(zl, l), self.current(zk, k));
+                        self.project((a.0 - b.0, a.1 - b.1))
+                    } else {
+                        let (a, b) = (self.original(zl, l), self.original(zk, k));
+                        self.dual_project((a.0 - b.0, a.1 - b.1))
+                    };
+                    self.push(d.round() as i32)?;
+                }
+                0x4B | 0x4C => self.push(self.ppem as i32)?,
+                0x4D => self.gs.auto_flip = true,
+                0x4E => self.gs.auto_flip = false,
+                0x4F => {
+                    self.pop()?; // DEBUG
+                }
+                // logic and arithmetic
+                0x50..=0x55 | 0x5A | 0x5B => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let result = match op {
+                        0x50 => a < b,
+                        0x51 => a <= b,
+                        0x52 => a > b,
+                        0x53 => a >= b,
+                        0x54 => a == b,
+                        0x55 => a != b,
+                        0x5A => a != 0 && b != 0,
+                        _ => a != 0 || b != 0,
+                    };
+                    self.push(result as i32)?;
+                }
+                0x56 | 0x57 => {
+                    let value = self.pop_f()?;
+                    let v = self.round(value) as i32;
+                    let odd = (v >> 6) & 1 == 1;
+                    self.push((odd == (op == 0x56)) as i32)?;
+                }
+                0x58 => {
+                    if self.pop()? == 0 {
+                        next = Self::skip_conditional(code, next, true)?;
+                    }
+                }
+                0x59 => {} // EIF
+                0x5C => {
+                    let v = self.pop()?;
+                    self.push((v == 0) as i32)?;
+                }
+                0x5D => self.delta_point(0)?,
+                0x71 => self.delta_point(16)?,
+                0x72 => self.delta_point(32)?,
+                0x73 => self.delta_cvt(0)?,
+                0x74 => self.delta_cvt(16)?,
+                0x75 => self.delta_cvt(32)?,
+                0x5E => self.gs.delta_base = self.pop()?,
+                0x5F => self.gs.delta_shift = self.pop()?.clamp(0, 6),
+                0x60..=0x63 | 0x8B | 0x8C => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let result = match op {
+                        0x60 => a.wrapping_add(b),
+                        0x61 => a.wrapping_sub(b),
+                        0x62 => {
+                            if b == 0 {
+                                return Err(anyhow!("division by zero"));
+                            }
+                            ((a as i64 * 64) / b as i64) as i32
+                        }
+                        0x63 => ((a as i64 * b as i64) / 64) as i32,
+                        0x8B => a.max(b),
+                        _ => a.min(b),
+                    };
+                    self.push(result)?;
+                }
+                0x64 => {
+                    let v = self.pop()?;
+                    self.push(v.wrapping_abs())?;
+                }
+                0x65 => {
+                    let v = self.pop()?;
+                    self.push(v.wrapping_neg())?;
+                }
+                0x66 => {
+                    let v = self.pop()?;
+                    self.push(v & -64)?;
+                }
+                0x67 => {
+                    let v = self.pop()?;
+                    self.push(v.wrapping_add(63) & -64)?;
+                }
+                0x68..=0x6B => {
+                    let v = self.pop_f()?;
+                    self.push(self.round(v) as i32)?;
+                }
+                0x6C..=0x6F => {} // NROUND without engine compensation leaves the value alone
+                0x76 | 0x77 => {
+                    let n = self.pop()?;
+                    let grid_period = if op == 0x76 { 64. } else { 64. * std::f32::consts::FRAC_1_SQRT_2 };
+                    let period = match (n >> 6) & 3 {
+                        0 => grid_period / 2.,
+                        1 => grid_period,
+                        _ => grid_period * 2.,
+                    };
+                    let phase = match (n >> 4) & 3 {
+                        0 => 0.,
+                        1 => period / 4.,
+                        2 => period / 2.,
+                        _ => period * 3. / 4.,
+                    };
+                    let threshold = match n & 0xF {
+                        0 => period - 1.,
+                        t => (t - 4) as f32 * period / 8.,
+                    };
+                    self.gs.round_state = RoundState::Super { period, phase, threshold };
+                }
+                0x78 | 0x79 => {
+                    let condition = self.pop()?;
+                    let offset = self.pop()?;
+                    if (condition != 0) == (op == 0x78) {
+                        next = (ip as i64 + offset as i64).try_into().map_err(|_| anyhow!("jump out of range"))?;
+                    }
+                }
+                0x7A => self.gs.round_state = RoundState::Off,
+                0x7C => self.gs.round_state = RoundState::UpToGrid,
+                0x7D => self.gs.round_state = RoundState::DownToGrid,
+                0x7E | 0x7F | 0x85 | 0x8D => {
+                    self.pop()?; // SANGW, AA, SCANCTRL, SCANTYPE don't affect an anti-aliased rasterizer
+                }
+                0x80 => {
+                    let zone = self.zone_index(0);
+                    for _ in 0..self.gs.loop_count.max(1) {
+                        let point = self.pop_point(0)?;
+                        self.zones[zone].on_curve[point] = !self.zones[zone].on_curve[point];
+                    }
+                    self.gs.loop_count = 1;
+                }
+                0x81 | 0x82 => {
+                    let high = self.pop()? as usize;
+                    let low = self.pop()? as usize;
+                    let zone = self.zone_index(0);
+                    self.check_point(zone, high)?;
+                    for point in low..=high {
+                        self.zones[zone].on_curve[point] = op == 0x81;
+                    }
+                }
+                0x86 | 0x87 => {
+                    let p2 = self.pop_point(1)?;
+                    let p1 = self.pop_point(2)?;
+                    let (z1, z2) = (self.zone_index(2), self.zone_index(1));
+                    let perpendicular = op & 1 == 1;
+                    self.gs.dual_projection = self.vector_from_line(self.original(z1, p1), self.original(z2, p2), perpendicular);
+                    self.gs.projection = self.vector_from_line(self.current(z1, p1), self.current(z2, p2), perpendicular);
+                }
+                0x88 => {
+                    let selector = self.pop()?;
+                    let mut result = 0;
+                    if selector & 1 != 0 {
+                        result |= INTERPRETER_VERSION;
+                    }
+                    if selector & 32 != 0 {
+                        result |= 1 << 12; // rendering in grayscale
+                    }
+                    self.push(result)?;
+                }
+                0x8A => {
+                    let a = self.pop()?;
+                    let b = self.pop()?;
+                    let c = self.pop()?;
+                    self.push(b)?;
+                    self.push(a)?;
+                    self.push(c)?;
+                }
+                0x8E => {
+                    let selector = self.pop()?;
+                    let value = self.pop()?;
+                    if self.in_prep && (1..=2).contains(&selector) {
+                        let bit = 1 << (selector - 1);
+                        if value != 0 {
+                            self.gs.instruct_control |= bit;
+                        } else {
+                            self.gs.instruct_control &= !bit;
+                        }
+                    }
+                }
+                0xC0..=0xFF => self.move_relative(op)?,
+                _ => return Err(anyhow!("unsupported instruction 0x{op:02X} at {ip}")),
+            }
+            ip = next;
+        }
+        Ok(())
+    }
+
+    /// MDRP and MIRP: place a point relative to rp0, either keeping its original distance or
+    /// using a CVT distance, with optional rounding and minimum distance.
+    fn move_relative(&mut self, op: u8) -> Result<()> {
+        let is_mirp = op >= 0xE0;
+        let set_rp0 = op & 0x10 != 0;
+        let keep_min_distance = op & 0x08 != 0;
+        let round = op & 0x04 != 0;
+
+        let cvt_distance = if is_mirp {
+            let index = self.pop()?;
+            Some(self.cvt_value(index)?)
+        } else {
+            None
+        };
+        let point = self.pop_point(1)?;
+        let (zone, ref_zone, rp0) = (self.zone_index(1), self.zone_index(0), self.gs.rp[0]);
+        self.check_point(ref_zone, rp0)?;
+
+        let (o, r) = (self.original(zone, point), self.original(ref_zone, rp0));
+        let original_distance = self.dual_project((o.0 - r.0, o.1 - r.1));
+        let single_width = |d: f32, gs: &GraphicsState| {
+            if (d.abs() - gs.single_width_value).abs() < gs.single_width_cut_in {
+                if d >= 0. {
+                    gs.single_width_value
+                } else {
+                    -gs.single_width_value
+                }
+            } else {
+                d
+            }
+        };
+
+        let mut distance = match cvt_distance {
+            Some(cvt) => {
+                let mut cvt = single_width(cvt, &self.gs);
+                if zone == TWILIGHT {
+                    let v = (r.0 + cvt * self.gs.freedom.0, r.1 + cvt * self.gs.freedom.1);
+                    self.zones[zone].original[point] = v;
+                    self.zones[zone].current[point] = v;
+                }
+                if self.gs.auto_flip && (original_distance >= 0.) != (cvt >= 0.) && cvt != 0. {
+                    cvt = -cvt;
+                }
+                if round {
+                    if (cvt - original_distance).abs() > self.gs.cvt_cut_in {
+                        cvt = original_distance;
+                    }
+                    self.round(cvt)
+                } else {
+                    cvt
+                }
+            }
+            None => {
+                let d = single_width(original_distance, &self.gs);
+                if round {
+                    self.round(d)
+                } else {
+                    d
+                }
+            }
+        };
+
+        if keep_min_distance {
+            let min = self.gs.min_distance;
+            if original_distance >= 0. {
+                distance = distance.max(min);
+            } else {
+                distance = distance.min(-min);
+            }
+        }
+
+        let (c, rc) = (self.current(zone, point), self.current(ref_zone, rp0));
+        let current_distance = self.project((c.0 - rc.0, c.1 - rc.1));
+        self.move_point(zone, point, distance - current_distance);
+
+        self.gs.rp[1] = rp0;
+        self.gs.rp[2] = point;
+        if set_rp0 {
+            self.gs.rp[0] = point;
+        }
+        Ok(())
+    }
+}
+
+/// IUP: move the untouched points of every contour along one axis, interpolating between the
+/// touched points around them (or shifting with the nearest one when outside their range).
+pub(crate) fn interpolate_untouched(
+    original: &[(f32, f32)],
+    current: &mut [(f32, f32)],
+    touched: &[bool],
+    contour_end_indices: &[u16],
+    x_axis: bool,
+) {
+    let coord = |p: (f32, f32)| if x_axis { p.0 } else { p.1 };
+    let mut start = 0usize;
+    for end in contour_end_indices {
+        let end = *end as usize;
+        if end >= current.len() || end < start {
+            break;
+        }
+        let touched_points: Vec<usize> = (start..=end).filter(|i| touched[*i]).collect();
+        for (k, &ref1) in touched_points.iter().enumerate() {
+            let ref2 = touched_points[(k + 1) % touched_points.len()];
+            let (o1, o2) = (coord(original[ref1]), coord(original[ref2]));
+            let (c1, c2) = (coord(current[ref1]), coord(current[ref2]));
+            let (lo_o, lo_c, hi_o, hi_c) = if o1 <= o2 { (o1, c1, o2, c2) } else { (o2, c2, o1, c1) };
+            // walk the untouched points between ref1 and ref2, wrapping around the contour
+            let mut i = if ref1 == end { start } else { ref1 + 1 };
+            while i != ref2 {
+                let o = coord(original[i]);
+                let value = if o <= lo_o {
+                    o + lo_c - lo_o
+                } else if o >= hi_o {
+                    o + hi_c - hi_o
+                } else {
+                    lo_c + (o - lo_o) * (hi_c - lo_c) / (hi_o - lo_o)
+                };
+                if x_axis {
+                    current[i].0 = value;
+                } else {
+                    current[i].1 = value;
+                }
+                i = if i == end { start } else { i + 1 };
+            }
+        }
+        start = end + 1;
+    }
+}
+
+pub(crate) fn read_u16_at(data: &[u8], offset: usize) -> u16 {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .unwrap_or(0)
+}
+
+impl Hinter {
+    pub fn new(font: &Font, ppem: u16) -> Result<Hinter> {
+        let maxp = font.table_data("maxp").ok_or_else(|| anyhow!("no maxp table"))?;
+        let max_twilight = read_u16_at(maxp, 16) as usize;
+        let max_storage = read_u16_at(maxp, 18) as usize;
+        let max_stack = (read_u16_at(maxp, 24) as usize).max(MIN_STACK_SIZE);
+
+        let scale = ppem as f32 * 64. / font.units_per_em() as f32;
+        let mut cvt = Vec::new();
+        if let Some(data) = font.table_data("cvt ") {
+            let mut reader = FontReader::new(data);
+            while let Ok(value) = reader.read_i16() {
+                cvt.push(value as f32 * scale);
+            }
+        }
+
+        let mut hinter = Hinter {
+            ppem,
+            scale,
+            functions: HashMap::new(),
+            cvt,
+            storage: vec![0; max_storage],
+            twilight: Zone::with_points(vec![(0., 0.); max_twilight]),
+            graphics_state: GraphicsState::default(),
+            max_stack,
+        };
+
+        if let Some(fpgm) = font.table_data("fpgm") {
+            hinter.run_font_program(fpgm, false)?;
+        }
+        // the graphics state prep leaves behind becomes the default for every glyph
+        hinter.graphics_state = GraphicsState::default();
+        if let Some(prep) = font.table_data("prep") {
+            hinter.run_font_program(prep, true)?;
+        }
+        Ok(hinter)
+    }
+
+    pub fn ppem(&self) -> u16 {
+        self.ppem
+    }
+
+    fn run_font_program(&mut self, code: &[u8], in_prep: bool) -> Result<()> {
+        let twilight = std::mem::take(&mut self.twilight);
+        let mut interpreter = Interpreter {
+            functions: &mut self.functions,
+            cvt: &mut self.cvt,
+            storage: &mut self.storage,
+            zones: [twilight, Zone::default()],
+            gs: self.graphics_state,
+            stack: Vec::new(),
+            max_stack: self.max_stack,
+            ppem: self.ppem,
+            scale: self.scale,
+            budget: INSTRUCTION_BUDGET,
+            depth: 0,
+            in_prep,
+        };
+        let result = interpreter.run(code);
+        let [twilight, _] = interpreter.zones;
+        self.twilight = twilight;
+        if in_prep {
+            // like the MS rasterizer, prep can't change the vectors, round state, reference
+            // points or zone pointers glyph programs start with
+            let defaults = GraphicsState::default();
+            self.graphics_state = GraphicsState {
+                projection: defaults.projection,
+                dual_projection: defaults.dual_projection,
+                freedom: defaults.freedom,
+                rp: defaults.rp,
+                zp: defaults.zp,
+                loop_count: defaults.loop_count,
+                round_state: defaults.round_state,
+                ..interpreter.gs
+            };
+        }
+        result
+    }
+
+    /// Run the glyph's instructions on its scaled outline. Any error (unknown instruction, bad
+    /// point reference, exhausted budget) is returned so the caller can fall back to unhinted.
+    pub fn hint_glyph(&self, font: &Font, index: u16) -> Result<HintedGlyph> {
+        let glyph = font.glyph(index).ok_or_else(|| anyhow!("glyph {index} out of range"))?;
+        let mut points: Vec<(f32, f32)> = glyph
+            .points()
+            .into_iter()
+            .map(|(x, y)| (x * self.scale, y * self.scale))
+            .collect();
+        let num_points = points.len();
+
+        let phantom_points = font.phantom_points(index).unwrap_or_default();
+        points.extend(phantom_points.iter().map(|(x, y)| (x * self.scale, y * self.scale)));
+
+        let mut zone = Zone::with_points(points);
+        // the horizontal phantom points are grid fitted before the program runs
+        for phantom in [num_points, num_points + 1] {
+            let x = floor_64(zone.current[phantom].0 + 32.);
+            zone.current[phantom].0 = x;
+            zone.original[phantom].0 = x;
+        }
+        zone.on_curve[..num_points].copy_from_slice(&glyph.on_curve);
+        zone.contour_end_indices = glyph.contour_end_indices.clone();
+
+        let mut functions = self.functions.clone();
+        let mut cvt = self.cvt.clone();
+        let mut storage = self.storage.clone();
+        let mut interpreter = Interpreter {
+            functions: &mut functions,
+            cvt: &mut cvt,
+            storage: &mut storage,
+            zones: [self.twilight.clone(), zone],
+            gs: self.graphics_state,
+            stack: Vec::new(),
+            max_stack: self.max_stack,
+            ppem: self.ppem,
+            scale: self.scale,
+            budget: INSTRUCTION_BUDGET,
+            depth: 0,
+            in_prep: false,
+        };
+        // instruct control bit 0 means prep asked for glyph programs to be skipped
+        if interpreter.gs.instruct_control & 1 == 0 && !glyph.instructions.is_empty() {
+            interpreter.run(&glyph.instructions)?;
+        }
+
+        let [_, zone] = interpreter.zones;
+        let (origin, advance_point) = (zone.current[num_points], zone.current[num_points + 1]);
+        let points: Vec<(f32, f32)> = zone.current[..num_points]
+            .iter()
+            .map(|(x, y)| ((x - origin.0) / 64., y / 64.))
+            .collect();
+        Ok(HintedGlyph {
+            left_side_bearing: points.iter().map(|(x, _)| *x).reduce(f32::min).unwrap_or(0.),
+            points,
+            on_curve: zone.on_curve[..num_points].to_vec(),
+            contour_end_indices: zone.contour_end_indices,
+            advance: (advance_point.0 - origin.0) / 64.,
+        })
+    }
+}
+
+impl Font {
+    /// The hinting state for a pixel size, created (running fpgm and prep) the first time it's needed.
+    pub fn hinter(&self, ppem: u16) -> Option<Arc<Hinter>> {
+        if let Some(hinter) = self.hinters.get(&ppem) {
+            self.stats.hinter_cache(true);
+            return hinter;
+        }
+        self.stats.hinter_cache(false);
+        self.hinters.get_or_insert_with(ppem, || match Hinter::new(self, ppem) {
+            Ok(hinter) => Some(Arc::new(hinter)),
+            Err(err) => {
+                println!("Hinting disabled at {ppem} ppem : {err}");
+                None
+            }
+        })
+    }
+
+    /// Whether there are TrueType instructions to hint the glyph with: the font's fpgm or prep
+    /// programs, or the glyph's own.
+    pub fn has_instructions(&self, index: u16) -> bool {
+        self.table_data("fpgm").is_some()
+            || self.table_data("prep").is_some()
+            || self.glyph(index).is_some_and(|glyph| !glyph.instructions.is_empty())
+    }
+
+    /// Say once per font that native hinting was asked of a glyph without instructions.
+    pub(crate) fn warn_uninstructed(&self) {
+        self.uninstructed_warning.call_once(|| {
+            println!("Native hinting without instructions : glyphs are drawn unhinted, try native-if-present")
+        });
+    }
+
+    /// The grid fitted outline of a glyph, or `None` if the font or the glyph can't be hinted.
+    pub fn hinted_glyph(&self, index: u16, ppem: u16) -> Option<HintedGlyph> {
+        if ppem == 0 || !self.glyph(index)?.is_simple {
+            return None;
+        }
+        self.hinter(ppem)?.hint_glyph(self, index).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{rectangle, TestFontBuilder};
+
+    // the instructions the programs below use
+    const PUSHB_1: u8 = 0xB0;
+    const PUSHB_3: u8 = 0xB2;
+    const PUSHW_1: u8 = 0xB8;
+    const PUSHW_2: u8 = 0xB9;
+    const FDEF: u8 = 0x2C;
+    const ENDF: u8 = 0x2D;
+    const LOOPCALL: u8 = 0x2A;
+    const ADD: u8 = 0x60;
+    const MUL: u8 = 0x63;
+    const SDB: u8 = 0x5E;
+    const DELTAP3: u8 = 0x72;
+    const DELTAC3: u8 = 0x75;
+
+    fn font_with_programs(fpgm: &[u8], prep: &[u8]) -> Font {
+        let mut builder = TestFontBuilder::new();
+        builder.add_glyph('a', &rectangle(0., 0., 100., 200.));
+        let mut tables = builder.font_builder().unwrap();
+        tables.add_table("fpgm", fpgm.to_vec()).add_table("prep", prep.to_vec()).add_table("cvt ", vec![0; 2]);
+        Font::from_bytes(tables.build().unwrap()).unwrap()
+    }
+
+    /// Push 32767² / 64, a count far over the instruction budget.
+    const HUGE_COUNT: [u8; 6] = [PUSHW_2, 0x7F, 0xFF, 0x7F, 0xFF, MUL];
+
+    #[test]
+    fn loopcall_charges_every_call() {
+        let mut fpgm = vec![PUSHB_1, 0, FDEF, ENDF];
+        fpgm.extend_from_slice(&HUGE_COUNT);
+        fpgm.extend_from_slice(&[PUSHB_1, 0, LOOPCALL]);
+        let font = font_with_programs(&fpgm, &[]);
+        match Hinter::new(&font, 16) {
+            Ok(_) => panic!("the loop should run out of budget"),
+            Err(err) => assert!(err.to_string().contains("budget"), "{err}"),
+        }
+    }
+
+    #[test]
+    fn loopcall_within_the_budget_runs() {
+        let fpgm = [PUSHB_1, 0, FDEF, ENDF, PUSHW_1, 0x10, 0x00, PUSHB_1, 0, LOOPCALL];
+        assert!(Hinter::new(&font_with_programs(&fpgm, &[]), 16).is_ok());
+    }
+
+    #[test]
+    fn deltas_at_the_largest_delta_base_do_not_overflow() {
+        // 32767² / 64 * 8192 / 64 and four times 32767, 3 short of i32::MAX
+        let mut prep = HUGE_COUNT.to_vec();
+        prep.extend_from_slice(&[PUSHW_1, 0x20, 0x00, MUL]);
+        for _ in 0..4 {
+            prep.extend_from_slice(&[PUSHW_1, 0x7F, 0xFF, ADD]);
+        }
+        prep.push(SDB);
+        // one exception each, for point 0 and cvt entry 0 at the last ppem of the range
+        prep.extend_from_slice(&[PUSHB_3, 0xF0, 0, 1, DELTAP3, PUSHB_3, 0xF0, 0, 1, DELTAC3]);
+        assert!(Hinter::new(&font_with_programs(&[], &prep), 16).is_ok());
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+
+use crate::item_variation::{DeltaSetIndexMap, ItemVariationStore};
+use crate::reader::FontReader;
+
+/// The horizontal metrics variations table: advance width (and optionally side bearing) deltas.
+#[derive(Debug, Clone, Default)]
+pub struct Hvar {
+    store: ItemVariationStore,
+    advance_map: Option<DeltaSetIndexMap>,
+    lsb_map: Option<DeltaSetIndexMap>,
+}
+
+impl Hvar {
+    pub fn from_reader(reader: &mut FontReader, hvar_offset: u32) -> Result<Hvar> {
+        let start = hvar_offset as u64;
+        reader.seek(start + 4)?; // skip version
+        let store_offset = reader.read_u32()? as u64;
+        let advance_map_offset = reader.read_u32()? as u64;
+        let lsb_map_offset = reader.read_u32()? as u64;
+
+        let mut read_map = |offset: u64| match offset {
+            0 => Ok(None),
+            offset => DeltaSetIndexMap::from_reader(reader, start + offset).map(Some),
+        };
+        let advance_map = read_map(advance_map_offset)?;
+        let lsb_map = read_map(lsb_map_offset)?;
+        Ok(Hvar {
+            store: ItemVariationStore::from_reader(reader, start + store_offset)?,
+            advance_map,
+            lsb_map,
+        })
+    }
+
+    /// Advance width delta in font units. Without a mapping the glyph id is the inner index in
+    /// the first item variation data.
+    pub fn advance_delta(&self, glyph: u16, coords: &[f32]) -> f32 {
+        let (outer, inner) = match &self.advance_map {
+            Some(map) => map.get(glyph as u32).unwrap_or((0, glyph)),
+            None => (0, glyph),
+        };
+        self.store.delta(outer, inner, coords)
+    }
+
+    /// Left side bearing delta in font units, `None` when the table has no side bearing deltas.
+    pub fn lsb_delta(&self, glyph: u16, coords: &[f32]) -> Option<f32> {
+        let (outer, inner) = self.lsb_map.as_ref()?.get(glyph as u32)?;
+        Some(self.store.delta(outer, inner, coords))
+    }
+}
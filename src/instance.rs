@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+
+use crate::font::Font;
+use crate::name::{
+    FAMILY_NAME, FULL_NAME, POSTSCRIPT_NAME, SUBFAMILY_NAME, TYPOGRAPHIC_FAMILY_NAME, TYPOGRAPHIC_SUBFAMILY_NAME,
+    VARIATIONS_POSTSCRIPT_NAME_PREFIX,
+};
+use crate::write::{set_u16, FontBuilder};
+
+/// Tables that only make sense in a variable font.
+const VARIATION_TABLES: [&str; 8] = ["fvar", "gvar", "avar", "HVAR", "VVAR", "MVAR", "cvar", "STAT"];
+
+const OS2_WEIGHT_CLASS: usize = 4;
+
+/// Subfamily names that can stay in name id 2 with the family in id 1.
+const RIBBI: [&str; 4] = ["Regular", "Italic", "Bold", "Bold Italic"];
+
+/// How `Font::instance_with` writes the instance. Made with `default` (permissions honored) and
+/// the `with_*` methods, fields can be added without breaking callers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct InstanceOptions {
+    /// Write the instance even when the font's embedding permissions don't allow editing it,
+    /// see `EmbeddingPermissions::allows_editing`
+    pub ignore_embedding_permissions: bool,
+}
+
+impl InstanceOptions {
+    pub fn with_ignore_embedding_permissions(mut self, ignore_embedding_permissions: bool) -> Self {
+        self.ignore_embedding_permissions = ignore_embedding_permissions;
+        self
+    }
+}
+
+impl Font {
+    /// Pin every axis of a variable font and write the result as a static TrueType font.
+    /// `settings` are user coordinates like for `set_variation`. Simple glyphs get their gvar
+    /// deltas, hmtx the varied advances and side bearings (from HVAR or the phantom points), and
+    /// the font is renamed after the instance: the fvar named instance at those coordinates or
+    /// else the axis values, e.g. "wght620". The variation tables are dropped. Composite glyphs
+    /// and metrics variations (MVAR) are written as they are at the default instance. Fails for
+    /// fonts whose embedding permissions don't allow editing them, see `instance_with`.
+    pub fn instance(&self, settings: &[(&str, f32)]) -> Result<Vec<u8>> {
+        self.instance_with(settings, &InstanceOptions::default())
+    }
+
+    /// `instance` with options.
+    pub fn instance_with(&self, settings: &[(&str, f32)], options: &InstanceOptions) -> Result<Vec<u8>> {
+        if !self.is_variable() {
+            return Err(anyhow!("Not a variable font"));
+        }
+        if !options.ignore_embedding_permissions {
+            self.embedding_permissions().check_editing("Instancing the font")?;
+        }
+        let mut font = Font::from_bytes(self.file_data().to_vec())?;
+        font.set_variation(settings);
+
+        let mut glyphs = Vec::with_capacity(font.num_glyphs() as usize);
+        let mut metrics = Vec::with_capacity(font.num_glyphs() as usize);
+        for index in 0..font.num_glyphs() {
+            let raw = font.raw_glyph(index).unwrap_or(&[]);
+            let glyph = font.glyph(index).ok_or_else(|| anyhow!("No glyph {index}"))?;
+            let (advance, lsb) = (font.advance_width(index), font.left_side_bearing(index));
+            let has_outline = glyph.is_simple && glyph.num_points() > 0 && !raw.is_empty();
+            glyphs.push(if has_outline { glyph.to_bytes() } else { raw.to_vec() });
+            metrics.push((advance, lsb));
+        }
+
+        let mut name = self.name_table().clone();
+        let family = self
+            .name(TYPOGRAPHIC_FAMILY_NAME)
+            .or(self.name(FAMILY_NAME))
+            .unwrap_or_default()
+            .to_string();
+        let (style, postscript_name) = self.instance_names(settings);
+        let full_name = format!("{family} {style}");
+        let postscript_name =
+            postscript_name.unwrap_or_else(|| format!("{}-{}", family.replace(' ', ""), style.replace(' ', "")));
+        if RIBBI.contains(&style.as_str()) {
+            name.set(FAMILY_NAME, &family);
+            name.set(SUBFAMILY_NAME, &style);
+            name.remove(TYPOGRAPHIC_FAMILY_NAME);
+            name.remove(TYPOGRAPHIC_SUBFAMILY_NAME);
+        } else {
+            name.set(FAMILY_NAME, &full_name);
+            name.set(SUBFAMILY_NAME, "Regular");
+            name.set(TYPOGRAPHIC_FAMILY_NAME, &family);
+            name.set(TYPOGRAPHIC_SUBFAMILY_NAME, &style);
+        }
+        name.set(FULL_NAME, &full_name);
+        name.set(POSTSCRIPT_NAME, &postscript_name);
+        name.remove(VARIATIONS_POSTSCRIPT_NAME_PREFIX);
+
+        let glyph_data: Vec<&[u8]> = glyphs.iter().map(Vec::as_slice).collect();
+        let mut builder = FontBuilder::from_font(self);
+        for tag in VARIATION_TABLES {
+            builder.remove_table(tag);
+        }
+        builder.set_glyphs(&glyph_data)?.set_horizontal_metrics(&metrics)?;
+        builder.add_table("name", name.to_bytes()?);
+        let weight = self
+            .variation_axes()
+            .iter()
+            .zip(self.user_coords(settings))
+            .find(|(axis, _)| axis.tag == "wght")
+            .map(|(_, value)| value);
+        if let (Some(os2), Some(weight)) = (builder.table_mut("OS/2"), weight) {
+            set_u16(os2, OS2_WEIGHT_CLASS, weight.round().clamp(1., 1000.) as u16, "OS/2")?;
+        }
+        builder.build()
+    }
+
+    /// The style name and PostScript name (when the font has one) of the named instance at the
+    /// coordinates of `settings`, or else a name made of the axis values away from the default.
+    fn instance_names(&self, settings: &[(&str, f32)]) -> (String, Option<String>) {
+        let coords = self.user_coords(settings);
+        if let Some(instance) = self.named_instances().into_iter().find(|instance| instance.coords == coords) {
+            return (instance.name, instance.postscript_name);
+        }
+        let axes = self.variation_axes();
+        let values: Vec<String> = axes
+            .iter()
+            .zip(&coords)
+            .filter(|(axis, value)| **value != axis.default)
+            .map(|(axis, value)| format!("{}{}", axis.tag, value))
+            .collect();
+        match values.is_empty() {
+            true => (self.name(SUBFAMILY_NAME).unwrap_or("Regular").to_string(), None),
+            false => (values.join(" "), None),
+        }
+    }
+}
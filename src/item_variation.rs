@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Result};
+
+use crate::reader::FontReader;
+
+/// A delta-set row with the LONG_WORDS bit in wordDeltaCount stores 32 and 16 bit deltas instead
+/// of 16 and 8 bit ones.
+const LONG_WORDS: u16 = 0x8000;
+
+/// The influence of one axis on a region: zero outside start..end, rising to one at the peak.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionAxis {
+    pub start: f32,
+    pub peak: f32,
+    pub end: f32,
+}
+
+impl RegionAxis {
+    pub(crate) fn scalar(&self, coord: f32) -> f32 {
+        let RegionAxis { start, peak, end } = *self;
+        // malformed ranges and ranges crossing zero don't restrict anything
+        if start > peak || peak > end || (start < 0. && end > 0. && peak != 0.) || peak == 0. {
+            return 1.;
+        }
+        if coord < start || coord > end {
+            0.
+        } else if coord == peak {
+            1.
+        } else if coord < peak {
+            (coord - start) / (peak - start)
+        } else {
+            (end - coord) / (end - peak)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ItemVariationData {
+    region_indices: Vec<u16>,
+    /// One row of deltas per item, one delta per region in `region_indices`
+    delta_sets: Vec<Vec<i32>>,
+}
+
+/// Deltas for values that vary across the design space (advances, metrics, anchor positions),
+/// shared by HVAR, MVAR, GDEF and friends. Items are addressed by (outer, inner) indices.
+#[derive(Debug, Clone, Default)]
+pub struct ItemVariationStore {
+    regions: Vec<Vec<RegionAxis>>,
+    data: Vec<ItemVariationData>,
+}
+
+impl ItemVariationStore {
+    pub fn from_reader(reader: &mut FontReader, store_offset: u64) -> Result<ItemVariationStore> {
+        reader.seek(store_offset)?;
+        let format = reader.read_u16()?;
+        if format != 1 {
+            return Err(anyhow!("Unknown item variation store format {format}"));
+        }
+        let region_list_offset = store_offset + reader.read_u32()? as u64;
+        let data_count = reader.read_u16()?;
+        let mut data_offsets = Vec::with_capacity(data_count as usize);
+        for _ in 0..data_count {
+            data_offsets.push(store_offset + reader.read_u32()? as u64);
+        }
+
+        reader.seek(region_list_offset)?;
+        let axis_count = reader.read_u16()?;
+        let region_count = reader.read_u16()?;
+        let mut regions = Vec::with_capacity(region_count as usize);
+        for _ in 0..region_count {
+            let mut axes = Vec::with_capacity(axis_count as usize);
+            for _ in 0..axis_count {
+                axes.push(RegionAxis {
+                    start: reader.read_f2dot14()?.to_f32(),
+                    peak: reader.read_f2dot14()?.to_f32(),
+                    end: reader.read_f2dot14()?.to_f32(),
+                });
+            }
+            regions.push(axes);
+        }
+
+        let mut data = Vec::with_capacity(data_count as usize);
+        for offset in data_offsets {
+            if offset == store_offset {
+                // a null offset
+                data.push(ItemVariationData::default());
+                continue;
+            }
+            reader.seek(offset)?;
+            let item_count = reader.read_u16()?;
+            let word_delta_count = reader.read_u16()?;
+            let region_index_count = reader.read_u16()?;
+            let mut region_indices = Vec::with_capacity(region_index_count as usize);
+            for _ in 0..region_index_count {
+                region_indices.push(reader.read_u16()?);
+            }
+            let long_words = word_delta_count & LONG_WORDS != 0;
+            let word_count = (word_delta_count & !LONG_WORDS) as usize;
+            let mut delta_sets = Vec::with_capacity(item_count as usize);
+            for _ in 0..item_count {
+                let mut row = Vec::with_capacity(region_index_count as usize);
+                for column in 0..region_index_count as usize {
+                    let delta = match (column < word_count, long_words) {
+                        (true, true) => reader.read_i32()?,
+                        (true, false) | (false, true) => reader.read_i16()? as i32,
+                        (false, false) => reader.read_i8()? as i32,
+                    };
+                    row.push(delta);
+                }
+                delta_sets.push(row);
+            }
+            data.push(ItemVariationData {
+                region_indices,
+                delta_sets,
+            });
+        }
+        Ok(ItemVariationStore { regions, data })
+    }
+
+    /// How much a region applies at the normalized coordinates `coords`, from 0 to 1.
+    pub fn region_scalar(&self, region: usize, coords: &[f32]) -> f32 {
+        let Some(axes) = self.regions.get(region) else {
+            return 0.;
+        };
+        axes.iter()
+            .enumerate()
+            .map(|(axis, region_axis)| region_axis.scalar(coords.get(axis).copied().unwrap_or(0.)))
+            .product()
+    }
+
+    /// The interpolated delta of an item, 0 for items that aren't in the store.
+    pub fn delta(&self, outer: u16, inner: u16, coords: &[f32]) -> f32 {
+        let Some(data) = self.data.get(outer as usize) else {
+            return 0.;
+        };
+        let Some(row) = data.delta_sets.get(inner as usize) else {
+            return 0.;
+        };
+        row.iter()
+            .zip(&data.region_indices)
+            .map(|(delta, region)| *delta as f32 * self.region_scalar(*region as usize, coords))
+            .sum()
+    }
+}
+
+/// Maps glyph ids (or other indices) to (outer, inner) item indices in an item variation store.
+#[derive(Debug, Clone, Default)]
+pub struct DeltaSetIndexMap {
+    entries: Vec<(u16, u16)>,
+}
+
+impl DeltaSetIndexMap {
+    pub fn from_reader(reader: &mut FontReader, map_offset: u64) -> Result<DeltaSetIndexMap> {
+        reader.seek(map_offset)?;
+        let format = reader.read_u8()?;
+        let entry_format = reader.read_u8()?;
+        let map_count = match format {
+            0 => reader.read_u16()? as u32,
+            1 => reader.read_u32()?,
+            _ => return Err(anyhow!("Unknown delta set index map format {format}")),
+        };
+        let inner_bits = (entry_format & 0x0f) as u32 + 1;
+        let entry_size = ((entry_format & 0x30) >> 4) as usize + 1;
+        let mut entries = Vec::with_capacity(map_count as usize);
+        for _ in 0..map_count {
+            let mut entry = 0u32;
+            for _ in 0..entry_size {
+                entry = entry << 8 | reader.read_u8()? as u32;
+            }
+            entries.push(((entry >> inner_bits) as u16, (entry & ((1 << inner_bits) - 1)) as u16));
+        }
+        Ok(DeltaSetIndexMap { entries })
+    }
+
+    /// The item for `index`, indices past the end of the map use its last entry.
+    pub fn get(&self, index: u32) -> Option<(u16, u16)> {
+        self.entries.get(index as usize).or(self.entries.last()).copied()
+    }
+}
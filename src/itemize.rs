@@ -0,0 +1,238 @@
+use std::ops::Range;
+
+use crate::otlayout::Tag;
+use crate::unicode_tables::SCRIPT_RANGES;
+
+/// The Unicode script (UAX #24) of a character, for the scripts the layout tells apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    /// Punctuation, spaces, digits and symbols shared by all scripts
+    Common,
+    /// Combining marks and selectors, they take the script of the character they follow
+    Inherited,
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Syriac,
+    Thaana,
+    Nko,
+    Devanagari,
+    Bengali,
+    Gurmukhi,
+    Gujarati,
+    Tamil,
+    Telugu,
+    Kannada,
+    Malayalam,
+    Thai,
+    Lao,
+    Tibetan,
+    Georgian,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Han,
+    /// Any script not listed
+    Other,
+}
+
+impl Script {
+    pub fn of(c: char) -> Script {
+        let c = c as u32;
+        let at = SCRIPT_RANGES.partition_point(|&(_, last, _)| last < c);
+        match SCRIPT_RANGES.get(at) {
+            Some(&(first, _, script)) if first <= c => script,
+            _ => Script::Other,
+        }
+    }
+
+    /// Whether the script is written right to left.
+    pub fn is_rtl(self) -> bool {
+        matches!(self, Script::Hebrew | Script::Arabic | Script::Syriac | Script::Thaana | Script::Nko)
+    }
+
+    /// Whether letters of the script connect and take contextual forms.
+    pub fn is_joining(self) -> bool {
+        matches!(self, Script::Arabic | Script::Syriac | Script::Nko)
+    }
+
+    /// The OpenType script tag, `DFLT` for the scripts that have none.
+    pub fn tag(self) -> Tag {
+        match self {
+            Script::Common | Script::Inherited | Script::Other => *b"DFLT",
+            Script::Latin => *b"latn",
+            Script::Greek => *b"grek",
+            Script::Cyrillic => *b"cyrl",
+            Script::Armenian => *b"armn",
+            Script::Hebrew => *b"hebr",
+            Script::Arabic => *b"arab",
+            Script::Syriac => *b"syrc",
+            Script::Thaana => *b"thaa",
+            Script::Nko => *b"nko ",
+            Script::Devanagari => *b"deva",
+            Script::Bengali => *b"beng",
+            Script::Gurmukhi => *b"guru",
+            Script::Gujarati => *b"gujr",
+            Script::Tamil => *b"taml",
+            Script::Telugu => *b"telu",
+            Script::Kannada => *b"knda",
+            Script::Malayalam => *b"mlym",
+            Script::Thai => *b"thai",
+            Script::Lao => *b"lao ",
+            Script::Tibetan => *b"tibt",
+            Script::Georgian => *b"geor",
+            Script::Hangul => *b"hang",
+            Script::Hiragana | Script::Katakana => *b"kana",
+            Script::Han => *b"hani",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    LeftToRight,
+    RightToLeft,
+}
+
+/// A piece of text in one script and one direction, laid out on its own. `range` is a byte range
+/// of the itemized text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub range: Range<usize>,
+    pub script: Script,
+    pub direction: Direction,
+}
+
+/// How a character takes part in itemization.
+enum CharClass {
+    /// Decides the script and direction of its run
+    Strong(Script, Direction),
+    /// Goes with the run before or after it
+    Neutral,
+    /// Stays with the character before it
+    Inherited,
+}
+
+fn char_class(c: char) -> CharClass {
+    match Script::of(c) {
+        // a simplification of the bidi weak types: digits read left to right, also in right to left text
+        Script::Common if c.is_ascii_digit() => CharClass::Strong(Script::Common, Direction::LeftToRight),
+        Script::Common => CharClass::Neutral,
+        Script::Inherited => CharClass::Inherited,
+        script if script.is_rtl() => CharClass::Strong(script, Direction::RightToLeft),
+        script => CharClass::Strong(script, Direction::LeftToRight),
+    }
+}
+
+/// The direction of the first letter of the text, left to right when there is none.
+pub fn paragraph_direction(text: &str) -> Direction {
+    text.chars()
+        .filter(|c| !c.is_ascii_digit())
+        .find_map(|c| match char_class(c) {
+            CharClass::Strong(_, direction) => Some(direction),
+            _ => None,
+        })
+        .unwrap_or(Direction::LeftToRight)
+}
+
+/// Split a line of text into runs by script and direction, in logical order and covering all of
+/// it. Characters of no particular script join the run of the letters around them: between runs
+/// in the same direction they stay with the run before, otherwise they take the paragraph
+/// direction.
+pub fn itemize(text: &str) -> Vec<TextRun> {
+    let paragraph = paragraph_direction(text);
+    let mut runs: Vec<TextRun> = Vec::new();
+    // where the neutral characters not yet given to a run start
+    let mut neutrals: Option<usize> = None;
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        let (script, direction) = match char_class(c) {
+            CharClass::Strong(script, direction) => (script, direction),
+            CharClass::Inherited if neutrals.is_none() && !runs.is_empty() => {
+                if let Some(run) = runs.last_mut() {
+                    run.range.end = end;
+                }
+                continue;
+            }
+            CharClass::Neutral | CharClass::Inherited => {
+                neutrals.get_or_insert(i);
+                continue;
+            }
+        };
+        match runs.last_mut() {
+            None => runs.push(TextRun {
+                range: 0..end,
+                script,
+                direction,
+            }),
+            Some(last)
+                if last.direction == direction
+                    && (last.script == script || script == Script::Common || last.script == Script::Common) =>
+            {
+                last.range.end = end;
+                if last.script == Script::Common {
+                    last.script = script;
+                }
+            }
+            Some(last) => {
+                let split = if last.direction == direction || last.direction == paragraph {
+                    i
+                } else {
+                    neutrals.unwrap_or(i)
+                };
+                last.range.end = split;
+                runs.push(TextRun {
+                    range: split..end,
+                    script,
+                    direction,
+                });
+            }
+        }
+        neutrals = None;
+    }
+    if let Some(start) = neutrals {
+        match runs.last_mut() {
+            Some(last) if last.direction == paragraph => last.range.end = text.len(),
+            _ => runs.push(TextRun {
+                range: start..text.len(),
+                script: Script::Common,
+                direction: paragraph,
+            }),
+        }
+    }
+    runs
+}
+
+/// The bidi embedding level of text in `direction` in a paragraph in `paragraph` direction, one
+/// level of embedding deep: 0 for left to right text in a left to right paragraph, 1 for right
+/// to left text, 2 for left to right text in a right to left paragraph.
+pub fn embedding_level(direction: Direction, paragraph: Direction) -> u8 {
+    match (paragraph, direction) {
+        (Direction::LeftToRight, Direction::LeftToRight) => 0,
+        (_, Direction::RightToLeft) => 1,
+        (Direction::RightToLeft, Direction::LeftToRight) => 2,
+    }
+}
+
+/// The order to show `runs` in from left to right, as indices into `runs`. Runs get the bidi
+/// embedding levels of a paragraph in `paragraph` direction and are reordered by rule L2 of
+/// UAX #9: from the highest level down, every sequence at that level or higher is reversed.
+pub fn visual_order(runs: &[TextRun], paragraph: Direction) -> Vec<usize> {
+    let levels: Vec<u8> = runs.iter().map(|run| embedding_level(run.direction, paragraph)).collect();
+    let mut order: Vec<usize> = (0..runs.len()).collect();
+    for level in (1..=levels.iter().copied().max().unwrap_or(0)).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            let start = i;
+            while i < order.len() && levels[order[i]] >= level {
+                i += 1;
+            }
+            order[start..i].reverse();
+            i += 1;
+        }
+    }
+    order
+}
@@ -0,0 +1,618 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::cmap::is_variation_selector;
+use crate::font::Font;
+use crate::linebreak::{break_between, break_class, is_invisible, BreakClass};
+use crate::raster::RasterOptions;
+use crate::render::{caret_position_with, hit_test_slanted_with, layout_line_with, slanted_caret};
+use crate::size::FontSize;
+use crate::source::GlyphSource;
+
+/// U+00AD, a place a word may be hyphenated: invisible and taking no room, unless the line
+/// breaks there, then it ends the line as a hyphen.
+const SOFT_HYPHEN: char = '\u{AD}';
+/// U+FFFC, stands for the next of the `LayoutOptions::inline_objects` in the text.
+pub const OBJECT_REPLACEMENT: char = '\u{FFFC}';
+
+/// What to draw for a character the font has no glyph for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MissingGlyphStyle {
+    /// The font's glyph 0
+    #[default]
+    NotDef,
+    /// A hollow box with the code point in hex inside, see `hexbox::hex_box_outline`
+    HexBox,
+    /// Nothing, the character takes no space
+    Skip,
+}
+
+/// How glyph positions are rounded to the pixel grid. Measuring, wrapping and hit testing round
+/// the same way as drawing, so boxes line up with the glyphs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PositionPolicy {
+    /// Positions as computed, for text that moves smoothly: bitmaps are drawn at a fraction of
+    /// a pixel (see `render::SUBPIXEL_POSITIONS`), paths anywhere
+    #[default]
+    None,
+    /// The pen x, advances and kerning on whole pixels, y as given
+    SnapX,
+    /// The pen x and the baseline on whole pixels, for crisp UI text
+    SnapXY,
+}
+
+impl PositionPolicy {
+    pub fn snap_x(self, x: f32) -> f32 {
+        match self {
+            PositionPolicy::None => x,
+            PositionPolicy::SnapX | PositionPolicy::SnapXY => x.round(),
+        }
+    }
+
+    pub fn snap_y(self, y: f32) -> f32 {
+        match self {
+            PositionPolicy::None | PositionPolicy::SnapX => y,
+            PositionPolicy::SnapXY => y.round(),
+        }
+    }
+
+    /// The policy glyphs are actually placed by when rasterized with `options`: glyphs hinted
+    /// by their instructions or drawn from embedded bitmaps go on whole pixels whatever the
+    /// layout asks for. Laying out, measuring and the shape cache all round through this.
+    pub fn for_raster(self, options: Option<&RasterOptions>) -> PositionPolicy {
+        match options {
+            Some(options) if options.hinting.is_native() || options.prefer_embedded_bitmaps => PositionPolicy::SnapXY,
+            _ => self,
+        }
+    }
+}
+
+/// The distance from one baseline to the next.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LineHeight {
+    /// The font's ascender - descender + line gap
+    #[default]
+    Normal,
+    /// The normal line height times a factor
+    Multiple(f32),
+    /// A fixed distance in pixels
+    Exact(f32),
+}
+
+impl LineHeight {
+    /// The baseline to baseline distance in pixels for `source` at `px_size`.
+    pub fn pixels<S: GlyphSource + ?Sized>(self, source: &S, px_size: f32) -> f32 {
+        let (ascender, descender, line_gap) = source.vertical_metrics();
+        let normal = (ascender as f32 - descender as f32 + line_gap as f32) * px_size / source.units_per_em() as f32;
+        match self {
+            LineHeight::Normal => normal,
+            LineHeight::Multiple(factor) => normal * factor,
+            LineHeight::Exact(pixels) => pixels,
+        }
+    }
+}
+
+/// How text is laid out: the glyphs of a line (see `render::layout_line_with`) and the lines
+/// of a text (see `layout_text`). Made with `default` (.notdef for missing glyphs, the font's
+/// line height, no paragraph or letter spacing, unsnapped positions, no trak, overrides,
+/// inline objects or debug boxes) and the `with_*` methods, fields can be added without breaking callers.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct LayoutOptions {
+    pub missing_glyph: MissingGlyphStyle,
+    pub line_height: LineHeight,
+    /// Extra space in pixels above a paragraph that follows a blank line
+    pub paragraph_spacing: f32,
+    pub position: PositionPolicy,
+    /// Extra space in pixels after each glyph (not marks), negative to bring glyphs together
+    pub letter_spacing: f32,
+    /// Add the tracking the font gives for the size (its trak table, see `Font::tracking`) to
+    /// `letter_spacing`
+    pub use_trak: bool,
+    /// By glyph index, how glyphs are laid out instead of by their metrics
+    pub glyph_overrides: HashMap<u16, GlyphOverride>,
+    /// What the U+FFFC characters of the text stand for, the first one for the first of them and
+    /// so on. Those past the end of the list are laid out as characters
+    pub inline_objects: Vec<InlineObject>,
+    /// Stroke the layout boxes over the text when drawing it, see `debug_boxes`
+    pub debug_boxes: bool,
+}
+
+impl LayoutOptions {
+    pub fn with_missing_glyph(mut self, missing_glyph: MissingGlyphStyle) -> Self {
+        self.missing_glyph = missing_glyph;
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: LineHeight) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn with_paragraph_spacing(mut self, paragraph_spacing: f32) -> Self {
+        self.paragraph_spacing = paragraph_spacing;
+        self
+    }
+
+    pub fn with_position(mut self, position: PositionPolicy) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_letter_spacing(mut self, letter_spacing: f32) -> Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    pub fn with_use_trak(mut self, use_trak: bool) -> Self {
+        self.use_trak = use_trak;
+        self
+    }
+
+    pub fn with_glyph_overrides(mut self, glyph_overrides: HashMap<u16, GlyphOverride>) -> Self {
+        self.glyph_overrides = glyph_overrides;
+        self
+    }
+
+    pub fn with_inline_objects(mut self, inline_objects: Vec<InlineObject>) -> Self {
+        self.inline_objects = inline_objects;
+        self
+    }
+
+    pub fn with_debug_boxes(mut self, debug_boxes: bool) -> Self {
+        self.debug_boxes = debug_boxes;
+        self
+    }
+}
+
+/// Something the caller draws in the middle of the text, like an image or a widget, in place
+/// of a U+FFFC. It takes its width in the line (and the letter spacing after it) and the line
+/// grows to fit it, it can't be broken. Sizes in pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InlineObject {
+    pub width: f32,
+    pub height: f32,
+    /// How far the bottom of the object is below the baseline, negative to raise it above
+    pub baseline_offset: f32,
+}
+
+impl InlineObject {
+    /// From the baseline up to the top of the object.
+    pub fn ascent(&self) -> f32 {
+        self.height - self.baseline_offset
+    }
+
+    /// The rectangle (x, top, width, height) the object covers with its pen position (on the
+    /// baseline, y down) at `position`.
+    pub fn rect(&self, position: (f32, f32)) -> (f32, f32, f32, f32) {
+        (position.0, position.1 - self.ascent(), self.width, self.height)
+    }
+}
+
+/// A glyph laid out apart from its own metrics, like an icon given the advance of the cell it
+/// sits in. Applies after kerning, measuring and wrapping go by it too.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GlyphOverride {
+    /// In pixels, instead of the glyph's advance
+    pub advance: Option<f32>,
+    /// In pixels (y down), where the glyph is drawn from its pen position, the pen doesn't move
+    pub offset: (f32, f32),
+}
+
+/// One line of wrapped text: the byte range of the paragraph it shows and its width in pixels
+/// (without the trailing spaces).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineBox {
+    pub range: Range<usize>,
+    pub width: f32,
+}
+
+/// A line of a laid out text, see `layout_text`. Positions are in pixels down from the top of
+/// the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    /// Byte range in the whole text, without the newline
+    pub range: Range<usize>,
+    /// Without the trailing spaces
+    pub width: f32,
+    pub top: f32,
+    pub baseline: f32,
+    pub height: f32,
+    /// The indices of the inline objects on the line
+    pub objects: Range<usize>,
+}
+
+/// The lines of a text wrapped to a width, with the size and options they were laid out with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextLayout {
+    pub lines: Vec<TextLine>,
+    pub px_size: f32,
+    pub options: LayoutOptions,
+}
+
+impl Font {
+    /// The glyph for each character of `text` with the byte offset of the character, its cluster.
+    /// A variation selector picks the variant glyph of the character before it and belongs to
+    /// that character's cluster, it doesn't get a glyph of its own (nor do stray selectors).
+    pub fn glyph_clusters(&self, text: &str) -> Vec<(usize, u16)> {
+        glyph_clusters(self, text)
+    }
+
+    /// Greedy word wrap of a paragraph (no newlines) into lines at most `max_width` wide. Lines
+    /// break after spaces, between ideographs, or after soft hyphens when the line fits with the
+    /// hyphen drawn there, never at no-break spaces (see `linebreak`). A word longer than a line
+    /// is broken between characters. An empty paragraph still gives one empty line.
+    pub fn wrap_paragraph(&self, text: &str, size: impl Into<FontSize>, max_width: f32) -> Vec<LineBox> {
+        let px_size = size.into().px_per_em();
+        wrap_paragraph(self, text, px_size, max_width)
+    }
+
+    /// Lay out text with newlines into wrapped lines stacked at the line height of `options`,
+    /// see `layout_text`.
+    pub fn layout_text(
+        &self,
+        text: &str,
+        size: impl Into<FontSize>,
+        max_width: f32,
+        options: &LayoutOptions,
+    ) -> TextLayout {
+        let px_size = size.into().px_per_em();
+        layout_text(self, text, px_size, max_width, options)
+    }
+}
+
+/// `Font::glyph_clusters` for any glyph source.
+pub fn glyph_clusters<S: GlyphSource + ?Sized>(source: &S, text: &str) -> Vec<(usize, u16)> {
+    source
+        .map_str(text)
+        .into_iter()
+        .map(|mapping| (mapping.byte_range.start, mapping.glyph.unwrap_or(0)))
+        .collect()
+}
+
+/// The glyph a soft hyphen is drawn with where a line breaks after it: U+2010 when the font
+/// has it, the hyphen-minus otherwise.
+pub(crate) fn hyphen_glyph<S: GlyphSource + ?Sized>(source: &S) -> u16 {
+    source.glyph_index('\u{2010}').or_else(|| source.glyph_index('-')).unwrap_or(0)
+}
+
+/// The glyph `render::layout_line_with` draws a character with, given the one the font maps it
+/// to: a soft hyphen's is the hyphen it becomes at the end of a line, no-break spaces the font
+/// doesn't have are drawn as spaces.
+pub(crate) fn drawn_glyph<S: GlyphSource + ?Sized>(source: &S, c: char, index: u16) -> u16 {
+    match break_class(c) {
+        BreakClass::SoftHyphen => hyphen_glyph(source),
+        BreakClass::Glue if index == 0 => source.glyph_index(' ').unwrap_or(0),
+        _ => index,
+    }
+}
+
+/// Whether the character at byte `at` of a line is drawn as nothing and takes no room: the
+/// invisible ones, and soft hyphens but one ending the line.
+pub(crate) fn is_hidden(text: &str, at: usize) -> bool {
+    let Some(c) = text[at..].chars().next() else {
+        return false;
+    };
+    match break_class(c) {
+        BreakClass::SoftHyphen => at + c.len_utf8() < text.len(),
+        class => is_invisible(class),
+    }
+}
+
+/// The inline object the character at byte `at` of a line stands for with its index counted
+/// from the start of the line, `None` for other characters and U+FFFC with no object.
+pub(crate) fn inline_object(text: &str, at: usize, options: &LayoutOptions) -> Option<(usize, InlineObject)> {
+    if options.inline_objects.is_empty() || !text[at..].starts_with(OBJECT_REPLACEMENT) {
+        return None;
+    }
+    let index = text[..at].matches(OBJECT_REPLACEMENT).count();
+    options.inline_objects.get(index).map(|object| (index, *object))
+}
+
+/// The glyph wrapping measures a character with, a soft hyphen's being the hyphen it may become.
+fn wrapping_glyph<S: GlyphSource + ?Sized>(source: &S, c: char) -> u16 {
+    drawn_glyph(source, c, source.glyph_index(c).unwrap_or(0))
+}
+
+/// The advance of a character for wrapping, see `wrap_with_advances`.
+pub(crate) fn char_advance<S: GlyphSource + ?Sized>(source: &S, c: char, scale: f32) -> f32 {
+    source.advance(wrapping_glyph(source, c)) as f32 * scale
+}
+
+/// The space in pixels `options` add after each glyph at `px_size`: the letter spacing and the
+/// font's tracking when asked for.
+pub(crate) fn letter_spacing<S: GlyphSource + ?Sized>(source: &S, px_size: f32, options: &LayoutOptions) -> f32 {
+    match options.use_trak {
+        true => options.letter_spacing + source.tracking(px_size),
+        false => options.letter_spacing,
+    }
+}
+
+/// `char_advance`, or the advance `options` override it with, and the letter spacing.
+fn char_advance_with<S: GlyphSource + ?Sized>(source: &S, c: char, px_size: f32, options: &LayoutOptions) -> f32 {
+    let index = wrapping_glyph(source, c);
+    let advance = match options.glyph_overrides.get(&index).and_then(|glyph| glyph.advance) {
+        Some(advance) => advance,
+        None => char_advance(source, c, px_size / source.units_per_em() as f32),
+    };
+    match source.is_mark(index) {
+        true => advance,
+        false => advance + letter_spacing(source, px_size, options),
+    }
+}
+
+/// `Font::wrap_paragraph` for any glyph source.
+pub fn wrap_paragraph<S: GlyphSource + ?Sized>(source: &S, text: &str, px_size: f32, max_width: f32) -> Vec<LineBox> {
+    let scale = px_size / source.units_per_em() as f32;
+    wrap_with_advances(text, max_width, |_, c| char_advance(source, c, scale))
+}
+
+/// `Font::layout_text` for any glyph source. Each line of the text is word wrapped like
+/// `wrap_paragraph`, soft wrapped and hard broken lines alike follow each other at the line
+/// height, an empty line is a blank line of the same height, and the first line after blank
+/// lines starts a new paragraph, `paragraph_spacing` further down. Extra line height (or the
+/// lack of it with an exact height below normal) is shared above and below the line, so the
+/// baseline sits ascender below the top of a line of normal height. Advances (overridden ones
+/// included) are snapped for wrapping and baselines placed as the position policy says. A line
+/// with inline objects reaching above or below it grows by as much, the baseline moving down
+/// for the part above.
+pub fn layout_text<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    px_size: f32,
+    max_width: f32,
+    options: &LayoutOptions,
+) -> TextLayout {
+    let height = options.line_height.pixels(source, px_size);
+    let leading = height - LineHeight::Normal.pixels(source, px_size);
+    let ascent = source.vertical_metrics().0 as f32 * px_size / source.units_per_em() as f32;
+    let above = leading / 2. + ascent;
+    // the byte offsets of the U+FFFC that have an object, the index of an object is its position
+    let objects: Vec<usize> = text
+        .match_indices(OBJECT_REPLACEMENT)
+        .map(|(at, _)| at)
+        .take(options.inline_objects.len())
+        .collect();
+    let objects_before = |at: usize| objects.partition_point(|object| *object < at);
+    let mut lines = Vec::new();
+    let (mut top, mut start, mut after_blank) = (0., 0, false);
+    for paragraph in text.split('\n') {
+        if paragraph.is_empty() {
+            after_blank = true;
+        } else if after_blank && !lines.is_empty() {
+            top += options.paragraph_spacing;
+            after_blank = false;
+        }
+        let advance = |i: usize, c| {
+            let advance = match objects.binary_search(&(start + i)) {
+                Ok(index) => options.inline_objects[index].width + letter_spacing(source, px_size, options),
+                Err(_) => char_advance_with(source, c, px_size, options),
+            };
+            options.position.snap_x(advance)
+        };
+        for line in wrap_with_advances(paragraph, max_width, advance) {
+            let range = start + line.range.start..start + line.range.end;
+            let line_objects = objects_before(range.start)..objects_before(range.end);
+            let (mut extra_above, mut extra_below) = (0f32, 0f32);
+            for object in &options.inline_objects[line_objects.clone()] {
+                extra_above = extra_above.max(object.ascent() - above);
+                extra_below = extra_below.max(object.baseline_offset - (height - above));
+            }
+            lines.push(TextLine {
+                range,
+                width: line.width,
+                top,
+                baseline: options.position.snap_y(top + extra_above + above),
+                height: height + extra_above + extra_below,
+                objects: line_objects,
+            });
+            top += height + extra_above + extra_below;
+        }
+        start += paragraph.len() + 1;
+    }
+    TextLayout {
+        lines,
+        px_size,
+        options: options.clone(),
+    }
+}
+
+impl TextLayout {
+    /// From the top of the first line to the bottom of the last.
+    pub fn height(&self) -> f32 {
+        self.lines.last().map_or(0., |line| line.top + line.height)
+    }
+
+    pub fn width(&self) -> f32 {
+        self.lines.iter().map(|line| line.width).fold(0., f32::max)
+    }
+
+    /// The line at height `y`, the nearest one above or below the text and the one above in
+    /// paragraph spacing.
+    pub fn line_at_y(&self, y: f32) -> usize {
+        self.lines.partition_point(|line| line.top <= y).saturating_sub(1)
+    }
+
+    /// The line the caret at byte `offset` of the text is on: where a soft wrap splits the text
+    /// the offset starts the next line.
+    pub fn line_of_offset(&self, offset: usize) -> usize {
+        self.lines.partition_point(|line| line.range.start <= offset).saturating_sub(1)
+    }
+
+    /// The options `line` is laid out with by `render::layout_line_with`: the layout's, less the
+    /// inline objects of the lines before, so that the line's first U+FFFC is its first object.
+    pub fn line_options(&self, line: &TextLine) -> Cow<'_, LayoutOptions> {
+        if line.objects.start == 0 {
+            return Cow::Borrowed(&self.options);
+        }
+        let mut options = self.options.clone();
+        options.inline_objects.drain(..line.objects.start.min(options.inline_objects.len()));
+        Cow::Owned(options)
+    }
+
+    /// The byte offset of the grapheme boundary nearest to `point` in the text laid out, going
+    /// by carets that lean with the font's caret slope.
+    pub fn hit_test<S: GlyphSource + ?Sized>(&self, source: &S, text: &str, point: (f32, f32)) -> usize {
+        let Some(line) = self.lines.get(self.line_at_y(point.1)) else {
+            return 0;
+        };
+        let options = self.line_options(line);
+        let point = (point.0, line.baseline - point.1);
+        line.range.start + hit_test_slanted_with(source, &text[line.range.clone()], point, self.px_size, &options)
+    }
+
+    /// Each inline object of the text with its pen position (on the baseline, y down from the
+    /// top of the text), where the caller draws it, see `InlineObject::rect`.
+    pub fn inline_objects<S: GlyphSource + ?Sized>(&self, source: &S, text: &str) -> Vec<(usize, (f32, f32))> {
+        let mut objects = Vec::new();
+        for line in self.lines.iter().filter(|line| !line.objects.is_empty()) {
+            let options = self.line_options(line);
+            let origin = (0., line.baseline);
+            let (glyphs, _) = layout_line_with(source, &text[line.range.clone()], origin, self.px_size, None, &options);
+            let placed = glyphs.iter().filter_map(|glyph| Some((line.objects.start + glyph.object?, glyph.position)));
+            objects.extend(placed);
+        }
+        objects
+    }
+
+    /// The index of the inline object under `point`, if any.
+    pub fn object_at<S: GlyphSource + ?Sized>(&self, source: &S, text: &str, point: (f32, f32)) -> Option<usize> {
+        let (x, y) = point;
+        self.inline_objects(source, text).into_iter().find_map(|(index, position)| {
+            let (left, top, width, height) = self.options.inline_objects[index].rect(position);
+            (x >= left && x < left + width && y >= top && y < top + height).then_some(index)
+        })
+    }
+
+    /// The top and bottom points of the caret at byte `offset`, as tall as the line and leaning
+    /// with the font's caret slope (see `render::slanted_caret`).
+    pub fn caret<S: GlyphSource + ?Sized>(&self, source: &S, text: &str, offset: usize) -> ((f32, f32), (f32, f32)) {
+        let Some(line) = self.lines.get(self.line_of_offset(offset)) else {
+            return ((0., 0.), (0., LineHeight::Normal.pixels(source, self.px_size)));
+        };
+        let offset = offset.clamp(line.range.start, line.range.end) - line.range.start;
+        let x = caret_position_with(source, &text[line.range.clone()], offset, self.px_size, &self.line_options(line));
+        slanted_caret(source, x, line.baseline, (line.top, line.top + line.height), self.px_size)
+    }
+
+    /// The rectangles (x, top, width, height) highlighting the byte `range` of the text, one
+    /// for each line it touches, as tall as the lines. A selection going on past the end of a
+    /// line covers the line to its width.
+    pub fn selection_rects<S: GlyphSource + ?Sized>(
+        &self,
+        source: &S,
+        text: &str,
+        range: Range<usize>,
+    ) -> Vec<(f32, f32, f32, f32)> {
+        let mut rects = Vec::new();
+        for line in &self.lines {
+            if range.end < line.range.start || range.start > line.range.end || range.is_empty() {
+                continue;
+            }
+            let (line_text, options) = (&text[line.range.clone()], self.line_options(line));
+            let start = range.start.max(line.range.start) - line.range.start;
+            let end = range.end.min(line.range.end) - line.range.start;
+            let left = caret_position_with(source, line_text, start, self.px_size, &options);
+            let right = match range.end > line.range.end {
+                true => line.width.max(left),
+                false => caret_position_with(source, line_text, end, self.px_size, &options),
+            };
+            if left != right {
+                rects.push((left.min(right), line.top, (right - left).abs(), line.height));
+            }
+        }
+        rects
+    }
+}
+
+/// The word wrap of `wrap_paragraph` with the advance of each character (and its byte offset)
+/// given by `advance`, for text mixing sizes or fonts. Lines break where `linebreak` allows it.
+/// The advance of a soft hyphen is the one of the hyphen it ends a line with, a line broken
+/// there keeps it and its width includes it.
+pub(crate) fn wrap_with_advances(text: &str, max_width: f32, advance: impl Fn(usize, char) -> f32) -> Vec<LineBox> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut lines = Vec::new();
+    let mut line_start = 0usize;
+    let mut x = 0.;
+    // byte offset after the last break opportunity of the current line, the width of the line
+    // broken there and whether that was after a space
+    let mut last_break: Option<(usize, f32, bool)> = None;
+    // the class of the last character of the line that has one for breaking
+    let mut previous: Option<BreakClass> = None;
+
+    let mut k = 0;
+    while let Some(&(i, c)) = chars.get(k) {
+        k += 1;
+        // selectors stay with the character before them and take no room
+        if is_variation_selector(c) {
+            continue;
+        }
+        let class = break_class(c);
+        let before = previous.replace(class);
+        match class {
+            BreakClass::SoftHyphen => {
+                let width = x + advance(i, c);
+                if i > line_start && width <= max_width {
+                    last_break = Some((i + c.len_utf8(), width, false));
+                }
+                continue;
+            }
+            BreakClass::Space => {
+                // in a run of spaces the line width stays the one before the first space
+                let width = match last_break {
+                    Some((at, width, true)) if at == i => width,
+                    _ => x,
+                };
+                last_break = Some((i + 1, width, true));
+                x += advance(i, c);
+                continue;
+            }
+            _ => {}
+        }
+        // spaces and soft hyphens made their own opportunity, or didn't fit
+        let opportunity = before.is_some_and(|before| {
+            !matches!(before, BreakClass::Space | BreakClass::SoftHyphen) && break_between(before, class)
+        });
+        if opportunity && i > line_start {
+            last_break = Some((i, x, false));
+        }
+        if is_invisible(class) {
+            continue;
+        }
+        let char_advance = advance(i, c);
+        if x + char_advance > max_width && i > line_start {
+            let (end, width) = match last_break {
+                Some((at, width, _)) => (at, width),
+                // a soft hyphen the break falls right after goes to the next line, it didn't fit
+                None => match line_start + text[line_start..i].trim_end_matches(SOFT_HYPHEN).len() {
+                    end if end > line_start => (end, x),
+                    _ => (i, x),
+                },
+            };
+            lines.push(LineBox {
+                range: line_start..end,
+                width,
+            });
+            // what comes after the break starts the next line, go over it again
+            line_start = end;
+            k = chars.partition_point(|(at, _)| *at < end);
+            x = 0.;
+            last_break = None;
+            previous = None;
+            continue;
+        }
+        x += char_advance;
+    }
+    let content_end = line_start + text[line_start..].trim_end_matches(' ').len();
+    let trailing_width: f32 = (content_end..text.len()).map(|at| advance(at, ' ')).sum();
+    let mut width = x - trailing_width;
+    if let Some(before) = text[line_start..].strip_suffix(SOFT_HYPHEN) {
+        width += advance(line_start + before.len(), SOFT_HYPHEN);
+    }
+    lines.push(LineBox {
+        range: line_start..text.len(),
+        width,
+    });
+    lines
+}
@@ -0,0 +1,127 @@
+pub mod autofit;
+pub mod arabic;
+pub mod avar;
+pub mod base;
+pub mod bitmap;
+pub mod cache;
+pub mod cmap;
+pub mod composite;
+pub mod contour_stats;
+pub mod debug_boxes;
+pub mod direct;
+pub mod duplicates;
+pub mod eblc;
+pub mod embedding;
+mod emoji;
+pub mod fixed;
+pub mod flatten;
+pub mod font;
+#[cfg(feature = "os-fonts")]
+pub mod font_database;
+#[cfg(feature = "freetype-compare")]
+pub mod ft_compare;
+pub mod fvar;
+pub mod geom;
+pub mod gdef;
+pub mod glyph;
+pub mod gpos;
+pub mod grapheme;
+pub mod gsub;
+pub mod gvar;
+pub mod hexbox;
+pub mod hinting;
+pub mod hvar;
+pub mod instance;
+pub mod item_variation;
+pub mod itemize;
+pub mod layout;
+pub mod linebreak;
+pub mod merge;
+pub mod metrics;
+pub mod name;
+pub mod number;
+pub mod os2;
+pub mod otlayout;
+pub mod outline;
+pub mod overlap;
+pub mod partial;
+pub mod path_effects;
+pub mod pdf;
+pub mod post;
+pub mod raster;
+pub mod raster_diff;
+pub mod reader;
+pub mod render;
+mod simd;
+pub mod sdf;
+pub mod shape_cache;
+pub mod shape_diff;
+pub mod size;
+pub mod source;
+pub mod stat;
+pub mod stats;
+pub mod stroke;
+pub mod style;
+pub mod styled;
+pub mod svg;
+#[cfg(any(test, feature = "test-fonts"))]
+pub mod test_fonts;
+pub mod text_svg;
+pub mod trace;
+pub mod trak;
+pub mod transform;
+mod unicode_tables;
+pub mod validate;
+pub mod vertical;
+pub mod winding;
+pub mod write;
+
+pub use bitmap::{Bitmap, BitmapFormat};
+pub use cache::ShardedCache;
+pub use cmap::CharMapping;
+pub use composite::{Component, GlyphDescription};
+pub use contour_stats::{ContourDirection, ContourStats};
+pub use direct::{PixelBuffer, RenderScratch};
+pub use embedding::{EmbeddingPermissions, EmbeddingUsage};
+pub use flatten::Contour;
+pub use fixed::{F2Dot14, Fixed};
+pub use font::{Font, ParseOptions};
+#[cfg(feature = "os-fonts")]
+pub use font_database::{FontDatabase, SystemFace};
+pub use gdef::GlyphClass;
+pub use fvar::{Axis, Instance};
+pub use glyph::GlyphData;
+pub use instance::InstanceOptions;
+pub use itemize::{Direction, Script, TextRun};
+pub use layout::{
+    GlyphOverride, InlineObject, LayoutOptions, LineBox, LineHeight, MissingGlyphStyle, PositionPolicy, TextLayout,
+    TextLine,
+};
+pub use merge::{ConflictPolicy, MergeOptions};
+pub use metrics::CellMetrics;
+pub use outline::{CubicContour, CubicSegment, Outline, OutlinePoint};
+pub use partial::PartialFont;
+pub use pdf::UnitRounding;
+pub use shape_cache::ShapeCache;
+pub use shape_diff::LineDiff;
+pub use size::FontSize;
+pub use source::GlyphSource;
+pub use stats::Stats;
+pub use raster::{FillRule, GlyphBitmap, Hinting, RasterBounds, RasterOptions, RenderMode, SubpixelOrder};
+pub use raster_diff::RasterDiff;
+pub use sdf::MsdfBitmap;
+pub use render::{GlyphBitmapCache, TextCaches};
+pub use stroke::{StrokeCap, StrokeJoin, StrokeOptions};
+pub use style::{FontCollectionIndex, FontStyle};
+pub use styled::{Span, StyledText};
+pub use text_svg::SvgTextOptions;
+pub use trace::{ShapingStep, ShapingTrace};
+pub use transform::FontUnitsToPixels;
+pub use validate::{Severity, ValidationReport};
+pub use write::FontBuilder;
+
+// a font is parsed once and shared between threads behind an Arc
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Font>();
+};
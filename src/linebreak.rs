@@ -0,0 +1,61 @@
+//! Where lines may break, a small part of UAX #14: after spaces, soft hyphens and zero width
+//! spaces, between ideographs (and between an ideograph and a letter), never at no-break spaces
+//! or word joiners nor before combining marks. Words of other scripts stay whole. `break_class`
+//! and `break_between` are what the wrapper asks, the full algorithm can take their place.
+
+use crate::grapheme::{grapheme_break, GraphemeBreak};
+use crate::itemize::Script;
+
+/// How a character takes part in line breaking, after the UAX #14 classes of the same names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BreakClass {
+    /// U+0020, lines break after a run of them, which hangs past the line end
+    Space,
+    /// U+200B, an invisible break opportunity
+    ZeroWidthSpace,
+    /// U+00AD, a break opportunity that shows a hyphen when taken
+    SoftHyphen,
+    /// No-break spaces (U+00A0, U+2007, U+202F): drawn like spaces, never broken at
+    Glue,
+    /// U+2060 and U+FEFF: invisible, keep the characters on both sides together
+    WordJoiner,
+    /// Marks and joiners, which stay with the character before them
+    CombiningMark,
+    /// Han, hiragana and katakana, lines can break before and after each
+    Ideographic,
+    /// Everything else, letters and digits staying together in words
+    Alphabetic,
+}
+
+pub fn break_class(c: char) -> BreakClass {
+    match c {
+        ' ' => BreakClass::Space,
+        '\u{200B}' => BreakClass::ZeroWidthSpace,
+        '\u{AD}' => BreakClass::SoftHyphen,
+        '\u{A0}' | '\u{2007}' | '\u{202F}' => BreakClass::Glue,
+        '\u{2060}' | '\u{FEFF}' => BreakClass::WordJoiner,
+        c if matches!(grapheme_break(c), GraphemeBreak::Extend | GraphemeBreak::Zwj | GraphemeBreak::SpacingMark) => {
+            BreakClass::CombiningMark
+        }
+        c if matches!(Script::of(c), Script::Han | Script::Hiragana | Script::Katakana) => BreakClass::Ideographic,
+        _ => BreakClass::Alphabetic,
+    }
+}
+
+/// Whether a line may break between a character of class `before` and one of class `after`.
+/// After a soft hyphen that's only when the hyphen fits, which the wrapper decides.
+pub fn break_between(before: BreakClass, after: BreakClass) -> bool {
+    use BreakClass::*;
+    match (before, after) {
+        (_, Space | ZeroWidthSpace | SoftHyphen | Glue | WordJoiner | CombiningMark) => false,
+        (Glue | WordJoiner, _) => false,
+        (Space | ZeroWidthSpace | SoftHyphen, _) => true,
+        (Ideographic, _) | (_, Ideographic) => true,
+        _ => false,
+    }
+}
+
+/// Characters drawn without a glyph and taking no room.
+pub fn is_invisible(class: BreakClass) -> bool {
+    matches!(class, BreakClass::ZeroWidthSpace | BreakClass::WordJoiner)
+}
@@ -1,252 +1,1744 @@
-
-use minifb::{MouseMode, Window, WindowOptions, ScaleMode, Scale};
-use anyhow::{anyhow, Result};
-use byteorder::{BigEndian, ReadBytesExt};
-use std::io::{Cursor, Seek, SeekFrom};
-use std::{collections::HashMap, fs::File, hash::Hash, io::Read};
+use minifb::{InputCallback, Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 
 use raqote::*;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::hint::black_box;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use font_rendering::grapheme::{grapheme_boundaries, next_grapheme_boundary, previous_grapheme_boundary};
+use font_rendering::itemize::{itemize, paragraph_direction};
+use font_rendering::number::fixed;
+use font_rendering::render::{layout_line_with, PositionedGlyph};
+use font_rendering::{
+    merge, name, Axis, Bitmap, BitmapFormat, Component, ConflictPolicy, ContourDirection, ContourStats, Direction, Font,
+    GlyphBitmapCache, GlyphDescription, Hinting, InlineObject, InstanceOptions, LayoutOptions, LineBox, MergeOptions,
+    MissingGlyphStyle, Outline, PixelBuffer, PositionPolicy, RasterOptions, RenderMode, RenderScratch, ShapeCache,
+    SubpixelOrder, SvgTextOptions, TextCaches,
+};
 
+/// Used without a font path when the builtin font isn't compiled in.
+const DEFAULT_FONT_PATH: &str = "Inconsolata-Regular.ttf"; // SourceCodePro-Regular.ttf
 const WIDTH: usize = 800;
 const HEIGHT: usize = 600;
 
-fn bit_is_set(flag: u8, flag_bit_index: u8) -> bool {
-    // 00100000, 6 -> 00000001 & 00000001
-    return ((flag >> flag_bit_index) & 1) == 1;
+const CELL_SIZE: f32 = 80.;
+const HEADER_HEIGHT: f32 = 40.;
+const STATUS_HEIGHT: f32 = 24.;
+
+const EDITOR_PX_SIZE: f32 = 16.;
+const EDITOR_MARGIN: f32 = 10.;
+const CARET_BLINK: Duration = Duration::from_millis(500);
+
+const TEXT_VIEW_PX_SIZE: f32 = 16.;
+const SCROLL_LINES: i32 = 3;
+
+fn black() -> SolidSource {
+    SolidSource::from_unpremultiplied_argb(0xff, 0, 0, 0)
 }
 
-fn get_coordinates(cursor: &mut Cursor<Vec<u8>>, flags: &Vec<u8>, is_x: bool) -> Result<Vec<i16>> {
-    let num_points = flags.len();
-    let mut coords: Vec<i16> = vec![0i16; num_points as usize];
+fn grey() -> SolidSource {
+    SolidSource::from_unpremultiplied_argb(0xff, 0x90, 0x90, 0x90)
+}
 
-    for i in 0..(num_points as usize) {
-        coords[i] = if i == 0 { 0 } else { coords[i - 1] };
-        let flag: u8 = flags[i as usize];
-        let on_curve = bit_is_set(flag, 0);
-        let mut is_short = false;
-        let mut is_positive_short = false;
-        if is_x {
-            is_short = bit_is_set(flag, 1);
-            is_positive_short = bit_is_set(flag, 4);
-        } else {
-            is_short = bit_is_set(flag, 2);
-            is_positive_short = bit_is_set(flag, 5);
+fn red() -> SolidSource {
+    SolidSource::from_unpremultiplied_argb(0xff, 0xd0, 0, 0)
+}
+
+fn codepoints_label(chars: &[char]) -> String {
+    chars
+        .iter()
+        .map(|c| format!("U+{:04X}", *c as u32))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Grid of every glyph in the font, labelled by index.
+fn draw_glyph_grid(dt: &mut DrawTarget, font: &Font, first_row: usize, columns: usize) {
+    let scale = font.scale_for_px(CELL_SIZE * 0.6);
+    let rows = ((HEIGHT as f32 - HEADER_HEIGHT) / CELL_SIZE) as usize;
+    let mut header = format!("{} glyphs - click one to inspect it", font.num_glyphs());
+    let axes = font.variation_axes();
+    if !axes.is_empty() {
+        header += &format!(" - axes: {}", axes_label(&axes));
+    }
+    font.draw_text(dt, &header, (10., 28.), 18., black());
+    for row in 0..rows {
+        for column in 0..columns {
+            let index = (first_row + row) * columns + column;
+            if index >= font.num_glyphs() as usize {
+                return;
+            }
+            let x = column as f32 * CELL_SIZE;
+            let y = HEADER_HEIGHT + row as f32 * CELL_SIZE;
+            let mut pb = PathBuilder::new();
+            pb.rect(x, y, CELL_SIZE, CELL_SIZE);
+            dt.stroke(&pb.finish(), &Source::Solid(grey()), &StrokeStyle::default(), &DrawOptions::new());
+
+            let outline = font.glyph_outline(index as u16).unwrap();
+            let path = outline.to_path(scale, (x + CELL_SIZE * 0.2, y + CELL_SIZE * 0.65));
+            dt.fill(&path, &Source::Solid(black()), &DrawOptions::new());
+            font.draw_text(dt, &index.to_string(), (x + 4., y + CELL_SIZE - 4.), 12., grey());
+        }
+    }
+}
+
+/// Printable ASCII laid out on the font's character cells, only available for monospaced fonts.
+fn draw_cell_grid(dt: &mut DrawTarget, font: &Font, px_size: f32) {
+    let Some(cell) = font.cell_metrics(px_size) else {
+        font.draw_text(dt, "Not a monospaced font", (10., 28.), 18., black());
+        return;
+    };
+    font.draw_text(
+        dt,
+        &format!("Cells of {:.1} x {:.1} px at {px_size} px", cell.width, cell.height),
+        (10., 28.),
+        18.,
+        black(),
+    );
+    let columns = ((WIDTH as f32 - 20.) / cell.width) as usize;
+    let mut buf = [0u8; 4];
+    for (i, c) in (0x20u8..=0x7E).map(|c| c as char).enumerate() {
+        let x = 10. + (i % columns) as f32 * cell.width;
+        let y = HEADER_HEIGHT + (i / columns) as f32 * cell.height;
+        let mut pb = PathBuilder::new();
+        pb.rect(x, y, cell.width, cell.height);
+        dt.stroke(&pb.finish(), &Source::Solid(grey()), &StrokeStyle::default(), &DrawOptions::new());
+        font.draw_text(dt, c.encode_utf8(&mut buf), (x, y + cell.baseline), px_size, black());
+    }
+}
+
+/// The same text rendered in grayscale on the left and with LCD subpixels on the right.
+fn draw_subpixel_comparison(dt: &mut DrawTarget, font: &Font, order: SubpixelOrder) {
+    font.draw_text(
+        dt,
+        &format!("Grayscale | Subpixel {order:?} (B to flip the order)"),
+        (10., 28.),
+        18.,
+        black(),
+    );
+    let mut pb = PathBuilder::new();
+    pb.move_to(WIDTH as f32 / 2., HEADER_HEIGHT);
+    pb.line_to(WIDTH as f32 / 2., HEIGHT as f32);
+    dt.stroke(&pb.finish(), &Source::Solid(grey()), &StrokeStyle::default(), &DrawOptions::new());
+
+    let lines = [
+        "The quick brown fox jumps over",
+        "the lazy dog. 0123456789",
+        "Sphinx of black quartz,",
+        "judge my vow! {[(<>)]}",
+    ];
+    let grayscale = RasterOptions::default();
+    let subpixel = RasterOptions::default().with_mode(RenderMode::Lcd(order));
+    let mut y = HEADER_HEIGHT + 20.;
+    for px_size in [10., 12., 14.] {
+        for line in lines {
+            font.draw_text_with(dt, line, (10., y), px_size, black(), &grayscale);
+            font.draw_text_with(dt, line, (WIDTH as f32 / 2. + 10., y), px_size, black(), &subpixel);
+            y += px_size * 1.5;
+        }
+        y += 10.;
+    }
+}
+
+/// The same line under each position policy, moving right a tenth of a pixel per frame on a
+/// baseline between pixels, with the box each one measures outlined: unrounded glyphs glide
+/// (from subpixel positioned bitmaps), snapped ones jump a pixel at a time.
+fn draw_positioning_demo(dt: &mut DrawTarget, font: &Font, frame: u64, cache: &GlyphBitmapCache) {
+    const TEXT: &str = "Sphinx of black quartz, judge my vow";
+    const PX_SIZE: f32 = 16.;
+    font.draw_text(dt, "Position policies, 0.1 px per frame (P to leave)", (10., 28.), 18., black());
+    let shift = (frame % 1000) as f32 * 0.1;
+    let policies = [
+        ("None", PositionPolicy::None),
+        ("SnapX", PositionPolicy::SnapX),
+        ("SnapXY", PositionPolicy::SnapXY),
+    ];
+    for (row, (label, position)) in policies.into_iter().enumerate() {
+        let baseline = HEADER_HEIGHT + 40. + row as f32 * 50. + 0.4;
+        font.draw_text(dt, label, (10., baseline), 14., grey());
+        let layout = LayoutOptions::default().with_position(position);
+        let origin = (90. + shift, baseline);
+        let (glyphs, end_x) = layout_line_with(font, TEXT, origin, PX_SIZE, None, &layout);
+        font.draw_glyphs_cached(dt, &glyphs, PX_SIZE, black(), cache);
+        let start_x = glyphs.first().map_or(origin.0, |glyph| glyph.position.0);
+        let top = glyphs.first().map_or(origin.1, |glyph| glyph.position.1) - PX_SIZE;
+        let mut pb = PathBuilder::new();
+        pb.rect(start_x, top, end_x - start_x, PX_SIZE * 1.3);
+        dt.stroke(&pb.finish(), &Source::Solid(grey()), &StrokeStyle::default(), &DrawOptions::new());
+    }
+}
+
+/// A waterfall of the same sizes under each hinting mode, stacked to compare how each fits the
+/// glyphs to the pixel grid.
+fn draw_hinting_waterfall(dt: &mut DrawTarget, font: &Font, cache: &GlyphBitmapCache) {
+    const TEXT: &str = "Hamburgefonstiv 0123456789";
+    const SIZES: [f32; 4] = [9., 11., 13., 16.];
+    let instructed = match font.table_data("fpgm").is_some() || font.table_data("prep").is_some() {
+        true => "has instructions",
+        false => "no fpgm or prep",
+    };
+    font.draw_text(dt, &format!("Hinting modes, the font {instructed} (H to leave)"), (10., 28.), 18., black());
+    let mut y = HEADER_HEIGHT + 10.;
+    for hinting in Hinting::ALL {
+        font.draw_text(dt, &format!("{hinting:?}"), (10., y + 14.), 14., grey());
+        y += 20.;
+        let options = RasterOptions::default().with_hinting(hinting);
+        for px_size in SIZES {
+            y += px_size * 1.3;
+            let layout = LayoutOptions::default();
+            let (glyphs, _) = layout_line_with(font, TEXT, (20., y), px_size, Some(&options), &layout);
+            font.draw_glyphs_cached_with(dt, &glyphs, px_size, black(), cache, hinting);
         }
+        y += 10.;
+    }
+}
 
-        // coordinate offset is 1 byte
-        if is_short {
-            let offset: u8 = cursor.read_u8()?;
-            let sign: i16 = if is_positive_short { 1 } else { -1 };
-            coords[i] += sign * (offset as i16);
-        } else if !is_positive_short {
-            // coordinate offset value is represented by 2 byes (signed)
-            coords[i] += cursor.read_i16::<BigEndian>()?;
+/// Characters typed into the window, collected by minifb's input callback.
+struct TextInput(Rc<RefCell<Vec<char>>>);
+
+impl InputCallback for TextInput {
+    fn add_char(&mut self, uni_char: u32) {
+        if let Some(c) = char::from_u32(uni_char).filter(|c| !c.is_control()) {
+            self.0.borrow_mut().push(c);
         }
     }
-    return Ok(coords);
 }
-#[derive(Debug)]
-struct GlyphData {
-    x_coords: Vec<i16>,
-    y_coords: Vec<i16>,
-    contour_end_indices: Vec<u16>,
-    is_simple: bool
+
+fn int_rect(x0: f32, y0: f32, x1: f32, y1: f32) -> IntRect {
+    IntRect::new(
+        IntPoint::new(x0.floor() as i32, y0.floor() as i32),
+        IntPoint::new(x1.ceil() as i32, y1.ceil() as i32),
+    )
+}
+
+/// The parts of the window that need to be drawn again this frame.
+#[derive(Debug, Default)]
+struct DirtyRegion {
+    rects: Vec<IntRect>,
+    everything: bool,
 }
 
-impl GlyphData {
-    fn from_cursor(cursor: &mut Cursor<Vec<u8>>) -> Result<GlyphData> {
-        //let num_contour_end_indices
-        let mut contour_end_indices: Vec<u16> = Vec::new();
+impl DirtyRegion {
+    fn add(&mut self, rect: IntRect) {
+        if !self.everything {
+            self.rects.push(rect);
+        }
+    }
+
+    fn invalidate_all(&mut self) {
+        self.everything = true;
+        self.rects.clear();
+    }
+
+    fn clear(&mut self) {
+        self.everything = false;
+        self.rects.clear();
+    }
+}
+
+/// A small plain text editor, the view dirty rect redraws are for.
+struct Editor {
+    lines: Vec<String>,
+    /// Line and byte offset in the line of the caret, always on a grapheme boundary
+    caret: (usize, usize),
+    caret_visible: bool,
+    last_blink: Instant,
+    first_line: usize,
+    was_mouse_down: bool,
+}
+
+impl Editor {
+    fn new() -> Editor {
+        Editor {
+            lines: vec!["Type here, Esc leaves the editor.".to_string(), String::new()],
+            caret: (1, 0),
+            caret_visible: true,
+            last_blink: Instant::now(),
+            first_line: 0,
+            was_mouse_down: false,
+        }
+    }
+
+    fn line_height(font: &Font) -> f32 {
+        (font.line_height() as f32 * font.scale_for_px(EDITOR_PX_SIZE)).ceil()
+    }
+
+    fn line_top(&self, font: &Font, line: usize) -> f32 {
+        HEADER_HEIGHT + (line as f32 - self.first_line as f32) * Editor::line_height(font)
+    }
+
+    fn line_baseline(&self, font: &Font, line: usize) -> f32 {
+        self.line_top(font, line) + font.ascender() as f32 * font.scale_for_px(EDITOR_PX_SIZE)
+    }
+
+    /// The caret offset in line `to` that is as many grapheme clusters in as the caret is in
+    /// line `from`, clamped to the end of the line.
+    fn same_column(&self, from: usize, to: usize) -> usize {
+        let column = grapheme_boundaries(&self.lines[from]).partition_point(|boundary| *boundary < self.caret.1);
+        grapheme_boundaries(&self.lines[to])
+            .get(column)
+            .copied()
+            .unwrap_or(self.lines[to].len())
+    }
+
+    fn caret_x(&self, font: &Font) -> f32 {
+        EDITOR_MARGIN + font.caret_position(&self.lines[self.caret.0], self.caret.1, EDITOR_PX_SIZE)
+    }
+
+    /// Where an edit at the caret can change a line from: the caret, unless right to left text
+    /// in the line moves the characters before it too.
+    fn edit_x(&self, font: &Font) -> f32 {
+        let line = &self.lines[self.caret.0];
+        if itemize(line).iter().all(|run| run.direction == Direction::LeftToRight) {
+            self.caret_x(font)
+        } else {
+            EDITOR_MARGIN
+        }
+    }
+
+    /// The top and bottom points of the caret, leaning with an italic font's caret slope.
+    fn caret_line(&self, font: &Font) -> ((f32, f32), (f32, f32)) {
+        let top = self.line_top(font, self.caret.0);
+        let (baseline, extent) = (self.line_baseline(font, self.caret.0), (top, top + Editor::line_height(font)));
+        let ((top_x, top), (bottom_x, bottom)) =
+            font.caret_line(&self.lines[self.caret.0], self.caret.1, EDITOR_PX_SIZE, baseline, extent);
+        ((EDITOR_MARGIN + top_x, top), (EDITOR_MARGIN + bottom_x, bottom))
+    }
+
+    fn caret_rect(&self, font: &Font) -> IntRect {
+        let ((top_x, top), (bottom_x, bottom)) = self.caret_line(font);
+        int_rect(top_x.min(bottom_x) - 1., top, top_x.max(bottom_x) + 2., bottom)
+    }
+
+    /// From `x` to the end of a line.
+    fn rest_of_line_rect(&self, font: &Font, line: usize, x: f32) -> IntRect {
+        let top = self.line_top(font, line);
+        int_rect(x - 1., top, WIDTH as f32, top + Editor::line_height(font))
+    }
+
+    /// A line and everything below it, for edits that move the following lines.
+    fn lines_from_rect(&self, font: &Font, line: usize) -> IntRect {
+        int_rect(0., self.line_top(font, line), WIDTH as f32, HEIGHT as f32 - STATUS_HEIGHT)
+    }
+
+    /// Move the caret, invalidating where it was and where it ends up.
+    fn move_caret(&mut self, font: &Font, caret: (usize, usize), dirty: &mut DirtyRegion) {
+        dirty.add(self.caret_rect(font));
+        self.caret = caret;
+        self.caret_visible = true;
+        self.last_blink = Instant::now();
+        dirty.add(self.caret_rect(font));
+    }
+
+    /// Move the caret a grapheme cluster to the right or left as the line is shown, through
+    /// right to left text the other way than through the rest. Past the end of the line the
+    /// caret goes to the line before or after, whichever that end is in the line's direction.
+    fn move_caret_sideways(&mut self, font: &Font, rightwards: bool, dirty: &mut DirtyRegion) {
+        let (line, at) = self.caret;
+        if let Some(at) = font.move_caret_visually(&self.lines[line], at, rightwards, EDITOR_PX_SIZE) {
+            self.move_caret(font, (line, at), dirty);
+        } else if rightwards == (paragraph_direction(&self.lines[line]) == Direction::LeftToRight) {
+            if line + 1 < self.lines.len() {
+                self.move_caret(font, (line + 1, 0), dirty);
+            }
+        } else if line > 0 {
+            self.move_caret(font, (line - 1, self.lines[line - 1].len()), dirty);
+        }
+    }
+
+    fn handle_input(&mut self, window: &Window, font: &Font, typed: &[char], dirty: &mut DirtyRegion) {
+        for c in typed {
+            let (line, at) = self.caret;
+            dirty.add(self.rest_of_line_rect(font, line, self.edit_x(font)));
+            self.lines[line].insert(at, *c);
+            dirty.add(self.rest_of_line_rect(font, line, self.edit_x(font)));
+            self.move_caret(font, (line, at + c.len_utf8()), dirty);
+        }
+        let (line, at) = self.caret;
+        let line_len = self.lines[line].len();
+        if window.is_key_pressed(Key::Enter, KeyRepeat::Yes) {
+            let rest = self.lines[line].split_off(at);
+            self.lines.insert(line + 1, rest);
+            dirty.add(self.lines_from_rect(font, line));
+            self.move_caret(font, (line + 1, 0), dirty);
+        } else if window.is_key_pressed(Key::Backspace, KeyRepeat::Yes) {
+            if at > 0 {
+                dirty.add(self.rest_of_line_rect(font, line, self.edit_x(font)));
+                let start = previous_grapheme_boundary(&self.lines[line], at);
+                self.lines[line].replace_range(start..at, "");
+                self.move_caret(font, (line, start), dirty);
+                dirty.add(self.rest_of_line_rect(font, line, self.edit_x(font)));
+            } else if line > 0 {
+                let removed = self.lines.remove(line);
+                let previous_len = self.lines[line - 1].len();
+                self.lines[line - 1] += &removed;
+                dirty.add(self.lines_from_rect(font, line - 1));
+                self.move_caret(font, (line - 1, previous_len), dirty);
+            }
+        } else if window.is_key_pressed(Key::Delete, KeyRepeat::Yes) {
+            if at < line_len {
+                dirty.add(self.rest_of_line_rect(font, line, self.edit_x(font)));
+                let end = next_grapheme_boundary(&self.lines[line], at);
+                self.lines[line].replace_range(at..end, "");
+                dirty.add(self.rest_of_line_rect(font, line, self.edit_x(font)));
+            } else if line + 1 < self.lines.len() {
+                let removed = self.lines.remove(line + 1);
+                self.lines[line] += &removed;
+                dirty.add(self.lines_from_rect(font, line));
+            }
+        } else if window.is_key_pressed(Key::Left, KeyRepeat::Yes) {
+            self.move_caret_sideways(font, false, dirty);
+        } else if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
+            self.move_caret_sideways(font, true, dirty);
+        } else if window.is_key_pressed(Key::Up, KeyRepeat::Yes) && line > 0 {
+            let at = self.same_column(line, line - 1);
+            self.move_caret(font, (line - 1, at), dirty);
+        } else if window.is_key_pressed(Key::Down, KeyRepeat::Yes) && line + 1 < self.lines.len() {
+            let at = self.same_column(line, line + 1);
+            self.move_caret(font, (line + 1, at), dirty);
+        } else if window.is_key_pressed(Key::Home, KeyRepeat::No) {
+            self.move_caret(font, (line, 0), dirty);
+        } else if window.is_key_pressed(Key::End, KeyRepeat::No) {
+            self.move_caret(font, (line, line_len), dirty);
+        }
+
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if mouse_down && !self.was_mouse_down {
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                let line = self.first_line + ((y - HEADER_HEIGHT).max(0.) / Editor::line_height(font)) as usize;
+                if y >= HEADER_HEIGHT && line < self.lines.len() {
+                    let point = (x - EDITOR_MARGIN, self.line_baseline(font, line) - y);
+                    let at = font.hit_test_slanted(&self.lines[line], point, EDITOR_PX_SIZE);
+                    self.move_caret(font, (line, at), dirty);
+                }
+            }
+        }
+        self.was_mouse_down = mouse_down;
 
-        let num_contour_end_indices = cursor.read_i16::<BigEndian>()?; //slice_as_u16_be!(data, &start_bytes);
-        cursor.seek(SeekFrom::Current(8))?; // skip bounding box for the character data
-        if num_contour_end_indices >= 0 {
-            for i in 0..num_contour_end_indices {
-                contour_end_indices.push(cursor.read_u16::<BigEndian>()?)
+        if let Some((_, scroll_y)) = window.get_scroll_wheel() {
+            if scroll_y < 0. && self.first_line + 1 < self.lines.len() {
+                self.first_line += 1;
+                dirty.invalidate_all();
+            } else if scroll_y > 0. && self.first_line > 0 {
+                self.first_line -= 1;
+                dirty.invalidate_all();
             }
+        }
 
-            let num_points = contour_end_indices.last().unwrap() + 1; // I'm guessing the last element in the contour indices represents the last point, and we just add one because points are indexed from 0
+        if self.last_blink.elapsed() >= CARET_BLINK {
+            self.caret_visible = !self.caret_visible;
+            self.last_blink = Instant::now();
+            dirty.add(self.caret_rect(font));
+        }
+    }
 
-            // get number of instructions and skip them (instruction : 1 byte)
-            let num_instructions = cursor.read_i16::<BigEndian>()?;
-            cursor.seek(SeekFrom::Current(num_instructions as i64));
+    /// Draw the lines (and caret) that intersect `clip`, or all of them.
+    fn draw(&self, dt: &mut DrawTarget, font: &Font, clip: Option<IntRect>) {
+        if clip.is_none() {
+            font.draw_text(dt, "Editor - Esc to leave", (10., 28.), 18., black());
+        }
+        let line_height = Editor::line_height(font);
+        let baseline = font.ascender() as f32 * font.scale_for_px(EDITOR_PX_SIZE);
+        for line in self.first_line..self.lines.len() {
+            let top = self.line_top(font, line);
+            if top >= HEIGHT as f32 - STATUS_HEIGHT {
+                break;
+            }
+            let bounds = int_rect(0., top, WIDTH as f32, top + line_height);
+            if clip.is_some_and(|clip| clip.intersection(&bounds).is_none()) {
+                continue;
+            }
+            font.draw_text(dt, &self.lines[line], (EDITOR_MARGIN, top + baseline), EDITOR_PX_SIZE, black());
+        }
+        if self.caret_visible && self.caret.0 >= self.first_line {
+            let ((top_x, top), (bottom_x, bottom)) = self.caret_line(font);
+            let mut pb = PathBuilder::new();
+            pb.move_to(top_x, top);
+            pb.line_to(top_x + 1., top);
+            pb.line_to(bottom_x + 1., bottom);
+            pb.line_to(bottom_x, bottom);
+            pb.close();
+            dt.fill(&pb.finish(), &Source::Solid(black()), &DrawOptions::new());
+        }
+    }
+}
 
-            // adding all of the flags
-            let mut flags: Vec<u8> = Vec::new();
+/// A text file wrapped to the window width. Paragraphs are only laid out once they scroll into
+/// view, and only the visible lines are drawn.
+struct TextView {
+    paragraphs: Vec<String>,
+    /// Line boxes of each paragraph, `None` until it's been laid out at the current wrap width
+    layouts: Vec<Option<Vec<LineBox>>>,
+    wrap_width: f32,
+    /// Paragraph and byte offset the first visible line starts at, which survives re-wrapping
+    top: (usize, usize),
+    glyph_cache: GlyphBitmapCache,
+    /// Stroke the layout boxes over the lines, see `Font::draw_debug_boxes`
+    debug_boxes: bool,
+}
 
-            let mut i = 0;
-            while i < num_points {
-                let flag: u8 = cursor.read_u8()?;
-                flags.push(flag);
+impl TextView {
+    /// Rasterizes the characters of the file up front, so the first frame only blits.
+    fn load(path: &str, font: &Font) -> std::io::Result<TextView> {
+        let text = std::fs::read_to_string(path)?;
+        let paragraphs: Vec<String> = text.lines().map(|line| line.replace('\t', "    ")).collect();
+        let glyph_cache = GlyphBitmapCache::new();
+        let chars: BTreeSet<char> = paragraphs.iter().flat_map(|paragraph| paragraph.chars()).collect();
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let _ = glyph_cache.prewarm_parallel(font, chars.into_iter(), &[TEXT_VIEW_PX_SIZE], threads, |_, _| {
+            ControlFlow::Continue(())
+        });
+        Ok(TextView {
+            layouts: vec![None; paragraphs.len()],
+            paragraphs,
+            wrap_width: 0.,
+            top: (0, 0),
+            glyph_cache,
+            debug_boxes: false,
+        })
+    }
 
-                // handle repeat
-                if bit_is_set(flag, 3) {
-                    let num_repetitions: u8 = cursor.read_u8()?;
-                    for j in 0..num_repetitions {
-                        flags.push(flag);
-                    }
-                    i += num_repetitions as u16;
+    fn line_height(font: &Font) -> f32 {
+        (font.line_height() as f32 * font.scale_for_px(TEXT_VIEW_PX_SIZE)).ceil()
+    }
+
+    fn set_wrap_width(&mut self, width: f32) {
+        if width != self.wrap_width {
+            self.wrap_width = width;
+            self.layouts.iter_mut().for_each(|layout| *layout = None);
+        }
+    }
+
+    fn lines(&mut self, font: &Font, paragraph: usize) -> &[LineBox] {
+        let (text, wrap_width) = (&self.paragraphs[paragraph], self.wrap_width);
+        self.layouts[paragraph].get_or_insert_with(|| font.wrap_paragraph(text, TEXT_VIEW_PX_SIZE, wrap_width))
+    }
+
+    /// The line of the top paragraph that contains the top byte offset.
+    fn top_line(&mut self, font: &Font) -> usize {
+        let (paragraph, offset) = self.top;
+        let lines = self.lines(font, paragraph);
+        lines.iter().rposition(|line| line.range.start <= offset).unwrap_or(0)
+    }
+
+    fn scroll(&mut self, font: &Font, delta: i32) {
+        if self.paragraphs.is_empty() {
+            return;
+        }
+        let (mut paragraph, mut line) = (self.top.0, self.top_line(font) as i32 + delta);
+        while line < 0 && paragraph > 0 {
+            paragraph -= 1;
+            line += self.lines(font, paragraph).len() as i32;
+        }
+        while line >= self.lines(font, paragraph).len() as i32 && paragraph + 1 < self.paragraphs.len() {
+            line -= self.lines(font, paragraph).len() as i32;
+            paragraph += 1;
+        }
+        let lines = self.lines(font, paragraph);
+        let line = line.clamp(0, lines.len() as i32 - 1) as usize;
+        self.top = (paragraph, lines[line].range.start);
+    }
+
+    fn visible_lines(height: f32, font: &Font) -> i32 {
+        ((height - HEADER_HEIGHT - STATUS_HEIGHT) / TextView::line_height(font)) as i32
+    }
+
+    /// Call `draw_line` with each line that fits above the status line and its origin, returns
+    /// how many there were.
+    fn for_each_visible_line(
+        &mut self,
+        font: &Font,
+        height: f32,
+        mut draw_line: impl FnMut(&TextView, &str, (f32, f32)),
+    ) -> usize {
+        let line_height = TextView::line_height(font);
+        let baseline = font.ascender() as f32 * font.scale_for_px(TEXT_VIEW_PX_SIZE);
+        let bottom = height - STATUS_HEIGHT;
+        let mut y = HEADER_HEIGHT;
+        let (mut paragraph, mut line) = (self.top.0, if self.paragraphs.is_empty() { 0 } else { self.top_line(font) });
+        let mut drawn = 0;
+        while paragraph < self.paragraphs.len() && y + line_height <= bottom {
+            let lines = self.lines(font, paragraph).to_vec();
+            for line_box in &lines[line..] {
+                if y + line_height > bottom {
+                    break;
                 }
-                i += 1;
+                let text = &self.paragraphs[paragraph][line_box.range.clone()];
+                draw_line(self, text, (EDITOR_MARGIN, y + baseline));
+                y += line_height;
+                drawn += 1;
             }
+            paragraph += 1;
+            line = 0;
+        }
+        drawn
+    }
 
-            let mut x_coords: Vec<i16> = get_coordinates(cursor, &flags, true)?;
-            let mut y_coords: Vec<i16> = get_coordinates(cursor, &flags, false)?;
+    fn header(&self, drawn: usize) -> String {
+        let laid_out = self.layouts.iter().filter(|layout| layout.is_some()).count();
+        format!(
+            "Paragraph {} of {}, {drawn} lines drawn, {laid_out} paragraphs laid out",
+            self.top.0 + 1,
+            self.paragraphs.len()
+        )
+    }
 
-            Ok(GlyphData {
-                x_coords,
-                y_coords,
-                contour_end_indices,
-                is_simple:true
-            })
+    fn draw(&mut self, dt: &mut DrawTarget, font: &Font, height: f32) {
+        let drawn = self.for_each_visible_line(font, height, |view, text, origin| {
+            let caches = TextCaches {
+                glyphs: Some(&view.glyph_cache),
+                shapes: None,
+            };
+            font.draw_text_cached(dt, text, origin, TEXT_VIEW_PX_SIZE, black(), caches);
+            if view.debug_boxes {
+                font.draw_debug_boxes(dt, text, origin, TEXT_VIEW_PX_SIZE, &LayoutOptions::default());
+            }
+        });
+        font.draw_text(dt, &self.header(drawn), (10., 28.), 18., black());
+    }
+
+    /// `draw` straight into the window's pixels, without a DrawTarget.
+    fn draw_direct(&mut self, target: &mut PixelBuffer, font: &Font, height: f32, scratch: &mut RenderScratch) {
+        let drawn = self.for_each_visible_line(font, height, |_, text, origin| {
+            font.render_text_into_with(target, text, TEXT_VIEW_PX_SIZE, origin, black(), scratch);
+        });
+        let header = self.header(drawn) + ", drawn directly";
+        font.render_text_into_with(target, &header, 18., (10., 28.), black(), scratch);
+    }
+}
+
+/// The bytes of a minifb window buffer, for drawing into it with `Font::render_text_into`.
+fn window_bytes(pixels: &mut [u32]) -> &mut [u8] {
+    // SAFETY: u8 has no alignment or invalid values, the bytes are those of the same pixels
+    unsafe { std::slice::from_raw_parts_mut(pixels.as_mut_ptr().cast::<u8>(), std::mem::size_of_val(pixels)) }
+}
+
+/// Status line at the bottom of the editor showing how long the last redraw took.
+fn draw_frame_stats(dt: &mut DrawTarget, font: &Font, render_time: Duration, dirty_rects: usize) {
+    let top = HEIGHT as f32 - STATUS_HEIGHT;
+    let white = SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff);
+    dt.fill_rect(0., top, WIDTH as f32, STATUS_HEIGHT, &Source::Solid(white), &DrawOptions::new());
+    let rects = if dirty_rects == 0 { "full redraw".to_string() } else { format!("{dirty_rects} dirty rects") };
+    let label = format!("render {:.3} ms, {rects}", render_time.as_secs_f64() * 1000.);
+    font.draw_text(dt, &label, (10., top + 17.), 14., grey());
+}
+
+/// Enlarged view of a single glyph with its points, and the components of a composite.
+fn draw_glyph_inspector(dt: &mut DrawTarget, font: &Font, index: u16) {
+    let outline = font.glyph_outline(index).unwrap();
+    let chars = font.chars_for_glyph(index);
+    let mut header = format!("Glyph {index}");
+    if let Some(name) = font.glyph_name(index) {
+        header += &format!("  '{name}'");
+    }
+    if !chars.is_empty() {
+        header += &format!("  {}", codepoints_label(&chars));
+    }
+    font.draw_text(dt, &header, (10., 28.), 18., black());
+
+    let scale = font.scale_for_px(HEIGHT as f32 * 0.6);
+    let origin = (WIDTH as f32 * 0.3, HEIGHT as f32 * 0.8);
+    let path = outline.to_path(scale, origin);
+    dt.fill(&path, &Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0xc0, 0xc0, 0xe0)), &DrawOptions::new());
+    dt.stroke(&path, &Source::Solid(black()), &StrokeStyle::default(), &DrawOptions::new());
+
+    if let Some(GlyphDescription::Composite(components)) = font.glyph_description(index) {
+        for (i, component) in components.iter().enumerate() {
+            let label = component_label(component);
+            font.draw_text(dt, &label, (10., 56. + i as f32 * 20.), 14., grey());
+        }
+    }
+    if let Some(glyph) = font.glyph(index) {
+        draw_contour_panel(dt, font, &glyph.contour_stats());
+    }
+
+    for point in outline.points() {
+        let x = origin.0 + point.x * scale;
+        let y = origin.1 - point.y * scale;
+        let color = if point.on_curve {
+            red()
         } else {
-            println!("Skipping compound glyph");
-            Ok(GlyphData {
-                x_coords: vec![],
-                y_coords: vec![],
-                contour_end_indices: vec![],
-                is_simple:false
-            })
-        }
-    }
-}
-/*
-#[derive(Debug)]
-struct FontHeader {
-    version: u32,
-    font_revision: u32,
-
-}*/
-
-#[derive(Debug)]
-struct Font {
-    tables: HashMap<String, (u32, u32, u32)>, // tag :(checkSum, offset, length)
-    glyph_data: Vec<GlyphData>,
-    cursor: Cursor<Vec<u8>>
-
-}
-
-impl Font {
-    pub fn read_truetype(filename: &str) -> Result<Font> {
-        if let Ok(mut font_file) = File::open(filename) {
-            let mut contents = Vec::<u8>::new();
-            font_file.read_to_end(&mut contents);
-            let file_len:usize = contents.len();
-            let mut cursor = Cursor::new(contents);
-            cursor.seek(SeekFrom::Current(4)); // Skip scaler type
-            let num_tables = cursor.read_u16::<BigEndian>()?;
-            println!("Font file has {num_tables} tables");
-            cursor.seek(SeekFrom::Current(2 + 2 + 2)); // Skip some of the fields in the file header
-
-            let mut tables: HashMap<String, (u32, u32, u32)> = HashMap::new();
-            for i in 0..num_tables {
-                // tag : 4 | checkSum : 4 | offset : 4 | length : 4
-                let mut buf = vec![0u8; 4];
-                cursor.read_exact(&mut buf)?;
-                let tag: String = String::from_utf8(buf).unwrap();
-                let check_sum = cursor.read_u32::<BigEndian>()?;
-                let offset = cursor.read_u32::<BigEndian>()?;
-                let length = cursor.read_u32::<BigEndian>()?;
-                println!(
-                    "Table directory with tag {tag} --- offset = {offset} | length = {length}"
-                );
-                tables.insert(tag, (check_sum, offset, length));
-            }
-
-            // get number of glyphs
-            let (_, maxp_table_offset, _) = tables.get("maxp").unwrap(); // TODO: Error handling on all of the unwraps
-            cursor.seek(SeekFrom::Start(*maxp_table_offset as u64 + 4)); // we skip 4 bytes here for the "version number"
-            let num_glyphs = cursor.read_u16::<BigEndian>()?;
-            println!("Font contains {num_glyphs} glyphs");
-
-            let (_, head_table_offset, _) = tables.get("head").unwrap();
-            cursor.seek(SeekFrom::Start((head_table_offset + 50) as u64)); // skip some 50 bytes of additional information
-
-            let use_two_byte_entry = cursor.read_i16::<BigEndian>()? == 0; // check if we use two bye entries (indexToLocFormat)
-
-            let (_, location_table_offset, _) = tables.get("loca").unwrap();
-
-            // working with the glyph table
-            let (_, glyph_table_offset, glyph_table_len) = tables.get("glyf").unwrap();
-            // cursor.seek(SeekFrom::Start(*glyph_table_offset as u64));
-
-            let mut glyph_locations: Vec<u64> = vec![0u64; num_glyphs as usize];
-            let mut glyph_data_list = Vec::<GlyphData>::new();
-
-            for i in 0..(num_glyphs as u64) {
-                cursor.seek(SeekFrom::Start(
-                    (*location_table_offset as u64 + i * (if use_two_byte_entry { 2 } else { 4 }))
-                        as u64,
-                ))?;
-
-                let glyph_start_offset = if use_two_byte_entry {
-                    cursor.read_u16::<BigEndian>()? as u32 * 2u32
-                } else {
-                    cursor.read_u32::<BigEndian>()?
+            SolidSource::from_unpremultiplied_argb(0xff, 0, 0x80, 0)
+        };
+        let mut pb = PathBuilder::new();
+        pb.rect(x - 2., y - 2., 4., 4.);
+        dt.fill(&pb.finish(), &Source::Solid(color), &DrawOptions::new());
+    }
+}
+
+/// The side panel of the inspector: two lines per contour, red for the suspicious ones.
+fn draw_contour_panel(dt: &mut DrawTarget, font: &Font, contours: &[ContourStats]) {
+    const PANEL_WIDTH: f32 = 260.;
+    const LINE_HEIGHT: f32 = 15.;
+    let x = WIDTH as f32 - PANEL_WIDTH;
+    let max_lines = ((HEIGHT as f32 - 80.) / LINE_HEIGHT) as usize;
+    let mut y = 56.;
+    for (i, contour) in contours.iter().enumerate() {
+        if (i + 1) * 2 > max_lines {
+            font.draw_text(dt, &format!("... {} more contours", contours.len() - i), (x, y), 12., grey());
+            break;
+        }
+        let direction = match contour.direction {
+            ContourDirection::Clockwise => "cw",
+            ContourDirection::CounterClockwise => "ccw",
+            ContourDirection::Degenerate => "flat",
+        };
+        let (on, off) = (contour.on_curve, contour.off_curve);
+        let first = format!("{i}: {} pts, {on} on {off} off, {direction}", contour.num_points);
+        let (x_min, y_min, x_max, y_max) = contour.bbox;
+        let start = if contour.starts_off_curve { " starts off" } else { "" };
+        let problems = contour.problems();
+        let (second, colors) = match problems.is_empty() {
+            true => {
+                let bbox = format!("({x_min}, {y_min})-({x_max}, {y_max})");
+                (format!("  @{} {bbox}{start}", contour.start_index), (black(), grey()))
+            }
+            false => (format!("  {}", problems.join(", ")), (red(), red())),
+        };
+        font.draw_text(dt, &first, (x, y), 12., colors.0);
+        font.draw_text(dt, &second, (x, y + LINE_HEIGHT), 12., colors.1);
+        y += 2. * LINE_HEIGHT;
+    }
+}
+
+/// "glyph 36 at (120, 430)", with the matrix when it isn't the identity.
+fn component_label(component: &Component) -> String {
+    let [xx, yx, xy, yy, dx, dy] = component.transform;
+    let mut label = match component.anchor_points {
+        Some((parent, child)) => format!("glyph {} point {child} on point {parent}", component.glyph_index),
+        None => format!("glyph {} at ({dx}, {dy})", component.glyph_index),
+    };
+    if [xx, yx, xy, yy] != [1., 0., 0., 1.] {
+        label += &format!(" [{xx:.3} {yx:.3} {xy:.3} {yy:.3}]");
+    }
+    label
+}
+
+fn axes_label(axes: &[Axis]) -> String {
+    axes.iter()
+        .filter(|axis| !axis.hidden)
+        .map(|axis| format!("{} {}-{}", axis.tag, axis.min, axis.max))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `info [font] [--stats] [--glyph <index>]`: names, tables, duplicate outlines, embedded bitmap
+/// sizes and for variable fonts the axes and named instances. `--stats` adds the parse timings,
+/// `--glyph` prints the contours of one glyph instead.
+fn info(path: Option<&str>, stats: bool, glyph: Option<u16>) -> i32 {
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    if let Some(index) = glyph {
+        return glyph_info(font, index);
+    }
+    for (label, name_id) in [("Family", name::FAMILY_NAME), ("Style", name::SUBFAMILY_NAME), ("Version", name::VERSION)] {
+        println!("{label}: {}", font.name(name_id).unwrap_or("-"));
+    }
+    println!("Glyphs: {}, units per em: {}", font.num_glyphs(), font.units_per_em());
+    println!("Revision: {}", fixed(font.font_revision().to_f32(), 3));
+    let style = font.style();
+    let slope = if style.is_italic { "italic" } else { "upright" };
+    println!("Weight: {}, width: {}, {slope}", style.weight, style.width);
+    println!("Tables: {}", font.table_tags().join(" "));
+    println!("Embedding: {}", font.embedding_permissions());
+    let duplicates = font.find_duplicate_glyphs();
+    if !duplicates.is_empty() {
+        println!("Duplicate outlines:");
+        for group in &duplicates {
+            let glyphs: Vec<String> = group
+                .iter()
+                .map(|index| match font.glyph_name(*index) {
+                    Some(name) => format!("{index} ({name})"),
+                    None => index.to_string(),
+                })
+                .collect();
+            println!("  {}", glyphs.join(", "));
+        }
+    }
+    let strikes = font.bitmap_strike_sizes();
+    if !strikes.is_empty() {
+        let sizes: Vec<String> = strikes.iter().map(|ppem| ppem.to_string()).collect();
+        println!("Bitmap strikes (ppem): {}", sizes.join(" "));
+    }
+    let axes = font.variation_axes();
+    if !axes.is_empty() {
+        println!("Axes:");
+        for axis in &axes {
+            let hidden = if axis.hidden { " (hidden)" } else { "" };
+            println!("  {} {}: {} to {}, default {}{hidden}", axis.tag, axis.name, axis.min, axis.max, axis.default);
+        }
+        println!("Named instances:");
+        for instance in font.named_instances() {
+            let coords: Vec<String> =
+                axes.iter().zip(&instance.coords).map(|(axis, value)| format!("{}={value}", axis.tag)).collect();
+            println!("  {}: {}", instance.name, coords.join(" "));
+        }
+    }
+    if stats {
+        println!("{}", font.stats());
+    }
+    0
+}
+
+/// `info --glyph <index>`: the kind of glyph, then its components or the stats of each contour.
+fn glyph_info(font: &Font, index: u16) -> i32 {
+    let Some(glyph) = font.glyph(index) else {
+        eprintln!("error: the font has no glyph {index}, it has {}", font.num_glyphs());
+        return 1;
+    };
+    let name = font.glyph_name(index).map(|name| format!(" '{name}'")).unwrap_or_default();
+    println!("Glyph {index}{name}: bbox ({}, {})-({}, {})", glyph.x_min, glyph.y_min, glyph.x_max, glyph.y_max);
+    if let Some(GlyphDescription::Composite(components)) = font.glyph_description(index) {
+        println!("Composite of {} components:", components.len());
+        for component in &components {
+            println!("  {}", component_label(component));
+        }
+        return 0;
+    }
+    println!("{} contours, {} points:", glyph.num_contours(), glyph.num_points());
+    for (i, contour) in glyph.contour_stats().iter().enumerate() {
+        println!("  {i}: {contour}");
+    }
+    0
+}
+
+/// `compare-ft [font] [--size <ppem>] [--threshold <pixels>]`: compare the metrics of every
+/// glyph with FreeType's at one size, print the glyphs that differ and fail when one differs by
+/// more than the threshold (a pixel by default).
+#[cfg(feature = "freetype-compare")]
+fn compare_ft(path: Option<&str>, ppem: u32, threshold: f32) -> i32 {
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    let comparisons = match font.compare_metrics_with_freetype(ppem) {
+        Ok(comparisons) => comparisons,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return 1;
+        }
+    };
+    println!("glyph   advance (ours/ft)   size (ours/ft)      left (ours/ft)  top (ours/ft)  deviation");
+    let mut failed = 0;
+    let mut worst = 0f32;
+    for comparison in &comparisons {
+        let deviation = comparison.max_deviation();
+        worst = worst.max(deviation);
+        // the advances are computed in floats on both sides, ignore their rounding noise
+        if deviation < 0.01 {
+            continue;
+        }
+        let (ours, ft) = (&comparison.ours, &comparison.freetype);
+        let marker = if deviation > threshold { " !" } else { "" };
+        println!(
+            "{:<7} {:>8}/{:<8}  {:>3}x{:<3}/{:>3}x{:<3}  {:>5}/{:<5}     {:>5}/{:<5}   {}{marker}",
+            comparison.index,
+            fixed(ours.advance, 3),
+            fixed(ft.advance, 3),
+            ours.width,
+            ours.height,
+            ft.width,
+            ft.height,
+            ours.left,
+            ft.left,
+            ours.top,
+            ft.top,
+            fixed(deviation, 3),
+        );
+        if deviation > threshold {
+            failed += 1;
+        }
+    }
+    let glyphs = comparisons.len();
+    let (threshold, worst) = (fixed(threshold, 3), fixed(worst, 3));
+    println!("{glyphs} glyphs at {ppem} ppem, {failed} off by more than {threshold} px, worst {worst} px");
+    i32::from(failed > 0)
+}
+
+/// `shape-diff <old> <new> --text-file <samples> [--size <px>] [--tolerance <px>] [--glyphs]`:
+/// lay out every line of the samples with both fonts and print the lines that differ, with
+/// `--glyphs` the glyphs that do. Sizes are 1000 px by default, the units of a font with 1000
+/// units per em. Fails when a line's glyphs change or its positions move by more than the
+/// tolerance (0 by default).
+fn shape_diff(paths: [&str; 2], text_file: &str, px_size: f32, tolerance: f32, glyphs: bool) -> i32 {
+    let [Some(old), Some(new)] = paths.map(|path| open_font(Some(path))) else {
+        return 1;
+    };
+    let text = match std::fs::read_to_string(text_file) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("error: {text_file} : {err}");
+            return 1;
+        }
+    };
+    let diffs = old.diff_shaping(new, &text, px_size);
+    let mut failed = 0;
+    for diff in &diffs {
+        let deviation = diff.max_deviation();
+        let marker = if deviation > tolerance { " !" } else { "" };
+        let change = match diff.glyphs_differ() {
+            true => "glyphs changed".to_string(),
+            false => format!("off by up to {}", fixed(deviation, 3)),
+        };
+        let (old_width, new_width) = (fixed(diff.old_width, 3), fixed(diff.new_width, 3));
+        println!("line {}: width {old_width} -> {new_width}, {change}{marker}: {:?}", diff.line, diff.text);
+        if glyphs {
+            let describe = |glyph: Option<&PositionedGlyph>| match glyph {
+                Some(glyph) => format!(
+                    "glyph {} for byte {} at {},{} advance {}",
+                    glyph.index,
+                    glyph.cluster,
+                    fixed(glyph.position.0, 3),
+                    fixed(glyph.position.1, 3),
+                    fixed(glyph.advance, 3)
+                ),
+                None => "nothing".to_string(),
+            };
+            for (i, old, new) in diff.glyph_changes() {
+                println!("  {i}: {} -> {}", describe(old), describe(new));
+            }
+        }
+        if deviation > tolerance {
+            failed += 1;
+        }
+    }
+    let lines = text.lines().count();
+    println!("{lines} lines, {} differ, {failed} by more than {tolerance} px", diffs.len());
+    i32::from(failed > 0)
+}
+
+/// `raster-diff [font] --text <text> --left <options> --right <options> [--size <px>] [--out
+/// <diff.png>] [--threshold <difference>]`: draw the text with two sets of raster options (see
+/// `RasterOptions::from_str`), print how much the pixels differ and write the heatmap. Fails
+/// when a pixel channel differs by more than the threshold, 0 to 255 (0 by default).
+fn raster_diff(
+    path: Option<&str>,
+    text: &str,
+    px_size: f32,
+    options: [&str; 2],
+    output: Option<&str>,
+    threshold: u8,
+) -> i32 {
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    let parsed = options.map(|options| options.parse::<RasterOptions>().map_err(|err| (options, err)));
+    let [left, right] = match parsed {
+        [Ok(left), Ok(right)] => [left, right],
+        [Err((options, err)), _] | [_, Err((options, err))] => {
+            eprintln!("error: {options} : {err}");
+            return 2;
+        }
+    };
+    let (_, _, diff) = font.compare_raster_options(text, px_size, &left, &right);
+    println!("left:  {left:?}");
+    println!("right: {right:?}");
+    println!(
+        "{}x{} pixels, {} changed, max difference {}, mean difference {}",
+        diff.heatmap.width,
+        diff.heatmap.height,
+        diff.changed_pixels,
+        diff.max_difference,
+        fixed(diff.mean_difference as f32, 3)
+    );
+    if let Some(output) = output {
+        if let Err(err) = diff.heatmap.write_png(output) {
+            eprintln!("error: {output} : {err}");
+            return 1;
+        }
+    }
+    i32::from(diff.max_difference > threshold)
+}
+
+/// `bench [font]`: time drawing a paragraph by filling glyph paths with raqote, by rasterizing
+/// every glyph with the crate's rasterizer and by blitting cached glyph bitmaps, then a frame of
+/// repeated labels with and without a shape cache, and an atlas of the paragraph's glyphs at
+/// three sizes sharing flattened outlines.
+fn bench(path: Option<&str>) -> i32 {
+    const PARAGRAPH: &str = "The quick brown fox jumps over the lazy dog. Pack my box with five dozen liquor jugs.";
+    const LINES: usize = 30;
+    const ROUNDS: u32 = 20;
+    const LABELS: usize = 100;
+    const LABEL_TEXTS: [&str; 4] = ["File", "Edit", "Glyph 1234 (uni00E9)", "Zoom: 100%"];
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    let cache = GlyphBitmapCache::new();
+    let options = RasterOptions::default();
+    let time = |draw: &dyn Fn(&mut DrawTarget, (f32, f32))| {
+        let mut dt = DrawTarget::new(WIDTH as i32, HEIGHT as i32);
+        let started = Instant::now();
+        for _ in 0..ROUNDS {
+            for line in 0..LINES {
+                draw(&mut dt, (EDITOR_MARGIN, HEADER_HEIGHT + line as f32 * 19.));
+            }
+        }
+        started.elapsed() / ROUNDS
+    };
+    let paths = time(&|dt, origin| {
+        font.draw_text(dt, PARAGRAPH, origin, TEXT_VIEW_PX_SIZE, black());
+    });
+    let rasterized = time(&|dt, origin| {
+        font.draw_text_with(dt, PARAGRAPH, origin, TEXT_VIEW_PX_SIZE, black(), &options);
+    });
+    let cached = time(&|dt, origin| {
+        let caches = TextCaches {
+            glyphs: Some(&cache),
+            shapes: None,
+        };
+        font.draw_text_cached(dt, PARAGRAPH, origin, TEXT_VIEW_PX_SIZE, black(), caches);
+    });
+    println!("Per paragraph of {LINES} lines");
+    for (label, elapsed) in [("Paths", paths), ("Rasterized", rasterized), ("Cached bitmaps", cached)] {
+        println!("{label}: {elapsed:?}, {:.1}x the paths", paths.as_secs_f64() / elapsed.as_secs_f64());
+    }
+
+    let shapes = RefCell::new(ShapeCache::default());
+    let time_labels = |shaped: bool| {
+        let mut dt = DrawTarget::new(WIDTH as i32, HEIGHT as i32);
+        let started = Instant::now();
+        for _ in 0..ROUNDS {
+            for label in 0..LABELS {
+                let origin = ((label % 5) as f32 * 150. + 10., (label / 5) as f32 * 28. + HEADER_HEIGHT);
+                let text = LABEL_TEXTS[label % LABEL_TEXTS.len()];
+                let mut shapes = shaped.then(|| shapes.borrow_mut());
+                let caches = TextCaches {
+                    glyphs: Some(&cache),
+                    shapes: shapes.as_deref_mut(),
                 };
+                font.draw_text_cached(&mut dt, text, origin, 14., black(), caches);
+            }
+        }
+        started.elapsed() / ROUNDS
+    };
+    let (unshaped, shaped) = (time_labels(false), time_labels(true));
+    println!("Per frame of {LABELS} labels");
+    println!("Laid out every frame: {unshaped:?}");
+    println!("Shape cache: {shaped:?}, {:.1}x faster", unshaped.as_secs_f64() / shaped.as_secs_f64());
 
-                let glyph_offset = *glyph_table_offset + glyph_start_offset;
-                if glyph_offset as usize > file_len {
-                    return Err(anyhow!("Glyph offset beyond file size: offset = {}, file size = {}", glyph_offset, file_len));
-                }
+    let long_paragraph = PARAGRAPH.repeat(LINES * 4);
+    let time_mapping = |batched: bool| {
+        let started = Instant::now();
+        for _ in 0..ROUNDS {
+            if batched {
+                black_box(font.map_str(&long_paragraph));
+            } else {
+                black_box(long_paragraph.chars().map(|c| font.glyph_index_for_char(c)).collect::<Vec<_>>());
+            }
+        }
+        started.elapsed() / ROUNDS
+    };
+    let (per_char, batched) = (time_mapping(false), time_mapping(true));
+    println!("Mapping {} characters", long_paragraph.chars().count());
+    println!("Per character lookups: {per_char:?}");
+    println!("map_str: {batched:?}, {:.1}x faster", per_char.as_secs_f64() / batched.as_secs_f64());
 
-                glyph_locations[i as usize] = glyph_offset as u64;
+    // a font of its own, the glyphs above are flattened already
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    let glyphs: BTreeSet<u16> = PARAGRAPH.chars().filter_map(|c| font.glyph_index_for_char(c)).collect();
+    let started = Instant::now();
+    for px_size in [16., 17., 18.] {
+        for index in &glyphs {
+            black_box(font.rasterize_glyph_with(*index, px_size, &options));
+        }
+    }
+    let stats = font.stats();
+    println!("Atlas of {} glyphs at 16, 17 and 18px: {:?}", glyphs.len(), started.elapsed());
+    println!("Flattened {} times, reused {} times", stats.flatten_cache_misses, stats.flatten_cache_hits);
+    0
+}
 
+/// `tofu [font] <output.png>`: draw a line of characters the font is unlikely to have with each
+/// missing glyph style, one under the other.
+fn tofu(path: Option<&str>, output: &str) -> i32 {
+    const TEXT: &str = "Tofu: \u{0F3A}\u{16A0}\u{2615}\u{A4D0}\u{1F600}\u{10348} end";
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    let mut dt = DrawTarget::new(WIDTH as i32, 160);
+    dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+    let styles = [
+        ("NotDef", MissingGlyphStyle::NotDef),
+        ("HexBox", MissingGlyphStyle::HexBox),
+        ("Skip", MissingGlyphStyle::Skip),
+    ];
+    for (row, (label, style)) in styles.into_iter().enumerate() {
+        let y = 40. + row as f32 * 45.;
+        font.draw_text(&mut dt, label, (10., y), 16., grey());
+        let layout = LayoutOptions::default().with_missing_glyph(style);
+        font.draw_text_with_layout(&mut dt, TEXT, (100., y), 32., black(), &layout);
+    }
+    let bitmap = Bitmap::from_argb_premultiplied(WIDTH, 160, dt.get_data());
+    match bitmap.write_png(output) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {output} : {err}");
+            1
+        }
+    }
+}
+
+/// `effects [font] <output.png>`: "BOLD" as drawn, outset and inset by 30 units, with its
+/// corners rounded, and both, one row each.
+fn path_effects(path: Option<&str>, output: &str) -> i32 {
+    const TEXT: &str = "BOLD";
+    const PX_SIZE: f32 = 64.;
+    const AMOUNT: f32 = 30.;
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    type Effect = fn(&Outline) -> Outline;
+    let rows: [(&str, Effect); 5] = [
+        ("As drawn", |outline| outline.clone()),
+        ("Outset 30", |outline| outline.offset(AMOUNT)),
+        ("Inset 30", |outline| outline.offset(-AMOUNT)),
+        ("Rounded 60", |outline| outline.round_corners(2. * AMOUNT)),
+        ("Both", |outline| outline.round_corners(2. * AMOUNT).offset(AMOUNT)),
+    ];
+    let height = 20 + rows.len() as i32 * 90;
+    let mut dt = DrawTarget::new(WIDTH as i32, height);
+    dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+    let scale = font.scale_for_px(PX_SIZE);
+    for (row, (label, effect)) in rows.iter().enumerate() {
+        let baseline = 85. + row as f32 * 90.;
+        font.draw_text(&mut dt, label, (10., baseline), 16., grey());
+        let mut x = 140.;
+        for c in TEXT.chars() {
+            let index = font.glyph_index_for_char(c).unwrap_or(0);
+            if let Some(outline) = font.glyph_outline(index) {
+                let path = effect(&outline).to_path(scale, (x, baseline));
+                dt.fill(&path, &Source::Solid(black()), &DrawOptions::new());
             }
+            x += (font.advance_width(index) as f32 + 2. * AMOUNT) * scale;
+        }
+    }
+    let bitmap = Bitmap::from_argb_premultiplied(WIDTH, height as usize, dt.get_data());
+    match bitmap.write_png(output) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {output} : {err}");
+            1
+        }
+    }
+}
 
+/// `--export-svg-text <output.svg>`: write the text file (or a pangram) laid out like the text
+/// view as an SVG document instead of opening the window.
+fn export_svg_text(font: &Font, text_file: Option<&str>, output: &str) -> i32 {
+    let text = match text_file.map(std::fs::read_to_string) {
+        Some(Ok(text)) => text,
+        Some(Err(err)) => {
+            eprintln!("error: {} : {err}", text_file.unwrap_or_default());
+            return 1;
+        }
+        None => "The quick brown fox jumps over the lazy dog.".to_string(),
+    };
+    let options = SvgTextOptions::default().with_max_width(Some(WIDTH as f32 - 2. * EDITOR_MARGIN));
+    match std::fs::write(output, font.text_to_svg(&text, TEXT_VIEW_PX_SIZE, &options)) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {output} : {err}");
+            1
+        }
+    }
+}
 
-            for i in 0..(num_glyphs as u64) {
+/// `validate [font]`: print the validation report, the exit code is 1 when the font has errors.
+fn validate(path: Option<&str>) -> i32 {
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    let report = font.validate();
+    println!("{report}");
+    if report.has_errors() {
+        1
+    } else {
+        0
+    }
+}
 
-                cursor.seek(SeekFrom::Start(
-                    (*glyph_locations.get(i as usize).unwrap()),
-                ));
-                match GlyphData::from_cursor(&mut cursor) {
-                    Ok(glyph_data) => {
-                        println!("{i} \n");
-                        glyph_data_list.push(glyph_data);
-                    }
-                    Err(err) => println!("Error : {err}"),
-                }
+/// `instance <font> <output> [tag=value ...] [--ignore-permissions]`: write a static font pinned
+/// at the given axis values, e.g. `instance Variable.ttf Semibold.ttf wght=600`, and with
+/// `--ignore-permissions` even when its fsType doesn't allow editing it.
+fn instance(path: &str, output: &str, settings: &[String], options: &InstanceOptions) -> i32 {
+    let font = match Font::read_truetype(path) {
+        Ok(font) => font,
+        Err(err) => {
+            eprintln!("error: {path} : {err}");
+            return 1;
+        }
+    };
+    let mut coords = Vec::with_capacity(settings.len());
+    for setting in settings {
+        match setting.split_once('=').and_then(|(tag, value)| Some((tag, value.parse::<f32>().ok()?))) {
+            Some(coord) => coords.push(coord),
+            None => {
+                eprintln!("error: expected tag=value, got {setting}");
+                return 1;
             }
+        }
+    }
+    match font.instance_with(&coords, options).and_then(|bytes| Ok(std::fs::write(output, bytes)?)) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {output} : {err}");
+            1
+        }
+    }
+}
 
+/// `merge <base> <addition> <output> [--prefer-addition] [--scale] [--ignore-permissions]`:
+/// append the glyphs of one font to another, `--scale` allows fonts with different units per
+/// em and `--ignore-permissions` fonts whose fsType doesn't allow editing them.
+fn merge_fonts(paths: &[String], flags: &[String]) -> i32 {
+    let [base, addition, output] = paths else {
+        eprintln!("usage: merge <base> <addition> <output> [--prefer-addition] [--scale] [--ignore-permissions]");
+        return 2;
+    };
+    let has_flag = |name: &str| flags.iter().any(|flag| flag == name);
+    let conflicts = match has_flag("--prefer-addition") {
+        true => ConflictPolicy::PreferAddition,
+        false => ConflictPolicy::KeepBase,
+    };
+    let options = MergeOptions::default()
+        .with_conflicts(conflicts)
+        .with_scale_to_base_units(has_flag("--scale"))
+        .with_ignore_embedding_permissions(has_flag("--ignore-permissions"));
+    let fonts = Font::read_truetype(base).and_then(|base| Ok((base, Font::read_truetype(addition)?)));
+    let result = fonts
+        .and_then(|(base, addition)| merge::merge_with(&base, &addition, &options))
+        .and_then(|bytes| Ok(std::fs::write(output, bytes)?));
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {output} : {err}");
+            1
+        }
+    }
+}
 
+/// The font at `path`, or without a path the builtin font (with the `embedded-font` feature) or
+/// else Inconsolata-Regular.ttf from the working directory. Fonts live as long as the program.
+fn open_font(path: Option<&str>) -> Option<&'static Font> {
+    #[cfg(feature = "embedded-font")]
+    if path.is_none() {
+        return Some(Font::builtin());
+    }
+    let path = path.unwrap_or(DEFAULT_FONT_PATH);
+    match Font::read_truetype(path) {
+        Ok(font) => Some(Box::leak(Box::new(font))),
+        Err(err) => {
+            eprintln!("error: {path} : {err}");
+            None
+        }
+    }
+}
 
+/// `trace [font] [--text <text>] [--size <px>]`: print what laying out the line did, glyph
+/// by glyph, see `ShapingTrace` (which says why Latin text shows no liga ligatures).
+fn shaping_trace(path: Option<&str>, text: &str, px_size: f32) -> i32 {
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    print!("{}", font.shaping_trace(text, px_size, &LayoutOptions::default()));
+    0
+}
 
-            println!("Number of tables : {num_tables}");
-            return Ok(Font {
-                tables,
-                glyph_data:glyph_data_list,
-                cursor
-            });
-        } else {
-            println!("Failed to read file contents");
-            Err(anyhow!("Failed to read file contents"))
+/// `inline [font] <output.png> [--boxes]`: a wrapped paragraph with two colored squares laid
+/// out in it as inline objects, the taller one making its line taller. `--boxes` strokes the
+/// layout boxes over it (see `debug_boxes`), a picture of them to compare between versions.
+fn inline_objects(path: Option<&str>, output: &str, boxes: bool) -> i32 {
+    const TEXT: &str = "Press the \u{FFFC} button to go on, or the bigger \u{FFFC} one to start over.";
+    const PX_SIZE: f32 = 28.;
+    const ORIGIN: (f32, f32) = (20., 20.);
+    let Some(font) = open_font(path) else {
+        return 1;
+    };
+    let colors = [
+        SolidSource::from_unpremultiplied_argb(0xff, 0xe0, 0x40, 0x30),
+        SolidSource::from_unpremultiplied_argb(0xff, 0x30, 0x70, 0xe0),
+    ];
+    let layout_options = LayoutOptions::default()
+        .with_inline_objects(vec![
+            InlineObject { width: 20., height: 20., baseline_offset: 0. },
+            InlineObject { width: 48., height: 56., baseline_offset: 12. },
+        ])
+        .with_debug_boxes(boxes);
+    let layout = font.layout_text(TEXT, PX_SIZE, 420., &layout_options);
+    let height = (ORIGIN.1 * 2. + layout.height()).ceil() as i32;
+    let mut dt = DrawTarget::new(WIDTH as i32, height);
+    dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+    font.draw_layout(&mut dt, TEXT, &layout, ORIGIN, black());
+    for (index, position) in layout.inline_objects(font, TEXT) {
+        let (x, top, width, height) = layout.options.inline_objects[index].rect(position);
+        let mut pb = PathBuilder::new();
+        pb.rect(ORIGIN.0 + x, ORIGIN.1 + top, width, height);
+        dt.fill(&pb.finish(), &Source::Solid(colors[index % colors.len()]), &DrawOptions::new());
+        let center = (x + width / 2., top + height / 2.);
+        let hit = layout.object_at(font, TEXT, center);
+        println!("object {index} at {position:?}, hit test at its center: {hit:?}");
+    }
+    let bitmap = Bitmap::from_argb_premultiplied(WIDTH, height as usize, dt.get_data());
+    match bitmap.write_png(output) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {output} : {err}");
+            1
         }
     }
 }
 
+/// `--family <name>`: the file of the regular face of an installed family, from the fonts in
+/// the system font directories.
+#[cfg(feature = "os-fonts")]
+fn family_path(family: &str) -> Option<String> {
+    match font_rendering::FontDatabase::scan_system().find_family(family, 400, false) {
+        Some(path) => Some(path.to_string_lossy().into_owned()),
+        None => {
+            eprintln!("error: no installed font of the family {family}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "os-fonts"))]
+fn family_path(family: &str) -> Option<String> {
+    eprintln!("error: --family {family} needs the os-fonts feature");
+    None
+}
+
 fn main() {
-    //let font_read = Font::read_truetype("Inconsolata-Regular.ttf"); //SourceCodePro-Regular.ttf
-    //let font_read = Font::read_truetype("SourceCodePro-Regular.ttf"); //s
+    let mut font_path: Option<String> = None;
+    let mut text_file: Option<String> = None;
+    let mut print_stats = false;
+    let mut svg_output: Option<String> = None;
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("validate") => {
+            args.next();
+            let path = args.next();
+            std::process::exit(validate(path.as_deref()));
+        }
+        Some("bench") => {
+            args.next();
+            let path = args.next();
+            std::process::exit(bench(path.as_deref()));
+        }
+        Some("tofu") => {
+            args.next();
+            let (path, output) = match (args.next(), args.next()) {
+                (Some(path), Some(output)) => (Some(path), output),
+                (Some(output), None) => (None, output),
+                _ => {
+                    eprintln!("usage: tofu [font] <output.png>");
+                    std::process::exit(2);
+                }
+            };
+            std::process::exit(tofu(path.as_deref(), &output));
+        }
+        Some("effects") => {
+            args.next();
+            let (path, output) = match (args.next(), args.next()) {
+                (Some(path), Some(output)) => (Some(path), output),
+                (Some(output), None) => (None, output),
+                _ => {
+                    eprintln!("usage: effects [font] <output.png>");
+                    std::process::exit(2);
+                }
+            };
+            std::process::exit(path_effects(path.as_deref(), &output));
+        }
+        Some("inline") => {
+            args.next();
+            let (flags, mut paths): (Vec<String>, Vec<String>) = args.partition(|arg| arg.starts_with("--"));
+            let (path, output) = match (paths.pop(), paths.pop(), paths.is_empty()) {
+                (Some(output), path, true) => (path, output),
+                _ => {
+                    eprintln!("usage: inline [font] <output.png> [--boxes]");
+                    std::process::exit(2);
+                }
+            };
+            let boxes = flags.iter().any(|flag| flag == "--boxes");
+            std::process::exit(inline_objects(path.as_deref(), &output, boxes));
+        }
+        Some("info") => {
+            args.next();
+            let (mut path, mut stats, mut glyph) = (None, false, None);
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--stats" => stats = true,
+                    "--glyph" => match args.next().map(|index| index.parse::<u16>()) {
+                        Some(Ok(index)) => glyph = Some(index),
+                        _ => {
+                            eprintln!("usage: info [font] [--stats] [--glyph <index>]");
+                            std::process::exit(2);
+                        }
+                    },
+                    _ => path = Some(arg),
+                }
+            }
+            std::process::exit(info(path.as_deref(), stats, glyph));
+        }
+        Some("trace") => {
+            args.next();
+            let (mut path, mut text, mut size) = (None, String::from("ffi AV"), 32.);
+            while let Some(arg) = args.next() {
+                let parsed = match arg.as_str() {
+                    "--text" => args.next().map(|value| text = value),
+                    "--size" => args.next().and_then(|value| value.parse().ok()).map(|value| size = value),
+                    _ => {
+                        path = Some(arg);
+                        Some(())
+                    }
+                };
+                if parsed.is_none() {
+                    eprintln!("usage: trace [font] [--text <text>] [--size <px>]");
+                    std::process::exit(2);
+                }
+            }
+            std::process::exit(shaping_trace(path.as_deref(), &text, size));
+        }
+        Some("instance") => {
+            args.next();
+            let (Some(path), Some(output)) = (args.next(), args.next()) else {
+                eprintln!("usage: instance <font> <output> [tag=value ...] [--ignore-permissions]");
+                std::process::exit(2);
+            };
+            let (flags, settings): (Vec<String>, Vec<String>) = args.partition(|arg| arg.starts_with("--"));
+            let ignore_permissions = flags.iter().any(|flag| flag == "--ignore-permissions");
+            let options = InstanceOptions::default().with_ignore_embedding_permissions(ignore_permissions);
+            std::process::exit(instance(&path, &output, &settings, &options));
+        }
+        #[cfg(feature = "freetype-compare")]
+        Some("compare-ft") => {
+            args.next();
+            let (mut path, mut size, mut threshold) = (None, 16, 1.);
+            while let Some(arg) = args.next() {
+                let parsed = match arg.as_str() {
+                    "--size" => args.next().and_then(|value| value.parse().ok()).map(|value| size = value),
+                    "--threshold" => args.next().and_then(|value| value.parse().ok()).map(|value| threshold = value),
+                    _ => {
+                        path = Some(arg);
+                        Some(())
+                    }
+                };
+                if parsed.is_none() {
+                    eprintln!("usage: compare-ft [font] [--size <ppem>] [--threshold <pixels>]");
+                    std::process::exit(2);
+                }
+            }
+            std::process::exit(compare_ft(path.as_deref(), size, threshold));
+        }
+        Some("shape-diff") => {
+            args.next();
+            let usage = "usage: shape-diff <old> <new> --text-file <samples> \
+                [--size <px>] [--tolerance <px>] [--glyphs]";
+            let (mut paths, mut text_file, mut size, mut tolerance, mut glyphs) = (Vec::new(), None, 1000., 0., false);
+            while let Some(arg) = args.next() {
+                let parsed = match arg.as_str() {
+                    "--text-file" => args.next().map(|path| text_file = Some(path)),
+                    "--size" => args.next().and_then(|value| value.parse().ok()).map(|value| size = value),
+                    "--tolerance" => args.next().and_then(|value| value.parse().ok()).map(|value| tolerance = value),
+                    "--glyphs" => {
+                        glyphs = true;
+                        Some(())
+                    }
+                    _ => {
+                        paths.push(arg);
+                        Some(())
+                    }
+                };
+                if parsed.is_none() {
+                    eprintln!("{usage}");
+                    std::process::exit(2);
+                }
+            }
+            let (Some(text_file), [old, new]) = (text_file, paths.as_slice()) else {
+                eprintln!("{usage}");
+                std::process::exit(2);
+            };
+            std::process::exit(shape_diff([old, new], &text_file, size, tolerance, glyphs));
+        }
+        Some("raster-diff") => {
+            args.next();
+            let usage = "usage: raster-diff [font] --text <text> --left <options> --right <options> \
+                [--size <px>] [--out <diff.png>] [--threshold <difference>]";
+            let (mut path, mut text, mut size, mut output, mut threshold) = (None, None, 16., None, 0);
+            let (mut left, mut right) = (None, None);
+            while let Some(arg) = args.next() {
+                let parsed = match arg.as_str() {
+                    "--text" => args.next().map(|value| text = Some(value)),
+                    "--left" => args.next().map(|value| left = Some(value)),
+                    "--right" => args.next().map(|value| right = Some(value)),
+                    "--out" => args.next().map(|value| output = Some(value)),
+                    "--size" => args.next().and_then(|value| value.parse().ok()).map(|value| size = value),
+                    "--threshold" => args.next().and_then(|value| value.parse().ok()).map(|value| threshold = value),
+                    _ => {
+                        path = Some(arg);
+                        Some(())
+                    }
+                };
+                if parsed.is_none() {
+                    eprintln!("{usage}");
+                    std::process::exit(2);
+                }
+            }
+            let (Some(text), Some(left), Some(right)) = (text, left, right) else {
+                eprintln!("{usage}");
+                std::process::exit(2);
+            };
+            let options = [left.as_str(), right.as_str()];
+            std::process::exit(raster_diff(path.as_deref(), &text, size, options, output.as_deref(), threshold));
+        }
+        Some("merge") => {
+            args.next();
+            let (flags, paths): (Vec<String>, Vec<String>) = args.partition(|arg| arg.starts_with("--"));
+            std::process::exit(merge_fonts(&paths, &flags));
+        }
+        _ => {}
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--text-file" => text_file = args.next(),
+            "--stats" => print_stats = true,
+            "--export-svg-text" => svg_output = args.next(),
+            "--family" => match args.next().as_deref().map(family_path) {
+                Some(Some(path)) => font_path = Some(path),
+                _ => std::process::exit(1),
+            },
+            _ => font_path = Some(arg),
+        }
+    }
+    let Some(font) = open_font(font_path.as_deref()) else {
+        std::process::exit(1);
+    };
+    if let Some(output) = svg_output {
+        std::process::exit(export_svg_text(font, text_file.as_deref(), &output));
+    }
+    // parse the glyphs in the background, the grid parses whatever it shows on demand meanwhile
+    let (progress_sender, progress) = mpsc::channel();
+    std::thread::spawn(move || {
+        // stops once the window is gone and nobody listens anymore
+        font.parse_all_glyphs_with(|done, total| {
+            if progress_sender.send((done, total)).is_err() {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+    });
+    let mut text_view = text_file.map(|path| TextView::load(&path, font).unwrap());
+    let mut show_text = text_view.is_some();
+    // the text view drawn with render_text_into, see TextView::draw_direct
+    let mut draw_direct = false;
+    let mut direct_scratch = RenderScratch::new(256);
+    let mut window_pixels: Vec<u32> = Vec::new();
 
     let mut window = Window::new("Text renderer", WIDTH, HEIGHT, WindowOptions {
+        resize: true,
         ..WindowOptions::default()
-    }).unwrap();
-
+    })
+    .unwrap();
 
-    let size = window.get_size();
+    let mut size = window.get_size();
     let mut dt = DrawTarget::new(size.0 as i32, size.1 as i32);
-    loop {
-        dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
-        let mut pb = PathBuilder::new();
-        if let Some(pos) = window.get_mouse_pos(MouseMode::Clamp) {
+    let columns = (WIDTH as f32 / CELL_SIZE) as usize;
+    let mut first_row = 0usize;
+    let mut inspected: Option<u16> = None;
+    let mut show_cells = false;
+    let mut show_subpixel = false;
+    let mut show_positioning = false;
+    let positioning_cache = GlyphBitmapCache::new();
+    let mut show_hinting = false;
+    let hinting_cache = GlyphBitmapCache::new();
+    let mut frame_count = 0u64;
+    let mut subpixel_order = SubpixelOrder::Rgb;
+    let mut was_mouse_down = false;
+    let mut editor: Option<Editor> = None;
+    let mut dirty = DirtyRegion::default();
+    let typed = Rc::new(RefCell::new(Vec::new()));
+    window.set_input_callback(Box::new(TextInput(typed.clone())));
 
-            pb.rect(pos.0, pos.1, 100., 130.);
-            let path = pb.finish();
-            dt.fill(&path, &Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0, 0xff, 0)), &DrawOptions::new());
+    while window.is_open() {
+        frame_count += 1;
+        if let Some((done, total)) = progress.try_iter().last() {
+            if done < total {
+                window.set_title(&format!("Text renderer - parsing glyphs {done}/{total}"));
+            } else {
+                window.set_title("Text renderer");
+            }
+        }
+        if window.get_size() != size {
+            size = window.get_size();
+            dt = DrawTarget::new(size.0 as i32, size.1 as i32);
+            dirty.invalidate_all();
+        }
+        let typed_chars: Vec<char> = typed.borrow_mut().drain(..).collect();
+        let mut leaving_editor = false;
+        if let Some(text_editor) = &mut editor {
+            if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+                editor = None;
+                leaving_editor = true;
+            } else {
+                text_editor.handle_input(&window, font, &typed_chars, &mut dirty);
+            }
+        } else {
+            dirty.invalidate_all();
+        }
+        if let Some(text_editor) = &editor {
+            let started = Instant::now();
+            let dirty_rects = dirty.rects.len();
+            if dirty.everything {
+                dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+                text_editor.draw(&mut dt, font, None);
+            } else {
+                let white = SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff);
+                for rect in &dirty.rects {
+                    dt.push_clip_rect(*rect);
+                    dt.fill_rect(
+                        rect.min.x as f32,
+                        rect.min.y as f32,
+                        rect.width() as f32,
+                        rect.height() as f32,
+                        &Source::Solid(white),
+                        &DrawOptions::new(),
+                    );
+                    text_editor.draw(&mut dt, font, Some(*rect));
+                    dt.pop_clip();
+                }
+            }
+            draw_frame_stats(&mut dt, font, started.elapsed(), dirty_rects);
+            dirty.clear();
+            let frame = Bitmap::from_argb_premultiplied(size.0, size.1, dt.get_data()).convert(BitmapFormat::Xrgb, 4);
+            window.update_with_buffer(&frame.to_u32_pixels(), size.0, size.1).unwrap();
+            continue;
+        }
 
+        if let Some(view) = text_view.as_mut().filter(|_| show_text) {
+            view.set_wrap_width(size.0 as f32 - 2. * EDITOR_MARGIN);
+            let page = TextView::visible_lines(size.1 as f32, font) - 1;
+            if let Some((_, scroll_y)) = window.get_scroll_wheel() {
+                view.scroll(font, if scroll_y < 0. { SCROLL_LINES } else { -SCROLL_LINES });
+            }
+            if window.is_key_pressed(Key::PageDown, KeyRepeat::Yes) {
+                view.scroll(font, page.max(1));
+            }
+            if window.is_key_pressed(Key::PageUp, KeyRepeat::Yes) {
+                view.scroll(font, -page.max(1));
+            }
+            if window.is_key_pressed(Key::T, KeyRepeat::No) {
+                show_text = false;
+            }
+            if window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+                break;
+            }
+            if window.is_key_pressed(Key::D, KeyRepeat::No) {
+                draw_direct = !draw_direct;
+            }
+            if window.is_key_pressed(Key::X, KeyRepeat::No) {
+                view.debug_boxes = !view.debug_boxes;
+            }
+            if draw_direct {
+                window_pixels.clear();
+                window_pixels.resize(size.0 * size.1, 0x00ff_ffff);
+                let bytes = window_bytes(&mut window_pixels);
+                let mut target = PixelBuffer::new(bytes, size.0, size.1, size.0 * 4, BitmapFormat::Xrgb).unwrap();
+                view.draw_direct(&mut target, font, size.1 as f32, &mut direct_scratch);
+                dirty.clear();
+                window.update_with_buffer(&window_pixels, size.0, size.1).unwrap();
+                continue;
+            }
+            dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+            view.draw(&mut dt, font, size.1 as f32);
+            dirty.clear();
+            let frame = Bitmap::from_argb_premultiplied(size.0, size.1, dt.get_data()).convert(BitmapFormat::Xrgb, 4);
+            window.update_with_buffer(&frame.to_u32_pixels(), size.0, size.1).unwrap();
+            continue;
+        }
+        if window.is_key_pressed(Key::T, KeyRepeat::No) && text_view.is_some() && inspected.is_none() {
+            show_text = true;
+            continue;
+        }
+        if window.is_key_pressed(Key::E, KeyRepeat::No) && inspected.is_none() {
+            editor = Some(Editor::new());
+            dirty.invalidate_all();
+            continue;
+        }
+        if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            show_cells = !show_cells;
+        }
+        if window.is_key_pressed(Key::L, KeyRepeat::No) {
+            show_subpixel = !show_subpixel;
+        }
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            show_positioning = !show_positioning;
+        }
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            show_hinting = !show_hinting;
+        }
+        if show_subpixel && window.is_key_pressed(Key::B, KeyRepeat::No) {
+            subpixel_order = match subpixel_order {
+                SubpixelOrder::Rgb => SubpixelOrder::Bgr,
+                SubpixelOrder::Bgr => SubpixelOrder::Rgb,
+            };
+        }
+        if !leaving_editor && window.is_key_pressed(Key::Escape, KeyRepeat::No) {
+            // escape leaves the inspector first, then closes the viewer
+            if inspected.take().is_none() {
+                break;
+            }
+        }
+        if let Some(index) = inspected {
+            if window.is_key_pressed(Key::Right, KeyRepeat::Yes) && index + 1 < font.num_glyphs() {
+                inspected = Some(index + 1);
+            }
+            if window.is_key_pressed(Key::Left, KeyRepeat::Yes) && index > 0 {
+                inspected = Some(index - 1);
+            }
+        } else if !show_cells && !show_subpixel && !show_positioning && !show_hinting {
+            if let Some((_, scroll_y)) = window.get_scroll_wheel() {
+                if scroll_y < 0. {
+                    first_row += 1;
+                } else if scroll_y > 0. {
+                    first_row = first_row.saturating_sub(1);
+                }
+            }
+            let mouse_down = window.get_mouse_down(MouseButton::Left);
+            if mouse_down && !was_mouse_down {
+                if let Some(pos) = window.get_mouse_pos(MouseMode::Discard) {
+                    if pos.1 > HEADER_HEIGHT {
+                        let column = (pos.0 / CELL_SIZE) as usize;
+                        let row = ((pos.1 - HEADER_HEIGHT) / CELL_SIZE) as usize + first_row;
+                        let index = row * columns + column;
+                        if index < font.num_glyphs() as usize {
+                            inspected = Some(index as u16);
+                        }
+                    }
+                }
+            }
+            was_mouse_down = mouse_down;
+        }
 
-            window.update_with_buffer(dt.get_data(), size.0, size.1).unwrap();
+        dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+        match inspected {
+            Some(index) => draw_glyph_inspector(&mut dt, font, index),
+            None if show_hinting => draw_hinting_waterfall(&mut dt, font, &hinting_cache),
+            None if show_positioning => draw_positioning_demo(&mut dt, font, frame_count, &positioning_cache),
+            None if show_subpixel => draw_subpixel_comparison(&mut dt, font, subpixel_order),
+            None if show_cells => draw_cell_grid(&mut dt, font, 32.),
+            None => draw_glyph_grid(&mut dt, font, first_row, columns),
         }
+        dirty.clear();
+        let frame = Bitmap::from_argb_premultiplied(size.0, size.1, dt.get_data()).convert(BitmapFormat::Xrgb, 4);
+        window.update_with_buffer(&frame.to_u32_pixels(), size.0, size.1).unwrap();
+    }
+    if print_stats {
+        println!("{}", font.stats());
     }
 }
@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+use crate::font::Font;
+use crate::glyph::{
+    ARGS_ARE_XY_VALUES, ARG_1_AND_2_ARE_WORDS, MORE_COMPONENTS, WE_HAVE_AN_X_AND_Y_SCALE, WE_HAVE_A_SCALE,
+    WE_HAVE_A_TWO_BY_TWO, WE_HAVE_INSTRUCTIONS,
+};
+use crate::hinting::read_u16_at;
+use crate::write::FontBuilder;
+
+/// Tables of the base font indexed by glyph that the merge doesn't extend to the added glyphs.
+const GLYPH_INDEXED_TABLES: [&str; 4] = ["hdmx", "LTSH", "vhea", "vmtx"];
+
+/// post format 3: no glyph names. Format 2 names only cover the base font's glyphs.
+const POST_FORMAT_3: u32 = 0x0003_0000;
+const POST_HEADER_LENGTH: usize = 32;
+
+/// maxp 1.0 fields from maxPoints to maxComponentDepth, all limits the merged font takes the
+/// larger of.
+const MAXP_LIMITS: std::ops::Range<usize> = 6..32;
+
+/// Which glyph a character gets when both fonts map it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    #[default]
+    KeepBase,
+    PreferAddition,
+}
+
+/// How `merge_with` merges. Made with `default` (the base font's glyph for characters both map,
+/// same units per em, permissions honored) and the `with_*` methods, fields can be added without
+/// breaking callers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MergeOptions {
+    pub conflicts: ConflictPolicy,
+    /// Scale the added glyphs to the base font's units per em when the two differ, instead of
+    /// failing
+    pub scale_to_base_units: bool,
+    /// Merge even when the embedding permissions of either font don't allow editing it, see
+    /// `EmbeddingPermissions::allows_editing`
+    pub ignore_embedding_permissions: bool,
+}
+
+impl MergeOptions {
+    pub fn with_conflicts(mut self, conflicts: ConflictPolicy) -> Self {
+        self.conflicts = conflicts;
+        self
+    }
+
+    pub fn with_scale_to_base_units(mut self, scale_to_base_units: bool) -> Self {
+        self.scale_to_base_units = scale_to_base_units;
+        self
+    }
+
+    pub fn with_ignore_embedding_permissions(mut self, ignore_embedding_permissions: bool) -> Self {
+        self.ignore_embedding_permissions = ignore_embedding_permissions;
+        self
+    }
+}
+
+/// Merge `addition` into `base` with the default options, see `merge_with`.
+pub fn merge(base: &Font, addition: &Font) -> Result<Vec<u8>> {
+    merge_with(base, addition, &MergeOptions::default())
+}
+
+/// Append the glyphs of `addition` after the glyphs of `base` and write the union as a TrueType
+/// font: composite components are renumbered, hmtx and cmap extended, head's bounding box and
+/// maxp's limits cover both fonts. Everything else (names, layout tables, hinting programs) is
+/// the base font's, so the added glyphs lose their instructions and the cmap keeps only its
+/// format 4 and 12 mappings. Fails when the embedding permissions of either font don't allow
+/// editing it.
+pub fn merge_with(base: &Font, addition: &Font, options: &MergeOptions) -> Result<Vec<u8>> {
+    if base.is_variable() {
+        return Err(anyhow!("Merging into a variable font isn't supported"));
+    }
+    if !options.ignore_embedding_permissions {
+        base.embedding_permissions().check_editing("Merging into the base font")?;
+        addition.embedding_permissions().check_editing("Merging the added font")?;
+    }
+    let scale = match (base.units_per_em(), addition.units_per_em()) {
+        (base_units, added_units) if base_units == added_units => None,
+        (base_units, added_units) if options.scale_to_base_units => Some(base_units as f32 / added_units as f32),
+        (base_units, added_units) => {
+            return Err(anyhow!(
+                "Units per em differ: {base_units} in the base font, {added_units} in the addition"
+            ))
+        }
+    };
+    let offset = base.num_glyphs();
+    let total = offset as usize + addition.num_glyphs() as usize;
+    if total > u16::MAX as usize {
+        return Err(anyhow!("The merged font would have {total} glyphs"));
+    }
+    let scaled = |value: i16| match scale {
+        Some(scale) => (value as f32 * scale).round() as i16,
+        None => value,
+    };
+
+    let mut glyphs: Vec<Vec<u8>> = Vec::with_capacity(total);
+    let mut metrics = Vec::with_capacity(total);
+    for index in 0..offset {
+        glyphs.push(base.raw_glyph(index).unwrap_or_default().to_vec());
+        metrics.push((base.advance_width(index), base.left_side_bearing(index)));
+    }
+    for index in 0..addition.num_glyphs() {
+        let raw = addition.raw_glyph(index).unwrap_or_default();
+        let glyph = addition.glyph(index).ok_or_else(|| anyhow!("No glyph {index} in the addition"))?;
+        glyphs.push(if raw.is_empty() {
+            Vec::new()
+        } else if glyph.is_simple {
+            let mut glyph = glyph.clone();
+            for coord in glyph.x_coords.iter_mut().chain(glyph.y_coords.iter_mut()) {
+                *coord = scaled(*coord);
+            }
+            glyph.instructions.clear();
+            glyph.to_bytes()
+        } else {
+            renumber_components(raw, offset, scale)
+                .ok_or_else(|| anyhow!("Glyph {index} of the addition is truncated"))?
+        });
+        let advance = match scale {
+            Some(scale) => (addition.advance_width(index) as f32 * scale).round() as u16,
+            None => addition.advance_width(index),
+        };
+        metrics.push((advance, scaled(addition.left_side_bearing(index))));
+    }
+
+    let mut mapping = BTreeMap::new();
+    for index in 0..offset {
+        for c in base.chars_for_glyph(index) {
+            mapping.insert(c, index);
+        }
+    }
+    for index in 0..addition.num_glyphs() {
+        for c in addition.chars_for_glyph(index) {
+            if options.conflicts == ConflictPolicy::PreferAddition || !mapping.contains_key(&c) {
+                mapping.insert(c, offset + index);
+            }
+        }
+    }
+    let mapping: Vec<(char, u16)> = mapping.into_iter().collect();
+
+    let glyph_data: Vec<&[u8]> = glyphs.iter().map(Vec::as_slice).collect();
+    let mut builder = FontBuilder::from_font(base);
+    for tag in GLYPH_INDEXED_TABLES {
+        builder.remove_table(tag);
+    }
+    builder
+        .set_glyphs(&glyph_data)?
+        .set_horizontal_metrics(&metrics)?
+        .set_cmap(&mapping);
+    if let (Some(maxp), Some(added)) = (builder.table_mut("maxp"), addition.table_data("maxp")) {
+        let length = maxp.len().min(added.len());
+        for at in MAXP_LIMITS.step_by(2).filter(|at| at + 2 <= length) {
+            let limit = read_u16_at(maxp, at).max(read_u16_at(added, at));
+            maxp[at..at + 2].copy_from_slice(&limit.to_be_bytes());
+        }
+    }
+    if let Some(post) = builder.table_mut("post") {
+        if post.len() > POST_HEADER_LENGTH {
+            post.truncate(POST_HEADER_LENGTH);
+            post[..4].copy_from_slice(&POST_FORMAT_3.to_be_bytes());
+        }
+    }
+    builder.build()
+}
+
+/// A composite glyph record with its component glyph indices moved up by `offset` and its
+/// instructions dropped. With `scale` the bounding box and the component offsets are scaled,
+/// offsets are then always written as words. `None` when the record is truncated.
+fn renumber_components(glyph: &[u8], offset: u16, scale: Option<f32>) -> Option<Vec<u8>> {
+    let scaled = |value: i16| match scale {
+        Some(scale) => (value as f32 * scale).round() as i16,
+        None => value,
+    };
+    let mut data = glyph.get(..2)?.to_vec();
+    for at in (2..10).step_by(2) {
+        data.extend_from_slice(&scaled(read_u16_at(glyph.get(..10)?, at) as i16).to_be_bytes());
+    }
+    let mut at = 10;
+    loop {
+        let mut flags = read_u16_at(glyph.get(..at + 4)?, at);
+        let index = read_u16_at(glyph, at + 2).checked_add(offset)?;
+        let words = flags & ARG_1_AND_2_ARE_WORDS != 0;
+        let args_length = if words { 4 } else { 2 };
+        let args = glyph.get(at + 4..at + 4 + args_length)?;
+        at += 4 + args_length;
+        let transform_length = if flags & WE_HAVE_A_SCALE != 0 {
+            2
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            4
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            8
+        } else {
+            0
+        };
+        let transform = glyph.get(at..at + transform_length)?;
+        at += transform_length;
+
+        flags &= !WE_HAVE_INSTRUCTIONS;
+        let args = match (scale, flags & ARGS_ARE_XY_VALUES != 0) {
+            (Some(_), true) => {
+                flags |= ARG_1_AND_2_ARE_WORDS;
+                let (dx, dy) = match words {
+                    true => (read_u16_at(args, 0) as i16, read_u16_at(args, 2) as i16),
+                    false => (args[0] as i8 as i16, args[1] as i8 as i16),
+                };
+                [scaled(dx).to_be_bytes(), scaled(dy).to_be_bytes()].concat()
+            }
+            _ => args.to_vec(),
+        };
+        data.extend_from_slice(&flags.to_be_bytes());
+        data.extend_from_slice(&index.to_be_bytes());
+        data.extend_from_slice(&args);
+        data.extend_from_slice(transform);
+        if flags & MORE_COMPONENTS == 0 {
+            return Some(data);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::font::Font;
+
+/// Size of a character cell for a monospaced font at a given pixel size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellMetrics {
+    pub width: f32,
+    pub height: f32,
+    /// Distance from the top of the cell to the baseline
+    pub baseline: f32,
+}
+
+impl Font {
+    /// How far the caret leans right per unit up (run over rise), 0 for upright fonts and ones
+    /// with a flat slope.
+    pub fn caret_slant(&self) -> f32 {
+        match self.caret_slope() {
+            (0, _) => 0.,
+            (rise, run) => run as f32 / rise as f32,
+        }
+    }
+
+    /// Distance between two baselines in font units (ascender - descender + lineGap).
+    pub fn line_height(&self) -> i32 {
+        self.ascender() as i32 - self.descender() as i32 + self.line_gap() as i32
+    }
+
+    /// Advance width shared by all of the printable ASCII characters the font maps, if they all agree.
+    fn ascii_fixed_advance(&self) -> Option<u16> {
+        let mut advances = (0x20u8..=0x7E)
+            .filter_map(|c| self.glyph_index_for_char(c as char))
+            .map(|index| self.advance_width(index))
+            .filter(|advance| *advance != 0);
+        let first = advances.next()?;
+        advances.all(|advance| advance == first).then_some(first)
+    }
+
+    pub fn is_monospace(&self) -> bool {
+        if self.post().is_fixed_pitch {
+            return true;
+        }
+        if self.os2().is_some_and(|os2| os2.panose_is_monospaced()) {
+            return true;
+        }
+        self.ascii_fixed_advance().is_some()
+    }
+
+    /// Cell size for terminal-style grids, `None` when the font isn't monospaced.
+    pub fn cell_metrics(&self, px_size: f32) -> Option<CellMetrics> {
+        if !self.is_monospace() {
+            return None;
+        }
+        // the flags can claim fixed pitch even when ASCII doesn't agree, so fall back to the space
+        let advance = self
+            .ascii_fixed_advance()
+            .or_else(|| self.glyph_index_for_char(' ').map(|index| self.advance_width(index)))?;
+        let scale = self.scale_for_px(px_size);
+        Some(CellMetrics {
+            width: advance as f32 * scale,
+            height: self.line_height() as f32 * scale,
+            baseline: self.ascender() as f32 * scale,
+        })
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+
+use crate::reader::FontReader;
+
+pub const FAMILY_NAME: u16 = 1;
+pub const SUBFAMILY_NAME: u16 = 2;
+pub const FULL_NAME: u16 = 4;
+pub const VERSION: u16 = 5;
+pub const POSTSCRIPT_NAME: u16 = 6;
+pub const TYPOGRAPHIC_FAMILY_NAME: u16 = 16;
+pub const TYPOGRAPHIC_SUBFAMILY_NAME: u16 = 17;
+pub const VARIATIONS_POSTSCRIPT_NAME_PREFIX: u16 = 25;
+
+const PLATFORM_UNICODE: u16 = 0;
+const PLATFORM_MACINTOSH: u16 = 1;
+const PLATFORM_WINDOWS: u16 = 3;
+const WINDOWS_ENGLISH_US: u16 = 0x409;
+
+#[derive(Debug, Clone)]
+pub struct NameRecord {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub language_id: u16,
+    pub name_id: u16,
+    pub value: String,
+}
+
+/// The `name` table, with every string we know how to decode.
+#[derive(Debug, Clone, Default)]
+pub struct Name {
+    pub records: Vec<NameRecord>,
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+impl Name {
+    pub fn from_reader(reader: &mut FontReader, name_offset: u32) -> Result<Name> {
+        reader.seek(name_offset as u64 + 2)?; // skip version
+        let count = reader.read_u16()?;
+        let storage_offset = name_offset as u64 + reader.read_u16()? as u64;
+
+        let mut records = Vec::with_capacity(count as usize);
+        for i in 0..count as u64 {
+            // platformID, encodingID, languageID, nameID, length, offset : 2 bytes each
+            reader.seek(name_offset as u64 + 6 + i * 12)?;
+            let platform_id = reader.read_u16()?;
+            let encoding_id = reader.read_u16()?;
+            let language_id = reader.read_u16()?;
+            let name_id = reader.read_u16()?;
+            let length = reader.read_u16()?;
+            let offset = reader.read_u16()?;
+
+            reader.seek(storage_offset + offset as u64)?;
+            let mut bytes = vec![0u8; length as usize];
+            if reader.read_exact(&mut bytes).is_err() {
+                continue;
+            }
+            let value = match (platform_id, encoding_id) {
+                (PLATFORM_UNICODE, _) | (PLATFORM_WINDOWS, 0 | 1 | 10) => decode_utf16(&bytes),
+                // Mac Roman, close enough to Latin-1 for the ASCII names fonts actually use
+                (PLATFORM_MACINTOSH, 0) => bytes.iter().map(|b| *b as char).collect(),
+                _ => continue,
+            };
+            records.push(NameRecord {
+                platform_id,
+                encoding_id,
+                language_id,
+                name_id,
+                value,
+            });
+        }
+        Ok(Name { records })
+    }
+
+    /// The string for `name_id`, preferring the Windows US English one, then any Windows or
+    /// Unicode one, then Macintosh.
+    pub fn get(&self, name_id: u16) -> Option<&str> {
+        let rank = |record: &NameRecord| match (record.platform_id, record.language_id) {
+            (PLATFORM_WINDOWS, WINDOWS_ENGLISH_US) => 0,
+            (PLATFORM_WINDOWS, _) | (PLATFORM_UNICODE, _) => 1,
+            _ => 2,
+        };
+        self.records
+            .iter()
+            .filter(|record| record.name_id == name_id)
+            .min_by_key(|record| rank(record))
+            .map(|record| record.value.as_str())
+    }
+
+    /// Give every record of `name_id` the value `value`, adding a Windows US English record when
+    /// there's none.
+    pub fn set(&mut self, name_id: u16, value: &str) {
+        let mut found = false;
+        for record in self.records.iter_mut().filter(|record| record.name_id == name_id) {
+            record.value = value.to_string();
+            found = true;
+        }
+        if !found {
+            self.records.push(NameRecord {
+                platform_id: PLATFORM_WINDOWS,
+                encoding_id: 1,
+                language_id: WINDOWS_ENGLISH_US,
+                name_id,
+                value: value.to_string(),
+            });
+        }
+    }
+
+    pub fn remove(&mut self, name_id: u16) {
+        self.records.retain(|record| record.name_id != name_id);
+    }
+
+    /// Encode a format 0 name table, the records sorted the way the spec wants them. Macintosh
+    /// strings are written one byte per character, with `?` for what doesn't fit.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut records: Vec<&NameRecord> = self.records.iter().collect();
+        records.sort_by_key(|record| (record.platform_id, record.encoding_id, record.language_id, record.name_id));
+        let storage_offset = 6 + 12 * records.len();
+        let mut header = Vec::with_capacity(storage_offset);
+        let mut storage = Vec::new();
+        for value in [0, records.len() as u16, storage_offset as u16] {
+            header.extend_from_slice(&value.to_be_bytes());
+        }
+        for record in records {
+            let bytes: Vec<u8> = match record.platform_id {
+                PLATFORM_MACINTOSH => record.value.chars().map(|c| u8::try_from(c).unwrap_or(b'?')).collect(),
+                _ => record.value.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            };
+            let length = u16::try_from(bytes.len()).map_err(|_| anyhow!("Name {} is too long", record.name_id))?;
+            let offset = u16::try_from(storage.len()).map_err(|_| anyhow!("Too many names for the name table"))?;
+            for value in [record.platform_id, record.encoding_id, record.language_id, record.name_id, length, offset] {
+                header.extend_from_slice(&value.to_be_bytes());
+            }
+            storage.extend_from_slice(&bytes);
+        }
+        header.extend_from_slice(&storage);
+        Ok(header)
+    }
+}
@@ -0,0 +1,39 @@
+//! How floats are written in the SVG, PDF and text output, so the same input prints the same
+//! bytes everywhere: always '.' for the decimal point, never an exponent, no trailing zeros,
+//! no sign on 0, and "0" for NaN and the infinities, which the formats can't hold.
+
+/// `value` with at most `decimals` decimals, e.g. `fixed(2.50, 3)` is "2.5".
+pub fn fixed(value: f32, decimals: usize) -> String {
+    if !value.is_finite() {
+        return "0".to_string();
+    }
+    let text = format!("{:.*}", decimals, value as f64);
+    let text = match text.contains('.') {
+        true => text.trim_end_matches('0').trim_end_matches('.'),
+        false => &text,
+    };
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// `value` rounded to `digits` significant digits, at most the 7 an f32 holds, e.g.
+/// `significant(0.00048828125, 3)` is "0.000488" and `significant(123456., 3)` is "123000".
+pub fn significant(value: f32, digits: usize) -> String {
+    if !value.is_finite() || value == 0. {
+        return "0".to_string();
+    }
+    let value = value as f64;
+    let magnitude = value.abs().log10().floor() as i32;
+    let decimals = digits.clamp(1, 7) as i32 - 1 - magnitude;
+    if decimals >= 0 {
+        return fixed(value as f32, decimals as usize);
+    }
+    // the digits that are kept followed by zeros, not the f64 the rounding makes
+    let kept = (value / 10f64.powi(-decimals)).round();
+    match kept {
+        0. => "0".to_string(),
+        kept => format!("{kept:.0}{}", "0".repeat(-decimals as usize)),
+    }
+}
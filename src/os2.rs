@@ -0,0 +1,66 @@
+use anyhow::Result;
+
+use crate::reader::FontReader;
+
+/// The fields of the `OS/2` table we currently care about.
+#[derive(Debug, Clone, Default)]
+pub struct Os2 {
+    pub weight_class: u16,
+    pub width_class: u16,
+    pub fs_type: u16,
+    pub panose: [u8; 10],
+    pub fs_selection: u16,
+    pub typo_ascender: i16,
+    pub typo_descender: i16,
+    pub typo_line_gap: i16,
+    /// sxHeight and sCapHeight, only present from version 2 on
+    pub x_height: Option<i16>,
+    pub cap_height: Option<i16>,
+}
+
+impl Os2 {
+    pub fn from_reader(reader: &mut FontReader, os2_offset: u32) -> Result<Os2> {
+        reader.seek(os2_offset as u64)?;
+        let version = reader.read_u16()?;
+        reader.skip(2)?; // skip xAvgCharWidth
+        let weight_class = reader.read_u16()?;
+        let width_class = reader.read_u16()?;
+        let fs_type = reader.read_u16()?;
+
+        reader.seek(os2_offset as u64 + 32)?; // skip sub/superscript, strikeout and family class fields
+        let mut panose = [0u8; 10];
+        reader.read_exact(&mut panose)?;
+
+        reader.seek(os2_offset as u64 + 62)?; // skip unicode ranges and vendor id
+        let fs_selection = reader.read_u16()?;
+        reader.skip(4)?; // skip first/last char index
+        let typo_ascender = reader.read_i16()?;
+        let typo_descender = reader.read_i16()?;
+        let typo_line_gap = reader.read_i16()?;
+
+        let (x_height, cap_height) = if version >= 2 {
+            reader.seek(os2_offset as u64 + 86)?; // skip win metrics and code page ranges
+            (Some(reader.read_i16()?), Some(reader.read_i16()?))
+        } else {
+            (None, None)
+        };
+
+        Ok(Os2 {
+            weight_class,
+            width_class,
+            fs_type,
+            panose,
+            fs_selection,
+            typo_ascender,
+            typo_descender,
+            typo_line_gap,
+            x_height,
+            cap_height,
+        })
+    }
+
+    /// PANOSE says monospaced when the family is Latin Text (2) and the proportion is Monospaced (9).
+    pub fn panose_is_monospaced(&self) -> bool {
+        self.panose[0] == 2 && self.panose[3] == 9
+    }
+}
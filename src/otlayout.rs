@@ -0,0 +1,174 @@
+use anyhow::{anyhow, Result};
+
+use crate::reader::FontReader;
+
+/// A four byte OpenType tag naming a script, feature or baseline, like `*b"latn"`.
+pub type Tag = [u8; 4];
+
+/// The glyphs a subtable applies to, mapping each to its coverage index.
+#[derive(Debug, Clone)]
+pub enum Coverage {
+    Glyphs(Vec<u16>),
+    /// (start, end, coverage index of start)
+    Ranges(Vec<(u16, u16, u16)>),
+}
+
+impl Coverage {
+    pub fn from_reader(reader: &mut FontReader, offset: u64) -> Result<Coverage> {
+        reader.seek(offset)?;
+        let format = reader.read_u16()?;
+        let count = reader.read_u16()?;
+        match format {
+            1 => Ok(Coverage::Glyphs(
+                (0..count).map(|_| reader.read_u16()).collect::<Result<_, _>>()?,
+            )),
+            2 => {
+                let mut ranges = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    ranges.push((
+                        reader.read_u16()?,
+                        reader.read_u16()?,
+                        reader.read_u16()?,
+                    ));
+                }
+                Ok(Coverage::Ranges(ranges))
+            }
+            _ => Err(anyhow!("Unknown coverage format {format}")),
+        }
+    }
+
+    pub fn index(&self, glyph: u16) -> Option<u16> {
+        match self {
+            Coverage::Glyphs(glyphs) => glyphs.binary_search(&glyph).ok().map(|index| index as u16),
+            Coverage::Ranges(ranges) => {
+                let at = ranges.partition_point(|(_, end, _)| *end < glyph);
+                let (start, _, start_index) = ranges.get(at).filter(|(start, _, _)| *start <= glyph)?;
+                Some(start_index + (glyph - start))
+            }
+        }
+    }
+}
+
+/// Assigns glyphs to classes, glyphs it doesn't list are class 0.
+#[derive(Debug, Clone, Default)]
+pub struct ClassDef {
+    /// (start, end, class), sorted
+    ranges: Vec<(u16, u16, u16)>,
+}
+
+impl ClassDef {
+    pub fn from_reader(reader: &mut FontReader, offset: u64) -> Result<ClassDef> {
+        reader.seek(offset)?;
+        let format = reader.read_u16()?;
+        let mut ranges = Vec::new();
+        match format {
+            1 => {
+                let start_glyph = reader.read_u16()?;
+                let glyph_count = reader.read_u16()?;
+                for i in 0..glyph_count {
+                    let class = reader.read_u16()?;
+                    let glyph = start_glyph.wrapping_add(i);
+                    if class != 0 {
+                        ranges.push((glyph, glyph, class));
+                    }
+                }
+            }
+            2 => {
+                let range_count = reader.read_u16()?;
+                for _ in 0..range_count {
+                    ranges.push((
+                        reader.read_u16()?,
+                        reader.read_u16()?,
+                        reader.read_u16()?,
+                    ));
+                }
+            }
+            _ => return Err(anyhow!("Unknown class definition format {format}")),
+        }
+        ranges.sort();
+        Ok(ClassDef { ranges })
+    }
+
+    pub fn class(&self, glyph: u16) -> u16 {
+        let at = self.ranges.partition_point(|(_, end, _)| *end < glyph);
+        match self.ranges.get(at) {
+            Some((start, _, class)) if *start <= glyph => *class,
+            _ => 0,
+        }
+    }
+}
+
+/// A lookup's type, flags and the absolute offsets of its subtables (extension subtables
+/// resolved to the ones they wrap).
+#[derive(Debug, Clone)]
+pub struct LookupHeader {
+    pub lookup_type: u16,
+    pub flags: u16,
+    pub subtables: Vec<u64>,
+}
+
+/// Read the feature list and lookup list of a GSUB or GPOS table at `table_offset`. Returns the
+/// indices of the lookups used by `feature_tag` in any script and language (sorted, as lookups
+/// apply in lookup list order) and every lookup header.
+pub fn read_feature_lookups(
+    reader: &mut FontReader,
+    table_offset: u64,
+    feature_tag: &[u8; 4],
+    extension_type: u16,
+) -> Result<(Vec<u16>, Vec<LookupHeader>)> {
+    reader.seek(table_offset + 6)?; // skip version and the script list
+    let feature_list = table_offset + reader.read_u16()? as u64;
+    let lookup_list = table_offset + reader.read_u16()? as u64;
+
+    reader.seek(feature_list)?;
+    let feature_count = reader.read_u16()?;
+    let mut features = Vec::new();
+    for _ in 0..feature_count {
+        let mut tag = [0u8; 4];
+        reader.read_exact(&mut tag)?;
+        let offset = reader.read_u16()?;
+        if &tag == feature_tag {
+            features.push(feature_list + offset as u64);
+        }
+    }
+    let mut lookup_indices = Vec::new();
+    for feature in features {
+        reader.seek(feature + 2)?; // skip featureParamsOffset
+        let count = reader.read_u16()?;
+        for _ in 0..count {
+            lookup_indices.push(reader.read_u16()?);
+        }
+    }
+    lookup_indices.sort();
+    lookup_indices.dedup();
+
+    reader.seek(lookup_list)?;
+    let lookup_count = reader.read_u16()?;
+    let lookup_offsets = (0..lookup_count).map(|_| reader.read_u16()).collect::<Result<Vec<_>, _>>()?;
+    let mut lookups = Vec::with_capacity(lookup_count as usize);
+    for offset in lookup_offsets {
+        let lookup = lookup_list + offset as u64;
+        reader.seek(lookup)?;
+        let mut lookup_type = reader.read_u16()?;
+        let flags = reader.read_u16()?;
+        let subtable_count = reader.read_u16()?;
+        let mut subtables = Vec::with_capacity(subtable_count as usize);
+        for _ in 0..subtable_count {
+            subtables.push(lookup + reader.read_u16()? as u64);
+        }
+        if lookup_type == extension_type {
+            // extension subtables: format, the real lookup type and a 32 bit offset
+            for subtable in &mut subtables {
+                reader.seek(*subtable + 2)?;
+                lookup_type = reader.read_u16()?;
+                *subtable += reader.read_u32()? as u64;
+            }
+        }
+        lookups.push(LookupHeader {
+            lookup_type,
+            flags,
+            subtables,
+        });
+    }
+    Ok((lookup_indices, lookups))
+}
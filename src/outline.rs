@@ -0,0 +1,337 @@
+use anyhow::{anyhow, Result};
+use raqote::Path;
+
+use crate::flatten::{flatten_segments, Contour as Polyline};
+use crate::geom;
+use crate::glyph::{GlyphData, Segment};
+use crate::render::contours_to_path;
+
+/// A point of an outline, on the curve or the control point of a quadratic curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlinePoint {
+    pub x: f32,
+    pub y: f32,
+    pub on_curve: bool,
+}
+
+/// One closed contour of an outline, the last point connecting back to the first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Contour {
+    pub points: Vec<OutlinePoint>,
+}
+
+/// A piece of a cubic contour, a straight line or a cubic bezier (start, two controls, end).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CubicSegment {
+    Line((f32, f32), (f32, f32)),
+    Cubic((f32, f32), (f32, f32), (f32, f32), (f32, f32)),
+}
+
+/// A closed contour of lines and cubic curves, like CFF and PDF outlines have. It ends where it
+/// starts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CubicContour {
+    pub segments: Vec<CubicSegment>,
+}
+
+/// A glyph outline as its contours, y up, in whatever units the points are in (font units from
+/// `GlyphData::outline`). Unlike the parallel arrays of `GlyphData` each contour holds its own
+/// points, and transforms apply to all of them at once. `bbox` is (x_min, y_min, x_max, y_max)
+/// of the points, off-curve ones included like the glyf header does, all 0 without points.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outline {
+    pub contours: Vec<Contour>,
+    pub bbox: (f32, f32, f32, f32),
+}
+
+impl GlyphData {
+    /// The points of the glyph split into contours, see `Outline::from_points`. Composite
+    /// glyphs have no points here.
+    pub fn outline(&self) -> Outline {
+        Outline::from_points(&self.points(), &self.on_curve, &self.contour_end_indices)
+    }
+
+    /// A simple glyph of the outline's points, rounded to whole font units, its bounding box
+    /// from them and no instructions. Fails when a point or the point count doesn't fit glyf.
+    pub fn from_outline(outline: &Outline) -> Result<GlyphData> {
+        let mut glyph = GlyphData::empty();
+        let coordinate = |value: f32| {
+            let rounded = value.round();
+            match rounded >= i16::MIN as f32 && rounded <= i16::MAX as f32 {
+                true => Ok(rounded as i16),
+                false => Err(anyhow!("The coordinate {value} doesn't fit in a glyph")),
+            }
+        };
+        for contour in outline.contours.iter().filter(|contour| !contour.points.is_empty()) {
+            for point in &contour.points {
+                glyph.x_coords.push(coordinate(point.x)?);
+                glyph.y_coords.push(coordinate(point.y)?);
+                glyph.on_curve.push(point.on_curve);
+            }
+            let end = u16::try_from(glyph.x_coords.len() - 1)
+                .map_err(|_| anyhow!("More than {} points don't fit in a glyph", u16::MAX))?;
+            glyph.contour_end_indices.push(end);
+        }
+        (glyph.x_min, glyph.y_min, glyph.x_max, glyph.y_max) = glyph.bounds();
+        Ok(glyph)
+    }
+}
+
+impl Contour {
+    /// The line and quadratic segments of the contour, with the implied on-curve points between
+    /// consecutive off-curve points filled in. They end where they start.
+    pub fn segments(&self) -> Vec<Segment> {
+        let points = &self.points;
+        let len = points.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let midpoint = |a: (f32, f32), b: (f32, f32)| ((a.0 + b.0) / 2., (a.1 + b.1) / 2.);
+        let position = |point: &OutlinePoint| (point.x, point.y);
+        // start from an on-curve point, or the midpoint of the first two off-curve points
+        let (start_point, first) = match points.iter().position(|point| point.on_curve) {
+            Some(i) => (position(&points[i]), i),
+            None => (midpoint(position(&points[0]), position(&points[1 % len])), 0),
+        };
+
+        let mut segments = Vec::with_capacity(len);
+        let mut current = start_point;
+        let mut control: Option<(f32, f32)> = None;
+        for step in 1..=len {
+            let point = &points[(first + step) % len];
+            let p = position(point);
+            if point.on_curve {
+                match control.take() {
+                    Some(c) => segments.push(Segment::Quad(current, c, p)),
+                    None => segments.push(Segment::Line(current, p)),
+                }
+                current = p;
+            } else {
+                if let Some(c) = control {
+                    // two off-curve points in a row imply an on-curve point between them
+                    let m = midpoint(c, p);
+                    segments.push(Segment::Quad(current, c, m));
+                    current = m;
+                }
+                control = Some(p);
+            }
+        }
+        if let Some(c) = control {
+            segments.push(Segment::Quad(current, c, start_point));
+        }
+        segments
+    }
+}
+
+impl CubicSegment {
+    pub fn start(&self) -> (f32, f32) {
+        match self {
+            CubicSegment::Line(p0, _) | CubicSegment::Cubic(p0, _, _, _) => *p0,
+        }
+    }
+
+    pub fn end(&self) -> (f32, f32) {
+        match self {
+            CubicSegment::Line(_, p1) | CubicSegment::Cubic(_, _, _, p1) => *p1,
+        }
+    }
+
+    /// Quadratic curves within `tolerance` of the cubic, end to end: the cubic split into
+    /// equal parameter steps until each piece is close enough to the quadratic whose control is
+    /// where the piece's tangents meet.
+    fn to_quads(self, tolerance: f32) -> Vec<Segment> {
+        let (p0, c1, c2, p1) = match self {
+            CubicSegment::Line(p0, p1) => return vec![Segment::Line(p0, p1)],
+            CubicSegment::Cubic(p0, c1, c2, p1) => (p0, c1, c2, p1),
+        };
+        // the distance of each piece from its quadratic is at most sqrt(3) / 36 times the length
+        // of the cubic's third difference, which shrinks with the cube of the number of pieces
+        let third = (p1.0 - 3. * c2.0 + 3. * c1.0 - p0.0, p1.1 - 3. * c2.1 + 3. * c1.1 - p0.1);
+        let error = 3f32.sqrt() / 36. * (third.0 * third.0 + third.1 * third.1).sqrt();
+        let pieces = (error / tolerance.max(f32::EPSILON)).cbrt().ceil().clamp(1., MAX_CUBIC_PIECES) as usize;
+        let at = |t: f32| {
+            let u = 1. - t;
+            let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+            (a * p0.0 + b * c1.0 + c * c2.0 + d * p1.0, a * p0.1 + b * c1.1 + c * c2.1 + d * p1.1)
+        };
+        let derivative = |t: f32| {
+            let u = 1. - t;
+            let (a, b, c) = (3. * u * u, 6. * u * t, 3. * t * t);
+            (
+                a * (c1.0 - p0.0) + b * (c2.0 - c1.0) + c * (p1.0 - c2.0),
+                a * (c1.1 - p0.1) + b * (c2.1 - c1.1) + c * (p1.1 - c2.1),
+            )
+        };
+        let mut quads = Vec::with_capacity(pieces);
+        let mut start = p0;
+        for piece in 0..pieces {
+            let (t0, t1) = (piece as f32 / pieces as f32, (piece + 1) as f32 / pieces as f32);
+            let end = if piece + 1 == pieces { p1 } else { at(t1) };
+            // the controls of the piece as a cubic of its own, then the quadratic's control
+            // 3/4 of the way from the ends' average to theirs
+            let (d0, d1, h) = (derivative(t0), derivative(t1), (t1 - t0) / 3.);
+            let q1 = (start.0 + d0.0 * h, start.1 + d0.1 * h);
+            let q2 = (end.0 - d1.0 * h, end.1 - d1.1 * h);
+            let control = (
+                (3. * (q1.0 + q2.0) - start.0 - end.0) / 4.,
+                (3. * (q1.1 + q2.1) - start.1 - end.1) / 4.,
+            );
+            quads.push(Segment::Quad(start, control, end));
+            start = end;
+        }
+        quads
+    }
+}
+
+/// The most quadratics one cubic becomes in `Outline::from_cubics`, whatever the tolerance.
+const MAX_CUBIC_PIECES: f32 = 64.;
+
+impl Outline {
+    /// Split TrueType style points (with their on/off-curve flags) into contours at the contour
+    /// end indices. Empty contours and those going past the points are left out.
+    pub fn from_points(points: &[(f32, f32)], on_curve: &[bool], contour_end_indices: &[u16]) -> Outline {
+        let mut contours = Vec::with_capacity(contour_end_indices.len());
+        let mut start = 0usize;
+        for end in contour_end_indices {
+            let range = start..(*end as usize + 1);
+            start = range.end.max(start);
+            if range.is_empty() || range.end > points.len() || range.end > on_curve.len() {
+                continue;
+            }
+            let points = range
+                .map(|i| OutlinePoint {
+                    x: points[i].0,
+                    y: points[i].1,
+                    on_curve: on_curve[i],
+                })
+                .collect();
+            contours.push(Contour { points });
+        }
+        let mut outline = Outline {
+            contours,
+            bbox: (0., 0., 0., 0.),
+        };
+        outline.update_bbox();
+        outline
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.contours.iter().map(|contour| contour.points.len()).sum()
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &OutlinePoint> + '_ {
+        self.contours.iter().flat_map(|contour| contour.points.iter())
+    }
+
+    /// Recompute `bbox` from the points, after editing them by hand.
+    pub fn update_bbox(&mut self) {
+        let mut points = self.contours.iter().flat_map(|contour| contour.points.iter());
+        self.bbox = match points.next() {
+            Some(first) => points.fold((first.x, first.y, first.x, first.y), |(x_min, y_min, x_max, y_max), point| {
+                (x_min.min(point.x), y_min.min(point.y), x_max.max(point.x), y_max.max(point.y))
+            }),
+            None => (0., 0., 0., 0.),
+        };
+    }
+
+    /// (x_min, y_min, x_max, y_max) of the curves themselves rather than their points: off-curve
+    /// points only count where the curves they pull turn, so it fits inside `bbox`. All 0
+    /// without points.
+    pub fn tight_bbox(&self) -> (f32, f32, f32, f32) {
+        geom::segments_bounds(&self.segments()).unwrap_or_default()
+    }
+
+    /// Map every point through the affine `transform` [xx, yx, xy, yy, dx, dy]: x' = xx * x +
+    /// xy * y + dx and y' = yx * x + yy * y + dy, the layout of a composite component's matrix.
+    pub fn transform(&mut self, transform: [f32; 6]) {
+        let [xx, yx, xy, yy, dx, dy] = transform;
+        for point in self.contours.iter_mut().flat_map(|contour| contour.points.iter_mut()) {
+            (point.x, point.y) = (xx * point.x + xy * point.y + dx, yx * point.x + yy * point.y + dy);
+        }
+        self.update_bbox();
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.transform([1., 0., 0., 1., dx, dy]);
+    }
+
+    pub fn scale(&mut self, scale: f32) {
+        self.transform([scale, 0., 0., scale, 0., 0.]);
+    }
+
+    /// Add the contours of `other` after these, like the components of a composite glyph.
+    pub fn append(&mut self, other: Outline) {
+        let (x_min, y_min, x_max, y_max) = other.bbox;
+        self.bbox = match (self.num_points(), other.num_points()) {
+            (_, 0) => self.bbox,
+            (0, _) => other.bbox,
+            _ => (self.bbox.0.min(x_min), self.bbox.1.min(y_min), self.bbox.2.max(x_max), self.bbox.3.max(y_max)),
+        };
+        self.contours.extend(other.contours);
+    }
+
+    /// The segments of each contour, see `Contour::segments`.
+    pub fn segments(&self) -> Vec<Vec<Segment>> {
+        self.contours.iter().map(Contour::segments).collect()
+    }
+
+    /// The contours with each quadratic raised to the cubic drawing the same curve (controls 2/3
+    /// of the way from the ends to the quadratic's control), lines kept as lines.
+    pub fn to_cubics(&self) -> Vec<CubicContour> {
+        let cubic = |segment: Segment| match segment {
+            Segment::Line(p0, p1) => CubicSegment::Line(p0, p1),
+            Segment::Quad(p0, c, p1) => CubicSegment::Cubic(
+                p0,
+                (p0.0 + 2. / 3. * (c.0 - p0.0), p0.1 + 2. / 3. * (c.1 - p0.1)),
+                (p1.0 + 2. / 3. * (c.0 - p1.0), p1.1 + 2. / 3. * (c.1 - p1.1)),
+                p1,
+            ),
+        };
+        self.contours
+            .iter()
+            .map(|contour| CubicContour {
+                segments: contour.segments().into_iter().map(cubic).collect(),
+            })
+            .collect()
+    }
+
+    /// An outline of lines and quadratics within `tolerance` (in the units of the points) of
+    /// cubic contours, for CFF style outlines, see `to_cubics` for the other way. Each segment
+    /// starts where the one before it ends; a cubic that is a raised quadratic comes back as
+    /// that one quadratic.
+    pub fn from_cubics(contours: &[CubicContour], tolerance: f32) -> Outline {
+        let mut outline = Outline::default();
+        for contour in contours {
+            let Some(first) = contour.segments.first() else {
+                continue;
+            };
+            let on = |(x, y): (f32, f32)| OutlinePoint { x, y, on_curve: true };
+            let mut points = vec![on(first.start())];
+            for segment in contour.segments.iter().flat_map(|segment| segment.to_quads(tolerance)) {
+                if let Segment::Quad(_, (x, y), _) = segment {
+                    points.push(OutlinePoint { x, y, on_curve: false });
+                }
+                points.push(on(segment.end()));
+            }
+            // the contour closes by itself, the end back at the start isn't a point of its own
+            if points.len() > 1 && points.last() == points.first() {
+                points.pop();
+            }
+            outline.contours.push(Contour { points });
+        }
+        outline.update_bbox();
+        outline
+    }
+
+    /// Approximate every contour with a closed polyline within `tolerance` of the outline, in
+    /// the units of the points.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        flatten_segments(self.segments(), tolerance)
+    }
+
+    /// A path of the outline, scaled from its units and flipped so that y points down, with the
+    /// outline's origin at `origin` in the draw target.
+    pub fn to_path(&self, scale: f32, origin: (f32, f32)) -> Path {
+        contours_to_path(&self.segments(), scale, origin)
+    }
+}
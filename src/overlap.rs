@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::flatten::Contour;
+use crate::glyph::GlyphData;
+
+/// Curves are flattened this finely (in font units) before the union, the result is a polyline.
+const OVERLAP_TOLERANCE_FONT_UNITS: f32 = 0.5;
+/// Intersection points are snapped to a grid of this many steps per font unit so that edges that
+/// meet numerically also meet exactly, which is what the linking step relies on.
+const SNAP_STEPS: f64 = 64.;
+/// How far from an edge (in font units) we sample the winding number on either side of it.
+const PROBE_DISTANCE: f64 = 1. / 256.;
+
+type Point = (f64, f64);
+type SnappedPoint = (i64, i64);
+
+fn snap(p: Point) -> SnappedPoint {
+    ((p.0 * SNAP_STEPS).round() as i64, (p.1 * SNAP_STEPS).round() as i64)
+}
+
+fn unsnap(p: SnappedPoint) -> Point {
+    (p.0 as f64 / SNAP_STEPS, p.1 as f64 / SNAP_STEPS)
+}
+
+fn cross(a: Point, b: Point) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+/// Parameters along `a` (and along `b`) where the two segments touch, including the endpoints of
+/// collinear overlaps so that shared edges get split at the same places on both sides.
+fn intersection_params(a: (Point, Point), b: (Point, Point)) -> (Vec<f64>, Vec<f64>) {
+    let (mut on_a, mut on_b) = (Vec::new(), Vec::new());
+    let r = sub(a.1, a.0);
+    let s = sub(b.1, b.0);
+    let denominator = cross(r, s);
+    let qp = sub(b.0, a.0);
+    let r_len2 = r.0 * r.0 + r.1 * r.1;
+    let s_len2 = s.0 * s.0 + s.1 * s.1;
+    if r_len2 == 0. || s_len2 == 0. {
+        return (on_a, on_b);
+    }
+
+    if denominator.abs() <= 1e-12 * r_len2.sqrt() * s_len2.sqrt() {
+        // parallel, only interesting when collinear
+        if cross(qp, r).abs() > 1e-9 * r_len2.sqrt() {
+            return (on_a, on_b);
+        }
+        let project_a = |p: Point| ((p.0 - a.0 .0) * r.0 + (p.1 - a.0 .1) * r.1) / r_len2;
+        let project_b = |p: Point| ((p.0 - b.0 .0) * s.0 + (p.1 - b.0 .1) * s.1) / s_len2;
+        for p in [b.0, b.1] {
+            let t = project_a(p);
+            if t > 0. && t < 1. {
+                on_a.push(t);
+            }
+        }
+        for p in [a.0, a.1] {
+            let u = project_b(p);
+            if u > 0. && u < 1. {
+                on_b.push(u);
+            }
+        }
+        return (on_a, on_b);
+    }
+
+    let t = cross(qp, s) / denominator;
+    let u = cross(qp, r) / denominator;
+    if (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u) {
+        on_a.push(t);
+        on_b.push(u);
+    }
+    (on_a, on_b)
+}
+
+/// Non-zero winding number of the original edges around a point.
+pub(crate) fn winding_number(edges: &[(Point, Point)], point: Point) -> i32 {
+    let mut winding = 0;
+    for (p0, p1) in edges {
+        if p0.1 <= point.1 {
+            if p1.1 > point.1 && cross(sub(*p1, *p0), sub(point, *p0)) > 0. {
+                winding += 1;
+            }
+        } else if p1.1 <= point.1 && cross(sub(*p1, *p0), sub(point, *p0)) < 0. {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+impl GlyphData {
+    /// Union of all the contours under the non-zero fill rule, as a polyline outline without any
+    /// overlapping or self-intersecting contours. Outer contours come out clockwise, holes
+    /// counter-clockwise, like TrueType expects.
+    pub fn remove_overlaps(&self) -> GlyphData {
+        union_contours(&self.flatten(OVERLAP_TOLERANCE_FONT_UNITS), |winding| winding != 0)
+    }
+}
+
+/// The outline of the area of polylines where `inside` holds for the winding number, see
+/// `winding_number` (negative inside clockwise contours), as polyline contours in font units.
+/// Outer contours come out clockwise, holes counter-clockwise.
+pub(crate) fn union_contours(contours: &[Contour], inside: impl Fn(i32) -> bool) -> GlyphData {
+    let edges: Vec<(Point, Point)> = contours
+        .iter()
+        .flat_map(|contour| contour.edges())
+        .map(|(p0, p1)| {
+            let snapped = |p: (f32, f32)| unsnap(snap((p.0 as f64, p.1 as f64)));
+            (snapped(p0), snapped(p1))
+        })
+        .filter(|(p0, p1)| p0 != p1)
+        .collect();
+
+    // sweep over the edges sorted by their lowest y, so only edges whose vertical extents
+    // overlap are tested against each other
+    let mut splits: Vec<Vec<f64>> = vec![Vec::new(); edges.len()];
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    let y_range = |e: &(Point, Point)| (e.0 .1.min(e.1 .1), e.0 .1.max(e.1 .1));
+    let x_range = |e: &(Point, Point)| (e.0 .0.min(e.1 .0), e.0 .0.max(e.1 .0));
+    order.sort_by(|a, b| y_range(&edges[*a]).0.total_cmp(&y_range(&edges[*b]).0));
+    for (position, &i) in order.iter().enumerate() {
+        let (_, i_y_max) = y_range(&edges[i]);
+        let (i_x_min, i_x_max) = x_range(&edges[i]);
+        for &j in &order[position + 1..] {
+            let (j_y_min, _) = y_range(&edges[j]);
+            if j_y_min > i_y_max {
+                break;
+            }
+            let (j_x_min, j_x_max) = x_range(&edges[j]);
+            if j_x_min > i_x_max || j_x_max < i_x_min {
+                continue;
+            }
+            let (on_i, on_j) = intersection_params(edges[i], edges[j]);
+            splits[i].extend(on_i);
+            splits[j].extend(on_j);
+        }
+    }
+
+    // split every edge at its intersections and keep the pieces separating inside from outside
+    let mut kept: HashSet<(SnappedPoint, SnappedPoint)> = HashSet::new();
+    let mut kept_order: Vec<(SnappedPoint, SnappedPoint)> = Vec::new();
+    for (edge, params) in edges.iter().zip(splits.iter_mut()) {
+        params.push(0.);
+        params.push(1.);
+        params.sort_by(|a, b| a.total_cmp(b));
+        let along = |t: f64| (edge.0 .0 + (edge.1 .0 - edge.0 .0) * t, edge.0 .1 + (edge.1 .1 - edge.0 .1) * t);
+        for pair in params.windows(2) {
+            let (start, end) = (snap(along(pair[0])), snap(along(pair[1])));
+            if start == end {
+                continue;
+            }
+            let (p0, p1) = (unsnap(start), unsnap(end));
+            let d = sub(p1, p0);
+            let len = (d.0 * d.0 + d.1 * d.1).sqrt();
+            let normal = (-d.1 / len * PROBE_DISTANCE, d.0 / len * PROBE_DISTANCE);
+            let mid = ((p0.0 + p1.0) / 2., (p0.1 + p1.1) / 2.);
+            let left_inside = inside(winding_number(&edges, (mid.0 + normal.0, mid.1 + normal.1)));
+            let right_inside = inside(winding_number(&edges, (mid.0 - normal.0, mid.1 - normal.1)));
+            let directed = match (left_inside, right_inside) {
+                (false, true) => (start, end), // clockwise, the inside is on the right
+                (true, false) => (end, start),
+                _ => continue,
+            };
+            // coincident edges of different contours end up identical here, only keep one
+            if kept.insert(directed) {
+                kept_order.push(directed);
+            }
+        }
+    }
+
+    // link the surviving edges back into closed loops
+    let mut outgoing: HashMap<SnappedPoint, Vec<usize>> = HashMap::new();
+    for (i, (start, _)) in kept_order.iter().enumerate() {
+        outgoing.entry(*start).or_default().push(i);
+    }
+    let mut used = vec![false; kept_order.len()];
+    let mut result = GlyphData::empty();
+    for first in 0..kept_order.len() {
+        if used[first] {
+            continue;
+        }
+        let mut loop_points: Vec<SnappedPoint> = Vec::new();
+        let mut current = first;
+        loop {
+            used[current] = true;
+            let (start, end) = kept_order[current];
+            loop_points.push(start);
+            let next = outgoing
+                .get(&end)
+                .and_then(|candidates| candidates.iter().copied().find(|candidate| !used[*candidate]));
+            match next {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        push_polyline_contour(&mut result, &loop_points);
+    }
+    result
+}
+
+/// Round a loop to font units, drop repeated and collinear points and append it as a contour.
+fn push_polyline_contour(glyph: &mut GlyphData, points: &[SnappedPoint]) {
+    let mut rounded: Vec<(i16, i16)> = Vec::with_capacity(points.len());
+    for p in points {
+        let (x, y) = unsnap(*p);
+        let point = (x.round() as i16, y.round() as i16);
+        if rounded.last() != Some(&point) {
+            rounded.push(point);
+        }
+    }
+    while rounded.len() > 1 && rounded.first() == rounded.last() {
+        rounded.pop();
+    }
+    // repeatedly drop points lying on the line between their neighbours
+    let mut i = 0;
+    while rounded.len() >= 3 && i < rounded.len() {
+        let n = rounded.len();
+        let (prev, point, next) = (rounded[(i + n - 1) % n], rounded[i], rounded[(i + 1) % n]);
+        let turn = (point.0 as i32 - prev.0 as i32) * (next.1 as i32 - point.1 as i32)
+            - (point.1 as i32 - prev.1 as i32) * (next.0 as i32 - point.0 as i32);
+        if turn == 0 {
+            rounded.remove(i);
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+    if rounded.len() < 3 {
+        return;
+    }
+    for (x, y) in rounded {
+        glyph.x_coords.push(x);
+        glyph.y_coords.push(y);
+        glyph.on_curve.push(true);
+    }
+    glyph.contour_end_indices.push(glyph.x_coords.len() as u16 - 1);
+}
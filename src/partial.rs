@@ -0,0 +1,218 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::cmap::Cmap;
+use crate::font::{read_table_directory, Font, TableDirectory};
+use crate::name::Name;
+use crate::os2::Os2;
+use crate::otlayout::Tag;
+use crate::reader::FontReader;
+use crate::style::{family_name, FontStyle};
+
+/// A font parsed only as far as some of its tables, see `Font::parse_tables`: what those tables
+/// say is there, everything else is `None`. Nothing outside the table directory and the
+/// requested tables is read.
+#[derive(Debug)]
+pub struct PartialFont {
+    data: Cow<'static, [u8]>,
+    tables: HashMap<String, (u32, u32, u32)>,
+    /// The requested tags the font has
+    parsed: Vec<String>,
+    /// unitsPerEm and macStyle
+    head: Option<(u16, u16)>,
+    /// Ascender, descender and line gap
+    hhea: Option<(i16, i16, i16)>,
+    num_glyphs: Option<u16>,
+    name: Option<Name>,
+    os2: Option<Os2>,
+    cmap: Option<Cmap>,
+    bytes_read: usize,
+}
+
+impl Font {
+    /// Read the table directory of a font file and of its tables only `tags`, for looking at
+    /// the names, metrics or coverage of many fonts without paying for glyf, loca and the
+    /// layout tables. head, hhea, maxp, name, OS/2 and cmap are parsed, other tags are only
+    /// made available through `PartialFont::table_data`. `PartialFont::into_font` parses the
+    /// rest.
+    pub fn parse_tables(data: impl Into<Cow<'static, [u8]>>, tags: &[Tag]) -> Result<PartialFont> {
+        let data = data.into();
+        let reader = FontReader::counting(&data);
+        let TableDirectory { tables, .. } = read_table_directory(&mut reader.clone(), data.len(), false)?;
+        let mut parsed = Vec::new();
+        for tag in tags {
+            let tag = String::from_utf8_lossy(tag).into_owned();
+            if tables.contains_key(&tag) && !parsed.contains(&tag) {
+                parsed.push(tag);
+            }
+        }
+        let table = |tag: &str| {
+            let (_, offset, length) = tables.get(tag).filter(|_| parsed.iter().any(|parsed| parsed == tag))?;
+            Some((reader.table(tag, *offset, *length), *offset))
+        };
+
+        let head = match table("head") {
+            Some((mut head, offset)) => {
+                head.seek(offset as u64 + 18)?; // skip the version, revision, checksum adjustment, magic and flags
+                let units_per_em = head.read_u16()?;
+                head.seek(offset as u64 + 44)?; // skip the dates and the bounding box
+                Some((units_per_em, head.read_u16()?))
+            }
+            None => None,
+        };
+        let hhea = match table("hhea") {
+            Some((mut hhea, offset)) => {
+                hhea.seek(offset as u64 + 4)?; // skip version
+                Some((hhea.read_i16()?, hhea.read_i16()?, hhea.read_i16()?))
+            }
+            None => None,
+        };
+        let num_glyphs = match table("maxp") {
+            Some((mut maxp, offset)) => {
+                maxp.seek(offset as u64 + 4)?; // skip version
+                Some(maxp.read_u16()?)
+            }
+            None => None,
+        };
+        let name = table("name").and_then(|(mut reader, offset)| Name::from_reader(&mut reader, offset).ok());
+        let os2 = table("OS/2").and_then(|(mut reader, offset)| Os2::from_reader(&mut reader, offset).ok());
+        // without maxp any glyph index goes
+        let cmap_glyphs = num_glyphs.unwrap_or(u16::MAX);
+        let cmap = table("cmap")
+            .and_then(|(mut reader, offset)| Cmap::from_reader(&mut reader, offset, cmap_glyphs).ok());
+        let bytes_read = reader.bytes_read().unwrap_or_default();
+        Ok(PartialFont {
+            data,
+            tables,
+            parsed,
+            head,
+            hhea,
+            num_glyphs,
+            name,
+            os2,
+            cmap,
+            bytes_read,
+        })
+    }
+}
+
+impl PartialFont {
+    /// Whether the font has the table, parsed or not.
+    pub fn has_table(&self, tag: &str) -> bool {
+        self.tables.contains_key(tag)
+    }
+
+    /// Raw bytes of a requested table, `None` for the others.
+    pub fn table_data(&self, tag: &str) -> Option<&[u8]> {
+        let (_, offset, length) = self.tables.get(tag).filter(|_| self.parsed.iter().any(|parsed| parsed == tag))?;
+        self.data.get(*offset as usize..(*offset as usize + *length as usize))
+    }
+
+    /// How many bytes of the file parsing read, of the table directory and the requested
+    /// tables: the fields used rather than whole tables.
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// With name, see `Font::name`.
+    pub fn name(&self, name_id: u16) -> Option<&str> {
+        self.name.as_ref()?.get(name_id)
+    }
+
+    /// With name, see `Font::family_name`.
+    pub fn family_name(&self) -> Option<&str> {
+        family_name(|name_id| self.name(name_id))
+    }
+
+    /// With OS/2 or head, see `Font::style`.
+    pub fn style(&self) -> Option<FontStyle> {
+        if self.os2.is_none() && self.head.is_none() {
+            return None;
+        }
+        Some(FontStyle::from_tables(self.os2.as_ref(), self.head.map_or(0, |(_, mac_style)| mac_style)))
+    }
+
+    pub fn os2(&self) -> Option<&Os2> {
+        self.os2.as_ref()
+    }
+
+    /// With head.
+    pub fn units_per_em(&self) -> Option<u16> {
+        self.head.map(|(units_per_em, _)| units_per_em)
+    }
+
+    /// With hhea, ascender, descender and line gap in font units like `Font::ascender` and
+    /// the others.
+    pub fn vertical_metrics(&self) -> Option<(i16, i16, i16)> {
+        self.hhea
+    }
+
+    /// With maxp.
+    pub fn num_glyphs(&self) -> Option<u16> {
+        self.num_glyphs
+    }
+
+    /// With cmap, the glyph of `c`. Without maxp to bound them the glyph indices aren't checked.
+    pub fn glyph_index_for_char(&self, c: char) -> Option<u16> {
+        self.cmap.as_ref()?.glyph_index(c)
+    }
+
+    /// With cmap, whether the font maps every character of `text`.
+    pub fn covers(&self, text: &str) -> Option<bool> {
+        let cmap = self.cmap.as_ref()?;
+        Some(text.chars().all(|c| cmap.glyph_index(c).is_some()))
+    }
+
+    /// Parse the whole font, from the same data.
+    pub fn into_font(self) -> Result<Font> {
+        Font::from_bytes(self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{rectangle, TestFontBuilder};
+
+    fn test_font() -> Vec<u8> {
+        let mut builder = TestFontBuilder::new();
+        builder.family("Partial").add_glyph('a', &rectangle(0., 0., 100., 200.));
+        builder.build().unwrap()
+    }
+
+    /// The directory fields read: numTables and every table record.
+    fn directory_bytes(font: &[u8]) -> usize {
+        2 + 16 * u16::from_be_bytes([font[4], font[5]]) as usize
+    }
+
+    #[test]
+    fn only_the_directory_is_read_without_tags() {
+        let font = test_font();
+        let partial = Font::parse_tables(font.clone(), &[]).unwrap();
+        assert_eq!(partial.bytes_read(), directory_bytes(&font));
+        assert_eq!(partial.units_per_em(), None);
+    }
+
+    #[test]
+    fn bytes_read_counts_the_fields_read() {
+        let font = test_font();
+        // unitsPerEm and macStyle, then numGlyphs
+        let partial = Font::parse_tables(font.clone(), &[*b"head", *b"maxp"]).unwrap();
+        assert_eq!(partial.bytes_read(), directory_bytes(&font) + 2 * 2 + 2);
+        assert_eq!(partial.units_per_em(), Some(1000));
+        assert_eq!(partial.num_glyphs(), Some(2));
+    }
+
+    #[test]
+    fn bytes_read_stays_within_the_requested_tables() {
+        let font = test_font();
+        let partial = Font::parse_tables(font.clone(), &[*b"name", *b"cmap"]).unwrap();
+        let full = Font::from_bytes(font.clone()).unwrap();
+        let tables = full.table_data("name").unwrap().len() + full.table_data("cmap").unwrap().len();
+        assert!(partial.bytes_read() > directory_bytes(&font));
+        assert!(partial.bytes_read() <= directory_bytes(&font) + tables);
+        assert_eq!(partial.family_name(), Some("Partial"));
+        assert_eq!(partial.glyph_index_for_char('a'), Some(1));
+    }
+}
@@ -0,0 +1,181 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::flatten::Contour as Polyline;
+use crate::glyph::GlyphData;
+use crate::outline::{Contour, Outline, OutlinePoint};
+use crate::overlap::union_contours;
+
+/// Arcs of offset joins are flattened to stay this close (in font units) to the circle.
+const ARC_TOLERANCE_FONT_UNITS: f32 = 0.5;
+/// On-curve points where the outline turns by more than this (in radians) are rounded.
+const CORNER_ANGLE: f32 = PI / 6.;
+
+type Point = (f32, f32);
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn scale(a: Point, factor: f32) -> Point {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn length(a: Point) -> f32 {
+    a.0.hypot(a.1)
+}
+
+fn normalize(a: Point) -> Point {
+    scale(a, 1. / length(a))
+}
+
+/// The angle from direction `a` to direction `b`, positive turning left (y up).
+fn turn(a: Point, b: Point) -> f32 {
+    (a.0 * b.1 - a.1 * b.0).atan2(a.0 * b.0 + a.1 * b.1)
+}
+
+/// Points of the arc around `center` of `radius` from angle `from` turning by `sweep`, the
+/// start excluded.
+fn arc(points: &mut Vec<Point>, center: Point, radius: f32, from: f32, sweep: f32) {
+    let step = 2. * (1. - ARC_TOLERANCE_FONT_UNITS / radius).clamp(-1., 1.).acos();
+    let steps = (sweep.abs() / step.max(1e-3)).ceil().clamp(1., 256.) as usize;
+    for i in 1..=steps {
+        let angle = from + sweep * i as f32 / steps as f32;
+        points.push((center.0 + radius * angle.cos(), center.1 + radius * angle.sin()));
+    }
+}
+
+/// The closed polyline moved `amount` to the left of its edges, outwards for the clockwise
+/// outer contours and counter-clockwise holes TrueType outlines have. Where the moved edges
+/// part they're joined by an arc around the corner, where they cross they're cut where they
+/// cross or, when one is too short for that, joined through the corner: the loops that makes
+/// are inside the shape and disappear in the union.
+fn offset_polyline(contour: &Polyline, amount: f32) -> Polyline {
+    let mut corners: Vec<Point> = contour.points.clone();
+    corners.dedup();
+    if corners.len() > 1 && corners.first() == corners.last() {
+        corners.pop();
+    }
+    let n = corners.len();
+    if n < 3 {
+        return Polyline::default();
+    }
+    let left = |direction: Point| scale((-direction.1, direction.0), amount);
+    let mut points = Vec::with_capacity(n * 2);
+    for i in 0..n {
+        let (previous, corner, next) = (corners[(i + n - 1) % n], corners[i], corners[(i + 1) % n]);
+        let (incoming, outgoing) = (sub(corner, previous), sub(next, corner));
+        let (incoming_length, outgoing_length) = (length(incoming), length(outgoing));
+        let (incoming, outgoing) = (normalize(incoming), normalize(outgoing));
+        let (start, end) = (add(corner, left(incoming)), add(corner, left(outgoing)));
+        let sweep = turn(incoming, outgoing);
+        // an outset parts at right turns, an inset at left turns
+        if (sweep < 0.) == (amount > 0.) {
+            points.push(start);
+            let from = (start.1 - corner.1).atan2(start.0 - corner.0);
+            arc(&mut points, corner, amount.abs(), from, sweep);
+            continue;
+        }
+        // the moved edges cross this far back from the corner along each of them
+        let overlap = (sweep / 2.).abs().tan() * amount.abs();
+        if overlap <= incoming_length.min(outgoing_length) {
+            points.push(sub(start, scale(incoming, overlap)));
+        } else {
+            points.extend([start, corner, end]);
+        }
+    }
+    points.push(points[0]);
+    Polyline { points }
+}
+
+/// An outline's contours with overlaps removed and TrueType's winding, as polylines.
+fn union_polylines(outline: &Outline) -> Option<Vec<Polyline>> {
+    let glyph = GlyphData::from_outline(outline).ok()?.remove_overlaps();
+    Some(glyph.outline().flatten(ARC_TOLERANCE_FONT_UNITS))
+}
+
+impl Outline {
+    /// The outline grown by `amount` font units on every side, or shrunk for a negative
+    /// amount, with round joins: counters and holes shrink as the strokes grow. Overlaps are
+    /// removed first and the result is a union without any, a polyline outline in whole font
+    /// units like `GlyphData::remove_overlaps` makes, and shapes an inset removes entirely
+    /// are gone from it. Outlines with points that don't fit glyf come back unchanged.
+    pub fn offset(&self, amount_font_units: f32) -> Outline {
+        let Some(contours) = union_polylines(self) else {
+            return self.clone();
+        };
+        if amount_font_units == 0. {
+            return union_contours(&contours, |winding| winding != 0).outline();
+        }
+        let offset: Vec<Polyline> =
+            contours.iter().map(|contour| offset_polyline(contour, amount_font_units)).collect();
+        // clockwise contours wind -1: keep what the moved contours still cover that way, not
+        // the inside out loops of parts an inset went past
+        union_contours(&offset, |winding| winding < 0).outline()
+    }
+
+    /// The outline with its sharp corners (on-curve points turning more than 30 degrees)
+    /// rounded to arcs of `radius` font units, as one quadratic curve up to a right angle and
+    /// two past it. The radius shrinks where the segments next to a corner are too short for
+    /// it, so that corners sharing a segment meet at most halfway along it.
+    pub fn round_corners(&self, radius: f32) -> Outline {
+        let mut rounded = Outline::default();
+        for contour in &self.contours {
+            rounded.contours.push(round_contour(contour, radius.max(0.)));
+        }
+        rounded.update_bbox();
+        rounded
+    }
+}
+
+fn round_contour(contour: &Contour, radius: f32) -> Contour {
+    let points = &contour.points;
+    let n = points.len();
+    if n < 3 || radius == 0. {
+        return contour.clone();
+    }
+    let position = |point: &OutlinePoint| (point.x, point.y);
+    let on = |(x, y): Point| OutlinePoint { x, y, on_curve: true };
+    let off = |(x, y): Point| OutlinePoint { x, y, on_curve: false };
+    let mut rounded = Vec::with_capacity(n * 3);
+    for i in 0..n {
+        let point = points[i];
+        let (previous, next) = (position(&points[(i + n - 1) % n]), position(&points[(i + 1) % n]));
+        let corner = position(&point);
+        let (to_previous, to_next) = (length(sub(corner, previous)), length(sub(next, corner)));
+        if !point.on_curve || to_previous == 0. || to_next == 0. {
+            rounded.push(point);
+            continue;
+        }
+        let (incoming, outgoing) = (normalize(sub(corner, previous)), normalize(sub(next, corner)));
+        let signed_angle = turn(incoming, outgoing);
+        let angle = signed_angle.abs();
+        if angle <= CORNER_ANGLE {
+            rounded.push(point);
+            continue;
+        }
+        // the arc touches the segments `cut` from the corner, the radius shrinking to fit
+        let cut = (radius * (angle / 2.).tan()).min(to_previous / 2.).min(to_next / 2.);
+        let radius = cut / (angle / 2.).tan();
+        let (start, end) = (sub(corner, scale(incoming, cut)), add(corner, scale(outgoing, cut)));
+        rounded.push(on(start));
+        if angle <= FRAC_PI_2 {
+            // a quadratic from the tangent points through the corner is close to the arc
+            rounded.push(off(corner));
+        } else {
+            // two halves meeting at the middle of the arc, each turning half as much
+            let half_cut = radius * (angle / 4.).tan();
+            let (sin, cos) = (signed_angle / 2.).sin_cos();
+            let middle_direction = (incoming.0 * cos - incoming.1 * sin, incoming.0 * sin + incoming.1 * cos);
+            let first_control = add(start, scale(incoming, half_cut));
+            let middle = add(first_control, scale(middle_direction, half_cut));
+            let second_control = sub(end, scale(outgoing, half_cut));
+            rounded.extend([off(first_control), on(middle), off(second_control)]);
+        }
+        rounded.push(on(end));
+    }
+    Contour { points: rounded }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use crate::font::Font;
+use crate::outline::{CubicSegment, Outline};
+use crate::number::fixed;
+
+/// Units per em of PDF glyph space, what Type3 procedures and font widths are in.
+pub const PDF_UNITS_PER_EM: u16 = 1000;
+
+/// How coordinates scaled to another units per em are rounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum UnitRounding {
+    /// Keep the exact fraction
+    #[default]
+    None,
+    /// To the nearest unit, halves away from zero
+    Round,
+    Floor,
+    Ceil,
+}
+
+impl UnitRounding {
+    fn apply(self, value: f64) -> f32 {
+        (match self {
+            UnitRounding::None => value,
+            UnitRounding::Round => value.round(),
+            UnitRounding::Floor => value.floor(),
+            UnitRounding::Ceil => value.ceil(),
+        }) as f32
+    }
+}
+
+impl Font {
+    /// `value` in font units scaled to `target_upem` units per em, computed in f64 so that a
+    /// scale like 1000 / 2048 doesn't lose precision before rounding.
+    fn to_units(&self, value: f32, target_upem: u16, rounding: UnitRounding) -> f32 {
+        rounding.apply(value as f64 * target_upem as f64 / self.units_per_em().max(1) as f64)
+    }
+
+    /// The outline of a glyph (composites resolved) in a space of `target_upem` units per em,
+    /// exactly scaled. Empty for glyphs past the end of the font.
+    pub fn glyph_outline_scaled(&self, index: u16, target_upem: u16) -> Outline {
+        self.glyph_outline_scaled_with(index, target_upem, UnitRounding::None)
+    }
+
+    /// Like `glyph_outline_scaled` with every coordinate rounded by `rounding`; `bbox` is that
+    /// of the rounded points.
+    pub fn glyph_outline_scaled_with(&self, index: u16, target_upem: u16, rounding: UnitRounding) -> Outline {
+        let mut outline = self.glyph_outline(index).unwrap_or_default();
+        for point in outline.contours.iter_mut().flat_map(|contour| contour.points.iter_mut()) {
+            point.x = self.to_units(point.x, target_upem, rounding);
+            point.y = self.to_units(point.y, target_upem, rounding);
+        }
+        outline.update_bbox();
+        outline
+    }
+
+    /// The advance width of a glyph (at the current variation coordinates) in a space of
+    /// `target_upem` units per em.
+    pub fn advance_width_scaled(&self, index: u16, target_upem: u16, rounding: UnitRounding) -> f32 {
+        self.to_units(self.advance_width(index) as f32, target_upem, rounding)
+    }
+
+    /// A Type3 glyph procedure for each distinct character of `chars`, in the order they first
+    /// appear, in 1000 units per em (a FontMatrix of [0.001 0 0 0.001 0 0]). Each one starts with
+    /// `wx 0 llx lly urx ury d1` and fills the outline with m/l/c/h and f, the quadratic curves
+    /// raised to cubics. Unmapped characters get .notdef.
+    pub fn to_type3_glyph_procs(&self, chars: &str) -> Vec<(char, String)> {
+        let mut procs: Vec<(char, String)> = Vec::new();
+        for c in chars.chars() {
+            if procs.iter().any(|(seen, _)| *seen == c) {
+                continue;
+            }
+            let index = self.glyph_index_for_char(c).unwrap_or(0);
+            procs.push((c, self.type3_glyph_proc(index)));
+        }
+        procs
+    }
+
+    fn type3_glyph_proc(&self, index: u16) -> String {
+        let outline = self.glyph_outline_scaled(index, PDF_UNITS_PER_EM);
+        let width = self.advance_width_scaled(index, PDF_UNITS_PER_EM, UnitRounding::None);
+        let (x_min, y_min, x_max, y_max) = outline.tight_bbox();
+        let bbox = [x_min.floor(), y_min.floor(), x_max.ceil(), y_max.ceil()].map(|value| fixed(value, 0));
+        let mut proc = format!("{} 0 {} d1\n", fixed(width, 3), bbox.join(" "));
+        let contours = outline.to_cubics();
+        if contours.iter().all(|contour| contour.segments.is_empty()) {
+            return proc;
+        }
+        let point = |p: (f32, f32)| format!("{} {}", fixed(p.0, 3), fixed(p.1, 3));
+        for contour in &contours {
+            let Some(first) = contour.segments.first() else {
+                continue;
+            };
+            let _ = writeln!(proc, "{} m", point(first.start()));
+            for segment in &contour.segments {
+                match *segment {
+                    CubicSegment::Line(_, p1) => {
+                        let _ = writeln!(proc, "{} l", point(p1));
+                    }
+                    CubicSegment::Cubic(_, c1, c2, p1) => {
+                        let _ = writeln!(proc, "{} {} {} c", point(c1), point(c2), point(p1));
+                    }
+                }
+            }
+            proc.push_str("h\n");
+        }
+        proc.push_str("f\n");
+        proc
+    }
+}
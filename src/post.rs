@@ -0,0 +1,103 @@
+use anyhow::Result;
+
+use crate::reader::FontReader;
+
+/// The 258 standard Macintosh glyph names used by post table formats 1 and 2.
+const MAC_GLYPH_NAMES: [&str; 258] = [
+    ".notdef", ".null", "nonmarkingreturn", "space", "exclam", "quotedbl", "numbersign", "dollar",
+    "percent", "ampersand", "quotesingle", "parenleft", "parenright", "asterisk", "plus", "comma",
+    "hyphen", "period", "slash", "zero", "one", "two", "three", "four", "five", "six", "seven",
+    "eight", "nine", "colon", "semicolon", "less", "equal", "greater", "question", "at", "A", "B",
+    "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U",
+    "V", "W", "X", "Y", "Z", "bracketleft", "backslash", "bracketright", "asciicircum",
+    "underscore", "grave", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n",
+    "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "braceleft", "bar", "braceright",
+    "asciitilde", "Adieresis", "Aring", "Ccedilla", "Eacute", "Ntilde", "Odieresis", "Udieresis",
+    "aacute", "agrave", "acircumflex", "adieresis", "atilde", "aring", "ccedilla", "eacute",
+    "egrave", "ecircumflex", "edieresis", "iacute", "igrave", "icircumflex", "idieresis",
+    "ntilde", "oacute", "ograve", "ocircumflex", "odieresis", "otilde", "uacute", "ugrave",
+    "ucircumflex", "udieresis", "dagger", "degree", "cent", "sterling", "section", "bullet",
+    "paragraph", "germandbls", "registered", "copyright", "trademark", "acute", "dieresis",
+    "notequal", "AE", "Oslash", "infinity", "plusminus", "lessequal", "greaterequal", "yen", "mu",
+    "partialdiff", "summation", "product", "pi", "integral", "ordfeminine", "ordmasculine",
+    "Omega", "ae", "oslash", "questiondown", "exclamdown", "logicalnot", "radical", "florin",
+    "approxequal", "Delta", "guillemotleft", "guillemotright", "ellipsis", "nonbreakingspace",
+    "Agrave", "Atilde", "Otilde", "OE", "oe", "endash", "emdash", "quotedblleft", "quotedblright",
+    "quoteleft", "quoteright", "divide", "lozenge", "ydieresis", "Ydieresis", "fraction",
+    "currency", "guilsinglleft", "guilsinglright", "fi", "fl", "daggerdbl", "periodcentered",
+    "quotesinglbase", "quotedblbase", "perthousand", "Acircumflex", "Ecircumflex", "Aacute",
+    "Edieresis", "Egrave", "Iacute", "Icircumflex", "Idieresis", "Igrave", "Oacute",
+    "Ocircumflex", "apple", "Ograve", "Uacute", "Ucircumflex", "Ugrave", "dotlessi",
+    "circumflex", "tilde", "macron", "breve", "dotaccent", "ring", "cedilla", "hungarumlaut",
+    "ogonek", "caron", "Lslash", "lslash", "Scaron", "scaron", "Zcaron", "zcaron", "brokenbar",
+    "Eth", "eth", "Yacute", "yacute", "Thorn", "thorn", "minus", "multiply", "onesuperior",
+    "twosuperior", "threesuperior", "onehalf", "onequarter", "threequarters", "franc", "Gbreve",
+    "gbreve", "Idotaccent", "Scedilla", "scedilla", "Cacute", "cacute", "Ccaron", "ccaron",
+    "dcroat",
+];
+
+/// The `post` table: glyph names (only formats 1 and 2 carry them) and a few flags.
+#[derive(Debug, Clone, Default)]
+pub struct Post {
+    /// Top of the underline from the baseline, in font units (negative below it)
+    pub underline_position: i16,
+    pub underline_thickness: i16,
+    pub is_fixed_pitch: bool,
+    names: Vec<String>,
+}
+
+impl Post {
+    pub fn from_reader(reader: &mut FontReader, post_offset: u32, post_length: u32, num_glyphs: u16) -> Result<Post> {
+        reader.seek(post_offset as u64)?;
+        let version = reader.read_u32()?;
+        reader.skip(4)?; // skip italicAngle
+        let underline_position = reader.read_i16()?;
+        let underline_thickness = reader.read_i16()?;
+        let is_fixed_pitch = reader.read_u32()? != 0;
+        let names = match version {
+            0x00010000 => MAC_GLYPH_NAMES.iter().map(|name| name.to_string()).collect(),
+            0x00020000 => {
+                reader.seek(post_offset as u64 + 32)?; // skip the fixed size header
+                let num_indices = reader.read_u16()?;
+                let mut name_indices = Vec::with_capacity(num_indices as usize);
+                for _ in 0..num_indices {
+                    name_indices.push(reader.read_u16()?);
+                }
+
+                // the rest of the table is a list of pascal strings
+                let table_end = post_offset as u64 + post_length as u64;
+                let mut custom_names: Vec<String> = Vec::new();
+                while reader.position() < table_end {
+                    let len = reader.read_u8()?;
+                    let mut buf = vec![0u8; len as usize];
+                    reader.read_exact(&mut buf)?;
+                    custom_names.push(String::from_utf8_lossy(&buf).into_owned());
+                }
+
+                name_indices
+                    .iter()
+                    .map(|index| match *index as usize {
+                        i if i < 258 => MAC_GLYPH_NAMES[i].to_string(),
+                        i => custom_names.get(i - 258).cloned().unwrap_or_default(),
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        let mut names: Vec<String> = names;
+        names.truncate(num_glyphs as usize);
+        Ok(Post {
+            underline_position,
+            underline_thickness,
+            is_fixed_pitch,
+            names,
+        })
+    }
+
+    pub fn glyph_name(&self, index: u16) -> Option<&str> {
+        self.names
+            .get(index as usize)
+            .map(|name| name.as_str())
+            .filter(|name| !name.is_empty())
+    }
+}
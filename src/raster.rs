@@ -0,0 +1,620 @@
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+use crate::flatten::{flatten_segments, tolerance_bucket, Contour};
+use crate::font::Font;
+use crate::geom;
+use crate::glyph::Segment;
+use crate::outline::Outline;
+use crate::simd;
+use crate::size::FontSize;
+use crate::source::GlyphSource;
+use crate::transform::FontUnitsToPixels;
+
+/// Maximum distance in pixels between the flattened polylines and the real curves.
+pub const FLATTEN_TOLERANCE_PX: f32 = 0.1;
+
+/// How overlapping and nested contours combine into coverage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FillRule {
+    /// Inside wherever the winding number isn't zero, what TrueType outlines are designed for
+    #[default]
+    NonZero,
+    /// Inside wherever the winding number is odd, for fonts whose contour directions are broken
+    EvenOdd,
+}
+
+/// Physical order of the color subpixels on an LCD panel, left to right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SubpixelOrder {
+    #[default]
+    Rgb,
+    Bgr,
+}
+
+/// What kind of coverage a rasterized glyph carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RenderMode {
+    /// One coverage value per pixel
+    #[default]
+    Grayscale,
+    /// One coverage value per color channel, for LCD panels with horizontal subpixels
+    Lcd(SubpixelOrder),
+}
+
+/// 5-tap FIR filter spreading each subpixel over its neighbours to tame LCD color fringes (sums to 1).
+const LCD_FILTER: [f32; 5] = [1. / 9., 2. / 9., 3. / 9., 2. / 9., 1. / 9.];
+
+/// How outlines are fitted to the pixel grid before they're rasterized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Hinting {
+    /// The outline only scaled
+    #[default]
+    None,
+    /// Snapped vertically (blue zones and horizontal stems) by the autofitter
+    AutoVertical,
+    /// Grid fitted by the font's TrueType instructions at the nearest integer ppem, the plain
+    /// outline for glyphs they can't hint. A font without instructions gets a warning
+    Native,
+    /// `Native` for fonts with instructions (fpgm, prep or the glyph's own), `AutoVertical` for
+    /// the others and for the glyphs the instructions fail on
+    NativeIfPresent,
+}
+
+impl Hinting {
+    pub const ALL: [Hinting; 4] = [Hinting::None, Hinting::AutoVertical, Hinting::Native, Hinting::NativeIfPresent];
+
+    /// Whether the font's instructions may run, which places glyphs on whole pixels and moves
+    /// the pen by the hinted advances.
+    pub fn is_native(self) -> bool {
+        matches!(self, Hinting::Native | Hinting::NativeIfPresent)
+    }
+}
+
+/// How glyphs are rasterized. Made with `default` (nonzero fill, grayscale, no hinting, outlines
+/// over embedded bitmaps) and the `with_*` methods or parsed from settings (see `from_str`),
+/// fields can be added without breaking callers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct RasterOptions {
+    pub fill_rule: FillRule,
+    pub mode: RenderMode,
+    pub hinting: Hinting,
+    /// Use the bitmaps embedded in EBLC/EBDT when the font has a strike for the exact pixel
+    /// size, instead of rasterizing the outline
+    pub prefer_embedded_bitmaps: bool,
+}
+
+impl RasterOptions {
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: RenderMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_hinting(mut self, hinting: Hinting) -> Self {
+        self.hinting = hinting;
+        self
+    }
+
+    pub fn with_prefer_embedded_bitmaps(mut self, prefer_embedded_bitmaps: bool) -> Self {
+        self.prefer_embedded_bitmaps = prefer_embedded_bitmaps;
+        self
+    }
+}
+
+/// Options written as comma separated `key=value` settings over the defaults, e.g.
+/// `hinting=native,mode=lcd-bgr`: `hinting` takes none (or off), auto-vertical, native (or on)
+/// and native-if-present, `embedded-bitmaps` on/off, `mode` grayscale, lcd (or lcd-rgb) and
+/// lcd-bgr, `fill-rule` nonzero and evenodd.
+impl FromStr for RasterOptions {
+    type Err = anyhow::Error;
+
+    fn from_str(settings: &str) -> Result<RasterOptions> {
+        let mut options = RasterOptions::default();
+        for setting in settings.split(',').map(str::trim).filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting.split_once('=').ok_or_else(|| anyhow!("expected key=value, got {setting}"))?;
+            let switch = || match value {
+                "on" | "true" => Ok(true),
+                "off" | "false" => Ok(false),
+                _ => Err(anyhow!("{key} is on or off, got {value}")),
+            };
+            match key {
+                "hinting" => {
+                    options.hinting = match value {
+                        "none" | "off" => Hinting::None,
+                        "auto-vertical" => Hinting::AutoVertical,
+                        "native" | "on" => Hinting::Native,
+                        "native-if-present" => Hinting::NativeIfPresent,
+                        _ => return Err(anyhow!("unknown hinting {value}")),
+                    }
+                }
+                "embedded-bitmaps" => options.prefer_embedded_bitmaps = switch()?,
+                "mode" => {
+                    options.mode = match value {
+                        "grayscale" => RenderMode::Grayscale,
+                        "lcd" | "lcd-rgb" => RenderMode::Lcd(SubpixelOrder::Rgb),
+                        "lcd-bgr" => RenderMode::Lcd(SubpixelOrder::Bgr),
+                        _ => return Err(anyhow!("unknown mode {value}")),
+                    }
+                }
+                "fill-rule" => {
+                    options.fill_rule = match value {
+                        "nonzero" => FillRule::NonZero,
+                        "evenodd" => FillRule::EvenOdd,
+                        _ => return Err(anyhow!("unknown fill rule {value}")),
+                    }
+                }
+                _ => return Err(anyhow!("unknown raster option {key}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// An 8-bit coverage bitmap of a rasterized glyph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GlyphBitmap {
+    pub width: usize,
+    pub height: usize,
+    /// Horizontal offset of the left edge of the bitmap from the glyph origin, in pixels
+    pub left: i32,
+    /// Distance from the baseline up to the top edge of the bitmap, in pixels
+    pub top: i32,
+    pub mode: RenderMode,
+    /// Coverage row by row from the top: `width * height` values, or `width * height * 3` for
+    /// LCD bitmaps, which are always stored as R, G, B whatever the subpixel order
+    pub data: Vec<u8>,
+}
+
+/// The pixels a glyph bitmap covers, see `raster_bounds`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RasterBounds {
+    /// Horizontal offset of the left edge from the glyph origin, in pixels
+    pub left: i32,
+    /// Distance from the baseline up to the top edge, in pixels
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl GlyphBitmap {
+    pub fn bounds(&self) -> RasterBounds {
+        RasterBounds {
+            left: self.left,
+            top: self.top,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Signed area accumulation rasterizer: every line adds its coverage deltas to a buffer, a prefix
+/// sum along each row then turns those into the final coverage.
+pub struct Rasterizer {
+    width: usize,
+    height: usize,
+    stride: usize,
+    accumulation: Vec<f32>,
+}
+
+impl Rasterizer {
+    pub fn new(width: usize, height: usize) -> Rasterizer {
+        // lines touching the right edge write one and two cells past the last pixel
+        let stride = width + 2;
+        Rasterizer {
+            width,
+            height,
+            stride,
+            accumulation: vec![0.0; stride * height],
+        }
+    }
+
+    /// Empty the rasterizer and make it `width` x `height`, keeping its buffer when it's large
+    /// enough.
+    pub(crate) fn reset(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.stride = width + 2;
+        self.accumulation.clear();
+        self.accumulation.resize(self.stride * height, 0.0);
+    }
+
+    /// Add a line in pixel coordinates (y pointing down, inside the `width` x `height` box).
+    pub fn draw_line(&mut self, p0: (f32, f32), p1: (f32, f32)) {
+        if (p0.1 - p1.1).abs() <= f32::EPSILON {
+            return;
+        }
+        let clamp = |p: (f32, f32)| (p.0.clamp(0., self.width as f32), p.1);
+        let (p0, p1) = (clamp(p0), clamp(p1));
+        let (direction, p0, p1) = if p0.1 < p1.1 { (1.0, p0, p1) } else { (-1.0, p1, p0) };
+        let dxdy = (p1.0 - p0.0) / (p1.1 - p0.1);
+        let mut x = p0.0;
+        if p0.1 < 0. {
+            x -= p0.1 * dxdy;
+        }
+        let y_start = p0.1.max(0.) as usize;
+        let y_end = self.height.min(p1.1.ceil().max(0.) as usize);
+
+        for y in y_start..y_end {
+            let row = y * self.stride;
+            let dy = ((y + 1) as f32).min(p1.1) - (y as f32).max(p0.1);
+            let x_next = x + dxdy * dy;
+            let d = dy * direction;
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let x0_floor = x0.floor();
+            let x0i = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil as usize;
+
+            if x1i <= x0i + 1 {
+                // the line stays within a single pixel column on this row
+                let x_mid = 0.5 * (x + x_next) - x0_floor;
+                self.accumulation[row + x0i] += d - d * x_mid;
+                self.accumulation[row + x0i + 1] += d * x_mid;
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1_ceil + 1.0;
+                let a_max = 0.5 * s * x1f * x1f;
+                self.accumulation[row + x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    self.accumulation[row + x0i + 1] += d * (1.0 - a0 - a_max);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.accumulation[row + x0i + 1] += d * (a1 - a0);
+                    for xi in (x0i + 2)..(x1i - 1) {
+                        self.accumulation[row + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    self.accumulation[row + x1i - 1] += d * (1.0 - a2 - a_max);
+                }
+                self.accumulation[row + x1i] += d * a_max;
+            }
+            x = x_next;
+        }
+    }
+
+    /// Add every edge of the polylines, mapping font units to pixels with `transform`.
+    pub fn draw_contours(&mut self, contours: &[Contour], transform: impl Fn((f32, f32)) -> (f32, f32)) {
+        for contour in contours {
+            for (p0, p1) in contour.edges() {
+                self.draw_line(transform(p0), transform(p1));
+            }
+        }
+    }
+
+    /// Resolve the accumulated deltas into coverage, summing them along each row with
+    /// `simd::accumulate`. What `spans` reports, written into a `width * height` bitmap.
+    pub fn coverage(&self, fill_rule: FillRule) -> Vec<u8> {
+        let mut data = vec![0; self.width * self.height];
+        self.spans(fill_rule, |y, x, len, coverage| {
+            let start = y * self.width + x;
+            data[start..start + len].fill(coverage);
+        });
+        data
+    }
+
+    /// Resolve the accumulated deltas into runs of pixels of the same non-zero coverage, as
+    /// `(row, column, length, coverage)` from the top left. Runs come row by row from the top
+    /// and left to right within a row, they don't overlap and pixels without coverage are in
+    /// none of them.
+    pub fn spans(&self, fill_rule: FillRule, callback: impl FnMut(usize, usize, usize, u8)) {
+        self.spans_with(&mut Vec::new(), fill_rule, callback);
+    }
+
+    /// `spans`, summing each row into `sums` instead of a buffer of its own.
+    pub(crate) fn spans_with(
+        &self,
+        sums: &mut Vec<f32>,
+        fill_rule: FillRule,
+        mut callback: impl FnMut(usize, usize, usize, u8),
+    ) {
+        sums.resize(self.width, 0.0);
+        for (y, row) in self.accumulation.chunks(self.stride).enumerate() {
+            simd::accumulate(&row[..self.width], sums);
+            let mut run: Option<(usize, u8)> = None;
+            for (x, accumulated) in sums.iter().enumerate() {
+                let coverage = resolve_coverage(*accumulated, fill_rule);
+                match run {
+                    Some((_, run_coverage)) if run_coverage == coverage => continue,
+                    Some((start, run_coverage)) => callback(y, start, x - start, run_coverage),
+                    None => {}
+                }
+                run = (coverage != 0).then_some((x, coverage));
+            }
+            if let Some((start, coverage)) = run {
+                callback(y, start, self.width - start, coverage);
+            }
+        }
+    }
+}
+
+/// The 8-bit coverage of a pixel whose deltas sum to `accumulated`.
+fn resolve_coverage(accumulated: f32, fill_rule: FillRule) -> u8 {
+    let coverage = match fill_rule {
+        FillRule::NonZero => accumulated.abs().min(1.0),
+        FillRule::EvenOdd => {
+            // fold the winding number so that 1 is inside and 0 and 2 are outside
+            let folded = accumulated.abs() % 2.0;
+            if folded > 1.0 {
+                2.0 - folded
+            } else {
+                folded
+            }
+        }
+    };
+    (coverage * 255.0 + 0.5) as u8
+}
+
+/// Rasterize an outline in font units, scaled by `scale` pixels per font unit, into runs of
+/// pixels of the same coverage for callers blending into surfaces of their own, as
+/// `callback(y, x, length, coverage)`. `x` counts pixels right of the glyph origin and `y`
+/// pixels down from the baseline, so the run covers the pixels `x..x + length` of the row
+/// whose top is `y` pixels below it (above it when negative). The runs come sorted by `y`
+/// then `x`, they don't overlap and pixels the outline doesn't cover are in none of them:
+/// drawn into a bitmap they make exactly the grayscale bitmap `Font::rasterize_glyph` makes
+/// of a glyph with this outline.
+pub fn rasterize_spans(outline: &Outline, scale: f32, mut callback: impl FnMut(i32, i32, u32, u8)) {
+    let segments = FontUnitsToPixels::scaling(scale).apply_segments(&outline.segments());
+    let bounds = pixel_bounds(&segments, 0., RenderMode::Grayscale);
+    if bounds == RasterBounds::default() {
+        return;
+    }
+    // flattened in font units as finely as `Font::flattened_glyph` does for unhinted glyphs
+    let tolerance = 2f32.powi(tolerance_bucket(FLATTEN_TOLERANCE_PX / scale));
+    let scaled = |contour: Contour| Contour {
+        points: contour.points.iter().map(|(x, y)| (x * scale, y * scale)).collect(),
+    };
+    let contours: Vec<Contour> = outline.flatten(tolerance).into_iter().map(scaled).collect();
+    let rasterizer = rasterize_grayscale(&contours, bounds);
+    rasterizer.spans(FillRule::NonZero, |row, column, len, coverage| {
+        callback(row as i32 - bounds.top, bounds.left + column as i32, len as u32, coverage)
+    });
+}
+
+impl Font {
+    /// Rasterize a glyph into a coverage bitmap at the given pixel size (pixels per em).
+    pub fn rasterize_glyph(&self, index: u16, size: impl Into<FontSize>) -> Option<GlyphBitmap> {
+        let px_size = size.into().px_per_em();
+        self.rasterize_glyph_with(index, px_size, &RasterOptions::default())
+    }
+
+    /// The glyph grid fitted as `options.hinting` says, see `Hinting`. With
+    /// `options.prefer_embedded_bitmaps` an embedded bitmap of the nearest ppem wins over that.
+    pub fn rasterize_glyph_with(
+        &self,
+        index: u16,
+        size: impl Into<FontSize>,
+        options: &RasterOptions,
+    ) -> Option<GlyphBitmap> {
+        let px_size = size.into().px_per_em();
+        rasterize(self, index, px_size, options)
+    }
+
+    /// The bitmap `rasterize_glyph` would make for the glyph drawn `position_fraction` of a pixel
+    /// right of a whole pixel (as `draw_glyphs_cached` does), computed from the outline without
+    /// rasterizing. An empty rectangle at the origin for glyphs without an outline.
+    pub fn raster_bounds(&self, index: u16, px_size: f32, position_fraction: f32) -> RasterBounds {
+        raster_bounds(self, index, px_size, &RasterOptions::default(), position_fraction).unwrap_or_default()
+    }
+}
+
+/// Rasterize a glyph of any source at `px_size`: its bitmap at the nearest ppem when the options
+/// prefer those and it has one, else its scaled outline.
+pub fn rasterize<S: GlyphSource + ?Sized>(
+    source: &S,
+    index: u16,
+    px_size: f32,
+    options: &RasterOptions,
+) -> Option<GlyphBitmap> {
+    rasterize_at(source, index, px_size, options, 0.)
+}
+
+/// `rasterize` with the outline moved `x_offset` pixels right of the glyph origin first, to
+/// draw the glyph at a fraction of a pixel from a bitmap placed on whole pixels. Embedded
+/// bitmaps can't move, they ignore the offset.
+pub fn rasterize_at<S: GlyphSource + ?Sized>(
+    source: &S,
+    index: u16,
+    px_size: f32,
+    options: &RasterOptions,
+    x_offset: f32,
+) -> Option<GlyphBitmap> {
+    let embedded = options
+        .prefer_embedded_bitmaps
+        .then(|| source.bitmap(index, px_size.round() as u16))
+        .flatten();
+    if let Some(mut bitmap) = embedded {
+        if let RenderMode::Lcd(_) = options.mode {
+            // the same coverage on each subpixel, the pixels are already what the designer drew
+            bitmap.data = bitmap.data.iter().flat_map(|coverage| [*coverage; 3]).collect();
+            bitmap.mode = options.mode;
+        }
+        return Some(bitmap);
+    }
+    let segments = source.scaled_outline(index, px_size, options)?;
+    let bounds = pixel_bounds(&segments, x_offset, options.mode);
+    let mut contours = source
+        .flattened_outline(index, px_size, options, FLATTEN_TOLERANCE_PX)
+        .unwrap_or_else(|| flatten_segments(segments, FLATTEN_TOLERANCE_PX));
+    if x_offset != 0. {
+        for point in contours.iter_mut().flat_map(|contour| contour.points.iter_mut()) {
+            point.0 += x_offset;
+        }
+    }
+    Some(rasterize_contours(&contours, bounds, options.fill_rule, options.mode))
+}
+
+/// The size and place of the bitmap `rasterize_at` makes for `source`'s glyph, without
+/// rasterizing it: the pixels the scaled outline reaches into, curve extrema solved exactly,
+/// and for LCD bitmaps a pixel more on each side for the filter. Embedded bitmaps are looked up.
+/// `None` when the glyph has no outline, like `rasterize_at`.
+pub fn raster_bounds<S: GlyphSource + ?Sized>(
+    source: &S,
+    index: u16,
+    px_size: f32,
+    options: &RasterOptions,
+    x_offset: f32,
+) -> Option<RasterBounds> {
+    let embedded = options
+        .prefer_embedded_bitmaps
+        .then(|| source.bitmap(index, px_size.round() as u16))
+        .flatten();
+    if let Some(bitmap) = embedded {
+        return Some(bitmap.bounds());
+    }
+    let segments = source.scaled_outline(index, px_size, options)?;
+    Some(pixel_bounds(&segments, x_offset, options.mode))
+}
+
+/// The whole pixels covering the contours (given in pixels, y up) moved `x_offset` right, empty
+/// at the origin without any segment.
+fn pixel_bounds(contours: &[Vec<Segment>], x_offset: f32, mode: RenderMode) -> RasterBounds {
+    let Some((x_min, y_min, x_max, y_max)) = geom::segments_bounds(contours) else {
+        return RasterBounds::default();
+    };
+    let (x_min, x_max) = (x_min + x_offset, x_max + x_offset);
+    let mut left = x_min.floor() as i32;
+    let mut right = x_max.ceil() as i32;
+    if let RenderMode::Lcd(_) = mode {
+        // the filter spreads coverage two subpixels out, which can reach into the next pixel
+        left -= 1;
+        right += 1;
+    }
+    let top = y_max.ceil() as i32;
+    let bottom = y_min.floor() as i32;
+    RasterBounds {
+        left,
+        top,
+        width: (right - left) as usize,
+        height: (top - bottom) as usize,
+    }
+}
+
+/// Rasterize contours given in pixels (y up, relative to the glyph origin) into a bitmap
+/// covering `bounds`, see `pixel_bounds`.
+fn rasterize_contours(
+    contours: &[Contour],
+    bounds: RasterBounds,
+    fill_rule: FillRule,
+    mode: RenderMode,
+) -> GlyphBitmap {
+    if bounds == RasterBounds::default() {
+        return GlyphBitmap {
+            mode,
+            ..GlyphBitmap::default()
+        };
+    }
+    let RasterBounds { left, top, width, height } = bounds;
+    let to_bitmap = FontUnitsToPixels::with_scale(1., (-left as f32, top as f32));
+    let data = match mode {
+        RenderMode::Grayscale => rasterize_grayscale(contours, bounds).coverage(fill_rule),
+        RenderMode::Lcd(order) => {
+            let mut rasterizer = Rasterizer::new(width * 3, height);
+            // three samples across each pixel
+            rasterizer.draw_contours(contours, |p| {
+                let (x, y) = to_bitmap.apply(p);
+                (x * 3., y)
+            });
+            lcd_filter(&rasterizer.coverage(fill_rule), width, order)
+        }
+    };
+    GlyphBitmap {
+        width,
+        height,
+        left,
+        top,
+        mode,
+        data,
+    }
+}
+
+/// A rasterizer for `bounds` with the contours (in pixels, y up) drawn into it.
+fn rasterize_grayscale(contours: &[Contour], bounds: RasterBounds) -> Rasterizer {
+    let to_bitmap = FontUnitsToPixels::with_scale(1., (-bounds.left as f32, bounds.top as f32));
+    let mut rasterizer = Rasterizer::new(bounds.width, bounds.height);
+    rasterizer.draw_contours(contours, |p| to_bitmap.apply(p));
+    rasterizer
+}
+
+/// Filter 3x horizontal coverage across subpixels and pack it into R, G, B pixels.
+fn lcd_filter(subpixels: &[u8], width: usize, order: SubpixelOrder) -> Vec<u8> {
+    let sub_width = width * 3;
+    let mut data = Vec::with_capacity(subpixels.len());
+    for row in subpixels.chunks(sub_width) {
+        let filtered: Vec<u8> = (0..sub_width)
+            .map(|s| {
+                let sum: f32 = LCD_FILTER
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, weight)| {
+                        let i = (s + k).checked_sub(2)?;
+                        row.get(i).map(|c| *c as f32 * weight)
+                    })
+                    .sum();
+                (sum + 0.5).min(255.) as u8
+            })
+            .collect();
+        for pixel in filtered.chunks(3) {
+            match order {
+                SubpixelOrder::Rgb => data.extend_from_slice(pixel),
+                SubpixelOrder::Bgr => data.extend([pixel[2], pixel[1], pixel[0]]),
+            }
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The grayscale bitmap `rasterize_spans` describes, as large as `expected` so that a span
+    /// outside it fails the comparison.
+    fn bitmap_from_spans(outline: &Outline, scale: f32, expected: &GlyphBitmap) -> Vec<u8> {
+        let mut data = vec![0; expected.width * expected.height];
+        let mut last = None;
+        rasterize_spans(outline, scale, |y, x, len, coverage| {
+            assert!(last <= Some((y, x)), "span ({y}, {x}) after {last:?}");
+            assert!(len > 0 && coverage > 0);
+            let (row, column) = (y + expected.top, x - expected.left);
+            assert!(row >= 0 && (row as usize) < expected.height, "row {y} outside the bitmap");
+            assert!(column >= 0 && column as usize + len as usize <= expected.width);
+            let start = row as usize * expected.width + column as usize;
+            for pixel in &mut data[start..start + len as usize] {
+                assert_eq!(*pixel, 0, "spans overlap at ({y}, {x})");
+                *pixel = coverage;
+            }
+            last = Some((y, x + len as i32));
+        });
+        data
+    }
+
+    #[test]
+    fn spans_rebuild_the_glyph_bitmaps() {
+        let font = Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf")).unwrap();
+        for c in ['A', 'g', 'O', '@', '%', 'ß', '.'] {
+            let index = font.glyph_index_for_char(c).unwrap();
+            let outline = font.glyph_outline(index).unwrap();
+            for px_size in [9., 14., 31.5, 120.] {
+                let expected = font.rasterize_glyph(index, px_size).unwrap();
+                let rebuilt = bitmap_from_spans(&outline, font.scale_for_px(px_size), &expected);
+                assert!(rebuilt == expected.data, "{c:?} at {px_size}px");
+            }
+        }
+    }
+
+    #[test]
+    fn glyphs_without_an_outline_have_no_spans() {
+        let font = Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf")).unwrap();
+        let space = font.glyph_index_for_char(' ').unwrap();
+        let outline = font.glyph_outline(space).unwrap_or_default();
+        rasterize_spans(&outline, font.scale_for_px(14.), |y, x, _, _| panic!("span at ({y}, {x})"));
+    }
+}
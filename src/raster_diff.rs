@@ -0,0 +1,244 @@
+use raqote::{DrawTarget, SolidSource};
+
+use crate::bitmap::{Bitmap, BitmapFormat};
+use crate::flatten::flatten_segments;
+use crate::font::Font;
+use crate::geom::{segments_bounds, union};
+use crate::outline::Outline;
+use crate::raster::{FillRule, RasterOptions, Rasterizer};
+use crate::render::layout_line;
+
+/// How two renderings differ pixel by pixel, a pixel's difference being the largest of its
+/// channels'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterDiff {
+    /// 0 to 255
+    pub max_difference: u8,
+    /// Over all the pixels, unchanged ones included
+    pub mean_difference: f64,
+    pub changed_pixels: usize,
+    pub total_pixels: usize,
+    /// White where the renderings agree, red where they don't, stretched so that the largest
+    /// difference is full red
+    pub heatmap: Bitmap,
+}
+
+/// Compare two images pixel by pixel over the larger of the two, pixels past the edge of the
+/// smaller one reading as transparent black.
+pub fn diff_bitmaps(a: &Bitmap, b: &Bitmap) -> RasterDiff {
+    let (width, height) = (a.width.max(b.width), a.height.max(b.height));
+    let pixel = |bitmap: &Bitmap, x: usize, y: usize| match x < bitmap.width && y < bitmap.height {
+        true => bitmap.get_rgba(x, y),
+        false => [0; 4],
+    };
+    let mut differences = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let (a, b) = (pixel(a, x, y), pixel(b, x, y));
+            differences.push((0..4).map(|channel| a[channel].abs_diff(b[channel])).max().unwrap_or(0));
+        }
+    }
+    let max_difference = differences.iter().copied().max().unwrap_or(0);
+    let total: u64 = differences.iter().map(|difference| *difference as u64).sum();
+    let mut heatmap = Bitmap::new(width, height, BitmapFormat::Rgba8, 4);
+    for (i, difference) in differences.iter().enumerate() {
+        let stretched = (*difference as u32 * 255 / max_difference.max(1) as u32) as u8;
+        heatmap.set_rgba(i % width, i / width, [255, 255 - stretched, 255 - stretched, 255]);
+    }
+    RasterDiff {
+        max_difference,
+        mean_difference: total as f64 / differences.len().max(1) as f64,
+        changed_pixels: differences.iter().filter(|difference| **difference > 0).count(),
+        total_pixels: differences.len(),
+        heatmap,
+    }
+}
+
+/// The size in pixels of the longer side of the frame `assert_outlines_similar!` compares in.
+pub const SIMILARITY_RESOLUTION: usize = 256;
+
+/// How alike two outlines look once rasterized, which doesn't depend on the order of their
+/// contours or on where each contour starts, unlike comparing points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlineSimilarity {
+    /// Covered area both share over area either covers, 1 for the same shape (two empty outlines
+    /// included), 0 for shapes that don't overlap
+    pub iou: f32,
+    /// Mean coverage difference over the frame, 0 to 1
+    pub mean_difference: f32,
+}
+
+/// Rasterize both outlines with the nonzero rule into one frame around the union of their
+/// bounds, scaled so that its longer side is `resolution` pixels, and compare the coverages. The
+/// frame being shared, an outline that moved or grew doesn't compare as the same shape.
+pub fn outline_similarity(a: &Outline, b: &Outline, resolution: usize) -> OutlineSimilarity {
+    let (a, b) = (a.segments(), b.segments());
+    let bounds = match (segments_bounds(&a), segments_bounds(&b)) {
+        (Some(x), Some(y)) => union(x, y),
+        (Some(bounds), None) | (None, Some(bounds)) => bounds,
+        (None, None) => {
+            return OutlineSimilarity {
+                iou: 1.,
+                mean_difference: 0.,
+            }
+        }
+    };
+    let (x_min, y_min, x_max, y_max) = bounds;
+    let scale = resolution.max(1) as f32 / (x_max - x_min).max(y_max - y_min).max(f32::EPSILON);
+    let width = ((x_max - x_min) * scale).ceil() as usize + 2;
+    let height = ((y_max - y_min) * scale).ceil() as usize + 2;
+    // a pixel of margin on every side, y flipped to point down
+    let to_frame = |(x, y): (f32, f32)| (1. + (x - x_min) * scale, 1. + (y_max - y) * scale);
+    let coverage = |segments: Vec<Vec<_>>| {
+        let mut rasterizer = Rasterizer::new(width, height);
+        rasterizer.draw_contours(&flatten_segments(segments, 0.25 / scale), to_frame);
+        rasterizer.coverage(FillRule::NonZero)
+    };
+    let (a, b) = (coverage(a), coverage(b));
+    let (mut intersection, mut either, mut difference) = (0u64, 0u64, 0u64);
+    for (a, b) in a.iter().zip(&b) {
+        intersection += (*a).min(*b) as u64;
+        either += (*a).max(*b) as u64;
+        difference += a.abs_diff(*b) as u64;
+    }
+    OutlineSimilarity {
+        iou: match either {
+            0 => 1.,
+            _ => intersection as f32 / either as f32,
+        },
+        mean_difference: difference as f32 / (255 * a.len().max(1)) as f32,
+    }
+}
+
+/// Panic unless the outlines `a` and `b` compare with an `OutlineSimilarity::iou` of at least
+/// `threshold` at `SIMILARITY_RESOLUTION`, for checking that a parser change kept glyphs looking
+/// the same even where it reorders their points. Only with the `test-fonts` feature, and in the
+/// crate's own tests.
+#[cfg(any(test, feature = "test-fonts"))]
+#[macro_export]
+macro_rules! assert_outlines_similar {
+    ($a:expr, $b:expr, $threshold:expr $(,)?) => {{
+        let resolution = $crate::raster_diff::SIMILARITY_RESOLUTION;
+        let similarity = $crate::raster_diff::outline_similarity(&$a, &$b, resolution);
+        let threshold: f32 = $threshold;
+        assert!(
+            similarity.iou >= threshold,
+            "outlines differ: IoU {} below {}, mean difference {}",
+            similarity.iou,
+            threshold,
+            similarity.mean_difference,
+        );
+    }};
+}
+
+impl Font {
+    /// The lines of `text` drawn black on white by `draw_text_with` with the `left` options and
+    /// with the `right` ones, on canvases of one size that fits both, and how they differ.
+    pub fn compare_raster_options(
+        &self,
+        text: &str,
+        px_size: f32,
+        left: &RasterOptions,
+        right: &RasterOptions,
+    ) -> (Bitmap, Bitmap, RasterDiff) {
+        let scale = self.scale_for_px(px_size);
+        let line_height = (self.line_height() as f32 * scale).ceil();
+        let margin = (px_size / 4.).ceil() + 2.;
+        let widest = |options: &RasterOptions| {
+            let line_width = |line: &str| layout_line(self, line, (0., 0.), px_size, Some(options)).1;
+            text.lines().map(line_width).fold(0f32, f32::max)
+        };
+        let width = (widest(left).max(widest(right)) + 2. * margin).ceil() as usize;
+        let height = (text.lines().count() as f32 * line_height + 2. * margin).ceil() as usize;
+        let render = |options: &RasterOptions| {
+            let mut dt = DrawTarget::new(width as i32, height as i32);
+            dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+            let black = SolidSource::from_unpremultiplied_argb(0xff, 0, 0, 0);
+            let mut baseline = margin + (self.ascender() as f32 * scale).round();
+            for line in text.lines() {
+                self.draw_text_with(&mut dt, line, (margin, baseline), px_size, black, options);
+                baseline += line_height;
+            }
+            Bitmap::from_argb_premultiplied(width, height, dt.get_data())
+        };
+        let (left, right) = (render(left), render(right));
+        let diff = diff_bitmaps(&left, &right);
+        (left, right, diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(font: &Font, c: char) -> Outline {
+        font.glyph_outline(font.glyph_index_for_char(c).unwrap()).unwrap()
+    }
+
+    fn inconsolata() -> Font {
+        Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf")).unwrap()
+    }
+
+    #[test]
+    fn reordered_contours_look_the_same() {
+        let font = inconsolata();
+        for c in ['8', 'B', '%'] {
+            let outline = glyph(&font, c);
+            assert!(outline.contours.len() > 1, "{c:?}");
+            let mut reordered = outline.clone();
+            reordered.contours.reverse();
+            assert_ne!(reordered, outline);
+            assert_outlines_similar!(outline, reordered, 0.999);
+        }
+    }
+
+    #[test]
+    fn contours_starting_elsewhere_look_the_same() {
+        let font = inconsolata();
+        for c in ['O', 'g', 'S'] {
+            let outline = glyph(&font, c);
+            for by in [1, 2, 5] {
+                let mut rotated = outline.clone();
+                for contour in &mut rotated.contours {
+                    let by = by % contour.points.len();
+                    contour.points.rotate_left(by);
+                }
+                assert_ne!(rotated, outline);
+                assert_outlines_similar!(outline, rotated, 0.999);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_outlines_look_the_same() {
+        assert_outlines_similar!(Outline::default(), Outline::default(), 1.);
+    }
+
+    #[test]
+    #[should_panic(expected = "outlines differ")]
+    fn different_glyphs_are_told_apart() {
+        let font = inconsolata();
+        assert_outlines_similar!(glyph(&font, 'O'), glyph(&font, 'Q'), 0.9);
+    }
+
+    #[test]
+    #[should_panic(expected = "outlines differ")]
+    fn moved_outlines_are_told_apart() {
+        let font = inconsolata();
+        let outline = glyph(&font, 'l');
+        let mut moved = outline.clone();
+        for point in moved.contours.iter_mut().flat_map(|contour| &mut contour.points) {
+            point.x += 100.;
+        }
+        moved.update_bbox();
+        assert_outlines_similar!(outline, moved, 0.9);
+    }
+
+    #[test]
+    fn similar_glyphs_are_only_similar() {
+        let font = inconsolata();
+        let similarity = outline_similarity(&glyph(&font, 'O'), &glyph(&font, 'Q'), SIMILARITY_RESOLUTION);
+        assert!(similarity.iou > 0.5 && similarity.iou < 0.9, "{similarity:?}");
+        assert!(similarity.mean_difference > 0.);
+    }
+}
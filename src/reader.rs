@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Error, Result};
+use std::cell::Cell;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::fixed::{F2Dot14, Fixed};
+
+/// A big endian reader over the font file that knows which table (and which part of it) it's
+/// reading, so that a truncated or corrupt font fails with an error like "unexpected EOF reading
+/// flags of glyph 381 in glyf at offset 0x1A2B4" rather than a bare EOF. Positions are absolute
+/// offsets into the data, like the offsets tables store, and reads can't go past the end of the
+/// current table.
+#[derive(Debug, Clone)]
+pub struct FontReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bounds: Range<usize>,
+    table: Option<String>,
+    /// What is being read, outermost first, see `with_context`
+    context: Vec<String>,
+    /// Bytes read so far, shared with the table readers made from this one, see `counting`
+    read_count: Option<Rc<Cell<usize>>>,
+}
+
+impl<'a> FontReader<'a> {
+    pub fn new(data: &'a [u8]) -> FontReader<'a> {
+        FontReader {
+            data,
+            position: 0,
+            bounds: 0..data.len(),
+            table: None,
+            context: Vec::new(),
+            read_count: None,
+        }
+    }
+
+    /// A reader that counts the bytes read through it and through its clones and table readers,
+    /// see `bytes_read`.
+    pub fn counting(data: &'a [u8]) -> FontReader<'a> {
+        FontReader {
+            read_count: Some(Rc::new(Cell::new(0))),
+            ..FontReader::new(data)
+        }
+    }
+
+    /// The bytes a `counting` reader and the readers made from it have read, reading the same
+    /// bytes twice counting twice. `None` for readers that don't count.
+    pub fn bytes_read(&self) -> Option<usize> {
+        self.read_count.as_ref().map(|count| count.get())
+    }
+
+    /// A reader for the table `tag` at `offset`, positioned at its start and bounded to its
+    /// `length` (and to the end of the data).
+    pub fn table(&self, tag: &str, offset: u32, length: u32) -> FontReader<'a> {
+        let start = (offset as usize).min(self.data.len());
+        FontReader {
+            data: self.data,
+            position: start,
+            bounds: start..(start + length as usize).min(self.data.len()),
+            table: Some(tag.to_string()),
+            context: Vec::new(),
+            read_count: self.read_count.clone(),
+        }
+    }
+
+    /// Run `read` with `label` describing what it reads, for the errors of the reads inside.
+    /// Contexts nest: "flags" inside "glyph 381" reads as "flags of glyph 381".
+    pub fn with_context<T>(&mut self, label: impl Into<String>, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.context.push(label.into());
+        let result = read(self);
+        self.context.pop();
+        result
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position as u64
+    }
+
+    /// Move to an absolute offset. Seeking out of the table is fine, reading there isn't.
+    pub fn seek(&mut self, offset: u64) -> Result<()> {
+        self.position = usize::try_from(offset).unwrap_or(usize::MAX);
+        Ok(())
+    }
+
+    pub fn skip(&mut self, count: u64) -> Result<()> {
+        self.seek(self.position() + count)
+    }
+
+    fn error(&self) -> Error {
+        let problem = if self.position < self.bounds.start { "out of bounds read" } else { "unexpected EOF" };
+        let what: Vec<&str> = self.context.iter().rev().map(String::as_str).collect();
+        let what = if what.is_empty() { String::new() } else { format!(" reading {}", what.join(" of ")) };
+        let table = self.table.as_ref().map(|table| format!(" in {table}")).unwrap_or_default();
+        anyhow!("{problem}{what}{table} at offset 0x{:X}", self.position)
+    }
+
+    /// The next `length` bytes, borrowed from the font data.
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(length).ok_or_else(|| self.error())?;
+        if self.position < self.bounds.start || end > self.bounds.end {
+            return Err(self.error());
+        }
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        if let Some(count) = &self.read_count {
+            count.set(count.get() + length);
+        }
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.read_bytes(N)?);
+        Ok(bytes)
+    }
+
+    pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        buf.copy_from_slice(self.read_bytes(buf.len())?);
+        Ok(())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_u24(&mut self) -> Result<u32> {
+        let [a, b, c] = self.read_array()?;
+        Ok(u32::from_be_bytes([0, a, b, c]))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_fixed(&mut self) -> Result<Fixed> {
+        Ok(Fixed(self.read_i32()?))
+    }
+
+    pub fn read_f2dot14(&mut self) -> Result<F2Dot14> {
+        Ok(F2Dot14(self.read_i16()?))
+    }
+}
@@ -0,0 +1,1107 @@
+use raqote::{DrawOptions, DrawTarget, Path, PathBuilder, SolidSource, Source};
+use std::ops::{ControlFlow, Range};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+use crate::cache::ShardedCache;
+use crate::debug_boxes::{stroke_layout_box, stroke_line_boxes, stroke_text_line_boxes};
+use crate::font::Font;
+use crate::glyph::{GlyphData, Segment};
+use crate::grapheme::grapheme_boundaries;
+use crate::itemize::{embedding_level, itemize, paragraph_direction, visual_order, Direction};
+use crate::hexbox::{hex_box_advance, hex_box_outline};
+use crate::emoji::shape_emoji_sequences;
+use crate::layout::{
+    drawn_glyph, glyph_clusters, inline_object, is_hidden, letter_spacing, InlineObject, LayoutOptions,
+    MissingGlyphStyle, PositionPolicy, TextLayout,
+};
+use crate::raster::{rasterize, rasterize_at, GlyphBitmap, Hinting, RasterOptions, RenderMode};
+use crate::shape_cache::ShapeCache;
+use crate::simd;
+use crate::size::FontSize;
+use crate::source::GlyphSource;
+use crate::stroke::StrokeOptions;
+use crate::trace::{ShapingStep, ShapingTrace};
+use crate::transform::FontUnitsToPixels;
+
+/// A glyph placed on the baseline at `position` (in draw target pixels).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub index: u16,
+    pub position: (f32, f32),
+    /// Byte offset in the text of the character the glyph was made for
+    pub cluster: usize,
+    /// How far the glyph moves the pen, 0 for a mark attached to its base
+    pub advance: f32,
+    /// The character a hex box is drawn for instead of the glyph (which is then 0), see
+    /// `MissingGlyphStyle::HexBox`
+    pub missing: Option<char>,
+    /// The inline object (see `LayoutOptions::inline_objects`, counted from the start of the
+    /// line) the caller draws here instead of a glyph, which is then 0
+    pub object: Option<usize>,
+}
+
+impl GlyphData {
+    /// Build a path for the glyph outline, scaled from font units and flipped so that y points down.
+    /// `origin` is the position of the glyph origin (on the baseline) in the draw target.
+    pub fn to_path(&self, scale: f32, origin: (f32, f32)) -> Path {
+        self.outline().to_path(scale, origin)
+    }
+}
+
+/// `Outline::to_path` for contours given as segments.
+pub fn contours_to_path(contours: &[Vec<Segment>], scale: f32, origin: (f32, f32)) -> Path {
+    let transform = FontUnitsToPixels::with_scale(scale, origin);
+    contours_to_path_with(contours, |p| transform.apply(p))
+}
+
+/// A path of contours given as segments with every point mapped to draw target pixels by `map`,
+/// for glyphs drawn through any affine transform (see `Outline::transform`).
+pub fn contours_to_path_with(contours: &[Vec<Segment>], map: impl Fn((f32, f32)) -> (f32, f32)) -> Path {
+    let mut pb = PathBuilder::new();
+    for segments in contours {
+        let Some(first) = segments.first() else {
+            continue;
+        };
+        let start = map(first.start());
+        pb.move_to(start.0, start.1);
+        for segment in segments {
+            match *segment {
+                Segment::Line(_, p1) => {
+                    let p1 = map(p1);
+                    pb.line_to(p1.0, p1.1);
+                }
+                Segment::Quad(_, c, p1) => {
+                    let (c, p1) = (map(c), map(p1));
+                    pb.quad_to(c.0, c.1, p1.0, p1.1);
+                }
+            }
+        }
+        pb.close();
+    }
+    pb.finish()
+}
+
+/// Composite a coverage bitmap in `color` onto the draw target with its origin at pixel (x, y).
+/// LCD bitmaps blend each color channel with its own coverage, which assumes an opaque background.
+pub fn blit_coverage(dt: &mut DrawTarget, bitmap: &GlyphBitmap, x: i32, y: i32, color: SolidSource) {
+    let (dt_width, dt_height) = (dt.width(), dt.height());
+    let data = dt.get_data_mut();
+    let channels = match bitmap.mode {
+        RenderMode::Grayscale => 1,
+        RenderMode::Lcd(_) => 3,
+    };
+    let alpha = color.a as f32 / 255.;
+    for row in 0..bitmap.height {
+        let py = y - bitmap.top + row as i32;
+        if py < 0 || py >= dt_height {
+            continue;
+        }
+        for column in 0..bitmap.width {
+            let px = x + bitmap.left + column as i32;
+            if px < 0 || px >= dt_width {
+                continue;
+            }
+            let at = (row * bitmap.width + column) * channels;
+            let coverage = |channel: usize| bitmap.data[at + channel.min(channels - 1)] as f32 / 255.;
+            let (r, g, b) = (coverage(0), coverage(1), coverage(2));
+            if r == 0. && g == 0. && b == 0. {
+                continue;
+            }
+            let pixel = &mut data[(py * dt_width + px) as usize];
+            // both the draw target and the color are premultiplied
+            let blend = |shift: u32, source: u8, coverage: f32| {
+                let dst = ((*pixel >> shift) & 0xff) as f32;
+                ((dst * (1. - coverage * alpha) + source as f32 * coverage).round().min(255.) as u32) << shift
+            };
+            let a = blend(24, color.a, r.max(g).max(b)) >> 24;
+            *pixel = a << 24 | blend(16, color.r, r) | blend(8, color.g, g) | blend(0, color.b, b);
+        }
+    }
+}
+
+/// Positions a glyph bitmap is rasterized at within a pixel horizontally: a glyph at a fraction
+/// of a pixel is drawn from the bitmap rasterized at the nearest of 0, 1/4, 1/2 and 3/4 pixel.
+pub const SUBPIXEL_POSITIONS: u8 = 4;
+
+/// Grayscale glyph bitmaps by glyph index, pixel size (its bits), subpixel position (see
+/// `SUBPIXEL_POSITIONS`), hinting and variation coordinates (a hash of their bits, see
+/// `coords_key`), rasterized once and blitted every time they're drawn, see
+/// `Font::draw_text_cached`. Snapped positions only ever use subpixel position 0. One cache is
+/// for one font: the glyph indices of another font hit the first one's bitmaps. The bitmaps of
+/// every instance drawn stay until `clear`, an animation through the axes should clear it now
+/// and then.
+pub type GlyphBitmapCache = ShardedCache<(u16, u32, u8, Hinting, u64), Option<Arc<GlyphBitmap>>>;
+
+/// FNV-1a of the bits of the normalized variation coordinates, the same for the same instance.
+fn coords_key(coords: &[f32]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+    coords
+        .iter()
+        .flat_map(|coord| coord.to_bits().to_le_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+impl ShardedCache<(u16, u32, u8, Hinting, u64), Option<Arc<GlyphBitmap>>> {
+    /// The bitmap of a glyph at a subpixel position, rasterized with the default options but
+    /// `hinting` on a miss.
+    fn bitmap(
+        &self,
+        font: &Font,
+        index: u16,
+        px_size: f32,
+        subpixel: u8,
+        hinting: Hinting,
+    ) -> Option<Arc<GlyphBitmap>> {
+        let key = (index, px_size.to_bits(), subpixel, hinting, coords_key(&font.normalized_coords));
+        self.get_or_insert_with(key, || {
+            let offset = subpixel as f32 / SUBPIXEL_POSITIONS as f32;
+            let options = RasterOptions {
+                hinting,
+                ..RasterOptions::default()
+            };
+            rasterize_at(font, index, px_size, &options, offset).map(Arc::new)
+        })
+    }
+
+    /// Rasterize ahead of time, say during a loading screen, the unhinted bitmaps
+    /// `draw_text_cached` needs for `chars` at each of `sizes`, at every subpixel position, so
+    /// that drawing them later never rasterizes. Characters map to their glyphs through cmap,
+    /// nothing is shaped: joining forms and other glyphs only shaping picks aren't included.
+    /// `progress` gets the glyph and size combinations done and the total after each one,
+    /// returning `Break` stops early.
+    pub fn prewarm(
+        &self,
+        font: &Font,
+        chars: impl Iterator<Item = char>,
+        sizes: &[f32],
+        mut progress: impl FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let jobs = prewarm_jobs(font, chars, sizes);
+        for (done, (index, px_size)) in jobs.iter().enumerate() {
+            self.prewarm_glyph(font, *index, *px_size);
+            progress(done + 1, jobs.len())?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// `prewarm` spread over `threads` threads. `progress` is called on the calling thread.
+    pub fn prewarm_parallel(
+        &self,
+        font: &Font,
+        chars: impl Iterator<Item = char>,
+        sizes: &[f32],
+        threads: usize,
+        mut progress: impl FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let jobs = prewarm_jobs(font, chars, sizes);
+        let (next, stop) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let (sender, done) = mpsc::channel();
+        std::thread::scope(|scope| {
+            for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+                let sender = sender.clone();
+                let (jobs, next, stop) = (&jobs, &next, &stop);
+                scope.spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        let Some((index, px_size)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                            break;
+                        };
+                        self.prewarm_glyph(font, *index, *px_size);
+                        if sender.send(()).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+            // the workers are done when every sender is gone
+            for (count, ()) in done.iter().enumerate() {
+                if progress(count + 1, jobs.len()).is_break() {
+                    stop.store(true, Ordering::Relaxed);
+                    return ControlFlow::Break(());
+                }
+            }
+            ControlFlow::Continue(())
+        })
+    }
+
+    fn prewarm_glyph(&self, font: &Font, index: u16, px_size: f32) {
+        for subpixel in 0..SUBPIXEL_POSITIONS {
+            self.bitmap(font, index, px_size, subpixel, Hinting::None);
+        }
+    }
+}
+
+/// Each distinct glyph `chars` map to (.notdef for unmapped ones) at each size.
+fn prewarm_jobs(font: &Font, chars: impl Iterator<Item = char>, sizes: &[f32]) -> Vec<(u16, f32)> {
+    let mut glyphs: Vec<u16> = chars.map(|c| font.glyph_index_for_char(c).unwrap_or(0)).collect();
+    glyphs.sort_unstable();
+    glyphs.dedup();
+    sizes.iter().flat_map(|px_size| glyphs.iter().map(|index| (*index, *px_size))).collect()
+}
+
+/// The whole pixel and subpixel position (see `SUBPIXEL_POSITIONS`) nearest to `x`.
+fn subpixel_position(x: f32) -> (i32, u8) {
+    let steps = (x * SUBPIXEL_POSITIONS as f32).round() as i32;
+    let positions = SUBPIXEL_POSITIONS as i32;
+    (steps.div_euclid(positions), steps.rem_euclid(positions) as u8)
+}
+
+/// `blit_coverage` for grayscale bitmaps with integer math only: the part of the bitmap that
+/// falls inside the draw target is found up front (glyphs partly off the target are clipped),
+/// then each row is composited source-over in the premultiplied color by
+/// `simd::blend_a8_row`. LCD bitmaps go through `blit_coverage`.
+pub fn blit_a8(dt: &mut DrawTarget, bitmap: &GlyphBitmap, x: i32, y: i32, color: SolidSource) {
+    if bitmap.mode != RenderMode::Grayscale {
+        return blit_coverage(dt, bitmap, x, y, color);
+    }
+    let (dt_width, dt_height) = (dt.width(), dt.height());
+    let (left, top) = (x + bitmap.left, y - bitmap.top);
+    let columns = left.saturating_neg().max(0)..dt_width.saturating_sub(left).min(bitmap.width as i32);
+    let rows = top.saturating_neg().max(0)..dt_height.saturating_sub(top).min(bitmap.height as i32);
+    if columns.is_empty() || rows.is_empty() {
+        return;
+    }
+    let data = dt.get_data_mut();
+    let color = [color.a as u32, color.r as u32, color.g as u32, color.b as u32];
+    for row in rows {
+        let start = row as usize * bitmap.width;
+        let coverage = &bitmap.data[start + columns.start as usize..start + columns.end as usize];
+        let at = ((top + row) * dt_width + left + columns.start) as usize;
+        simd::blend_a8_row(&mut data[at..at + coverage.len()], coverage, color);
+    }
+}
+
+/// The caches `Font::draw_text_cached` draws through. With `glyphs` each glyph is rasterized in
+/// grayscale once per pixel size and blitted with `blit_a8` on whole pixels, without it the
+/// outlines are filled through raqote paths like `draw_text` does. With `shapes` the line is
+/// laid out once.
+#[derive(Debug, Default)]
+pub struct TextCaches<'a> {
+    pub glyphs: Option<&'a GlyphBitmapCache>,
+    pub shapes: Option<&'a mut ShapeCache>,
+}
+
+impl Font {
+    pub fn scale_for_px(&self, px_size: f32) -> f32 {
+        px_size / self.units_per_em() as f32
+    }
+
+    /// Width in pixels of a single line of text, the distance the pen moves when drawing it.
+    pub fn measure_text(&self, text: &str, size: impl Into<FontSize>) -> f32 {
+        let px_size = size.into().px_per_em();
+        measure_text(self, text, px_size)
+    }
+
+    /// `measure_text` with the characters the font has no glyph for measured as `layout` says.
+    pub fn measure_text_with_layout(&self, text: &str, size: impl Into<FontSize>, layout: &LayoutOptions) -> f32 {
+        let px_size = size.into().px_per_em();
+        layout_line_with(self, text, (0., 0.), px_size, None, layout).1
+    }
+
+    /// `measure_text` of the line `draw_text_with` draws with `options`, by the hinted or
+    /// bitmap advances and on whole pixels when those apply.
+    pub fn measure_text_with(&self, text: &str, size: impl Into<FontSize>, options: &RasterOptions) -> f32 {
+        let px_size = size.into().px_per_em();
+        layout_line(self, text, (0., 0.), px_size, Some(options)).1
+    }
+
+    /// How much rounding moved the end of the line `text` measures as with `options` and
+    /// `layout`, see `rounding_error`.
+    pub fn rounding_error(
+        &self,
+        text: &str,
+        px_size: f32,
+        options: Option<&RasterOptions>,
+        layout: &LayoutOptions,
+    ) -> f32 {
+        rounding_error(self, text, px_size, options, layout)
+    }
+
+    /// `measure_text`, laying the line out through `shapes` when there's a cache.
+    pub fn measure_text_cached(&self, text: &str, size: impl Into<FontSize>, shapes: Option<&mut ShapeCache>) -> f32 {
+        let px_size = size.into().px_per_em();
+        match shapes {
+            Some(shapes) => shapes.layout_line(self, text, (0., 0.), px_size, None).1,
+            None => measure_text(self, text, px_size),
+        }
+    }
+
+    /// Pen x (from the start of the line) of a caret in front of the character at byte `offset`
+    /// of `text`, or after the last character at the end of the text. At the end of a run
+    /// embedded in the other direction the caret stays after the run's last character.
+    pub fn caret_position(&self, text: &str, offset: usize, px_size: f32) -> f32 {
+        caret_position(self, text, offset, px_size)
+    }
+
+    /// The byte offset in `text` of the caret position closest to pen x `x`, to place the
+    /// caret where a line is clicked. Only grapheme boundaries are caret positions.
+    pub fn hit_test(&self, text: &str, x: f32, px_size: f32) -> usize {
+        hit_test(self, text, x, px_size)
+    }
+
+    /// `caret_position` drawn as a line leaning with the font's caret slope, from `top` to
+    /// `bottom` of a line with its baseline at `baseline` (y down, the x of the line's start
+    /// at 0). Returns its top and bottom points.
+    pub fn caret_line(
+        &self,
+        text: &str,
+        offset: usize,
+        px_size: f32,
+        baseline: f32,
+        (top, bottom): (f32, f32),
+    ) -> ((f32, f32), (f32, f32)) {
+        let x = caret_position(self, text, offset, px_size);
+        slanted_caret(self, x, baseline, (top, bottom), px_size)
+    }
+
+    /// `hit_test` for a point `rise` pixels above the baseline, the boundaries between the
+    /// characters leaning like the caret, so clicking by an italic stem picks its side.
+    pub fn hit_test_slanted(&self, text: &str, (x, rise): (f32, f32), px_size: f32) -> usize {
+        hit_test_slanted_with(self, text, (x, rise), px_size, &LayoutOptions::default())
+    }
+
+    /// The caret position to the right (or left) of the caret at byte `offset` of `text` as
+    /// the line is shown, `None` past that end of the line.
+    pub fn move_caret_visually(&self, text: &str, offset: usize, rightwards: bool, px_size: f32) -> Option<usize> {
+        move_caret_visually_with(self, text, offset, rightwards, px_size, &LayoutOptions::default())
+    }
+
+    /// Draw a single line of text with its baseline starting at `origin`, returns the pen
+    /// position after the last glyph.
+    pub fn draw_text(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        size: impl Into<FontSize>,
+        color: SolidSource,
+    ) -> f32 {
+        let px_size = size.into().px_per_em();
+        self.draw_text_fill_stroke(dt, text, origin, px_size, Some(color), None)
+    }
+
+    /// `draw_text` through whichever of `caches` are given, see `TextCaches`.
+    pub fn draw_text_cached(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        size: impl Into<FontSize>,
+        color: SolidSource,
+        caches: TextCaches,
+    ) -> f32 {
+        let px_size = size.into().px_per_em();
+        let TextCaches { glyphs, shapes } = caches;
+        let (positioned, end_x) = match shapes {
+            Some(shapes) => shapes.layout_line(self, text, origin, px_size, None),
+            None => layout_line(self, text, origin, px_size, None),
+        };
+        match glyphs {
+            Some(cache) => self.draw_glyphs_cached(dt, &positioned, px_size, color, cache),
+            None => {
+                let scale = self.scale_for_px(px_size);
+                for PositionedGlyph { index, position, .. } in positioned {
+                    if let Some(outline) = self.drawn_outline(index) {
+                        dt.fill(&outline.to_path(scale, position), &Source::Solid(color), &DrawOptions::new());
+                    }
+                }
+            }
+        }
+        end_x
+    }
+
+    /// Fill glyph `index` as large as it fits in `rect` (x, top, width, height), its ink (not
+    /// its advance and line height) centered in the rectangle and keeping its aspect ratio, the
+    /// way icons are drawn in buttons. Returns false for a glyph with nothing to draw.
+    pub fn draw_glyph_in_box(
+        &self,
+        dt: &mut DrawTarget,
+        index: u16,
+        rect: (f32, f32, f32, f32),
+        color: SolidSource,
+    ) -> bool {
+        let Some(outline) = self.glyph_outline(index).filter(|outline| outline.num_points() > 0) else {
+            return false;
+        };
+        let (x_min, y_min, x_max, y_max) = outline.tight_bbox();
+        let (width, height) = (x_max - x_min, y_max - y_min);
+        let scale = match (width > 0., height > 0.) {
+            (true, true) => (rect.2 / width).min(rect.3 / height),
+            (true, false) => rect.2 / width,
+            (false, true) => rect.3 / height,
+            (false, false) => return false,
+        };
+        // the glyph origin that puts the middle of the ink (y up) on the middle of the rectangle
+        let ink_center = ((x_min + x_max) / 2., (y_min + y_max) / 2.);
+        let origin = (rect.0 + rect.2 / 2. - ink_center.0 * scale, rect.1 + rect.3 / 2. + ink_center.1 * scale);
+        dt.fill(&outline.to_path(scale, origin), &Source::Solid(color), &DrawOptions::new());
+        true
+    }
+
+    /// Blit laid out glyphs from bitmaps rasterized once into `cache`, each at its position:
+    /// a whole pixel position blits the bitmap as is, one between pixels (from
+    /// `PositionPolicy::None`) the bitmap rasterized at the nearest subpixel position. The
+    /// baseline is rounded to a pixel. Hex boxes are filled as paths, inline objects skipped.
+    pub fn draw_glyphs_cached(
+        &self,
+        dt: &mut DrawTarget,
+        glyphs: &[PositionedGlyph],
+        px_size: f32,
+        color: SolidSource,
+        cache: &GlyphBitmapCache,
+    ) {
+        self.draw_glyphs_cached_with(dt, glyphs, px_size, color, cache, Hinting::None)
+    }
+
+    /// `draw_glyphs_cached` from bitmaps grid fitted as `hinting` says, for glyphs laid out with
+    /// the same hinting.
+    pub fn draw_glyphs_cached_with(
+        &self,
+        dt: &mut DrawTarget,
+        glyphs: &[PositionedGlyph],
+        px_size: f32,
+        color: SolidSource,
+        cache: &GlyphBitmapCache,
+        hinting: Hinting,
+    ) {
+        for PositionedGlyph { index, position, missing, object, .. } in glyphs {
+            if object.is_some() {
+                continue;
+            }
+            if let Some(c) = missing {
+                let path = contours_to_path(&hex_box_outline(self, *c), self.scale_for_px(px_size), *position);
+                dt.fill(&path, &Source::Solid(color), &DrawOptions::new());
+                continue;
+            }
+            let (x, subpixel) = subpixel_position(position.0);
+            if let Some(bitmap) = cache.bitmap(self, *index, px_size, subpixel, hinting) {
+                blit_a8(dt, &bitmap, x, position.1.round() as i32, color);
+            }
+        }
+    }
+
+    /// Like `draw_text` but through the crate's own rasterizer, so that hinting, autofit and LCD
+    /// subpixel rendering from `options` apply. Glyphs are placed on whole pixels, with hinting the
+    /// pen moves by the hinted advances (measured between the grid fitted phantom points) and
+    /// with embedded bitmaps by the advances of their metrics.
+    pub fn draw_text_with(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        size: impl Into<FontSize>,
+        color: SolidSource,
+        options: &RasterOptions,
+    ) -> f32 {
+        let px_size = size.into().px_per_em();
+        draw_text_with(self, dt, text, origin, px_size, color, options)
+    }
+
+    /// Outline-only (hollow) text with the default miter joins.
+    pub fn draw_text_stroked(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        size: impl Into<FontSize>,
+        stroke_width: f32,
+        color: SolidSource,
+    ) -> f32 {
+        let px_size = size.into().px_per_em();
+        self.draw_text_fill_stroke(dt, text, origin, px_size, None, Some((&StrokeOptions::new(stroke_width), color)))
+    }
+
+    /// Fill and/or stroke a line of text. The layout and each glyph path are built once and shared
+    /// by both passes, the stroke is drawn on top of the fill.
+    pub fn draw_text_fill_stroke(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        size: impl Into<FontSize>,
+        fill: Option<SolidSource>,
+        stroke: Option<(&StrokeOptions, SolidSource)>,
+    ) -> f32 {
+        let px_size = size.into().px_per_em();
+        self.draw_line_fill_stroke(dt, layout_line(self, text, origin, px_size, None), px_size, fill, stroke)
+    }
+
+    /// `draw_text` with the characters the font has no glyph for drawn as `layout` says, over
+    /// its debug boxes when it has `debug_boxes` set (see `draw_debug_boxes`).
+    pub fn draw_text_with_layout(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        size: impl Into<FontSize>,
+        color: SolidSource,
+        layout: &LayoutOptions,
+    ) -> f32 {
+        let px_size = size.into().px_per_em();
+        let (glyphs, end_x) = layout_line_with(self, text, origin, px_size, None, layout);
+        if layout.debug_boxes {
+            stroke_line_boxes(self, dt, (&glyphs, end_x), origin, px_size);
+        }
+        self.draw_line_fill_stroke(dt, (glyphs, end_x), px_size, Some(color), None)
+    }
+
+    /// Draw the lines of `layout`, the text it was made from, with the top left of the text at
+    /// `origin`, each line as `draw_text_with_layout` does. Inline objects are left to the
+    /// caller (see `TextLayout::inline_objects`). With `debug_boxes` in the layout's options the
+    /// line boxes are those of its `TextLine`s and a box goes around the whole layout.
+    pub fn draw_layout(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        layout: &TextLayout,
+        origin: (f32, f32),
+        color: SolidSource,
+    ) {
+        for line in &layout.lines {
+            let options = layout.line_options(line);
+            let line_origin = (origin.0, origin.1 + line.baseline);
+            let (glyphs, end_x) =
+                layout_line_with(self, &text[line.range.clone()], line_origin, layout.px_size, None, &options);
+            if options.debug_boxes {
+                stroke_text_line_boxes(self, dt, &glyphs, line, origin, layout.px_size);
+            }
+            self.draw_line_fill_stroke(dt, (glyphs, end_x), layout.px_size, Some(color), None);
+        }
+        if layout.options.debug_boxes {
+            stroke_layout_box(dt, layout, origin);
+        }
+    }
+
+    /// Fill and/or stroke glyphs laid out by `layout_line_with`, hex boxes included, inline
+    /// objects left to the caller.
+    fn draw_line_fill_stroke(
+        &self,
+        dt: &mut DrawTarget,
+        (glyphs, end_x): (Vec<PositionedGlyph>, f32),
+        px_size: f32,
+        fill: Option<SolidSource>,
+        stroke: Option<(&StrokeOptions, SolidSource)>,
+    ) -> f32 {
+        let scale = self.scale_for_px(px_size);
+        let stroke_style = stroke.map(|(options, color)| (options.to_raqote(), color));
+        for PositionedGlyph { index, position, missing, object, .. } in glyphs {
+            if object.is_some() {
+                continue;
+            }
+            let path = match (missing, self.drawn_outline(index)) {
+                (Some(c), _) => contours_to_path(&hex_box_outline(self, c), scale, position),
+                (None, Some(outline)) => outline.to_path(scale, position),
+                (None, None) => continue,
+            };
+            if let Some(color) = fill {
+                dt.fill(&path, &Source::Solid(color), &DrawOptions::new());
+            }
+            if let Some((style, color)) = &stroke_style {
+                dt.stroke(&path, &Source::Solid(*color), style, &DrawOptions::new());
+            }
+        }
+        end_x
+    }
+}
+
+/// Glyph indices and their pen positions for a single line of text from any glyph source, plus
+/// the final pen x. The text is itemized into runs by script and direction, each run is laid out
+/// on its own (runs of joining scripts shaped first) and the runs are put in visual order, right
+/// to left runs with their glyphs reversed. Kerning applies between base glyphs of a run (marks
+/// don't break up a pair), marks are placed on their base without moving the pen. With raster
+/// `options` that hint or use embedded bitmaps the pen starts on a whole pixel and moves by the
+/// hinted or bitmap advances and rounded adjustments.
+pub fn layout_line<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    origin: (f32, f32),
+    px_size: f32,
+    options: Option<&RasterOptions>,
+) -> (Vec<PositionedGlyph>, f32) {
+    layout_line_with(source, text, origin, px_size, options, &LayoutOptions::default())
+}
+
+/// `layout_line` with characters the font has no glyph for laid out as `layout` says: as glyph
+/// 0, as a hex box (a `PositionedGlyph` with `missing` set, as wide as the box) or not at all.
+/// Glyphs with a `GlyphOverride` in `layout` take its advance and are moved by its offset, and
+/// each glyph but marks is followed by the letter spacing (and tracking) of `layout`. The
+/// U+FFFC of inline objects take their width, as a `PositionedGlyph` with `object` set.
+pub fn layout_line_with<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    origin: (f32, f32),
+    px_size: f32,
+    options: Option<&RasterOptions>,
+    layout: &LayoutOptions,
+) -> (Vec<PositionedGlyph>, f32) {
+    layout_line_traced(source, text, origin, px_size, options, layout, &mut ShapingTrace::disabled())
+}
+
+/// `layout_line_with` recording in `trace` each character's glyph, the substitutions, the
+/// kerning and where each glyph goes, see `ShapingTrace`.
+pub fn layout_line_traced<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    origin: (f32, f32),
+    px_size: f32,
+    options: Option<&RasterOptions>,
+    layout: &LayoutOptions,
+    trace: &mut ShapingTrace,
+) -> (Vec<PositionedGlyph>, f32) {
+    let scale = px_size / source.units_per_em() as f32;
+    let ppem = px_size.round() as u16;
+    let hinting = options.map_or(Hinting::None, |options| options.hinting);
+    // by the hinted advance when the glyph is hinted by its instructions, see `scaled_outline`
+    let native = |index: u16| match hinting {
+        Hinting::Native => true,
+        Hinting::NativeIfPresent => source.has_instructions(index),
+        Hinting::None | Hinting::AutoVertical => false,
+    };
+    let embedded_bitmaps = options.is_some_and(|options| options.prefer_embedded_bitmaps);
+    let policy = layout.position.for_raster(options);
+    let snap = |v: f32| policy.snap_x(v);
+    let snap_y = |v: f32| policy.snap_y(v);
+    let spacing = letter_spacing(source, px_size, layout);
+    let mut x = snap(origin.0);
+    let y = layout.position.snap_y(origin.1);
+    let mut glyphs: Vec<PositionedGlyph> = Vec::with_capacity(text.len());
+    let runs = itemize(text);
+    for run in visual_order(&runs, paragraph_direction(text)).into_iter().map(|i| &runs[i]) {
+        let rtl = run.direction == Direction::RightToLeft;
+        let mut clusters: Vec<(usize, u16)> = glyph_clusters(source, &text[run.range.clone()])
+            .into_iter()
+            .map(|(cluster, index)| (run.range.start + cluster, index))
+            .collect();
+        for (cluster, glyph) in &clusters {
+            let character = text[*cluster..].chars().next().unwrap_or_default();
+            trace.record(|| ShapingStep::Mapped {
+                cluster: *cluster,
+                character,
+                glyph: *glyph,
+            });
+        }
+        if run.script.is_joining() {
+            source.shape_joining(text, &mut clusters, trace);
+        }
+        shape_emoji_sequences(source, text, run.range.clone(), &mut clusters, trace);
+        if rtl {
+            clusters = reverse_keeping_marks(source, clusters);
+        }
+        // the last base glyph and where it was placed
+        let mut base: Option<(u16, (f32, f32))> = None;
+        for (cluster, index) in clusters {
+            // invisible characters and soft hyphens the line doesn't end with aren't drawn
+            if is_hidden(text, cluster) {
+                continue;
+            }
+            if let Some((object, InlineObject { width, .. })) = inline_object(text, cluster, layout) {
+                let advance = snap(width + spacing);
+                glyphs.push(PositionedGlyph {
+                    index: 0,
+                    position: (x, y),
+                    cluster,
+                    advance,
+                    missing: None,
+                    object: Some(object),
+                });
+                x += advance;
+                base = None;
+                continue;
+            }
+            let index = text[cluster..].chars().next().map_or(index, |c| drawn_glyph(source, c, index));
+            let missing = if index == 0 { text[cluster..].chars().next() } else { None };
+            match (missing, layout.missing_glyph) {
+                (Some(_), MissingGlyphStyle::Skip) => continue,
+                (Some(c), MissingGlyphStyle::HexBox) => {
+                    let advance = snap(hex_box_advance(source.units_per_em(), c) * scale + spacing);
+                    glyphs.push(PositionedGlyph {
+                        index,
+                        position: (x, y),
+                        cluster,
+                        advance,
+                        missing: Some(c),
+                        object: None,
+                    });
+                    x += advance;
+                    base = None;
+                    continue;
+                }
+                _ => {}
+            }
+            let adjustment = layout.glyph_overrides.get(&index).copied().unwrap_or_default();
+            let (dx, dy) = (snap(adjustment.offset.0), snap_y(adjustment.offset.1));
+            if source.is_mark(index) {
+                let attachment = base.and_then(|(base, position)| Some((source.mark_offset(base, index)?, position)));
+                if let Some(((mark_dx, mark_dy), position)) = attachment {
+                    let (mark_dx, mark_dy) = (snap(mark_dx as f32 * scale), snap_y(mark_dy as f32 * scale));
+                    glyphs.push(PositionedGlyph {
+                        index,
+                        position: (position.0 + mark_dx + dx, position.1 - mark_dy + dy),
+                        cluster,
+                        advance: 0.,
+                        missing: None,
+                        object: None,
+                    });
+                    continue;
+                }
+            } else {
+                if let Some((previous, _)) = base {
+                    // pairs are kerned in logical order
+                    let (first, second) = if rtl { (index, previous) } else { (previous, index) };
+                    let kerning = source.kerning(first, second);
+                    if kerning != 0 {
+                        trace.record(|| ShapingStep::Kerned {
+                            first,
+                            second,
+                            adjustment: kerning,
+                            table: *b"GPOS",
+                        });
+                    }
+                    x += snap(kerning as f32 * scale);
+                }
+                base = Some((index, (x + dx, y + dy)));
+            }
+            let embedded_advance = embedded_bitmaps
+                .then(|| source.bitmap_advance(index, ppem))
+                .flatten()
+                .map(f32::from);
+            let hinted_advance = || native(index).then(|| source.hinted_advance(index, ppem)).flatten();
+            let advance = match adjustment.advance {
+                Some(advance) => snap(advance + spacing),
+                None => match embedded_advance.or_else(hinted_advance) {
+                    Some(advance) if spacing == 0. => advance,
+                    Some(advance) => snap(advance + spacing),
+                    None => snap(source.advance(index) as f32 * scale + spacing),
+                },
+            };
+            glyphs.push(PositionedGlyph {
+                index,
+                position: (x + dx, y + dy),
+                cluster,
+                advance,
+                missing: None,
+                object: None,
+            });
+            x += advance;
+        }
+    }
+    for glyph in &glyphs {
+        trace.record(|| ShapingStep::Positioned(*glyph));
+    }
+    (glyphs, x)
+}
+
+/// Reverse the glyphs of a right to left run into visual order, keeping each base glyph
+/// followed by its marks.
+fn reverse_keeping_marks<S: GlyphSource + ?Sized>(source: &S, clusters: Vec<(usize, u16)>) -> Vec<(usize, u16)> {
+    let mut groups: Vec<Vec<(usize, u16)>> = Vec::new();
+    for glyph in clusters {
+        match groups.last_mut() {
+            Some(group) if source.is_mark(glyph.1) => group.push(glyph),
+            _ => groups.push(vec![glyph]),
+        }
+    }
+    groups.into_iter().rev().flatten().collect()
+}
+
+/// `Font::measure_text` for any glyph source.
+pub fn measure_text<S: GlyphSource + ?Sized>(source: &S, text: &str, px_size: f32) -> f32 {
+    layout_line(source, text, (0., 0.), px_size, None).1
+}
+
+/// The pen x at the end of a line laid out with `options` and `layout` less the one laid out
+/// from the outline advances without rounding, the pixels snapping (see
+/// `PositionPolicy::for_raster`) and hinted advances added up to. Justifying a line can spread
+/// this over its gaps instead of the last word taking all of it.
+pub fn rounding_error<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    px_size: f32,
+    options: Option<&RasterOptions>,
+    layout: &LayoutOptions,
+) -> f32 {
+    let exact = LayoutOptions {
+        position: PositionPolicy::None,
+        ..layout.clone()
+    };
+    let rounded = layout_line_with(source, text, (0., 0.), px_size, options, layout).1;
+    rounded - layout_line_with(source, text, (0., 0.), px_size, None, &exact).1
+}
+
+/// Where a character of a line that has a glyph of its own is: its byte range, leading and
+/// trailing edge (pen x from the start of the line) and embedding level. A character leads on
+/// its left in left to right runs and on its right in right to left ones.
+struct ClusterEdges {
+    range: Range<usize>,
+    leading: f32,
+    trailing: f32,
+    level: u8,
+}
+
+/// The `ClusterEdges` of the characters of a line, in logical order.
+fn cluster_edges<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    px_size: f32,
+    layout: &LayoutOptions,
+) -> Vec<ClusterEdges> {
+    let runs = itemize(text);
+    let paragraph = paragraph_direction(text);
+    let mut bases: Vec<PositionedGlyph> = layout_line_with(source, text, (0., 0.), px_size, None, layout)
+        .0
+        .into_iter()
+        .filter(|glyph| glyph.advance != 0. || !source.is_mark(glyph.index))
+        .collect();
+    bases.sort_by_key(|glyph| glyph.cluster);
+    // the glyphs of a cluster (an emoji sequence drawn as its components) span all of them
+    let mut spans: Vec<(usize, f32, f32)> = Vec::with_capacity(bases.len());
+    for glyph in &bases {
+        let (left, right) = (glyph.position.0, glyph.position.0 + glyph.advance);
+        match spans.last_mut() {
+            Some(span) if span.0 == glyph.cluster => *span = (span.0, span.1.min(left), span.2.max(right)),
+            _ => spans.push((glyph.cluster, left, right)),
+        }
+    }
+    let ends: Vec<usize> = spans.iter().skip(1).map(|span| span.0).chain([text.len()]).collect();
+    let mut edges: Vec<ClusterEdges> = spans
+        .iter()
+        .zip(ends)
+        .map(|(&(cluster, left, right), end)| {
+            let direction = runs
+                .iter()
+                .find(|run| run.range.contains(&cluster))
+                .map_or(paragraph, |run| run.direction);
+            let (leading, trailing) = match direction {
+                Direction::LeftToRight => (left, right),
+                Direction::RightToLeft => (right, left),
+            };
+            ClusterEdges {
+                range: cluster..end,
+                leading,
+                trailing,
+                level: embedding_level(direction, paragraph),
+            }
+        })
+        .collect();
+    // a hidden character has no glyph, it takes no room right after the character before
+    let hidden: Vec<usize> = text.char_indices().map(|(at, _)| at).filter(|at| is_hidden(text, *at)).collect();
+    for at in hidden {
+        let Some(i) = edges.iter().position(|edge| edge.range.start < at && edge.range.end > at) else {
+            continue;
+        };
+        let (end, trailing, level) = (edges[i].range.end, edges[i].trailing, edges[i].level);
+        edges[i].range.end = at;
+        let hyphen = ClusterEdges {
+            range: at..end,
+            leading: trailing,
+            trailing,
+            level,
+        };
+        edges.insert(i + 1, hyphen);
+    }
+    edges
+}
+
+/// The pen x of the caret at byte `offset` of a line with these edges: the leading edge of the
+/// character at the offset, unless the offset ends a run embedded deeper than what follows (or
+/// ends the line), then the trailing edge of the character before, where typing more of that
+/// run goes. Either way each edge between characters is the place of one offset.
+fn caret_x(edges: &[ClusterEdges], offset: usize) -> f32 {
+    let after = edges.iter().find(|edge| edge.range.contains(&offset));
+    let before = edges.iter().find(|edge| edge.range.end == offset);
+    match (before, after) {
+        (Some(before), Some(after)) if before.level > after.level => before.trailing,
+        (_, Some(after)) => after.leading,
+        (Some(before), None) => before.trailing,
+        (None, None) => 0.,
+    }
+}
+
+/// `Font::caret_position` for any glyph source.
+pub fn caret_position<S: GlyphSource + ?Sized>(source: &S, text: &str, offset: usize, px_size: f32) -> f32 {
+    caret_position_with(source, text, offset, px_size, &LayoutOptions::default())
+}
+
+/// `caret_position` in the line as `layout_line_with` lays it out.
+pub fn caret_position_with<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    offset: usize,
+    px_size: f32,
+    layout: &LayoutOptions,
+) -> f32 {
+    caret_x(&cluster_edges(source, text, px_size, layout), offset)
+}
+
+/// The caret positions of a line from left to right: each grapheme boundary with the pen x
+/// `caret_position_with` puts it at.
+pub fn caret_stops_with<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    px_size: f32,
+    layout: &LayoutOptions,
+) -> Vec<(usize, f32)> {
+    let edges = cluster_edges(source, text, px_size, layout);
+    let mut stops: Vec<(usize, f32)> = grapheme_boundaries(text)
+        .into_iter()
+        .map(|offset| (offset, caret_x(&edges, offset)))
+        .collect();
+    stops.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    stops
+}
+
+/// `Font::hit_test` for any glyph source.
+pub fn hit_test<S: GlyphSource + ?Sized>(source: &S, text: &str, x: f32, px_size: f32) -> usize {
+    hit_test_with(source, text, x, px_size, &LayoutOptions::default())
+}
+
+/// `hit_test` in the line as `layout_line_with` lays it out.
+pub fn hit_test_with<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    x: f32,
+    px_size: f32,
+    layout: &LayoutOptions,
+) -> usize {
+    caret_stops_with(source, text, px_size, layout)
+        .into_iter()
+        .min_by(|a, b| (a.1 - x).abs().total_cmp(&(b.1 - x).abs()))
+        .map_or(0, |(offset, _)| offset)
+}
+
+/// The caret of pen x `x` as a line from `top` to `bottom` (y down) that crosses the baseline at
+/// `baseline` the caret offset right of `x` and leans with the caret slope of `source`. Returns
+/// its top and bottom points.
+pub fn slanted_caret<S: GlyphSource + ?Sized>(
+    source: &S,
+    x: f32,
+    baseline: f32,
+    (top, bottom): (f32, f32),
+    px_size: f32,
+) -> ((f32, f32), (f32, f32)) {
+    let (slant, offset) = source.caret_slant();
+    let x = x + offset * px_size / source.units_per_em() as f32;
+    ((x + (baseline - top) * slant, top), (x - (bottom - baseline) * slant, bottom))
+}
+
+/// `hit_test_with` for a point `rise` pixels above the baseline, measured against the carets
+/// as `slanted_caret` leans them.
+pub fn hit_test_slanted_with<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    (x, rise): (f32, f32),
+    px_size: f32,
+    layout: &LayoutOptions,
+) -> usize {
+    let (slant, offset) = source.caret_slant();
+    let x = x - offset * px_size / source.units_per_em() as f32 - rise * slant;
+    hit_test_with(source, text, x, px_size, layout)
+}
+
+/// The caret position next to the one at byte `offset` on screen, to the right or to the left,
+/// `None` at that end of the line. Across runs of the other direction this goes through the
+/// text out of logical order, the way the arrow keys move the caret.
+pub fn move_caret_visually_with<S: GlyphSource + ?Sized>(
+    source: &S,
+    text: &str,
+    offset: usize,
+    rightwards: bool,
+    px_size: f32,
+    layout: &LayoutOptions,
+) -> Option<usize> {
+    let stops = caret_stops_with(source, text, px_size, layout);
+    let i = stops.iter().position(|(stop, _)| *stop == offset)?;
+    let next = if rightwards { i + 1 } else { i.checked_sub(1)? };
+    stops.get(next).map(|(stop, _)| *stop)
+}
+
+/// `Font::draw_text_with` for any glyph source: lay out the line and blit each glyph rasterized
+/// by `raster::rasterize`.
+pub fn draw_text_with<S: GlyphSource + ?Sized>(
+    source: &S,
+    dt: &mut DrawTarget,
+    text: &str,
+    origin: (f32, f32),
+    px_size: f32,
+    color: SolidSource,
+    options: &RasterOptions,
+) -> f32 {
+    let (glyphs, end_x) = layout_line(source, text, origin, px_size, Some(options));
+    for PositionedGlyph { index, position, .. } in glyphs {
+        if let Some(bitmap) = rasterize(source, index, px_size, options) {
+            blit_a8(dt, &bitmap, position.0.round() as i32, position.1.round() as i32, color);
+        }
+    }
+    end_x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fonts::{rectangle, TestFontBuilder};
+
+    fn test_font() -> Font {
+        let mut builder = TestFontBuilder::new();
+        builder.add_glyph('a', &rectangle(50., 0., 450., 600.)).add_glyph('b', &rectangle(50., 0., 450., 700.));
+        Font::from_bytes(builder.build().unwrap()).unwrap()
+    }
+
+    /// A xorshift generator, for tests that try many inputs reproducibly.
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    #[test]
+    fn measured_advances_end_where_drawing_does() {
+        const CHARS: &[char] = &['a', 'f', 'i', 'l', 'm', 'W', 'T', 'o', '.', ',', ' ', '1', '\u{e9}', '\u{2014}', '?'];
+        let fonts = ["Inconsolata-Regular.ttf", "SourceCodePro-Regular.ttf"]
+            .map(|name| Font::read_truetype(&format!("{}/{name}", env!("CARGO_MANIFEST_DIR"))).unwrap());
+        let hinting = [Hinting::None, Hinting::AutoVertical, Hinting::Native];
+        let mut dt = DrawTarget::new(64, 64);
+        let color = SolidSource::from_unpremultiplied_argb(0xff, 0, 0, 0);
+        let mut random = Xorshift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..200 {
+            let font = &fonts[random.below(fonts.len())];
+            let length = 1 + random.below(24);
+            let text: String = (0..length).map(|_| CHARS[random.below(CHARS.len())]).collect();
+            let px_size = 6. + random.below(600) as f32 / 10.;
+            let origin = (random.below(100) as f32 / 7., 30.);
+            let end_x = font.draw_text(&mut dt, &text, origin, px_size, color);
+            let measured = font.measure_text(&text, px_size);
+            assert!((origin.0 + measured - end_x).abs() < 1e-4, "{text:?} at {px_size}px");
+            let options = RasterOptions::default().with_hinting(hinting[random.below(hinting.len())]);
+            let end_x = font.draw_text_with(&mut dt, &text, (0., 30.), px_size, color, &options);
+            let measured = font.measure_text_with(&text, px_size, &options);
+            assert!((measured - end_x).abs() < 1e-4, "{text:?} at {px_size}px with {:?}", options.hinting);
+        }
+    }
+
+    #[test]
+    fn glyph_bitmaps_are_cached_per_instance() {
+        let mut font = test_font();
+        let cache = GlyphBitmapCache::new();
+        let bitmap = |font: &Font| cache.bitmap(font, 1, 16., 0, Hinting::None);
+        bitmap(&font);
+        bitmap(&font);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        font.normalized_coords = vec![0.5];
+        bitmap(&font);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        font.normalized_coords = Vec::new();
+        bitmap(&font);
+        assert_eq!((cache.hits(), cache.misses()), (2, 2));
+    }
+}
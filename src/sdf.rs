@@ -0,0 +1,333 @@
+use crate::bitmap::{Bitmap, BitmapFormat};
+use crate::flatten::Contour;
+use crate::font::Font;
+use crate::glyph::GlyphData;
+use crate::overlap::winding_number;
+use crate::raster::{GlyphBitmap, RasterBounds, RenderMode, FLATTEN_TOLERANCE_PX};
+
+/// The signed distance field of contours given in pixels (y up, relative to the glyph origin)
+/// over the pixels of `bounds`: for each pixel center the distance to the nearest edge, mapped
+/// so that 128 is on the outline and `spread` pixels inside or outside saturate at 255 or 0.
+/// Whether a sample is inside comes from the non-zero winding number of all the contours around
+/// it, never from the orientation of its nearest edge, so overlapping contours are inside
+/// everywhere they overlap and samples equidistant from two edges get the same sign whichever
+/// of them is found first.
+pub fn signed_distance_field(contours: &[Contour], bounds: RasterBounds, spread: f32) -> Vec<u8> {
+    let RasterBounds { left, top, width, height } = bounds;
+    let edges: Vec<((f64, f64), (f64, f64))> = contours
+        .iter()
+        .flat_map(|contour| contour.edges())
+        .map(|(p0, p1)| ((p0.0 as f64, p0.1 as f64), (p1.0 as f64, p1.1 as f64)))
+        .filter(|(p0, p1)| p0 != p1)
+        .collect();
+    let spread = spread.max(f32::EPSILON);
+    let mut data = vec![0; width * height];
+    for row in 0..height {
+        let y = top as f64 - row as f64 - 0.5;
+        for column in 0..width {
+            let point = (left as f64 + column as f64 + 0.5, y);
+            let distance = edges
+                .iter()
+                .map(|(p0, p1)| distance_to_edge(point, *p0, *p1))
+                .fold(f64::INFINITY, f64::min) as f32;
+            let signed = match winding_number(&edges, point) != 0 {
+                true => distance,
+                false => -distance,
+            };
+            data[row * width + column] = ((0.5 + signed / (2. * spread)).clamp(0., 1.) * 255.).round() as u8;
+        }
+    }
+    data
+}
+
+/// Distance from `point` to the segment from `p0` to `p1`.
+fn distance_to_edge(point: (f64, f64), p0: (f64, f64), p1: (f64, f64)) -> f64 {
+    let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+    let t = (((point.0 - p0.0) * dx + (point.1 - p0.1) * dy) / (dx * dx + dy * dy)).clamp(0., 1.);
+    let (x, y) = (p0.0 + t * dx - point.0, p0.1 + t * dy - point.1);
+    (x * x + y * y).sqrt()
+}
+
+/// Channels of an MSDF edge color, an edge counts for the channels of its color.
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const WHITE: u8 = RED | GREEN | BLUE;
+/// The colors edges switch between at corners, any two of them share one channel.
+const EDGE_COLORS: [u8; 3] = [GREEN | BLUE, RED | BLUE, RED | GREEN];
+/// Contours are polylines, a vertex turning more than this (in radians) is a corner: curves
+/// flattened for `remove_overlaps` turn a few degrees per vertex, glyph corners far more.
+const CORNER_ANGLE: f64 = std::f64::consts::PI / 6.;
+/// Edges this close to equally near (in pixels) are told apart by how square to them a sample is
+const TIE_DISTANCE: f64 = 1e-9;
+
+/// A polyline edge of a contour and the channels it draws in. Runs of edges between corners
+/// are colored alike; at the ends of a run the distance is measured to the edge's line rather
+/// than to its end point, so that channels meet in a sharp corner.
+#[derive(Debug, Clone, Copy)]
+struct ColoredEdge {
+    p0: (f64, f64),
+    p1: (f64, f64),
+    color: u8,
+    starts_run: bool,
+    ends_run: bool,
+}
+
+/// Color the edges of a closed polyline, msdfgen's simple strategy: no corners (a smooth
+/// contour), all white; one corner (a teardrop), three runs; otherwise colors switching at
+/// every corner, the last run differing from the first.
+fn color_edges(contour: &Contour, edges: &mut Vec<ColoredEdge>) {
+    let points: Vec<(f64, f64)> = contour.points.iter().map(|p| (p.0 as f64, p.1 as f64)).collect();
+    let mut segments: Vec<((f64, f64), (f64, f64))> =
+        points.windows(2).map(|pair| (pair[0], pair[1])).filter(|(p0, p1)| p0 != p1).collect();
+    let n = segments.len();
+    if n == 0 {
+        return;
+    }
+    let direction = |(p0, p1): ((f64, f64), (f64, f64))| (p1.0 - p0.0, p1.1 - p0.1);
+    let (cos, sin) = (CORNER_ANGLE.cos(), CORNER_ANGLE.sin());
+    let corners: Vec<usize> = (0..n)
+        .filter(|i| {
+            let (a, b) = (direction(segments[(i + n - 1) % n]), direction(segments[*i]));
+            let (dot, cross) = (a.0 * b.0 + a.1 * b.1, a.0 * b.1 - a.1 * b.0);
+            let length = (a.0.hypot(a.1) * b.0.hypot(b.1)).max(f64::MIN_POSITIVE);
+            dot / length < cos || (cross / length).abs() > sin
+        })
+        .collect();
+    // start at a corner, so that runs don't wrap around the end of the list
+    segments.rotate_left(corners.first().copied().unwrap_or(0));
+    let corners: Vec<usize> = corners.iter().map(|corner| corner - corners[0]).collect();
+    // the run each edge belongs to, and the color of each run
+    let (runs, colors): (Vec<usize>, Vec<u8>) = match corners.len() {
+        0 => (vec![0; n], vec![WHITE]),
+        1 => ((0..n).map(|i| (i * 3 / n).min(2)).collect(), vec![EDGE_COLORS[1], WHITE, EDGE_COLORS[2]]),
+        count => {
+            let runs = (0..n).map(|i| corners.iter().rposition(|corner| *corner <= i).unwrap_or(0)).collect();
+            let mut colors: Vec<u8> = (0..count).map(|run| EDGE_COLORS[run % 3]).collect();
+            if colors[count - 1] == colors[0] {
+                let (first, before_last) = (colors[0], colors[count - 2]);
+                let third = EDGE_COLORS.into_iter().find(|color| *color != first && *color != before_last);
+                colors[count - 1] = third.unwrap_or(before_last);
+            }
+            (runs, colors)
+        }
+    };
+    let smooth = corners.is_empty();
+    for (i, (p0, p1)) in segments.into_iter().enumerate() {
+        edges.push(ColoredEdge {
+            p0,
+            p1,
+            color: colors[runs[i]],
+            starts_run: !smooth && (i == 0 || runs[i - 1] != runs[i]),
+            ends_run: !smooth && (i == n - 1 || runs[i + 1] != runs[i]),
+        });
+    }
+}
+
+/// The median of three values, what an MSDF shader computes from a texel's channels.
+pub fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.max(b).min(a.min(b).max(c))
+}
+
+/// The multi-channel signed distance field of contours given in pixels (y up), laid out like
+/// `signed_distance_field`: three bytes per pixel, each channel the signed distance to the
+/// nearest edge of the channel's color. The median of the three is the signed distance of
+/// `signed_distance_field` away from corners and keeps corners sharp when the field is
+/// magnified, as msdfgen's fields do. Samples whose median lands on the wrong side of the
+/// outline (by winding number) get the plain distance in all three channels.
+pub fn multi_channel_distance_field(contours: &[Contour], bounds: RasterBounds, spread: f32) -> Vec<u8> {
+    let RasterBounds { left, top, width, height } = bounds;
+    let mut edges = Vec::new();
+    for contour in contours {
+        color_edges(contour, &mut edges);
+    }
+    let lines: Vec<((f64, f64), (f64, f64))> = edges.iter().map(|edge| (edge.p0, edge.p1)).collect();
+    let spread = spread.max(f32::EPSILON) as f64;
+    let encode = |distance: f64| ((0.5 + distance / (2. * spread)).clamp(0., 1.) * 255.).round() as u8;
+    let mut data = vec![0; width * height * 3];
+    for row in 0..height {
+        let y = top as f64 - row as f64 - 0.5;
+        for column in 0..width {
+            let point = (left as f64 + column as f64 + 0.5, y);
+            // per channel: distance, how square to the edge the point is, and the edge
+            let mut nearest: [Option<(f64, f64, &ColoredEdge)>; 3] = [None; 3];
+            let mut distance = f64::INFINITY;
+            for edge in &edges {
+                let (edge_distance, orthogonality) = distance_and_orthogonality(point, edge.p0, edge.p1);
+                distance = distance.min(edge_distance);
+                for (channel, nearest) in nearest.iter_mut().enumerate() {
+                    // of equally near edges (meeting at the nearest point) the squarest one
+                    let closer = match nearest {
+                        None => true,
+                        Some((best, best_orthogonality, _)) => {
+                            edge_distance < *best - TIE_DISTANCE
+                                || (edge_distance <= *best + TIE_DISTANCE && orthogonality > *best_orthogonality)
+                        }
+                    };
+                    if edge.color & (1 << channel) != 0 && closer {
+                        *nearest = Some((edge_distance, orthogonality, edge));
+                    }
+                }
+            }
+            let inside = winding_number(&lines, point) != 0;
+            let channels =
+                nearest.map(|nearest| nearest.map_or(-spread, |(_, _, edge)| signed_pseudo_distance(point, edge)));
+            let at = (row * width + column) * 3;
+            let pixel = &mut data[at..at + 3];
+            let median = median(channels[0] as f32, channels[1] as f32, channels[2] as f32);
+            if (median > 0.) == inside || median == 0. {
+                for (value, channel) in pixel.iter_mut().zip(channels) {
+                    *value = encode(channel);
+                }
+            } else {
+                pixel.fill(encode(if inside { distance } else { -distance }));
+            }
+        }
+    }
+    data
+}
+
+/// Distance from `point` to the segment from `p0` to `p1`, and the sine of the angle between the
+/// segment and the way to the point, 1 when the point is square to it.
+fn distance_and_orthogonality(point: (f64, f64), p0: (f64, f64), p1: (f64, f64)) -> (f64, f64) {
+    let distance = distance_to_edge(point, p0, p1);
+    let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+    let cross = dx * (point.1 - p0.1) - dy * (point.0 - p0.0);
+    let orthogonality = (cross / (dx.hypot(dy) * distance)).abs();
+    (distance, if orthogonality.is_finite() { orthogonality } else { 1. })
+}
+
+/// The distance from `point` to the edge, positive on its right (inside for the clockwise
+/// contours of `remove_overlaps`), to the edge's line past the open ends of a run.
+fn signed_pseudo_distance(point: (f64, f64), edge: &ColoredEdge) -> f64 {
+    let (p0, p1) = (edge.p0, edge.p1);
+    let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+    let t = ((point.0 - p0.0) * dx + (point.1 - p0.1) * dy) / (dx * dx + dy * dy);
+    let cross = dx * (point.1 - p0.1) - dy * (point.0 - p0.0);
+    let distance = match (t < 0. && edge.starts_run) || (t > 1. && edge.ends_run) {
+        true => cross.abs() / dx.hypot(dy),
+        false => distance_to_edge(point, p0, p1),
+    };
+    if cross < 0. {
+        distance
+    } else {
+        -distance
+    }
+}
+
+/// A multi-channel signed distance field of a glyph, see `Font::glyph_msdf`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MsdfBitmap {
+    /// Horizontal offset of the left edge of the bitmap from the glyph origin, in pixels
+    pub left: i32,
+    /// Distance from the baseline up to the top edge of the bitmap, in pixels
+    pub top: i32,
+    /// The distance range in pixels each side of the outline the channels span, 0 to 255
+    pub spread: f32,
+    /// `BitmapFormat::Rgb8`, tightly packed
+    pub bitmap: Bitmap,
+}
+
+impl MsdfBitmap {
+    /// Draw the glyph from the field magnified `scale` times, the way an MSDF shader does:
+    /// channels sampled bilinearly, their median turned into coverage over one output pixel.
+    pub fn render(&self, scale: f32) -> GlyphBitmap {
+        let (width, height) = (self.bitmap.width, self.bitmap.height);
+        let out_width = (width as f32 * scale).round() as usize;
+        let out_height = (height as f32 * scale).round() as usize;
+        let texel = |x: usize, y: usize, channel: usize| {
+            self.bitmap.data[y * self.bitmap.stride + x * 3 + channel] as f32 / 255.
+        };
+        let mut data = vec![0; out_width * out_height];
+        for out_y in 0..out_height {
+            let v = ((out_y as f32 + 0.5) / scale - 0.5).clamp(0., height as f32 - 1.);
+            let (y0, fy) = (v.floor() as usize, v.fract());
+            let y1 = (y0 + 1).min(height - 1);
+            for out_x in 0..out_width {
+                let u = ((out_x as f32 + 0.5) / scale - 0.5).clamp(0., width as f32 - 1.);
+                let (x0, fx) = (u.floor() as usize, u.fract());
+                let x1 = (x0 + 1).min(width - 1);
+                let sample = |channel| {
+                    let top = texel(x0, y0, channel) * (1. - fx) + texel(x1, y0, channel) * fx;
+                    let bottom = texel(x0, y1, channel) * (1. - fx) + texel(x1, y1, channel) * fx;
+                    top * (1. - fy) + bottom * fy
+                };
+                let distance = (median(sample(0), sample(1), sample(2)) - 0.5) * 2. * self.spread * scale;
+                data[out_y * out_width + out_x] = ((distance + 0.5).clamp(0., 1.) * 255.).round() as u8;
+            }
+        }
+        GlyphBitmap {
+            width: out_width,
+            height: out_height,
+            left: (self.left as f32 * scale).round() as i32,
+            top: (self.top as f32 * scale).round() as i32,
+            mode: RenderMode::Grayscale,
+            data,
+        }
+    }
+}
+
+impl Font {
+    /// The glyph as a signed distance field at `px_size`, see `signed_distance_field`, in a
+    /// grayscale bitmap reaching `spread` pixels past the outline on every side. The outline
+    /// (composites put together) goes through `GlyphData::remove_overlaps` first, so distances
+    /// are to the edges of the filled shape: the edges of one contour running inside another
+    /// don't pull the field towards 128 in the middle of a stroke.
+    pub fn glyph_sdf(&self, index: u16, px_size: f32, spread: f32) -> Option<GlyphBitmap> {
+        let (contours, bounds) = self.distance_field_contours(index, px_size, spread)?;
+        Some(GlyphBitmap {
+            width: bounds.width,
+            height: bounds.height,
+            left: bounds.left,
+            top: bounds.top,
+            mode: RenderMode::Grayscale,
+            data: signed_distance_field(&contours, bounds, spread),
+        })
+    }
+
+    /// `glyph_sdf` with three channels, see `multi_channel_distance_field`: sharp corners at any
+    /// magnification for shaders that take the median of the channels (`MsdfBitmap::render`
+    /// does the same on the CPU).
+    pub fn glyph_msdf(&self, index: u16, px_size: f32, spread: f32) -> Option<MsdfBitmap> {
+        let (contours, bounds) = self.distance_field_contours(index, px_size, spread)?;
+        let mut bitmap = Bitmap::new(bounds.width, bounds.height, BitmapFormat::Rgb8, 1);
+        bitmap.data = multi_channel_distance_field(&contours, bounds, spread);
+        Some(MsdfBitmap {
+            left: bounds.left,
+            top: bounds.top,
+            spread: spread.max(0.),
+            bitmap,
+        })
+    }
+
+    /// The glyph's outline without overlaps flattened in pixels, and the pixels a field
+    /// reaching `spread` past it covers (empty at the origin for glyphs without an outline).
+    fn distance_field_contours(&self, index: u16, px_size: f32, spread: f32) -> Option<(Vec<Contour>, RasterBounds)> {
+        let outline = GlyphData::from_outline(&self.drawn_outline(index)?).ok()?.remove_overlaps();
+        let scale = self.scale_for_px(px_size);
+        let mut contours = outline.flatten(FLATTEN_TOLERANCE_PX / scale);
+        for point in contours.iter_mut().flat_map(|contour| contour.points.iter_mut()) {
+            *point = (point.0 * scale, point.1 * scale);
+        }
+        let points = contours.iter().flat_map(|contour| contour.points.iter());
+        let bounds = points.fold(None, |bounds: Option<(f32, f32, f32, f32)>, (x, y)| match bounds {
+            None => Some((*x, *y, *x, *y)),
+            Some((x_min, y_min, x_max, y_max)) => Some((x_min.min(*x), y_min.min(*y), x_max.max(*x), y_max.max(*y))),
+        });
+        let Some((x_min, y_min, x_max, y_max)) = bounds else {
+            return Some((contours, RasterBounds::default()));
+        };
+        let spread = spread.max(0.);
+        let left = (x_min - spread).floor() as i32;
+        let right = (x_max + spread).ceil() as i32;
+        let top = (y_max + spread).ceil() as i32;
+        let bottom = (y_min - spread).floor() as i32;
+        let bounds = RasterBounds {
+            left,
+            top,
+            width: (right - left) as usize,
+            height: (top - bottom) as usize,
+        };
+        Some((contours, bounds))
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::font::Font;
+use crate::layout::PositionPolicy;
+use crate::raster::RasterOptions;
+use crate::render::{layout_line, PositionedGlyph};
+
+const DEFAULT_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ShapeKey {
+    text: String,
+    px_size: u32, // the bits of the f32
+    options: Option<RasterOptions>,
+}
+
+#[derive(Debug, Clone)]
+struct Shape {
+    glyphs: Vec<PositionedGlyph>,
+    end_x: f32,
+    last_used: u64,
+}
+
+/// Laid out lines of text by (text, pixel size, raster options), for UI code drawing the same
+/// labels every frame: cmap lookups, shaping and kerning run once per label. Lines are stored as
+/// laid out at the origin and moved to where they're drawn. When more than the capacity are
+/// stored the least recently used goes. One cache is for one font, it empties itself when the
+/// font's variation coordinates change.
+#[derive(Debug, Clone)]
+pub struct ShapeCache {
+    capacity: usize,
+    shapes: HashMap<ShapeKey, Shape>,
+    /// The variation coordinates the stored lines were laid out at
+    coords: Vec<f32>,
+    clock: u64,
+}
+
+impl Default for ShapeCache {
+    fn default() -> Self {
+        ShapeCache::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ShapeCache {
+    /// A cache holding up to `capacity` lines (at least one).
+    pub fn new(capacity: usize) -> ShapeCache {
+        ShapeCache {
+            capacity: capacity.max(1),
+            shapes: HashMap::new(),
+            coords: Vec::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+    }
+
+    /// `render::layout_line` of `font`, from the cache when the line was laid out before.
+    pub fn layout_line(
+        &mut self,
+        font: &Font,
+        text: &str,
+        origin: (f32, f32),
+        px_size: f32,
+        options: Option<&RasterOptions>,
+    ) -> (Vec<PositionedGlyph>, f32) {
+        if self.coords != font.normalized_coords {
+            self.shapes.clear();
+            self.coords.clone_from(&font.normalized_coords);
+        }
+        self.clock += 1;
+        let key = ShapeKey {
+            text: text.to_string(),
+            px_size: px_size.to_bits(),
+            options: options.cloned(),
+        };
+        if !self.shapes.contains_key(&key) {
+            if self.shapes.len() >= self.capacity {
+                let oldest = self.shapes.iter().min_by_key(|(_, shape)| shape.last_used).map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    self.shapes.remove(&oldest);
+                }
+            }
+            let (glyphs, end_x) = layout_line(font, text, (0., 0.), px_size, options);
+            self.shapes.insert(key.clone(), Shape { glyphs, end_x, last_used: 0 });
+        }
+        let shape = self.shapes.get_mut(&key).unwrap();
+        shape.last_used = self.clock;
+
+        // where layout_line starts the line, see `PositionPolicy::for_raster`
+        let x = PositionPolicy::None.for_raster(options).snap_x(origin.0);
+        let glyphs = shape
+            .glyphs
+            .iter()
+            .map(|glyph| PositionedGlyph {
+                position: (glyph.position.0 + x, glyph.position.1 + origin.1),
+                ..*glyph
+            })
+            .collect();
+        (glyphs, shape.end_x + x)
+    }
+}
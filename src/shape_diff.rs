@@ -0,0 +1,74 @@
+use crate::font::Font;
+use crate::render::{layout_line, PositionedGlyph};
+
+/// A line of text the two fonts lay out differently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineDiff {
+    /// Line number in the text, from 1
+    pub line: usize,
+    pub text: String,
+    /// The glyphs each font laid out, pen positions from the start of the line
+    pub old: Vec<PositionedGlyph>,
+    pub new: Vec<PositionedGlyph>,
+    pub old_width: f32,
+    pub new_width: f32,
+}
+
+impl LineDiff {
+    /// Whether the fonts picked different glyphs for the line, or as many for different
+    /// characters.
+    pub fn glyphs_differ(&self) -> bool {
+        let ids = |glyphs: &[PositionedGlyph]| -> Vec<(u16, usize)> {
+            glyphs.iter().map(|glyph| (glyph.index, glyph.cluster)).collect()
+        };
+        ids(&self.old) != ids(&self.new)
+    }
+
+    /// The largest difference in pixels between the widths of the line and the advances and
+    /// positions of its glyphs, infinite when the glyphs differ.
+    pub fn max_deviation(&self) -> f32 {
+        if self.glyphs_differ() {
+            return f32::INFINITY;
+        }
+        let mut deviation = (self.new_width - self.old_width).abs();
+        for (old, new) in self.old.iter().zip(&self.new) {
+            deviation = deviation.max((new.advance - old.advance).abs());
+            deviation = deviation.max((new.position.0 - old.position.0).abs());
+            deviation = deviation.max((new.position.1 - old.position.1).abs());
+        }
+        deviation
+    }
+
+    /// The glyphs that differ, by their place in the line, `None` past the end of the shorter
+    /// line.
+    pub fn glyph_changes(&self) -> Vec<(usize, Option<&PositionedGlyph>, Option<&PositionedGlyph>)> {
+        (0..self.old.len().max(self.new.len()))
+            .map(|i| (i, self.old.get(i), self.new.get(i)))
+            .filter(|(_, old, new)| old != new)
+            .collect()
+    }
+}
+
+impl Font {
+    /// Lay out each line of `text` at `px_size` with this font and with `other`, a newer
+    /// version of it, and return the lines that come out differently in order. Lines are laid
+    /// out unhinted by `layout_line`, so kerning, mark placement and joining changes all show.
+    pub fn diff_shaping(&self, other: &Font, text: &str, px_size: f32) -> Vec<LineDiff> {
+        let mut diffs = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let (old, old_width) = layout_line(self, line, (0., 0.), px_size, None);
+            let (new, new_width) = layout_line(other, line, (0., 0.), px_size, None);
+            if old != new || old_width != new_width {
+                diffs.push(LineDiff {
+                    line: i + 1,
+                    text: line.to_string(),
+                    old,
+                    new,
+                    old_width,
+                    new_width,
+                });
+            }
+        }
+        diffs
+    }
+}
@@ -0,0 +1,211 @@
+//! SSE2 versions of the two hot loops of drawing text: the prefix sum turning the rasterizer's
+//! accumulated deltas into winding numbers, and the A8 blend of `render::blit_a8`. SSE2 is part
+//! of x86_64 so they're picked at compile time there, other targets use the scalar versions.
+//! Both versions of a loop give bit-identical results.
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+use std::arch::x86_64::*;
+
+/// `sums[i]` = `deltas[0] + ... + deltas[i]`, summed four values at a time: each block's own
+/// prefix sums are added in the order of a vector shift-and-add, [d0, d1 + d0, (d2 + d1) + d0,
+/// (d3 + d2) + (d1 + d0)], then the block is added to the sum so far. The leftover values at the
+/// end are added one by one.
+pub(crate) fn accumulate(deltas: &[f32], sums: &mut [f32]) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    // SAFETY: SSE2 is available, the target has the feature
+    unsafe {
+        accumulate_sse2(deltas, sums)
+    }
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+    accumulate_scalar(deltas, sums)
+}
+
+#[cfg_attr(all(target_arch = "x86_64", target_feature = "sse2"), allow(dead_code))]
+fn accumulate_scalar(deltas: &[f32], sums: &mut [f32]) {
+    let mut accumulated = 0.0f32;
+    let blocks = deltas.len() / 4 * 4;
+    for (d, sum) in deltas[..blocks].chunks_exact(4).zip(sums.chunks_exact_mut(4)) {
+        // the zeros a vector shift brings in are added too, they matter for -0.0
+        let (s1, s2, s3) = (d[1] + d[0], d[2] + d[1], d[3] + d[2]);
+        let block = [d[0] + 0.0 + 0.0, s1 + 0.0, s2 + (d[0] + 0.0), s3 + s1];
+        for (sum, value) in sum.iter_mut().zip(block) {
+            *sum = accumulated + value;
+        }
+        accumulated = sum[3];
+    }
+    for (delta, sum) in deltas[blocks..].iter().zip(&mut sums[blocks..]) {
+        accumulated += delta;
+        *sum = accumulated;
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+unsafe fn accumulate_sse2(deltas: &[f32], sums: &mut [f32]) {
+    let length = deltas.len().min(sums.len());
+    let blocks = length / 4 * 4;
+    let mut accumulated = _mm_setzero_ps();
+    let shift = |v: __m128, bytes| match bytes {
+        4 => _mm_castsi128_ps(_mm_slli_si128(_mm_castps_si128(v), 4)),
+        _ => _mm_castsi128_ps(_mm_slli_si128(_mm_castps_si128(v), 8)),
+    };
+    for at in (0..blocks).step_by(4) {
+        let mut block = _mm_loadu_ps(deltas.as_ptr().add(at));
+        block = _mm_add_ps(block, shift(block, 4));
+        block = _mm_add_ps(block, shift(block, 8));
+        let sum = _mm_add_ps(accumulated, block);
+        _mm_storeu_ps(sums.as_mut_ptr().add(at), sum);
+        accumulated = _mm_shuffle_ps(sum, sum, 0xff);
+    }
+    let mut accumulated = _mm_cvtss_f32(accumulated);
+    for (delta, sum) in deltas[blocks..length].iter().zip(&mut sums[blocks..length]) {
+        accumulated += delta;
+        *sum = accumulated;
+    }
+}
+
+/// `a * b / 255` rounded, for 8-bit values.
+pub(crate) fn mul_div_255(a: u32, b: u32) -> u32 {
+    let product = a * b + 128;
+    (product + (product >> 8)) >> 8
+}
+
+/// Composite a row of A8 `coverage` source-over onto premultiplied ARGB `pixels` in the
+/// premultiplied `color` (a, r, g, b).
+pub(crate) fn blend_a8_row(pixels: &mut [u32], coverage: &[u8], color: [u32; 4]) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    // SAFETY: SSE2 is available, the target has the feature
+    unsafe {
+        blend_a8_row_sse2(pixels, coverage, color)
+    }
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+    blend_a8_row_scalar(pixels, coverage, color)
+}
+
+fn blend_a8_row_scalar(pixels: &mut [u32], coverage: &[u8], [a, r, g, b]: [u32; 4]) {
+    for (pixel, coverage) in pixels.iter_mut().zip(coverage) {
+        let coverage = *coverage as u32;
+        if coverage == 0 {
+            continue;
+        }
+        let inverse = 255 - mul_div_255(a, coverage);
+        let blend = |shift: u32, source: u32| {
+            let dst = (*pixel >> shift) & 0xff;
+            (mul_div_255(source, coverage) + mul_div_255(dst, inverse)).min(255) << shift
+        };
+        *pixel = blend(24, a) | blend(16, r) | blend(8, g) | blend(0, b);
+    }
+}
+
+/// Four pixels at a time, their channels widened to 16 bits. A zero coverage leaves a pixel as
+/// it is here too, `mul_div_255(dst, 255)` is `dst`.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+unsafe fn blend_a8_row_sse2(pixels: &mut [u32], coverage: &[u8], color: [u32; 4]) {
+    let length = pixels.len().min(coverage.len());
+    let blocks = length / 4 * 4;
+    let zero = _mm_setzero_si128();
+    let (half, full) = (_mm_set1_epi16(128), _mm_set1_epi16(255));
+    let mul_div_255 = |a: __m128i, b: __m128i| {
+        let product = _mm_add_epi16(_mm_mullo_epi16(a, b), half);
+        _mm_srli_epi16(_mm_add_epi16(product, _mm_srli_epi16(product, 8)), 8)
+    };
+    let [a, r, g, b] = color.map(|channel| channel as i16);
+    // two pixels worth of channels, in the order of the bytes of a pixel in memory
+    let source = _mm_set_epi16(a, r, g, b, a, r, g, b);
+    let alpha = _mm_set1_epi16(a);
+    for at in (0..blocks).step_by(4) {
+        let covered = u32::from_le_bytes([coverage[at], coverage[at + 1], coverage[at + 2], coverage[at + 3]]);
+        if covered == 0 {
+            continue;
+        }
+        let spread = _mm_cvtsi32_si128(covered as i32);
+        let spread = _mm_unpacklo_epi16(_mm_unpacklo_epi8(spread, spread), _mm_unpacklo_epi8(spread, spread));
+        let destination = _mm_loadu_si128(pixels.as_ptr().add(at) as *const __m128i);
+        let blend = |dst: __m128i, coverage: __m128i| {
+            let inverse = _mm_sub_epi16(full, mul_div_255(alpha, coverage));
+            _mm_add_epi16(mul_div_255(source, coverage), mul_div_255(dst, inverse))
+        };
+        let low = blend(_mm_unpacklo_epi8(destination, zero), _mm_unpacklo_epi8(spread, zero));
+        let high = blend(_mm_unpackhi_epi8(destination, zero), _mm_unpackhi_epi8(spread, zero));
+        // packing saturates, like the min(255) of the scalar version
+        _mm_storeu_si128(pixels.as_mut_ptr().add(at) as *mut __m128i, _mm_packus_epi16(low, high));
+    }
+    blend_a8_row_scalar(&mut pixels[blocks..length], &coverage[blocks..length], color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64, the values don't matter as long as they vary.
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// A delta the rasterizer could accumulate, now and then a zero of either sign.
+        fn delta(&mut self) -> f32 {
+            match self.next() % 8 {
+                0 => 0.0,
+                1 => -0.0,
+                _ => (self.next() % 2001) as f32 / 1000. - 1. + (self.next() % 7) as f32 * 1e-7,
+            }
+        }
+    }
+
+    #[test]
+    fn accumulate_matches_the_scalar_version_bit_for_bit() {
+        let mut random = Xorshift(0x9e37_79b9_7f4a_7c15);
+        for length in (0..40).chain([255, 256, 1023]) {
+            let deltas = (0..length).map(|_| random.delta()).collect::<Vec<_>>();
+            let (mut sums, mut expected) = (vec![f32::NAN; length], vec![f32::NAN; length]);
+            accumulate(&deltas, &mut sums);
+            accumulate_scalar(&deltas, &mut expected);
+            let bits = |values: &[f32]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&sums), bits(&expected), "{length} deltas {deltas:?}");
+        }
+    }
+
+    #[test]
+    fn accumulate_keeps_negative_zeros() {
+        let deltas = [-0.0; 7];
+        let mut sums = [1.0; 7];
+        accumulate(&deltas, &mut sums);
+        let mut expected = [1.0; 7];
+        accumulate_scalar(&deltas, &mut expected);
+        assert_eq!(sums.map(f32::to_bits), expected.map(f32::to_bits));
+    }
+
+    #[test]
+    fn blending_matches_the_scalar_version() {
+        let mut random = Xorshift(0x2545_f491_4f6c_dd1d);
+        for length in (0..20).chain([64, 257]) {
+            let a = (random.next() % 256) as u32;
+            // premultiplied, no channel above the alpha
+            let color = [a, a * (random.next() % 256) as u32 / 255, a / 2, a * 3 / 4];
+            let coverage = (0..length)
+                .map(|_| match random.next() % 4 {
+                    0 => 0,
+                    1 => 255,
+                    _ => random.next() as u8,
+                })
+                .collect::<Vec<_>>();
+            let pixels = (0..length)
+                .map(|_| {
+                    let alpha = random.next() % 256;
+                    let channel = |random: &mut Xorshift| random.next() % (alpha + 1);
+                    let (r, g, b) = (channel(&mut random), channel(&mut random), channel(&mut random));
+                    (alpha << 24 | r << 16 | g << 8 | b) as u32
+                })
+                .collect::<Vec<_>>();
+            let (mut blended, mut expected) = (pixels.clone(), pixels);
+            blend_a8_row(&mut blended, &coverage, color);
+            blend_a8_row_scalar(&mut expected, &coverage, color);
+            assert_eq!(blended, expected, "color {color:?} coverage {coverage:?}");
+        }
+    }
+}
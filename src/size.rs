@@ -0,0 +1,147 @@
+//! Font sizes in the units they're given in. Laying out, drawing, measuring and rasterizing
+//! take an `impl Into<FontSize>`, a plain `f32` being the pixels per em of the target. Sizes in
+//! points depend on the resolution, the same 12pt being 12px at 72 dpi and 16px at 96 dpi, and
+//! a `FontSize` keeps both.
+
+use crate::font::Font;
+
+/// Points to the inch, as in PostScript, CSS and the trak table.
+pub const POINTS_PER_INCH: f32 = 72.;
+
+/// A font size as pixels per em at a resolution, see `px` and `pt`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontSize {
+    px_per_em: f32,
+    dpi: f32,
+}
+
+impl FontSize {
+    /// `px_per_em` pixels to the em, a pixel a point (72 dpi) like `Font::tracking` takes it.
+    pub fn px(px_per_em: f32) -> FontSize {
+        FontSize {
+            px_per_em,
+            dpi: POINTS_PER_INCH,
+        }
+    }
+
+    /// `points` points on a target of `dpi` pixels to the inch, e.g. 12pt at 96 dpi is 16px.
+    pub fn pt(points: f32, dpi: f32) -> FontSize {
+        FontSize {
+            px_per_em: points * dpi / POINTS_PER_INCH,
+            dpi,
+        }
+    }
+
+    /// The size on a target with `factor` times the pixels to the inch, like a HiDPI screen
+    /// of a scale factor of 2: the same points in more pixels.
+    pub fn scaled(self, factor: f32) -> FontSize {
+        FontSize {
+            px_per_em: self.px_per_em * factor,
+            dpi: self.dpi * factor,
+        }
+    }
+
+    /// What the functions taking a `px_size` take.
+    pub fn px_per_em(self) -> f32 {
+        self.px_per_em
+    }
+
+    pub fn points(self) -> f32 {
+        self.px_per_em * POINTS_PER_INCH / self.dpi
+    }
+
+    pub fn dpi(self) -> f32 {
+        self.dpi
+    }
+
+    /// Pixels per font unit of a font with `units_per_em`, see `Font::scale_for_px`.
+    pub fn em_units(self, units_per_em: u16) -> f32 {
+        self.px_per_em / units_per_em as f32
+    }
+
+    /// `units` font units of a font with `units_per_em` in pixels.
+    pub fn units_to_px(self, units: f32, units_per_em: u16) -> f32 {
+        units * self.em_units(units_per_em)
+    }
+
+    /// `px` pixels in font units of a font with `units_per_em`.
+    pub fn px_to_units(self, px: f32, units_per_em: u16) -> f32 {
+        px / self.em_units(units_per_em)
+    }
+}
+
+/// Pixels per em, a pixel a point.
+impl From<f32> for FontSize {
+    fn from(px_per_em: f32) -> FontSize {
+        FontSize::px(px_per_em)
+    }
+}
+
+impl Font {
+    /// `scale_for_px` of `size`.
+    pub fn scale_for(&self, size: FontSize) -> f32 {
+        size.em_units(self.units_per_em())
+    }
+
+    /// `tracking` looked up in the trak table at the point size of `size`, rather than taking a
+    /// pixel per point, in pixels of `size`.
+    pub fn tracking_for(&self, size: FontSize, track: f32) -> f32 {
+        self.trak()
+            .map_or(0., |trak| trak.value(size.points(), track) * self.scale_for(size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutOptions;
+    use crate::test_fonts::{rectangle, TestFontBuilder};
+
+    fn test_font() -> Font {
+        let mut builder = TestFontBuilder::new();
+        builder
+            .units_per_em(2048)
+            .vertical_metrics(1700, -348)
+            .add_glyph_with_advance('a', &rectangle(100., 0., 1100., 1100.), Some(1229))
+            .add_glyph_with_advance(' ', &rectangle(0., 0., 0., 0.), Some(569))
+            .add_kern('a', 'a', -41);
+        Font::from_bytes(builder.build().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn points_at_96_dpi_are_a_third_more_pixels() {
+        let size = FontSize::pt(12., 96.);
+        assert_eq!(size.px_per_em(), 16.);
+        assert_eq!(size.points(), 12.);
+        assert_eq!(FontSize::pt(9., 96.).px_per_em(), 12.);
+        assert_eq!(FontSize::pt(12., POINTS_PER_INCH), FontSize::px(12.));
+        let hidpi = size.scaled(2.);
+        assert_eq!((hidpi.px_per_em(), hidpi.points(), hidpi.dpi()), (32., 12., 192.));
+    }
+
+    #[test]
+    fn sizes_in_points_draw_like_their_pixels() {
+        let font = test_font();
+        let (pt, px) = (FontSize::pt(12., 96.), FontSize::px(16.));
+        assert_eq!(font.measure_text("aa a", pt), font.measure_text("aa a", 16.));
+        assert_eq!(font.rasterize_glyph(1, pt), font.rasterize_glyph(1, px));
+        let (wrapped, expected) = (font.wrap_paragraph("aa aa aa", pt, 40.), font.wrap_paragraph("aa aa aa", 16., 40.));
+        assert_eq!(wrapped, expected);
+    }
+
+    #[test]
+    fn metrics_scale_linearly() {
+        let font = test_font();
+        let unit = font.scale_for(FontSize::px(1.));
+        let layout = LayoutOptions::default();
+        let base = font.measure_text_with_layout("aa a", 1., &layout);
+        for px in [7., 12., 16., 33., 100.] {
+            let size = FontSize::px(px);
+            assert_eq!(font.scale_for(size), unit * px);
+            assert_eq!(size.units_to_px(2048., 2048), px);
+            assert!((size.px_to_units(size.units_to_px(1229., 2048), 2048) - 1229.).abs() < 1e-3);
+            let width = font.measure_text_with_layout("aa a", size, &layout);
+            assert!((width - base * px).abs() < 1e-3, "{width} at {px}px, {} expected", base * px);
+        }
+    }
+}
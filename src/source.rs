@@ -0,0 +1,333 @@
+use crate::cmap::{map_str_with, CharMapping};
+use crate::flatten::Contour;
+use crate::font::Font;
+use crate::glyph::Segment;
+use crate::outline::Outline;
+use crate::raster::{GlyphBitmap, Hinting, RasterOptions};
+use crate::trace::ShapingTrace;
+use crate::transform::FontUnitsToPixels;
+
+/// Where the layout and the rasterizer get their glyphs from. `Font` reads them from a TrueType
+/// file, other backends (or an in-memory set of shapes) only have to provide a cmap, advances and
+/// outlines or bitmaps to share the same pipeline, see `render::layout_line` and
+/// `raster::rasterize`. The methods with defaults are the refinements a TrueType font adds.
+pub trait GlyphSource {
+    fn units_per_em(&self) -> u16;
+
+    fn glyph_index(&self, c: char) -> Option<u16>;
+
+    /// The variant glyph for `c` followed by a variation selector.
+    fn glyph_index_with_variation(&self, _c: char, _selector: char) -> Option<u16> {
+        None
+    }
+
+    /// The glyph of each character of `text`, see `Font::map_str`. One lookup per character by
+    /// default.
+    fn map_str(&self, text: &str) -> Vec<CharMapping> {
+        map_str_with(text, |c, selector| {
+            selector
+                .and_then(|selector| self.glyph_index_with_variation(c, selector))
+                .or_else(|| self.glyph_index(c))
+        })
+    }
+
+    /// Advance width in font units.
+    fn advance(&self, index: u16) -> u16;
+
+    /// Ascender, descender (negative below the baseline) and line gap in font units, for the
+    /// distance between lines. Four fifths of the em above the baseline by default.
+    fn vertical_metrics(&self) -> (i16, i16, i16) {
+        let units_per_em = self.units_per_em() as i32;
+        ((units_per_em * 4 / 5) as i16, (-units_per_em / 5) as i16, 0)
+    }
+
+    /// The contours of the glyph as line and quadratic segments in font units, y up. Every
+    /// contour ends where it started.
+    fn outline(&self, index: u16) -> Option<Vec<Vec<Segment>>>;
+
+    /// The outline in pixels at `px_size` (y up, relative to the glyph origin), grid fitted when
+    /// `options` ask for it and the source knows how. Plainly scaled by default.
+    fn scaled_outline(&self, index: u16, px_size: f32, _options: &RasterOptions) -> Option<Vec<Vec<Segment>>> {
+        let outline = self.outline(index)?;
+        Some(FontUnitsToPixels::scaling(px_size / self.units_per_em() as f32).apply_segments(&outline))
+    }
+
+    /// `scaled_outline` flattened within `tolerance_px`, for sources that keep flattened outlines
+    /// around. `None`, the default, has the rasterizer flatten `scaled_outline` itself.
+    fn flattened_outline(
+        &self,
+        _index: u16,
+        _px_size: f32,
+        _options: &RasterOptions,
+        _tolerance_px: f32,
+    ) -> Option<Vec<Contour>> {
+        None
+    }
+
+    /// A ready-made bitmap for the glyph at exactly `ppem`, used instead of the outline when the
+    /// raster options prefer embedded bitmaps.
+    fn bitmap(&self, _index: u16, _ppem: u16) -> Option<GlyphBitmap> {
+        None
+    }
+
+    /// Advance in pixels of the glyph's bitmap at `ppem`.
+    fn bitmap_advance(&self, _index: u16, _ppem: u16) -> Option<u16> {
+        None
+    }
+
+    /// Advance in pixels of the grid fitted glyph at `ppem`, for layouts with hinting on.
+    fn hinted_advance(&self, _index: u16, _ppem: u16) -> Option<f32> {
+        None
+    }
+
+    /// Whether the glyph has TrueType instructions to grid fit it with, for
+    /// `Hinting::NativeIfPresent`.
+    fn has_instructions(&self, _index: u16) -> bool {
+        false
+    }
+
+    /// Adjustment in font units of the space between two glyphs.
+    fn kerning(&self, _first: u16, _second: u16) -> i16 {
+        0
+    }
+
+    /// Extra space in pixels after each glyph the font asks for at `px_size`, see
+    /// `Font::tracking`.
+    fn tracking(&self, _px_size: f32) -> f32 {
+        0.
+    }
+
+    /// How far the caret leans right per unit up and how far right of the pen it's drawn in
+    /// font units, upright by default.
+    fn caret_slant(&self) -> (f32, f32) {
+        (0., 0.)
+    }
+
+    /// Marks take no room of their own and go on the glyph before them.
+    fn is_mark(&self, _index: u16) -> bool {
+        false
+    }
+
+    /// Where `mark` goes relative to `base`, in font units.
+    fn mark_offset(&self, _base: u16, _mark: u16) -> Option<(i16, i16)> {
+        None
+    }
+
+    /// Give the glyphs (cluster, glyph index) of a run of a joining script their contextual
+    /// forms, clusters being byte offsets in `text`, recording the substitutions in `trace`.
+    fn shape_joining(&self, _text: &str, _glyphs: &mut Vec<(usize, u16)>, _trace: &mut ShapingTrace) {}
+
+    /// Replace the glyphs (cluster, glyph index) of an emoji sequence by the font's glyph for
+    /// the whole sequence, when it has one, recording the substitutions in `trace`.
+    fn shape_emoji(&self, _glyphs: &mut Vec<(usize, u16)>, _trace: &mut ShapingTrace) {}
+}
+
+impl GlyphSource for Font {
+    fn units_per_em(&self) -> u16 {
+        Font::units_per_em(self)
+    }
+
+    fn glyph_index(&self, c: char) -> Option<u16> {
+        self.glyph_index_for_char(c)
+    }
+
+    fn glyph_index_with_variation(&self, c: char, selector: char) -> Option<u16> {
+        Font::glyph_index_with_variation(self, c, selector)
+    }
+
+    fn map_str(&self, text: &str) -> Vec<CharMapping> {
+        Font::map_str(self, text)
+    }
+
+    fn advance(&self, index: u16) -> u16 {
+        self.advance_width(index)
+    }
+
+    fn vertical_metrics(&self) -> (i16, i16, i16) {
+        (self.ascender(), self.descender(), self.line_gap())
+    }
+
+    fn outline(&self, index: u16) -> Option<Vec<Vec<Segment>>> {
+        Some(self.drawn_outline(index)?.segments())
+    }
+
+    /// Hinted by the glyph's instructions, or else autofitted, as `options.hinting` says and
+    /// when that works for the glyph. The box a blank .notdef is drawn as is only scaled.
+    fn scaled_outline(&self, index: u16, px_size: f32, options: &RasterOptions) -> Option<Vec<Vec<Segment>>> {
+        let native = match options.hinting {
+            Hinting::Native => {
+                if !self.has_instructions(index) {
+                    self.warn_uninstructed();
+                }
+                true
+            }
+            Hinting::NativeIfPresent => self.has_instructions(index),
+            Hinting::None | Hinting::AutoVertical => false,
+        };
+        let synthesized = index == 0 && self.notdef_is_blank();
+        let hinted = (native && !synthesized).then(|| self.hinted_glyph(index, px_size.round() as u16)).flatten();
+        if let Some(hinted) = hinted {
+            return Some(Outline::from_points(&hinted.points, &hinted.on_curve, &hinted.contour_end_indices).segments());
+        }
+        let autofit = !synthesized && matches!(options.hinting, Hinting::AutoVertical | Hinting::NativeIfPresent);
+        let fitted = autofit.then(|| self.autofit_glyph(index, px_size)).flatten();
+        if let Some(fitted) = fitted {
+            return Some(Outline::from_points(&fitted.points, &fitted.on_curve, &fitted.contour_end_indices).segments());
+        }
+        let outline = self.drawn_outline(index)?;
+        Some(FontUnitsToPixels::scaling(self.scale_for_px(px_size)).apply_segments(&outline.segments()))
+    }
+
+    /// Scaled from `Font::flattened_glyph` when the outline is neither hinted nor autofitted.
+    fn flattened_outline(
+        &self,
+        index: u16,
+        px_size: f32,
+        options: &RasterOptions,
+        tolerance_px: f32,
+    ) -> Option<Vec<Contour>> {
+        if options.hinting != Hinting::None {
+            return None;
+        }
+        let scale = self.scale_for_px(px_size);
+        let contours = self.flattened_glyph(index, tolerance_px / scale)?;
+        let scaled = |contour: &Contour| Contour {
+            points: contour.points.iter().map(|(x, y)| (x * scale, y * scale)).collect(),
+        };
+        Some(contours.iter().map(scaled).collect())
+    }
+
+    fn bitmap(&self, index: u16, ppem: u16) -> Option<GlyphBitmap> {
+        self.embedded_bitmap(index, ppem)
+    }
+
+    fn bitmap_advance(&self, index: u16, ppem: u16) -> Option<u16> {
+        self.embedded_bitmap_advance(index, ppem).map(u16::from)
+    }
+
+    fn hinted_advance(&self, index: u16, ppem: u16) -> Option<f32> {
+        Some(self.hinted_glyph(index, ppem)?.advance)
+    }
+
+    fn has_instructions(&self, index: u16) -> bool {
+        self.has_instructions(index)
+    }
+
+    fn kerning(&self, first: u16, second: u16) -> i16 {
+        Font::kerning(self, first, second)
+    }
+
+    fn tracking(&self, px_size: f32) -> f32 {
+        Font::tracking(self, px_size, 0.)
+    }
+
+    fn caret_slant(&self) -> (f32, f32) {
+        (Font::caret_slant(self), self.caret_offset() as f32)
+    }
+
+    fn is_mark(&self, index: u16) -> bool {
+        Font::is_mark(self, index)
+    }
+
+    fn mark_offset(&self, base: u16, mark: u16) -> Option<(i16, i16)> {
+        Font::mark_offset(self, base, mark)
+    }
+
+    fn shape_joining(&self, text: &str, glyphs: &mut Vec<(usize, u16)>, trace: &mut ShapingTrace) {
+        Font::shape_joining(self, text, glyphs, trace)
+    }
+
+    fn shape_emoji(&self, glyphs: &mut Vec<(usize, u16)>, trace: &mut ShapingTrace) {
+        Font::shape_emoji(self, glyphs, trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::wrap_paragraph;
+    use crate::raster::rasterize;
+    use crate::render::layout_line;
+
+    /// A font of boxes kept in memory: a glyph per lowercase letter and one for space, 1000
+    /// units per em. Letters are 500 units wide, "m" and "w" 1000, and "av" is kerned.
+    struct BoxFont;
+
+    impl BoxFont {
+        const CHARS: &'static str = " abcdefghijklmnopqrstuvwxyz";
+    }
+
+    impl GlyphSource for BoxFont {
+        fn units_per_em(&self) -> u16 {
+            1000
+        }
+
+        fn glyph_index(&self, c: char) -> Option<u16> {
+            BoxFont::CHARS.find(c).map(|i| i as u16 + 1)
+        }
+
+        fn advance(&self, index: u16) -> u16 {
+            match BoxFont::CHARS.as_bytes().get(index.wrapping_sub(1) as usize) {
+                Some(b' ') => 250,
+                Some(b'm' | b'w') => 1000,
+                Some(_) => 500,
+                None => 600,
+            }
+        }
+
+        fn outline(&self, index: u16) -> Option<Vec<Vec<Segment>>> {
+            let width = match index {
+                1 => return Some(Vec::new()),
+                _ => self.advance(index) as f32 - 100.,
+            };
+            let corners = [(50., 0.), (50., 700.), (50. + width, 700.), (50. + width, 0.), (50., 0.)];
+            Some(vec![corners.windows(2).map(|pair| Segment::Line(pair[0], pair[1])).collect()])
+        }
+
+        fn kerning(&self, first: u16, second: u16) -> i16 {
+            match (self.glyph_index('a'), self.glyph_index('v')) {
+                (Some(a), Some(v)) if (first, second) == (a, v) => -100,
+                _ => 0,
+            }
+        }
+    }
+
+    #[test]
+    fn layout_line_places_glyphs_by_their_advances_and_kerning() {
+        // 1000 units per em at 10px, 100 units a pixel
+        let (glyphs, end_x) = layout_line(&BoxFont, "mav b", (5., 20.), 10., None);
+        let positions: Vec<(f32, f32)> = glyphs.iter().map(|glyph| glyph.position).collect();
+        assert_eq!(positions, [(5., 20.), (15., 20.), (19., 20.), (24., 20.), (26.5, 20.)]);
+        assert_eq!(end_x, 31.5);
+        let indices: Vec<u16> = glyphs.iter().map(|glyph| glyph.index).collect();
+        assert_eq!(indices, [14, 2, 23, 1, 3]);
+        let clusters: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+        assert_eq!(clusters, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn layout_line_gives_unmapped_characters_the_notdef_glyph() {
+        let (glyphs, end_x) = layout_line(&BoxFont, "aZ", (0., 0.), 10., None);
+        assert_eq!(glyphs[1].index, 0);
+        assert_eq!(end_x, 5. + 6.);
+    }
+
+    #[test]
+    fn wrap_paragraph_breaks_between_words() {
+        // "ab" and "cd" are 10px, the space 2.5px
+        let lines = wrap_paragraph(&BoxFont, "ab cd ab", 10., 25.);
+        let ranges: Vec<_> = lines.iter().map(|line| line.range.clone()).collect();
+        assert_eq!(ranges, [0..6, 6..8]);
+        assert_eq!(lines[0].width, 22.5);
+        assert_eq!(lines[1].width, 10.);
+        assert_eq!(wrap_paragraph(&BoxFont, "ab cd ab", 10., 100.).len(), 1);
+    }
+
+    #[test]
+    fn box_glyphs_rasterize_to_their_outline() {
+        let bitmap = rasterize(&BoxFont, BoxFont.glyph_index('a').unwrap(), 10., &RasterOptions::default()).unwrap();
+        // 400 by 700 units from 50 units right of the origin
+        assert_eq!((bitmap.width, bitmap.height), (5, 7));
+        assert!(bitmap.data.iter().filter(|coverage| **coverage == 255).count() >= 3 * 7);
+    }
+}
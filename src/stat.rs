@@ -0,0 +1,100 @@
+use anyhow::Result;
+
+use crate::reader::FontReader;
+
+/// Axis value flag: the name can be left out when building a style name ("Regular").
+const ELIDABLE_AXIS_VALUE_NAME: u16 = 0x2;
+
+#[derive(Debug, Clone)]
+pub struct DesignAxis {
+    pub tag: String,
+    pub name_id: u16,
+    /// Position of the axis when composing style names
+    pub ordering: u16,
+}
+
+/// A named position (or range, for format 2) on one design axis. Format 4 values that combine
+/// several axes are skipped.
+#[derive(Debug, Clone)]
+pub struct AxisValue {
+    pub axis_index: u16,
+    pub name_id: u16,
+    pub value: f32,
+    pub range: (f32, f32),
+    pub elidable: bool,
+}
+
+/// The style attributes table, which names the styles along each design axis.
+#[derive(Debug, Clone, Default)]
+pub struct Stat {
+    pub design_axes: Vec<DesignAxis>,
+    pub axis_values: Vec<AxisValue>,
+    pub elided_fallback_name_id: Option<u16>,
+}
+
+impl Stat {
+    pub fn from_reader(reader: &mut FontReader, stat_offset: u32) -> Result<Stat> {
+        let start = stat_offset as u64;
+        reader.seek(start + 2)?; // skip majorVersion
+        let minor_version = reader.read_u16()?;
+        let design_axis_size = reader.read_u16()? as u64;
+        let design_axis_count = reader.read_u16()?;
+        let design_axes_offset = reader.read_u32()? as u64;
+        let axis_value_count = reader.read_u16()?;
+        let axis_value_offsets = start + reader.read_u32()? as u64;
+        let elided_fallback_name_id = if minor_version >= 1 { Some(reader.read_u16()?) } else { None };
+
+        let mut design_axes = Vec::with_capacity(design_axis_count as usize);
+        for i in 0..design_axis_count as u64 {
+            reader.seek(start + design_axes_offset + i * design_axis_size)?;
+            let mut tag = [0u8; 4];
+            reader.read_exact(&mut tag)?;
+            design_axes.push(DesignAxis {
+                tag: String::from_utf8_lossy(&tag).into_owned(),
+                name_id: reader.read_u16()?,
+                ordering: reader.read_u16()?,
+            });
+        }
+
+        let mut axis_values = Vec::new();
+        for i in 0..axis_value_count as u64 {
+            reader.seek(axis_value_offsets + i * 2)?;
+            let offset = reader.read_u16()? as u64;
+            reader.seek(axis_value_offsets + offset)?;
+            let format = reader.read_u16()?;
+            if !(1..=3).contains(&format) {
+                continue;
+            }
+            let axis_index = reader.read_u16()?;
+            let flags = reader.read_u16()?;
+            let name_id = reader.read_u16()?;
+            let value = reader.read_fixed()?.to_f32();
+            let range = match format {
+                2 => (reader.read_fixed()?.to_f32(), reader.read_fixed()?.to_f32()),
+                _ => (value, value),
+            };
+            axis_values.push(AxisValue {
+                axis_index,
+                name_id,
+                value,
+                range,
+                elidable: flags & ELIDABLE_AXIS_VALUE_NAME != 0,
+            });
+        }
+
+        Ok(Stat {
+            design_axes,
+            axis_values,
+            elided_fallback_name_id,
+        })
+    }
+
+    /// The axis value naming `value` on the design axis tagged `tag`.
+    pub fn axis_value(&self, tag: &str, value: f32) -> Option<&AxisValue> {
+        let axis_index = self.design_axes.iter().position(|axis| axis.tag == tag)? as u16;
+        self.axis_values
+            .iter()
+            .filter(|axis_value| axis_value.axis_index == axis_index)
+            .find(|axis_value| value >= axis_value.range.0 && value <= axis_value.range.1)
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::glyph::GlyphData;
+
+/// Per glyph parse times go in power of two buckets of nanoseconds, bucket `i` holding the
+/// times from 2^i up to 2^(i + 1) ns (the last one everything longer, from 2 seconds).
+const TIME_BUCKETS: usize = 32;
+
+/// The counters a font keeps while it's parsed and used, atomic adds only so they stay on.
+#[derive(Debug, Default)]
+pub(crate) struct StatsCollector {
+    table_parse_times: Vec<(String, Duration)>,
+    glyphs_parsed: AtomicU64,
+    glyph_parse_nanos: AtomicU64,
+    glyph_parse_buckets: [AtomicU64; TIME_BUCKETS],
+    glyph_bytes: AtomicU64,
+    glyph_cache_hits: AtomicU64,
+    glyph_cache_misses: AtomicU64,
+    hinter_cache_hits: AtomicU64,
+    hinter_cache_misses: AtomicU64,
+    flatten_cache_hits: AtomicU64,
+    flatten_cache_misses: AtomicU64,
+}
+
+impl StatsCollector {
+    pub(crate) fn new(table_parse_times: Vec<(String, Duration)>) -> StatsCollector {
+        StatsCollector {
+            table_parse_times,
+            ..StatsCollector::default()
+        }
+    }
+
+    pub(crate) fn record_glyph(&self, elapsed: Duration, glyph: &GlyphData) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        let bucket = (63 - nanos.max(1).leading_zeros() as usize).min(TIME_BUCKETS - 1);
+        self.glyphs_parsed.fetch_add(1, Ordering::Relaxed);
+        self.glyph_parse_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.glyph_parse_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.glyph_bytes.fetch_add(glyph.heap_size() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn glyph_cache(&self, hit: bool) {
+        let counter = if hit { &self.glyph_cache_hits } else { &self.glyph_cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn hinter_cache(&self, hit: bool) {
+        let counter = if hit { &self.hinter_cache_hits } else { &self.hinter_cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn flatten_cache(&self, hit: bool) {
+        let counter = if hit { &self.flatten_cache_hits } else { &self.flatten_cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The upper bound of the bucket the `percentile` (0 to 1) of the parse times falls in.
+    fn glyph_parse_percentile(&self, buckets: &[u64], percentile: f64) -> Duration {
+        let total: u64 = buckets.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((total as f64 * percentile).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, count) in buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_nanos(1 << (i + 1));
+            }
+        }
+        Duration::from_nanos(1 << TIME_BUCKETS)
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let buckets: Vec<u64> = self.glyph_parse_buckets.iter().map(load).collect();
+        Stats {
+            table_parse_times: self.table_parse_times.clone(),
+            glyphs_parsed: load(&self.glyphs_parsed),
+            glyph_parse_time: Duration::from_nanos(load(&self.glyph_parse_nanos)),
+            glyph_parse_p50: self.glyph_parse_percentile(&buckets, 0.5),
+            glyph_parse_p90: self.glyph_parse_percentile(&buckets, 0.9),
+            glyph_parse_p99: self.glyph_parse_percentile(&buckets, 0.99),
+            glyph_bytes: load(&self.glyph_bytes),
+            glyph_cache_hits: load(&self.glyph_cache_hits),
+            glyph_cache_misses: load(&self.glyph_cache_misses),
+            hinter_cache_hits: load(&self.hinter_cache_hits),
+            hinter_cache_misses: load(&self.hinter_cache_misses),
+            flatten_cache_hits: load(&self.flatten_cache_hits),
+            flatten_cache_misses: load(&self.flatten_cache_misses),
+        }
+    }
+}
+
+/// Where a font spent its time and memory so far, see `Font::stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// How long each table the font has took to parse when it was loaded, in the order they were
+    /// parsed
+    pub table_parse_times: Vec<(String, Duration)>,
+    /// Glyphs parsed from glyf, counting again those parsed after a variation change
+    pub glyphs_parsed: u64,
+    pub glyph_parse_time: Duration,
+    /// Percentiles of the time to parse one glyph, rounded up to a power of two nanoseconds
+    pub glyph_parse_p50: Duration,
+    pub glyph_parse_p90: Duration,
+    pub glyph_parse_p99: Duration,
+    /// Heap memory held by the parsed glyphs
+    pub glyph_bytes: u64,
+    pub glyph_cache_hits: u64,
+    pub glyph_cache_misses: u64,
+    pub hinter_cache_hits: u64,
+    pub hinter_cache_misses: u64,
+    /// Lookups of `Font::flattened_glyph`, a miss being one flattening
+    pub flatten_cache_hits: u64,
+    pub flatten_cache_misses: u64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Table parse times:")?;
+        for (tag, elapsed) in &self.table_parse_times {
+            writeln!(f, "  {tag}: {elapsed:?}")?;
+        }
+        writeln!(
+            f,
+            "Glyphs parsed: {} in {:?} (p50 {:?}, p90 {:?}, p99 {:?}), {} bytes",
+            self.glyphs_parsed,
+            self.glyph_parse_time,
+            self.glyph_parse_p50,
+            self.glyph_parse_p90,
+            self.glyph_parse_p99,
+            self.glyph_bytes
+        )?;
+        writeln!(f, "Glyph cache: {} hits, {} misses", self.glyph_cache_hits, self.glyph_cache_misses)?;
+        writeln!(f, "Hinter cache: {} hits, {} misses", self.hinter_cache_hits, self.hinter_cache_misses)?;
+        write!(f, "Flatten cache: {} hits, {} misses", self.flatten_cache_hits, self.flatten_cache_misses)
+    }
+}
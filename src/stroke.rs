@@ -0,0 +1,224 @@
+use std::f32::consts::PI;
+
+use crate::font::Font;
+use crate::raster::{FillRule, GlyphBitmap, Rasterizer, RenderMode, FLATTEN_TOLERANCE_PX};
+use crate::transform::FontUnitsToPixels;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrokeJoin {
+    #[default]
+    Miter,
+    Round,
+}
+
+/// Glyph contours are always closed so caps only show up on degenerate, open looking contours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrokeCap {
+    #[default]
+    Butt,
+    Round,
+}
+
+/// How outlines are stroked. Made with `new` (miter joins up to a limit of 4, butt caps) and
+/// the `with_*` methods, fields can be added without breaking callers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct StrokeOptions {
+    /// Stroke width in pixels, centered on the outline
+    pub width: f32,
+    pub join: StrokeJoin,
+    pub cap: StrokeCap,
+    /// Miter joins longer than `miter_limit * width / 2` fall back to a bevel
+    pub miter_limit: f32,
+}
+
+impl StrokeOptions {
+    pub fn new(width: f32) -> StrokeOptions {
+        StrokeOptions {
+            width,
+            join: StrokeJoin::Miter,
+            cap: StrokeCap::Butt,
+            miter_limit: 4.,
+        }
+    }
+
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_join(mut self, join: StrokeJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: StrokeCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    pub fn to_raqote(&self) -> raqote::StrokeStyle {
+        raqote::StrokeStyle {
+            width: self.width,
+            cap: match self.cap {
+                StrokeCap::Butt => raqote::LineCap::Butt,
+                StrokeCap::Round => raqote::LineCap::Round,
+            },
+            join: match self.join {
+                StrokeJoin::Miter => raqote::LineJoin::Miter,
+                StrokeJoin::Round => raqote::LineJoin::Round,
+            },
+            miter_limit: self.miter_limit,
+            ..raqote::StrokeStyle::default()
+        }
+    }
+}
+
+/// Draw a closed polygon with a consistent (positive) orientation, so that overlapping pieces
+/// accumulate instead of cancelling out under the non-zero rule.
+fn draw_polygon(rasterizer: &mut Rasterizer, points: &[(f32, f32)]) {
+    let area: f32 = (0..points.len())
+        .map(|i| {
+            let (p0, p1) = (points[i], points[(i + 1) % points.len()]);
+            p0.0 * p1.1 - p1.0 * p0.1
+        })
+        .sum();
+    let n = points.len();
+    for i in 0..n {
+        let (p0, p1) = (points[i], points[(i + 1) % n]);
+        if area >= 0. {
+            rasterizer.draw_line(p0, p1);
+        } else {
+            rasterizer.draw_line(p1, p0);
+        }
+    }
+}
+
+fn circle_points(center: (f32, f32), radius: f32) -> Vec<(f32, f32)> {
+    // enough segments for the chord error to stay under the flattening tolerance
+    let steps = ((PI / (1. - FLATTEN_TOLERANCE_PX / radius.max(FLATTEN_TOLERANCE_PX * 2.)).acos()).ceil() as usize).clamp(8, 64);
+    (0..steps)
+        .map(|i| {
+            let angle = i as f32 / steps as f32 * 2. * PI;
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Stroke closed polylines (in pixel space) into the rasterizer. The stroke is the union of a
+/// rectangle around every edge plus a join piece at every vertex, which makes up the ring between
+/// the outline offset outwards and inwards by half the width.
+pub fn stroke_polylines(rasterizer: &mut Rasterizer, polylines: &[Vec<(f32, f32)>], options: &StrokeOptions) {
+    let half_width = options.width / 2.;
+    if half_width <= 0. {
+        return;
+    }
+    for points in polylines {
+        // closed polylines repeat their first point at the end
+        let mut vertices: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+        for p in points {
+            if vertices.last().is_none_or(|last| (last.0 - p.0).hypot(last.1 - p.1) > 1e-4) {
+                vertices.push(*p);
+            }
+        }
+        if vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        let n = vertices.len();
+        if n < 2 {
+            if let (Some(p), StrokeCap::Round) = (vertices.first(), options.cap) {
+                draw_polygon(rasterizer, &circle_points(*p, half_width));
+            }
+            continue;
+        }
+
+        let normal = |a: (f32, f32), b: (f32, f32)| {
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let len = dx.hypot(dy);
+            ((-dy / len * half_width, dx / len * half_width), (dx / len, dy / len))
+        };
+        for i in 0..n {
+            let (prev, v, next) = (vertices[(i + n - 1) % n], vertices[i], vertices[(i + 1) % n]);
+            let (n1, d1) = normal(v, next);
+            draw_polygon(
+                rasterizer,
+                &[(v.0 + n1.0, v.1 + n1.1), (next.0 + n1.0, next.1 + n1.1), (next.0 - n1.0, next.1 - n1.1), (v.0 - n1.0, v.1 - n1.1)],
+            );
+
+            let (n0, d0) = normal(prev, v);
+            match options.join {
+                StrokeJoin::Round => draw_polygon(rasterizer, &circle_points(v, half_width)),
+                StrokeJoin::Miter => {
+                    let turn = d0.0 * d1.1 - d0.1 * d1.0;
+                    if turn.abs() < 1e-6 {
+                        continue;
+                    }
+                    // the join goes on the outside of the turn
+                    let side = if turn > 0. { -1. } else { 1. };
+                    let a = (v.0 + side * n0.0, v.1 + side * n0.1);
+                    let b = (v.0 + side * n1.0, v.1 + side * n1.1);
+                    let cos_angle = (d0.0 * d1.0 + d0.1 * d1.1).clamp(-1., 1.);
+                    let miter_length = 1. / ((1. + cos_angle) / 2.).sqrt().max(1e-6);
+                    if miter_length <= options.miter_limit {
+                        // the miter tip sits along the bisector of the two offset normals
+                        let bisector = (a.0 + b.0 - 2. * v.0, a.1 + b.1 - 2. * v.1);
+                        let len = bisector.0.hypot(bisector.1);
+                        let tip = (v.0 + bisector.0 / len * half_width * miter_length, v.1 + bisector.1 / len * half_width * miter_length);
+                        draw_polygon(rasterizer, &[v, a, tip, b]);
+                    } else {
+                        draw_polygon(rasterizer, &[v, a, b]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Font {
+    /// Headless outline-only rendering of a glyph: the coverage of the stroke ring around its contours.
+    pub fn rasterize_glyph_stroked(&self, index: u16, px_size: f32, options: &StrokeOptions) -> Option<GlyphBitmap> {
+        let outline = self.drawn_outline(index)?;
+        let scale = self.scale_for_px(px_size);
+        let contours = self.flattened_glyph(index, FLATTEN_TOLERANCE_PX / scale)?;
+        if contours.is_empty() {
+            return Some(GlyphBitmap::default());
+        }
+
+        // the stroke (and miters) can stick out past the outline's bounding box
+        let padding = options.width / 2. * options.miter_limit.max(1.);
+        let (x_min, y_min, x_max, y_max) = outline.tight_bbox();
+        let (x_min, y_min, x_max, y_max) = (x_min * scale, y_min * scale, x_max * scale, y_max * scale);
+        let left = (x_min - padding).floor() as i32;
+        let right = (x_max + padding).ceil() as i32;
+        let top = (y_max + padding).ceil() as i32;
+        let bottom = (y_min - padding).floor() as i32;
+        let (width, height) = ((right - left) as usize, (top - bottom) as usize);
+
+        let to_bitmap = FontUnitsToPixels::with_scale(scale, (-left as f32, top as f32));
+        let polylines: Vec<Vec<(f32, f32)>> = contours
+            .iter()
+            .map(|contour| {
+                contour
+                    .points
+                    .iter()
+                    .map(|p| to_bitmap.apply(*p))
+                    .collect()
+            })
+            .collect();
+        let mut rasterizer = Rasterizer::new(width, height);
+        stroke_polylines(&mut rasterizer, &polylines, options);
+        Some(GlyphBitmap {
+            width,
+            height,
+            left,
+            top,
+            mode: RenderMode::Grayscale,
+            data: rasterizer.coverage(FillRule::NonZero),
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use crate::font::Font;
+use crate::name::{FAMILY_NAME, TYPOGRAPHIC_FAMILY_NAME};
+use crate::os2::Os2;
+
+const FS_SELECTION_ITALIC: u16 = 1 << 0;
+const FS_SELECTION_BOLD: u16 = 1 << 5;
+const FS_SELECTION_OBLIQUE: u16 = 1 << 9;
+const MAC_STYLE_BOLD: u16 = 1 << 0;
+const MAC_STYLE_ITALIC: u16 = 1 << 1;
+const MAC_STYLE_CONDENSED: u16 = 1 << 5;
+const MAC_STYLE_EXTENDED: u16 = 1 << 6;
+
+const REGULAR_WEIGHT: u16 = 400;
+const BOLD_WEIGHT: u16 = 700;
+const NORMAL_WIDTH: u16 = 5;
+
+/// Where a face sits in its family. OS/2 wins over head.macStyle when the two disagree, as it
+/// does on Windows: macStyle is only read for fonts without an OS/2 table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontStyle {
+    /// 1 to 1000, 400 regular and 700 bold: usWeightClass (the 1 to 9 some old fonts use
+    /// scaled by 100), else 700 for fonts flagged bold and 400 for the others
+    pub weight: u16,
+    /// fsSelection italic or oblique, else macStyle italic
+    pub is_italic: bool,
+    /// Whether the font says it's the bold member of a regular, italic, bold and bold italic
+    /// family: fsSelection bold, else macStyle bold
+    pub is_bold_flagged: bool,
+    /// 1 ultra-condensed to 9 ultra-expanded, 5 normal: usWidthClass, else 3 or 7 for macStyle
+    /// condensed or extended
+    pub width: u16,
+}
+
+impl FontStyle {
+    /// The style from a font's OS/2 table, when it has one, and head.macStyle.
+    pub fn from_tables(os2: Option<&Os2>, mac_style: u16) -> FontStyle {
+        let Some(os2) = os2 else {
+            let is_bold_flagged = mac_style & MAC_STYLE_BOLD != 0;
+            let width = match (mac_style & MAC_STYLE_CONDENSED != 0, mac_style & MAC_STYLE_EXTENDED != 0) {
+                (true, false) => 3,
+                (false, true) => 7,
+                _ => NORMAL_WIDTH,
+            };
+            return FontStyle {
+                weight: if is_bold_flagged { BOLD_WEIGHT } else { REGULAR_WEIGHT },
+                is_italic: mac_style & MAC_STYLE_ITALIC != 0,
+                is_bold_flagged,
+                width,
+            };
+        };
+        let is_bold_flagged = os2.fs_selection & FS_SELECTION_BOLD != 0;
+        let weight = match os2.weight_class {
+            0 if is_bold_flagged => BOLD_WEIGHT,
+            0 => REGULAR_WEIGHT,
+            1..=9 => os2.weight_class * 100,
+            weight => weight.min(1000),
+        };
+        FontStyle {
+            weight,
+            is_italic: os2.fs_selection & (FS_SELECTION_ITALIC | FS_SELECTION_OBLIQUE) != 0,
+            is_bold_flagged,
+            width: match os2.width_class {
+                1..=9 => os2.width_class,
+                _ => NORMAL_WIDTH,
+            },
+        }
+    }
+
+}
+
+/// The typographic family name, else the legacy one (which splits families of more than four
+/// styles), from the name table strings by id.
+pub(crate) fn family_name<'a>(name: impl Fn(u16) -> Option<&'a str>) -> Option<&'a str> {
+    name(TYPOGRAPHIC_FAMILY_NAME).or_else(|| name(FAMILY_NAME))
+}
+
+impl Font {
+    /// The weight, slope and width the font's OS/2 and head tables declare.
+    pub fn style(&self) -> FontStyle {
+        FontStyle::from_tables(self.os2(), self.mac_style())
+    }
+
+    /// The family the font belongs to, see `FontCollectionIndex`.
+    pub fn family_name(&self) -> Option<&str> {
+        family_name(|name_id| self.name(name_id))
+    }
+}
+
+/// How well a face of `available` weight matches a request for `desired`, lower is better,
+/// following the CSS font matching order: from 400 to 500 the weights up to 500 come first,
+/// then the lighter ones, then the heavier; below 400 the lighter ones first, above 500 the
+/// heavier ones first, the nearest first in each group.
+fn weight_rank(desired: u16, available: u16) -> (u8, u16) {
+    let distance = desired.abs_diff(available);
+    match desired {
+        400..=500 if (desired..=500).contains(&available) => (0, distance),
+        400..=500 if available < desired => (1, distance),
+        400..=500 => (2, distance),
+        ..400 => (u8::from(available > desired), distance),
+        _ => (u8::from(available < desired), distance),
+    }
+}
+
+/// How well a face of `style` matches a request for `weight` and `italic`, lower is better:
+/// faces of the asked slope win (an upright face stands in for a missing italic and the other
+/// way round), then the nearest weight by `weight_rank`, then the width nearest normal.
+pub(crate) fn match_rank(style: &FontStyle, weight: u16, italic: bool) -> impl Ord {
+    (style.is_italic != italic, weight_rank(weight, style.weight), style.width.abs_diff(NORMAL_WIDTH))
+}
+
+/// Faces of several loaded fonts by family and style, to pick "the bold italic of Noto Sans"
+/// out of the files an application loaded. Family names compare ignoring case.
+#[derive(Debug, Clone)]
+pub struct FontCollectionIndex<'a> {
+    /// Position in the list the index was made from, family name, style and font of each face
+    faces: Vec<(usize, String, FontStyle, &'a Font)>,
+}
+
+impl<'a> FontCollectionIndex<'a> {
+    /// Index `fonts`, skipping those without a family name.
+    pub fn new(fonts: &[&'a Font]) -> FontCollectionIndex<'a> {
+        let faces = fonts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, font)| Some((i, font.family_name()?.to_string(), font.style(), *font)))
+            .collect();
+        FontCollectionIndex { faces }
+    }
+
+    /// The family names, each once, in the order the fonts were given.
+    pub fn families(&self) -> Vec<&str> {
+        let mut families: Vec<&str> = Vec::new();
+        for (_, family, _, _) in &self.faces {
+            if !families.iter().any(|known| known.eq_ignore_ascii_case(family)) {
+                families.push(family);
+            }
+        }
+        families
+    }
+
+    /// The face of `family` best matching `weight` and `italic`: faces of the asked slope win
+    /// (an upright face stands in for a missing italic and the other way round), then the
+    /// nearest weight in the CSS font matching order, then the width nearest normal. The first
+    /// of equal faces wins.
+    pub fn find(&self, family: &str, weight: u16, italic: bool) -> Option<&'a Font> {
+        self.best_face(family, weight, italic).map(|(_, _, _, font)| *font)
+    }
+
+    /// `find` as the position of the face in the list the index was made from, for
+    /// `Span::font_index`.
+    pub fn find_index(&self, family: &str, weight: u16, italic: bool) -> Option<usize> {
+        self.best_face(family, weight, italic).map(|(i, _, _, _)| *i)
+    }
+
+    fn best_face(&self, family: &str, weight: u16, italic: bool) -> Option<&(usize, String, FontStyle, &'a Font)> {
+        self.faces
+            .iter()
+            .filter(|(_, face_family, _, _)| face_family.eq_ignore_ascii_case(family))
+            .min_by_key(|(_, _, style, _)| match_rank(style, weight, italic))
+    }
+}
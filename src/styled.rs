@@ -0,0 +1,264 @@
+use raqote::{DrawOptions, DrawTarget, PathBuilder, SolidSource, Source};
+use std::ops::Range;
+
+use crate::base::ROMAN_BASELINE;
+use crate::font::Font;
+use crate::itemize::{itemize, Script};
+use crate::layout::{char_advance, wrap_with_advances};
+use crate::render::{layout_line, PositionedGlyph};
+use crate::text_svg::{write_svg, SvgGlyph};
+
+/// The style of a byte range of a `StyledText`. `font_index` picks the font from the list the
+/// text is laid out with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub range: Range<usize>,
+    pub color: SolidSource,
+    pub px_size: f32,
+    pub underline: bool,
+    pub font_index: usize,
+}
+
+/// A paragraph of rich text: the whole string and the spans styling it, laid out together so
+/// that lines wrap across spans. The first span covers the whole text, later spans win over
+/// earlier ones where they overlap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledText {
+    pub text: String,
+    pub spans: Vec<Span>,
+}
+
+/// A glyph of a styled line with the span it takes its style from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StyledGlyph {
+    /// Placed with the pen starting at 0 and on the line's baseline at y 0
+    pub glyph: PositionedGlyph,
+    pub span: usize,
+}
+
+/// One wrapped line of a `StyledText`: its byte range and width like a `LineBox`, and the
+/// largest ascent and descent (in pixels, both positive) of the spans on it. Lines follow each
+/// other `height` apart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledLine {
+    pub range: Range<usize>,
+    pub width: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    pub height: f32,
+    pub glyphs: Vec<StyledGlyph>,
+}
+
+impl StyledText {
+    /// Text in a single style, font 0 without underline.
+    pub fn new(text: &str, color: SolidSource, px_size: f32) -> StyledText {
+        StyledText {
+            text: text.to_string(),
+            spans: vec![Span {
+                range: 0..text.len(),
+                color,
+                px_size,
+                underline: false,
+                font_index: 0,
+            }],
+        }
+    }
+
+    pub fn push_span(&mut self, span: Span) -> &mut Self {
+        self.spans.push(span);
+        self
+    }
+
+    /// The index of the span styling the byte at `offset`: the last one covering it, or the
+    /// first span when none does.
+    pub fn span_at(&self, offset: usize) -> usize {
+        self.spans.iter().rposition(|span| span.range.contains(&offset)).unwrap_or(0)
+    }
+
+    /// The text cut where the style changes, each range with the span styling it.
+    fn style_runs(&self) -> Vec<(Range<usize>, usize)> {
+        let mut boundaries: Vec<usize> = self
+            .spans
+            .iter()
+            .flat_map(|span| [span.range.start, span.range.end])
+            .chain([0, self.text.len()])
+            .filter(|at| *at <= self.text.len() && self.text.is_char_boundary(*at))
+            .collect();
+        boundaries.sort();
+        boundaries.dedup();
+        boundaries.windows(2).map(|pair| (pair[0]..pair[1], self.span_at(pair[0]))).collect()
+    }
+}
+
+/// The script of the first letters of `text` that have one, `Common` when none does.
+fn leading_script(text: &str) -> Script {
+    itemize(text)
+        .into_iter()
+        .map(|run| run.script)
+        .find(|script| !matches!(script, Script::Common | Script::Inherited))
+        .unwrap_or(Script::Common)
+}
+
+/// The font of a span, the first one when its index is past the list.
+fn span_font<'a>(fonts: &[&'a Font], span: &Span) -> &'a Font {
+    fonts.get(span.font_index).unwrap_or(&fonts[0])
+}
+
+/// Wrap a styled paragraph into lines at most `max_width` wide (like `Font::wrap_paragraph`,
+/// each character measured in the font and size of its span) and lay each line out. Colors and
+/// underlines don't change the layout: a line is shaped in pieces of the same font and size, so
+/// kerning goes across a color change. Pieces in another font or size than the first span are
+/// moved up or down so that they sit on the paragraph's baseline (the one BASE gives for the
+/// script the paragraph starts in) where both fonts have a BASE table. Empty when there are no
+/// fonts or spans.
+pub fn layout_styled(fonts: &[&Font], text: &StyledText, max_width: f32) -> Vec<StyledLine> {
+    if fonts.is_empty() || text.spans.is_empty() {
+        return Vec::new();
+    }
+    let runs = text.style_runs();
+    let span_of = |offset: usize| {
+        let run = runs.partition_point(|(range, _)| range.end <= offset).min(runs.len().saturating_sub(1));
+        runs.get(run).map_or(0, |(_, span)| *span)
+    };
+    let advance = |offset: usize, c: char| {
+        let span = &text.spans[span_of(offset)];
+        let font = span_font(fonts, span);
+        char_advance(font, c, font.scale_for_px(span.px_size))
+    };
+    let same_shaping = |a: &Span, b: &Span| a.font_index == b.font_index && a.px_size == b.px_size;
+    let (dominant, dominant_script) = (&text.spans[0], leading_script(&text.text));
+    let dominant_font = span_font(fonts, dominant);
+    let baseline = dominant_font.default_baseline(dominant_script.tag()).unwrap_or(ROMAN_BASELINE);
+    // how far down a piece in `span`'s style goes to have its baseline on the paragraph's
+    let baseline_shift = |span: &Span, script: Script| match same_shaping(span, dominant) {
+        true => 0.,
+        false => span_font(fonts, span).baseline_shift(
+            script,
+            span.px_size,
+            dominant_font,
+            dominant_script,
+            dominant.px_size,
+            baseline,
+        ),
+    };
+
+    let mut lines = Vec::new();
+    for line in wrap_with_advances(&text.text, max_width, advance) {
+        // the runs of the line, merged where only the color or underline changes
+        let mut pieces: Vec<(Range<usize>, usize)> = Vec::new();
+        for (range, span) in &runs {
+            let range = range.start.max(line.range.start)..range.end.min(line.range.end);
+            if range.is_empty() {
+                continue;
+            }
+            match pieces.last_mut() {
+                Some((piece, first)) if same_shaping(&text.spans[*first], &text.spans[*span]) => piece.end = range.end,
+                _ => pieces.push((range, *span)),
+            }
+        }
+        let mut glyphs = Vec::new();
+        let (mut ascent, mut descent, mut gap) = (0f32, 0f32, 0f32);
+        let mut x = 0.;
+        let piece_shift = |(range, span): &(Range<usize>, usize)| {
+            let script = match leading_script(&text.text[range.clone()]) {
+                Script::Common => dominant_script,
+                script => script,
+            };
+            baseline_shift(&text.spans[*span], script)
+        };
+        let line_spans = match pieces.is_empty() {
+            true => vec![(span_of(line.range.start), 0.)],
+            false => pieces.iter().map(|piece| (piece.1, piece_shift(piece))).collect(),
+        };
+        for (span, shift) in line_spans {
+            let span = &text.spans[span];
+            let font = span_font(fonts, span);
+            let scale = font.scale_for_px(span.px_size);
+            ascent = ascent.max(font.ascender() as f32 * scale - shift);
+            descent = descent.max(-font.descender() as f32 * scale + shift);
+            gap = gap.max(font.line_gap() as f32 * scale);
+        }
+        for piece in pieces {
+            let shift = piece_shift(&piece);
+            let (range, span) = piece;
+            let style = &text.spans[span];
+            let font = span_font(fonts, style);
+            let (positioned, end_x) = layout_line(font, &text.text[range.clone()], (x, shift), style.px_size, None);
+            glyphs.extend(positioned.into_iter().map(|glyph| {
+                let cluster = range.start + glyph.cluster;
+                StyledGlyph {
+                    glyph: PositionedGlyph { cluster, ..glyph },
+                    span: span_of(cluster),
+                }
+            }));
+            x = end_x;
+        }
+        lines.push(StyledLine {
+            range: line.range,
+            width: line.width,
+            ascent,
+            descent,
+            height: (ascent + descent + gap).ceil(),
+            glyphs,
+        });
+    }
+    lines
+}
+
+/// Lay out and draw a styled paragraph with the top of its first line at `origin`, each glyph
+/// in the color of its span and underlined spans underlined at the position and thickness post
+/// gives. Returns the height of the paragraph.
+pub fn draw_styled(dt: &mut DrawTarget, fonts: &[&Font], text: &StyledText, origin: (f32, f32), max_width: f32) -> f32 {
+    let mut y = origin.1;
+    for line in layout_styled(fonts, text, max_width) {
+        let baseline = y + line.ascent;
+        for StyledGlyph { glyph, span } in &line.glyphs {
+            let span = &text.spans[*span];
+            let font = span_font(fonts, span);
+            let position = (origin.0 + glyph.position.0, baseline + glyph.position.1);
+            if let Some(outline) = font.drawn_outline(glyph.index) {
+                let path = outline.to_path(font.scale_for_px(span.px_size), position);
+                dt.fill(&path, &Source::Solid(span.color), &DrawOptions::new());
+            }
+            if span.underline && glyph.advance > 0. {
+                let scale = font.scale_for_px(span.px_size);
+                let post = font.post();
+                let (top, thickness) = match post.underline_thickness {
+                    0 => (-0.1 * font.units_per_em() as f32, 0.05 * font.units_per_em() as f32),
+                    thickness => (post.underline_position as f32, thickness as f32),
+                };
+                let mut pb = PathBuilder::new();
+                pb.rect(position.0, baseline - top * scale, glyph.advance, (thickness * scale).max(1.));
+                dt.fill(&pb.finish(), &Source::Solid(span.color), &DrawOptions::new());
+            }
+        }
+        y += line.height;
+    }
+    y - origin.1
+}
+
+/// Lay out a styled paragraph like `draw_styled` and write it as an SVG document, see
+/// `Font::text_to_svg`. With `merge_paths` each color span is one path. Underlines aren't
+/// written.
+pub fn styled_to_svg(fonts: &[&Font], text: &StyledText, max_width: f32, merge_paths: bool) -> String {
+    let mut glyphs = Vec::new();
+    let (mut y, mut width) = (0., 0f32);
+    for line in layout_styled(fonts, text, max_width) {
+        let baseline = y + line.ascent;
+        glyphs.extend(line.glyphs.iter().map(|StyledGlyph { glyph, span }| {
+            let span = &text.spans[*span];
+            SvgGlyph {
+                font: span_font(fonts, span),
+                index: glyph.index,
+                missing: glyph.missing,
+                position: (glyph.position.0, baseline + glyph.position.1),
+                px_size: span.px_size,
+                color: span.color,
+            }
+        }));
+        width = width.max(line.width);
+        y += line.height;
+    }
+    let width = if max_width.is_finite() { max_width } else { width };
+    write_svg(&glyphs, (width, y), merge_paths)
+}
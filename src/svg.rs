@@ -0,0 +1,82 @@
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use std::borrow::Cow;
+use std::io::Read;
+use std::ops::RangeInclusive;
+
+use crate::font::Font;
+use crate::reader::FontReader;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Debug, Clone)]
+struct SvgRecord {
+    glyphs: RangeInclusive<u16>,
+    offset: u32, // from the start of the font file
+    length: u32,
+}
+
+/// The document index of the `SVG ` table, glyph ranges and where their documents are.
+#[derive(Debug, Clone, Default)]
+pub struct Svg {
+    records: Vec<SvgRecord>,
+}
+
+/// The SVG document drawing a glyph. One document can hold several glyphs, each is the element
+/// with id `glyph<index>`, in a coordinate system where the em box spans units per em with y down
+/// from the baseline.
+#[derive(Debug, Clone)]
+pub struct SvgDocument<'a> {
+    /// The SVG source, already decompressed when stored gzipped
+    pub data: Cow<'a, [u8]>,
+    /// Glyph ids the document covers
+    pub glyphs: RangeInclusive<u16>,
+}
+
+impl Svg {
+    pub fn from_reader(reader: &mut FontReader, svg_offset: u32) -> Result<Svg> {
+        reader.seek(svg_offset as u64 + 2)?; // skip version
+        let list_offset = svg_offset + reader.read_u32()?;
+        reader.seek(list_offset as u64)?;
+        let num_entries = reader.read_u16()?;
+        let mut records = Vec::with_capacity(num_entries as usize);
+        for _ in 0..num_entries {
+            let start = reader.read_u16()?;
+            let end = reader.read_u16()?;
+            let offset = list_offset + reader.read_u32()?;
+            let length = reader.read_u32()?;
+            records.push(SvgRecord {
+                glyphs: start..=end,
+                offset,
+                length,
+            });
+        }
+        Ok(Svg { records })
+    }
+}
+
+impl Font {
+    /// The SVG document for a glyph from the `SVG ` table, `None` when the font has none for it.
+    pub fn svg_glyph(&self, index: u16) -> Option<SvgDocument<'_>> {
+        // records are sorted by glyph id and don't overlap
+        let records = &self.svg()?.records;
+        let at = records.partition_point(|record| *record.glyphs.end() < index);
+        let record = records.get(at).filter(|record| record.glyphs.contains(&index))?;
+        let start = record.offset as usize;
+        let bytes = self.file_data().get(start..start + record.length as usize)?;
+        let data = if bytes.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            if let Err(err) = GzDecoder::new(bytes).read_to_end(&mut decompressed) {
+                println!("Error decompressing the SVG document of glyph {index} : {err}");
+                return None;
+            }
+            Cow::Owned(decompressed)
+        } else {
+            Cow::Borrowed(bytes)
+        };
+        Some(SvgDocument {
+            data,
+            glyphs: record.glyphs.clone(),
+        })
+    }
+}
@@ -0,0 +1,505 @@
+//! Tiny synthetic TrueType fonts, for reproducing a parser or rendering problem with exactly
+//! the glyphs, mappings and kerning pairs it takes rather than with a third-party font file.
+//! `TestFontBuilder` writes only the tables a font needs to load (head, hhea, maxp, hmtx, loca,
+//! glyf and cmap), plus name when the font is given a family, GPOS when it has kerning and
+//! OS/2 when it's given embedding permissions. Only built for tests and with the `test-fonts`
+//! feature.
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+use crate::glyph::{GlyphData, ARGS_ARE_XY_VALUES, ARG_1_AND_2_ARE_WORDS, MORE_COMPONENTS};
+use crate::outline::{Contour, Outline, OutlinePoint};
+use crate::write::FontBuilder;
+
+const HEAD_MAGIC: u32 = 0x5F0F_3CF5;
+/// Baseline at y = 0, left side bearing point at x = 0
+const HEAD_FLAGS: u16 = 0x0003;
+const PAIR_ADJUSTMENT: u16 = 2;
+/// ValueRecord with only XAdvance
+const X_ADVANCE: u16 = 0x0004;
+/// OS/2 version 0, up to usWinDescent
+const OS2_LENGTH: usize = 78;
+const FS_SELECTION_REGULAR: u16 = 0x0040;
+
+/// Which cmap subtable the font gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Unicode,
+    Symbol,
+    MacRoman,
+}
+
+#[derive(Debug, Clone)]
+enum TestGlyph {
+    Simple(GlyphData),
+    /// Components by character, with their offsets
+    Composite(Vec<(char, (i16, i16))>),
+    /// A glyf record as given
+    Raw(Vec<u8>),
+}
+
+/// Builds a font glyph by glyph: glyph 0 is an empty .notdef, the others follow in the order
+/// they're added, each mapped from its character.
+#[derive(Debug, Clone)]
+pub struct TestFontBuilder {
+    units_per_em: u16,
+    ascender: i16,
+    descender: i16,
+    family: Option<String>,
+    /// OS/2 fsType
+    fs_type: Option<u16>,
+    encoding: Encoding,
+    /// Character, glyph and advance width
+    glyphs: Vec<(char, TestGlyph, Option<u16>)>,
+    kerning: BTreeMap<(char, char), i16>,
+}
+
+impl Default for TestFontBuilder {
+    fn default() -> Self {
+        TestFontBuilder::new()
+    }
+}
+
+/// A closed rectangle, clockwise like TrueType's outer contours.
+pub fn rectangle(x_min: f32, y_min: f32, x_max: f32, y_max: f32) -> Outline {
+    let point = |x, y| OutlinePoint { x, y, on_curve: true };
+    let points = vec![point(x_min, y_min), point(x_min, y_max), point(x_max, y_max), point(x_max, y_min)];
+    Outline {
+        contours: vec![Contour { points }],
+        bbox: (x_min, y_min, x_max, y_max),
+    }
+}
+
+/// A square "o" the way broken fonts wind it: a rectangle and a hole `thickness` inside it,
+/// both clockwise, so the nonzero fill covers the hole and only the even-odd one leaves it.
+pub fn miswound_ring(x_min: f32, y_min: f32, x_max: f32, y_max: f32, thickness: f32) -> Outline {
+    let hole = rectangle(x_min + thickness, y_min + thickness, x_max - thickness, y_max - thickness);
+    let mut ring = rectangle(x_min, y_min, x_max, y_max);
+    ring.contours.extend(hole.contours);
+    ring
+}
+
+impl TestFontBuilder {
+    /// A font of 1000 units per em, ascender 800 and descender -200, with only .notdef.
+    pub fn new() -> TestFontBuilder {
+        TestFontBuilder {
+            units_per_em: 1000,
+            ascender: 800,
+            descender: -200,
+            family: None,
+            fs_type: None,
+            encoding: Encoding::Unicode,
+            glyphs: Vec::new(),
+            kerning: BTreeMap::new(),
+        }
+    }
+
+    pub fn units_per_em(&mut self, units_per_em: u16) -> &mut Self {
+        self.units_per_em = units_per_em;
+        self
+    }
+
+    pub fn vertical_metrics(&mut self, ascender: i16, descender: i16) -> &mut Self {
+        self.ascender = ascender;
+        self.descender = descender;
+        self
+    }
+
+    /// Give the font a name table naming it `family` Regular.
+    pub fn family(&mut self, family: &str) -> &mut Self {
+        self.family = Some(family.to_string());
+        self
+    }
+
+    /// Give the font an OS/2 table with the embedding permission bits `fs_type`, see
+    /// `EmbeddingPermissions`.
+    pub fn embedding_permissions(&mut self, fs_type: u16) -> &mut Self {
+        self.fs_type = Some(fs_type);
+        self
+    }
+
+    /// Write the characters' mappings as a Windows symbol font does, in a (3, 0) subtable: add
+    /// the glyphs for U+F020 to U+F0FF to make a font like Wingdings.
+    pub fn symbol_encoding(&mut self) -> &mut Self {
+        self.encoding = Encoding::Symbol;
+        self
+    }
+
+    /// Write the characters' mappings as an old Mac font does, in a MacRoman (1, 0) format 0
+    /// subtable only: characters MacRoman doesn't have and glyphs past 255 are left out.
+    pub fn mac_roman_encoding(&mut self) -> &mut Self {
+        self.encoding = Encoding::MacRoman;
+        self
+    }
+
+    /// A simple glyph of `outline` (in font units) for `c`, advancing by its right edge plus
+    /// its left side bearing, half an em for an empty outline. Adding a character again
+    /// replaces its glyph.
+    pub fn add_glyph(&mut self, c: char, outline: &Outline) -> &mut Self {
+        self.add_glyph_with_advance(c, outline, None)
+    }
+
+    /// `add_glyph` with the advance width given, `None` for the default.
+    pub fn add_glyph_with_advance(&mut self, c: char, outline: &Outline, advance: Option<u16>) -> &mut Self {
+        // a glyph that doesn't fit is left empty, to keep the builder chaining
+        let glyph = GlyphData::from_outline(outline).unwrap_or_else(|_| GlyphData::empty());
+        self.insert(c, TestGlyph::Simple(glyph), advance)
+    }
+
+    /// A composite glyph for `c` of the glyphs of `components` (added before or after it)
+    /// placed at their offsets. It advances like its first component, and without components
+    /// it's an empty glyph.
+    pub fn add_composite(&mut self, c: char, components: &[(char, (i16, i16))]) -> &mut Self {
+        let glyph = match components {
+            [] => TestGlyph::Simple(GlyphData::empty()),
+            _ => TestGlyph::Composite(components.to_vec()),
+        };
+        self.insert(c, glyph, None)
+    }
+
+    /// A glyph for `c` that is the glyf record `record` byte for byte, to exercise a flag or
+    /// header combination `add_glyph` wouldn't write. An empty record is an empty glyph.
+    pub fn add_raw_glyph(&mut self, c: char, record: &[u8], advance: u16) -> &mut Self {
+        self.insert(c, TestGlyph::Raw(record.to_vec()), Some(advance))
+    }
+
+    /// A GPOS kern pair between the glyphs of `first` and `second`, in font units.
+    pub fn add_kern(&mut self, first: char, second: char, adjustment: i16) -> &mut Self {
+        self.kerning.insert((first, second), adjustment);
+        self
+    }
+
+    fn insert(&mut self, c: char, glyph: TestGlyph, advance: Option<u16>) -> &mut Self {
+        match self.glyphs.iter_mut().find(|(known, _, _)| *known == c) {
+            Some(entry) => *entry = (c, glyph, advance),
+            None => self.glyphs.push((c, glyph, advance)),
+        }
+        self
+    }
+
+    /// The glyph index of `c` in the built font, 0 (.notdef) for characters without a glyph.
+    pub fn glyph_index(&self, c: char) -> u16 {
+        self.glyphs.iter().position(|(known, _, _)| *known == c).map_or(0, |i| i as u16 + 1)
+    }
+
+    /// Write the font file. Fails when a composite or kern pair names a character without a
+    /// glyph, or a composite contains itself.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        self.font_builder()?.build()
+    }
+
+    /// The tables `build` writes, to change or `FontBuilder::remove_table` some of them first,
+    /// for a font that is broken in one particular way.
+    pub fn font_builder(&self) -> Result<FontBuilder<'static>> {
+        let num_glyphs = u16::try_from(self.glyphs.len() + 1).map_err(|_| anyhow!("Too many glyphs"))?;
+        let index = |c: char| match self.glyph_index(c) {
+            0 => Err(anyhow!("No glyph for {c:?}")),
+            index => Ok(index),
+        };
+
+        // the records in glyph order, composites made after the glyphs they use
+        let mut records: Vec<Option<Record>> = vec![None; num_glyphs as usize];
+        records[0] = Some(Record::default());
+        while records.iter().any(Option::is_none) {
+            let mut progress = false;
+            for (i, (_, glyph, _)) in self.glyphs.iter().enumerate() {
+                if records[i + 1].is_some() {
+                    continue;
+                }
+                let record = match glyph {
+                    TestGlyph::Simple(glyph) if glyph.num_points() == 0 => Record::default(),
+                    TestGlyph::Simple(glyph) => Record {
+                        data: glyph.to_bytes(),
+                        bounds: glyph.bounds(),
+                        points: glyph.num_points(),
+                        contours: glyph.num_contours(),
+                        ..Record::default()
+                    },
+                    TestGlyph::Raw(data) => Record::raw(data),
+                    TestGlyph::Composite(components) => {
+                        let mut placed = Vec::with_capacity(components.len());
+                        for (component, offset) in components {
+                            let component = index(*component)?;
+                            match &records[component as usize] {
+                                Some(record) => placed.push((component, *offset, record)),
+                                None => break,
+                            }
+                        }
+                        if placed.len() < components.len() {
+                            continue;
+                        }
+                        Record::composite(&placed)
+                    }
+                };
+                records[i + 1] = Some(record);
+                progress = true;
+            }
+            if !progress {
+                return Err(anyhow!("A composite glyph contains itself"));
+            }
+        }
+        let records: Vec<Record> = records.into_iter().flatten().collect();
+
+        let notdef_advance = self.units_per_em / 2;
+        let mut metrics = vec![(notdef_advance, 0)];
+        for (i, Record { bounds: (x_min, ..), .. }) in records.iter().enumerate().skip(1) {
+            // composites advance like their first component, down to a glyph that isn't one
+            let mut base = i - 1;
+            while let (TestGlyph::Composite(components), None) = (&self.glyphs[base].1, self.glyphs[base].2) {
+                base = index(components[0].0)? as usize - 1;
+            }
+            let default = match &records[base + 1].bounds {
+                (x_min, _, x_max, _) if x_min == x_max => notdef_advance,
+                (x_min, _, x_max, _) => x_max.saturating_add((*x_min).max(0)).max(0) as u16,
+            };
+            metrics.push((self.glyphs[base].2.unwrap_or(default), *x_min));
+        }
+
+        let mut builder = FontBuilder::new();
+        builder
+            .add_table("head", self.head())
+            .add_table("hhea", self.hhea())
+            .add_table("maxp", maxp(num_glyphs, &records));
+        let glyphs: Vec<&[u8]> = records.iter().map(|record| record.data.as_slice()).collect();
+        builder.set_glyphs(&glyphs)?.set_horizontal_metrics(&metrics)?;
+        let mapping: Vec<(char, u16)> = self.glyphs.iter().map(|(c, _, _)| (*c, self.glyph_index(*c))).collect();
+        match self.encoding {
+            Encoding::Unicode => builder.set_cmap(&mapping),
+            Encoding::Symbol => builder.set_symbol_cmap(&mapping),
+            Encoding::MacRoman => builder.set_mac_roman_cmap(&mapping),
+        };
+        if let Some(family) = &self.family {
+            builder.add_table("name", name_table(family));
+        }
+        if let Some(fs_type) = self.fs_type {
+            builder.add_table("OS/2", self.os2(fs_type));
+        }
+        if !self.kerning.is_empty() {
+            let mut pairs = Vec::with_capacity(self.kerning.len());
+            for ((first, second), adjustment) in &self.kerning {
+                pairs.push((index(*first)?, index(*second)?, *adjustment));
+            }
+            builder.add_table("GPOS", gpos_table(&mut pairs));
+        }
+        Ok(builder)
+    }
+
+    fn head(&self) -> Vec<u8> {
+        let mut head = Vec::with_capacity(54);
+        for value in [0x0001_0000u32, 0x0001_0000, 0, HEAD_MAGIC] {
+            head.extend_from_slice(&value.to_be_bytes());
+        }
+        head.extend_from_slice(&HEAD_FLAGS.to_be_bytes());
+        head.extend_from_slice(&self.units_per_em.to_be_bytes());
+        head.extend_from_slice(&[0; 16]); // created and modified
+        // bounding box (set with the glyphs), macStyle, lowestRecPPEM, fontDirectionHint,
+        // indexToLocFormat (set with the glyphs) and glyphDataFormat
+        for value in [0u16, 0, 0, 0, 0, 8, 2, 0, 0] {
+            head.extend_from_slice(&value.to_be_bytes());
+        }
+        head
+    }
+
+    /// A regular weight and width OS/2 table with the typo and win metrics of hhea.
+    fn os2(&self, fs_type: u16) -> Vec<u8> {
+        let mut os2 = vec![0; OS2_LENGTH];
+        let mut set = |at: usize, value: u16| os2[at..at + 2].copy_from_slice(&value.to_be_bytes());
+        // usWeightClass, usWidthClass, fsType and fsSelection
+        for (at, value) in [(4, 400), (6, 5), (8, fs_type), (62, FS_SELECTION_REGULAR)] {
+            set(at, value);
+        }
+        // sTypoAscender, sTypoDescender, usWinAscent and usWinDescent
+        let (ascender, descender) = (self.ascender, self.descender);
+        for (at, value) in [(68, ascender), (70, descender), (74, ascender), (76, descender.saturating_neg())] {
+            set(at, value as u16);
+        }
+        os2
+    }
+
+    fn hhea(&self) -> Vec<u8> {
+        let mut hhea = Vec::with_capacity(36);
+        hhea.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        for value in [self.ascender, self.descender, 0] {
+            hhea.extend_from_slice(&value.to_be_bytes());
+        }
+        // advanceWidthMax, the side bearing minimums and xMaxExtent (set with the metrics), the
+        // caret slope (upright) and offset, reserved fields, metricDataFormat and
+        // numberOfHMetrics (set with the metrics)
+        for value in [0i16, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0] {
+            hhea.extend_from_slice(&value.to_be_bytes());
+        }
+        hhea
+    }
+}
+
+/// A glyf record and what maxp counts of it.
+#[derive(Debug, Clone, Default)]
+struct Record {
+    data: Vec<u8>,
+    bounds: (i16, i16, i16, i16),
+    /// Points and contours, of the simple glyphs a composite is made of for one
+    points: usize,
+    contours: usize,
+    /// Components of a composite
+    components: usize,
+    /// 0 for simple glyphs, 1 for composites of simple glyphs and so on
+    depth: u16,
+}
+
+impl Record {
+    /// A record as given, counted from its header and contour ends.
+    fn raw(data: &[u8]) -> Record {
+        let field = |at: usize| data.get(at..at + 2).map_or(0, |bytes| i16::from_be_bytes([bytes[0], bytes[1]]));
+        let contours = field(0).max(0) as usize;
+        let points = match contours {
+            0 => 0,
+            _ => field(10 + 2 * (contours - 1)) as u16 as usize + 1,
+        };
+        Record {
+            data: data.to_vec(),
+            bounds: (field(2), field(4), field(6), field(8)),
+            points,
+            contours,
+            ..Record::default()
+        }
+    }
+
+    /// A composite of (glyph index, offset, record) components.
+    fn composite(components: &[(u16, (i16, i16), &Record)]) -> Record {
+        let bounds = components
+            .iter()
+            .filter(|(_, _, record)| record.bounds.0 != record.bounds.2)
+            .map(|(_, (dx, dy), record)| {
+                let (x_min, y_min, x_max, y_max) = record.bounds;
+                let (x_min, x_max) = (x_min.saturating_add(*dx), x_max.saturating_add(*dx));
+                (x_min, y_min.saturating_add(*dy), x_max, y_max.saturating_add(*dy))
+            })
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+            .unwrap_or_default();
+        let mut data = Vec::new();
+        for value in [-1, bounds.0, bounds.1, bounds.2, bounds.3] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        for (i, (glyph_index, (dx, dy), _)) in components.iter().enumerate() {
+            let mut flags = ARG_1_AND_2_ARE_WORDS | ARGS_ARE_XY_VALUES;
+            if i + 1 < components.len() {
+                flags |= MORE_COMPONENTS;
+            }
+            for value in [flags, *glyph_index, *dx as u16, *dy as u16] {
+                data.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        Record {
+            data,
+            bounds,
+            points: components.iter().map(|(_, _, record)| record.points).sum(),
+            contours: components.iter().map(|(_, _, record)| record.contours).sum(),
+            components: components.len(),
+            depth: 1 + components.iter().map(|(_, _, record)| record.depth).max().unwrap_or(0),
+        }
+    }
+}
+
+/// maxp version 1.0 with the limits of the glyphs and none for hinting.
+fn maxp(num_glyphs: u16, records: &[Record]) -> Vec<u8> {
+    let max = |count: fn(&Record) -> usize, composite: bool| {
+        let records = records.iter().filter(|record| (record.depth > 0) == composite);
+        records.map(count).max().unwrap_or(0).min(u16::MAX as usize) as u16
+    };
+    let fields = [
+        num_glyphs,
+        max(|record| record.points, false),
+        max(|record| record.contours, false),
+        max(|record| record.points, true),
+        max(|record| record.contours, true),
+        1, // maxZones, no twilight zone
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        max(|record| record.components, true),
+        max(|record| record.depth as usize, true),
+    ];
+    let mut maxp = Vec::with_capacity(32);
+    maxp.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    for value in fields {
+        maxp.extend_from_slice(&value.to_be_bytes());
+    }
+    maxp
+}
+
+/// A name table of Windows Unicode records naming `family` Regular.
+fn name_table(family: &str) -> Vec<u8> {
+    let postscript: String = family.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    let names = [
+        (1, family.to_string()),
+        (2, "Regular".to_string()),
+        (4, format!("{family} Regular")),
+        (6, format!("{postscript}-Regular")),
+    ];
+    let mut strings = Vec::new();
+    let mut table = Vec::new();
+    for value in [0, names.len() as u16, 6 + 12 * names.len() as u16] {
+        table.extend_from_slice(&value.to_be_bytes());
+    }
+    for (name_id, string) in &names {
+        let encoded: Vec<u8> = string.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect();
+        // platform, encoding, language (en-US), name id, length and offset
+        for value in [3, 1, 0x0409, *name_id, encoded.len() as u16, strings.len() as u16] {
+            table.extend_from_slice(&value.to_be_bytes());
+        }
+        strings.extend_from_slice(&encoded);
+    }
+    table.extend_from_slice(&strings);
+    table
+}
+
+/// A GPOS table with one kern feature for the default script, of one format 1 pair adjustment
+/// lookup for the (first, second, adjustment) pairs.
+fn gpos_table(pairs: &mut [(u16, u16, i16)]) -> Vec<u8> {
+    pairs.sort();
+    let mut firsts: Vec<u16> = pairs.iter().map(|(first, _, _)| *first).collect();
+    firsts.dedup();
+
+    // the pair adjustment subtable: header, pair sets and coverage
+    let header_size = 10 + 2 * firsts.len();
+    let mut pair_sets = Vec::new();
+    let mut pair_set_offsets = Vec::with_capacity(firsts.len());
+    for first in &firsts {
+        pair_set_offsets.push((header_size + pair_sets.len()) as u16);
+        let set: Vec<_> = pairs.iter().filter(|(pair_first, _, _)| pair_first == first).collect();
+        pair_sets.extend_from_slice(&(set.len() as u16).to_be_bytes());
+        for (_, second, adjustment) in set {
+            pair_sets.extend_from_slice(&second.to_be_bytes());
+            pair_sets.extend_from_slice(&adjustment.to_be_bytes());
+        }
+    }
+    let coverage_offset = (header_size + pair_sets.len()) as u16;
+    let mut subtable = Vec::new();
+    for value in [1, coverage_offset, X_ADVANCE, 0, firsts.len() as u16] {
+        subtable.extend_from_slice(&value.to_be_bytes());
+    }
+    subtable.extend(pair_set_offsets.iter().flat_map(|offset| offset.to_be_bytes()));
+    subtable.extend_from_slice(&pair_sets);
+    for value in [1, firsts.len() as u16] {
+        subtable.extend_from_slice(&value.to_be_bytes());
+    }
+    subtable.extend(firsts.iter().flat_map(|first| first.to_be_bytes()));
+
+    let mut gpos = Vec::new();
+    let header: [u16; 5] = [1, 0, 10, 30, 44]; // version 1.0, then the list offsets
+    // script list: DFLT, its default language system using feature 0
+    let script_list: [u16; 10] = [1, u16::from_be_bytes(*b"DF"), u16::from_be_bytes(*b"LT"), 8, 4, 0, 0, 0xFFFF, 1, 0];
+    // feature list: kern, using lookup 0
+    let feature_list: [u16; 7] = [1, u16::from_be_bytes(*b"ke"), u16::from_be_bytes(*b"rn"), 8, 0, 1, 0];
+    // lookup list: one pair adjustment lookup with one subtable
+    let lookup_list: [u16; 6] = [1, 4, PAIR_ADJUSTMENT, 0, 1, 8];
+    for value in header.iter().chain(&script_list).chain(&feature_list).chain(&lookup_list) {
+        gpos.extend_from_slice(&value.to_be_bytes());
+    }
+    gpos.extend_from_slice(&subtable);
+    gpos
+}
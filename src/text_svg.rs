@@ -0,0 +1,192 @@
+use raqote::SolidSource;
+use std::fmt::Write;
+
+use crate::font::Font;
+use crate::glyph::Segment;
+use crate::hexbox::hex_box_outline;
+use crate::layout::{layout_text, LayoutOptions};
+use crate::number::{fixed, significant};
+use crate::render::layout_line_with;
+use crate::transform::FontUnitsToPixels;
+
+/// How `Font::text_to_svg` lays out and writes the text. Made with `default` (no wrapping, the
+/// default layout, black, a path per glyph) and the `with_*` methods, fields can be added
+/// without breaking callers.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SvgTextOptions {
+    /// Wrap width in pixels, `None` to only break lines at newlines
+    pub max_width: Option<f32>,
+    pub layout: LayoutOptions,
+    pub color: SolidSource,
+    /// One path for all the glyphs of a color instead of a path per glyph
+    pub merge_paths: bool,
+}
+
+impl Default for SvgTextOptions {
+    fn default() -> SvgTextOptions {
+        SvgTextOptions {
+            max_width: None,
+            layout: LayoutOptions::default(),
+            color: SolidSource::from_unpremultiplied_argb(0xff, 0, 0, 0),
+            merge_paths: false,
+        }
+    }
+}
+
+impl SvgTextOptions {
+    pub fn with_max_width(mut self, max_width: Option<f32>) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: LayoutOptions) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_color(mut self, color: SolidSource) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_merge_paths(mut self, merge_paths: bool) -> Self {
+        self.merge_paths = merge_paths;
+        self
+    }
+}
+
+/// A glyph to write, placed in pixels with y down: its pen position on the baseline.
+pub(crate) struct SvgGlyph<'a> {
+    pub font: &'a Font,
+    pub index: u16,
+    pub missing: Option<char>,
+    pub position: (f32, f32),
+    pub px_size: f32,
+    pub color: SolidSource,
+}
+
+/// The fill color as `#rrggbb` and its opacity, unpremultiplied.
+fn fill(color: SolidSource) -> String {
+    let unpremultiply = |channel: u8| match color.a {
+        0 => 0,
+        a => (channel as u32 * 255 / a as u32).min(255),
+    };
+    let rgb = format!("#{:02x}{:02x}{:02x}", unpremultiply(color.r), unpremultiply(color.g), unpremultiply(color.b));
+    match color.a {
+        0xff => format!("fill=\"{rgb}\""),
+        a => format!("fill=\"{rgb}\" fill-opacity=\"{}\"", fixed(a as f32 / 255., 3)),
+    }
+}
+
+/// Path data for contours, each point mapped through `transform`.
+fn path_data(contours: &[Vec<Segment>], transform: impl Fn((f32, f32)) -> (f32, f32), decimals: usize) -> String {
+    let mut d = String::new();
+    let point = |d: &mut String, p: (f32, f32)| {
+        let p = transform(p);
+        let _ = write!(d, "{} {}", fixed(p.0, decimals), fixed(p.1, decimals));
+    };
+    for segments in contours {
+        let Some(first) = segments.first() else {
+            continue;
+        };
+        d.push_str(if d.is_empty() { "M" } else { " M" });
+        point(&mut d, first.start());
+        for segment in segments {
+            match *segment {
+                Segment::Line(_, p1) => {
+                    d.push_str(" L");
+                    point(&mut d, p1);
+                }
+                Segment::Quad(_, c, p1) => {
+                    d.push_str(" Q");
+                    point(&mut d, c);
+                    d.push(' ');
+                    point(&mut d, p1);
+                }
+            }
+        }
+        d.push_str(" Z");
+    }
+    d
+}
+
+fn glyph_contours(glyph: &SvgGlyph) -> Vec<Vec<Segment>> {
+    match glyph.missing {
+        Some(c) => hex_box_outline(glyph.font, c),
+        None => glyph.font.drawn_outline(glyph.index).map(|outline| outline.segments()).unwrap_or_default(),
+    }
+}
+
+/// An SVG document `width` by `height` pixels with the glyphs filled in their colors: each glyph
+/// a path in font units placed by a transform, or with `merge_paths` each run of glyphs of one
+/// color a path in pixels. Glyphs without an outline are left out.
+pub(crate) fn write_svg(glyphs: &[SvgGlyph], (width, height): (f32, f32), merge_paths: bool) -> String {
+    let (width, height) = (fixed(width.max(0.).ceil(), 0), fixed(height.max(0.).ceil(), 0));
+    let mut svg = String::from("<svg xmlns=\"http://www.w3.org/2000/svg\"");
+    let _ = writeln!(svg, " width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">");
+    let mut at = 0;
+    while at < glyphs.len() {
+        let color = glyphs[at].color;
+        let run = match merge_paths {
+            true => glyphs[at..].iter().take_while(|glyph| glyph.color == color).count(),
+            false => 1,
+        };
+        let mut d = String::new();
+        for glyph in &glyphs[at..at + run] {
+            let contours = glyph_contours(glyph);
+            if contours.iter().all(|segments| segments.is_empty()) {
+                continue;
+            }
+            let to_pixels = FontUnitsToPixels::with_scale(glyph.font.scale_for_px(glyph.px_size), glyph.position);
+            if !merge_paths {
+                let [xx, _, _, yy, dx, dy] = to_pixels.matrix();
+                let (x, y, scale_x, scale_y) = (fixed(dx, 3), fixed(dy, 3), significant(xx, 8), significant(yy, 8));
+                let d = path_data(&contours, |p| p, 3);
+                let transform = format!("translate({x} {y}) scale({scale_x} {scale_y})");
+                let _ = writeln!(svg, "<path transform=\"{transform}\" {} d=\"{d}\"/>", fill(color));
+                continue;
+            }
+            let glyph_d = path_data(&contours, |p| to_pixels.apply(p), 3);
+            if !d.is_empty() {
+                d.push(' ');
+            }
+            d.push_str(&glyph_d);
+        }
+        if !d.is_empty() {
+            let _ = writeln!(svg, "<path {} d=\"{d}\"/>", fill(color));
+        }
+        at += run;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+impl Font {
+    /// Lay out `text` like `layout_text` (newlines, wrapping, line height) with each line laid out
+    /// by `layout_line_with` (kerning, marks, joining) and write it as an SVG document as large as
+    /// the text, see `SvgTextOptions`. Numbers are written by `number::fixed`, so the output only
+    /// changes when the layout does.
+    pub fn text_to_svg(&self, text: &str, px_size: f32, options: &SvgTextOptions) -> String {
+        let max_width = options.max_width.unwrap_or(f32::INFINITY);
+        let layout = layout_text(self, text, px_size, max_width, &options.layout);
+        let mut glyphs = Vec::new();
+        for line in &layout.lines {
+            let line_text = &text[line.range.clone()];
+            let origin = (0., line.baseline);
+            let (positioned, _) = layout_line_with(self, line_text, origin, px_size, None, &layout.line_options(line));
+            // inline objects are the caller's to draw
+            let positioned = positioned.into_iter().filter(|glyph| glyph.object.is_none());
+            glyphs.extend(positioned.map(|glyph| SvgGlyph {
+                font: self,
+                index: glyph.index,
+                missing: glyph.missing,
+                position: glyph.position,
+                px_size,
+                color: options.color,
+            }));
+        }
+        let width = options.max_width.unwrap_or_else(|| layout.width());
+        write_svg(&glyphs, (width, layout.height()), options.merge_paths)
+    }
+}
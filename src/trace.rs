@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::font::Font;
+use crate::layout::LayoutOptions;
+use crate::number::fixed;
+use crate::otlayout::Tag;
+use crate::render::{layout_line_traced, PositionedGlyph};
+
+/// One thing laying out a line did, see `ShapingTrace`. Clusters are byte offsets in the line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapingStep {
+    /// cmap gave the character its glyph, 0 when the font has none
+    Mapped { cluster: usize, character: char, glyph: u16 },
+    /// A GSUB lookup (by its index in the lookup list) of `feature` replaced the glyphs
+    /// `before`, one for a single substitution and the components for a ligature, by `after`.
+    /// Only for the features layout applies, see `ShapingTrace`
+    Substituted {
+        feature: Tag,
+        lookup: u16,
+        cluster: usize,
+        before: Vec<u16>,
+        after: u16,
+    },
+    /// The pen moved by `adjustment` font units between two glyphs, from the kern lookups of
+    /// `table` (GPOS, the only one read)
+    Kerned {
+        first: u16,
+        second: u16,
+        adjustment: i16,
+        table: Tag,
+    },
+    /// Where the glyph ended up
+    Positioned(PositionedGlyph),
+}
+
+/// The steps of laying out a line in the order they happened, for finding out why a line
+/// looks the way it does: check `steps`, or print it. Tracing is on for the layout calls a
+/// trace is given to (see `render::layout_line_traced`) and costs nothing otherwise.
+///
+/// Layout only substitutes glyphs where it shapes: the Arabic joining forms and rlig in joining
+/// scripts, ccmp and liga inside emoji sequences. liga doesn't apply to ordinary runs, so "ffi"
+/// in Latin text traces as the glyphs cmap gave it whether or not the font has the ligature,
+/// and no `Substituted` step there isn't a lookup failing to match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapingTrace {
+    pub steps: Vec<ShapingStep>,
+    enabled: bool,
+}
+
+impl ShapingTrace {
+    pub fn new() -> ShapingTrace {
+        ShapingTrace {
+            steps: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    /// A trace that records nothing, for the layout calls that aren't traced.
+    pub(crate) fn disabled() -> ShapingTrace {
+        ShapingTrace {
+            steps: Vec::new(),
+            enabled: false,
+        }
+    }
+
+    /// Add the step `step` makes, only made when tracing.
+    pub(crate) fn record(&mut self, step: impl FnOnce() -> ShapingStep) {
+        if self.enabled {
+            self.steps.push(step());
+        }
+    }
+
+    /// The substitutions that formed glyph `glyph`.
+    pub fn substitutions_to(&self, glyph: u16) -> impl Iterator<Item = &ShapingStep> + '_ {
+        self.steps
+            .iter()
+            .filter(move |step| matches!(step, ShapingStep::Substituted { after, .. } if *after == glyph))
+    }
+}
+
+impl Default for ShapingTrace {
+    fn default() -> ShapingTrace {
+        ShapingTrace::new()
+    }
+}
+
+/// One step per line, e.g. `liga lookup 3: 73 76 -> 1234 at 0`.
+impl fmt::Display for ShapingTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tag = |tag: &Tag| String::from_utf8_lossy(tag).into_owned();
+        for step in &self.steps {
+            match step {
+                ShapingStep::Mapped { cluster, character, glyph } => {
+                    writeln!(f, "cmap {character:?} U+{:04X} -> {glyph} at {cluster}", *character as u32)?
+                }
+                ShapingStep::Substituted {
+                    feature,
+                    lookup,
+                    cluster,
+                    before,
+                    after,
+                } => {
+                    let before: Vec<String> = before.iter().map(u16::to_string).collect();
+                    writeln!(f, "{} lookup {lookup}: {} -> {after} at {cluster}", tag(feature), before.join(" "))?
+                }
+                ShapingStep::Kerned {
+                    first,
+                    second,
+                    adjustment,
+                    table,
+                } => writeln!(f, "kern {first} {second}: {adjustment:+} units from {}", tag(table))?,
+                ShapingStep::Positioned(glyph) => {
+                    let (x, y) = glyph.position;
+                    let (index, cluster, advance) = (glyph.index, glyph.cluster, glyph.advance);
+                    let (x, y, advance) = (fixed(x, 2), fixed(y, 2), fixed(advance, 2));
+                    write!(f, "place {index} at {cluster} ({x}, {y}) advance {advance}")?;
+                    if let Some(c) = glyph.missing {
+                        write!(f, ", hex box for {c:?}")?;
+                    }
+                    if let Some(object) = glyph.object {
+                        write!(f, ", inline object {object}")?;
+                    }
+                    writeln!(f)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Font {
+    /// Lay out a line from the origin like `render::layout_line_with` and return what that
+    /// did, see `ShapingTrace`.
+    pub fn shaping_trace(&self, text: &str, px_size: f32, layout: &LayoutOptions) -> ShapingTrace {
+        let mut trace = ShapingTrace::new();
+        layout_line_traced(self, text, (0., 0.), px_size, None, layout, &mut trace);
+        trace
+    }
+}
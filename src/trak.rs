@@ -0,0 +1,82 @@
+use anyhow::Result;
+
+use crate::font::Font;
+use crate::reader::FontReader;
+use crate::size::FontSize;
+
+/// The tracking table of Apple fonts: how much to space glyphs apart (or bring them together)
+/// at each point size. Each track (0 normal, -1 tight, 1 loose) gives a value in font units per
+/// size of the size table. Only the horizontal track data is read.
+#[derive(Debug, Clone, Default)]
+pub struct Trak {
+    /// Point sizes, ascending
+    pub sizes: Vec<f32>,
+    /// (track, a value per size), by ascending track
+    pub tracks: Vec<(f32, Vec<i16>)>,
+}
+
+impl Trak {
+    pub fn from_reader(reader: &mut FontReader, trak_offset: u32) -> Result<Trak> {
+        let start = trak_offset as u64;
+        reader.seek(start + 6)?; // skip version and format
+        let horizontal = match reader.read_u16()? {
+            0 => return Ok(Trak::default()),
+            offset => start + offset as u64,
+        };
+        reader.seek(horizontal)?;
+        let track_count = reader.read_u16()?;
+        let size_count = reader.read_u16()?;
+        let size_table = start + reader.read_u32()? as u64;
+        let mut entries = Vec::with_capacity(track_count as usize);
+        for _ in 0..track_count {
+            let track = reader.read_fixed()?.to_f32();
+            reader.skip(2)?; // skip nameIndex
+            entries.push((track, start + reader.read_u16()? as u64));
+        }
+        let mut tracks = Vec::with_capacity(entries.len());
+        for (track, offset) in entries {
+            reader.seek(offset)?;
+            let values = (0..size_count).map(|_| reader.read_i16()).collect::<Result<_, _>>()?;
+            tracks.push((track, values));
+        }
+        tracks.sort_by(|a, b| a.0.total_cmp(&b.0));
+        reader.seek(size_table)?;
+        let sizes = (0..size_count).map(|_| Ok(reader.read_fixed()?.to_f32())).collect::<Result<_>>()?;
+        Ok(Trak { sizes, tracks })
+    }
+
+    /// The tracking of `track` at `size` points in font units, linearly interpolated between the
+    /// sizes (and tracks) of the table and held at its first and last ones beyond them.
+    pub fn value(&self, size: f32, track: f32) -> f32 {
+        let at_size = |values: &[i16]| interpolate(&self.sizes, |i| values.get(i).copied().unwrap_or(0) as f32, size);
+        let track_values: Vec<f32> = self.tracks.iter().map(|(track, _)| *track).collect();
+        interpolate(&track_values, |i| at_size(&self.tracks[i].1), track)
+    }
+}
+
+/// The piecewise linear function through (`keys[i]`, `value(i)`) at `at`, constant past both
+/// ends, 0 without keys.
+fn interpolate(keys: &[f32], value: impl Fn(usize) -> f32, at: f32) -> f32 {
+    let Some(last) = keys.len().checked_sub(1) else {
+        return 0.;
+    };
+    let next = keys.partition_point(|key| *key < at);
+    if next == 0 {
+        return value(0);
+    }
+    if next > last {
+        return value(last);
+    }
+    let (k0, k1) = (keys[next - 1], keys[next]);
+    let t = (at - k0) / (k1 - k0);
+    value(next - 1) + t * (value(next) - value(next - 1))
+}
+
+impl Font {
+    /// The trak tracking of `track` (0 the normal one) at `px_size` in pixels, taking a pixel
+    /// per point (72 dpi), see `tracking_for` for other resolutions. 0 for fonts without a trak
+    /// table.
+    pub fn tracking(&self, px_size: f32, track: f32) -> f32 {
+        self.tracking_for(FontSize::px(px_size), track)
+    }
+}
@@ -0,0 +1,158 @@
+//! The conversions every drawing of a glyph needs. Outlines are in font units with y pointing
+//! up and the glyph origin on the baseline at (0, 0); draw targets and bitmaps are in pixels
+//! with y pointing down. A `FontUnitsToPixels` scales (units per em to pixels per em), flips y
+//! and moves the glyph origin to where it's drawn, in that order.
+//!
+//! ```
+//! use font_rendering::FontUnitsToPixels;
+//!
+//! // a font of 2048 units per em drawn at 16px, the glyph origin at (10, 20) in the target
+//! let to_pixels = FontUnitsToPixels::new(2048, 16., (10., 20.));
+//! // half an em above the baseline is 8px up, so 8px less in y
+//! assert_eq!(to_pixels.apply((0., 1024.)), (10., 12.));
+//! assert_eq!(to_pixels.apply((256., -256.)), (12., 22.));
+//! assert_eq!(to_pixels.length(128.), 1.);
+//! ```
+
+use crate::glyph::Segment;
+use crate::outline::Outline;
+
+/// p' = origin + scale * (x, y), with y negated when `flip_y`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontUnitsToPixels {
+    /// Pixels per font unit
+    pub scale: f32,
+    pub flip_y: bool,
+    /// Where the glyph origin goes
+    pub origin: (f32, f32),
+}
+
+impl Default for FontUnitsToPixels {
+    fn default() -> FontUnitsToPixels {
+        FontUnitsToPixels::IDENTITY
+    }
+}
+
+impl FontUnitsToPixels {
+    /// Leaves points where they are.
+    pub const IDENTITY: FontUnitsToPixels = FontUnitsToPixels {
+        scale: 1.,
+        flip_y: false,
+        origin: (0., 0.),
+    };
+
+    /// Font units of a font with `units_per_em` to pixels at `px_size` (pixels per em) in a y
+    /// down draw target, the glyph origin at `baseline_origin` there.
+    pub fn new(units_per_em: u16, px_size: f32, baseline_origin: (f32, f32)) -> FontUnitsToPixels {
+        FontUnitsToPixels::with_scale(px_size / units_per_em as f32, baseline_origin)
+    }
+
+    /// `new` from the scale (see `Font::scale_for_px`) rather than the size.
+    pub fn with_scale(scale: f32, baseline_origin: (f32, f32)) -> FontUnitsToPixels {
+        FontUnitsToPixels {
+            scale,
+            flip_y: true,
+            origin: baseline_origin,
+        }
+    }
+
+    /// Scaling alone, y staying up and the glyph origin at (0, 0): pixels relative to the glyph
+    /// origin, what outlines are rasterized from.
+    pub fn scaling(scale: f32) -> FontUnitsToPixels {
+        FontUnitsToPixels {
+            scale,
+            flip_y: false,
+            origin: (0., 0.),
+        }
+    }
+
+    /// This transform followed by `next`, as one.
+    ///
+    /// ```
+    /// use font_rendering::FontUnitsToPixels;
+    ///
+    /// // pixels relative to the glyph origin, y up, then placed in a y down target
+    /// let to_glyph = FontUnitsToPixels::scaling(0.5);
+    /// let onto_target = FontUnitsToPixels::with_scale(1., (100., 50.));
+    /// let both = to_glyph.then(onto_target);
+    /// assert_eq!(both.apply((20., 40.)), onto_target.apply(to_glyph.apply((20., 40.))));
+    /// assert_eq!(both.apply((20., 40.)), (110., 30.));
+    /// assert_eq!(both, FontUnitsToPixels::with_scale(0.5, (100., 50.)));
+    /// ```
+    pub fn then(self, next: FontUnitsToPixels) -> FontUnitsToPixels {
+        let origin = next.apply(self.origin);
+        FontUnitsToPixels {
+            scale: self.scale * next.scale,
+            flip_y: self.flip_y != next.flip_y,
+            origin,
+        }
+    }
+
+    /// Map a point.
+    pub fn apply(&self, p: (f32, f32)) -> (f32, f32) {
+        let y = if self.flip_y { -p.1 } else { p.1 };
+        (self.origin.0 + p.0 * self.scale, self.origin.1 + y * self.scale)
+    }
+
+    /// Map a distance, which only scales.
+    pub fn length(&self, units: f32) -> f32 {
+        units * self.scale
+    }
+
+    /// The same map as an affine matrix [xx, yx, xy, yy, dx, dy], see `Outline::transform`.
+    pub fn matrix(&self) -> [f32; 6] {
+        let yy = if self.flip_y { -self.scale } else { self.scale };
+        [self.scale, 0., 0., yy, self.origin.0, self.origin.1]
+    }
+
+    pub fn apply_segment(&self, segment: &Segment) -> Segment {
+        match *segment {
+            Segment::Line(p0, p1) => Segment::Line(self.apply(p0), self.apply(p1)),
+            Segment::Quad(p0, c, p1) => Segment::Quad(self.apply(p0), self.apply(c), self.apply(p1)),
+        }
+    }
+
+    /// Map contours given as segments.
+    pub fn apply_segments(&self, contours: &[Vec<Segment>]) -> Vec<Vec<Segment>> {
+        contours
+            .iter()
+            .map(|segments| segments.iter().map(|segment| self.apply_segment(segment)).collect())
+            .collect()
+    }
+
+    /// A copy of the outline with every point mapped, `bbox` included.
+    ///
+    /// ```
+    /// use font_rendering::outline::Outline;
+    /// use font_rendering::FontUnitsToPixels;
+    ///
+    /// // a triangle 500 units wide and 700 tall in a font of 1000 units per em
+    /// let outline = Outline::from_points(&[(0., 0.), (0., 700.), (500., 0.)], &[true; 3], &[2]);
+    /// let drawn = FontUnitsToPixels::new(1000, 20., (5., 30.)).apply_outline(&outline);
+    /// assert_eq!(drawn.bbox, (5., 16., 15., 30.));
+    /// // the top of the triangle is 14px above the baseline
+    /// assert_eq!((drawn.contours[0].points[1].x, drawn.contours[0].points[1].y), (5., 16.));
+    /// ```
+    pub fn apply_outline(&self, outline: &Outline) -> Outline {
+        let mut outline = outline.clone();
+        outline.transform(self.matrix());
+        outline
+    }
+}
+
+/// A length given in ems, like 0.05 em of letter spacing, in font units.
+///
+/// ```
+/// use font_rendering::transform::{ems_to_units, units_to_ems};
+///
+/// assert_eq!(ems_to_units(0.05, 2000), 100.);
+/// assert_eq!(units_to_ems(ems_to_units(0.25, 2048), 2048), 0.25);
+/// ```
+pub fn ems_to_units(ems: f32, units_per_em: u16) -> f32 {
+    ems * units_per_em as f32
+}
+
+/// A length in font units in ems, to compare fonts of different units per em.
+pub fn units_to_ems(units: f32, units_per_em: u16) -> f32 {
+    units / units_per_em as f32
+}
@@ -0,0 +1,1021 @@
+// Unicode character properties the layout and the editor need, as sorted code point ranges,
+// generated from the Unicode 14.0 character database with perl.
+//
+// Scripts (UAX #24): unassigned code points are folded into the range before them and code
+// points of other scripts are left out, they come back as `Script::Other`.
+//
+// Joining types (Unicode chapter 9.2) of the Arabic, Syriac and N'Ko blocks and ZWJ; code points
+// not listed are non-joining.
+//
+// Grapheme cluster break classes (UAX #29) with `\p{Grapheme_Cluster_Break=...}` and
+// `\p{Extended_Pictographic}`, leaving out CR, LF, ZWJ, the regional indicators and the Hangul
+// syllables (which `grapheme_break` works out itself); code points not listed are `Other`.
+
+use crate::arabic::JoiningType::{self, *};
+use crate::grapheme::GraphemeBreak::{self, *};
+use crate::itemize::Script::{self, *};
+
+pub(crate) static SCRIPT_RANGES: &[(u32, u32, Script)] = &[
+    (0x0000, 0x0040, Common),
+    (0x0041, 0x005A, Latin),
+    (0x005B, 0x0060, Common),
+    (0x0061, 0x007A, Latin),
+    (0x007B, 0x00A9, Common),
+    (0x00AA, 0x00AA, Latin),
+    (0x00AB, 0x00B9, Common),
+    (0x00BA, 0x00BA, Latin),
+    (0x00BB, 0x00BF, Common),
+    (0x00C0, 0x00D6, Latin),
+    (0x00D7, 0x00D7, Common),
+    (0x00D8, 0x00F6, Latin),
+    (0x00F7, 0x00F7, Common),
+    (0x00F8, 0x02B8, Latin),
+    (0x02B9, 0x02DF, Common),
+    (0x02E0, 0x02E4, Latin),
+    (0x02E5, 0x02E9, Common),
+    (0x02EC, 0x02FF, Common),
+    (0x0300, 0x036F, Inherited),
+    (0x0370, 0x0373, Greek),
+    (0x0374, 0x0374, Common),
+    (0x0375, 0x037D, Greek),
+    (0x037E, 0x037E, Common),
+    (0x037F, 0x0384, Greek),
+    (0x0385, 0x0385, Common),
+    (0x0386, 0x0386, Greek),
+    (0x0387, 0x0387, Common),
+    (0x0388, 0x03E1, Greek),
+    (0x03F0, 0x03FF, Greek),
+    (0x0400, 0x0484, Cyrillic),
+    (0x0485, 0x0486, Inherited),
+    (0x0487, 0x0530, Cyrillic),
+    (0x0531, 0x0590, Armenian),
+    (0x0591, 0x05FF, Hebrew),
+    (0x0600, 0x0604, Arabic),
+    (0x0605, 0x0605, Common),
+    (0x0606, 0x060B, Arabic),
+    (0x060C, 0x060C, Common),
+    (0x060D, 0x061A, Arabic),
+    (0x061B, 0x061B, Common),
+    (0x061C, 0x061E, Arabic),
+    (0x061F, 0x061F, Common),
+    (0x0620, 0x063F, Arabic),
+    (0x0640, 0x0640, Common),
+    (0x0641, 0x064A, Arabic),
+    (0x064B, 0x0655, Inherited),
+    (0x0656, 0x066F, Arabic),
+    (0x0670, 0x0670, Inherited),
+    (0x0671, 0x06DC, Arabic),
+    (0x06DD, 0x06DD, Common),
+    (0x06DE, 0x06FF, Arabic),
+    (0x0700, 0x074F, Syriac),
+    (0x0750, 0x077F, Arabic),
+    (0x0780, 0x07BF, Thaana),
+    (0x07C0, 0x07FF, Nko),
+    (0x0860, 0x086F, Syriac),
+    (0x0870, 0x08E1, Arabic),
+    (0x08E2, 0x08E2, Common),
+    (0x08E3, 0x08FF, Arabic),
+    (0x0900, 0x0950, Devanagari),
+    (0x0951, 0x0954, Inherited),
+    (0x0955, 0x0963, Devanagari),
+    (0x0964, 0x0965, Common),
+    (0x0966, 0x097F, Devanagari),
+    (0x0980, 0x0A00, Bengali),
+    (0x0A01, 0x0A80, Gurmukhi),
+    (0x0A81, 0x0B00, Gujarati),
+    (0x0B82, 0x0BFF, Tamil),
+    (0x0C00, 0x0C7F, Telugu),
+    (0x0C80, 0x0CFF, Kannada),
+    (0x0D00, 0x0D80, Malayalam),
+    (0x0E01, 0x0E3E, Thai),
+    (0x0E3F, 0x0E3F, Common),
+    (0x0E40, 0x0E80, Thai),
+    (0x0E81, 0x0EFF, Lao),
+    (0x0F00, 0x0FD4, Tibetan),
+    (0x0FD5, 0x0FD8, Common),
+    (0x0FD9, 0x0FFF, Tibetan),
+    (0x10A0, 0x10FA, Georgian),
+    (0x10FB, 0x10FB, Common),
+    (0x10FC, 0x10FF, Georgian),
+    (0x1100, 0x11FF, Hangul),
+    (0x16EB, 0x16ED, Common),
+    (0x1735, 0x173F, Common),
+    (0x1802, 0x1803, Common),
+    (0x1805, 0x1805, Common),
+    (0x1AB0, 0x1AFF, Inherited),
+    (0x1C80, 0x1C8F, Cyrillic),
+    (0x1C90, 0x1CBF, Georgian),
+    (0x1CD0, 0x1CD2, Inherited),
+    (0x1CD3, 0x1CD3, Common),
+    (0x1CD4, 0x1CE0, Inherited),
+    (0x1CE1, 0x1CE1, Common),
+    (0x1CE2, 0x1CE8, Inherited),
+    (0x1CE9, 0x1CEC, Common),
+    (0x1CED, 0x1CED, Inherited),
+    (0x1CEE, 0x1CF3, Common),
+    (0x1CF4, 0x1CF4, Inherited),
+    (0x1CF5, 0x1CF7, Common),
+    (0x1CF8, 0x1CF9, Inherited),
+    (0x1CFA, 0x1CFF, Common),
+    (0x1D00, 0x1D25, Latin),
+    (0x1D26, 0x1D2A, Greek),
+    (0x1D2B, 0x1D2B, Cyrillic),
+    (0x1D2C, 0x1D5C, Latin),
+    (0x1D5D, 0x1D61, Greek),
+    (0x1D62, 0x1D65, Latin),
+    (0x1D66, 0x1D6A, Greek),
+    (0x1D6B, 0x1D77, Latin),
+    (0x1D78, 0x1D78, Cyrillic),
+    (0x1D79, 0x1DBE, Latin),
+    (0x1DBF, 0x1DBF, Greek),
+    (0x1DC0, 0x1DFF, Inherited),
+    (0x1E00, 0x1EFF, Latin),
+    (0x1F00, 0x1FFF, Greek),
+    (0x2000, 0x200B, Common),
+    (0x200C, 0x200D, Inherited),
+    (0x200E, 0x2070, Common),
+    (0x2071, 0x2073, Latin),
+    (0x2074, 0x207E, Common),
+    (0x207F, 0x207F, Latin),
+    (0x2080, 0x208F, Common),
+    (0x2090, 0x209F, Latin),
+    (0x20A0, 0x20CF, Common),
+    (0x20D0, 0x20FF, Inherited),
+    (0x2100, 0x2125, Common),
+    (0x2126, 0x2126, Greek),
+    (0x2127, 0x2129, Common),
+    (0x212A, 0x212B, Latin),
+    (0x212C, 0x2131, Common),
+    (0x2132, 0x2132, Latin),
+    (0x2133, 0x214D, Common),
+    (0x214E, 0x214E, Latin),
+    (0x214F, 0x215F, Common),
+    (0x2160, 0x2188, Latin),
+    (0x2189, 0x27FF, Common),
+    (0x2900, 0x2BFF, Common),
+    (0x2C60, 0x2C7F, Latin),
+    (0x2D00, 0x2D2F, Georgian),
+    (0x2DE0, 0x2DFF, Cyrillic),
+    (0x2E00, 0x2E7F, Common),
+    (0x2E80, 0x2FEF, Han),
+    (0x2FF0, 0x3004, Common),
+    (0x3005, 0x3005, Han),
+    (0x3006, 0x3006, Common),
+    (0x3007, 0x3007, Han),
+    (0x3008, 0x3020, Common),
+    (0x3021, 0x3029, Han),
+    (0x302A, 0x302D, Inherited),
+    (0x302E, 0x302F, Hangul),
+    (0x3030, 0x3037, Common),
+    (0x3038, 0x303B, Han),
+    (0x303C, 0x3040, Common),
+    (0x3041, 0x3098, Hiragana),
+    (0x3099, 0x309A, Inherited),
+    (0x309B, 0x309C, Common),
+    (0x309D, 0x309F, Hiragana),
+    (0x30A0, 0x30A0, Common),
+    (0x30A1, 0x30FA, Katakana),
+    (0x30FB, 0x30FC, Common),
+    (0x30FD, 0x3104, Katakana),
+    (0x3131, 0x318F, Hangul),
+    (0x3190, 0x319F, Common),
+    (0x31C0, 0x31EF, Common),
+    (0x31F0, 0x31FF, Katakana),
+    (0x3200, 0x321F, Hangul),
+    (0x3220, 0x325F, Common),
+    (0x3260, 0x327E, Hangul),
+    (0x327F, 0x32CF, Common),
+    (0x32D0, 0x32FE, Katakana),
+    (0x32FF, 0x32FF, Common),
+    (0x3300, 0x3357, Katakana),
+    (0x3358, 0x33FF, Common),
+    (0x3400, 0x4DBF, Han),
+    (0x4DC0, 0x4DFF, Common),
+    (0x4E00, 0x9FFF, Han),
+    (0xA640, 0xA69F, Cyrillic),
+    (0xA700, 0xA721, Common),
+    (0xA722, 0xA787, Latin),
+    (0xA788, 0xA78A, Common),
+    (0xA78B, 0xA7FF, Latin),
+    (0xA830, 0xA83F, Common),
+    (0xA8E0, 0xA8FF, Devanagari),
+    (0xA92E, 0xA92E, Common),
+    (0xA960, 0xA97F, Hangul),
+    (0xA9CF, 0xA9CF, Common),
+    (0xAB30, 0xAB5A, Latin),
+    (0xAB5B, 0xAB5B, Common),
+    (0xAB5C, 0xAB64, Latin),
+    (0xAB65, 0xAB65, Greek),
+    (0xAB66, 0xAB69, Latin),
+    (0xAB6A, 0xAB6F, Common),
+    (0xAC00, 0xD7FF, Hangul),
+    (0xF900, 0xFAFF, Han),
+    (0xFB00, 0xFB12, Latin),
+    (0xFB13, 0xFB1C, Armenian),
+    (0xFB1D, 0xFB4F, Hebrew),
+    (0xFB50, 0xFD3D, Arabic),
+    (0xFD3E, 0xFD3F, Common),
+    (0xFD40, 0xFDFF, Arabic),
+    (0xFE00, 0xFE0F, Inherited),
+    (0xFE10, 0xFE1F, Common),
+    (0xFE20, 0xFE2D, Inherited),
+    (0xFE2E, 0xFE2F, Cyrillic),
+    (0xFE30, 0xFE6F, Common),
+    (0xFE70, 0xFEFE, Arabic),
+    (0xFEFF, 0xFF20, Common),
+    (0xFF21, 0xFF3A, Latin),
+    (0xFF3B, 0xFF40, Common),
+    (0xFF41, 0xFF5A, Latin),
+    (0xFF5B, 0xFF65, Common),
+    (0xFF66, 0xFF6F, Katakana),
+    (0xFF70, 0xFF70, Common),
+    (0xFF71, 0xFF9D, Katakana),
+    (0xFF9E, 0xFF9F, Common),
+    (0xFFA0, 0xFFDF, Hangul),
+    (0xFFE0, 0xFFFF, Common),
+    (0x10100, 0x1013F, Common),
+    (0x10140, 0x1018F, Greek),
+    (0x10190, 0x1019F, Common),
+    (0x101A0, 0x101CF, Greek),
+    (0x101D0, 0x101FC, Common),
+    (0x101FD, 0x1027F, Inherited),
+    (0x102E0, 0x102E0, Inherited),
+    (0x102E1, 0x102FF, Common),
+    (0x10780, 0x107FF, Latin),
+    (0x10E60, 0x10E7F, Arabic),
+    (0x1133B, 0x1133B, Inherited),
+    (0x11FC0, 0x11FFF, Tamil),
+    (0x16FE2, 0x16FE3, Han),
+    (0x16FF0, 0x16FFF, Han),
+    (0x1AFF0, 0x1B000, Katakana),
+    (0x1B001, 0x1B11F, Hiragana),
+    (0x1B120, 0x1B14F, Katakana),
+    (0x1B150, 0x1B163, Hiragana),
+    (0x1B164, 0x1B16F, Katakana),
+    (0x1BCA0, 0x1CEFF, Common),
+    (0x1CF00, 0x1CF4F, Inherited),
+    (0x1CF50, 0x1D166, Common),
+    (0x1D167, 0x1D169, Inherited),
+    (0x1D16A, 0x1D17A, Common),
+    (0x1D17B, 0x1D182, Inherited),
+    (0x1D183, 0x1D184, Common),
+    (0x1D185, 0x1D18B, Inherited),
+    (0x1D18C, 0x1D1A9, Common),
+    (0x1D1AA, 0x1D1AD, Inherited),
+    (0x1D1AE, 0x1D1FF, Common),
+    (0x1D200, 0x1D2DF, Greek),
+    (0x1D2E0, 0x1D7FF, Common),
+    (0x1DF00, 0x1DFFF, Latin),
+    (0x1EC71, 0x1EDFF, Common),
+    (0x1EE00, 0x1EFFF, Arabic),
+    (0x1F000, 0x1F1FF, Common),
+    (0x1F200, 0x1F200, Hiragana),
+    (0x1F201, 0x1FFFF, Common),
+    (0x20000, 0x3134A, Han),
+];
+
+pub(crate) static JOINING_RANGES: &[(u32, u32, JoiningType)] = &[
+    (0x0610, 0x061A, Transparent),
+    (0x061C, 0x061C, Transparent),
+    (0x0620, 0x0620, Dual),
+    (0x0622, 0x0625, Right),
+    (0x0626, 0x0626, Dual),
+    (0x0627, 0x0627, Right),
+    (0x0628, 0x0628, Dual),
+    (0x0629, 0x0629, Right),
+    (0x062A, 0x062E, Dual),
+    (0x062F, 0x0632, Right),
+    (0x0633, 0x063F, Dual),
+    (0x0640, 0x0640, JoinCausing),
+    (0x0641, 0x0647, Dual),
+    (0x0648, 0x0648, Right),
+    (0x0649, 0x064A, Dual),
+    (0x064B, 0x065F, Transparent),
+    (0x066E, 0x066F, Dual),
+    (0x0670, 0x0670, Transparent),
+    (0x0671, 0x0673, Right),
+    (0x0675, 0x0677, Right),
+    (0x0678, 0x0687, Dual),
+    (0x0688, 0x0699, Right),
+    (0x069A, 0x06BF, Dual),
+    (0x06C0, 0x06C0, Right),
+    (0x06C1, 0x06C2, Dual),
+    (0x06C3, 0x06CB, Right),
+    (0x06CC, 0x06CC, Dual),
+    (0x06CD, 0x06CD, Right),
+    (0x06CE, 0x06CE, Dual),
+    (0x06CF, 0x06CF, Right),
+    (0x06D0, 0x06D1, Dual),
+    (0x06D2, 0x06D3, Right),
+    (0x06D5, 0x06D5, Right),
+    (0x06D6, 0x06DC, Transparent),
+    (0x06DF, 0x06E4, Transparent),
+    (0x06E7, 0x06E8, Transparent),
+    (0x06EA, 0x06ED, Transparent),
+    (0x06EE, 0x06EF, Right),
+    (0x06FA, 0x06FC, Dual),
+    (0x06FF, 0x06FF, Dual),
+    (0x070F, 0x070F, Transparent),
+    (0x0710, 0x0710, Right),
+    (0x0711, 0x0711, Transparent),
+    (0x0712, 0x0714, Dual),
+    (0x0715, 0x0719, Right),
+    (0x071A, 0x071D, Dual),
+    (0x071E, 0x071E, Right),
+    (0x071F, 0x0727, Dual),
+    (0x0728, 0x0728, Right),
+    (0x0729, 0x0729, Dual),
+    (0x072A, 0x072A, Right),
+    (0x072B, 0x072B, Dual),
+    (0x072C, 0x072C, Right),
+    (0x072D, 0x072E, Dual),
+    (0x072F, 0x072F, Right),
+    (0x0730, 0x074A, Transparent),
+    (0x074D, 0x074D, Right),
+    (0x074E, 0x0758, Dual),
+    (0x0759, 0x075B, Right),
+    (0x075C, 0x076A, Dual),
+    (0x076B, 0x076C, Right),
+    (0x076D, 0x0770, Dual),
+    (0x0771, 0x0771, Right),
+    (0x0772, 0x0772, Dual),
+    (0x0773, 0x0774, Right),
+    (0x0775, 0x0777, Dual),
+    (0x0778, 0x0779, Right),
+    (0x077A, 0x077F, Dual),
+    (0x07A6, 0x07B0, Transparent),
+    (0x07CA, 0x07EA, Dual),
+    (0x07EB, 0x07F3, Transparent),
+    (0x07FA, 0x07FA, JoinCausing),
+    (0x07FD, 0x07FD, Transparent),
+    (0x0816, 0x0819, Transparent),
+    (0x081B, 0x0823, Transparent),
+    (0x0825, 0x0827, Transparent),
+    (0x0829, 0x082D, Transparent),
+    (0x0840, 0x0840, Right),
+    (0x0841, 0x0845, Dual),
+    (0x0846, 0x0847, Right),
+    (0x0848, 0x0848, Dual),
+    (0x0849, 0x0849, Right),
+    (0x084A, 0x0853, Dual),
+    (0x0854, 0x0854, Right),
+    (0x0855, 0x0855, Dual),
+    (0x0856, 0x0858, Right),
+    (0x0859, 0x085B, Transparent),
+    (0x0860, 0x0860, Dual),
+    (0x0862, 0x0865, Dual),
+    (0x0867, 0x0867, Right),
+    (0x0868, 0x0868, Dual),
+    (0x0869, 0x086A, Right),
+    (0x0870, 0x0882, Right),
+    (0x0883, 0x0885, JoinCausing),
+    (0x0886, 0x0886, Dual),
+    (0x0889, 0x088D, Dual),
+    (0x088E, 0x088E, Right),
+    (0x0898, 0x089F, Transparent),
+    (0x08A0, 0x08A9, Dual),
+    (0x08AA, 0x08AC, Right),
+    (0x08AE, 0x08AE, Right),
+    (0x08AF, 0x08B0, Dual),
+    (0x08B1, 0x08B2, Right),
+    (0x08B3, 0x08B8, Dual),
+    (0x08B9, 0x08B9, Right),
+    (0x08BA, 0x08C8, Dual),
+    (0x08CA, 0x08E1, Transparent),
+    (0x08E3, 0x08FF, Transparent),
+    (0x200D, 0x200D, JoinCausing),
+];
+
+pub(crate) static GRAPHEME_BREAK_RANGES: &[(u32, u32, GraphemeBreak)] = &[
+    (0x0000, 0x0009, Control),
+    (0x000B, 0x000C, Control),
+    (0x000E, 0x001F, Control),
+    (0x007F, 0x009F, Control),
+    (0x00A9, 0x00A9, ExtendedPictographic),
+    (0x00AD, 0x00AD, Control),
+    (0x00AE, 0x00AE, ExtendedPictographic),
+    (0x0300, 0x036F, Extend),
+    (0x0483, 0x0489, Extend),
+    (0x0591, 0x05BD, Extend),
+    (0x05BF, 0x05BF, Extend),
+    (0x05C1, 0x05C2, Extend),
+    (0x05C4, 0x05C5, Extend),
+    (0x05C7, 0x05C7, Extend),
+    (0x0600, 0x0605, Prepend),
+    (0x0610, 0x061A, Extend),
+    (0x061C, 0x061C, Control),
+    (0x064B, 0x065F, Extend),
+    (0x0670, 0x0670, Extend),
+    (0x06D6, 0x06DC, Extend),
+    (0x06DD, 0x06DD, Prepend),
+    (0x06DF, 0x06E4, Extend),
+    (0x06E7, 0x06E8, Extend),
+    (0x06EA, 0x06ED, Extend),
+    (0x070F, 0x070F, Prepend),
+    (0x0711, 0x0711, Extend),
+    (0x0730, 0x074A, Extend),
+    (0x07A6, 0x07B0, Extend),
+    (0x07EB, 0x07F3, Extend),
+    (0x07FD, 0x07FD, Extend),
+    (0x0816, 0x0819, Extend),
+    (0x081B, 0x0823, Extend),
+    (0x0825, 0x0827, Extend),
+    (0x0829, 0x082D, Extend),
+    (0x0859, 0x085B, Extend),
+    (0x0890, 0x0891, Prepend),
+    (0x0898, 0x089F, Extend),
+    (0x08CA, 0x08E1, Extend),
+    (0x08E2, 0x08E2, Prepend),
+    (0x08E3, 0x0902, Extend),
+    (0x0903, 0x0903, SpacingMark),
+    (0x093A, 0x093A, Extend),
+    (0x093B, 0x093B, SpacingMark),
+    (0x093C, 0x093C, Extend),
+    (0x093E, 0x0940, SpacingMark),
+    (0x0941, 0x0948, Extend),
+    (0x0949, 0x094C, SpacingMark),
+    (0x094D, 0x094D, Extend),
+    (0x094E, 0x094F, SpacingMark),
+    (0x0951, 0x0957, Extend),
+    (0x0962, 0x0963, Extend),
+    (0x0981, 0x0981, Extend),
+    (0x0982, 0x0983, SpacingMark),
+    (0x09BC, 0x09BC, Extend),
+    (0x09BE, 0x09BE, Extend),
+    (0x09BF, 0x09C0, SpacingMark),
+    (0x09C1, 0x09C4, Extend),
+    (0x09C7, 0x09C8, SpacingMark),
+    (0x09CB, 0x09CC, SpacingMark),
+    (0x09CD, 0x09CD, Extend),
+    (0x09D7, 0x09D7, Extend),
+    (0x09E2, 0x09E3, Extend),
+    (0x09FE, 0x09FE, Extend),
+    (0x0A01, 0x0A02, Extend),
+    (0x0A03, 0x0A03, SpacingMark),
+    (0x0A3C, 0x0A3C, Extend),
+    (0x0A3E, 0x0A40, SpacingMark),
+    (0x0A41, 0x0A42, Extend),
+    (0x0A47, 0x0A48, Extend),
+    (0x0A4B, 0x0A4D, Extend),
+    (0x0A51, 0x0A51, Extend),
+    (0x0A70, 0x0A71, Extend),
+    (0x0A75, 0x0A75, Extend),
+    (0x0A81, 0x0A82, Extend),
+    (0x0A83, 0x0A83, SpacingMark),
+    (0x0ABC, 0x0ABC, Extend),
+    (0x0ABE, 0x0AC0, SpacingMark),
+    (0x0AC1, 0x0AC5, Extend),
+    (0x0AC7, 0x0AC8, Extend),
+    (0x0AC9, 0x0AC9, SpacingMark),
+    (0x0ACB, 0x0ACC, SpacingMark),
+    (0x0ACD, 0x0ACD, Extend),
+    (0x0AE2, 0x0AE3, Extend),
+    (0x0AFA, 0x0AFF, Extend),
+    (0x0B01, 0x0B01, Extend),
+    (0x0B02, 0x0B03, SpacingMark),
+    (0x0B3C, 0x0B3C, Extend),
+    (0x0B3E, 0x0B3F, Extend),
+    (0x0B40, 0x0B40, SpacingMark),
+    (0x0B41, 0x0B44, Extend),
+    (0x0B47, 0x0B48, SpacingMark),
+    (0x0B4B, 0x0B4C, SpacingMark),
+    (0x0B4D, 0x0B4D, Extend),
+    (0x0B55, 0x0B57, Extend),
+    (0x0B62, 0x0B63, Extend),
+    (0x0B82, 0x0B82, Extend),
+    (0x0BBE, 0x0BBE, Extend),
+    (0x0BBF, 0x0BBF, SpacingMark),
+    (0x0BC0, 0x0BC0, Extend),
+    (0x0BC1, 0x0BC2, SpacingMark),
+    (0x0BC6, 0x0BC8, SpacingMark),
+    (0x0BCA, 0x0BCC, SpacingMark),
+    (0x0BCD, 0x0BCD, Extend),
+    (0x0BD7, 0x0BD7, Extend),
+    (0x0C00, 0x0C00, Extend),
+    (0x0C01, 0x0C03, SpacingMark),
+    (0x0C04, 0x0C04, Extend),
+    (0x0C3C, 0x0C3C, Extend),
+    (0x0C3E, 0x0C40, Extend),
+    (0x0C41, 0x0C44, SpacingMark),
+    (0x0C46, 0x0C48, Extend),
+    (0x0C4A, 0x0C4D, Extend),
+    (0x0C55, 0x0C56, Extend),
+    (0x0C62, 0x0C63, Extend),
+    (0x0C81, 0x0C81, Extend),
+    (0x0C82, 0x0C83, SpacingMark),
+    (0x0CBC, 0x0CBC, Extend),
+    (0x0CBE, 0x0CBE, SpacingMark),
+    (0x0CBF, 0x0CBF, Extend),
+    (0x0CC0, 0x0CC1, SpacingMark),
+    (0x0CC2, 0x0CC2, Extend),
+    (0x0CC3, 0x0CC4, SpacingMark),
+    (0x0CC6, 0x0CC6, Extend),
+    (0x0CC7, 0x0CC8, SpacingMark),
+    (0x0CCA, 0x0CCB, SpacingMark),
+    (0x0CCC, 0x0CCD, Extend),
+    (0x0CD5, 0x0CD6, Extend),
+    (0x0CE2, 0x0CE3, Extend),
+    (0x0D00, 0x0D01, Extend),
+    (0x0D02, 0x0D03, SpacingMark),
+    (0x0D3B, 0x0D3C, Extend),
+    (0x0D3E, 0x0D3E, Extend),
+    (0x0D3F, 0x0D40, SpacingMark),
+    (0x0D41, 0x0D44, Extend),
+    (0x0D46, 0x0D48, SpacingMark),
+    (0x0D4A, 0x0D4C, SpacingMark),
+    (0x0D4D, 0x0D4D, Extend),
+    (0x0D4E, 0x0D4E, Prepend),
+    (0x0D57, 0x0D57, Extend),
+    (0x0D62, 0x0D63, Extend),
+    (0x0D81, 0x0D81, Extend),
+    (0x0D82, 0x0D83, SpacingMark),
+    (0x0DCA, 0x0DCA, Extend),
+    (0x0DCF, 0x0DCF, Extend),
+    (0x0DD0, 0x0DD1, SpacingMark),
+    (0x0DD2, 0x0DD4, Extend),
+    (0x0DD6, 0x0DD6, Extend),
+    (0x0DD8, 0x0DDE, SpacingMark),
+    (0x0DDF, 0x0DDF, Extend),
+    (0x0DF2, 0x0DF3, SpacingMark),
+    (0x0E31, 0x0E31, Extend),
+    (0x0E33, 0x0E33, SpacingMark),
+    (0x0E34, 0x0E3A, Extend),
+    (0x0E47, 0x0E4E, Extend),
+    (0x0EB1, 0x0EB1, Extend),
+    (0x0EB3, 0x0EB3, SpacingMark),
+    (0x0EB4, 0x0EBC, Extend),
+    (0x0EC8, 0x0ECD, Extend),
+    (0x0F18, 0x0F19, Extend),
+    (0x0F35, 0x0F35, Extend),
+    (0x0F37, 0x0F37, Extend),
+    (0x0F39, 0x0F39, Extend),
+    (0x0F3E, 0x0F3F, SpacingMark),
+    (0x0F71, 0x0F7E, Extend),
+    (0x0F7F, 0x0F7F, SpacingMark),
+    (0x0F80, 0x0F84, Extend),
+    (0x0F86, 0x0F87, Extend),
+    (0x0F8D, 0x0F97, Extend),
+    (0x0F99, 0x0FBC, Extend),
+    (0x0FC6, 0x0FC6, Extend),
+    (0x102D, 0x1030, Extend),
+    (0x1031, 0x1031, SpacingMark),
+    (0x1032, 0x1037, Extend),
+    (0x1039, 0x103A, Extend),
+    (0x103B, 0x103C, SpacingMark),
+    (0x103D, 0x103E, Extend),
+    (0x1056, 0x1057, SpacingMark),
+    (0x1058, 0x1059, Extend),
+    (0x105E, 0x1060, Extend),
+    (0x1071, 0x1074, Extend),
+    (0x1082, 0x1082, Extend),
+    (0x1084, 0x1084, SpacingMark),
+    (0x1085, 0x1086, Extend),
+    (0x108D, 0x108D, Extend),
+    (0x109D, 0x109D, Extend),
+    (0x1100, 0x115F, L),
+    (0x1160, 0x11A7, V),
+    (0x11A8, 0x11FF, T),
+    (0x135D, 0x135F, Extend),
+    (0x1712, 0x1714, Extend),
+    (0x1715, 0x1715, SpacingMark),
+    (0x1732, 0x1733, Extend),
+    (0x1734, 0x1734, SpacingMark),
+    (0x1752, 0x1753, Extend),
+    (0x1772, 0x1773, Extend),
+    (0x17B4, 0x17B5, Extend),
+    (0x17B6, 0x17B6, SpacingMark),
+    (0x17B7, 0x17BD, Extend),
+    (0x17BE, 0x17C5, SpacingMark),
+    (0x17C6, 0x17C6, Extend),
+    (0x17C7, 0x17C8, SpacingMark),
+    (0x17C9, 0x17D3, Extend),
+    (0x17DD, 0x17DD, Extend),
+    (0x180B, 0x180D, Extend),
+    (0x180E, 0x180E, Control),
+    (0x180F, 0x180F, Extend),
+    (0x1885, 0x1886, Extend),
+    (0x18A9, 0x18A9, Extend),
+    (0x1920, 0x1922, Extend),
+    (0x1923, 0x1926, SpacingMark),
+    (0x1927, 0x1928, Extend),
+    (0x1929, 0x192B, SpacingMark),
+    (0x1930, 0x1931, SpacingMark),
+    (0x1932, 0x1932, Extend),
+    (0x1933, 0x1938, SpacingMark),
+    (0x1939, 0x193B, Extend),
+    (0x1A17, 0x1A18, Extend),
+    (0x1A19, 0x1A1A, SpacingMark),
+    (0x1A1B, 0x1A1B, Extend),
+    (0x1A55, 0x1A55, SpacingMark),
+    (0x1A56, 0x1A56, Extend),
+    (0x1A57, 0x1A57, SpacingMark),
+    (0x1A58, 0x1A5E, Extend),
+    (0x1A60, 0x1A60, Extend),
+    (0x1A62, 0x1A62, Extend),
+    (0x1A65, 0x1A6C, Extend),
+    (0x1A6D, 0x1A72, SpacingMark),
+    (0x1A73, 0x1A7C, Extend),
+    (0x1A7F, 0x1A7F, Extend),
+    (0x1AB0, 0x1ACE, Extend),
+    (0x1B00, 0x1B03, Extend),
+    (0x1B04, 0x1B04, SpacingMark),
+    (0x1B34, 0x1B3A, Extend),
+    (0x1B3B, 0x1B3B, SpacingMark),
+    (0x1B3C, 0x1B3C, Extend),
+    (0x1B3D, 0x1B41, SpacingMark),
+    (0x1B42, 0x1B42, Extend),
+    (0x1B43, 0x1B44, SpacingMark),
+    (0x1B6B, 0x1B73, Extend),
+    (0x1B80, 0x1B81, Extend),
+    (0x1B82, 0x1B82, SpacingMark),
+    (0x1BA1, 0x1BA1, SpacingMark),
+    (0x1BA2, 0x1BA5, Extend),
+    (0x1BA6, 0x1BA7, SpacingMark),
+    (0x1BA8, 0x1BA9, Extend),
+    (0x1BAA, 0x1BAA, SpacingMark),
+    (0x1BAB, 0x1BAD, Extend),
+    (0x1BE6, 0x1BE6, Extend),
+    (0x1BE7, 0x1BE7, SpacingMark),
+    (0x1BE8, 0x1BE9, Extend),
+    (0x1BEA, 0x1BEC, SpacingMark),
+    (0x1BED, 0x1BED, Extend),
+    (0x1BEE, 0x1BEE, SpacingMark),
+    (0x1BEF, 0x1BF1, Extend),
+    (0x1BF2, 0x1BF3, SpacingMark),
+    (0x1C24, 0x1C2B, SpacingMark),
+    (0x1C2C, 0x1C33, Extend),
+    (0x1C34, 0x1C35, SpacingMark),
+    (0x1C36, 0x1C37, Extend),
+    (0x1CD0, 0x1CD2, Extend),
+    (0x1CD4, 0x1CE0, Extend),
+    (0x1CE1, 0x1CE1, SpacingMark),
+    (0x1CE2, 0x1CE8, Extend),
+    (0x1CED, 0x1CED, Extend),
+    (0x1CF4, 0x1CF4, Extend),
+    (0x1CF7, 0x1CF7, SpacingMark),
+    (0x1CF8, 0x1CF9, Extend),
+    (0x1DC0, 0x1DFF, Extend),
+    (0x200B, 0x200B, Control),
+    (0x200C, 0x200C, Extend),
+    (0x200E, 0x200F, Control),
+    (0x2028, 0x202E, Control),
+    (0x203C, 0x203C, ExtendedPictographic),
+    (0x2049, 0x2049, ExtendedPictographic),
+    (0x2060, 0x206F, Control),
+    (0x20D0, 0x20F0, Extend),
+    (0x2122, 0x2122, ExtendedPictographic),
+    (0x2139, 0x2139, ExtendedPictographic),
+    (0x2194, 0x2199, ExtendedPictographic),
+    (0x21A9, 0x21AA, ExtendedPictographic),
+    (0x231A, 0x231B, ExtendedPictographic),
+    (0x2328, 0x2328, ExtendedPictographic),
+    (0x2388, 0x2388, ExtendedPictographic),
+    (0x23CF, 0x23CF, ExtendedPictographic),
+    (0x23E9, 0x23F3, ExtendedPictographic),
+    (0x23F8, 0x23FA, ExtendedPictographic),
+    (0x24C2, 0x24C2, ExtendedPictographic),
+    (0x25AA, 0x25AB, ExtendedPictographic),
+    (0x25B6, 0x25B6, ExtendedPictographic),
+    (0x25C0, 0x25C0, ExtendedPictographic),
+    (0x25FB, 0x25FE, ExtendedPictographic),
+    (0x2600, 0x2605, ExtendedPictographic),
+    (0x2607, 0x2612, ExtendedPictographic),
+    (0x2614, 0x2685, ExtendedPictographic),
+    (0x2690, 0x2705, ExtendedPictographic),
+    (0x2708, 0x2712, ExtendedPictographic),
+    (0x2714, 0x2714, ExtendedPictographic),
+    (0x2716, 0x2716, ExtendedPictographic),
+    (0x271D, 0x271D, ExtendedPictographic),
+    (0x2721, 0x2721, ExtendedPictographic),
+    (0x2728, 0x2728, ExtendedPictographic),
+    (0x2733, 0x2734, ExtendedPictographic),
+    (0x2744, 0x2744, ExtendedPictographic),
+    (0x2747, 0x2747, ExtendedPictographic),
+    (0x274C, 0x274C, ExtendedPictographic),
+    (0x274E, 0x274E, ExtendedPictographic),
+    (0x2753, 0x2755, ExtendedPictographic),
+    (0x2757, 0x2757, ExtendedPictographic),
+    (0x2763, 0x2767, ExtendedPictographic),
+    (0x2795, 0x2797, ExtendedPictographic),
+    (0x27A1, 0x27A1, ExtendedPictographic),
+    (0x27B0, 0x27B0, ExtendedPictographic),
+    (0x27BF, 0x27BF, ExtendedPictographic),
+    (0x2934, 0x2935, ExtendedPictographic),
+    (0x2B05, 0x2B07, ExtendedPictographic),
+    (0x2B1B, 0x2B1C, ExtendedPictographic),
+    (0x2B50, 0x2B50, ExtendedPictographic),
+    (0x2B55, 0x2B55, ExtendedPictographic),
+    (0x2CEF, 0x2CF1, Extend),
+    (0x2D7F, 0x2D7F, Extend),
+    (0x2DE0, 0x2DFF, Extend),
+    (0x302A, 0x302F, Extend),
+    (0x3030, 0x3030, ExtendedPictographic),
+    (0x303D, 0x303D, ExtendedPictographic),
+    (0x3099, 0x309A, Extend),
+    (0x3297, 0x3297, ExtendedPictographic),
+    (0x3299, 0x3299, ExtendedPictographic),
+    (0xA66F, 0xA672, Extend),
+    (0xA674, 0xA67D, Extend),
+    (0xA69E, 0xA69F, Extend),
+    (0xA6F0, 0xA6F1, Extend),
+    (0xA802, 0xA802, Extend),
+    (0xA806, 0xA806, Extend),
+    (0xA80B, 0xA80B, Extend),
+    (0xA823, 0xA824, SpacingMark),
+    (0xA825, 0xA826, Extend),
+    (0xA827, 0xA827, SpacingMark),
+    (0xA82C, 0xA82C, Extend),
+    (0xA880, 0xA881, SpacingMark),
+    (0xA8B4, 0xA8C3, SpacingMark),
+    (0xA8C4, 0xA8C5, Extend),
+    (0xA8E0, 0xA8F1, Extend),
+    (0xA8FF, 0xA8FF, Extend),
+    (0xA926, 0xA92D, Extend),
+    (0xA947, 0xA951, Extend),
+    (0xA952, 0xA953, SpacingMark),
+    (0xA960, 0xA97C, L),
+    (0xA980, 0xA982, Extend),
+    (0xA983, 0xA983, SpacingMark),
+    (0xA9B3, 0xA9B3, Extend),
+    (0xA9B4, 0xA9B5, SpacingMark),
+    (0xA9B6, 0xA9B9, Extend),
+    (0xA9BA, 0xA9BB, SpacingMark),
+    (0xA9BC, 0xA9BD, Extend),
+    (0xA9BE, 0xA9C0, SpacingMark),
+    (0xA9E5, 0xA9E5, Extend),
+    (0xAA29, 0xAA2E, Extend),
+    (0xAA2F, 0xAA30, SpacingMark),
+    (0xAA31, 0xAA32, Extend),
+    (0xAA33, 0xAA34, SpacingMark),
+    (0xAA35, 0xAA36, Extend),
+    (0xAA43, 0xAA43, Extend),
+    (0xAA4C, 0xAA4C, Extend),
+    (0xAA4D, 0xAA4D, SpacingMark),
+    (0xAA7C, 0xAA7C, Extend),
+    (0xAAB0, 0xAAB0, Extend),
+    (0xAAB2, 0xAAB4, Extend),
+    (0xAAB7, 0xAAB8, Extend),
+    (0xAABE, 0xAABF, Extend),
+    (0xAAC1, 0xAAC1, Extend),
+    (0xAAEB, 0xAAEB, SpacingMark),
+    (0xAAEC, 0xAAED, Extend),
+    (0xAAEE, 0xAAEF, SpacingMark),
+    (0xAAF5, 0xAAF5, SpacingMark),
+    (0xAAF6, 0xAAF6, Extend),
+    (0xABE3, 0xABE4, SpacingMark),
+    (0xABE5, 0xABE5, Extend),
+    (0xABE6, 0xABE7, SpacingMark),
+    (0xABE8, 0xABE8, Extend),
+    (0xABE9, 0xABEA, SpacingMark),
+    (0xABEC, 0xABEC, SpacingMark),
+    (0xABED, 0xABED, Extend),
+    (0xD7B0, 0xD7C6, V),
+    (0xD7CB, 0xD7FB, T),
+    (0xFB1E, 0xFB1E, Extend),
+    (0xFE00, 0xFE0F, Extend),
+    (0xFE20, 0xFE2F, Extend),
+    (0xFEFF, 0xFEFF, Control),
+    (0xFF9E, 0xFF9F, Extend),
+    (0xFFF0, 0xFFFB, Control),
+    (0x101FD, 0x101FD, Extend),
+    (0x102E0, 0x102E0, Extend),
+    (0x10376, 0x1037A, Extend),
+    (0x10A01, 0x10A03, Extend),
+    (0x10A05, 0x10A06, Extend),
+    (0x10A0C, 0x10A0F, Extend),
+    (0x10A38, 0x10A3A, Extend),
+    (0x10A3F, 0x10A3F, Extend),
+    (0x10AE5, 0x10AE6, Extend),
+    (0x10D24, 0x10D27, Extend),
+    (0x10EAB, 0x10EAC, Extend),
+    (0x10F46, 0x10F50, Extend),
+    (0x10F82, 0x10F85, Extend),
+    (0x11000, 0x11000, SpacingMark),
+    (0x11001, 0x11001, Extend),
+    (0x11002, 0x11002, SpacingMark),
+    (0x11038, 0x11046, Extend),
+    (0x11070, 0x11070, Extend),
+    (0x11073, 0x11074, Extend),
+    (0x1107F, 0x11081, Extend),
+    (0x11082, 0x11082, SpacingMark),
+    (0x110B0, 0x110B2, SpacingMark),
+    (0x110B3, 0x110B6, Extend),
+    (0x110B7, 0x110B8, SpacingMark),
+    (0x110B9, 0x110BA, Extend),
+    (0x110BD, 0x110BD, Prepend),
+    (0x110C2, 0x110C2, Extend),
+    (0x110CD, 0x110CD, Prepend),
+    (0x11100, 0x11102, Extend),
+    (0x11127, 0x1112B, Extend),
+    (0x1112C, 0x1112C, SpacingMark),
+    (0x1112D, 0x11134, Extend),
+    (0x11145, 0x11146, SpacingMark),
+    (0x11173, 0x11173, Extend),
+    (0x11180, 0x11181, Extend),
+    (0x11182, 0x11182, SpacingMark),
+    (0x111B3, 0x111B5, SpacingMark),
+    (0x111B6, 0x111BE, Extend),
+    (0x111BF, 0x111C0, SpacingMark),
+    (0x111C2, 0x111C3, Prepend),
+    (0x111C9, 0x111CC, Extend),
+    (0x111CE, 0x111CE, SpacingMark),
+    (0x111CF, 0x111CF, Extend),
+    (0x1122C, 0x1122E, SpacingMark),
+    (0x1122F, 0x11231, Extend),
+    (0x11232, 0x11233, SpacingMark),
+    (0x11234, 0x11234, Extend),
+    (0x11235, 0x11235, SpacingMark),
+    (0x11236, 0x11237, Extend),
+    (0x1123E, 0x1123E, Extend),
+    (0x112DF, 0x112DF, Extend),
+    (0x112E0, 0x112E2, SpacingMark),
+    (0x112E3, 0x112EA, Extend),
+    (0x11300, 0x11301, Extend),
+    (0x11302, 0x11303, SpacingMark),
+    (0x1133B, 0x1133C, Extend),
+    (0x1133E, 0x1133E, Extend),
+    (0x1133F, 0x1133F, SpacingMark),
+    (0x11340, 0x11340, Extend),
+    (0x11341, 0x11344, SpacingMark),
+    (0x11347, 0x11348, SpacingMark),
+    (0x1134B, 0x1134D, SpacingMark),
+    (0x11357, 0x11357, Extend),
+    (0x11362, 0x11363, SpacingMark),
+    (0x11366, 0x1136C, Extend),
+    (0x11370, 0x11374, Extend),
+    (0x11435, 0x11437, SpacingMark),
+    (0x11438, 0x1143F, Extend),
+    (0x11440, 0x11441, SpacingMark),
+    (0x11442, 0x11444, Extend),
+    (0x11445, 0x11445, SpacingMark),
+    (0x11446, 0x11446, Extend),
+    (0x1145E, 0x1145E, Extend),
+    (0x114B0, 0x114B0, Extend),
+    (0x114B1, 0x114B2, SpacingMark),
+    (0x114B3, 0x114B8, Extend),
+    (0x114B9, 0x114B9, SpacingMark),
+    (0x114BA, 0x114BA, Extend),
+    (0x114BB, 0x114BC, SpacingMark),
+    (0x114BD, 0x114BD, Extend),
+    (0x114BE, 0x114BE, SpacingMark),
+    (0x114BF, 0x114C0, Extend),
+    (0x114C1, 0x114C1, SpacingMark),
+    (0x114C2, 0x114C3, Extend),
+    (0x115AF, 0x115AF, Extend),
+    (0x115B0, 0x115B1, SpacingMark),
+    (0x115B2, 0x115B5, Extend),
+    (0x115B8, 0x115BB, SpacingMark),
+    (0x115BC, 0x115BD, Extend),
+    (0x115BE, 0x115BE, SpacingMark),
+    (0x115BF, 0x115C0, Extend),
+    (0x115DC, 0x115DD, Extend),
+    (0x11630, 0x11632, SpacingMark),
+    (0x11633, 0x1163A, Extend),
+    (0x1163B, 0x1163C, SpacingMark),
+    (0x1163D, 0x1163D, Extend),
+    (0x1163E, 0x1163E, SpacingMark),
+    (0x1163F, 0x11640, Extend),
+    (0x116AB, 0x116AB, Extend),
+    (0x116AC, 0x116AC, SpacingMark),
+    (0x116AD, 0x116AD, Extend),
+    (0x116AE, 0x116AF, SpacingMark),
+    (0x116B0, 0x116B5, Extend),
+    (0x116B6, 0x116B6, SpacingMark),
+    (0x116B7, 0x116B7, Extend),
+    (0x1171D, 0x1171F, Extend),
+    (0x11722, 0x11725, Extend),
+    (0x11726, 0x11726, SpacingMark),
+    (0x11727, 0x1172B, Extend),
+    (0x1182C, 0x1182E, SpacingMark),
+    (0x1182F, 0x11837, Extend),
+    (0x11838, 0x11838, SpacingMark),
+    (0x11839, 0x1183A, Extend),
+    (0x11930, 0x11930, Extend),
+    (0x11931, 0x11935, SpacingMark),
+    (0x11937, 0x11938, SpacingMark),
+    (0x1193B, 0x1193C, Extend),
+    (0x1193D, 0x1193D, SpacingMark),
+    (0x1193E, 0x1193E, Extend),
+    (0x1193F, 0x1193F, Prepend),
+    (0x11940, 0x11940, SpacingMark),
+    (0x11941, 0x11941, Prepend),
+    (0x11942, 0x11942, SpacingMark),
+    (0x11943, 0x11943, Extend),
+    (0x119D1, 0x119D3, SpacingMark),
+    (0x119D4, 0x119D7, Extend),
+    (0x119DA, 0x119DB, Extend),
+    (0x119DC, 0x119DF, SpacingMark),
+    (0x119E0, 0x119E0, Extend),
+    (0x119E4, 0x119E4, SpacingMark),
+    (0x11A01, 0x11A0A, Extend),
+    (0x11A33, 0x11A38, Extend),
+    (0x11A39, 0x11A39, SpacingMark),
+    (0x11A3A, 0x11A3A, Prepend),
+    (0x11A3B, 0x11A3E, Extend),
+    (0x11A47, 0x11A47, Extend),
+    (0x11A51, 0x11A56, Extend),
+    (0x11A57, 0x11A58, SpacingMark),
+    (0x11A59, 0x11A5B, Extend),
+    (0x11A84, 0x11A89, Prepend),
+    (0x11A8A, 0x11A96, Extend),
+    (0x11A97, 0x11A97, SpacingMark),
+    (0x11A98, 0x11A99, Extend),
+    (0x11C2F, 0x11C2F, SpacingMark),
+    (0x11C30, 0x11C36, Extend),
+    (0x11C38, 0x11C3D, Extend),
+    (0x11C3E, 0x11C3E, SpacingMark),
+    (0x11C3F, 0x11C3F, Extend),
+    (0x11C92, 0x11CA7, Extend),
+    (0x11CA9, 0x11CA9, SpacingMark),
+    (0x11CAA, 0x11CB0, Extend),
+    (0x11CB1, 0x11CB1, SpacingMark),
+    (0x11CB2, 0x11CB3, Extend),
+    (0x11CB4, 0x11CB4, SpacingMark),
+    (0x11CB5, 0x11CB6, Extend),
+    (0x11D31, 0x11D36, Extend),
+    (0x11D3A, 0x11D3A, Extend),
+    (0x11D3C, 0x11D3D, Extend),
+    (0x11D3F, 0x11D45, Extend),
+    (0x11D46, 0x11D46, Prepend),
+    (0x11D47, 0x11D47, Extend),
+    (0x11D8A, 0x11D8E, SpacingMark),
+    (0x11D90, 0x11D91, Extend),
+    (0x11D93, 0x11D94, SpacingMark),
+    (0x11D95, 0x11D95, Extend),
+    (0x11D96, 0x11D96, SpacingMark),
+    (0x11D97, 0x11D97, Extend),
+    (0x11EF3, 0x11EF4, Extend),
+    (0x11EF5, 0x11EF6, SpacingMark),
+    (0x13430, 0x13438, Control),
+    (0x16AF0, 0x16AF4, Extend),
+    (0x16B30, 0x16B36, Extend),
+    (0x16F4F, 0x16F4F, Extend),
+    (0x16F51, 0x16F87, SpacingMark),
+    (0x16F8F, 0x16F92, Extend),
+    (0x16FE4, 0x16FE4, Extend),
+    (0x16FF0, 0x16FF1, SpacingMark),
+    (0x1BC9D, 0x1BC9E, Extend),
+    (0x1BCA0, 0x1BCA3, Control),
+    (0x1CF00, 0x1CF2D, Extend),
+    (0x1CF30, 0x1CF46, Extend),
+    (0x1D165, 0x1D165, Extend),
+    (0x1D166, 0x1D166, SpacingMark),
+    (0x1D167, 0x1D169, Extend),
+    (0x1D16D, 0x1D16D, SpacingMark),
+    (0x1D16E, 0x1D172, Extend),
+    (0x1D173, 0x1D17A, Control),
+    (0x1D17B, 0x1D182, Extend),
+    (0x1D185, 0x1D18B, Extend),
+    (0x1D1AA, 0x1D1AD, Extend),
+    (0x1D242, 0x1D244, Extend),
+    (0x1DA00, 0x1DA36, Extend),
+    (0x1DA3B, 0x1DA6C, Extend),
+    (0x1DA75, 0x1DA75, Extend),
+    (0x1DA84, 0x1DA84, Extend),
+    (0x1DA9B, 0x1DA9F, Extend),
+    (0x1DAA1, 0x1DAAF, Extend),
+    (0x1E000, 0x1E006, Extend),
+    (0x1E008, 0x1E018, Extend),
+    (0x1E01B, 0x1E021, Extend),
+    (0x1E023, 0x1E024, Extend),
+    (0x1E026, 0x1E02A, Extend),
+    (0x1E130, 0x1E136, Extend),
+    (0x1E2AE, 0x1E2AE, Extend),
+    (0x1E2EC, 0x1E2EF, Extend),
+    (0x1E8D0, 0x1E8D6, Extend),
+    (0x1E944, 0x1E94A, Extend),
+    (0x1F000, 0x1F0FF, ExtendedPictographic),
+    (0x1F10D, 0x1F10F, ExtendedPictographic),
+    (0x1F12F, 0x1F12F, ExtendedPictographic),
+    (0x1F16C, 0x1F171, ExtendedPictographic),
+    (0x1F17E, 0x1F17F, ExtendedPictographic),
+    (0x1F18E, 0x1F18E, ExtendedPictographic),
+    (0x1F191, 0x1F19A, ExtendedPictographic),
+    (0x1F1AD, 0x1F1E5, ExtendedPictographic),
+    (0x1F201, 0x1F20F, ExtendedPictographic),
+    (0x1F21A, 0x1F21A, ExtendedPictographic),
+    (0x1F22F, 0x1F22F, ExtendedPictographic),
+    (0x1F232, 0x1F23A, ExtendedPictographic),
+    (0x1F23C, 0x1F23F, ExtendedPictographic),
+    (0x1F249, 0x1F3FA, ExtendedPictographic),
+    (0x1F3FB, 0x1F3FF, Extend),
+    (0x1F400, 0x1F53D, ExtendedPictographic),
+    (0x1F546, 0x1F64F, ExtendedPictographic),
+    (0x1F680, 0x1F6FF, ExtendedPictographic),
+    (0x1F774, 0x1F77F, ExtendedPictographic),
+    (0x1F7D5, 0x1F7FF, ExtendedPictographic),
+    (0x1F80C, 0x1F80F, ExtendedPictographic),
+    (0x1F848, 0x1F84F, ExtendedPictographic),
+    (0x1F85A, 0x1F85F, ExtendedPictographic),
+    (0x1F888, 0x1F88F, ExtendedPictographic),
+    (0x1F8AE, 0x1F8FF, ExtendedPictographic),
+    (0x1F90C, 0x1F93A, ExtendedPictographic),
+    (0x1F93C, 0x1F945, ExtendedPictographic),
+    (0x1F947, 0x1FAFF, ExtendedPictographic),
+    (0x1FC00, 0x1FFFD, ExtendedPictographic),
+    (0xE0000, 0xE001F, Control),
+    (0xE0020, 0xE007F, Extend),
+    (0xE0080, 0xE00FF, Control),
+    (0xE0100, 0xE01EF, Extend),
+    (0xE01F0, 0xE0FFF, Control),
+];
@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::composite::component_indices;
+use crate::font::Font;
+use crate::hinting::read_u16_at;
+use crate::write::table_checksum;
+
+/// How far (in font units) a glyph's declared bounding box may be off from its points.
+const BBOX_TOLERANCE: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Suspicious but renderers cope with it
+    Warning,
+    /// The font is broken, some renderers will reject it or draw garbage
+    Error,
+}
+
+/// Where an issue was found: a table, and the glyph within it when there is one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueLocation {
+    pub table: String,
+    pub glyph: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub location: IssueLocation,
+    pub message: String,
+}
+
+/// Structural problems found by `Font::validate`.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    fn push(&mut self, severity: Severity, table: &str, glyph: Option<u16>, message: String) {
+        self.issues.push(ValidationIssue {
+            severity,
+            location: IssueLocation {
+                table: table.to_string(),
+                glyph,
+            },
+            message,
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Warning)
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.location.glyph {
+            Some(glyph) => write!(f, "{severity}: [{} glyph {glyph}] {}", self.location.table, self.message),
+            None => write!(f, "{severity}: [{}] {}", self.location.table, self.message),
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+        write!(
+            f,
+            "{} errors, {} warnings",
+            self.errors().count(),
+            self.warnings().count()
+        )
+    }
+}
+
+impl Font {
+    /// Check the font for structural problems: missing tables, table checksums, loca, glyph
+    /// bounding boxes, composite references, hmtx and cmap. Never fails, everything found goes in the report.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let num_glyphs = self.num_glyphs();
+
+        let mut checksums: Vec<(&str, u32)> = self.table_checksums().collect();
+        checksums.sort();
+        for (tag, recorded) in checksums {
+            let Some(data) = self.table_data(tag) else {
+                report.push(Severity::Error, tag, None, "table extends past the end of the file".to_string());
+                continue;
+            };
+            let mut computed = table_checksum(data);
+            if tag == "head" && data.len() >= 12 {
+                // checkSumAdjustment is left out of the head checksum
+                computed = computed.wrapping_sub(u32::from_be_bytes([data[8], data[9], data[10], data[11]]));
+            }
+            if computed != recorded {
+                report.push(
+                    Severity::Warning,
+                    tag,
+                    None,
+                    format!("checksum mismatch: recorded {recorded:#010x}, computed {computed:#010x}"),
+                );
+            }
+        }
+
+        for (tag, offset, length) in self.duplicate_tables() {
+            report.push(
+                Severity::Error,
+                tag,
+                None,
+                format!("duplicate table directory entry (offset {offset}, length {length}) ignored"),
+            );
+        }
+
+        for (tag, assumption) in self.assumptions() {
+            report.push(Severity::Error, tag, None, format!("required table missing, assumed {assumption}"));
+        }
+
+        let glyf = self.table_data("glyf").unwrap_or_default();
+        let mut glyph_ranges = vec![None; num_glyphs as usize];
+        if let Some(loca) = self.table_data("loca") {
+            let short = !self.long_loca();
+            let entry = |i: usize| {
+                if short {
+                    read_u16_at(loca, i * 2) as usize * 2
+                } else {
+                    loca.get(i * 4..i * 4 + 4)
+                        .map_or(0, |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+                }
+            };
+            let entries = num_glyphs as usize + 1;
+            let loca_entries = loca.len() / if short { 2 } else { 4 };
+            let declared = self.declared_num_glyphs();
+            if declared != num_glyphs {
+                report.push(
+                    Severity::Error,
+                    "maxp",
+                    None,
+                    format!("numGlyphs is {declared} but loca only has {loca_entries} entries, {num_glyphs} glyphs used"),
+                );
+            } else if loca_entries < entries {
+                report.push(Severity::Error, "loca", None, format!("too short for {entries} entries"));
+            } else if loca_entries > entries {
+                report.push(
+                    Severity::Warning,
+                    "loca",
+                    None,
+                    format!("{loca_entries} entries for {num_glyphs} glyphs, the extra ones are ignored"),
+                );
+            }
+            for (i, range) in glyph_ranges.iter_mut().enumerate() {
+                let (start, end) = (entry(i), entry(i + 1));
+                if end < start {
+                    report.push(
+                        Severity::Error,
+                        "loca",
+                        Some(i as u16),
+                        format!("offsets decrease from {start} to {end}"),
+                    );
+                } else if end > glyf.len() {
+                    report.push(
+                        Severity::Error,
+                        "loca",
+                        Some(i as u16),
+                        format!("glyph data ends at {end}, past the end of glyf ({} bytes)", glyf.len()),
+                    );
+                } else {
+                    *range = Some(start..end);
+                }
+            }
+        }
+
+        let mut components: Vec<Vec<u16>> = vec![Vec::new(); num_glyphs as usize];
+        for index in 0..num_glyphs {
+            let Some(range) = glyph_ranges[index as usize].clone().filter(|range| !range.is_empty()) else {
+                continue;
+            };
+            let data = &glyf[range];
+            let num_contours = read_u16_at(data, 0) as i16;
+            if num_contours < 0 {
+                match component_indices(data) {
+                    Some(indices) => {
+                        for component in &indices {
+                            if *component >= num_glyphs {
+                                report.push(
+                                    Severity::Error,
+                                    "glyf",
+                                    Some(index),
+                                    format!("component references glyph {component}, the font has {num_glyphs}"),
+                                );
+                            }
+                        }
+                        components[index as usize] = indices;
+                    }
+                    None => report.push(
+                        Severity::Error,
+                        "glyf",
+                        Some(index),
+                        "component records run past the glyph data".to_string(),
+                    ),
+                }
+                continue;
+            }
+            let Some(glyph) = self.glyph(index).filter(|glyph| glyph.num_points() > 0) else {
+                continue;
+            };
+            let x = glyph.x_coords.iter().map(|x| *x as i32);
+            let y = glyph.y_coords.iter().map(|y| *y as i32);
+            let computed = (
+                x.clone().min().unwrap(),
+                y.clone().min().unwrap(),
+                x.max().unwrap(),
+                y.max().unwrap(),
+            );
+            let declared = (glyph.x_min as i32, glyph.y_min as i32, glyph.x_max as i32, glyph.y_max as i32);
+            let off = [
+                computed.0 - declared.0,
+                computed.1 - declared.1,
+                computed.2 - declared.2,
+                computed.3 - declared.3,
+            ];
+            if off.iter().any(|d| d.abs() > BBOX_TOLERANCE) {
+                report.push(
+                    Severity::Warning,
+                    "glyf",
+                    Some(index),
+                    format!("declared bbox {declared:?} but the points span {computed:?}"),
+                );
+            }
+        }
+
+        // a composite that reaches itself through its components would recurse forever
+        let mut in_cycle = HashSet::new();
+        for index in 0..num_glyphs {
+            if components[index as usize].is_empty() || in_cycle.contains(&index) {
+                continue;
+            }
+            let mut stack: Vec<(u16, usize)> = vec![(index, 0)];
+            let mut path = vec![index];
+            while let Some((glyph, next)) = stack.last_mut() {
+                let Some(component) = components[*glyph as usize].get(*next).copied() else {
+                    stack.pop();
+                    path.pop();
+                    continue;
+                };
+                *next += 1;
+                if component >= num_glyphs {
+                    continue;
+                }
+                if component == index {
+                    in_cycle.extend(path.iter().copied());
+                    let cycle: Vec<String> = path.iter().chain([&component]).map(|g| g.to_string()).collect();
+                    report.push(
+                        Severity::Error,
+                        "glyf",
+                        Some(index),
+                        format!("composite cycle {}", cycle.join(" -> ")),
+                    );
+                    break;
+                }
+                if !path.contains(&component) {
+                    stack.push((component, 0));
+                    path.push(component);
+                }
+            }
+        }
+
+        match (self.table_data("hhea"), self.table_data("hmtx")) {
+            (Some(hhea), Some(hmtx)) => {
+                let num_h_metrics = read_u16_at(hhea, 34) as usize;
+                let expected = num_h_metrics * 4 + (num_glyphs as usize).saturating_sub(num_h_metrics) * 2;
+                if num_h_metrics == 0 || num_h_metrics > num_glyphs as usize {
+                    report.push(
+                        Severity::Error,
+                        "hhea",
+                        None,
+                        format!("numberOfHMetrics is {num_h_metrics} for {num_glyphs} glyphs"),
+                    );
+                }
+                if hmtx.len() < expected {
+                    report.push(
+                        Severity::Error,
+                        "hmtx",
+                        None,
+                        format!("{} bytes, {num_glyphs} glyphs need {expected}", hmtx.len()),
+                    );
+                }
+            }
+            _ => report.push(Severity::Error, "hmtx", None, "hhea or hmtx table missing".to_string()),
+        }
+
+        if let Some(cmap) = self.cmap() {
+            for (glyph, chars) in cmap.out_of_range_mappings() {
+                let first = chars.first().map_or(0, |c| *c as u32);
+                report.push(
+                    Severity::Error,
+                    "cmap",
+                    u16::try_from(glyph).ok(),
+                    format!("{} characters from U+{first:04X} map to glyph {glyph}, past the last one", chars.len()),
+                );
+            }
+        }
+
+        report
+    }
+}
@@ -0,0 +1,153 @@
+//! Vertical text, top to bottom in a column: CJK characters stand upright, in the vertical forms
+//! of the GSUB `vrt2` (or `vert`) feature, one vmtx advance after the other and centered on the
+//! column. Other text (Latin, digits and the like) is laid out as usual and turned 90°
+//! clockwise, its baseline running down the column.
+
+use raqote::{DrawOptions, DrawTarget, SolidSource, Source};
+
+use crate::font::Font;
+use crate::render::{contours_to_path_with, layout_line};
+use crate::transform::FontUnitsToPixels;
+
+/// A glyph of a vertical line, drawn through its own transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerticalGlyph {
+    pub index: u16,
+    /// Byte offset in the text of the character the glyph was made for
+    pub cluster: usize,
+    /// Font units to draw target pixels, [xx, yx, xy, yy, dx, dy] as `Outline::transform` takes
+    pub transform: [f32; 6],
+    /// How far down the glyph moves the pen, in pixels
+    pub advance: f32,
+    /// Turned 90° clockwise rather than upright
+    pub rotated: bool,
+}
+
+/// Whether a character stands upright in vertical text, a rough cut of the Vertical_Orientation
+/// property (UAX #50): the CJK blocks with their punctuation and fullwidth forms, symbols and
+/// emoji.
+pub fn is_upright(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11FF
+            | 0x2600..=0x26FF
+            | 0x2E80..=0x2FFF
+            | 0x3000..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA960..=0xA97F
+            | 0xAC00..=0xD7FF
+            | 0xF900..=0xFAFF
+            | 0xFE10..=0xFE1F
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F000..=0x1FAFF
+            | 0x20000..=0x3FFFF
+    )
+}
+
+impl Font {
+    /// The glyph standing for `index` in vertical text, its `vrt2` form or else its `vert` one.
+    pub fn vertical_form(&self, index: u16) -> u16 {
+        let Some(gsub) = self.gsub() else {
+            return index;
+        };
+        match gsub.has_feature(b"vrt2") {
+            true => gsub.substitute(b"vrt2", index),
+            false => gsub.substitute(b"vert", index),
+        }
+    }
+
+    /// Where the top of a glyph standing upright sits above its horizontal origin, in font
+    /// units: the top side bearing above its box, or the ascender for fonts without vmtx.
+    fn vertical_origin(&self, index: u16) -> f32 {
+        match (self.top_side_bearing(index), self.glyph(index)) {
+            (Some(tsb), Some(glyph)) => glyph.y_max as f32 + tsb as f32,
+            _ => self.ascender() as f32,
+        }
+    }
+
+    /// The vmtx advance, or the distance from the ascender to the descender without vmtx.
+    fn vertical_advance(&self, index: u16) -> f32 {
+        match self.advance_height(index) {
+            Some(advance) => advance as f32,
+            None => (self.ascender() as i32 - self.descender() as i32) as f32,
+        }
+    }
+
+    /// Lay out one line of text top to bottom in a column centered on `origin.0`, starting at
+    /// `origin.1`. Upright glyphs are centered on the column, turned ones have the middle of
+    /// the ascender and descender on it. Returns the glyphs and the pen y after the last one.
+    pub fn layout_vertical(&self, text: &str, origin: (f32, f32), px_size: f32) -> (Vec<VerticalGlyph>, f32) {
+        let scale = self.scale_for_px(px_size);
+        let middle = (self.ascender() as f32 + self.descender() as f32) / 2.;
+        let mut glyphs = Vec::new();
+        let mut y = origin.1;
+        let mut start = 0;
+        while let Some(first) = text[start..].chars().next() {
+            let upright = is_upright(first);
+            let end = text[start..]
+                .char_indices()
+                .find(|(_, c)| is_upright(*c) != upright)
+                .map_or(text.len(), |(at, _)| start + at);
+            let run = &text[start..end];
+            if upright {
+                for (cluster, index) in self.glyph_clusters(run) {
+                    let index = self.vertical_form(index);
+                    let advance = self.vertical_advance(index) * scale;
+                    let position = (
+                        origin.0 - self.advance_width(index) as f32 * scale / 2.,
+                        y + self.vertical_origin(index) * scale,
+                    );
+                    glyphs.push(VerticalGlyph {
+                        index,
+                        cluster: start + cluster,
+                        transform: FontUnitsToPixels::with_scale(scale, position).matrix(),
+                        advance,
+                        rotated: false,
+                    });
+                    y += advance;
+                }
+            } else {
+                // laid out on a horizontal baseline from (0, 0), then turned: right becomes
+                // down and up becomes right
+                let (positioned, width) = layout_line(self, run, (0., 0.), px_size, None);
+                for glyph in positioned {
+                    let (dx, dy) = (origin.0 - middle * scale - glyph.position.1, y + glyph.position.0);
+                    glyphs.push(VerticalGlyph {
+                        index: glyph.index,
+                        cluster: start + glyph.cluster,
+                        transform: [0., scale, scale, 0., dx, dy],
+                        advance: glyph.advance,
+                        rotated: true,
+                    });
+                }
+                y += width;
+            }
+            start = end;
+        }
+        (glyphs, y)
+    }
+
+    /// Lay out and fill a vertical line like `layout_vertical`. Returns the pen y after it.
+    pub fn draw_text_vertical(
+        &self,
+        dt: &mut DrawTarget,
+        text: &str,
+        origin: (f32, f32),
+        px_size: f32,
+        color: SolidSource,
+    ) -> f32 {
+        let (glyphs, end_y) = self.layout_vertical(text, origin, px_size);
+        for glyph in glyphs {
+            let Some(outline) = self.drawn_outline(glyph.index) else {
+                continue;
+            };
+            let [xx, yx, xy, yy, dx, dy] = glyph.transform;
+            let path = contours_to_path_with(&outline.segments(), |(x, y)| (xx * x + xy * y + dx, yx * x + yy * y + dy));
+            dt.fill(&path, &Source::Solid(color), &DrawOptions::new());
+        }
+        end_y
+    }
+}
@@ -0,0 +1,70 @@
+use crate::flatten::Contour;
+use crate::glyph::GlyphData;
+
+/// Flattening tolerance used for the containment tests, one font unit is far below anything visible.
+const WINDING_TOLERANCE_FONT_UNITS: f32 = 1.0;
+
+impl Contour {
+    /// Shoelace area of the polyline in font units (y up): positive when counter-clockwise.
+    pub fn signed_area(&self) -> f32 {
+        self.edges().map(|(p0, p1)| p0.0 * p1.1 - p1.0 * p0.1).sum::<f32>() / 2.
+    }
+
+    /// TrueType wants outer contours clockwise and holes counter-clockwise.
+    pub fn is_clockwise(&self) -> bool {
+        self.signed_area() < 0.
+    }
+
+    /// Even-odd point in polygon test.
+    pub fn contains_point(&self, point: (f32, f32)) -> bool {
+        let mut inside = false;
+        for (p0, p1) in self.edges() {
+            if (p0.1 > point.1) != (p1.1 > point.1) {
+                let x = p0.0 + (point.1 - p0.1) / (p1.1 - p0.1) * (p1.0 - p0.0);
+                if x > point.0 {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+impl GlyphData {
+    /// Re-orient the contours so that outer contours run clockwise and holes (contours nested an odd
+    /// number of times inside others) run counter-clockwise. Returns whether anything changed.
+    pub fn fix_winding(&mut self) -> bool {
+        if !self.is_simple || self.num_contours() == 0 {
+            return false;
+        }
+        let contours = self.flatten(WINDING_TOLERANCE_FONT_UNITS);
+        // flatten skips empty contours, keep the raw ranges lined up with the polylines
+        let ranges: Vec<_> = self.contour_ranges().filter(|range| !range.is_empty()).collect();
+        if ranges.len() != contours.len() {
+            return false;
+        }
+
+        let mut changed = false;
+        for (i, contour) in contours.iter().enumerate() {
+            let area = contour.signed_area();
+            if area == 0. {
+                continue; // degenerate, there is no direction to fix
+            }
+            let probe = contour.points[0];
+            let depth = contours
+                .iter()
+                .enumerate()
+                .filter(|(j, other)| *j != i && other.signed_area().abs() > area.abs() && other.contains_point(probe))
+                .count();
+            let should_be_clockwise = depth % 2 == 0;
+            if contour.is_clockwise() != should_be_clockwise {
+                let range = ranges[i].clone();
+                self.x_coords[range.clone()].reverse();
+                self.y_coords[range.clone()].reverse();
+                self.on_curve[range].reverse();
+                changed = true;
+            }
+        }
+        changed
+    }
+}
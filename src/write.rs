@@ -0,0 +1,603 @@
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::cmap::mac_roman_byte;
+use crate::font::Font;
+use crate::glyph::GlyphData;
+use crate::hinting::read_u16_at;
+use crate::outline::Outline;
+
+/// sfnt version of fonts with TrueType outlines
+const TRUETYPE_VERSION: u32 = 0x0001_0000;
+/// The whole file checksums to this once head.checkSumAdjustment is set
+const CHECKSUM_MAGIC: u32 = 0xB1B0_AFBA;
+const HEAD_CHECKSUM_ADJUSTMENT: usize = 8;
+const HEAD_X_MIN: usize = 36;
+const HEAD_INDEX_TO_LOC_FORMAT: usize = 50;
+const MAXP_NUM_GLYPHS: usize = 4;
+const MAXP_MAX_POINTS: usize = 6;
+const MAXP_MAX_CONTOURS: usize = 8;
+const HHEA_ADVANCE_WIDTH_MAX: usize = 10;
+const HHEA_MIN_LEFT_SIDE_BEARING: usize = 12;
+const HHEA_MIN_RIGHT_SIDE_BEARING: usize = 14;
+const HHEA_X_MAX_EXTENT: usize = 16;
+const HHEA_NUMBER_OF_H_METRICS: usize = 34;
+
+/// Sum of the table as big endian u32s, zero padded to a multiple of 4 bytes.
+pub(crate) fn table_checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Overwrite a big endian u16 field of a table, failing when the table is too short to have it.
+pub(crate) fn set_u16(table: &mut [u8], at: usize, value: u16, tag: &str) -> Result<()> {
+    let field = table
+        .get_mut(at..at + 2)
+        .ok_or_else(|| anyhow!("{tag} is too short to set the field at {at}"))?;
+    field.copy_from_slice(&value.to_be_bytes());
+    Ok(())
+}
+
+/// x_min, y_min, x_max, y_max
+type Bounds = (i16, i16, i16, i16);
+
+/// The bounding box from the header of a glyph record, `None` for an empty one.
+fn record_bounds(glyph: &[u8]) -> Option<Bounds> {
+    let field = |at: usize| i16::from_be_bytes([glyph[at], glyph[at + 1]]);
+    (glyph.len() >= 10).then(|| (field(2), field(4), field(6), field(8)))
+}
+
+/// Assembles an sfnt file from tables: kept as they are in a parsed font (borrowed, not copied)
+/// or regenerated. `build` lays out the table directory and the padded tables and fills in the
+/// checksums, the subsetter and the instancer make their fonts with it.
+#[derive(Debug, Clone)]
+pub struct FontBuilder<'a> {
+    sfnt_version: u32,
+    /// By tag, the directory has to be sorted
+    tables: BTreeMap<String, Cow<'a, [u8]>>,
+}
+
+impl Default for FontBuilder<'_> {
+    fn default() -> Self {
+        FontBuilder::new()
+    }
+}
+
+impl<'a> FontBuilder<'a> {
+    /// An empty TrueType font.
+    pub fn new() -> FontBuilder<'a> {
+        FontBuilder {
+            sfnt_version: TRUETYPE_VERSION,
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Every table of `font` passed through as is.
+    pub fn from_font(font: &'a Font) -> FontBuilder<'a> {
+        let sfnt_version = font
+            .file_data()
+            .get(..4)
+            .map_or(TRUETYPE_VERSION, |bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        let tables = font
+            .table_tags()
+            .into_iter()
+            .filter_map(|tag| Some((tag.to_string(), Cow::Borrowed(font.table_data(tag)?))))
+            .collect();
+        FontBuilder { sfnt_version, tables }
+    }
+
+    /// Add a table or replace the one with the same tag.
+    pub fn add_table(&mut self, tag: &str, data: impl Into<Cow<'a, [u8]>>) -> &mut Self {
+        self.tables.insert(tag.to_string(), data.into());
+        self
+    }
+
+    pub fn remove_table(&mut self, tag: &str) -> &mut Self {
+        self.tables.remove(tag);
+        self
+    }
+
+    pub fn table(&self, tag: &str) -> Option<&[u8]> {
+        self.tables.get(tag).map(|data| data.as_ref())
+    }
+
+    /// A table to modify in place, copied first when it's still borrowed from the font.
+    pub fn table_mut(&mut self, tag: &str) -> Option<&mut Vec<u8>> {
+        self.tables.get_mut(tag).map(|data| data.to_mut())
+    }
+
+    /// Regenerate glyf and loca from the glyph records (in glyph index order, empty for glyphs
+    /// without an outline), picking the short loca format when the offsets fit. head's
+    /// indexToLocFormat and bounding box and maxp's glyph count follow.
+    pub fn set_glyphs(&mut self, glyphs: &[&[u8]]) -> Result<&mut Self> {
+        let mut glyf = Vec::new();
+        let mut offsets = Vec::with_capacity(glyphs.len() + 1);
+        for glyph in glyphs {
+            offsets.push(glyf.len() as u32);
+            glyf.extend_from_slice(glyph);
+            // glyph records are 4 byte aligned, short offsets need them at least even
+            glyf.resize(glyf.len().next_multiple_of(4), 0);
+        }
+        offsets.push(glyf.len() as u32);
+        let short = glyf.len() / 2 <= u16::MAX as usize;
+        let loca: Vec<u8> = if short {
+            offsets.iter().flat_map(|offset| ((offset / 2) as u16).to_be_bytes()).collect()
+        } else {
+            offsets.iter().flat_map(|offset| offset.to_be_bytes()).collect()
+        };
+        let num_glyphs = u16::try_from(glyphs.len()).map_err(|_| anyhow!("{} glyphs don't fit in maxp", glyphs.len()))?;
+        let head = self.table_mut("head").ok_or_else(|| anyhow!("No head table to record the loca format in"))?;
+        set_u16(head, HEAD_INDEX_TO_LOC_FORMAT, if short { 0 } else { 1 }, "head")?;
+        let bounds = glyphs.iter().filter_map(|glyph| record_bounds(glyph)).reduce(|a, b| {
+            (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))
+        });
+        let (x_min, y_min, x_max, y_max) = bounds.unwrap_or_default();
+        for (i, value) in [x_min, y_min, x_max, y_max].into_iter().enumerate() {
+            set_u16(head, HEAD_X_MIN + i * 2, value as u16, "head")?;
+        }
+        if let Some(maxp) = self.table_mut("maxp") {
+            set_u16(maxp, MAXP_NUM_GLYPHS, num_glyphs, "maxp")?;
+        }
+        self.add_table("glyf", glyf).add_table("loca", loca);
+        Ok(self)
+    }
+
+    /// Regenerate hmtx from (advance width, left side bearing) for every glyph. Trailing glyphs
+    /// with the advance of the one before them share its metric. hhea's count and maximum advance
+    /// follow, and its side bearing and extent extremes when the glyphs are there to measure.
+    pub fn set_horizontal_metrics(&mut self, metrics: &[(u16, i16)]) -> Result<&mut Self> {
+        let mut long_count = metrics.len();
+        while long_count > 1 && metrics[long_count - 1].0 == metrics[long_count - 2].0 {
+            long_count -= 1;
+        }
+        let mut hmtx = Vec::with_capacity(metrics.len() * 4);
+        for (i, (advance, lsb)) in metrics.iter().enumerate() {
+            if i < long_count {
+                hmtx.extend_from_slice(&advance.to_be_bytes());
+            }
+            hmtx.extend_from_slice(&lsb.to_be_bytes());
+        }
+        // (min left side bearing, min right side bearing, max extent) over the glyphs with an outline
+        let extents = self.glyph_bounds().and_then(|bounds| {
+            bounds
+                .iter()
+                .zip(metrics)
+                .filter_map(|(bounds, (advance, lsb))| {
+                    let (x_min, _, x_max, _) = (*bounds)?;
+                    let extent = lsb.saturating_add(x_max.saturating_sub(x_min));
+                    Some((*lsb, (*advance as i16).saturating_sub(extent), extent))
+                })
+                .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2)))
+        });
+        let advance_max = metrics.iter().map(|(advance, _)| *advance).max().unwrap_or(0);
+        let hhea = self.table_mut("hhea").ok_or_else(|| anyhow!("No hhea table to record the metric count in"))?;
+        set_u16(hhea, HHEA_NUMBER_OF_H_METRICS, long_count as u16, "hhea")?;
+        set_u16(hhea, HHEA_ADVANCE_WIDTH_MAX, advance_max, "hhea")?;
+        if let Some((min_lsb, min_rsb, max_extent)) = extents {
+            set_u16(hhea, HHEA_MIN_LEFT_SIDE_BEARING, min_lsb as u16, "hhea")?;
+            set_u16(hhea, HHEA_MIN_RIGHT_SIDE_BEARING, min_rsb as u16, "hhea")?;
+            set_u16(hhea, HHEA_X_MAX_EXTENT, max_extent as u16, "hhea")?;
+        }
+        self.add_table("hmtx", hmtx);
+        Ok(self)
+    }
+
+    /// The bounds of each glyph of the glyf and loca tables, `None` for glyphs without an outline
+    /// and altogether when the tables are missing.
+    fn glyph_bounds(&self) -> Option<Vec<Option<Bounds>>> {
+        let (head, loca, glyf) = (self.table("head")?, self.table("loca")?, self.table("glyf")?);
+        let short = head.get(HEAD_INDEX_TO_LOC_FORMAT..HEAD_INDEX_TO_LOC_FORMAT + 2)? == [0, 0];
+        let offsets: Vec<usize> = match short {
+            true => loca.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as usize * 2).collect(),
+            false => loca
+                .chunks_exact(4)
+                .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]) as usize)
+                .collect(),
+        };
+        Some(offsets.windows(2).map(|range| record_bounds(glyf.get(range[0]..range[1])?)).collect())
+    }
+
+    /// Regenerate cmap from (character, glyph index) pairs: a format 4 subtable for the BMP and,
+    /// when there are characters beyond it, a format 12 subtable for all of them.
+    pub fn set_cmap(&mut self, mapping: &[(char, u16)]) -> &mut Self {
+        let mut mapping = mapping.to_vec();
+        mapping.sort();
+        mapping.dedup_by_key(|(c, _)| *c);
+        self.add_table("cmap", build_cmap(&mapping));
+        self
+    }
+
+    /// Regenerate cmap as a Windows symbol font has it: only a (3, 0) format 4 subtable, the
+    /// characters usually at U+F020 to U+F0FF. Characters past the BMP are left out.
+    pub fn set_symbol_cmap(&mut self, mapping: &[(char, u16)]) -> &mut Self {
+        let mut bmp: Vec<(u32, u16)> =
+            mapping.iter().map(|(c, glyph)| (*c as u32, *glyph)).filter(|(code, _)| *code < 0xFFFF).collect();
+        bmp.sort();
+        bmp.dedup_by_key(|(code, _)| *code);
+        let mut cmap = Vec::new();
+        // version, one record (3, 0) and its offset past the header
+        for value in [0u16, 1, 3, 0] {
+            cmap.extend_from_slice(&value.to_be_bytes());
+        }
+        cmap.extend_from_slice(&12u32.to_be_bytes());
+        cmap.extend_from_slice(&build_format4(&bmp));
+        self.add_table("cmap", cmap);
+        self
+    }
+
+    /// Regenerate cmap as an old Mac font has it: only a (1, 0) MacRoman format 0 subtable.
+    /// Characters MacRoman doesn't have and glyphs past 255 are left out.
+    pub fn set_mac_roman_cmap(&mut self, mapping: &[(char, u16)]) -> &mut Self {
+        let mut cmap = Vec::with_capacity(12 + 262);
+        // version, one record (1, 0) and its offset past the header, then format, length and
+        // language of the subtable
+        for value in [0u16, 1, 1, 0, 0, 12, 0, 262, 0] {
+            cmap.extend_from_slice(&value.to_be_bytes());
+        }
+        let mut glyphs = [0u8; 256];
+        for (c, glyph) in mapping {
+            if let (Some(byte), Ok(glyph)) = (mac_roman_byte(*c), u8::try_from(*glyph)) {
+                glyphs[byte as usize] = glyph;
+            }
+        }
+        cmap.extend_from_slice(&glyphs);
+        self.add_table("cmap", cmap);
+        self
+    }
+
+    /// Lay out the sfnt: the table directory with its binary search fields, the tables 4 byte
+    /// aligned and zero padded, each table's checksum and head.checkSumAdjustment.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        let num_tables = u16::try_from(self.tables.len()).map_err(|_| anyhow!("Too many tables"))?;
+        let entry_selector = if num_tables == 0 { 0 } else { num_tables.ilog2() as u16 };
+        let search_range = (1u16 << entry_selector) * 16;
+        let range_shift = num_tables * 16 - search_range.min(num_tables * 16);
+
+        let directory_size = 12 + 16 * self.tables.len();
+        let mut file = Vec::with_capacity(directory_size + self.tables.values().map(|data| data.len() + 3).sum::<usize>());
+        file.extend_from_slice(&self.sfnt_version.to_be_bytes());
+        for value in [num_tables, search_range, entry_selector, range_shift] {
+            file.extend_from_slice(&value.to_be_bytes());
+        }
+        let mut offset = directory_size;
+        let mut head_offset = None;
+        for (tag, data) in &self.tables {
+            let tag_bytes: [u8; 4] = tag
+                .as_bytes()
+                .try_into()
+                .map_err(|_| anyhow!("Table tag {tag:?} isn't 4 bytes"))?;
+            let mut checksum = table_checksum(data);
+            if tag == "head" {
+                if data.len() < 54 {
+                    return Err(anyhow!("head is {} bytes, expected 54", data.len()));
+                }
+                // checksummed with checkSumAdjustment zeroed, as it's written
+                let adjustment = &data[HEAD_CHECKSUM_ADJUSTMENT..HEAD_CHECKSUM_ADJUSTMENT + 4];
+                checksum = checksum.wrapping_sub(u32::from_be_bytes([adjustment[0], adjustment[1], adjustment[2], adjustment[3]]));
+                head_offset = Some(offset);
+            }
+            file.extend_from_slice(&tag_bytes);
+            file.extend_from_slice(&checksum.to_be_bytes());
+            file.extend_from_slice(&(offset as u32).to_be_bytes());
+            file.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for data in self.tables.values() {
+            file.extend_from_slice(data);
+            file.resize(file.len().next_multiple_of(4), 0);
+        }
+        if let Some(head) = head_offset {
+            let adjustment_at = head + HEAD_CHECKSUM_ADJUSTMENT;
+            file[adjustment_at..adjustment_at + 4].fill(0);
+            let adjustment = CHECKSUM_MAGIC.wrapping_sub(table_checksum(&file));
+            file[adjustment_at..adjustment_at + 4].copy_from_slice(&adjustment.to_be_bytes());
+        }
+        Ok(file)
+    }
+}
+
+/// A cmap table for a sorted mapping without duplicate characters.
+fn build_cmap(mapping: &[(char, u16)]) -> Vec<u8> {
+    let bmp: Vec<(u32, u16)> = mapping
+        .iter()
+        .map(|(c, glyph)| (*c as u32, *glyph))
+        .filter(|(code, _)| *code < 0xFFFF)
+        .collect();
+    let format4 = build_format4(&bmp);
+    let format12 = (bmp.len() < mapping.len()).then(|| build_format12(mapping));
+
+    // (platform, encoding) records sorted as the spec wants, all BMP records share format 4
+    let mut records: Vec<(u16, u16, bool)> = vec![(0, 3, false), (3, 1, false)];
+    if format12.is_some() {
+        records.insert(1, (0, 4, true));
+        records.push((3, 10, true));
+    }
+    let header_size = 4 + 8 * records.len();
+    let mut cmap = Vec::new();
+    cmap.extend_from_slice(&0u16.to_be_bytes());
+    cmap.extend_from_slice(&(records.len() as u16).to_be_bytes());
+    let format12_offset = header_size + format4.len();
+    for (platform, encoding, full) in &records {
+        let offset = if *full { format12_offset } else { header_size };
+        cmap.extend_from_slice(&platform.to_be_bytes());
+        cmap.extend_from_slice(&encoding.to_be_bytes());
+        cmap.extend_from_slice(&(offset as u32).to_be_bytes());
+    }
+    cmap.extend_from_slice(&format4);
+    if let Some(format12) = format12 {
+        cmap.extend_from_slice(&format12);
+    }
+    cmap
+}
+
+/// Format 4: segments of consecutive codes whose glyphs are consecutive too, each mapped with
+/// idDelta (no glyph id array), and the final 0xFFFF segment.
+fn build_format4(mapping: &[(u32, u16)]) -> Vec<u8> {
+    // (start, end, delta)
+    let mut segments: Vec<(u16, u16, u16)> = Vec::new();
+    for (code, glyph) in mapping {
+        let (code, delta) = (*code as u16, glyph.wrapping_sub(*code as u16));
+        match segments.last_mut() {
+            Some((_, end, last_delta)) if *end + 1 == code && *last_delta == delta => *end = code,
+            _ => segments.push((code, code, delta)),
+        }
+    }
+    segments.push((0xFFFF, 0xFFFF, 1));
+
+    let seg_count = segments.len() as u16;
+    let entry_selector = seg_count.ilog2() as u16;
+    let search_range = 2 * (1u16 << entry_selector);
+    let length = 16 + 8 * segments.len();
+    let mut table = Vec::with_capacity(length);
+    for value in [4, length as u16, 0, seg_count * 2, search_range, entry_selector, seg_count * 2 - search_range] {
+        table.extend_from_slice(&value.to_be_bytes());
+    }
+    table.extend(segments.iter().flat_map(|(_, end, _)| end.to_be_bytes()));
+    table.extend_from_slice(&0u16.to_be_bytes()); // reservedPad
+    table.extend(segments.iter().flat_map(|(start, _, _)| start.to_be_bytes()));
+    table.extend(segments.iter().flat_map(|(_, _, delta)| delta.to_be_bytes()));
+    table.extend(segments.iter().flat_map(|_| 0u16.to_be_bytes())); // idRangeOffsets
+    table
+}
+
+/// Format 12: groups of consecutive codes mapped to consecutive glyphs.
+fn build_format12(mapping: &[(char, u16)]) -> Vec<u8> {
+    // (start code, end code, start glyph)
+    let mut groups: Vec<(u32, u32, u32)> = Vec::new();
+    for (c, glyph) in mapping {
+        let (code, glyph) = (*c as u32, *glyph as u32);
+        match groups.last_mut() {
+            Some((start, end, start_glyph)) if *end + 1 == code && *start_glyph + (code - *start) == glyph => *end = code,
+            _ => groups.push((code, code, glyph)),
+        }
+    }
+    let length = 16 + 12 * groups.len() as u32;
+    let mut table = Vec::with_capacity(length as usize);
+    table.extend_from_slice(&12u16.to_be_bytes());
+    table.extend_from_slice(&0u16.to_be_bytes());
+    for value in [length, 0, groups.len() as u32] {
+        table.extend_from_slice(&value.to_be_bytes());
+    }
+    for (start, end, start_glyph) in groups {
+        for value in [start, end, start_glyph] {
+            table.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+    table
+}
+
+impl Font {
+    /// A builder for this font with glyph `index` replaced by a simple glyph of `outline`, in
+    /// font units, see `GlyphData::from_outline`; `build` writes the font. glyf and loca are
+    /// rebuilt around the new record, and head's bounds, hhea's extremes, the glyph's left side
+    /// bearing and maxp's point and contour limits follow. TrueType instructions and gvar deltas go by
+    /// point number: the glyph keeps its instructions when it keeps its number of points, and
+    /// in a variable font it has to.
+    pub fn replace_glyph(&self, index: u16, outline: &Outline) -> Result<FontBuilder<'_>> {
+        let old = self
+            .glyph(index)
+            .ok_or_else(|| anyhow!("No glyph {index}, the font has {}", self.num_glyphs()))?;
+        let mut glyph = GlyphData::from_outline(outline)?;
+        let same_points = old.is_simple && old.num_points() == glyph.num_points();
+        if !same_points && self.table_data("gvar").is_some() {
+            return Err(anyhow!(
+                "Glyph {index} has variations for {} points, the new outline has {}",
+                old.num_points(),
+                glyph.num_points()
+            ));
+        }
+        if same_points {
+            glyph.instructions = old.instructions.clone();
+        }
+        let record = match glyph.num_points() {
+            0 => Vec::new(),
+            _ => glyph.to_bytes(),
+        };
+        let glyphs: Vec<&[u8]> = (0..self.num_glyphs())
+            .map(|i| match i == index {
+                true => record.as_slice(),
+                false => self.raw_glyph(i).unwrap_or_default(),
+            })
+            .collect();
+        let metrics: Vec<(u16, i16)> = (0..self.num_glyphs())
+            .map(|i| match i == index && glyph.num_points() > 0 {
+                true => (self.default_advance_width(i), glyph.x_min),
+                false => (self.default_advance_width(i), self.default_left_side_bearing(i)),
+            })
+            .collect();
+        let mut builder = FontBuilder::from_font(self);
+        builder.set_glyphs(&glyphs)?.set_horizontal_metrics(&metrics)?;
+        if let Some(maxp) = builder.table_mut("maxp") {
+            let limits = [(MAXP_MAX_POINTS, glyph.num_points()), (MAXP_MAX_CONTOURS, glyph.num_contours())];
+            for (at, count) in limits {
+                let limit = read_u16_at(maxp, at).max(count as u16);
+                if at + 2 <= maxp.len() {
+                    set_u16(maxp, at, limit, "maxp")?;
+                }
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Serialize the font back to a TrueType file with every table as it was read.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        FontBuilder::from_font(self).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outline::{Contour, OutlinePoint};
+    use crate::reader::FontReader;
+
+    const FIXTURES: [&str; 2] = [
+        concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf"),
+        concat!(env!("CARGO_MANIFEST_DIR"), "/SourceCodePro-Regular.ttf"),
+    ];
+
+    #[test]
+    fn unmodified_fonts_read_back_identically() {
+        for path in FIXTURES {
+            let font = Font::read_truetype(path).unwrap();
+            let written = font.to_bytes().unwrap();
+            assert_eq!(table_checksum(&written), CHECKSUM_MAGIC, "{path}");
+            let read = Font::from_bytes(written.clone()).unwrap();
+            assert_eq!(read.table_tags(), font.table_tags(), "{path}");
+            for tag in font.table_tags() {
+                let mut before = font.table_data(tag).unwrap().to_vec();
+                let mut after = read.table_data(tag).unwrap().to_vec();
+                if tag == "head" {
+                    before[HEAD_CHECKSUM_ADJUSTMENT..HEAD_CHECKSUM_ADJUSTMENT + 4].fill(0);
+                    after[HEAD_CHECKSUM_ADJUSTMENT..HEAD_CHECKSUM_ADJUSTMENT + 4].fill(0);
+                }
+                assert!(before == after, "{path}: {tag} changed");
+            }
+            assert_eq!(read.num_glyphs(), font.num_glyphs());
+            for index in 0..font.num_glyphs() {
+                assert_eq!(read.glyph_outline(index), font.glyph_outline(index), "{path}: glyph {index}");
+            }
+            let report = read.validate();
+            assert!(!report.has_errors() && report.warnings().count() == 0, "{path}: {report:?}");
+            // writing what was read changes nothing
+            assert!(read.to_bytes().unwrap() == written, "{path}");
+        }
+    }
+
+    #[test]
+    fn the_directory_has_its_binary_search_fields() {
+        let font = Font::read_truetype(FIXTURES[0]).unwrap();
+        let written = font.to_bytes().unwrap();
+        let mut reader = FontReader::new(&written);
+        assert_eq!(reader.read_u32().unwrap(), TRUETYPE_VERSION);
+        let num_tables = reader.read_u16().unwrap();
+        let (search_range, entry_selector, range_shift) =
+            (reader.read_u16().unwrap(), reader.read_u16().unwrap(), reader.read_u16().unwrap());
+        let power = 1u16 << entry_selector;
+        assert!(power <= num_tables && power * 2 > num_tables);
+        assert_eq!(search_range, power * 16);
+        assert_eq!(range_shift, num_tables * 16 - search_range);
+        let mut previous_tag = [0u8; 4];
+        for _ in 0..num_tables {
+            let tag = reader.read_u32().unwrap().to_be_bytes();
+            let (_checksum, offset, _length) =
+                (reader.read_u32().unwrap(), reader.read_u32().unwrap(), reader.read_u32().unwrap());
+            assert!(tag > previous_tag, "tables aren't sorted by tag");
+            assert_eq!(offset % 4, 0, "{} isn't 4 byte aligned", String::from_utf8_lossy(&tag));
+            previous_tag = tag;
+        }
+        assert_eq!(written.len() % 4, 0);
+    }
+
+    struct Xorshift(u64);
+
+    impl Xorshift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    /// A few contours of points close together, far apart, repeated or at the ends of the i16
+    /// range, with fractions for `from_outline` to round.
+    fn random_outline(random: &mut Xorshift) -> Outline {
+        let mut previous = (0., 0.);
+        let coordinate = |random: &mut Xorshift, previous: f32| match random.below(6) {
+            0 => previous,
+            1 => previous + random.below(511) as f32 - 255.,
+            2 => [i16::MIN, i16::MAX][random.below(2)] as f32,
+            _ => random.below(4001) as f32 - 2000. + random.below(100) as f32 / 100.,
+        };
+        let contours = (0..1 + random.below(4))
+            .map(|_| {
+                let points = (0..1 + random.below(40))
+                    .map(|_| {
+                        let x = coordinate(random, previous.0).clamp(i16::MIN as f32, i16::MAX as f32);
+                        let y = coordinate(random, previous.1).clamp(i16::MIN as f32, i16::MAX as f32);
+                        previous = (x, y);
+                        OutlinePoint {
+                            x,
+                            y,
+                            on_curve: random.below(3) > 0,
+                        }
+                    })
+                    .collect();
+                Contour { points }
+            })
+            .collect();
+        let mut outline = Outline {
+            contours,
+            ..Outline::default()
+        };
+        outline.update_bbox();
+        outline
+    }
+
+    #[test]
+    fn replaced_glyphs_read_back_as_written() {
+        let font = Font::read_truetype(FIXTURES[0]).unwrap();
+        let mut random = Xorshift(0x853c_49e6_748f_ea9b);
+        for case in 0..200 {
+            let index = 1 + random.below(font.num_glyphs() as usize - 1) as u16;
+            let outline = random_outline(&mut random);
+            let written = font.replace_glyph(index, &outline).unwrap().build().unwrap();
+            let read = Font::from_bytes(written).unwrap();
+            let mut expected = outline.clone();
+            for point in expected.contours.iter_mut().flat_map(|contour| &mut contour.points) {
+                (point.x, point.y) = (point.x.round(), point.y.round());
+            }
+            expected.update_bbox();
+            let glyph = read.glyph(index).unwrap();
+            assert_eq!(glyph.outline(), expected, "case {case}");
+            let (x_min, y_min, x_max, y_max) = expected.bbox;
+            let bounds = (x_min as i16, y_min as i16, x_max as i16, y_max as i16);
+            assert_eq!((glyph.x_min, glyph.y_min, glyph.x_max, glyph.y_max), bounds, "case {case}");
+            // decoding and encoding again gives the same record
+            let record = read.raw_glyph(index).unwrap();
+            let decoded = GlyphData::from_reader(&mut FontReader::new(record)).unwrap();
+            let encoded = decoded.to_bytes();
+            assert!(record.len() < encoded.len() + 4, "case {case}: {} bytes for {}", record.len(), encoded.len());
+            // past the record is the padding `set_glyphs` aligns the next one with
+            let padding = record.strip_prefix(encoded.as_slice());
+            assert!(padding.is_some_and(|padding| padding.iter().all(|byte| *byte == 0)), "case {case}");
+            for other in [index - 1, (index + 1) % font.num_glyphs()] {
+                let (before, after) = (font.raw_glyph(other).unwrap(), read.raw_glyph(other).unwrap());
+                assert!(after.starts_with(before), "case {case}: glyph {other} changed");
+            }
+            if case % 10 == 0 {
+                let report = read.validate();
+                assert!(!report.has_errors(), "case {case}: {report:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Walking the glyphs of a font with `Font::glyphs` holds one glyph at a time: the heap never
+//! grows by much more than the largest glyph, where caching every glyph keeps them all. A
+//! counting global allocator tracks the bytes the test's own thread has allocated.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use font_rendering::Font;
+
+struct CountingAllocator;
+
+thread_local! {
+    /// Bytes this thread allocated and hasn't freed yet, and the most there were
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(bytes: isize) {
+    // `try_with`, the thread locals may be gone while a thread exits
+    let _ = LIVE.try_with(|live| {
+        live.set(live.get() + bytes);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        track(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// What `run` peaked at above the bytes live when it started.
+fn peak_during(run: impl FnOnce()) -> isize {
+    let start = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(start));
+    run();
+    PEAK.with(Cell::get) - start
+}
+
+#[test]
+fn streamed_glyphs_are_held_one_at_a_time() {
+    let font = Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/SourceCodePro-Regular.ttf")).unwrap();
+    let mut largest = 0;
+    let streamed = peak_during(|| {
+        for (index, glyph) in font.glyphs() {
+            let glyph = glyph.unwrap_or_else(|err| panic!("glyph {index}: {err}"));
+            largest = largest.max(glyph.heap_size());
+        }
+    });
+    let cached = peak_during(|| {
+        for index in 0..font.num_glyphs() {
+            font.glyph(index).unwrap();
+        }
+    });
+    // parsing a glyph takes a few buffers of about its size alongside it
+    assert!(streamed <= 4 * largest as isize + 4096, "{streamed} bytes for glyphs of at most {largest}");
+    assert!(streamed * 20 < cached, "{streamed} bytes streaming, {cached} cached");
+}
+
+#[test]
+fn the_ascii_glyphs_stream_one_at_a_time() {
+    let font = Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/SourceCodePro-Regular.ttf")).unwrap();
+    let ascii: Vec<u16> = (' '..='~').filter_map(|c| font.glyph_index_for_char(c)).collect();
+    assert_eq!(ascii.len(), 95);
+    let mut largest = 0;
+    let peak = peak_during(|| {
+        for index in &ascii {
+            let mut glyphs = font.glyphs_in(*index..*index + 1);
+            let (parsed, glyph) = glyphs.next().unwrap();
+            assert_eq!(parsed, *index);
+            largest = largest.max(glyph.unwrap().heap_size());
+            assert!(glyphs.next().is_none());
+        }
+    });
+    assert!(peak <= 4 * largest as isize + 4096, "{peak} bytes for glyphs of at most {largest}");
+}
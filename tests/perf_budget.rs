@@ -0,0 +1,103 @@
+//! A performance budget: wrap 5,000 words of lorem ipsum at 14px and draw every line twice
+//! through a shape cache and a glyph bitmap cache, failing when that takes longer than the
+//! budget. It's there to catch things going quadratic or caches that never hit, not small
+//! slowdowns, and is only meaningful in a release build, so it's ignored by default:
+//!
+//! ```text
+//! cargo test --release --test perf_budget -- --ignored --nocapture
+//! ```
+//!
+//! The budget is `PERF_BUDGET` scaled by how much slower than `CALIBRATION_BASELINE` the
+//! calibration loop runs here, never scaled down on faster machines.
+//! `FONT_RENDERING_PERF_BUDGET_MS` replaces the 150ms.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use font_rendering::number::fixed;
+use font_rendering::{Font, GlyphBitmapCache, LayoutOptions, ShapeCache, TextCaches};
+use raqote::{DrawTarget, SolidSource};
+
+/// Words the paragraph is made of.
+const LOREM_IPSUM: &str = "lorem ipsum dolor sit amet consectetur adipiscing elit sed do eiusmod tempor incididunt ut \
+    labore et dolore magna aliqua ut enim ad minim veniam quis nostrud exercitation ullamco laboris nisi ut aliquip ex \
+    ea commodo consequat duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla \
+    pariatur excepteur sint occaecat cupidatat non proident sunt in culpa qui officia \
+    deserunt mollit anim id est laborum";
+const WORDS: usize = 5000;
+const PX_SIZE: f32 = 14.;
+/// The viewer's window, which the lines are drawn over each other in.
+const WIDTH: usize = 800;
+const HEIGHT: usize = 600;
+const MARGIN: f32 = 10.;
+/// What the test may take on the machine `CALIBRATION_BASELINE` was timed on.
+const PERF_BUDGET: Duration = Duration::from_millis(150);
+/// How long `calibration_loop` took in a release build when the budget was set: timed once, on
+/// one machine, not measured across several. Scaling by it assumes the crate's work slows down
+/// like the loop does, which is only roughly true.
+const CALIBRATION_BASELINE: Duration = Duration::from_millis(32);
+
+/// A fixed amount of integer work, the best of three runs, to tell how fast this machine is.
+fn calibration_loop() -> Duration {
+    (0..3)
+        .map(|_| {
+            let started = Instant::now();
+            let mut hash = 0xcbf2_9ce4_8422_2325u64;
+            for i in 0..black_box(20_000_000u64) {
+                hash = (hash ^ i).wrapping_mul(0x0100_0000_01b3);
+            }
+            black_box(hash);
+            started.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// `PERF_BUDGET` or `FONT_RENDERING_PERF_BUDGET_MS`.
+fn base_budget() -> Duration {
+    match std::env::var("FONT_RENDERING_PERF_BUDGET_MS") {
+        Ok(value) => match value.parse::<u64>() {
+            Ok(ms) => Duration::from_millis(ms),
+            Err(_) => panic!("FONT_RENDERING_PERF_BUDGET_MS={value} isn't a number of milliseconds"),
+        },
+        Err(_) => PERF_BUDGET,
+    }
+}
+
+#[test]
+#[ignore = "timing, run in a release build with --ignored"]
+fn wrapping_and_drawing_stay_within_budget() {
+    let font = Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf")).unwrap();
+    let calibration = calibration_loop();
+    let speed = calibration.as_secs_f64() / CALIBRATION_BASELINE.as_secs_f64();
+    let budget = base_budget().mul_f64(speed.max(1.));
+    let text = LOREM_IPSUM.split_whitespace().cycle().take(WORDS).collect::<Vec<_>>().join(" ");
+    let black = SolidSource::from_unpremultiplied_argb(0xff, 0, 0, 0);
+
+    let started = Instant::now();
+    let layout = font.layout_text(&text, PX_SIZE, WIDTH as f32 - 2. * MARGIN, &LayoutOptions::default());
+    let mut shapes = ShapeCache::new(layout.lines.len());
+    let glyphs = GlyphBitmapCache::new();
+    let mut dt = DrawTarget::new(WIDTH as i32, HEIGHT as i32);
+    let mut passes = Vec::new();
+    for _ in 0..2 {
+        let pass = Instant::now();
+        for line in &layout.lines {
+            let baseline = line.baseline % (HEIGHT as f32);
+            let caches = TextCaches {
+                glyphs: Some(&glyphs),
+                shapes: Some(&mut shapes),
+            };
+            let line_text = &text[line.range.clone()];
+            font.draw_text_cached(&mut dt, line_text, (MARGIN, baseline), PX_SIZE, black, caches);
+        }
+        passes.push(pass.elapsed());
+    }
+    let elapsed = started.elapsed();
+
+    println!("{WORDS} words in {} lines at {PX_SIZE}px", layout.lines.len());
+    println!("Drawn with empty caches: {:?}, with full caches: {:?}", passes[0], passes[1]);
+    println!("Calibration loop: {calibration:?}, {}x the baseline", fixed(speed as f32, 2));
+    println!("Total: {elapsed:?}, budget {budget:?}");
+    assert!(elapsed <= budget, "over the performance budget: {elapsed:?} > {budget:?}");
+}
@@ -0,0 +1,96 @@
+//! Redrawing a line with `Font::render_text_into_with` allocates nothing once the scratch has
+//! laid it out and its glyphs were flattened. A counting global allocator counts the
+//! allocations of the test's own thread.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use font_rendering::{Bitmap, BitmapFormat, Font, PixelBuffer, RenderScratch};
+use raqote::SolidSource;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    // `try_with`, the thread local may be gone while a thread exits
+    let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// How many allocations `run` made.
+fn allocations_during(run: impl FnOnce()) -> usize {
+    let start = ALLOCATIONS.with(Cell::get);
+    run();
+    ALLOCATIONS.with(Cell::get) - start
+}
+
+const FORMATS: [BitmapFormat; 5] = [
+    BitmapFormat::A8,
+    BitmapFormat::Rgb8,
+    BitmapFormat::Rgba8,
+    BitmapFormat::ArgbPremultiplied,
+    BitmapFormat::Xrgb,
+];
+
+fn inconsolata() -> Font {
+    Font::read_truetype(concat!(env!("CARGO_MANIFEST_DIR"), "/Inconsolata-Regular.ttf")).unwrap()
+}
+
+#[test]
+fn redrawing_a_line_allocates_nothing() {
+    let font = inconsolata();
+    let lines = ["The quick brown fox jumps over the lazy dog", "0123456789 !@#$%&*()", "Sphinx of black quartz"];
+    let color = SolidSource::from_unpremultiplied_argb(0xc0, 0x20, 0x40, 0x80);
+    for format in FORMATS {
+        let mut bitmap = Bitmap::new(400, 120, format, 4);
+        let mut scratch = RenderScratch::new(lines.len());
+        let mut target = bitmap.pixel_buffer();
+        let draw = |target: &mut PixelBuffer, scratch: &mut RenderScratch| {
+            for (i, line) in lines.iter().enumerate() {
+                font.render_text_into_with(target, line, 18., (4., 30. + 30. * i as f32), color, scratch);
+            }
+        };
+        draw(&mut target, &mut scratch);
+        let allocations = allocations_during(|| {
+            for _ in 0..20 {
+                draw(&mut target, &mut scratch);
+            }
+        });
+        assert_eq!(allocations, 0, "{format:?}");
+        assert!(bitmap.data.iter().any(|byte| *byte != 0), "{format:?}: nothing drawn");
+    }
+}
+
+#[test]
+fn glyphs_past_the_edges_are_clipped() {
+    let font = inconsolata();
+    let black = SolidSource::from_unpremultiplied_argb(0xff, 0, 0, 0);
+    let mut bitmap = Bitmap::new(40, 10, BitmapFormat::A8, 1);
+    let mut scratch = RenderScratch::default();
+    let mut target = bitmap.pixel_buffer();
+    for origin in [(-25., 12.), (30., 5.), (10., -20.), (10., 200.), (-1000., 5.)] {
+        font.render_text_into_with(&mut target, "WWWW", 24., origin, black, &mut scratch);
+    }
+    assert!(bitmap.data.iter().any(|byte| *byte != 0));
+}